@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitCode;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use clap::Parser;
+use elinor::trec;
+use elinor::Metric;
+use elinor::PredRelStore;
+use elinor::TrueRelStore;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Check the conformance of elinor's metrics against trec_eval and ir-measures."
+)]
+struct Args {
+    /// Suites to run. If not specified, all the suites are run.
+    #[arg(short, long, num_args = 1..)]
+    suite: Vec<String>,
+
+    /// Directory of the test data bundled in the repository.
+    #[arg(long, default_value = "test-data")]
+    data_dir: PathBuf,
+
+    /// Directory of the external collections, each of which is placed in
+    /// `<external-dir>/<suite>/qrels` and `<external-dir>/<suite>/run` in the TREC format.
+    #[arg(long, default_value = "conformance/data")]
+    external_dir: PathBuf,
+
+    /// Path to the trec_eval executable.
+    #[arg(long, default_value = "trec_eval")]
+    trec_eval: PathBuf,
+
+    /// Path to the Python interpreter with ir-measures installed.
+    #[arg(long, default_value = "python3")]
+    python: PathBuf,
+
+    /// Absolute tolerance of per-topic differences.
+    #[arg(long, default_value = "0.001")]
+    tolerance: f64,
+
+    /// Path to the output JSON file of the compatibility matrix.
+    #[arg(short, long)]
+    output_json: Option<PathBuf>,
+}
+
+/// Pair of true and predicted relevance files in the TREC format.
+struct Suite {
+    name: &'static str,
+    qrels: PathBuf,
+    run: PathBuf,
+}
+
+fn suites(args: &Args) -> Vec<Suite> {
+    let bundled = |name, qrels, run| Suite {
+        name,
+        qrels: args.data_dir.join("trec-eval").join(qrels),
+        run: args.data_dir.join("trec-eval").join(run),
+    };
+    let external = |name| Suite {
+        name,
+        qrels: args.external_dir.join(name).join("qrels"),
+        run: args.external_dir.join(name).join("run"),
+    };
+    vec![
+        bundled("trec-eval", "qrels.test", "results.test"),
+        bundled("trec-eval-rel-level", "qrels.rel_level", "results.test"),
+        external("msmarco"),
+        external("robust04"),
+        external("trec-dl-2019"),
+    ]
+}
+
+/// Reference implementations to compare with.
+#[derive(Clone, Copy, Debug)]
+enum Tool {
+    TrecEval,
+    IrMeasures,
+}
+
+impl Tool {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::TrecEval => "trec_eval",
+            Self::IrMeasures => "ir-measures",
+        }
+    }
+
+    /// Returns the name of the measure corresponding to the metric, if supported.
+    fn measure(self, metric: Metric) -> Option<String> {
+        let measure = match (self, metric) {
+            (Self::TrecEval, _) => return metric.trec_eval_name(),
+            (Self::IrMeasures, Metric::Success { k }) if k != 0 => format!("Success@{k}"),
+            (Self::IrMeasures, Metric::Precision { k }) if k != 0 => format!("P@{k}"),
+            (Self::IrMeasures, Metric::Recall { k }) if k != 0 => format!("R@{k}"),
+            (Self::IrMeasures, Metric::RPrecision) => "Rprec".to_string(),
+            (Self::IrMeasures, Metric::AP { k: 0 }) => "AP".to_string(),
+            (Self::IrMeasures, Metric::AP { k }) => format!("AP@{k}"),
+            (Self::IrMeasures, Metric::RR { k: 0 }) => "RR".to_string(),
+            (Self::IrMeasures, Metric::RR { k }) => format!("RR@{k}"),
+            (
+                Self::IrMeasures,
+                Metric::Bpref {
+                    k: 0,
+                    trec_compat: true,
+                },
+            ) => "Bpref".to_string(),
+            (Self::IrMeasures, Metric::NDCG { k: 0 }) => "nDCG".to_string(),
+            (Self::IrMeasures, Metric::NDCG { k }) => format!("nDCG@{k}"),
+            _ => return None,
+        };
+        Some(measure)
+    }
+
+    /// Runs the tool and returns per-topic scores keyed by (measure, query id).
+    ///
+    /// Returns `Ok(None)` if the tool is not installed.
+    fn run(
+        self,
+        args: &Args,
+        suite: &Suite,
+        measures: &[String],
+    ) -> Result<Option<BTreeMap<(String, String), f64>>> {
+        let output = match self {
+            Self::TrecEval => Command::new(&args.trec_eval)
+                .args(["-q", "-c", "-m", "all_trec"])
+                .arg(&suite.qrels)
+                .arg(&suite.run)
+                .output(),
+            Self::IrMeasures => Command::new(&args.python)
+                .args(["-m", "ir_measures"])
+                .arg(&suite.qrels)
+                .arg(&suite.run)
+                .args(measures)
+                .arg("--perquery")
+                .output(),
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if matches!(self, Self::IrMeasures) && stderr.contains("No module named ir_measures") {
+                return Ok(None);
+            }
+            return Err(anyhow!("{} failed: {}", self.name(), stderr));
+        }
+        let mut scores = BTreeMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let rows = line.split_whitespace().collect::<Vec<_>>();
+            if rows.len() != 3 {
+                continue;
+            }
+            // trec_eval: <measure> <query_id> <value>, ir-measures: <query_id> <measure> <value>
+            let (measure, query_id) = match self {
+                Self::TrecEval => (rows[0], rows[1]),
+                Self::IrMeasures => (rows[1], rows[0]),
+            };
+            if query_id == "all" {
+                continue;
+            }
+            if let Ok(value) = rows[2].parse::<f64>() {
+                scores.insert((measure.to_string(), query_id.to_string()), value);
+            }
+        }
+        Ok(Some(scores))
+    }
+}
+
+/// Cell of the compatibility matrix.
+enum Status {
+    /// The suite data is not available.
+    NoData,
+    /// The tool is not installed.
+    NotInstalled,
+    /// The tool does not support the metric.
+    Unsupported,
+    /// Number of topics compared and those mismatched.
+    Compared {
+        n_topics: usize,
+        n_mismatches: usize,
+    },
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NoData => write!(f, "no-data"),
+            Self::NotInstalled => write!(f, "not-installed"),
+            Self::Unsupported => write!(f, "unsupported"),
+            Self::Compared {
+                n_topics,
+                n_mismatches: 0,
+            } => write!(f, "ok({n_topics})"),
+            Self::Compared {
+                n_topics,
+                n_mismatches,
+            } => write!(f, "FAIL({n_mismatches}/{n_topics})"),
+        }
+    }
+}
+
+fn metrics() -> Vec<Metric> {
+    let ks = [5, 10, 20, 100];
+    let mut metrics = Vec::new();
+    for k in [1, 5, 10] {
+        metrics.push(Metric::Success { k });
+    }
+    for k in std::iter::once(0).chain(ks) {
+        metrics.push(Metric::Precision { k });
+    }
+    for k in std::iter::once(0).chain(ks) {
+        metrics.push(Metric::Recall { k });
+    }
+    metrics.push(Metric::RPrecision);
+    for k in std::iter::once(0).chain(ks) {
+        metrics.push(Metric::AP { k });
+    }
+    metrics.push(Metric::RR { k: 0 });
+    metrics.push(Metric::Bpref {
+        k: 0,
+        trec_compat: true,
+    });
+    for k in std::iter::once(0).chain(ks) {
+        metrics.push(Metric::NDCG { k });
+    }
+    metrics
+}
+
+fn load_suite(suite: &Suite) -> Result<(TrueRelStore<String>, PredRelStore<String>)> {
+    let true_rels = trec::parse_true_rels_in_trec(load_lines(&suite.qrels)?)?;
+    let pred_rels = trec::parse_pred_rels_in_trec(load_lines(&suite.run)?)?;
+    Ok((true_rels, pred_rels))
+}
+
+fn load_lines(path: &Path) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text.lines().map(|line| line.to_string()).collect())
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    let metrics = metrics();
+    let tools = [Tool::TrecEval, Tool::IrMeasures];
+
+    let mut suites = suites(&args);
+    if !args.suite.is_empty() {
+        for name in &args.suite {
+            if !suites.iter().any(|s| s.name == name) {
+                let names = suites.iter().map(|s| s.name).collect::<Vec<_>>();
+                return Err(anyhow!(
+                    "Unknown suite: {name} (available: {})",
+                    names.join(", ")
+                ));
+            }
+        }
+        suites.retain(|s| args.suite.iter().any(|name| name == s.name));
+    }
+
+    // Mapping from (suite, tool) to the statuses of the metrics.
+    let mut matrix: Vec<(String, Vec<Status>)> = vec![];
+    for suite in &suites {
+        let data = if suite.qrels.exists() && suite.run.exists() {
+            Some(load_suite(suite)?)
+        } else {
+            eprintln!(
+                "[{}] Data not found: {} and {}",
+                suite.name,
+                suite.qrels.display(),
+                suite.run.display()
+            );
+            None
+        };
+        for tool in tools {
+            let column = format!("{}/{}", suite.name, tool.name());
+            let Some((true_rels, pred_rels)) = &data else {
+                matrix.push((column, metrics.iter().map(|_| Status::NoData).collect()));
+                continue;
+            };
+            let measures = metrics
+                .iter()
+                .filter_map(|&metric| tool.measure(metric))
+                .collect::<Vec<_>>();
+            let Some(references) = tool.run(&args, suite, &measures)? else {
+                eprintln!("[{}] {} is not installed", suite.name, tool.name());
+                matrix.push((
+                    column,
+                    metrics.iter().map(|_| Status::NotInstalled).collect(),
+                ));
+                continue;
+            };
+            let mut statuses = vec![];
+            for &metric in &metrics {
+                let Some(measure) = tool.measure(metric) else {
+                    statuses.push(Status::Unsupported);
+                    continue;
+                };
+                let evaluation = elinor::evaluate(true_rels, pred_rels, metric)?;
+                let mut n_mismatches = 0;
+                for (query_id, &score) in evaluation.scores() {
+                    let reference = references.get(&(measure.clone(), query_id.clone()));
+                    let matched = reference.map_or(false, |&r| {
+                        (r - score).abs() <= args.tolerance + f64::EPSILON
+                    });
+                    if !matched {
+                        eprintln!(
+                            "[{}] {} {metric:#} mismatched at {query_id}: elinor={score}, reference={:?}",
+                            suite.name,
+                            tool.name(),
+                            reference
+                        );
+                        n_mismatches += 1;
+                    }
+                }
+                statuses.push(Status::Compared {
+                    n_topics: evaluation.scores().len(),
+                    n_mismatches,
+                });
+            }
+            matrix.push((column, statuses));
+        }
+    }
+
+    let header = matrix.iter().map(|(c, _)| c.as_str()).collect::<Vec<_>>();
+    println!("metric\t{}", header.join("\t"));
+    for (i, metric) in metrics.iter().enumerate() {
+        let row = matrix
+            .iter()
+            .map(|(_, statuses)| statuses[i].to_string())
+            .collect::<Vec<_>>();
+        println!("{metric:#}\t{}", row.join("\t"));
+    }
+
+    if let Some(output_json) = &args.output_json {
+        let mut json = serde_json::Map::new();
+        for (column, statuses) in &matrix {
+            let cells = metrics
+                .iter()
+                .zip(statuses)
+                .map(|(metric, status)| (format!("{metric:#}"), status.to_string().into()))
+                .collect::<serde_json::Map<_, _>>();
+            json.insert(column.clone(), cells.into());
+        }
+        std::fs::write(output_json, serde_json::to_string_pretty(&json)?)?;
+    }
+
+    let failed = matrix.iter().any(|(_, statuses)| {
+        statuses
+            .iter()
+            .any(|s| matches!(s, Status::Compared { n_mismatches, .. } if *n_mismatches > 0))
+    });
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,352 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor::id_normalization::IdNormalization;
+use elinor::id_normalization::IdNormalizer;
+use elinor::multi_collection::MultiCollectionEvaluation;
+use elinor::relevance::NonFiniteScorePolicy;
+use elinor::relevance::ScoreQuantization;
+use elinor::timing::TimingReport;
+use elinor::Metric;
+use elinor::PredRelStoreBuilder;
+use elinor::TrueRelStore;
+use elinor_cli::Outcome;
+use elinor_cli::TableColumn;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug)]
+enum PrintMode {
+    Pretty,
+    Raw,
+}
+
+impl FromStr for PrintMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("Invalid PrintMode: {}", s)),
+        }
+    }
+}
+
+/// Manifest of a benchmark, where relative paths are resolved from the directory of the manifest.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct Manifest {
+    /// Metrics to evaluate. If empty, nDCG@10 is used.
+    #[serde(default)]
+    metrics: Vec<String>,
+    /// Datasets in the order of the columns.
+    datasets: Vec<Dataset>,
+    /// Runs in the order of the rows.
+    runs: Vec<Run>,
+}
+
+/// Dataset of the benchmark.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct Dataset {
+    /// Name of the dataset.
+    name: String,
+    /// Path to the JSONL file of true relevance scores.
+    true_jsonl: PathBuf,
+}
+
+/// Run evaluated over the datasets.
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct Run {
+    /// Name of the run.
+    name: String,
+    /// Mapping from dataset names to the JSONL files of predicted relevance scores.
+    pred_jsonls: BTreeMap<String, PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Evaluate runs over the datasets of a benchmark into a consolidated table."
+)]
+struct Args {
+    /// Path to the input JSON manifest of the datasets, runs, and metrics.
+    #[arg(short, long)]
+    manifest: PathBuf,
+
+    /// Metrics to evaluate, overriding those in the manifest.
+    #[arg(long, num_args = 1..)]
+    metrics: Vec<Metric>,
+
+    /// Print mode for the output (pretty or raw).
+    #[arg(short, long, default_value = "pretty")]
+    print_mode: PrintMode,
+
+    /// Number of decimal places of real numbers in the output.
+    /// The printed tables use 4 if not specified, and the output CSV uses the full precision.
+    #[arg(long)]
+    precision: Option<usize>,
+
+    /// Path to the output CSV file of the consolidated table,
+    /// with the columns of run, metric, each dataset, and average.
+    /// If '-', the table is written to the standard output, and the printed tables go to the standard error.
+    #[arg(short, long)]
+    output_csv: Option<PathBuf>,
+
+    /// Policy for non-finite (NaN or infinite) predicted scores (reject, drop, or minimum).
+    #[arg(long, default_value = "reject")]
+    non_finite_policy: NonFiniteScorePolicy,
+
+    /// Step to round predicted scores to at ingestion (e.g., 1e-6),
+    /// so that tiny differences across platforms do not change tie-breaking.
+    #[arg(long)]
+    score_quantization: Option<ScoreQuantization>,
+
+    /// Normalization steps of query ids in both true and predicted relevance,
+    /// in the form of <step>,... from lowercase, strip_fragment, or md5 (e.g., lowercase,md5).
+    #[arg(long, value_delimiter = ',')]
+    query_id_normalization: Vec<IdNormalization>,
+
+    /// Normalization steps of document ids in both true and predicted relevance,
+    /// in the form of <step>,... from lowercase, strip_fragment, or md5 (e.g., strip_fragment,lowercase).
+    #[arg(long, value_delimiter = ',')]
+    doc_id_normalization: Vec<IdNormalization>,
+
+    /// Print the wall-clock time of building the stores and evaluating each metric for each run
+    /// to the standard error, in seconds.
+    #[arg(long)]
+    timings: bool,
+
+    /// Overwrite the existing output file.
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    elinor_cli::check_outputs(args.output_csv.as_deref(), args.force)?;
+
+    let manifest: Manifest = serde_json::from_str(&elinor_cli::read_to_string(&args.manifest)?)?;
+    if manifest.datasets.is_empty() || manifest.runs.is_empty() {
+        return Err(elinor_cli::input_error(
+            "The manifest must have at least one dataset and one run.",
+        ));
+    }
+    let base_dir = args.manifest.parent().unwrap_or_else(|| Path::new(""));
+    let metrics = if !args.metrics.is_empty() {
+        args.metrics.clone()
+    } else if !manifest.metrics.is_empty() {
+        manifest
+            .metrics
+            .iter()
+            .map(|metric| metric.parse())
+            .collect::<Result<Vec<Metric>, _>>()?
+    } else {
+        vec![Metric::NDCG { k: 10 }]
+    };
+
+    let normalizer = IdNormalizer::new()
+        .with_query_steps(args.query_id_normalization.clone())
+        .with_doc_steps(args.doc_id_normalization.clone());
+    let mut timings = TimingReport::new();
+    let mut true_rels = vec![];
+    for dataset in &manifest.datasets {
+        let true_lines = elinor_cli::load_lines(base_dir.join(&dataset.true_jsonl))?;
+        let start = Instant::now();
+        let true_records = elinor_cli::parse_true_records(&true_lines)?;
+        let true_records = normalizer.normalize_records(true_records);
+        true_rels.push(TrueRelStore::from_records(true_records)?);
+        timings.record(format!("{}/build_true_rels", dataset.name), start.elapsed());
+    }
+
+    // results[i][j] is the result of the i-th run for the j-th metric.
+    let mut results = vec![];
+    for run in &manifest.runs {
+        if let Some(name) = run
+            .pred_jsonls
+            .keys()
+            .find(|&name| !manifest.datasets.iter().any(|d| &d.name == name))
+        {
+            return Err(elinor_cli::input_error(format!(
+                "Run {} has an unknown dataset: {name}",
+                run.name
+            )));
+        }
+        let mut pred_rels = vec![];
+        for dataset in &manifest.datasets {
+            let pred_jsonl = run.pred_jsonls.get(&dataset.name).ok_or_else(|| {
+                elinor_cli::input_error(format!(
+                    "Run {} has no result for dataset {}",
+                    run.name, dataset.name
+                ))
+            })?;
+            let pred_lines = elinor_cli::load_lines(base_dir.join(pred_jsonl))?;
+            let start = Instant::now();
+            let pred_records = elinor_cli::parse_pred_records(&pred_lines)?;
+            let pred_records = normalizer.normalize_records(pred_records);
+            let mut b = PredRelStoreBuilder::new();
+            for record in pred_records {
+                let score = args
+                    .score_quantization
+                    .map_or(record.score, |q| q.quantize(record.score));
+                b.add_record_with_policy(
+                    record.query_id,
+                    record.doc_id,
+                    score,
+                    args.non_finite_policy,
+                )?;
+            }
+            pred_rels.push(b.build());
+            timings.record(
+                format!("{}/{}/build_pred_rels", run.name, dataset.name),
+                start.elapsed(),
+            );
+        }
+        let mut run_results = vec![];
+        for &metric in &metrics {
+            let collections = manifest
+                .datasets
+                .iter()
+                .map(|d| d.name.clone())
+                .zip(true_rels.iter().zip(pred_rels.iter()))
+                .map(|(name, (t, p))| (name, t, p));
+            let result = timings.time(format!("{}/{metric:#}", run.name), || {
+                elinor::multi_collection::evaluate_collections(collections, metric)
+            })?;
+            run_results.push(result);
+        }
+        results.push(run_results);
+    }
+    if args.timings {
+        eprintln!("{timings}");
+    }
+
+    let precision = args.precision.unwrap_or(4);
+    let mut out: Box<dyn Write> = if args
+        .output_csv
+        .as_deref()
+        .map_or(false, elinor_cli::is_stdio)
+    {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    for (j, metric) in metrics.iter().enumerate() {
+        writeln!(out, "# {metric:#}")?;
+        let mut cells = vec![std::iter::once("Run".to_string())
+            .chain(manifest.datasets.iter().map(|d| d.name.clone()))
+            .chain(std::iter::once("Average".to_string()))
+            .collect::<Vec<_>>()];
+        for (run, run_results) in manifest.runs.iter().zip(&results) {
+            let result = &run_results[j];
+            cells.push(
+                std::iter::once(run.name.clone())
+                    .chain(
+                        result
+                            .evaluations()
+                            .iter()
+                            .map(|(_, e)| format!("{:.precision$}", e.mean())),
+                    )
+                    .chain(std::iter::once(format!(
+                        "{:.precision$}",
+                        result.macro_mean()
+                    )))
+                    .collect(),
+            );
+        }
+        print_cells(&mut out, &cells, args.print_mode)?;
+    }
+
+    if let Some(output_csv) = &args.output_csv {
+        let columns = consolidated_columns(&manifest, &metrics, &results);
+        let file = elinor_cli::create_output(output_csv)?;
+        elinor_cli::write_table(file, &columns, b',', args.precision)?;
+    }
+    Ok(Outcome::Success)
+}
+
+fn consolidated_columns(
+    manifest: &Manifest,
+    metrics: &[Metric],
+    results: &[Vec<MultiCollectionEvaluation<String, String>>],
+) -> Vec<TableColumn> {
+    let mut run_names = vec![];
+    let mut metric_names = vec![];
+    let mut dataset_means = vec![vec![]; manifest.datasets.len()];
+    let mut averages = vec![];
+    for (run, run_results) in manifest.runs.iter().zip(results) {
+        for (metric, result) in metrics.iter().zip(run_results) {
+            run_names.push(run.name.clone());
+            metric_names.push(format!("{metric:#}"));
+            for (means, (_, e)) in dataset_means.iter_mut().zip(result.evaluations()) {
+                means.push(e.mean());
+            }
+            averages.push(result.macro_mean());
+        }
+    }
+    let mut columns = vec![
+        TableColumn::Text("run".to_string(), run_names),
+        TableColumn::Text("metric".to_string(), metric_names),
+    ];
+    for (dataset, means) in manifest.datasets.iter().zip(dataset_means) {
+        columns.push(TableColumn::Real(dataset.name.clone(), means));
+    }
+    columns.push(TableColumn::Real("average".to_string(), averages));
+    columns
+}
+
+fn print_cells(out: &mut dyn Write, cells: &[Vec<String>], mode: PrintMode) -> Result<()> {
+    match mode {
+        PrintMode::Pretty => {
+            let to_row = |row: &[String]| {
+                prettytable::Row::new(row.iter().map(|c| prettytable::Cell::new(c)).collect())
+            };
+            let mut table = prettytable::Table::new();
+            table.set_titles(to_row(&cells[0]));
+            for row in &cells[1..] {
+                table.add_row(to_row(row));
+            }
+            table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.print(out)?;
+        }
+        PrintMode::Raw => {
+            for row in cells {
+                writeln!(out, "{}", row.join("\t"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../schemas/benchmark-manifest.v1.json");
+        let schema = elinor::json_schema::schema_for::<Manifest>();
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            current == generated,
+            "{} is out of date; run the tests with UPDATE_SCHEMAS=1 to regenerate it.",
+            path.display()
+        );
+    }
+}
@@ -1,20 +1,38 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::str::FromStr;
 
+use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
-use elinor::statistical_tests::bootstrap_test::BootstrapTester;
-use elinor::statistical_tests::randomized_tukey_hsd_test::RandomizedTukeyHsdTester;
-use elinor::statistical_tests::StudentTTest;
-use elinor::statistical_tests::TukeyHsdTest;
-use elinor::statistical_tests::TwoWayAnovaWithoutReplication;
-use polars::prelude::*;
-use polars_lazy::prelude::*;
+use elinor::comparison::template::ReportRenderer;
+use elinor::comparison::template::ReportTemplate;
+use elinor::comparison::CompareOptions;
+use elinor::comparison::ComparisonReport;
+use elinor::comparison::ComparisonTest;
+use elinor::statistical_tests::corrections::Correction;
+use elinor::statistical_tests::effect_sizes::EffectSizeBands;
+use elinor::statistical_tests::effect_sizes::EffectSizeLabel;
+use elinor::statistical_tests::Standardization;
+use elinor::timing::TimingReport;
+use elinor_cli::InvalidScorePolicy;
+use elinor_cli::Outcome;
+use elinor_cli::ScoreMatrix;
+use elinor_cli::TableColumn;
+use elinor_cli::TableLayout;
+use elinor_cli::Warnings;
+use serde::Serialize;
 
 #[derive(Clone, Copy, Debug)]
 enum PrintMode {
     Pretty,
     Raw,
+    Markdown,
+    Latex,
 }
 
 impl FromStr for PrintMode {
@@ -24,11 +42,22 @@ impl FromStr for PrintMode {
         match s {
             "pretty" => Ok(Self::Pretty),
             "raw" => Ok(Self::Raw),
+            "markdown" => Ok(Self::Markdown),
+            "latex" => Ok(Self::Latex),
             _ => Err(format!("Invalid PrintMode: {}", s)),
         }
     }
 }
 
+/// Options for printing tables.
+#[derive(Clone, Debug)]
+struct PrintOptions {
+    mode: PrintMode,
+    precision: usize,
+    columns: Vec<String>,
+    transpose: bool,
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about = "Compare the performance of multiple models.")]
 struct Args {
@@ -40,10 +69,60 @@ struct Args {
     #[arg(long)]
     tab_separator: bool,
 
-    /// Print mode for the output (pretty or raw).
+    /// Layout of the input CSV files and the output CSV file of --diff-csv (wide or long).
+    /// The long layout has the topic, metric, and score columns.
+    #[arg(long, default_value = "wide")]
+    csv_layout: TableLayout,
+
+    /// Policy for invalid scores in the input CSV files, i.e., non-finite scores
+    /// and scores out of the bounds of the metrics (reject, drop, or clamp).
+    /// The drop policy regards them as missing, and the clamp policy clamps finite scores to the bounds.
+    #[arg(long, default_value = "reject")]
+    invalid_scores: InvalidScorePolicy,
+
+    /// Drop the topics missing in any of the systems for each metric instead of failing,
+    /// reporting the number of dropped topics as warnings.
+    #[arg(long)]
+    drop_missing_topics: bool,
+
+    /// Minimum number of topics remaining after dropping the missing ones, below which the comparison fails.
+    #[arg(long, default_value = "2", requires = "drop_missing_topics")]
+    min_topics: usize,
+
+    /// Standardize the scores topic by topic across the systems before the tests
+    /// (z for z-scores, cdf for their normal CDF values in [0, 1], or ab for Sakai's std-AB).
+    /// The means are also of the standardized scores, while the exported differences are of the raw ones.
+    /// Available only for two or more systems.
+    #[arg(long)]
+    standardize: Option<Standardization>,
+
+    /// Print mode for the output (pretty, raw, markdown, or latex).
+    /// The markdown mode prints GitHub Markdown tables, and the latex mode prints booktabs tables
+    /// with the headings as comments.
     #[arg(short, long, default_value = "pretty")]
     print_mode: PrintMode,
 
+    /// Number of decimal places of real numbers in the output.
+    /// The printed tables use 4 if not specified, and the CSV files use the full precision.
+    #[arg(long)]
+    precision: Option<usize>,
+
+    /// Columns of the statistics to print, separated by commas (e.g., mean,p,es).
+    /// A column can be specified by its name or abbreviation (e.g., p for p-value).
+    /// If not specified, all the columns are printed.
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Transpose the tables.
+    #[arg(long)]
+    transpose: bool,
+
+    /// Statistical tests to run, separated by commas
+    /// (t, bootstrap, wilcoxon, sign, and randomization for two systems, and anova, hsd, and randomization for more systems).
+    /// If not specified, all the tests are run.
+    #[arg(long, value_delimiter = ',')]
+    tests: Vec<ComparisonTest>,
+
     /// Number of resamples for the bootstrap test.
     #[arg(long, default_value = "10000")]
     n_resamples: usize,
@@ -51,34 +130,144 @@ struct Args {
     /// Number of iterations for the randomized test.
     #[arg(long, default_value = "10000")]
     n_iters: usize,
+
+    /// Correction of the p-values for testing many metrics (bonferroni, holm, or bh),
+    /// printed as the adj-p-value column. Available only for two systems.
+    #[arg(long)]
+    correction: Option<Correction>,
+
+    /// Path to the output CSV file of the topic-by-metric differences (System_1 - System_2).
+    /// Available only for two systems.
+    #[arg(long)]
+    diff_csv: Option<PathBuf>,
+
+    /// Path to the output SVG file of the heatmap of the topic-by-metric differences.
+    /// Available only for two systems.
+    #[arg(long)]
+    diff_svg: Option<PathBuf>,
+
+    /// Path to the output JSON file of all the computed statistics,
+    /// with the input paths and the options of the tests.
+    #[arg(long)]
+    output_json: Option<PathBuf>,
+
+    /// Label the effect sizes as negligible, small, medium, or large,
+    /// by Cohen's thresholds for d (0.2,0.5,0.8) for the t-test and Tukey HSD test
+    /// and for r (0.1,0.3,0.5) for the Wilcoxon signed-rank and sign tests.
+    #[arg(long)]
+    es_labels: bool,
+
+    /// Thresholds of the small, medium, and large labels for the t-test and Tukey HSD test,
+    /// separated by commas (e.g., 0.2,0.5,0.8). Implies --es-labels.
+    #[arg(long)]
+    es_bands: Option<EffectSizeBands>,
+
+    /// Path to a template file to render the results instead of printing the tables.
+    /// See the documentation of elinor::comparison::template for the syntax.
+    #[arg(long)]
+    template: Option<PathBuf>,
+
+    /// Gate mode with the significance level (e.g., 0.05), regarding System_1 as the baseline.
+    /// Exits with code 3 if System_2 has a lower mean than System_1 in any metric
+    /// with p-values below the level in all the tests run (adj-p-values if --correction is given).
+    /// Available only for two systems.
+    #[arg(long)]
+    gate: Option<f64>,
+
+    /// Print the wall-clock time of loading the inputs and running each test for each metric
+    /// to the standard error, in seconds.
+    #[arg(long)]
+    timings: bool,
+
+    /// Overwrite existing output files.
+    #[arg(long)]
+    force: bool,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
 
+fn run(args: Args) -> Result<Outcome> {
     if args.input_csvs.is_empty() {
-        return Err(anyhow::anyhow!("Specify at least one input CSV file."));
+        return Err(elinor_cli::input_error(
+            "Specify at least one input CSV file.",
+        ));
+    }
+    if args.correction.is_some() && args.input_csvs.len() != 2 {
+        return Err(elinor_cli::input_error(
+            "The correction is available only for two systems.",
+        ));
+    }
+    if args.standardize.is_some() && args.input_csvs.len() < 2 {
+        return Err(elinor_cli::input_error(
+            "The standardization is available only for two or more systems.",
+        ));
+    }
+    if (args.diff_csv.is_some() || args.diff_svg.is_some()) && args.input_csvs.len() != 2 {
+        return Err(elinor_cli::input_error(
+            "The differences can be exported only for two systems.",
+        ));
+    }
+    if let Some(alpha) = args.gate {
+        if args.input_csvs.len() != 2 {
+            return Err(elinor_cli::input_error(
+                "The gate mode is available only for two systems.",
+            ));
+        }
+        if alpha <= 0.0 || alpha > 1.0 {
+            return Err(elinor_cli::input_error(
+                "The significance level must be in the range (0, 1].",
+            ));
+        }
+    }
+    let output_paths = [&args.diff_csv, &args.diff_svg, &args.output_json]
+        .into_iter()
+        .flatten();
+    if output_paths.clone().any(elinor_cli::is_stdio) {
+        return Err(elinor_cli::input_error(
+            "The outputs cannot be written to the standard output, which is used for the results.",
+        ));
     }
+    elinor_cli::check_outputs(output_paths.map(PathBuf::as_path), args.force)?;
 
-    let separator = if args.tab_separator { b'\t' } else { b',' };
-    let csv_parse_options = CsvParseOptions {
-        separator,
-        ..Default::default()
+    let print_options = PrintOptions {
+        mode: args.print_mode,
+        precision: args.precision.unwrap_or(4),
+        columns: args.columns.iter().map(|c| c.to_lowercase()).collect(),
+        transpose: args.transpose,
     };
 
-    let mut dfs = vec![];
+    let mut compare_options = CompareOptions::new()
+        .with_tests(args.tests.clone())
+        .with_n_resamples(args.n_resamples)
+        .with_n_iters(args.n_iters)
+        .with_drop_missing_topics(args.drop_missing_topics)
+        .with_min_topics(args.min_topics);
+    if let Some(correction) = args.correction {
+        compare_options = compare_options.with_correction(correction);
+    }
+    if args.es_labels || args.es_bands.is_some() {
+        compare_options = compare_options
+            .with_effect_size_bands(args.es_bands.unwrap_or_else(EffectSizeBands::cohen_d))
+            .with_correlation_bands(EffectSizeBands::cohen_r());
+    }
+
+    let separator = if args.tab_separator { b'\t' } else { b',' };
+    let mut timings = TimingReport::new();
+    let mut warnings = Warnings::new(false);
+    let mut matrices = vec![];
     for input_csv in &args.input_csvs {
-        let df = CsvReadOptions::default()
-            .with_parse_options(csv_parse_options.clone())
-            .try_into_reader_with_file_path(Some(input_csv.clone()))?
-            .finish()?;
-        dfs.push(df);
+        matrices.push(timings.time(
+            format!("load/{}", elinor_cli::display_name(input_csv)),
+            || ScoreMatrix::from_csv_with_layout(input_csv, separator, args.csv_layout),
+        )?);
     }
 
     // Get the header name of the first column.
-    let topic_headers = dfs
+    let topic_headers = matrices
         .iter()
-        .map(|df| df.get_columns()[0].name().to_string())
+        .map(|matrix| matrix.topic_header().to_string())
         .collect::<Vec<_>>();
     if topic_headers
         .iter()
@@ -86,550 +275,1026 @@ fn main() -> Result<()> {
         .len()
         != 1
     {
-        return Err(anyhow::anyhow!(
+        return Err(elinor_cli::input_error(format!(
             "The header names of the first columns must be the same, but got: {:?}",
             topic_headers
-        ));
+        )));
     }
     let topic_header = topic_headers[0].as_str();
 
-    println!("# Basic statistics");
+    let metrics = extract_common_metrics(&matrices);
+    if metrics.is_empty() {
+        return Err(elinor_cli::input_error("No common metrics found."));
+    }
+    for (input_csv, matrix) in args.input_csvs.iter().zip(matrices.iter_mut()) {
+        let name = elinor_cli::display_name(input_csv);
+        let invalids = matrix
+            .sanitize(&metrics, args.invalid_scores)
+            .with_context(|| format!("Invalid scores in {name}"))?;
+        for invalid in invalids {
+            warnings.warn(format!("{invalid} in {name}"))?;
+        }
+    }
+    let mut score_maps = vec![];
+    for metric in &metrics {
+        let mut maps = vec![];
+        for matrix in &matrices {
+            maps.push(matrix.score_map(metric)?);
+        }
+        if let Some(method) = args.standardize {
+            maps = elinor::statistical_tests::standardize_scores(&maps, method)?;
+        }
+        score_maps.push((metric.clone(), maps));
+    }
+    let (report, test_timings) =
+        elinor::comparison::compare_score_maps_with_timings(&score_maps, &compare_options)?;
+    for comparison in &report.metrics {
+        if comparison.n_dropped_topics > 0 {
+            warnings.warn(format!(
+                "{} topics missing in some systems were dropped for {}.",
+                comparison.n_dropped_topics, comparison.metric
+            ))?;
+        }
+    }
+    if args.timings {
+        timings.extend_with_prefix("test", test_timings);
+        eprintln!("{timings}");
+    }
+    if let Some(output_json) = &args.output_json {
+        write_report_json(
+            output_json,
+            &args.input_csvs,
+            &report,
+            &compare_options,
+            args.standardize,
+        )?;
+    }
+    let differences = if matrices.len() == 2 && (args.diff_csv.is_some() || args.diff_svg.is_some())
+    {
+        let (differences, n_incomplete) = topic_differences(&matrices[0], &matrices[1], &metrics)?;
+        if n_incomplete > 0 {
+            warnings.warn(format!(
+                "{n_incomplete} topics without the scores of all the metrics in both systems were excluded from the differences."
+            ))?;
+        }
+        Some(differences)
+    } else {
+        None
+    };
+    let outcome = args.gate.map_or_else(
+        || warnings.outcome(),
+        |alpha| match gate(&report, alpha) {
+            Outcome::Success => warnings.outcome(),
+            outcome => outcome,
+        },
+    );
+
+    if let Some(template) = &args.template {
+        let source = std::fs::read_to_string(template)?;
+        let template = ReportTemplate::parse(&source)?.with_precision(args.precision.unwrap_or(4));
+        print!("{}", template.render(&report)?);
+        if let Some(differences) = &differences {
+            export_differences(
+                &metrics,
+                differences,
+                topic_header,
+                args.diff_csv.as_deref(),
+                args.diff_svg.as_deref(),
+                args.csv_layout,
+                args.precision,
+            )?;
+        }
+        return Ok(outcome);
+    }
+
+    print_heading("# Basic statistics", &print_options);
     {
         let columns = vec![
-            Series::new(
-                "Key".into(),
+            TableColumn::Text(
+                "Key".to_string(),
                 vec![
                     "n_systems".to_string(),
                     "n_topics".to_string(),
                     "n_metrics".to_string(),
                 ],
             ),
-            Series::new(
-                "Value".into(),
+            TableColumn::Integer(
+                "Value".to_string(),
                 vec![
-                    dfs.len() as u64,
-                    dfs[0].get_columns()[0].len() as u64,
-                    dfs[0].get_columns().len() as u64 - 1,
+                    matrices.len() as u64,
+                    report.n_topics as u64,
+                    matrices[0].metrics().len() as u64,
                 ],
             ),
         ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, args.print_mode);
+        print_table(&columns, &print_options);
     }
 
     // If there is only one input CSV file, just print the means.
     if args.input_csvs.len() == 1 {
-        println!("\n# Means");
+        print_heading("\n# Means", &print_options);
         {
-            let metrics = extract_metrics(&dfs[0]);
-            let values = get_means(&dfs[0], &metrics, topic_header);
             let columns = vec![
-                Series::new("Metric".into(), metrics),
-                Series::new("Score".into(), values),
+                TableColumn::Text("Metric".to_string(), metrics),
+                TableColumn::Real(
+                    "Score".to_string(),
+                    report
+                        .metrics
+                        .iter()
+                        .map(|m| m.means[0])
+                        .collect::<Vec<_>>(),
+                ),
             ];
-            let df = DataFrame::new(columns)?;
-            print_dataframe(&df, args.print_mode);
+            print_table(&columns, &print_options);
         }
-        return Ok(());
+        return Ok(outcome);
     }
 
-    println!("\n# Alias");
+    print_heading("\n# Alias", &print_options);
     {
         let columns = vec![
-            Series::new(
-                "Alias".into(),
-                (1..=dfs.len())
+            TableColumn::Text(
+                "Alias".to_string(),
+                (1..=matrices.len())
                     .map(|i| format!("System_{}", i))
                     .collect::<Vec<_>>(),
             ),
-            Series::new(
-                "Path".into(),
+            TableColumn::Text(
+                "Path".to_string(),
                 args.input_csvs
                     .iter()
-                    .map(|p| p.to_string_lossy())
+                    .map(|p| p.to_string_lossy().to_string())
                     .collect::<Vec<_>>(),
             ),
         ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, args.print_mode);
+        print_table(&columns, &print_options);
     }
 
-    if dfs.len() == 2 {
-        compare_two_systems(
-            &dfs[0],
-            &dfs[1],
+    if matrices.len() == 2 {
+        print_two_systems(&report, &print_options, &compare_options)?;
+    }
+    if let Some(differences) = &differences {
+        export_differences(
+            &metrics,
+            differences,
             topic_header,
-            args.print_mode,
-            args.n_resamples,
-            args.n_iters,
+            args.diff_csv.as_deref(),
+            args.diff_svg.as_deref(),
+            args.csv_layout,
+            args.precision,
         )?;
     }
-    if dfs.len() > 2 {
-        compare_multiple_systems(&dfs, topic_header, args.print_mode, args.n_iters)?;
+    if matrices.len() > 2 {
+        print_multiple_systems(&report, &print_options, &compare_options)?;
     }
 
-    Ok(())
-}
-
-fn extract_metrics(df: &DataFrame) -> Vec<String> {
-    df.get_columns()
-        .iter()
-        .skip(1) // The first column is the query_id
-        .map(|column| column.name().to_string())
-        .collect()
+    Ok(outcome)
 }
 
-fn extract_common_metrics<'a, I>(dfs: I) -> Vec<String>
+fn extract_common_metrics<'a, I>(matrices: I) -> Vec<String>
 where
-    I: IntoIterator<Item = &'a DataFrame>,
+    I: IntoIterator<Item = &'a ScoreMatrix>,
 {
-    let mut dfs = dfs.into_iter();
-    let mut common_metrics = extract_metrics(dfs.next().unwrap());
-    for df in dfs {
-        let metrics = extract_metrics(df);
-        common_metrics.retain(|metric| metrics.contains(metric));
+    let mut matrices = matrices.into_iter();
+    let mut common_metrics = matrices.next().unwrap().metrics().to_vec();
+    for matrix in matrices {
+        common_metrics.retain(|metric| matrix.metrics().contains(metric));
     }
     common_metrics
 }
 
-fn get_means(df: &DataFrame, metrics: &[String], topic_header: &str) -> Vec<f64> {
-    let means = df
-        .clone()
-        .lazy()
-        .select([col("*").exclude([topic_header]).mean()])
-        .collect()
-        .unwrap();
-    let values = metrics
-        .iter()
-        .map(|metric| {
-            means
-                .column(metric)
-                .unwrap()
-                .f64()
-                .unwrap()
-                .first()
-                .unwrap()
-        })
-        .collect::<Vec<_>>();
-    values
+/// Version of the JSON Schema `schemas/compare-output.v<N>.json` that the document follows.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// JSON document of the comparison, written by --output-json.
+#[derive(Serialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct JsonDocument<'a> {
+    /// Version of the JSON Schema.
+    schema_version: u32,
+    /// Input CSV files in the order of the systems.
+    systems: Vec<JsonSystem>,
+    /// Options of the statistical tests.
+    options: JsonOptions,
+    report: &'a ComparisonReport,
 }
 
-fn compare_two_systems(
-    df_1: &DataFrame,
-    df_2: &DataFrame,
-    topic_header: &str,
-    print_mode: PrintMode,
+/// Input CSV file of a system.
+#[derive(Serialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct JsonSystem {
+    /// Alias of the system (e.g., System_1).
+    alias: String,
+    /// Path to the input CSV file.
+    path: String,
+}
+
+/// Options of the statistical tests.
+#[derive(Serialize)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct JsonOptions {
+    /// Statistical tests run.
+    tests: Vec<String>,
+    /// Number of resamples for the bootstrap test.
     n_resamples: usize,
+    /// Number of iterations for the randomized tests.
     n_iters: usize,
+    /// Correction of the p-values over the metrics.
+    correction: Option<String>,
+    /// Topic-wise standardization of the scores before the tests.
+    standardization: Option<String>,
+}
+
+/// Writes the comparison report in JSON, with the input paths and the options of the tests.
+///
+/// NaN values are written as null.
+fn write_report_json(
+    output_json: &Path,
+    input_csvs: &[PathBuf],
+    report: &ComparisonReport,
+    compare_options: &CompareOptions,
+    standardization: Option<Standardization>,
 ) -> Result<()> {
-    let metrics = extract_common_metrics([df_1, df_2]);
-    if metrics.is_empty() {
-        return Err(anyhow::anyhow!("No common metrics found."));
-    }
+    let document = JsonDocument {
+        schema_version: JSON_SCHEMA_VERSION,
+        systems: input_csvs
+            .iter()
+            .enumerate()
+            .map(|(i, path)| JsonSystem {
+                alias: format!("System_{}", i + 1),
+                path: path.to_string_lossy().to_string(),
+            })
+            .collect(),
+        options: JsonOptions {
+            tests: ComparisonTest::ALL
+                .into_iter()
+                .filter(|&test| compare_options.runs(test))
+                .map(|test| test.to_string())
+                .collect(),
+            n_resamples: compare_options.n_resamples(),
+            n_iters: compare_options.n_iters(),
+            correction: compare_options.correction().map(|c| c.to_string()),
+            standardization: standardization.map(|s| s.to_string()),
+        },
+        report,
+    };
+    let mut file = elinor_cli::create_output(output_json)?;
+    serde_json::to_writer_pretty(&mut file, &document)?;
+    writeln!(file)?;
+    file.flush()?;
+    Ok(())
+}
 
-    println!("\n# Means");
-    {
-        let mut columns = vec![Series::new(
-            "Metric".into(),
-            metrics.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-        )];
-        for (i, df) in [df_1, df_2].into_iter().enumerate() {
-            let values = get_means(df, &metrics, topic_header);
-            columns.push(Series::new(format!("System_{}", i + 1).into(), values));
+/// Reports the metrics in which System_2 is significantly worse than System_1
+/// by all the tests run, returning [`Outcome::Regression`] if any.
+fn gate(report: &ComparisonReport, alpha: f64) -> Outcome {
+    let mut outcome = Outcome::Success;
+    for m in &report.metrics {
+        let p_values = [
+            m.student_t.map(|stat| (stat.p_value, stat.adj_p_value)),
+            m.bootstrap.map(|stat| (stat.p_value, stat.adj_p_value)),
+            m.wilcoxon.map(|stat| (stat.p_value, stat.adj_p_value)),
+            m.sign.map(|stat| (stat.p_value, stat.adj_p_value)),
+            m.randomization.map(|stat| (stat.p_value, stat.adj_p_value)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|(p_value, adj_p_value)| adj_p_value.unwrap_or(p_value))
+        .collect::<Vec<_>>();
+        if m.means[1] < m.means[0] && !p_values.is_empty() && p_values.iter().all(|&p| p < alpha) {
+            eprintln!(
+                "Regression: System_2 is significantly worse than System_1 in {} (alpha = {alpha}).",
+                m.metric
+            );
+            outcome = Outcome::Regression;
         }
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
     }
+    outcome
+}
 
-    let mut df_metrics = vec![];
-    for metric in &metrics {
-        let metric = metric.as_str();
-        let system_1 = df_1
-            .clone()
-            .lazy()
-            .select([col(topic_header), col(metric).alias("system_1")])
-            .collect()?;
-        let system_2 = df_2
-            .clone()
-            .lazy()
-            .select([col(topic_header), col(metric).alias("system_2")])
-            .collect()?;
-        let joined = system_1
-            .clone()
-            .lazy()
-            .join(
-                system_2.clone().lazy(),
-                [col(topic_header)],
-                [col(topic_header)],
-                JoinArgs::new(JoinType::Left),
-            )
-            .collect()?;
-        df_metrics.push(joined);
-    }
+fn print_two_systems(
+    report: &ComparisonReport,
+    print_options: &PrintOptions,
+    compare_options: &CompareOptions,
+) -> Result<()> {
+    let metrics = report
+        .metrics
+        .iter()
+        .map(|m| m.metric.clone())
+        .collect::<Vec<_>>();
 
-    println!("\n# Two-sided paired Student's t-test for (System_1 - System_2)");
+    print_heading("\n# Means", print_options);
     {
-        let mut stats = vec![];
-        for df in df_metrics.iter() {
-            let values_1 = df.column("system_1")?.f64()?;
-            let values_2 = df.column("system_2")?.f64()?;
-            let paired_samples = values_1
-                .into_iter()
-                .zip(values_2.into_iter())
-                .map(|(a, b)| (a.unwrap(), b.unwrap()));
-            stats.push(StudentTTest::from_paired_samples(paired_samples)?);
+        let mut columns = vec![TableColumn::Text("Metric".to_string(), metrics.clone())];
+        for i in 0..2 {
+            let values = report
+                .metrics
+                .iter()
+                .map(|m| m.means[i])
+                .collect::<Vec<_>>();
+            columns.push(TableColumn::Real(format!("System_{}", i + 1), values));
         }
-        let columns = vec![
-            Series::new(
-                "Metric".into(),
-                metrics.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-            ),
-            Series::new(
-                "Mean".into(),
-                stats.iter().map(|stat| stat.mean()).collect::<Vec<_>>(),
+        print_table(&columns, print_options);
+    }
+
+    let stats = report
+        .metrics
+        .iter()
+        .filter_map(|m| m.student_t)
+        .collect::<Vec<_>>();
+    if !stats.is_empty() {
+        print_heading(
+            "\n# Two-sided paired Student's t-test for (System_1 - System_2)",
+            print_options,
+        );
+        let mut columns = vec![
+            TableColumn::Text("Metric".to_string(), metrics.clone()),
+            TableColumn::Real(
+                "Mean".to_string(),
+                stats.iter().map(|stat| stat.mean).collect::<Vec<_>>(),
             ),
-            Series::new(
-                "Var".into(),
-                stats.iter().map(|stat| stat.variance()).collect::<Vec<_>>(),
+            TableColumn::Real(
+                "Var".to_string(),
+                stats.iter().map(|stat| stat.variance).collect::<Vec<_>>(),
             ),
-            Series::new(
-                "ES".into(),
+            TableColumn::Real(
+                "ES".to_string(),
                 stats
                     .iter()
-                    .map(|stat| stat.effect_size())
+                    .map(|stat| stat.effect_size)
                     .collect::<Vec<_>>(),
             ),
-            Series::new(
-                "t-stat".into(),
-                stats.iter().map(|stat| stat.t_stat()).collect::<Vec<_>>(),
+        ];
+        columns.extend(label_column(
+            stats.iter().map(|stat| stat.effect_size_label),
+        ));
+        columns.push(TableColumn::Real(
+            "t-stat".to_string(),
+            stats.iter().map(|stat| stat.t_stat).collect::<Vec<_>>(),
+        ));
+        columns.extend(p_value_columns(
+            stats.iter().map(|stat| (stat.p_value, stat.adj_p_value)),
+        ));
+        columns.push(TableColumn::Real(
+            "95% MOE".to_string(),
+            stats.iter().map(|stat| stat.moe95).collect::<Vec<_>>(),
+        ));
+        print_table(&columns, print_options);
+    }
+
+    let stats = report
+        .metrics
+        .iter()
+        .filter_map(|m| m.bootstrap)
+        .collect::<Vec<_>>();
+    if !stats.is_empty() {
+        print_heading(
+            &format!(
+                "\n# Two-sided paired Bootstrap test (n_resamples = {})",
+                compare_options.n_resamples()
+            ),
+            print_options,
+        );
+        let mut columns = vec![TableColumn::Text("Metric".to_string(), metrics.clone())];
+        columns.extend(p_value_columns(
+            stats.iter().map(|stat| (stat.p_value, stat.adj_p_value)),
+        ));
+        print_table(&columns, print_options);
+    }
+
+    let stats = report
+        .metrics
+        .iter()
+        .filter_map(|m| m.wilcoxon)
+        .collect::<Vec<_>>();
+    if !stats.is_empty() {
+        print_heading(
+            "\n# Two-sided Wilcoxon signed-rank test for (System_1 - System_2)",
+            print_options,
+        );
+        let mut columns = vec![
+            TableColumn::Text("Metric".to_string(), metrics.clone()),
+            TableColumn::Real(
+                "W".to_string(),
+                stats.iter().map(|stat| stat.w_stat).collect::<Vec<_>>(),
             ),
-            Series::new(
-                "p-value".into(),
-                stats.iter().map(|stat| stat.p_value()).collect::<Vec<_>>(),
+            TableColumn::Real(
+                "z-stat".to_string(),
+                stats.iter().map(|stat| stat.z_stat).collect::<Vec<_>>(),
             ),
-            Series::new(
-                "95% MOE".into(),
+            TableColumn::Real(
+                "ES".to_string(),
                 stats
                     .iter()
-                    .map(|stat| stat.margin_of_error(0.05).unwrap())
+                    .map(|stat| stat.effect_size)
                     .collect::<Vec<_>>(),
             ),
         ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
+        columns.extend(label_column(
+            stats.iter().map(|stat| stat.effect_size_label),
+        ));
+        columns.extend(p_value_columns(
+            stats.iter().map(|stat| (stat.p_value, stat.adj_p_value)),
+        ));
+        print_table(&columns, print_options);
     }
 
-    println!("\n# Two-sided paired Bootstrap test (n_resamples = {n_resamples})");
-    {
-        let mut stats = vec![];
-        let tester = BootstrapTester::new().with_n_resamples(n_resamples);
-        for df in df_metrics.iter() {
-            let values_1 = df.column("system_1")?.f64()?;
-            let values_2 = df.column("system_2")?.f64()?;
-            let paired_samples = values_1
-                .into_iter()
-                .zip(values_2.into_iter())
-                .map(|(a, b)| (a.unwrap(), b.unwrap()));
-            stats.push(tester.test(paired_samples)?);
-        }
-        let columns = vec![
-            Series::new(
-                "Metric".into(),
-                metrics.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+    let stats = report
+        .metrics
+        .iter()
+        .filter_map(|m| m.sign)
+        .collect::<Vec<_>>();
+    if !stats.is_empty() {
+        print_heading(
+            "\n# Two-sided paired sign test for (System_1 - System_2)",
+            print_options,
+        );
+        let mut columns = vec![
+            TableColumn::Text("Metric".to_string(), metrics.clone()),
+            TableColumn::Integer(
+                "Wins".to_string(),
+                stats
+                    .iter()
+                    .map(|stat| stat.n_wins as u64)
+                    .collect::<Vec<_>>(),
             ),
-            Series::new(
-                "p-value".into(),
-                stats.iter().map(|stat| stat.p_value()).collect::<Vec<_>>(),
+            TableColumn::Integer(
+                "Losses".to_string(),
+                stats
+                    .iter()
+                    .map(|stat| stat.n_losses as u64)
+                    .collect::<Vec<_>>(),
             ),
-        ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
-    }
-
-    println!("\n# Fisher's randomized test (n_iters = {n_iters})");
-    {
-        let mut stats = vec![];
-        let tester = RandomizedTukeyHsdTester::new(2).with_n_iters(n_iters);
-        for df in df_metrics.iter() {
-            let values_1 = df.column("system_1")?.f64()?;
-            let values_2 = df.column("system_2")?.f64()?;
-            let paired_scores = values_1
-                .into_iter()
-                .zip(values_2.into_iter())
-                .map(|(x, y)| [x.unwrap(), y.unwrap()]);
-            stats.push(tester.test(paired_scores)?);
-        }
-        let columns = vec![
-            Series::new(
-                "Metric".into(),
-                metrics.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+            TableColumn::Integer(
+                "Ties".to_string(),
+                stats
+                    .iter()
+                    .map(|stat| stat.n_ties as u64)
+                    .collect::<Vec<_>>(),
             ),
-            Series::new(
-                "p-value".into(),
+            TableColumn::Real(
+                "ES".to_string(),
                 stats
                     .iter()
-                    .map(|stat| stat.p_values()[0][1])
+                    .map(|stat| stat.effect_size)
                     .collect::<Vec<_>>(),
             ),
         ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
+        columns.extend(label_column(
+            stats.iter().map(|stat| stat.effect_size_label),
+        ));
+        columns.extend(p_value_columns(
+            stats.iter().map(|stat| (stat.p_value, stat.adj_p_value)),
+        ));
+        print_table(&columns, print_options);
+    }
+
+    let stats = report
+        .metrics
+        .iter()
+        .filter_map(|m| m.randomization)
+        .collect::<Vec<_>>();
+    if !stats.is_empty() {
+        print_heading(
+            &format!(
+                "\n# Fisher's randomized test (n_iters = {})",
+                compare_options.n_iters()
+            ),
+            print_options,
+        );
+        let mut columns = vec![TableColumn::Text("Metric".to_string(), metrics)];
+        columns.extend(p_value_columns(
+            stats.iter().map(|stat| (stat.p_value, stat.adj_p_value)),
+        ));
+        print_table(&columns, print_options);
     }
 
     Ok(())
 }
 
-fn compare_multiple_systems(
-    dfs: &[DataFrame],
+/// Returns the ES label column if the effect sizes are labeled.
+fn label_column<I>(labels: I) -> Option<TableColumn>
+where
+    I: IntoIterator<Item = Option<EffectSizeLabel>>,
+{
+    let labels = labels
+        .into_iter()
+        .map(|label| label.map(|label| label.to_string()))
+        .collect::<Option<Vec<_>>>()?;
+    Some(TableColumn::Text("ES label".to_string(), labels))
+}
+
+/// Returns the p-value column, followed by the adj-p-value column if the p-values are adjusted.
+fn p_value_columns<I>(p_values: I) -> Vec<TableColumn>
+where
+    I: IntoIterator<Item = (f64, Option<f64>)>,
+{
+    let (p_values, adjusted): (Vec<f64>, Vec<Option<f64>>) = p_values.into_iter().unzip();
+    let mut columns = vec![TableColumn::Real("p-value".to_string(), p_values)];
+    if adjusted.iter().all(Option::is_some) && !adjusted.is_empty() {
+        let adjusted = adjusted.into_iter().flatten().collect::<Vec<_>>();
+        columns.push(TableColumn::Real("adj-p-value".to_string(), adjusted));
+    }
+    columns
+}
+
+/// Topic id and the differences of the scores for each metric.
+type TopicDiffs = (String, Vec<f64>);
+
+/// Computes the topic-by-metric matrix of differences (System_1 - System_2),
+/// where the topics are sorted by the mean difference over the metrics in descending order.
+///
+/// Topics without the scores of all the metrics in both systems are excluded,
+/// and their number is returned together with the matrix.
+fn topic_differences(
+    matrix_1: &ScoreMatrix,
+    matrix_2: &ScoreMatrix,
+    metrics: &[String],
+) -> Result<(Vec<TopicDiffs>, usize)> {
+    let mut topic_diffs: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for (j, metric) in metrics.iter().enumerate() {
+        let scores_2 = matrix_2.score_map(metric)?;
+        for (topic_id, a) in matrix_1.score_map(metric)? {
+            let b = scores_2.get(&topic_id).copied().unwrap_or(f64::NAN);
+            topic_diffs
+                .entry(topic_id)
+                .or_insert_with(|| vec![f64::NAN; metrics.len()])[j] = a - b;
+        }
+        for topic_id in scores_2.into_keys() {
+            topic_diffs
+                .entry(topic_id)
+                .or_insert_with(|| vec![f64::NAN; metrics.len()]);
+        }
+    }
+    let n_topics = topic_diffs.len();
+    let mut topics = topic_diffs
+        .into_iter()
+        .filter(|(_, diffs)| diffs.iter().all(|d| !d.is_nan()))
+        .collect::<Vec<_>>();
+    let n_incomplete = n_topics - topics.len();
+    let mean_diff = |diffs: &[f64]| diffs.iter().sum::<f64>() / diffs.len() as f64;
+    topics.sort_by(|(_, a), (_, b)| mean_diff(b).total_cmp(&mean_diff(a)));
+    Ok((topics, n_incomplete))
+}
+
+/// Exports the matrix of differences computed by [`topic_differences`].
+fn export_differences(
+    metrics: &[String],
+    topics: &[TopicDiffs],
     topic_header: &str,
-    print_mode: PrintMode,
-    n_iters: usize,
+    diff_csv: Option<&Path>,
+    diff_svg: Option<&Path>,
+    layout: TableLayout,
+    precision: Option<usize>,
 ) -> Result<()> {
-    let metrics = extract_common_metrics(dfs);
-    if metrics.is_empty() {
-        return Err(anyhow::anyhow!("No common metrics found."));
+    if let Some(diff_csv) = diff_csv {
+        let mut columns = vec![TableColumn::Text(
+            topic_header.to_string(),
+            topics.iter().map(|(t, _)| t.clone()).collect::<Vec<_>>(),
+        )];
+        for (j, metric) in metrics.iter().enumerate() {
+            let values = topics.iter().map(|(_, d)| d[j]).collect::<Vec<_>>();
+            columns.push(TableColumn::Real(metric.to_string(), values));
+        }
+        if layout == TableLayout::Long {
+            columns = elinor_cli::to_long_columns(&columns)?;
+        }
+        let file = elinor_cli::create_output(diff_csv)?;
+        elinor_cli::write_table(file, &columns, b',', precision)?;
     }
+    if let Some(diff_svg) = diff_svg {
+        let mut file = elinor_cli::create_output(diff_svg)?;
+        file.write_all(render_heatmap_svg(metrics, topics, precision.unwrap_or(4)).as_bytes())?;
+        file.flush()?;
+    }
+    Ok(())
+}
 
-    let mut df_metrics = vec![];
-    for metric in &metrics {
-        let metric = metric.as_str();
-        let mut df_systems = vec![];
-        for (i, df) in dfs.iter().enumerate() {
-            let df_system = df
-                .clone()
-                .lazy()
-                .select([
-                    col(topic_header),
-                    col(metric).alias(format!("system_{}", i + 1)),
-                ])
-                .collect()?;
-            df_systems.push(df_system);
-        }
-        let joined = df_systems
-            .iter()
-            .skip(1)
-            .fold(df_systems[0].clone(), |acc, df| {
-                acc.lazy()
-                    .join(
-                        df.clone().lazy(),
-                        [col(topic_header)],
-                        [col(topic_header)],
-                        JoinArgs::new(JoinType::Left),
-                    )
-                    .collect()
-                    .unwrap()
-            });
-        df_metrics.push(joined);
-    }
-
-    let rthsd_tester = RandomizedTukeyHsdTester::new(dfs.len()).with_n_iters(n_iters);
-
-    for (metric, df_metric) in metrics.iter().zip(df_metrics.iter()) {
-        println!("\n# {metric:#}");
-
-        let mut data = vec![];
-        for i in 0..dfs.len() {
-            let values = df_metric
-                .column(format!("system_{}", i + 1).as_str())?
-                .f64()?;
-            data.push(values);
+/// Renders the heatmap of the differences in SVG,
+/// where positive values are red and negative values are blue.
+fn render_heatmap_svg(metrics: &[String], topics: &[TopicDiffs], precision: usize) -> String {
+    const CELL_WIDTH: usize = 80;
+    const CELL_HEIGHT: usize = 20;
+    const LABEL_WIDTH: usize = 120;
+    const HEADER_HEIGHT: usize = 30;
+
+    let max_abs = topics
+        .iter()
+        .flat_map(|(_, diffs)| diffs.iter())
+        .filter(|d| !d.is_nan())
+        .fold(0.0_f64, |acc, d| acc.max(d.abs()));
+    let width = LABEL_WIDTH + CELL_WIDTH * metrics.len();
+    let height = HEADER_HEIGHT + CELL_HEIGHT * topics.len();
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="12">"#
+    )
+    .unwrap();
+    for (j, metric) in metrics.iter().enumerate() {
+        let x = LABEL_WIDTH + CELL_WIDTH * j + CELL_WIDTH / 2;
+        writeln!(
+            svg,
+            r#"<text x="{x}" y="{}" text-anchor="middle">{}</text>"#,
+            HEADER_HEIGHT - 10,
+            escape_xml(metric)
+        )
+        .unwrap();
+    }
+    for (i, (topic, diffs)) in topics.iter().enumerate() {
+        let y = HEADER_HEIGHT + CELL_HEIGHT * i;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+            LABEL_WIDTH - 5,
+            y + CELL_HEIGHT - 5,
+            escape_xml(topic)
+        )
+        .unwrap();
+        for (j, &diff) in diffs.iter().enumerate() {
+            let x = LABEL_WIDTH + CELL_WIDTH * j;
+            let (fill, label) = if diff.is_nan() {
+                ("rgb(200,200,200)".to_string(), String::new())
+            } else {
+                let t = if max_abs == 0.0 { 0.0 } else { diff / max_abs };
+                let c = (255.0 * (1.0 - t.abs())).round() as u8;
+                let fill = if t >= 0.0 {
+                    format!("rgb(255,{c},{c})")
+                } else {
+                    format!("rgb({c},{c},255)")
+                };
+                (fill, format!("{diff:.precision$}"))
+            };
+            writeln!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{CELL_WIDTH}" height="{CELL_HEIGHT}" fill="{fill}" stroke="white"/>"#
+            )
+            .unwrap();
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" text-anchor="middle">{label}</text>"#,
+                x + CELL_WIDTH / 2,
+                y + CELL_HEIGHT - 5
+            )
+            .unwrap();
         }
-        let mut tupled_scores = vec![];
-        for i in 0..data[0].len() {
-            let mut scores = vec![];
-            for values in &data {
-                scores.push(values.get(i).unwrap());
-            }
-            tupled_scores.push(scores);
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn print_multiple_systems(
+    report: &ComparisonReport,
+    print_options: &PrintOptions,
+    compare_options: &CompareOptions,
+) -> Result<()> {
+    let n_systems = report.n_systems;
+    let system_names = (1..=n_systems)
+        .map(|i| format!("System_{}", i))
+        .collect::<Vec<_>>();
+
+    for comparison in &report.metrics {
+        print_heading(&format!("\n# {:#}", comparison.metric), print_options);
+
+        if let Some(anova) = &comparison.anova {
+            print_heading("## System means", print_options);
+            let columns = vec![
+                TableColumn::Text("System".to_string(), system_names.clone()),
+                TableColumn::Real("Mean".to_string(), anova.system_means.clone()),
+                TableColumn::Real("95% MOE".to_string(), vec![anova.moe95; n_systems]),
+            ];
+            print_table(&columns, print_options);
+
+            print_heading("## Two-way ANOVA without replication", print_options);
+            let columns = vec![
+                TableColumn::Text(
+                    "Factor".to_string(),
+                    vec![
+                        "Between-systems".to_string(),
+                        "Between-topics".to_string(),
+                        "Residual".to_string(),
+                    ],
+                ),
+                TableColumn::Real(
+                    "Variation ".to_string(),
+                    vec![
+                        anova.between_system_variation,
+                        anova.between_topic_variation,
+                        anova.residual_variation,
+                    ],
+                ),
+                TableColumn::Integer(
+                    "DF".to_string(),
+                    vec![
+                        n_systems as u64 - 1,
+                        anova.n_topics as u64 - 1,
+                        (n_systems as u64 - 1) * (anova.n_topics as u64 - 1),
+                    ],
+                ),
+                TableColumn::Real(
+                    "Variance".to_string(),
+                    vec![
+                        anova.between_system_variance,
+                        anova.between_topic_variance,
+                        anova.residual_variance,
+                    ],
+                ),
+                TableColumn::Real(
+                    "F-stat".to_string(),
+                    vec![
+                        anova.between_system_f_stat,
+                        anova.between_topic_f_stat,
+                        f64::NAN,
+                    ],
+                ),
+                TableColumn::Real(
+                    "p-value".to_string(),
+                    vec![
+                        anova.between_system_p_value,
+                        anova.between_topic_p_value,
+                        f64::NAN,
+                    ],
+                ),
+            ];
+            print_table(&columns, print_options);
         }
 
-        println!("## System means");
-        let anove_stat =
-            TwoWayAnovaWithoutReplication::from_tupled_samples(tupled_scores.iter(), dfs.len())?;
-        let system_means = anove_stat.system_means();
-        let moe95 = anove_stat.margin_of_error(0.05)?;
-        let columns = vec![
-            Series::new(
-                "System".into(),
-                (1..=dfs.len())
-                    .map(|i| format!("System_{i}"))
-                    .collect::<Vec<_>>(),
-            ),
-            Series::new("Mean".into(), system_means.to_vec()),
-            Series::new("95% MOE".into(), vec![moe95; dfs.len()]),
-        ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
+        if let Some(effect_sizes) = &comparison.tukey_hsd_effect_sizes {
+            print_heading("## Effect sizes for Tukey HSD test", print_options);
+            print_system_matrix("ES", effect_sizes, TableColumn::Real, print_options);
+        }
 
-        println!("## Two-way ANOVA without replication");
-        let columns = vec![
-            Series::new(
-                "Factor".into(),
-                vec!["Between-systems", "Between-topics", "Residual"],
-            ),
-            Series::new(
-                "Variation ".into(),
-                vec![
-                    anove_stat.between_system_variation(),
-                    anove_stat.between_topic_variation(),
-                    anove_stat.residual_variation(),
-                ],
-            ),
-            Series::new(
-                "DF".into(),
-                vec![
-                    anove_stat.n_systems() as u64 - 1,
-                    anove_stat.n_topics() as u64 - 1,
-                    (anove_stat.n_systems() as u64 - 1) * (anove_stat.n_topics() as u64 - 1),
-                ],
-            ),
-            Series::new(
-                "Variance".into(),
-                vec![
-                    anove_stat.between_system_variance(),
-                    anove_stat.between_topic_variance(),
-                    anove_stat.residual_variance(),
-                ],
-            ),
-            Series::new(
-                "F-stat".into(),
-                vec![
-                    anove_stat.between_system_f_stat(),
-                    anove_stat.between_topic_f_stat(),
-                    f64::NAN,
-                ],
-            ),
-            Series::new(
-                "p-value".into(),
-                vec![
-                    anove_stat.between_system_p_value(),
-                    anove_stat.between_topic_p_value(),
-                    f64::NAN,
-                ],
-            ),
-        ];
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
-
-        println!("## Effect sizes for Tukey HSD test");
-        let hsd_stat = TukeyHsdTest::from_tupled_samples(tupled_scores.iter(), dfs.len())?;
-        let effect_sizes = hsd_stat.effect_sizes();
-        let mut columns = vec![Series::new(
-            "ES".into(),
-            (1..=dfs.len())
-                .map(|i| format!("System_{}", i))
-                .collect::<Vec<_>>(),
-        )];
-        for i in 1..=dfs.len() {
-            let values = (1..=dfs.len())
-                .map(|j| effect_sizes[j - 1][i - 1])
-                .collect::<Vec<_>>();
-            columns.push(Series::new(format!("System_{}", i).into(), values));
+        if let Some(labels) = &comparison.tukey_hsd_effect_size_labels {
+            print_heading("## Effect size labels for Tukey HSD test", print_options);
+            let labels = labels
+                .iter()
+                .map(|row| row.iter().map(|label| label.to_string()).collect())
+                .collect::<Vec<Vec<_>>>();
+            print_system_matrix("ES label", &labels, TableColumn::Text, print_options);
         }
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
-
-        println!("## p-values for randomized Tukey HSD test (n_iters = {n_iters})");
-        let rthsd_stat = rthsd_tester.test(tupled_scores)?;
-        let p_values = rthsd_stat.p_values();
-        let mut columns = vec![Series::new(
-            "p-value".into(),
-            (1..=dfs.len())
-                .map(|i| format!("System_{}", i))
-                .collect::<Vec<_>>(),
-        )];
-        for i in 1..=dfs.len() {
-            let values = (1..=dfs.len())
-                .map(|j| p_values[j - 1][i - 1])
-                .collect::<Vec<_>>();
-            columns.push(Series::new(format!("System_{}", i).into(), values));
+
+        if let Some(p_values) = &comparison.randomized_tukey_hsd_p_values {
+            print_heading(
+                &format!(
+                    "## p-values for randomized Tukey HSD test (n_iters = {})",
+                    compare_options.n_iters()
+                ),
+                print_options,
+            );
+            print_system_matrix("p-value", p_values, TableColumn::Real, print_options);
         }
-        let df = DataFrame::new(columns)?;
-        print_dataframe(&df, print_mode);
     }
 
     Ok(())
 }
 
-fn print_dataframe(df: &DataFrame, print_mode: PrintMode) {
-    match print_mode {
+/// Prints the system-by-system matrix, where the column of System_i has the i-th column of the matrix.
+fn print_system_matrix<T, F>(
+    header: &str,
+    matrix: &[Vec<T>],
+    make_column: F,
+    print_options: &PrintOptions,
+) where
+    T: Clone,
+    F: Fn(String, Vec<T>) -> TableColumn,
+{
+    let n_systems = matrix.len();
+    let mut columns = vec![TableColumn::Text(
+        header.to_string(),
+        (1..=n_systems)
+            .map(|i| format!("System_{}", i))
+            .collect::<Vec<_>>(),
+    )];
+    for i in 1..=n_systems {
+        let values = matrix
+            .iter()
+            .map(|row| row[i - 1].clone())
+            .collect::<Vec<_>>();
+        columns.push(make_column(format!("System_{}", i), values));
+    }
+    print_table(&columns, print_options);
+}
+
+fn print_table(columns: &[TableColumn], print_options: &PrintOptions) {
+    let mut cells = columns_to_cells(columns, print_options.precision);
+    if !print_options.columns.is_empty() {
+        cells = select_columns(cells, &print_options.columns);
+    }
+    if print_options.transpose {
+        cells = transpose_cells(cells);
+    }
+    match print_options.mode {
         PrintMode::Pretty => {
-            df_to_prettytable(df).printstd();
+            cells_to_prettytable(&cells).printstd();
         }
         PrintMode::Raw => {
-            print_df_in_tsv(df);
+            for row in &cells {
+                println!("{}", row.join("\t"));
+            }
+        }
+        PrintMode::Markdown => {
+            print!("{}", cells_to_markdown(&cells));
+        }
+        PrintMode::Latex => {
+            print!("{}", cells_to_latex(&cells));
         }
     }
 }
 
-fn df_to_prettytable(df: &DataFrame) -> prettytable::Table {
-    let columns = df.get_columns();
-    let mut table = prettytable::Table::new();
-    table.set_titles(prettytable::Row::new(
-        columns
+/// Prints the heading of a table, which is a comment in the latex mode.
+fn print_heading(heading: &str, print_options: &PrintOptions) {
+    match print_options.mode {
+        PrintMode::Latex => {
+            let (newline, title) = heading
+                .strip_prefix('\n')
+                .map_or(("", heading), |title| ("\n", title));
+            println!("{newline}% {}", title.trim_start_matches('#').trim_start());
+        }
+        _ => println!("{heading}"),
+    }
+}
+
+/// Renders the cells as a GitHub Markdown table, where the first row is the header.
+fn cells_to_markdown(cells: &[Vec<String>]) -> String {
+    let escape = |cell: &String| cell.replace('|', "\\|");
+    let mut table = String::new();
+    for (i, row) in cells.iter().enumerate() {
+        let row = row.iter().map(escape).collect::<Vec<_>>();
+        table.push_str(&format!("| {} |\n", row.join(" | ")));
+        if i == 0 {
+            let rules = vec!["---"; row.len()];
+            table.push_str(&format!("| {} |\n", rules.join(" | ")));
+        }
+    }
+    table
+}
+
+/// Renders the cells as a LaTeX table with the booktabs package, where the first row is the header.
+///
+/// The first column is left-aligned, and the others are right-aligned.
+fn cells_to_latex(cells: &[Vec<String>]) -> String {
+    let n_columns = cells.first().map_or(0, Vec::len);
+    let alignment = (0..n_columns)
+        .map(|j| if j == 0 { 'l' } else { 'r' })
+        .collect::<String>();
+    let mut table = format!("\\begin{{tabular}}{{{alignment}}}\n\\toprule\n");
+    for (i, row) in cells.iter().enumerate() {
+        let row = row
             .iter()
-            .map(|s| s.name().as_str())
-            .map(prettytable::Cell::new)
-            .collect(),
-    ));
-    for i in 0..df.height() {
-        let mut row = vec![];
-        for column in columns.iter() {
-            let value = column.get(i).unwrap();
-            match value {
-                AnyValue::String(value) => {
-                    row.push(prettytable::Cell::new(value));
-                }
-                AnyValue::Float64(value) => {
-                    if value.is_nan() {
-                        row.push(prettytable::Cell::new(""));
-                    } else {
-                        row.push(prettytable::Cell::new(&format!("{value:.4}")));
-                    }
-                }
-                AnyValue::UInt64(value) => {
-                    row.push(prettytable::Cell::new(&format!("{value}")));
-                }
-                _ => {
-                    row.push(prettytable::Cell::new("N/A"));
-                }
-            }
+            .map(|cell| escape_latex(cell))
+            .collect::<Vec<_>>();
+        table.push_str(&format!("{} \\\\\n", row.join(" & ")));
+        if i == 0 {
+            table.push_str("\\midrule\n");
         }
-        table.add_row(prettytable::Row::new(row));
     }
-    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.push_str("\\bottomrule\n\\end{tabular}\n");
     table
 }
 
-fn print_df_in_tsv(df: &DataFrame) {
-    let columns = df.get_columns();
-    let header = columns
-        .iter()
-        .map(|s| s.name().as_str())
-        .collect::<Vec<_>>()
-        .join("\t");
-    println!("{}", header);
-    for i in 0..df.height() {
+fn escape_latex(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Converts the columns into the cells of a table, where the first row is the header.
+///
+/// NaN values are printed as empty cells.
+fn columns_to_cells(columns: &[TableColumn], precision: usize) -> Vec<Vec<String>> {
+    let mut cells = vec![columns.iter().map(|c| c.name().to_string()).collect()];
+    let n_rows = columns.first().map_or(0, TableColumn::len);
+    for i in 0..n_rows {
         let row = columns
             .iter()
-            .map(|column| {
-                let value = column.get(i).unwrap();
-                let value = match value {
-                    AnyValue::String(value) => value,
-                    AnyValue::Float64(value) => {
-                        if value.is_nan() {
-                            ""
-                        } else {
-                            &format!("{value:.4}")
-                        }
-                    }
-                    AnyValue::UInt64(value) => &format!("{value}"),
-                    _ => "N/A",
-                };
-                value.to_string()
+            .map(|column| match column {
+                TableColumn::Real(_, values) if values[i].is_nan() => String::new(),
+                _ => column.format(i, Some(precision)),
             })
-            .collect::<Vec<_>>()
-            .join("\t");
-        println!("{}", row);
+            .collect();
+        cells.push(row);
+    }
+    cells
+}
+
+/// Keeps the first column and the columns matching any of the keys.
+///
+/// A key matches a column if it equals the lowercased column name,
+/// its part before a hyphen (e.g., `p` for `p-value`),
+/// or its last word (e.g., `moe` for `95% MOE`).
+/// If no column matches, the cells are returned as they are.
+fn select_columns(cells: Vec<Vec<String>>, keys: &[String]) -> Vec<Vec<String>> {
+    let matches = |name: &str| {
+        let name = name.to_lowercase();
+        let prefix = name.split('-').next().unwrap_or_default();
+        let suffix = name.split(' ').next_back().unwrap_or_default();
+        keys.iter()
+            .any(|key| key == &name || key == prefix || key == suffix)
+    };
+    let selected = cells[0]
+        .iter()
+        .enumerate()
+        .filter(|&(j, name)| j == 0 || matches(name))
+        .map(|(j, _)| j)
+        .collect::<Vec<_>>();
+    if selected.len() == 1 {
+        return cells;
+    }
+    cells
+        .into_iter()
+        .map(|row| selected.iter().map(|&j| row[j].clone()).collect())
+        .collect()
+}
+
+fn transpose_cells(cells: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let n_columns = cells.first().map_or(0, |row| row.len());
+    (0..n_columns)
+        .map(|j| cells.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
+
+fn cells_to_prettytable(cells: &[Vec<String>]) -> prettytable::Table {
+    let to_row = |row: &[String]| {
+        prettytable::Row::new(row.iter().map(|c| prettytable::Cell::new(c)).collect())
+    };
+    let mut table = prettytable::Table::new();
+    table.set_titles(to_row(&cells[0]));
+    for row in &cells[1..] {
+        table.add_row(to_row(row));
+    }
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!(
+            "../schemas/compare-output.v{JSON_SCHEMA_VERSION}.json"
+        ));
+        let schema = elinor::json_schema::schema_for::<JsonDocument>();
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            current == generated,
+            "{} is out of date; run the tests with UPDATE_SCHEMAS=1 to regenerate it.",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_topic_differences() {
+        let matrix_1 =
+            ScoreMatrix::from_csv_str("q,a,b\nq_1,0.5,0.5\nq_2,1.0,0.0\n", b',').unwrap();
+        let matrix_2 =
+            ScoreMatrix::from_csv_str("q,a,b\nq_1,0.0,0.0\nq_2,0.5,0.5\n", b',').unwrap();
+        let metrics = vec!["a".to_string(), "b".to_string()];
+        let (topics, n_incomplete) = topic_differences(&matrix_1, &matrix_2, &metrics).unwrap();
+        assert_eq!(
+            topics,
+            vec![
+                ("q_1".to_string(), vec![0.5, 0.5]),
+                ("q_2".to_string(), vec![0.5, -0.5]),
+            ]
+        );
+        assert_eq!(n_incomplete, 0);
+    }
+
+    #[test]
+    fn test_topic_differences_incomplete_topics() {
+        let matrix_1 =
+            ScoreMatrix::from_csv_str("q,a,b\nq_1,0.0,0.0\nq_2,1.0,\nq_3,1.0,1.0\n", b',').unwrap();
+        let matrix_2 =
+            ScoreMatrix::from_csv_str("q,a,b\nq_1,0.5,0.5\nq_2,0.0,0.0\nq_4,0.0,0.0\n", b',')
+                .unwrap();
+        let metrics = vec!["a".to_string(), "b".to_string()];
+        let (topics, n_incomplete) = topic_differences(&matrix_1, &matrix_2, &metrics).unwrap();
+        assert_eq!(topics, vec![("q_1".to_string(), vec![-0.5, -0.5])]);
+        assert_eq!(n_incomplete, 3);
     }
 }
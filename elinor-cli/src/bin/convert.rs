@@ -1,12 +1,18 @@
-use std::fs::File;
-use std::io::BufWriter;
 use std::io::Write;
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::str::FromStr;
 
 use anyhow::Result;
 use clap::Parser;
+use elinor::id_normalization::IdNormalization;
+use elinor::id_normalization::IdNormalizer;
+use elinor::relevance::NonFiniteScorePolicy;
+use elinor::relevance::ScoreQuantization;
 use elinor::trec;
+use elinor::trec::ColumnMapping;
+use elinor::trec::TrecRunParser;
+use elinor_cli::Warnings;
 
 #[derive(Clone, Debug)]
 enum RelevanceType {
@@ -29,28 +35,98 @@ impl FromStr for RelevanceType {
 #[derive(Parser, Debug)]
 #[command(version, about = "Convert TREC format to JSONL format.")]
 struct Args {
-    /// Path to the input TREC file.
+    /// Path to the input TREC file, or '-' for the standard input.
     #[arg(short, long)]
     input_trec: PathBuf,
 
-    /// Path to the output JSONL file.
+    /// Path to the output JSONL file, or '-' for the standard output.
     #[arg(short, long)]
     output_jsonl: PathBuf,
 
     /// Relevance type from 'true' or 'pred'.
     #[arg(short, long)]
     rel_type: RelevanceType,
+
+    /// Policy for non-finite (NaN or infinite) predicted scores (reject, drop, or minimum).
+    #[arg(long, default_value = "reject")]
+    non_finite_policy: NonFiniteScorePolicy,
+
+    /// Step to round predicted scores to at ingestion (e.g., 1e-6),
+    /// so that tiny differences across platforms do not change tie-breaking.
+    #[arg(long)]
+    score_quantization: Option<ScoreQuantization>,
+
+    /// Path to the output TSV file of the original score strings, for auditing precision loss.
+    /// The ids in this file are not normalized.
+    #[arg(long)]
+    score_audit: Option<PathBuf>,
+
+    /// Mapping of columns for non-standard files in the form of <name>:<index>,...
+    /// from query_id, doc_id, score, and rank with zero-based indices (e.g., query_id:1,doc_id:0,score:2).
+    /// If not specified, the standard columns of the TREC format are used.
+    #[arg(long)]
+    columns: Option<ColumnMapping>,
+
+    /// Delimiter of columns, such as ',' (whitespace if not specified).
+    #[arg(long)]
+    delimiter: Option<char>,
+
+    /// Normalization steps of query ids, in the form of <step>,...
+    /// from lowercase, strip_fragment, or md5 (e.g., lowercase,md5).
+    #[arg(long, value_delimiter = ',')]
+    query_id_normalization: Vec<IdNormalization>,
+
+    /// Normalization steps of document ids, in the form of <step>,...
+    /// from lowercase, strip_fragment, or md5 (e.g., strip_fragment,lowercase).
+    #[arg(long, value_delimiter = ',')]
+    doc_id_normalization: Vec<IdNormalization>,
+
+    /// Overwrite existing output files.
+    #[arg(long)]
+    force: bool,
+
+    /// Treat warnings (e.g., non-finite or tied predicted scores) as errors.
+    #[arg(long)]
+    strict: bool,
+}
+
+fn main() -> ExitCode {
+    let args: Args = elinor_cli::parse_args();
+    let mut warnings = Warnings::new(args.strict);
+    let result = run(args, &mut warnings).map(|()| warnings.outcome());
+    elinor_cli::exit_code(result)
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn run(args: Args, warnings: &mut Warnings) -> Result<()> {
+    let output_paths = std::iter::once(&args.output_jsonl).chain(&args.score_audit);
+    elinor_cli::check_outputs(output_paths.map(PathBuf::as_path), args.force)?;
 
     let lines = elinor_cli::load_lines(&args.input_trec)?;
-    let mut writer = BufWriter::new(File::create(&args.output_jsonl)?);
+    let mut writer = elinor_cli::create_output(&args.output_jsonl)?;
+    let normalizer = IdNormalizer::new()
+        .with_query_steps(args.query_id_normalization.clone())
+        .with_doc_steps(args.doc_id_normalization.clone());
 
     match args.rel_type {
         RelevanceType::True => {
-            let true_rels = trec::parse_true_rels_in_trec(lines)?;
+            let true_rels = if args.columns.is_some() || args.delimiter.is_some() {
+                let columns = args
+                    .columns
+                    .unwrap_or_else(ColumnMapping::qrels)
+                    .with_delimiter(args.delimiter);
+                trec::parse_true_rels_with_columns(lines, columns)?
+            } else {
+                #[cfg(feature = "parallel")]
+                let true_rels = trec::parse_true_rels_in_trec_parallel(&lines)?;
+                #[cfg(not(feature = "parallel"))]
+                let true_rels = trec::parse_true_rels_in_trec(lines)?;
+                true_rels
+            };
+            let true_rels = if normalizer.is_identity() {
+                true_rels
+            } else {
+                normalizer.apply(&true_rels)?
+            };
             let true_records = true_rels.into_records();
             for record in true_records {
                 serde_json::to_writer(&mut writer, &record)?;
@@ -58,7 +134,46 @@ fn main() -> Result<()> {
             }
         }
         RelevanceType::Pred => {
-            let pred_rels = trec::parse_pred_rels_in_trec(lines)?;
+            let mut parser = TrecRunParser::new()
+                .with_non_finite_policy(args.non_finite_policy)
+                .with_keep_score_strings(args.score_audit.is_some())
+                .with_detect_collapsed_ties(true);
+            if let Some(quantization) = args.score_quantization {
+                parser = parser.with_quantization(quantization);
+            }
+            if args.columns.is_some() || args.delimiter.is_some() {
+                let columns = args
+                    .columns
+                    .unwrap_or_else(ColumnMapping::run)
+                    .with_delimiter(args.delimiter);
+                parser = parser.with_columns(columns);
+            }
+            let run = parser.parse(lines)?;
+            let n_non_finite = run.n_non_finite();
+            if n_non_finite > 0 {
+                warnings.warn(format!(
+                    "{n_non_finite} non-finite predicted scores were handled by the policy."
+                ))?;
+            }
+            let n_collapsed_ties = run.collapsed_ties().len();
+            if n_collapsed_ties > 0 {
+                warnings.warn(format!(
+                    "{n_collapsed_ties} ties were created by the precision loss of predicted scores."
+                ))?;
+            }
+            if let (Some(path), Some(score_strings)) = (&args.score_audit, run.score_strings()) {
+                let mut audit_writer = elinor_cli::create_output(path)?;
+                for ((query_id, doc_id), score) in score_strings {
+                    writeln!(audit_writer, "{query_id}\t{doc_id}\t{score}")?;
+                }
+                audit_writer.flush()?;
+            }
+            let pred_rels = run.into_pred_rels();
+            let pred_rels = if normalizer.is_identity() {
+                pred_rels
+            } else {
+                normalizer.apply(&pred_rels)?
+            };
             let pred_records = pred_rels.into_records();
             for record in pred_records {
                 serde_json::to_writer(&mut writer, &record)?;
@@ -66,5 +181,6 @@ fn main() -> Result<()> {
             }
         }
     }
+    writer.flush()?;
     Ok(())
 }
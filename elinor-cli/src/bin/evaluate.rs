@@ -1,90 +1,1099 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use anyhow::Result;
 use clap::Parser;
+use elinor::id_normalization::IdNormalization;
+use elinor::id_normalization::IdNormalizer;
+use elinor::metric_correlation::compute_metric_correlations;
+use elinor::metric_correlation::CorrelationMethod;
+use elinor::metrics::DcgConvention;
+use elinor::metrics::DcgDiscount;
+use elinor::metrics::DcgOptions;
+use elinor::metrics::DcgWeighting;
+use elinor::metrics::GainMapping;
+use elinor::metrics::IdealCutoff;
+use elinor::metrics::MetricOptions;
+use elinor::metrics::ShortRankingPolicy;
+use elinor::multi_collection::MultiCollectionEvaluation;
+use elinor::relevance::NonFiniteScorePolicy;
+use elinor::relevance::ScoreQuantization;
+use elinor::statistical_tests::tuples_from_common_keys;
+use elinor::statistical_tests::StudentTTest;
+use elinor::timing::TimingReport;
+use elinor::trec;
+use elinor::trec::TrecRunParser;
+use elinor::BootstrapEvaluation;
+use elinor::Evaluation;
 use elinor::Metric;
-use elinor::PredRecord;
 use elinor::PredRelStore;
-use elinor::TrueRecord;
+use elinor::PredRelStoreBuilder;
 use elinor::TrueRelStore;
-use polars::prelude::*;
+use elinor::TrueScore;
+use elinor_cli::CacheKey;
+use elinor_cli::HistoryMetric;
+use elinor_cli::HistoryRecord;
+use elinor_cli::ScoreCache;
+use elinor_cli::ScoreMatrix;
+use elinor_cli::TableColumn;
+use elinor_cli::TableLayout;
+use elinor_cli::Warnings;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    Jsonl,
+    Trec,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::Jsonl),
+            "trec" => Ok(Self::Trec),
+            _ => Err(format!("Invalid input format: {}", s)),
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Evaluate the performance of a ranking model.")]
 struct Args {
-    /// Path to the input JSONL file for true relevance.
-    #[arg(short, long)]
-    true_jsonl: PathBuf,
+    /// Path to the input JSONL file for true relevance, or '-' for the standard input.
+    /// A TREC qrels file can be given with --true-format trec.
+    #[arg(short, long, required_unless_present = "manifest")]
+    true_jsonl: Option<PathBuf>,
 
-    /// Path to the input JSONL file for predicted relevance.
-    #[arg(short, long)]
-    pred_jsonl: PathBuf,
+    /// Path to the input JSONL file for predicted relevance, or '-' for the standard input.
+    /// A TREC run file can be given with --pred-format trec.
+    #[arg(short, long, required_unless_present_any = ["manifest", "pred_parquet"])]
+    pred_jsonl: Option<PathBuf>,
+
+    /// Path to the input Parquet file for predicted relevance with the columns of query_id, doc_id, and score,
+    /// or '-' for the standard input, instead of --pred-jsonl. It requires the parquet feature.
+    #[arg(long, conflicts_with_all = ["pred_jsonl", "pred_format", "manifest"])]
+    pred_parquet: Option<PathBuf>,
+
+    /// Format of the input file for true relevance (jsonl or trec).
+    #[arg(long, default_value = "jsonl")]
+    true_format: InputFormat,
+
+    /// Format of the input file for predicted relevance (jsonl or trec).
+    #[arg(long, default_value = "jsonl")]
+    pred_format: InputFormat,
+
+    /// Path to the TSV manifest of '<name>\t<true_jsonl>\t<pred_jsonl>' lines to evaluate
+    /// the same system over multiple collections, where relative paths are resolved
+    /// from the directory of the manifest.
+    /// The per-collection means are printed with their macro average and between-collection standard deviation.
+    #[arg(long, conflicts_with_all = ["true_jsonl", "pred_jsonl", "rank_positions_jsonl", "unjudged_jsonl", "correlation_csv", "rel_levels", "bootstrap_resamples"])]
+    manifest: Option<PathBuf>,
 
     /// Path to the output CSV file.
+    /// The output files can be '-' for the standard output,
+    /// in which case the scores are printed to the standard error instead.
     #[arg(short, long)]
     output_csv: Option<PathBuf>,
 
+    /// Path to the output JSONL file for rank positions of relevant documents.
+    #[arg(long)]
+    rank_positions_jsonl: Option<PathBuf>,
+
+    /// Path to the output JSONL file for the per-query numbers of unjudged documents in the top-k,
+    /// where k is specified by --unjudged-k.
+    #[arg(long)]
+    unjudged_jsonl: Option<PathBuf>,
+
+    /// Number of top documents to count unjudged ones in --unjudged-jsonl (0 for all).
+    #[arg(long, default_value = "10")]
+    unjudged_k: usize,
+
+    /// Path to the output CSV file of the correlation matrix between the metrics across queries.
+    #[arg(long)]
+    correlation_csv: Option<PathBuf>,
+
+    /// Correlation coefficient in --correlation-csv (pearson or spearman).
+    #[arg(long, default_value = "pearson")]
+    correlation_method: CorrelationMethod,
+
+    /// Number of decimal places of real numbers in the output.
+    /// The printed scores use 4 if not specified, and the output CSV uses the full precision.
+    #[arg(long)]
+    precision: Option<usize>,
+
     /// Use tab separator instead of comma in the output CSV.
     #[arg(long)]
     tab_separator: bool,
 
+    /// Layout of the output CSV file specified by --output-csv (wide or long).
+    /// The long layout has the query_id, metric, and score columns.
+    #[arg(long, default_value = "wide")]
+    csv_layout: TableLayout,
+
     /// Metric to evaluate. If not specified, some default metrics are used.
     #[arg(short, long, num_args = 1..)]
     metrics: Vec<Metric>,
-}
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    /// Policy for non-finite (NaN or infinite) predicted scores (reject, drop, or minimum).
+    #[arg(long, default_value = "reject")]
+    non_finite_policy: NonFiniteScorePolicy,
 
-    let true_lines = elinor_cli::load_lines(&args.true_jsonl)?;
-    let true_records = true_lines
-        .into_iter()
-        .map(|line| serde_json::from_str::<TrueRecord<String>>(&line).unwrap());
-    let true_rels = TrueRelStore::from_records(true_records)?;
+    /// Step to round predicted scores to at ingestion (e.g., 1e-6),
+    /// so that tiny differences across platforms do not change tie-breaking.
+    #[arg(long)]
+    score_quantization: Option<ScoreQuantization>,
+
+    /// Normalization steps of query ids in both true and predicted relevance,
+    /// in the form of <step>,... from lowercase, strip_fragment, or md5 (e.g., lowercase,md5).
+    #[arg(long, value_delimiter = ',')]
+    query_id_normalization: Vec<IdNormalization>,
+
+    /// Normalization steps of document ids in both true and predicted relevance,
+    /// in the form of <step>,... from lowercase, strip_fragment, or md5 (e.g., strip_fragment,lowercase).
+    #[arg(long, value_delimiter = ',')]
+    doc_id_normalization: Vec<IdNormalization>,
+
+    /// Path to the file of query ids to evaluate, one per line.
+    /// Queries not in the file are removed from both true and predicted relevance.
+    #[arg(long)]
+    query_ids_file: Option<PathBuf>,
+
+    /// Fail if the kind of true relevance scores (binary or graded) mismatches that assumed by a metric.
+    #[arg(long)]
+    strict_relevance_kind: bool,
+
+    /// Custom gains for dcg and ndcg in the form of <grade>:<gain>,...
+    /// (e.g., 0:0,1:1,2:3,3:7), where unmapped grades have zero gains.
+    /// If not specified, the grades are used as gains.
+    #[arg(long)]
+    gains: Option<GainMapping>,
+
+    /// Discount of gains in the dcg family, standard (log2(i+1) as in trec_eval)
+    /// or original (max(1, log2(i)) without discounting the top ranks).
+    #[arg(long, default_value = "standard")]
+    dcg_discount: DcgDiscount,
+
+    /// Cutoff of the ideal ranking in the ndcg family, k (at the cutoff as in trec_eval)
+    /// or all (all the judged documents).
+    #[arg(long, default_value = "k")]
+    ideal_cutoff: IdealCutoff,
+
+    /// Log base of the discount in the dcg family (e.g., 10 for log10 discounts),
+    /// which must be greater than 1. It does not change the ndcg family with the standard discount.
+    #[arg(long, default_value = "2")]
+    dcg_log_base: f64,
+
+    /// Policy for queries retrieving fewer than k documents, pad (fixing the denominators of
+    /// precision, f1, and judged to k), normalize (dividing them by the number of retrieved documents),
+    /// or reject (failing for any metric with k).
+    #[arg(long, default_value = "pad")]
+    short_ranking_policy: ShortRankingPolicy,
+
+    /// Relevance levels, i.e., the minimum true relevance scores regarded as relevant,
+    /// in the form of <level>,... (e.g., 1,2).
+    /// If specified, each metric is evaluated for each level as a column named <metric>[l=<level>].
+    #[arg(long, value_delimiter = ',')]
+    rel_levels: Vec<TrueScore>,
+
+    /// Number of bootstrap resamples of queries to print 95% confidence intervals of the means.
+    #[arg(long)]
+    bootstrap_resamples: Option<usize>,
+
+    /// Path to the CSV file of the per-query scores of a baseline, written by --output-csv
+    /// with the same --tab-separator and --csv-layout options.
+    /// Each printed mean is followed by the difference from the baseline over the common queries
+    /// and the p-value of a two-sided paired Student's t-test.
+    #[arg(long, conflicts_with_all = ["manifest", "per_query"])]
+    baseline_csv: Option<PathBuf>,
+
+    /// Significance level to mark significant differences from the baseline with '*'.
+    #[arg(long, default_value = "0.05", requires = "baseline_csv")]
+    baseline_alpha: f64,
+
+    /// Path to the history file of evaluation runs, to which the means and 95% confidence intervals
+    /// of the metrics are appended as a JSONL record. The file is created if missing.
+    /// Use elinor-trend to print the history of a metric.
+    #[arg(long, conflicts_with = "manifest")]
+    history_jsonl: Option<PathBuf>,
+
+    /// Name of the run recorded in --history-jsonl (e.g., a commit hash).
+    /// If not specified, the file name of --pred-jsonl without extensions is used.
+    #[arg(long, requires = "history_jsonl")]
+    run_name: Option<String>,
+
+    /// Also print the geometric means of the scores as gm_<metric> (e.g., gm_ap, i.e., GMAP).
+    #[arg(long)]
+    geometric_mean: bool,
+
+    /// Do not use the on-disk cache of per-query scores.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory of the on-disk cache of per-query scores.
+    /// If not specified, `$XDG_CACHE_HOME/elinor` or `$HOME/.cache/elinor` is used.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Random state for the bootstrap resampling.
+    #[arg(long, default_value = "0")]
+    random_state: u64,
+
+    /// Print the per-query scores as '<measure>\t<query_id>\t<score>' lines followed by the 'all' lines of the means,
+    /// in the same format as `trec_eval -q`, instead of the basic statistics and means.
+    /// The metrics supported by trec_eval are named as in trec_eval (e.g., map for ap and ndcg_cut_10 for ndcg@10).
+    #[arg(long, conflicts_with_all = ["manifest", "bootstrap_resamples", "geometric_mean"])]
+    per_query: bool,
+
+    /// Print the wall-clock time of building the stores and evaluating each metric
+    /// to the standard error, in seconds.
+    #[arg(long)]
+    timings: bool,
+
+    /// Overwrite existing output files.
+    #[arg(long)]
+    force: bool,
+
+    /// Treat warnings (e.g., non-finite predicted scores) as errors.
+    #[arg(long)]
+    strict: bool,
+}
 
-    let pred_lines = elinor_cli::load_lines(&args.pred_jsonl)?;
-    let pred_records = pred_lines
+impl Args {
+    fn output_paths(&self) -> impl Iterator<Item = &Path> {
+        [
+            &self.output_csv,
+            &self.rank_positions_jsonl,
+            &self.unjudged_jsonl,
+            &self.correlation_csv,
+        ]
         .into_iter()
-        .map(|line| serde_json::from_str::<PredRecord<String>>(&line).unwrap());
-    let pred_rels = PredRelStore::from_records(pred_records)?;
+        .flatten()
+        .map(PathBuf::as_path)
+    }
 
-    let metrics = if args.metrics.is_empty() {
-        default_metrics()
-    } else {
-        args.metrics
+    /// Returns the writer of the printed scores,
+    /// which is the standard error if an output file is written to the standard output.
+    fn report_writer(&self) -> Box<dyn Write> {
+        if self.output_paths().any(elinor_cli::is_stdio) {
+            Box::new(std::io::stderr())
+        } else {
+            Box::new(std::io::stdout())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Args = elinor_cli::parse_args();
+    let mut warnings = Warnings::new(args.strict);
+    let result = run(args, &mut warnings).map(|()| warnings.outcome());
+    elinor_cli::exit_code(result)
+}
+
+fn run(args: Args, warnings: &mut Warnings) -> Result<()> {
+    elinor_cli::check_outputs(args.output_paths(), args.force)?;
+    let query_ids = load_query_ids(&args)?;
+    let baseline = load_baseline(&args)?;
+    if args
+        .history_jsonl
+        .as_deref()
+        .map_or(false, elinor_cli::is_stdio)
+    {
+        return Err(elinor_cli::input_error(
+            "The history file cannot be the standard output.",
+        ));
+    }
+    if let Some(manifest) = &args.manifest {
+        return evaluate_manifest(&args, manifest, query_ids.as_ref(), warnings);
+    }
+    elinor_cli::check_inputs(
+        [&args.true_jsonl, &args.pred_jsonl, &args.pred_parquet]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::as_path),
+    )?;
+
+    let true_lines = elinor_cli::load_lines(args.true_jsonl.as_ref().unwrap())?;
+    let pred_lines = match &args.pred_jsonl {
+        Some(pred_jsonl) => elinor_cli::load_lines(pred_jsonl)?,
+        None => vec![],
+    };
+    let pred_parquet = args
+        .pred_parquet
+        .as_ref()
+        .map(elinor_cli::read_bytes)
+        .transpose()?;
+    let cache = (!args.no_cache).then(|| {
+        ScoreCache::new(
+            args.cache_dir
+                .clone()
+                .unwrap_or_else(ScoreCache::default_dir),
+        )
+    });
+    let mut input_key = CacheKey::new();
+    input_key
+        .push_lines(&true_lines)
+        .push_lines(&pred_lines)
+        .push(&[u8::from(pred_parquet.is_some())])
+        .push(pred_parquet.as_deref().unwrap_or_default())
+        .push(&format!("{:?}", (args.true_format, args.pred_format)))
+        .push(&format!("{:?}", args.non_finite_policy))
+        .push(&format!("{:?}", args.query_id_normalization))
+        .push(&format!("{:?}", args.doc_id_normalization))
+        .push(&format!(
+            "{:?}",
+            args.score_quantization.map(|q| q.step().to_bits())
+        ))
+        .push(&format!(
+            "{:?}",
+            query_ids
+                .as_ref()
+                .map(|ids| ids.iter().collect::<BTreeSet<_>>())
+        ));
+
+    let normalizer = id_normalizer(&args);
+    let mut timings = TimingReport::new();
+    let true_rels = timings.time("build_true_rels", || {
+        build_true_rels(&true_lines, args.true_format, &normalizer)
+    })?;
+    let (pred_rels, n_non_finite_scores) = timings.time("build_pred_rels", || {
+        build_pred_rels(
+            &pred_lines,
+            pred_parquet.as_deref(),
+            args.pred_format,
+            args.non_finite_policy,
+            args.score_quantization,
+            &normalizer,
+        )
+    })?;
+    if n_non_finite_scores > 0 {
+        warnings.warn(format!(
+            "{n_non_finite_scores} non-finite predicted scores were handled by the policy."
+        ))?;
+    }
+    let (true_rels, pred_rels) = match &query_ids {
+        Some(query_ids) => restrict_queries(&true_rels, &pred_rels, query_ids, warnings, None)?,
+        None => (true_rels, pred_rels),
     };
 
-    println!("n_queries_in_true\t{}", true_rels.n_queries());
-    println!("n_queries_in_pred\t{}", pred_rels.n_queries());
-    println!("n_docs_in_true\t{}", true_rels.n_docs());
-    println!("n_docs_in_pred\t{}", pred_rels.n_docs());
-    println!("n_relevant_docs\t{}", n_relevant_docs(&true_rels));
+    let metrics = selected_metrics(&args);
+
+    let mut out = args.report_writer();
+    if !args.per_query {
+        writeln!(out, "n_queries_in_true\t{}", true_rels.n_queries())?;
+        writeln!(out, "n_queries_in_pred\t{}", pred_rels.n_queries())?;
+        writeln!(out, "n_docs_in_true\t{}", true_rels.n_docs())?;
+        writeln!(out, "n_docs_in_pred\t{}", pred_rels.n_docs())?;
+        writeln!(out, "n_relevant_docs\t{}", n_relevant_docs(&true_rels))?;
+        writeln!(out, "n_non_finite_scores\t{n_non_finite_scores}")?;
+    }
 
+    if !args.rel_levels.is_empty() && args.gains.is_some() {
+        return Err(elinor_cli::input_error(
+            "The --gains and --rel-levels options cannot be used together.",
+        ));
+    }
+    let convention = dcg_convention(&args)?;
+    if !args.rel_levels.is_empty() && convention != DcgConvention::new() {
+        return Err(elinor_cli::input_error(
+            "The --dcg-discount, --ideal-cutoff, and --dcg-log-base options cannot be used with --rel-levels.",
+        ));
+    }
+    let options = metric_options(&args, convention)?;
+    if args.strict_relevance_kind {
+        for &metric in &metrics {
+            elinor::metrics::validate_relevance_kind(&true_rels, metric)?;
+        }
+    }
+    let score_key = |metric: Metric| {
+        let gains = match metric {
+            Metric::DCG { .. } | Metric::NDCG { .. } => args.gains.as_ref(),
+            _ => None,
+        };
+        input_key
+            .clone()
+            .push(&metric.to_string())
+            .push(&format!("{:?}", gains.map(|g| g.to_string())))
+            .push(&format!("{convention:?}"))
+            .push(&args.short_ranking_policy.to_string())
+            .finish()
+    };
+
+    // The cached scores are loaded once, and the other metrics without the DCG options
+    // are evaluated together in a single sweep.
+    let mut cached_scores = BTreeMap::new();
+    let mut swept = BTreeMap::new();
+    if args.rel_levels.is_empty() {
+        if let Some(cache) = &cache {
+            for &metric in &metrics {
+                if let Some(scores) = cache.load(&score_key(metric)) {
+                    cached_scores.insert(metric, scores);
+                }
+            }
+        }
+        let sweep_metrics = metrics
+            .iter()
+            .copied()
+            .filter(|&metric| {
+                dcg_options(&args, metric, convention).is_none()
+                    && !cached_scores.contains_key(&metric)
+            })
+            .collect::<Vec<_>>();
+        if !sweep_metrics.is_empty() {
+            swept = timings.time("evaluate_all", || {
+                elinor_cli::evaluate_all(&true_rels, &pred_rels, &sweep_metrics, options)
+            })?;
+        }
+    }
+
+    let precision = args.precision.unwrap_or(4);
     let mut columns = vec![];
+    let mut named_scores = vec![];
+    let mut per_query_results = vec![];
+    let mut history_metrics = vec![];
+    for metric in metrics {
+        let start = Instant::now();
+        let mut is_swept = false;
+        let results = if args.rel_levels.is_empty() {
+            let key = score_key(metric);
+            let result = if let Some(scores) = cached_scores.remove(&metric) {
+                Evaluation::from_scores(metric, scores)
+            } else if let Some(result) = swept.remove(&metric) {
+                is_swept = true;
+                if let Some(cache) = &cache {
+                    cache.store(&key, result.scores());
+                }
+                result
+            } else {
+                let result = match dcg_options(&args, metric, convention) {
+                    Some(dcg_options) => elinor::evaluate_with_dcg_options(
+                        &true_rels,
+                        &pred_rels,
+                        metric,
+                        &dcg_options,
+                    )?,
+                    None if options != MetricOptions::new() => {
+                        let output = elinor::metrics::compute_metric_with_options(
+                            &true_rels, &pred_rels, metric, options,
+                        )?;
+                        Evaluation::from_scores(metric, output.into_scores())
+                    }
+                    None => elinor_cli::evaluate(&true_rels, &pred_rels, metric)?,
+                };
+                if let Some(cache) = &cache {
+                    cache.store(&key, result.scores());
+                }
+                result
+            };
+            vec![(format!("{metric:#}"), result)]
+        } else {
+            let keys = args
+                .rel_levels
+                .iter()
+                .map(|rel_lvl| {
+                    input_key
+                        .clone()
+                        .push(&metric.to_string())
+                        .push(&rel_lvl.to_string())
+                        .finish()
+                })
+                .collect::<Vec<_>>();
+            let cached = cache.as_ref().and_then(|cache| {
+                keys.iter()
+                    .map(|key| cache.load(key))
+                    .collect::<Option<Vec<_>>>()
+            });
+            let results = if let Some(cached) = cached {
+                cached
+                    .into_iter()
+                    .map(|scores| Evaluation::from_scores(metric, scores))
+                    .collect::<Vec<_>>()
+            } else {
+                let mut results = elinor::evaluate_at_rel_levels(
+                    &true_rels,
+                    &pred_rels,
+                    metric,
+                    &args.rel_levels,
+                )?;
+                let results = args
+                    .rel_levels
+                    .iter()
+                    .map(|rel_lvl| results.remove(rel_lvl).unwrap())
+                    .collect::<Vec<_>>();
+                if let Some(cache) = &cache {
+                    for (key, result) in keys.iter().zip(&results) {
+                        cache.store(key, result.scores());
+                    }
+                }
+                results
+            };
+            args.rel_levels
+                .iter()
+                .map(|rel_lvl| format!("{metric:#}[l={rel_lvl}]"))
+                .zip(results)
+                .collect()
+        };
+        if !is_swept {
+            timings.record(format!("{metric:#}"), start.elapsed());
+        }
+        for (name, result) in results {
+            let geometric_mean = args.geometric_mean.then(|| result.geometric_mean());
+            if args.history_jsonl.is_some() {
+                history_metrics.push(HistoryMetric::from_evaluation(name.clone(), &result));
+            }
+            let annotation = match &baseline {
+                Some(baseline) => {
+                    baseline_annotation(&args, baseline, &name, &result, precision, warnings)?
+                }
+                None => String::new(),
+            };
+            if args.correlation_csv.is_some() {
+                named_scores.push((
+                    name.clone(),
+                    Evaluation::from_scores(metric, result.scores().clone()),
+                ));
+            }
+            if let Some(n_resamples) = args.bootstrap_resamples {
+                let result = BootstrapEvaluation::new(result, n_resamples, args.random_state)?;
+                let (ci95_btm, ci95_top) = result.confidence_interval(0.05)?;
+                writeln!(
+                    out,
+                    "{}\t{:.precision$}\t[{:.precision$}, {:.precision$}]{}",
+                    name,
+                    result.evaluation().mean(),
+                    ci95_btm,
+                    ci95_top,
+                    annotation
+                )?;
+                push_columns(&mut columns, &name, result.evaluation());
+            } else if args.per_query {
+                let measure = if args.rel_levels.is_empty() {
+                    metric.trec_eval_name().unwrap_or_else(|| name.clone())
+                } else {
+                    name.clone()
+                };
+                push_columns(&mut columns, &name, &result);
+                per_query_results.push((measure, result));
+            } else {
+                writeln!(out, "{}\t{:.precision$}{}", name, result.mean(), annotation)?;
+                push_columns(&mut columns, &name, &result);
+            }
+            if let Some(geometric_mean) = geometric_mean {
+                writeln!(out, "gm_{name}\t{geometric_mean:.precision$}")?;
+            }
+        }
+    }
+
+    if args.per_query {
+        write_per_query(&mut out, &per_query_results, precision)?;
+    }
+    if args.timings {
+        eprintln!("{timings}");
+    }
+
+    if let Some(output_csv) = &args.output_csv {
+        write_csv(&args, output_csv, layout_columns(&args, columns)?)?;
+    }
+
+    if let Some(history_jsonl) = &args.history_jsonl {
+        let record = HistoryRecord {
+            run: run_name(&args),
+            timestamp: elinor_cli::format_utc_timestamp(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()),
+            ),
+            n_queries: true_rels.n_queries(),
+            metrics: history_metrics,
+        };
+        elinor_cli::append_history(history_jsonl, &record)?;
+    }
+
+    if let Some(correlation_csv) = &args.correlation_csv {
+        let matrix = compute_metric_correlations(
+            named_scores.iter().map(|(_, evaluation)| evaluation),
+            args.correlation_method,
+        )?;
+        let names = named_scores
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let mut columns = vec![TableColumn::Text("metric".to_string(), names.clone())];
+        for (name, row) in names.into_iter().zip(matrix) {
+            columns.push(TableColumn::Real(name, row));
+        }
+        write_csv(&args, correlation_csv, columns)?;
+    }
+
+    if let Some(rank_positions_jsonl) = args.rank_positions_jsonl {
+        let positions = elinor::rank_positions::extract_rank_positions(&true_rels, &pred_rels);
+        let mut file = elinor_cli::create_output(rank_positions_jsonl)?;
+        for position in positions {
+            writeln!(file, "{}", serde_json::to_string(&position)?)?;
+        }
+        file.flush()?;
+    }
+
+    if let Some(unjudged_jsonl) = args.unjudged_jsonl {
+        let counts =
+            elinor::metrics::compute_unjudged_counts(&true_rels, &pred_rels, args.unjudged_k)?;
+        let mut file = elinor_cli::create_output(unjudged_jsonl)?;
+        for (query_id, n_unjudged) in counts {
+            let line = serde_json::json!({
+                "query_id": query_id,
+                "n_unjudged": n_unjudged,
+            });
+            writeln!(file, "{line}")?;
+        }
+        file.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Evaluates the system over the collections listed in the manifest.
+fn evaluate_manifest(
+    args: &Args,
+    manifest: &Path,
+    query_ids: Option<&HashSet<String>>,
+    warnings: &mut Warnings,
+) -> Result<()> {
+    let base_dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let normalizer = id_normalizer(args);
+    let mut timings = TimingReport::new();
+    let mut collections = vec![];
+    for line in elinor_cli::load_lines(manifest)? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() != 3 {
+            return Err(elinor_cli::input_error(format!(
+                "Invalid line in the manifest: {line}"
+            )));
+        }
+        let true_lines = elinor_cli::load_lines(base_dir.join(fields[1]))?;
+        let true_rels = timings.time(format!("{}/build_true_rels", fields[0]), || {
+            build_true_rels(&true_lines, args.true_format, &normalizer)
+        })?;
+        let pred_lines = elinor_cli::load_lines(base_dir.join(fields[2]))?;
+        let (pred_rels, n_non_finite_scores) =
+            timings.time(format!("{}/build_pred_rels", fields[0]), || {
+                build_pred_rels(
+                    &pred_lines,
+                    None,
+                    args.pred_format,
+                    args.non_finite_policy,
+                    args.score_quantization,
+                    &normalizer,
+                )
+            })?;
+        if n_non_finite_scores > 0 {
+            warnings.warn(format!(
+                "{n_non_finite_scores} non-finite predicted scores in {} were handled by the policy.",
+                fields[0]
+            ))?;
+        }
+        let (true_rels, pred_rels) = match query_ids {
+            Some(query_ids) => {
+                restrict_queries(&true_rels, &pred_rels, query_ids, warnings, Some(fields[0]))?
+            }
+            None => (true_rels, pred_rels),
+        };
+        collections.push((fields[0].to_string(), true_rels, pred_rels));
+    }
+
+    let convention = dcg_convention(args)?;
+    let options = metric_options(args, convention)?;
+    let precision = args.precision.unwrap_or(4);
+    let metrics = selected_metrics(args);
+    if args.strict_relevance_kind {
+        for (_, true_rels, _) in &collections {
+            for &metric in &metrics {
+                elinor::metrics::validate_relevance_kind(true_rels, metric)?;
+            }
+        }
+    }
+
+    // The metrics without the DCG options are evaluated together in a single sweep for each collection.
+    let sweep_metrics = metrics
+        .iter()
+        .copied()
+        .filter(|&metric| dcg_options(args, metric, convention).is_none())
+        .collect::<Vec<_>>();
+    let mut swept = vec![];
+    for (name, true_rels, pred_rels) in &collections {
+        let results = if sweep_metrics.is_empty() {
+            BTreeMap::new()
+        } else {
+            timings.time(format!("{name}/evaluate_all"), || {
+                elinor_cli::evaluate_all(true_rels, pred_rels, &sweep_metrics, options)
+            })?
+        };
+        swept.push(results);
+    }
+
+    let mut out = args.report_writer();
+    let mut results = vec![];
     for metric in metrics {
-        let result = elinor::evaluate(&true_rels, &pred_rels, metric)?;
-        println!("{:#}\t{:.4}", metric, result.mean());
-        let scores = result.scores();
-        if columns.is_empty() {
-            let query_ids = scores.keys().map(|k| k.as_str()).collect::<Vec<_>>();
-            columns.push(Series::new("query_id".into(), query_ids));
+        let mut evaluations = vec![];
+        for ((name, true_rels, pred_rels), swept) in collections.iter().zip(&mut swept) {
+            if let Some(result) = swept.remove(&metric) {
+                evaluations.push((name.clone(), result));
+                continue;
+            }
+            let start = Instant::now();
+            let result = match dcg_options(args, metric, convention) {
+                Some(dcg_options) => {
+                    elinor::evaluate_with_dcg_options(true_rels, pred_rels, metric, &dcg_options)?
+                }
+                None if options != MetricOptions::new() => {
+                    let output = elinor::metrics::compute_metric_with_options(
+                        true_rels, pred_rels, metric, options,
+                    )?;
+                    Evaluation::from_scores(metric, output.into_scores())
+                }
+                None => elinor_cli::evaluate(true_rels, pred_rels, metric)?,
+            };
+            timings.record(format!("{name}/{metric:#}"), start.elapsed());
+            evaluations.push((name.clone(), result));
+        }
+        let result = MultiCollectionEvaluation::from_evaluations(evaluations)?;
+        for (name, evaluation) in result.evaluations() {
+            writeln!(out, "{metric:#}\t{name}\t{:.precision$}", evaluation.mean())?;
+        }
+        writeln!(
+            out,
+            "{metric:#}\tmacro_mean\t{:.precision$}",
+            result.macro_mean()
+        )?;
+        writeln!(
+            out,
+            "{metric:#}\tbetween_std_dev\t{:.precision$}",
+            result.between_std_dev()
+        )?;
+        results.push(result);
+    }
+    if args.timings {
+        eprintln!("{timings}");
+    }
+
+    if let Some(output_csv) = &args.output_csv {
+        let mut names = collections
+            .iter()
+            .map(|(name, _, _)| name.clone())
+            .collect::<Vec<_>>();
+        names.push("macro_mean".to_string());
+        names.push("between_std_dev".to_string());
+        let mut columns = vec![TableColumn::Text("collection".to_string(), names)];
+        for result in &results {
+            let mut values = result
+                .evaluations()
+                .iter()
+                .map(|(_, evaluation)| evaluation.mean())
+                .collect::<Vec<_>>();
+            values.push(result.macro_mean());
+            values.push(result.between_std_dev());
+            columns.push(TableColumn::Real(format!("{:#}", result.metric()), values));
+        }
+        write_csv(args, output_csv, layout_columns(args, columns)?)?;
+    }
+    Ok(())
+}
+
+fn dcg_convention(args: &Args) -> Result<DcgConvention> {
+    let convention = DcgConvention::new()
+        .with_discount(args.dcg_discount)
+        .with_ideal_cutoff(args.ideal_cutoff)
+        .with_log_base(args.dcg_log_base);
+    convention.validate().map_err(elinor_cli::input_error)?;
+    Ok(convention)
+}
+
+/// Returns the options of the DCG-family metric, or `None` if the metric is evaluated as is.
+fn dcg_options(args: &Args, metric: Metric, convention: DcgConvention) -> Option<DcgOptions> {
+    let mut options = DcgOptions::new().with_convention(convention);
+    if let (Metric::DCG { .. } | Metric::NDCG { .. }, Some(gains)) = (metric, &args.gains) {
+        options = options.with_weighting(DcgWeighting::Custom(gains.clone()));
+    }
+    (is_dcg_family(metric) && options != DcgOptions::new()).then_some(options)
+}
+
+fn metric_options(args: &Args, convention: DcgConvention) -> Result<MetricOptions> {
+    let options = MetricOptions::new().with_short_ranking_policy(args.short_ranking_policy);
+    if options != MetricOptions::new()
+        && (args.gains.is_some()
+            || convention != DcgConvention::new()
+            || !args.rel_levels.is_empty())
+    {
+        return Err(elinor_cli::input_error("The --short-ranking-policy option cannot be used with --gains, --dcg-discount, --ideal-cutoff, --dcg-log-base, and --rel-levels."));
+    }
+    Ok(options)
+}
+
+const fn is_dcg_family(metric: Metric) -> bool {
+    matches!(
+        metric,
+        Metric::DCG { .. }
+            | Metric::NDCG { .. }
+            | Metric::DCGBurges { .. }
+            | Metric::NDCGBurges { .. }
+    )
+}
+
+fn id_normalizer(args: &Args) -> IdNormalizer {
+    IdNormalizer::new()
+        .with_query_steps(args.query_id_normalization.clone())
+        .with_doc_steps(args.doc_id_normalization.clone())
+}
+
+/// Loads the query ids to evaluate, normalized in the same way as those in the inputs.
+fn load_query_ids(args: &Args) -> Result<Option<HashSet<String>>> {
+    let Some(query_ids_file) = &args.query_ids_file else {
+        return Ok(None);
+    };
+    elinor_cli::check_inputs([query_ids_file.as_path()])?;
+    let normalizer = id_normalizer(args);
+    let query_ids = elinor_cli::load_lines(query_ids_file)?
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|query_id| normalizer.normalize_query_id(query_id))
+        .collect::<HashSet<_>>();
+    if query_ids.is_empty() {
+        return Err(elinor_cli::input_error(format!(
+            "No query ids are found in {}",
+            elinor_cli::display_name(query_ids_file)
+        )));
+    }
+    Ok(Some(query_ids))
+}
+
+/// Restricts the true and predicted relevance to the given query ids,
+/// warning about the query ids not found in the true relevance.
+/// Returns the name of the run recorded in --history-jsonl.
+fn run_name(args: &Args) -> String {
+    if let Some(run_name) = &args.run_name {
+        return run_name.clone();
+    }
+    let pred_path = args
+        .pred_jsonl
+        .as_ref()
+        .or(args.pred_parquet.as_ref())
+        .unwrap();
+    let name = pred_path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+    name.split('.').next().unwrap_or_default().to_string()
+}
+
+/// Loads the per-query scores of the baseline specified by --baseline-csv if any.
+fn load_baseline(args: &Args) -> Result<Option<ScoreMatrix>> {
+    let Some(baseline_csv) = &args.baseline_csv else {
+        return Ok(None);
+    };
+    if args.baseline_alpha <= 0.0 || args.baseline_alpha > 1.0 {
+        return Err(elinor_cli::input_error(
+            "The significance level must be in the range (0, 1].",
+        ));
+    }
+    elinor_cli::check_inputs([baseline_csv.as_path()])?;
+    let separator = if args.tab_separator { b'\t' } else { b',' };
+    let matrix = ScoreMatrix::from_csv_with_layout(baseline_csv, separator, args.csv_layout)?;
+    Ok(Some(matrix))
+}
+
+/// Returns the annotation of the difference from the baseline and its significance,
+/// in the form of '\t<difference>\tp=<p-value>' followed by '*' if significant.
+fn baseline_annotation(
+    args: &Args,
+    baseline: &ScoreMatrix,
+    name: &str,
+    result: &Evaluation<String>,
+    precision: usize,
+    warnings: &mut Warnings,
+) -> Result<String> {
+    if !baseline.metrics().iter().any(|m| m == name) {
+        warnings.warn(format!(
+            "The metric {name} is not found in the baseline CSV."
+        ))?;
+        return Ok(String::new());
+    }
+    let baseline_scores = baseline.score_map(name)?;
+    let (tuples, n_dropped) = tuples_from_common_keys([result.scores(), &baseline_scores]);
+    if n_dropped > 0 {
+        warnings.warn(format!(
+            "{n_dropped} queries not common to the baseline were dropped for {name}."
+        ))?;
+    }
+    if tuples.is_empty() {
+        warnings.warn(format!("No queries are common to the baseline for {name}."))?;
+        return Ok(String::new());
+    }
+    let diff = tuples.iter().map(|t| t[0] - t[1]).sum::<f64>() / tuples.len() as f64;
+    match StudentTTest::from_paired_samples(tuples.iter().map(|t| (t[0], t[1]))) {
+        Ok(stat) => {
+            let p_value = stat.p_value();
+            let mark = if p_value < args.baseline_alpha {
+                "*"
+            } else {
+                ""
+            };
+            Ok(format!(
+                "\t{diff:+.precision$}\tp={p_value:.precision$}{mark}"
+            ))
+        }
+        Err(e) => {
+            warnings.warn(format!(
+                "The t-test against the baseline is uncomputable for {name}: {e}"
+            ))?;
+            Ok(format!("\t{diff:+.precision$}\tp=NA"))
         }
-        let values = scores.values().copied().collect::<Vec<_>>();
-        columns.push(Series::new(format!("{metric:#}").into(), values));
     }
+}
 
-    if let Some(output_csv) = args.output_csv {
-        let mut df = DataFrame::new(columns)?;
-        let mut file = std::fs::File::create(output_csv)?;
-        let separator = if args.tab_separator { b'\t' } else { b',' };
-        CsvWriter::new(&mut file)
-            .with_separator(separator)
-            .finish(&mut df)?;
+fn restrict_queries(
+    true_rels: &TrueRelStore<String>,
+    pred_rels: &PredRelStore<String>,
+    query_ids: &HashSet<String>,
+    warnings: &mut Warnings,
+    collection: Option<&str>,
+) -> Result<(TrueRelStore<String>, PredRelStore<String>)> {
+    let true_rels_subset = true_rels.retain_queries(query_ids);
+    let n_missing = query_ids.len() - true_rels_subset.n_queries();
+    if n_missing > 0 {
+        let suffix = collection.map_or_else(String::new, |name| format!(" in {name}"));
+        warnings.warn(format!(
+            "{n_missing} of the {} query ids in the query ids file are not found in the true relevance{suffix}.",
+            query_ids.len()
+        ))?;
     }
+    Ok((true_rels_subset, pred_rels.retain_queries(query_ids)))
+}
+
+fn build_true_rels(
+    true_lines: &[String],
+    format: InputFormat,
+    normalizer: &IdNormalizer,
+) -> Result<TrueRelStore<String>> {
+    let true_records = match format {
+        InputFormat::Jsonl => elinor_cli::parse_true_records(true_lines)?,
+        InputFormat::Trec => {
+            #[cfg(feature = "parallel")]
+            let true_rels = trec::parse_true_rels_in_trec_parallel(true_lines)?;
+            #[cfg(not(feature = "parallel"))]
+            let true_rels = trec::parse_true_rels_in_trec(true_lines)?;
+            true_rels.into_records()
+        }
+    };
+    let true_records = normalizer.normalize_records(true_records);
+    Ok(TrueRelStore::from_records(true_records)?)
+}
 
+/// Builds the predicted relevance from the lines, or from the Parquet data if given.
+fn build_pred_rels(
+    pred_lines: &[String],
+    pred_parquet: Option<&[u8]>,
+    format: InputFormat,
+    policy: NonFiniteScorePolicy,
+    quantization: Option<ScoreQuantization>,
+    normalizer: &IdNormalizer,
+) -> Result<(PredRelStore<String>, usize)> {
+    if format == InputFormat::Trec && pred_parquet.is_none() {
+        let mut parser = TrecRunParser::new().with_non_finite_policy(policy);
+        if let Some(quantization) = quantization {
+            parser = parser.with_quantization(quantization);
+        }
+        let run = parser.parse(pred_lines)?;
+        let n_non_finite_scores = run.n_non_finite();
+        let pred_records = normalizer.normalize_records(run.into_pred_rels().into_records());
+        return Ok((
+            PredRelStore::from_records(pred_records)?,
+            n_non_finite_scores,
+        ));
+    }
+    let pred_records = match pred_parquet {
+        Some(data) => elinor_cli::parse_pred_parquet(data)?,
+        None => elinor_cli::parse_pred_records(pred_lines)?,
+    };
+    let pred_records = normalizer.normalize_records(pred_records);
+    let mut n_non_finite_scores = 0;
+    let mut b = PredRelStoreBuilder::new();
+    for record in pred_records {
+        let score = quantization.map_or(record.score, |q| q.quantize(record.score));
+        if b.add_record_with_policy(record.query_id, record.doc_id, score, policy)? {
+            n_non_finite_scores += 1;
+        }
+    }
+    Ok((b.build(), n_non_finite_scores))
+}
+
+fn selected_metrics(args: &Args) -> Vec<Metric> {
+    if args.metrics.is_empty() {
+        default_metrics()
+    } else {
+        args.metrics.clone()
+    }
+}
+
+fn write_csv(args: &Args, output_csv: &Path, columns: Vec<TableColumn>) -> Result<()> {
+    let file = elinor_cli::create_output(output_csv)?;
+    let separator = if args.tab_separator { b'\t' } else { b',' };
+    elinor_cli::write_table(file, &columns, separator, args.precision)
+}
+
+/// Writes the per-query scores in the same format as `trec_eval -q`,
+/// i.e., the lines of each query in the order of query ids, followed by the lines of the means for `all`.
+fn write_per_query<W: Write>(
+    out: &mut W,
+    results: &[(String, Evaluation<String>)],
+    precision: usize,
+) -> Result<()> {
+    let query_ids = results
+        .iter()
+        .flat_map(|(_, result)| result.scores().keys())
+        .collect::<BTreeSet<_>>();
+    for query_id in query_ids {
+        for (measure, result) in results {
+            if let Some(score) = result.scores().get(query_id) {
+                writeln!(out, "{measure:<22}\t{query_id}\t{score:.precision$}")?;
+            }
+        }
+    }
+    for (measure, result) in results {
+        writeln!(out, "{measure:<22}\tall\t{:.precision$}", result.mean())?;
+    }
     Ok(())
 }
 
+/// Converts the wide columns of the per-query scores into the layout specified by --csv-layout.
+fn layout_columns(args: &Args, columns: Vec<TableColumn>) -> Result<Vec<TableColumn>> {
+    match args.csv_layout {
+        TableLayout::Wide => Ok(columns),
+        TableLayout::Long => elinor_cli::to_long_columns(&columns),
+    }
+}
+
+fn push_columns(columns: &mut Vec<TableColumn>, name: &str, result: &Evaluation<String>) {
+    let scores = result.scores();
+    if columns.is_empty() {
+        let query_ids = scores.keys().cloned().collect::<Vec<_>>();
+        columns.push(TableColumn::Text("query_id".to_string(), query_ids));
+    }
+    let values = scores.values().copied().collect::<Vec<_>>();
+    columns.push(TableColumn::Real(name.to_string(), values));
+}
+
 fn n_relevant_docs(true_rels: &TrueRelStore<String>) -> usize {
     let records = true_rels.records();
     records.into_iter().filter(|r| r.score > 0).count()
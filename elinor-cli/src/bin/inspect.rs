@@ -0,0 +1,209 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor::relevance::NonFiniteScorePolicy;
+use elinor::relevance::RelevanceStore;
+use elinor::trec;
+use elinor::PredRelStore;
+use elinor::PredRelStoreBuilder;
+use elinor::TrueRelStore;
+use elinor_cli::Outcome;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileFormat {
+    TrecQrels,
+    TrecRun,
+    JsonlTrue,
+    JsonlPred,
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trec_qrels" => Ok(Self::TrecQrels),
+            "trec_run" => Ok(Self::TrecRun),
+            "jsonl_true" => Ok(Self::JsonlTrue),
+            "jsonl_pred" => Ok(Self::JsonlPred),
+            _ => Err(format!("Invalid file format: {}", s)),
+        }
+    }
+}
+
+impl Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::TrecQrels => write!(f, "trec_qrels"),
+            Self::TrecRun => write!(f, "trec_run"),
+            Self::JsonlTrue => write!(f, "jsonl_true"),
+            Self::JsonlPred => write!(f, "jsonl_pred"),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Inspect a file of true or predicted relevance scores."
+)]
+struct Args {
+    /// Path to the input file in the TREC or JSONL format.
+    input_file: PathBuf,
+
+    /// File format from 'trec_qrels', 'trec_run', 'jsonl_true', or 'jsonl_pred'.
+    /// If not specified, it is detected from the content.
+    #[arg(short, long)]
+    format: Option<FileFormat>,
+
+    /// Number of sample records to print.
+    #[arg(short, long, default_value = "5")]
+    n_samples: usize,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    let lines = elinor_cli::load_lines(&args.input_file)?
+        .into_iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let format = match args.format {
+        Some(format) => format,
+        None => detect_format(&lines)?,
+    };
+    println!("format\t{format}");
+
+    match format {
+        FileFormat::TrecQrels => {
+            let true_rels = trec::parse_true_rels_in_trec(&lines)?;
+            print_true_summary(&true_rels, args.n_samples);
+        }
+        FileFormat::JsonlTrue => {
+            let true_records = elinor_cli::parse_true_records(&lines)?;
+            let true_rels = TrueRelStore::from_records(true_records)?;
+            print_true_summary(&true_rels, args.n_samples);
+        }
+        FileFormat::TrecRun => {
+            // Non-finite scores are dropped and counted.
+            let (pred_rels, n_non_finite) =
+                trec::parse_pred_rels_in_trec_with_policy(&lines, NonFiniteScorePolicy::Drop)?;
+            print_pred_summary(&pred_rels, n_non_finite, args.n_samples);
+        }
+        FileFormat::JsonlPred => {
+            let pred_records = elinor_cli::parse_pred_records(&lines)?;
+            let mut n_non_finite = 0;
+            let mut b = PredRelStoreBuilder::new();
+            for record in pred_records {
+                if b.add_record_with_policy(
+                    record.query_id,
+                    record.doc_id,
+                    record.score,
+                    NonFiniteScorePolicy::Drop,
+                )? {
+                    n_non_finite += 1;
+                }
+            }
+            print_pred_summary(&b.build(), n_non_finite, args.n_samples);
+        }
+    }
+    Ok(Outcome::Success)
+}
+
+/// Detects the format from the first line for TREC, or from all the scores for JSONL.
+fn detect_format(lines: &[String]) -> Result<FileFormat> {
+    let first = lines
+        .first()
+        .ok_or_else(|| elinor_cli::input_error("The input file is empty."))?;
+    if first.trim_start().starts_with('{') {
+        // The true relevance scores must be non-negative integers.
+        let is_true = lines.iter().all(|line| {
+            serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .and_then(|value| value.get("score").and_then(|score| score.as_u64()))
+                .is_some()
+        });
+        return Ok(if is_true {
+            FileFormat::JsonlTrue
+        } else {
+            FileFormat::JsonlPred
+        });
+    }
+    match first.split_whitespace().count() {
+        4 => Ok(FileFormat::TrecQrels),
+        n if n >= 5 => Ok(FileFormat::TrecRun),
+        _ => Err(elinor_cli::input_error(format!(
+            "Failed to detect the format from the first line: {first}"
+        ))),
+    }
+}
+
+fn print_store_summary<T: Ord + Clone>(store: &RelevanceStore<String, T>) {
+    let n_queries = store.n_queries();
+    let n_docs = store.n_docs();
+    println!("n_queries\t{n_queries}");
+    println!("n_docs\t{n_docs}");
+    if n_queries != 0 {
+        let n_docs_per_query = store
+            .query_ids()
+            .map(|query_id| store.get_map(query_id).unwrap().len())
+            .collect::<Vec<_>>();
+        println!(
+            "min_docs_per_query\t{}",
+            n_docs_per_query.iter().min().unwrap()
+        );
+        println!(
+            "max_docs_per_query\t{}",
+            n_docs_per_query.iter().max().unwrap()
+        );
+        println!(
+            "mean_docs_per_query\t{:.4}",
+            n_docs as f64 / n_queries as f64
+        );
+    }
+}
+
+fn print_samples<T: Ord + Clone + Display>(store: &RelevanceStore<String, T>, n_samples: usize) {
+    if n_samples == 0 {
+        return;
+    }
+    println!();
+    println!("query_id\tdoc_id\tscore");
+    let samples = store
+        .query_ids()
+        .flat_map(|query_id| {
+            store
+                .get_sorted(query_id)
+                .unwrap()
+                .iter()
+                .map(move |rel| (query_id, rel))
+        })
+        .take(n_samples);
+    for (query_id, rel) in samples {
+        println!("{query_id}\t{}\t{}", rel.doc_id, rel.score);
+    }
+}
+
+fn print_true_summary(true_rels: &TrueRelStore<String>, n_samples: usize) {
+    print_store_summary(true_rels);
+    for (grade, count) in true_rels.score_counts() {
+        println!("n_docs_in_grade_{grade}\t{count}");
+    }
+    print_samples(true_rels, n_samples);
+}
+
+fn print_pred_summary(pred_rels: &PredRelStore<String>, n_non_finite: usize, n_samples: usize) {
+    print_store_summary(pred_rels);
+    if let Some((min, max)) = pred_rels.score_range() {
+        println!("min_score\t{min}");
+        println!("max_score\t{max}");
+    }
+    println!("n_non_finite_scores\t{n_non_finite}");
+    print_samples(pred_rels, n_samples);
+}
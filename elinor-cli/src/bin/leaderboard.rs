@@ -0,0 +1,321 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::Context;
+use anyhow::Result;
+use clap::Parser;
+use elinor::statistical_tests::corrections::Correction;
+use elinor::statistical_tests::pairs_from_maps;
+use elinor::statistical_tests::StudentTTest;
+use elinor_cli::InvalidScorePolicy;
+use elinor_cli::Outcome;
+use elinor_cli::ScoreMatrix;
+use elinor_cli::TableColumn;
+use elinor_cli::TableLayout;
+use elinor_cli::Warnings;
+
+#[derive(Clone, Copy, Debug)]
+enum PrintMode {
+    Pretty,
+    Raw,
+}
+
+impl FromStr for PrintMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("Invalid PrintMode: {}", s)),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Rank systems by a metric and test whether each is significantly different from the top."
+)]
+struct Args {
+    /// Path to the input CSV files of the per-topic scores, one for each system.
+    #[arg(short, long, num_args = 2..)]
+    input_csvs: Vec<PathBuf>,
+
+    /// Names of the systems, separated by commas, in the order of the input CSV files.
+    /// If not specified, the file names without extensions are used.
+    #[arg(long, value_delimiter = ',')]
+    names: Vec<String>,
+
+    /// Metric to rank the systems by, which must be a column of all the input CSV files.
+    #[arg(short, long)]
+    metric: String,
+
+    /// Significance level of the paired t-tests against the top system.
+    #[arg(long, default_value = "0.05")]
+    alpha: f64,
+
+    /// Correction of the p-values for testing many systems (bonferroni, holm, or bh).
+    #[arg(long, default_value = "holm")]
+    correction: Correction,
+
+    /// Use tab separator instead of comma for the input CSV files.
+    #[arg(long)]
+    tab_separator: bool,
+
+    /// Layout of the input CSV files (wide or long).
+    #[arg(long, default_value = "wide")]
+    csv_layout: TableLayout,
+
+    /// Policy for invalid scores in the input CSV files, i.e., non-finite scores
+    /// and scores out of the bounds of the metric (reject, drop, or clamp).
+    #[arg(long, default_value = "reject")]
+    invalid_scores: InvalidScorePolicy,
+
+    /// Print mode for the output (pretty or raw).
+    #[arg(short, long, default_value = "pretty")]
+    print_mode: PrintMode,
+
+    /// Number of decimal places of real numbers in the output.
+    /// The printed table uses 4 if not specified, and the output CSV uses the full precision.
+    #[arg(long)]
+    precision: Option<usize>,
+
+    /// Path to the output CSV file of the leaderboard.
+    /// If '-', the table is written to the standard output, and the printed table goes to the standard error.
+    #[arg(short, long)]
+    output_csv: Option<PathBuf>,
+
+    /// Overwrite the existing output file.
+    #[arg(long)]
+    force: bool,
+}
+
+/// Row of the leaderboard.
+struct Entry {
+    name: String,
+    mean: f64,
+    /// p-value of the paired t-test against the top system, or `None` for the top system.
+    p_value: Option<f64>,
+    adj_p_value: Option<f64>,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    if !args.names.is_empty() && args.names.len() != args.input_csvs.len() {
+        return Err(elinor_cli::input_error(format!(
+            "The number of names ({}) must be the same as that of the input CSV files ({}).",
+            args.names.len(),
+            args.input_csvs.len()
+        )));
+    }
+    if args.alpha <= 0.0 || args.alpha > 1.0 {
+        return Err(elinor_cli::input_error(
+            "The significance level must be in the range (0, 1].",
+        ));
+    }
+    elinor_cli::check_inputs(args.input_csvs.iter().map(PathBuf::as_path))?;
+    elinor_cli::check_outputs(args.output_csv.as_deref(), args.force)?;
+
+    let names = if args.names.is_empty() {
+        args.input_csvs
+            .iter()
+            .map(|path| {
+                path.file_stem().map_or_else(
+                    || elinor_cli::display_name(path),
+                    |stem| stem.to_string_lossy().to_string(),
+                )
+            })
+            .collect()
+    } else {
+        args.names.clone()
+    };
+
+    let separator = if args.tab_separator { b'\t' } else { b',' };
+    let mut warnings = Warnings::new(false);
+    let mut score_maps = vec![];
+    for input_csv in &args.input_csvs {
+        let name = elinor_cli::display_name(input_csv);
+        let mut matrix = ScoreMatrix::from_csv_with_layout(input_csv, separator, args.csv_layout)?;
+        if !matrix.metrics().contains(&args.metric) {
+            return Err(elinor_cli::input_error(format!(
+                "Metric {} is not found in {name}",
+                args.metric
+            )));
+        }
+        let invalids = matrix
+            .sanitize(std::slice::from_ref(&args.metric), args.invalid_scores)
+            .with_context(|| format!("Invalid scores in {name}"))?;
+        for invalid in invalids {
+            warnings.warn(format!("{invalid} in {name}"))?;
+        }
+        score_maps.push(matrix.score_map(&args.metric)?);
+    }
+
+    // Sort the systems by the mean in descending order, keeping the input order for ties.
+    let means = score_maps
+        .iter()
+        .map(|scores| scores.values().sum::<f64>() / scores.len() as f64)
+        .collect::<Vec<_>>();
+    let mut order = (0..score_maps.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| means[b].total_cmp(&means[a]));
+    let top = order[0];
+
+    let mut p_values = vec![];
+    for &i in &order[1..] {
+        let pairs = pairs_from_maps(&score_maps[top], &score_maps[i])?;
+        p_values.push(paired_t_p_value(pairs)?);
+    }
+    let adj_p_values = args.correction.adjust(&p_values)?;
+
+    let mut entries = vec![Entry {
+        name: names[top].clone(),
+        mean: means[top],
+        p_value: None,
+        adj_p_value: None,
+    }];
+    for (k, &i) in order[1..].iter().enumerate() {
+        entries.push(Entry {
+            name: names[i].clone(),
+            mean: means[i],
+            p_value: Some(p_values[k]),
+            adj_p_value: Some(adj_p_values[k]),
+        });
+    }
+
+    let precision = args.precision.unwrap_or(4);
+    let mut out: Box<dyn Write> = if args
+        .output_csv
+        .as_deref()
+        .map_or(false, elinor_cli::is_stdio)
+    {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    writeln!(
+        out,
+        "# {} (paired t-test against the top, {} correction, alpha = {})",
+        args.metric, args.correction, args.alpha
+    )?;
+    let format_real =
+        |value: Option<f64>| value.map_or_else(String::new, |v| format!("{v:.precision$}"));
+    let mut cells = vec![[
+        "Rank",
+        "System",
+        "Mean",
+        "Diff",
+        "p-value",
+        "adj-p-value",
+        "Significant",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+    for (rank, entry) in entries.iter().enumerate() {
+        cells.push(vec![
+            (rank + 1).to_string(),
+            entry.name.clone(),
+            format!("{:.precision$}", entry.mean),
+            format!("{:.precision$}", entry.mean - entries[0].mean),
+            format_real(entry.p_value),
+            format_real(entry.adj_p_value),
+            significance_label(entry, args.alpha).to_string(),
+        ]);
+    }
+    print_cells(&mut out, &cells, args.print_mode)?;
+
+    if let Some(output_csv) = &args.output_csv {
+        let columns = vec![
+            TableColumn::Integer(
+                "rank".to_string(),
+                (1..=entries.len() as u64).collect::<Vec<_>>(),
+            ),
+            TableColumn::Text(
+                "system".to_string(),
+                entries.iter().map(|e| e.name.clone()).collect(),
+            ),
+            TableColumn::Real("mean".to_string(), entries.iter().map(|e| e.mean).collect()),
+            TableColumn::Real(
+                "diff".to_string(),
+                entries.iter().map(|e| e.mean - entries[0].mean).collect(),
+            ),
+            TableColumn::Real(
+                "p_value".to_string(),
+                entries
+                    .iter()
+                    .map(|e| e.p_value.unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+            TableColumn::Real(
+                "adj_p_value".to_string(),
+                entries
+                    .iter()
+                    .map(|e| e.adj_p_value.unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+            TableColumn::Text(
+                "significant".to_string(),
+                entries
+                    .iter()
+                    .map(|e| significance_label(e, args.alpha).to_string())
+                    .collect(),
+            ),
+        ];
+        let file = elinor_cli::create_output(output_csv)?;
+        elinor_cli::write_table(file, &columns, b',', args.precision)?;
+    }
+    Ok(warnings.outcome())
+}
+
+/// Computes the p-value of the two-sided paired t-test.
+///
+/// If the differences are constant, the variance is zero and the t-statistic is undefined,
+/// so the p-value is 1 for no difference and 0 otherwise.
+fn paired_t_p_value(pairs: Vec<(f64, f64)>) -> Result<f64> {
+    let diffs = pairs.iter().map(|(a, b)| a - b).collect::<Vec<_>>();
+    if diffs.windows(2).all(|w| w[0] == w[1]) {
+        return Ok(if diffs.first().map_or(true, |&d| d == 0.0) {
+            1.0
+        } else {
+            0.0
+        });
+    }
+    Ok(StudentTTest::from_paired_samples(pairs)?.p_value())
+}
+
+/// Returns "yes" or "no" depending on the adjusted p-value, or an empty string for the top system.
+fn significance_label(entry: &Entry, alpha: f64) -> &'static str {
+    match entry.adj_p_value {
+        Some(p) if p < alpha => "yes",
+        Some(_) => "no",
+        None => "",
+    }
+}
+
+fn print_cells(out: &mut dyn Write, cells: &[Vec<String>], mode: PrintMode) -> Result<()> {
+    match mode {
+        PrintMode::Pretty => {
+            let to_row = |row: &[String]| {
+                prettytable::Row::new(row.iter().map(|c| prettytable::Cell::new(c)).collect())
+            };
+            let mut table = prettytable::Table::new();
+            table.set_titles(to_row(&cells[0]));
+            for row in &cells[1..] {
+                table.add_row(to_row(row));
+            }
+            table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.print(out)?;
+        }
+        PrintMode::Raw => {
+            for row in cells {
+                writeln!(out, "{}", row.join("\t"))?;
+            }
+        }
+    }
+    Ok(())
+}
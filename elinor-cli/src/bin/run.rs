@@ -0,0 +1,287 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor_cli::Outcome;
+use toml_edit::DocumentMut;
+use toml_edit::Item;
+use toml_edit::TableLike;
+use toml_edit::Value;
+
+/// Sections of the config that can be overridden with --set.
+const SECTIONS: [&str; 2] = ["evaluate", "compare"];
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Run an experiment of evaluating and comparing systems end-to-end as configured in a TOML file."
+)]
+struct Args {
+    /// Path to the TOML config of the experiment.
+    /// Relative paths in the config are resolved from the directory of the config.
+    #[arg(short, long)]
+    config: PathBuf,
+
+    /// Override of an option in the config in the form of <section>.<key>=<value>,
+    /// where the section is evaluate or compare and the value is in TOML
+    /// (e.g., compare.gate=0.01 or 'evaluate.metrics=["ap", "ndcg@10"]').
+    /// Values that are not valid TOML are regarded as strings.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+
+    /// Print the commands without running them.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Step of the experiment, i.e., an invocation of a tool.
+struct Step {
+    label: String,
+    tool: &'static str,
+    args: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    elinor_cli::check_inputs([args.config.as_path()])?;
+    let text = elinor_cli::read_to_string(&args.config)?;
+    let mut config = text.parse::<DocumentMut>().map_err(|e| {
+        elinor_cli::input_error(format!(
+            "Failed to parse {}: {e}",
+            elinor_cli::display_name(&args.config)
+        ))
+    })?;
+    for assignment in &args.overrides {
+        apply_override(&mut config, assignment)?;
+    }
+    let steps = plan_steps(&config)?;
+
+    let work_dir = args
+        .config
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let name = config
+        .get("name")
+        .and_then(Item::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| elinor_cli::display_name(&args.config));
+
+    let mut outcome = Outcome::Success;
+    for step in steps {
+        eprintln!("# [{name}] {}", step.label);
+        eprintln!("{} {}", step.tool, step.args.join(" "));
+        if args.dry_run {
+            continue;
+        }
+        let status = Command::new(tool_path(step.tool))
+            .args(&step.args)
+            .current_dir(work_dir)
+            .status()
+            .map_err(|e| anyhow::anyhow!("Failed to run {}: {e}", step.tool))?;
+        match status.code() {
+            Some(0) => {}
+            Some(code) if code == i32::from(Outcome::Warnings.code()) => {
+                if outcome == Outcome::Success {
+                    outcome = Outcome::Warnings;
+                }
+            }
+            Some(code) if code == i32::from(Outcome::Regression.code()) => {
+                outcome = Outcome::Regression;
+            }
+            Some(code) if code == i32::from(elinor_cli::EXIT_INPUT_ERROR) => {
+                return Err(elinor_cli::input_error(format!(
+                    "{} failed with an input error.",
+                    step.label
+                )));
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "{} failed with the status {status}.",
+                    step.label
+                ))
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Plans the steps of evaluating each run and then comparing the runs if configured.
+///
+/// The options of a run override those in the evaluate section,
+/// and the output CSV of each run defaults to `<name>.csv`.
+fn plan_steps(config: &DocumentMut) -> Result<Vec<Step>> {
+    let common = section(config, "evaluate")?;
+    let runs = match config.get("runs") {
+        Some(item) => item
+            .as_array_of_tables()
+            .ok_or_else(|| elinor_cli::input_error("The runs must be an array of tables."))?,
+        None => {
+            return Err(elinor_cli::input_error(
+                "The config must have at least one run.",
+            ))
+        }
+    };
+    if runs.is_empty() {
+        return Err(elinor_cli::input_error(
+            "The config must have at least one run.",
+        ));
+    }
+
+    let mut steps = vec![];
+    let mut output_csvs = vec![];
+    for (i, table) in runs.iter().enumerate() {
+        let name = table
+            .get("name")
+            .and_then(Item::as_str)
+            .ok_or_else(|| elinor_cli::input_error(format!("The run {} must have a name.", i + 1)))?
+            .to_string();
+        let mut options = common.clone();
+        for (key, item) in table.iter().filter(|(key, _)| *key != "name") {
+            let value = item.as_value().ok_or_else(|| {
+                elinor_cli::input_error(format!(
+                    "The option {key} of the run {name} must be a value."
+                ))
+            })?;
+            set_option(&mut options, key, value.clone());
+        }
+        if !options.iter().any(|(key, _)| key == "output_csv") {
+            set_option(
+                &mut options,
+                "output_csv",
+                Value::from(format!("{name}.csv")),
+            );
+        }
+        let output_csv = options
+            .iter()
+            .find(|(key, _)| key == "output_csv")
+            .and_then(|(_, value)| value.as_str())
+            .ok_or_else(|| elinor_cli::input_error("The output_csv must be a string."))?
+            .to_string();
+        output_csvs.push(output_csv);
+        steps.push(Step {
+            label: format!("evaluate {name}"),
+            tool: "elinor-evaluate",
+            args: to_args(&options)?,
+        });
+    }
+
+    if config.contains_key("compare") {
+        let mut options = section(config, "compare")?;
+        if options.iter().any(|(key, _)| key == "input_csvs") {
+            return Err(elinor_cli::input_error(
+                "The input_csvs of compare are given by the output CSV files of the runs.",
+            ));
+        }
+        let input_csvs = output_csvs.into_iter().collect::<toml_edit::Array>();
+        options.insert(0, ("input_csvs".to_string(), Value::Array(input_csvs)));
+        steps.push(Step {
+            label: "compare".to_string(),
+            tool: "elinor-compare",
+            args: to_args(&options)?,
+        });
+    }
+    Ok(steps)
+}
+
+/// Returns the options in the section of the config, or an empty list if missing.
+fn section(config: &DocumentMut, name: &str) -> Result<Vec<(String, Value)>> {
+    let Some(item) = config.get(name) else {
+        return Ok(vec![]);
+    };
+    let table: &dyn TableLike = item
+        .as_table_like()
+        .ok_or_else(|| elinor_cli::input_error(format!("The {name} must be a table.")))?;
+    table
+        .iter()
+        .map(|(key, item)| {
+            let value = item.as_value().ok_or_else(|| {
+                elinor_cli::input_error(format!("The option {key} in {name} must be a value."))
+            })?;
+            Ok((key.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+/// Sets the option, replacing the existing one with the same key.
+fn set_option(options: &mut Vec<(String, Value)>, key: &str, value: Value) {
+    match options.iter_mut().find(|(k, _)| k == key) {
+        Some((_, v)) => *v = value,
+        None => options.push((key.to_string(), value)),
+    }
+}
+
+/// Converts the options into the command-line arguments,
+/// where `snake_case` keys become `--kebab-case` flags, `true` becomes a flag without a value,
+/// `false` is omitted, and arrays repeat the flag for each element.
+fn to_args(options: &[(String, Value)]) -> Result<Vec<String>> {
+    let mut args = vec![];
+    for (key, value) in options {
+        let flag = format!("--{}", key.replace('_', "-"));
+        match value {
+            Value::Boolean(b) => {
+                if *b.value() {
+                    args.push(flag);
+                }
+            }
+            Value::Array(array) => {
+                for element in array {
+                    args.push(flag.clone());
+                    args.push(scalar_to_string(key, element)?);
+                }
+            }
+            _ => {
+                args.push(flag);
+                args.push(scalar_to_string(key, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn scalar_to_string(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.value().clone()),
+        Value::Integer(i) => Ok(i.value().to_string()),
+        Value::Float(f) => Ok(f.value().to_string()),
+        _ => Err(elinor_cli::input_error(format!(
+            "The option {key} must be a string, a number, a boolean, or an array of them."
+        ))),
+    }
+}
+
+/// Applies the override in the form of <section>.<key>=<value> to the config.
+fn apply_override(config: &mut DocumentMut, assignment: &str) -> Result<()> {
+    let invalid = || {
+        elinor_cli::input_error(format!(
+            "Invalid override {assignment:?}, which must be in the form of <section>.<key>=<value>."
+        ))
+    };
+    let (path, raw) = assignment.split_once('=').ok_or_else(invalid)?;
+    let (section, key) = path.trim().split_once('.').ok_or_else(invalid)?;
+    if !SECTIONS.contains(&section) || key.is_empty() {
+        return Err(invalid());
+    }
+    let value = raw
+        .trim()
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(raw.trim()));
+    config[section][key] = Item::Value(value);
+    Ok(())
+}
+
+/// Returns the path of the tool next to this executable, or the bare name to search the `PATH`.
+fn tool_path(tool: &str) -> PathBuf {
+    let name = format!("{tool}{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(&name))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
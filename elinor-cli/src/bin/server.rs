@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor::comparison::CompareOptions;
+use elinor::comparison::ComparisonTest;
+use elinor::metrics::MetricOptions;
+use elinor::relevance::RecordId;
+use elinor::statistical_tests::corrections::Correction;
+use elinor::statistical_tests::effect_sizes::EffectSizeBands;
+use elinor::Metric;
+use elinor::PredRecord;
+use elinor::PredRelStore;
+use elinor::TrueRecord;
+use elinor::TrueRelStore;
+use elinor_cli::Outcome;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Serve the evaluation and comparison over HTTP with JSON requests and responses."
+)]
+struct Args {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Port to listen on.
+    #[arg(long, default_value = "8080")]
+    port: u16,
+
+    /// Maximum size of a request body in bytes.
+    #[arg(long, default_value = "67108864")]
+    max_body_bytes: usize,
+}
+
+/// Request body of `POST /evaluate`.
+#[derive(Deserialize, Debug)]
+struct EvaluateRequest {
+    /// True relevance records, in the same format as the JSONL lines of elinor-evaluate.
+    true_rels: Vec<TrueRecord<RecordId>>,
+    /// Predicted relevance records, in the same format as the JSONL lines of elinor-evaluate.
+    pred_rels: Vec<PredRecord<RecordId>>,
+    /// Metrics to evaluate (e.g., "ap" or "ndcg@10").
+    metrics: Vec<String>,
+}
+
+/// Response body of `POST /evaluate`.
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct EvaluateResponse {
+    /// Evaluation for each metric, in the request order.
+    metrics: Vec<MetricEvaluation>,
+}
+
+/// Evaluation of a metric.
+#[derive(Serialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+struct MetricEvaluation {
+    /// Name of the metric.
+    metric: String,
+    /// Macro-averaged score.
+    mean: f64,
+    /// Mapping from query ids to scores.
+    scores: BTreeMap<String, f64>,
+}
+
+/// Request body of `POST /compare`.
+#[derive(Deserialize, Debug)]
+struct CompareRequest {
+    /// Per-topic scores of the systems for each metric.
+    metrics: Vec<MetricScores>,
+    /// Statistical tests to run, where empty means all the tests.
+    #[serde(default)]
+    tests: Vec<String>,
+    n_resamples: Option<usize>,
+    n_iters: Option<usize>,
+    /// Correction of the p-values over the metrics (bonferroni, holm, or bh).
+    correction: Option<String>,
+    /// Label the effect sizes by Cohen's thresholds.
+    #[serde(default)]
+    es_labels: bool,
+    /// Drop the topics missing in any of the systems instead of failing.
+    #[serde(default)]
+    drop_missing_topics: bool,
+    /// Minimum number of topics remaining after dropping the missing ones.
+    min_topics: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MetricScores {
+    metric: String,
+    /// Mappings from topic ids to scores, one for each system.
+    systems: Vec<BTreeMap<String, f64>>,
+}
+
+#[derive(Serialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    let listener = TcpListener::bind((args.host.as_str(), args.port))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let max_body_bytes = args.max_body_bytes;
+        std::thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, max_body_bytes) {
+                eprintln!("Failed to handle a connection: {e:#}");
+            }
+        });
+    }
+    Ok(Outcome::Success)
+}
+
+/// Handles a single HTTP/1.1 request on the connection, which is closed after the response.
+fn handle_connection(stream: TcpStream, max_body_bytes: usize) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let (status, body) = if content_length > max_body_bytes {
+        error_response(
+            413,
+            format!("The request body exceeds {max_body_bytes} bytes."),
+        )
+    } else {
+        let mut body = Vec::with_capacity(content_length);
+        reader
+            .by_ref()
+            .take(content_length as u64)
+            .read_to_end(&mut body)?;
+        route(&method, &path, &body)
+    };
+    write_response(stream, status, &body)
+}
+
+/// Dispatches the request to the endpoint, returning the status code and JSON body.
+fn route(method: &str, path: &str, body: &[u8]) -> (u16, String) {
+    let result = match (method, path) {
+        ("GET", "/health") => Ok(r#"{"status":"ok"}"#.to_string()),
+        ("POST", "/evaluate") => handle_evaluate(body),
+        ("POST", "/compare") => handle_compare(body),
+        (_, "/health" | "/evaluate" | "/compare") => {
+            return error_response(405, format!("Method {method} is not allowed for {path}."))
+        }
+        _ => return error_response(404, format!("Unknown endpoint: {path}")),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(e) if elinor_cli::is_input_error(&e) => error_response(400, format!("{e:#}")),
+        Err(e) => error_response(500, format!("{e:#}")),
+    }
+}
+
+fn handle_evaluate(body: &[u8]) -> Result<String> {
+    let request: EvaluateRequest = serde_json::from_slice(body)?;
+    let metrics = request
+        .metrics
+        .iter()
+        .map(|metric| {
+            metric
+                .parse::<Metric>()
+                .map_err(|_| elinor_cli::input_error(format!("Invalid metric: {metric}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let true_rels = TrueRelStore::from_records(
+        request
+            .true_rels
+            .into_iter()
+            .map(elinor_cli::into_string_ids),
+    )?;
+    let pred_rels = PredRelStore::from_records(
+        request
+            .pred_rels
+            .into_iter()
+            .map(elinor_cli::into_string_ids),
+    )?;
+
+    let results = elinor_cli::evaluate_all(&true_rels, &pred_rels, &metrics, MetricOptions::new())?;
+    let evaluations = metrics
+        .iter()
+        .map(|metric| {
+            let evaluation = &results[metric];
+            MetricEvaluation {
+                metric: metric.to_string(),
+                mean: evaluation.mean(),
+                scores: evaluation.scores().clone(),
+            }
+        })
+        .collect();
+    Ok(serde_json::to_string(&EvaluateResponse {
+        metrics: evaluations,
+    })?)
+}
+
+fn handle_compare(body: &[u8]) -> Result<String> {
+    let request: CompareRequest = serde_json::from_slice(body)?;
+    let tests = request
+        .tests
+        .iter()
+        .map(|test| test.parse::<ComparisonTest>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut options = CompareOptions::new()
+        .with_tests(tests)
+        .with_drop_missing_topics(request.drop_missing_topics);
+    if let Some(n_resamples) = request.n_resamples {
+        options = options.with_n_resamples(n_resamples);
+    }
+    if let Some(n_iters) = request.n_iters {
+        options = options.with_n_iters(n_iters);
+    }
+    if let Some(min_topics) = request.min_topics {
+        options = options.with_min_topics(min_topics);
+    }
+    if let Some(correction) = &request.correction {
+        options = options.with_correction(correction.parse::<Correction>()?);
+    }
+    if request.es_labels {
+        options = options
+            .with_effect_size_bands(EffectSizeBands::cohen_d())
+            .with_correlation_bands(EffectSizeBands::cohen_r());
+    }
+
+    let score_maps = request
+        .metrics
+        .into_iter()
+        .map(|m| (m.metric, m.systems))
+        .collect::<Vec<_>>();
+    let report = elinor::comparison::compare_score_maps(&score_maps, &options)?;
+    Ok(serde_json::to_string(&report)?)
+}
+
+fn error_response(status: u16, error: String) -> (u16, String) {
+    let body = serde_json::to_string(&ErrorResponse { error }).unwrap();
+    (status, body)
+}
+
+fn write_response(mut stream: TcpStream, status: u16, body: &str) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn test_json_schema() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../schemas/evaluate-response.v1.json");
+        let schema = elinor::json_schema::schema_for::<EvaluateResponse>();
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            current == generated,
+            "{} is out of date; run the tests with UPDATE_SCHEMAS=1 to regenerate it.",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor::cross_validation::HoldoutSplitter;
+use elinor_cli::Warnings;
+
+const SPLIT_NAMES: [&str; 3] = ["train", "dev", "test"];
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Split topics into train/dev/test sets.")]
+struct Args {
+    /// Path to the input JSONL file of true relevance scores.
+    #[arg(short, long)]
+    true_jsonl: PathBuf,
+
+    /// Paths to the input JSONL files of predicted relevance scores,
+    /// which are filtered by the split topics.
+    #[arg(short, long, num_args = 1..)]
+    pred_jsonls: Vec<PathBuf>,
+
+    /// Path to the output directory.
+    #[arg(short, long)]
+    output_dir: PathBuf,
+
+    /// Ratio of training topics.
+    #[arg(long, default_value = "0.6")]
+    train_ratio: f64,
+
+    /// Ratio of development topics.
+    /// The remaining topics are used for testing.
+    #[arg(long, default_value = "0.2")]
+    dev_ratio: f64,
+
+    /// Random state for splitting the topics.
+    #[arg(long, default_value = "0")]
+    random_state: u64,
+
+    /// Path to the TSV file of '<query_id>\t<split>' lines, where <split> is 'train', 'dev', or 'test'.
+    /// If specified, the topics are split by the list instead of at random.
+    #[arg(long)]
+    topic_list: Option<PathBuf>,
+
+    /// Overwrite existing output files.
+    #[arg(long)]
+    force: bool,
+
+    /// Treat warnings (e.g., queries missing in the topic list) as errors.
+    #[arg(long)]
+    strict: bool,
+}
+
+fn main() -> ExitCode {
+    let args: Args = elinor_cli::parse_args();
+    let mut warnings = Warnings::new(args.strict);
+    let result = run(args, &mut warnings).map(|()| warnings.outcome());
+    elinor_cli::exit_code(result)
+}
+
+fn run(args: Args, warnings: &mut Warnings) -> Result<()> {
+    let mut output_paths = vec![];
+    for input in std::iter::once(&args.true_jsonl).chain(&args.pred_jsonls) {
+        for name in SPLIT_NAMES {
+            output_paths.push(split_path(input, &args.output_dir, name)?);
+        }
+    }
+    elinor_cli::check_outputs(output_paths.iter().map(PathBuf::as_path), args.force)?;
+
+    let true_lines = elinor_cli::load_lines(&args.true_jsonl)?;
+    let true_records = elinor_cli::parse_true_records(&true_lines)?;
+    let query_ids = true_records
+        .iter()
+        .map(|record| record.query_id.clone())
+        .collect::<BTreeSet<_>>();
+
+    let assignment = match &args.topic_list {
+        Some(topic_list) => load_topic_list(topic_list)?,
+        None => {
+            let split = HoldoutSplitter::new(args.train_ratio, args.dev_ratio)
+                .with_random_state(args.random_state)
+                .split(query_ids.iter().cloned())?;
+            let mut assignment = BTreeMap::new();
+            for (name, split_ids) in
+                SPLIT_NAMES
+                    .iter()
+                    .zip([split.train(), split.dev(), split.test()])
+            {
+                for query_id in split_ids {
+                    assignment.insert(query_id.clone(), name.to_string());
+                }
+            }
+            assignment
+        }
+    };
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    for name in SPLIT_NAMES {
+        let n_queries = query_ids
+            .iter()
+            .filter(|&query_id| assignment.get(query_id).map_or(false, |s| s == name))
+            .count();
+        println!("n_queries_in_{name}\t{n_queries}");
+    }
+    let n_unassigned = query_ids
+        .iter()
+        .filter(|&query_id| !assignment.contains_key(query_id))
+        .count();
+    if n_unassigned > 0 {
+        warnings.warn(format!(
+            "{n_unassigned} queries are not in the topic list and are dropped."
+        ))?;
+    }
+
+    let true_query_ids = true_records.into_iter().map(|record| record.query_id);
+    write_splits(
+        &args.true_jsonl,
+        &args.output_dir,
+        &assignment,
+        &true_lines,
+        true_query_ids,
+    )?;
+    for pred_jsonl in &args.pred_jsonls {
+        let pred_lines = elinor_cli::load_lines(pred_jsonl)?;
+        let pred_records = elinor_cli::parse_pred_records(&pred_lines)?;
+        let pred_query_ids = pred_records.into_iter().map(|record| record.query_id);
+        write_splits(
+            pred_jsonl,
+            &args.output_dir,
+            &assignment,
+            &pred_lines,
+            pred_query_ids,
+        )?;
+    }
+    Ok(())
+}
+
+fn load_topic_list(path: &Path) -> Result<BTreeMap<String, String>> {
+    let mut assignment = BTreeMap::new();
+    for line in elinor_cli::load_lines(path)? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (query_id, name) = line.split_once('\t').ok_or_else(|| {
+            elinor_cli::input_error(format!("Invalid line in the topic list: {line}"))
+        })?;
+        let name = name.trim();
+        if !SPLIT_NAMES.contains(&name) {
+            return Err(elinor_cli::input_error(format!(
+                "The split must be 'train', 'dev', or 'test', but got {name}"
+            )));
+        }
+        assignment.insert(query_id.to_string(), name.to_string());
+    }
+    Ok(assignment)
+}
+
+/// Returns the path of `<output_dir>/<stem>_<split>.jsonl` for the input.
+fn split_path(input: &Path, output_dir: &Path, name: &str) -> Result<PathBuf> {
+    let stem = input.file_stem().ok_or_else(|| {
+        elinor_cli::input_error(format!(
+            "Invalid input path: {}",
+            elinor_cli::display_name(input)
+        ))
+    })?;
+    let mut file_name = stem.to_os_string();
+    file_name.push(format!("_{name}.jsonl"));
+    Ok(output_dir.join(file_name))
+}
+
+/// Writes the lines into `<output_dir>/<stem>_<split>.jsonl` for each split.
+fn write_splits<I>(
+    input: &Path,
+    output_dir: &Path,
+    assignment: &BTreeMap<String, String>,
+    lines: &[String],
+    query_ids: I,
+) -> Result<()>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut writers = BTreeMap::new();
+    for name in SPLIT_NAMES {
+        let path = split_path(input, output_dir, name)?;
+        writers.insert(name.to_string(), elinor_cli::create_output(path)?);
+    }
+    for (line, query_id) in lines.iter().zip(query_ids) {
+        if let Some(name) = assignment.get(&query_id) {
+            let writer = writers.get_mut(name).unwrap();
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    for writer in writers.values_mut() {
+        writer.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::Parser;
+use elinor_cli::HistoryMetric;
+use elinor_cli::Outcome;
+use elinor_cli::TableColumn;
+use elinor_cli::Warnings;
+
+/// Width of the bars plotting the means.
+const BAR_WIDTH: usize = 30;
+
+#[derive(Clone, Copy, Debug)]
+enum PrintMode {
+    Pretty,
+    Raw,
+}
+
+impl FromStr for PrintMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "raw" => Ok(Self::Raw),
+            _ => Err(format!("Invalid PrintMode: {}", s)),
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "Print the history of a metric over the evaluation runs recorded by elinor-evaluate."
+)]
+struct Args {
+    /// Path to the history file written by elinor-evaluate with --history-jsonl.
+    #[arg(long)]
+    history_jsonl: PathBuf,
+
+    /// Metric to print the history of, as named in the output of elinor-evaluate (e.g., ndcg@10).
+    #[arg(short, long)]
+    metric: String,
+
+    /// Print only the last n runs.
+    #[arg(long)]
+    last: Option<usize>,
+
+    /// Print mode for the output (pretty or raw).
+    #[arg(short, long, default_value = "pretty")]
+    print_mode: PrintMode,
+
+    /// Number of decimal places of real numbers in the output.
+    /// The printed table uses 4 if not specified, and the output CSV uses the full precision.
+    #[arg(long)]
+    precision: Option<usize>,
+
+    /// Path to the output CSV file of the history.
+    /// If '-', the table is written to the standard output, and the printed table goes to the standard error.
+    #[arg(short, long)]
+    output_csv: Option<PathBuf>,
+
+    /// Overwrite the existing output file.
+    #[arg(long)]
+    force: bool,
+}
+
+/// Point of the history of the metric.
+struct Point {
+    run: String,
+    timestamp: String,
+    summary: HistoryMetric,
+}
+
+fn main() -> ExitCode {
+    elinor_cli::exit_code(run(elinor_cli::parse_args()))
+}
+
+fn run(args: Args) -> Result<Outcome> {
+    elinor_cli::check_inputs([args.history_jsonl.as_path()])?;
+    elinor_cli::check_outputs(args.output_csv.as_deref(), args.force)?;
+
+    let mut warnings = Warnings::new(false);
+    let records = elinor_cli::load_history(&args.history_jsonl)?;
+    let mut points = vec![];
+    let mut n_missing = 0;
+    for record in records {
+        match record.metrics.into_iter().find(|m| m.metric == args.metric) {
+            Some(summary) => points.push(Point {
+                run: record.run,
+                timestamp: record.timestamp,
+                summary,
+            }),
+            None => n_missing += 1,
+        }
+    }
+    if points.is_empty() {
+        return Err(elinor_cli::input_error(format!(
+            "The metric {} is not found in {}.",
+            args.metric,
+            elinor_cli::display_name(&args.history_jsonl)
+        )));
+    }
+    if n_missing > 0 {
+        warnings.warn(format!(
+            "{n_missing} runs without the metric {} were skipped.",
+            args.metric
+        ))?;
+    }
+    if let Some(last) = args.last {
+        points.drain(..points.len().saturating_sub(last));
+    }
+
+    let precision = args.precision.unwrap_or(4);
+    let mut out: Box<dyn Write> = if args
+        .output_csv
+        .as_deref()
+        .map_or(false, elinor_cli::is_stdio)
+    {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+    writeln!(out, "# {} (95% confidence intervals)", args.metric)?;
+    let format_real =
+        |value: Option<f64>| value.map_or_else(String::new, |v| format!("{v:.precision$}"));
+    let (lowest, highest) = points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(lowest, highest), p| (lowest.min(p.summary.mean), highest.max(p.summary.mean)),
+    );
+    let mut cells = vec![[
+        "Run",
+        "Timestamp",
+        "Mean",
+        "CI95 Lower",
+        "CI95 Upper",
+        "Diff",
+        "Plot",
+    ]
+    .map(str::to_string)
+    .to_vec()];
+    for (i, point) in points.iter().enumerate() {
+        let diff = (i > 0).then(|| point.summary.mean - points[i - 1].summary.mean);
+        cells.push(vec![
+            point.run.clone(),
+            point.timestamp.clone(),
+            format!("{:.precision$}", point.summary.mean),
+            format_real(point.summary.ci95_lower),
+            format_real(point.summary.ci95_upper),
+            diff.map_or_else(String::new, |d| format!("{d:+.precision$}")),
+            plot_bar(point.summary.mean, lowest, highest),
+        ]);
+    }
+    print_cells(&mut out, &cells, args.print_mode)?;
+
+    if let Some(output_csv) = &args.output_csv {
+        let columns = vec![
+            TableColumn::Text(
+                "run".to_string(),
+                points.iter().map(|p| p.run.clone()).collect(),
+            ),
+            TableColumn::Text(
+                "timestamp".to_string(),
+                points.iter().map(|p| p.timestamp.clone()).collect(),
+            ),
+            TableColumn::Real(
+                "mean".to_string(),
+                points.iter().map(|p| p.summary.mean).collect(),
+            ),
+            TableColumn::Real(
+                "ci95_lower".to_string(),
+                points
+                    .iter()
+                    .map(|p| p.summary.ci95_lower.unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+            TableColumn::Real(
+                "ci95_upper".to_string(),
+                points
+                    .iter()
+                    .map(|p| p.summary.ci95_upper.unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+        ];
+        let file = elinor_cli::create_output(output_csv)?;
+        elinor_cli::write_table(file, &columns, b',', args.precision)?;
+    }
+    Ok(warnings.outcome())
+}
+
+/// Plots the mean as a bar scaled between the lowest and highest means,
+/// where the lowest one has a bar of length one.
+fn plot_bar(mean: f64, lowest: f64, highest: f64) -> String {
+    let ratio = if highest > lowest {
+        (mean - lowest) / (highest - lowest)
+    } else {
+        1.0
+    };
+    let length = 1 + (ratio * (BAR_WIDTH - 1) as f64).round() as usize;
+    "#".repeat(length)
+}
+
+fn print_cells(out: &mut dyn Write, cells: &[Vec<String>], mode: PrintMode) -> Result<()> {
+    match mode {
+        PrintMode::Pretty => {
+            let to_row = |row: &[String]| {
+                prettytable::Row::new(row.iter().map(|c| prettytable::Cell::new(c)).collect())
+            };
+            let mut table = prettytable::Table::new();
+            table.set_titles(to_row(&cells[0]));
+            for row in &cells[1..] {
+                table.add_row(to_row(row));
+            }
+            table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+            table.print(out)?;
+        }
+        PrintMode::Raw => {
+            for row in cells {
+                writeln!(out, "{}", row.join("\t"))?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,1209 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
 
 use anyhow::Result;
+use elinor::metrics::MetricOptions;
+use elinor::relevance::RecordId;
+use elinor::ElinorError;
+use elinor::Evaluation;
+use elinor::Metric;
+use elinor::PredRecord;
+use elinor::PredRelStore;
+use elinor::Record;
+use elinor::TrueRecord;
+use elinor::TrueRelStore;
+use xxhash_rust::xxh3::Xxh3;
 
-/// Load lines from a file.
+/// Path meaning the standard input or output.
+pub const STDIO_PATH: &str = "-";
+
+/// Magic bytes of gzip files.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Magic bytes of zstd files.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Outcome of a tool that has run to the end.
+///
+/// Along with the errors, it determines the exit code of the tool:
+///
+/// | Exit code | Meaning |
+/// | --- | --- |
+/// | 0 | Success |
+/// | 1 | Other errors |
+/// | 2 | Validation warnings (see [`Warnings`]) |
+/// | 3 | Significant regression in the gate mode |
+/// | 4 | Input errors (see [`InputError`]) |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The tool has succeeded without any warnings.
+    Success,
+
+    /// The tool has succeeded with some warnings.
+    Warnings,
+
+    /// The gate mode has detected a significant regression.
+    Regression,
+}
+
+impl Outcome {
+    /// Returns the exit code of the outcome.
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Warnings => 2,
+            Self::Regression => 3,
+        }
+    }
+}
+
+/// Exit code for errors other than input errors.
+pub const EXIT_FAILURE: u8 = 1;
+
+/// Exit code for input errors, including invalid command-line arguments.
+pub const EXIT_INPUT_ERROR: u8 = 4;
+
+/// Error on the inputs of a tool, such as unreadable files, malformed records, or invalid arguments.
+#[derive(Debug)]
+pub struct InputError(pub String);
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Creates an [`InputError`] with the message.
+pub fn input_error<M: fmt::Display>(message: M) -> anyhow::Error {
+    InputError(message.to_string()).into()
+}
+
+/// Returns `true` if the error is caused by the inputs of a tool.
+///
+/// In addition to [`InputError`], JSON parse errors and elinor errors
+/// other than [`ElinorError::Uncomputable`] are regarded as input errors.
+pub fn is_input_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<InputError>()
+            || cause.is::<serde_json::Error>()
+            || cause
+                .downcast_ref::<ElinorError>()
+                .map_or(false, |e| !matches!(e, ElinorError::Uncomputable(_)))
+    })
+}
+
+/// Collector of validation warnings, which are promoted to errors in the strict mode.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    strict: bool,
+    n_warnings: usize,
+}
+
+impl Warnings {
+    /// Creates a new collector. If `strict` is `true`, every warning is an [`InputError`].
+    pub const fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            n_warnings: 0,
+        }
+    }
+
+    /// Reports a warning to the standard error,
+    /// or returns it as an [`InputError`] in the strict mode.
+    pub fn warn<M: fmt::Display>(&mut self, message: M) -> Result<()> {
+        if self.strict {
+            return Err(input_error(format!("{message} (--strict is enabled)")));
+        }
+        eprintln!("Warning: {message}");
+        self.n_warnings += 1;
+        Ok(())
+    }
+
+    /// Returns the number of reported warnings.
+    pub const fn len(&self) -> usize {
+        self.n_warnings
+    }
+
+    /// Returns `true` if no warnings have been reported.
+    pub const fn is_empty(&self) -> bool {
+        self.n_warnings == 0
+    }
+
+    /// Returns [`Outcome::Warnings`] if any warnings have been reported, or [`Outcome::Success`] otherwise.
+    pub const fn outcome(&self) -> Outcome {
+        if self.n_warnings == 0 {
+            Outcome::Success
+        } else {
+            Outcome::Warnings
+        }
+    }
+}
+
+/// Parses the command-line arguments, exiting with [`EXIT_INPUT_ERROR`] if they are invalid.
+///
+/// This replaces [`clap::Parser::parse`], which exits with code 2 reserved for warnings.
+pub fn parse_args<T: clap::Parser>() -> T {
+    T::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        let code = if e.use_stderr() { EXIT_INPUT_ERROR } else { 0 };
+        std::process::exit(code.into())
+    })
+}
+
+/// Converts the result of a tool into its exit code, printing the error if any.
+pub fn exit_code(result: Result<Outcome>) -> ExitCode {
+    match result {
+        Ok(outcome) => ExitCode::from(outcome.code()),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if is_input_error(&e) {
+                ExitCode::from(EXIT_INPUT_ERROR)
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
+    }
+}
+
+/// Load lines from a file, which can be compressed or the standard input (see [`open_input`]).
 pub fn load_lines<P: AsRef<Path>>(file: P) -> Result<Vec<String>> {
-    let file = File::open(file)?;
-    let reader = BufReader::new(file);
+    let path = file.as_ref();
+    let reader = open_input(path)?;
+    read_lines(reader)
+        .map_err(|e| input_error(format!("Failed to read {}: {e}", display_name(path))))
+}
+
+/// Reads lines from any buffered reader, such as the standard input.
+pub fn read_lines<R: BufRead>(reader: R) -> Result<Vec<String>> {
     let lines = reader.lines().collect::<Result<Vec<_>, _>>()?;
     Ok(lines)
 }
+
+/// Opens a file for reading, or the standard input if the path is [`STDIO_PATH`],
+/// transparently decompressing gzip data (and zstd data if the `zstd` feature is enabled).
+///
+/// The compression is detected by the magic bytes, not by the extension.
+pub fn open_input<P: AsRef<Path>>(file: P) -> Result<Box<dyn BufRead>> {
+    let path = file.as_ref();
+    if is_stdio(path) {
+        return decompress(std::io::stdin().lock(), path);
+    }
+    let file = File::open(path)
+        .map_err(|e| input_error(format!("Failed to open {}: {e}", display_name(path))))?;
+    decompress(BufReader::new(file), path)
+}
+
+/// Wraps the reader with the decoder detected by the magic bytes.
+fn decompress<R: BufRead + 'static>(mut reader: R, path: &Path) -> Result<Box<dyn BufRead>> {
+    let head = reader
+        .fill_buf()
+        .map_err(|e| input_error(format!("Failed to read {}: {e}", display_name(path))))?;
+    if head.starts_with(&GZIP_MAGIC) {
+        let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+        return Ok(Box::new(BufReader::new(decoder)));
+    }
+    if head.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        {
+            let decoder = zstd::stream::read::Decoder::with_buffer(reader)?;
+            return Ok(Box::new(BufReader::new(decoder)));
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err(input_error(format!(
+            "{} is compressed by zstd. Enable the zstd feature to read it.",
+            display_name(path)
+        )));
+    }
+    Ok(Box::new(reader))
+}
+
+/// Checks the input paths, where at most one of them can be [`STDIO_PATH`].
+pub fn check_inputs<'a, I>(paths: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    if paths.into_iter().filter(|&path| is_stdio(path)).count() > 1 {
+        return Err(input_error(
+            "At most one input can be read from the standard input.",
+        ));
+    }
+    Ok(())
+}
+
+/// Reads a whole file into a string, which can be compressed or the standard input (see [`open_input`]).
+pub fn read_to_string<P: AsRef<Path>>(file: P) -> Result<String> {
+    let path = file.as_ref();
+    let mut data = String::new();
+    open_input(path)?
+        .read_to_string(&mut data)
+        .map_err(|e| input_error(format!("Failed to read {}: {e}", display_name(path))))?;
+    Ok(data)
+}
+
+/// Reads the whole file as bytes, or the standard input if the path is [`STDIO_PATH`],
+/// transparently decompressing it as in [`open_input`].
+pub fn read_bytes<P: AsRef<Path>>(file: P) -> Result<Vec<u8>> {
+    let path = file.as_ref();
+    let mut data = vec![];
+    open_input(path)?
+        .read_to_end(&mut data)
+        .map_err(|e| input_error(format!("Failed to read {}: {e}", display_name(path))))?;
+    Ok(data)
+}
+
+/// Returns the name of the path for display, replacing invalid Unicode with U+FFFD.
+pub fn display_name<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref().to_string_lossy().into_owned()
+}
+
+/// Returns `true` if the path is [`STDIO_PATH`].
+pub fn is_stdio<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().as_os_str() == STDIO_PATH
+}
+
+/// Checks the output paths before running a tool, so that it does not fail after a long computation.
+///
+/// Existing files are rejected unless `force` is `true`,
+/// and at most one of the paths can be [`STDIO_PATH`].
+pub fn check_outputs<'a, I>(paths: I, force: bool) -> Result<()>
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    let mut n_stdio = 0;
+    for path in paths {
+        if is_stdio(path) {
+            n_stdio += 1;
+        } else if !force && path.exists() {
+            return Err(input_error(format!(
+                "The output file {} already exists. Use --force to overwrite it.",
+                display_name(path)
+            )));
+        }
+    }
+    if n_stdio > 1 {
+        return Err(input_error(
+            "At most one output can be written to the standard output.",
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a buffered writer to the output file, or to the standard output if the path is [`STDIO_PATH`].
+pub fn create_output<P: AsRef<Path>>(path: P) -> Result<Box<dyn Write>> {
+    let path = path.as_ref();
+    if is_stdio(path) {
+        return Ok(Box::new(BufWriter::new(std::io::stdout())));
+    }
+    let file = File::create(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", display_name(path)))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+/// Parses JSONL lines into true records, in parallel if the `parallel` feature is enabled.
+///
+/// Integer ids are accepted and joined with the same ids in strings, as in TREC files.
+pub fn parse_true_records(lines: &[String]) -> Result<Vec<TrueRecord<String>>> {
+    parse_jsonl(lines, |line| {
+        let record: TrueRecord<RecordId> = serde_json::from_str(line)?;
+        Ok(into_string_ids(record))
+    })
+}
+
+/// Parses JSONL lines into predicted records, in parallel if the `parallel` feature is enabled.
+///
+/// Integer ids are accepted and joined with the same ids in strings, as in TREC files.
+pub fn parse_pred_records(lines: &[String]) -> Result<Vec<PredRecord<String>>> {
+    parse_jsonl(lines, |line| {
+        let record: PredRecord<RecordId> = serde_json::from_str(line)?;
+        Ok(into_string_ids(record))
+    })
+}
+
+/// Parses Parquet data with the columns of `query_id`, `doc_id`, and `score` into predicted records.
+///
+/// It requires the `parquet` feature, failing otherwise.
+pub fn parse_pred_parquet(data: &[u8]) -> Result<Vec<PredRecord<String>>> {
+    #[cfg(feature = "parquet")]
+    {
+        Ok(PredRelStore::from_parquet(data)?.into_records())
+    }
+    #[cfg(not(feature = "parquet"))]
+    {
+        let _ = data;
+        Err(input_error(
+            "Parquet files cannot be read. Enable the parquet feature to read them.",
+        ))
+    }
+}
+
+/// Converts the ids of the record into strings, where integer ids become their decimal strings.
+pub fn into_string_ids<T>(record: Record<RecordId, T>) -> Record<String, T> {
+    Record {
+        query_id: record.query_id.into(),
+        doc_id: record.doc_id.into(),
+        score: record.score,
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn parse_jsonl<T, F>(lines: &[String], f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&str) -> Result<T> + Sync,
+{
+    use rayon::prelude::*;
+    lines.par_iter().map(|line| f(line)).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn parse_jsonl<T, F>(lines: &[String], f: F) -> Result<Vec<T>>
+where
+    F: Fn(&str) -> Result<T>,
+{
+    lines.iter().map(|line| f(line)).collect()
+}
+
+/// Evaluates the predicted relevance scores, in parallel if the `parallel` feature is enabled.
+pub fn evaluate(
+    true_rels: &TrueRelStore<String>,
+    pred_rels: &PredRelStore<String>,
+    metric: Metric,
+) -> Result<Evaluation<String>> {
+    #[cfg(feature = "parallel")]
+    let result = elinor::evaluate_parallel(true_rels, pred_rels, metric)?;
+    #[cfg(not(feature = "parallel"))]
+    let result = elinor::evaluate(true_rels, pred_rels, metric)?;
+    Ok(result)
+}
+
+/// Evaluates the predicted relevance scores for multiple metrics with the options,
+/// in parallel over the metrics if the `parallel` feature is enabled.
+pub fn evaluate_all(
+    true_rels: &TrueRelStore<String>,
+    pred_rels: &PredRelStore<String>,
+    metrics: &[Metric],
+    options: MetricOptions,
+) -> Result<BTreeMap<Metric, Evaluation<String>>> {
+    #[cfg(feature = "parallel")]
+    let results =
+        elinor::evaluate_all_parallel_with_options(true_rels, pred_rels, metrics, options)?;
+    #[cfg(not(feature = "parallel"))]
+    let results = elinor::evaluate_all_with_options(true_rels, pred_rels, metrics, options)?;
+    Ok(results)
+}
+
+/// Column of a table written by [`write_table`].
+pub enum TableColumn {
+    /// Column of strings.
+    Text(String, Vec<String>),
+
+    /// Column of real numbers.
+    Real(String, Vec<f64>),
+
+    /// Column of non-negative integers.
+    Integer(String, Vec<u64>),
+}
+
+impl TableColumn {
+    /// Name of the column.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Text(name, _) | Self::Real(name, _) | Self::Integer(name, _) => name,
+        }
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Text(_, values) => values.len(),
+            Self::Real(_, values) => values.len(),
+            Self::Integer(_, values) => values.len(),
+        }
+    }
+
+    /// Returns `true` if the column has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Formats the `i`-th value, with the full precision for real numbers if `precision` is `None`.
+    pub fn format(&self, i: usize, precision: Option<usize>) -> String {
+        match (self, precision) {
+            (Self::Text(_, values), _) => values[i].clone(),
+            (Self::Integer(_, values), _) => values[i].to_string(),
+            (Self::Real(_, values), Some(precision)) => format!("{:.precision$}", values[i]),
+            (Self::Real(_, values), None) => format!("{:?}", values[i]),
+        }
+    }
+}
+
+/// Writes the columns as a CSV table with the separator, without constructing dataframes.
+///
+/// Real numbers are written with the full precision if `precision` is `None`.
+/// Fields containing the separator, quotes, or line breaks are quoted.
+pub fn write_table<W: Write>(
+    mut writer: W,
+    columns: &[TableColumn],
+    separator: u8,
+    precision: Option<usize>,
+) -> Result<()> {
+    let n_rows = columns.first().map_or(0, TableColumn::len);
+    if columns.iter().any(|column| column.len() != n_rows) {
+        return Err(anyhow::anyhow!("The columns must have the same length."));
+    }
+    let separator = separator as char;
+    let escape = |field: &str| {
+        if field.contains([separator, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    let header = columns
+        .iter()
+        .map(|column| escape(column.name()))
+        .collect::<Vec<_>>();
+    writeln!(writer, "{}", header.join(&separator.to_string()))?;
+    for i in 0..n_rows {
+        let row = columns
+            .iter()
+            .map(|column| escape(&column.format(i, precision)))
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", row.join(&separator.to_string()))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Layout of topic-by-metric score tables in CSV.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableLayout {
+    /// One row for each topic, with the topic column followed by a column for each metric.
+    #[default]
+    Wide,
+
+    /// One row for each topic-metric pair, with the topic, `metric`, and `score` columns
+    /// (i.e., tidy data for R and other statistical tools).
+    Long,
+}
+
+impl FromStr for TableLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wide" => Ok(Self::Wide),
+            "long" => Ok(Self::Long),
+            _ => Err(format!("Invalid TableLayout: {}", s)),
+        }
+    }
+}
+
+/// Converts the columns of a wide table, i.e., the topic column of text followed by the metric columns of reals,
+/// into those of the long table, i.e., the topic, `metric`, and `score` columns.
+///
+/// The rows are ordered by the topics and then the metrics, and NaN scores are regarded as missing and skipped.
+pub fn to_long_columns(columns: &[TableColumn]) -> Result<Vec<TableColumn>> {
+    let Some((TableColumn::Text(topic_header, topic_ids), metric_columns)) = columns.split_first()
+    else {
+        return Err(anyhow::anyhow!(
+            "The first column of the wide table must have the topic ids."
+        ));
+    };
+    let mut metric_scores = vec![];
+    for column in metric_columns {
+        match column {
+            TableColumn::Real(name, values) if values.len() == topic_ids.len() => {
+                metric_scores.push((name, values));
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "The metric column {} must have as many reals as the topics.",
+                    column.name()
+                ))
+            }
+        }
+    }
+    let mut topics = vec![];
+    let mut metrics = vec![];
+    let mut scores = vec![];
+    for (i, topic_id) in topic_ids.iter().enumerate() {
+        for &(metric, values) in &metric_scores {
+            if values[i].is_nan() {
+                continue;
+            }
+            topics.push(topic_id.clone());
+            metrics.push(metric.clone());
+            scores.push(values[i]);
+        }
+    }
+    Ok(vec![
+        TableColumn::Text(topic_header.clone(), topics),
+        TableColumn::Text("metric".to_string(), metrics),
+        TableColumn::Real("score".to_string(), scores),
+    ])
+}
+
+/// Policy for invalid scores in [`ScoreMatrix`], i.e., non-finite scores
+/// and scores out of the bounds of the metrics (see [`Metric::bounds`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidScorePolicy {
+    /// Rejects the matrix with an error listing the invalid scores.
+    #[default]
+    Reject,
+
+    /// Regards the invalid scores as missing.
+    Drop,
+
+    /// Clamps the finite scores to the bounds, and regards the non-finite scores as missing.
+    Clamp,
+}
+
+impl FromStr for InvalidScorePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "drop" => Ok(Self::Drop),
+            "clamp" => Ok(Self::Clamp),
+            _ => Err(format!("Invalid InvalidScorePolicy: {}", s)),
+        }
+    }
+}
+
+/// Maximum number of invalid scores listed in the error of [`InvalidScorePolicy::Reject`].
+const MAX_LISTED_INVALID_SCORES: usize = 10;
+
+/// Topic-by-metric matrix of scores, read from a CSV file written by elinor-evaluate.
+///
+/// The first column has the topic ids, and the other columns have the scores of the metrics.
+/// Empty fields are regarded as missing scores.
+pub struct ScoreMatrix {
+    topic_header: String,
+    metrics: Vec<String>,
+    topic_ids: Vec<String>,
+    rows: Vec<Vec<Option<f64>>>,
+}
+
+impl ScoreMatrix {
+    /// Reads the matrix from a CSV file with the separator, which can be compressed (see [`open_input`]).
+    pub fn from_csv<P: AsRef<Path>>(file: P, separator: u8) -> Result<Self> {
+        let path = file.as_ref();
+        Self::from_csv_str(&read_to_string(path)?, separator)
+            .map_err(|e| input_error(format!("Failed to parse {}: {e}", display_name(path))))
+    }
+
+    /// Reads the matrix from a CSV file in the layout, which can be compressed (see [`open_input`]).
+    pub fn from_csv_with_layout<P: AsRef<Path>>(
+        file: P,
+        separator: u8,
+        layout: TableLayout,
+    ) -> Result<Self> {
+        let path = file.as_ref();
+        let data = read_to_string(path)?;
+        let matrix = match layout {
+            TableLayout::Wide => Self::from_csv_str(&data, separator),
+            TableLayout::Long => Self::from_long_csv_str(&data, separator),
+        };
+        matrix.map_err(|e| input_error(format!("Failed to parse {}: {e}", display_name(path))))
+    }
+
+    /// Reads the matrix from CSV data in the long layout with the separator (see [`TableLayout::Long`]).
+    ///
+    /// The topics and metrics are ordered by their first appearances,
+    /// and missing topic-metric pairs are regarded as missing scores.
+    pub fn from_long_csv_str(data: &str, separator: u8) -> Result<Self> {
+        let mut records = parse_csv(data, separator)?.into_iter();
+        let header = records
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("The CSV data must have a header."))?;
+        if header.len() != 3 {
+            return Err(anyhow::anyhow!(
+                "The CSV header in the long layout must have the topic, metric, and score columns, but got {header:?}."
+            ));
+        }
+        let topic_header = header.into_iter().next().unwrap();
+        let mut metrics: Vec<String> = vec![];
+        let mut topic_ids: Vec<String> = vec![];
+        let mut metric_indices = BTreeMap::new();
+        let mut topic_indices = BTreeMap::new();
+        let mut rows: Vec<Vec<Option<f64>>> = vec![];
+        let mut seen_pairs = BTreeSet::new();
+        for (i, record) in records.enumerate() {
+            let [topic_id, metric, field]: [String; 3] =
+                record.try_into().map_err(|record: Vec<_>| {
+                    anyhow::anyhow!(
+                        "The record {} must have 3 fields, but got {}.",
+                        i + 1,
+                        record.len()
+                    )
+                })?;
+            let score = if field.is_empty() {
+                None
+            } else {
+                Some(field.parse::<f64>().map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid score {field:?} for {metric} in the record {}.",
+                        i + 1
+                    )
+                })?)
+            };
+            let j = *metric_indices.entry(metric.clone()).or_insert_with(|| {
+                metrics.push(metric);
+                rows.iter_mut().for_each(|row| row.push(None));
+                metrics.len() - 1
+            });
+            let t = *topic_indices.entry(topic_id.clone()).or_insert_with(|| {
+                topic_ids.push(topic_id);
+                rows.push(vec![None; metrics.len()]);
+                topic_ids.len() - 1
+            });
+            if !seen_pairs.insert((t, j)) {
+                return Err(anyhow::anyhow!(
+                    "The pair of {} and {} is duplicated in the record {}.",
+                    topic_ids[t],
+                    metrics[j],
+                    i + 1
+                ));
+            }
+            rows[t][j] = score;
+        }
+        Ok(Self {
+            topic_header,
+            metrics,
+            topic_ids,
+            rows,
+        })
+    }
+
+    /// Reads the matrix from CSV data with the separator.
+    pub fn from_csv_str(data: &str, separator: u8) -> Result<Self> {
+        let mut records = parse_csv(data, separator)?.into_iter();
+        let mut header = records
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("The CSV data must have a header."))?
+            .into_iter();
+        let topic_header = header
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("The CSV header must have the topic column."))?;
+        let metrics = header.collect::<Vec<_>>();
+        let mut topic_ids = vec![];
+        let mut rows = vec![];
+        for (i, record) in records.enumerate() {
+            if record.len() != metrics.len() + 1 {
+                return Err(anyhow::anyhow!(
+                    "The record {} must have {} fields, but got {}.",
+                    i + 1,
+                    metrics.len() + 1,
+                    record.len()
+                ));
+            }
+            let mut fields = record.into_iter();
+            topic_ids.push(fields.next().unwrap());
+            let row = fields
+                .zip(&metrics)
+                .map(|(field, metric)| {
+                    if field.is_empty() {
+                        return Ok(None);
+                    }
+                    field.parse::<f64>().map(Some).map_err(|_| {
+                        anyhow::anyhow!(
+                            "Invalid score {field:?} for {metric} in the record {}.",
+                            i + 1
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            rows.push(row);
+        }
+        Ok(Self {
+            topic_header,
+            metrics,
+            topic_ids,
+            rows,
+        })
+    }
+
+    /// Header name of the topic column.
+    pub fn topic_header(&self) -> &str {
+        &self.topic_header
+    }
+
+    /// Names of the metrics.
+    pub fn metrics(&self) -> &[String] {
+        &self.metrics
+    }
+
+    /// Number of topics, i.e., records.
+    pub fn n_topics(&self) -> usize {
+        self.topic_ids.len()
+    }
+
+    /// Sanitizes the invalid scores of the metrics with the policy,
+    /// returning the descriptions of the invalid scores.
+    ///
+    /// A score is invalid if it is not finite or out of the bounds of the metric,
+    /// where the bounds are checked only for the metric names parsed by [`Metric`].
+    /// The other metrics in the matrix are left as they are.
+    ///
+    /// # Errors
+    ///
+    /// * [`InputError`] if a metric is not found.
+    /// * [`InputError`] listing the invalid scores if any with [`InvalidScorePolicy::Reject`].
+    pub fn sanitize(
+        &mut self,
+        metrics: &[String],
+        policy: InvalidScorePolicy,
+    ) -> Result<Vec<String>> {
+        let mut targets = vec![];
+        for metric in metrics {
+            let j = self.metric_index(metric)?;
+            let bounds = metric
+                .parse::<Metric>()
+                .map_or((f64::NEG_INFINITY, f64::INFINITY), |m| m.bounds());
+            targets.push((j, bounds));
+        }
+        let mut invalids = vec![];
+        for (i, (topic_id, row)) in self.topic_ids.iter().zip(self.rows.iter_mut()).enumerate() {
+            for &(j, (lower, upper)) in &targets {
+                let cell = &mut row[j];
+                let Some(score) = *cell else {
+                    continue;
+                };
+                let sanitized = if !score.is_finite() {
+                    None
+                } else if score < lower || score > upper {
+                    match policy {
+                        InvalidScorePolicy::Clamp => Some(score.clamp(lower, upper)),
+                        _ => None,
+                    }
+                } else {
+                    continue;
+                };
+                invalids.push(format!(
+                    "Invalid score {score} of {} for {topic_id} in the record {} (bounds: [{lower}, {upper}])",
+                    self.metrics[j],
+                    i + 1
+                ));
+                *cell = sanitized;
+            }
+        }
+        if policy == InvalidScorePolicy::Reject && !invalids.is_empty() {
+            let mut message = format!("Found {} invalid score(s):", invalids.len());
+            for invalid in invalids.iter().take(MAX_LISTED_INVALID_SCORES) {
+                message.push_str(&format!("\n  {invalid}"));
+            }
+            if invalids.len() > MAX_LISTED_INVALID_SCORES {
+                message.push_str(&format!(
+                    "\n  ... and {} more",
+                    invalids.len() - MAX_LISTED_INVALID_SCORES
+                ));
+            }
+            return Err(input_error(message));
+        }
+        Ok(invalids)
+    }
+
+    /// Returns the mapping from topic ids to the scores of the metric, skipping missing scores.
+    pub fn score_map(&self, metric: &str) -> Result<BTreeMap<String, f64>> {
+        let j = self.metric_index(metric)?;
+        Ok(self
+            .topic_ids
+            .iter()
+            .zip(&self.rows)
+            .filter_map(|(topic_id, row)| row[j].map(|score| (topic_id.clone(), score)))
+            .collect())
+    }
+
+    fn metric_index(&self, metric: &str) -> Result<usize> {
+        self.metrics
+            .iter()
+            .position(|m| m == metric)
+            .ok_or_else(|| input_error(format!("The metric {metric} is not found.")))
+    }
+}
+
+/// Splits CSV data into records of fields, skipping empty lines.
+///
+/// Fields can be quoted to contain the separator, quotes (escaped by doubling), and line breaks.
+fn parse_csv(data: &str, separator: u8) -> Result<Vec<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(separator)
+        .from_reader(data.as_bytes());
+    let mut records = vec![];
+    for record in reader.records() {
+        records.push(record?.iter().map(str::to_string).collect());
+    }
+    Ok(records)
+}
+
+/// On-disk cache of per-query scores, keyed by [`CacheKey`].
+///
+/// The cache is best-effort: broken entries are missed,
+/// and a failure to write an entry is warned once and disables further writes.
+pub struct ScoreCache {
+    dir: PathBuf,
+    is_writable: Cell<bool>,
+}
+
+impl ScoreCache {
+    /// Creates a cache in the given directory.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            is_writable: Cell::new(true),
+        }
+    }
+
+    /// Returns the default directory, `$XDG_CACHE_HOME/elinor` or `$HOME/.cache/elinor`,
+    /// falling back to the temporary directory.
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+            return PathBuf::from(dir).join("elinor");
+        }
+        if let Some(dir) = std::env::var_os("HOME") {
+            return PathBuf::from(dir).join(".cache").join("elinor");
+        }
+        std::env::temp_dir().join("elinor")
+    }
+
+    /// Loads the scores for the key, returning `None` if missing or broken.
+    pub fn load(&self, key: &str) -> Option<BTreeMap<String, f64>> {
+        let data = std::fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// Stores the scores for the key, warning instead of failing if the cache cannot be written.
+    pub fn store(&self, key: &str, scores: &BTreeMap<String, f64>) {
+        if !self.is_writable.get() {
+            return;
+        }
+        let result = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(self.path(key), serde_json::to_string(scores)?));
+        if let Err(e) = result {
+            eprintln!(
+                "Warning: Failed to write the cache in {}, which is disabled: {e}",
+                self.dir.display()
+            );
+            self.is_writable.set(false);
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+/// Key of [`ScoreCache`], which is the 128-bit XXH3 hash of the contents of the inputs.
+///
+/// The hash is stable across platforms and Rust versions, and the key includes the version of elinor-cli
+/// so that the scores cached before fixes of metrics are not reused.
+#[derive(Clone)]
+pub struct CacheKey {
+    hasher: Xxh3,
+}
+
+impl CacheKey {
+    /// Creates a key of no inputs other than the version.
+    pub fn new() -> Self {
+        let mut key = Self {
+            hasher: Xxh3::new(),
+        };
+        key.push(env!("CARGO_PKG_VERSION"));
+        key
+    }
+
+    /// Adds an input, prefixed with its length so that the boundaries of inputs are unambiguous.
+    pub fn push<T: AsRef<[u8]> + ?Sized>(&mut self, input: &T) -> &mut Self {
+        let input = input.as_ref();
+        self.hasher.update(&(input.len() as u64).to_le_bytes());
+        self.hasher.update(input);
+        self
+    }
+
+    /// Adds lines as an input.
+    pub fn push_lines(&mut self, lines: &[String]) -> &mut Self {
+        self.push(&(lines.len() as u64).to_le_bytes());
+        for line in lines {
+            self.push(line);
+        }
+        self
+    }
+
+    /// Returns the key as a hexadecimal string.
+    pub fn finish(&self) -> String {
+        format!("{:032x}", self.hasher.digest128())
+    }
+}
+
+impl Default for CacheKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summary of a metric in an evaluation run, recorded in the history file.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryMetric {
+    /// Name of the metric (e.g., `ndcg@10`).
+    pub metric: String,
+
+    /// Mean of the per-query scores.
+    pub mean: f64,
+
+    /// Lower bound of the 95% confidence interval of the mean, if computable.
+    pub ci95_lower: Option<f64>,
+
+    /// Upper bound of the 95% confidence interval of the mean, if computable.
+    pub ci95_upper: Option<f64>,
+}
+
+impl HistoryMetric {
+    /// Summarizes the per-query scores of the evaluation,
+    /// where the confidence interval is computed with the t-distribution.
+    ///
+    /// The interval is `None` if the evaluation has fewer than two queries,
+    /// and collapses to the mean if the scores are constant.
+    pub fn from_evaluation<K>(metric: String, evaluation: &Evaluation<K>) -> Self
+    where
+        K: Clone + Eq + Ord + fmt::Display,
+    {
+        let mean = evaluation.mean();
+        let samples = evaluation.scores().values().map(|&score| (score, 0.0));
+        let ci95 = match elinor::statistical_tests::StudentTTest::from_paired_samples(samples) {
+            Ok(stat) => stat.confidence_interval(0.05).ok(),
+            Err(ElinorError::Uncomputable(_)) => Some((mean, mean)),
+            Err(_) => None,
+        };
+        Self {
+            metric,
+            mean,
+            ci95_lower: ci95.map(|(lower, _)| lower),
+            ci95_upper: ci95.map(|(_, upper)| upper),
+        }
+    }
+}
+
+/// Record of an evaluation run in the history file, which is a JSONL file with a record per line.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryRecord {
+    /// Name of the run (e.g., a commit hash or an experiment name).
+    pub run: String,
+
+    /// Time of the evaluation in UTC, in the form of `YYYY-MM-DDThh:mm:ssZ`.
+    pub timestamp: String,
+
+    /// Number of evaluated queries.
+    pub n_queries: usize,
+
+    /// Summaries of the metrics.
+    pub metrics: Vec<HistoryMetric>,
+}
+
+/// Appends the record to the history file, creating it if missing.
+///
+/// Existing records are never rewritten, so the file can be shared by runs over time.
+pub fn append_history<P: AsRef<Path>>(file: P, record: &HistoryRecord) -> Result<()> {
+    let path = file.as_ref();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {e}", display_name(path)))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Loads the records in the history file in the order of appending.
+///
+/// # Errors
+///
+/// * [`InputError`] if a line is not a valid record.
+pub fn load_history<P: AsRef<Path>>(file: P) -> Result<Vec<HistoryRecord>> {
+    let path = file.as_ref();
+    let mut records = vec![];
+    for (i, line) in load_lines(path)?.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(line).map_err(|e| {
+            input_error(format!(
+                "Failed to parse the line {} of {}: {e}",
+                i + 1,
+                display_name(path)
+            ))
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Formats the seconds since the Unix epoch as `YYYY-MM-DDThh:mm:ssZ` in UTC.
+pub fn format_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+    // Converts the days into the civil date (Howard Hinnant's algorithm).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_quoted_fields() {
+        let data = "\"a,b\",c\n\"say \"\"hi\"\"\",\"x\ny\"\n";
+        let records = parse_csv(data, b',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a,b".to_string(), "c".to_string()],
+                vec!["say \"hi\"".to_string(), "x\ny".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_line_breaks() {
+        let data = "a,b\r\n\r\nc,d\n\ne,f";
+        let records = parse_csv(data, b',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["c".to_string(), "d".to_string()],
+                vec!["e".to_string(), "f".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_empty_fields() {
+        let records = parse_csv("a,,\n,b,\n", b',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), String::new(), String::new()],
+                vec![String::new(), "b".to_string(), String::new()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_separator() {
+        let records = parse_csv("a,b\tc\n\"d\te\"\tf\n", b'\t').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a,b".to_string(), "c".to_string()],
+                vec!["d\te".to_string(), "f".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_flexible() {
+        let records = parse_csv("a,b\nc\n", b',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["c".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_table_round_trip() {
+        let topic_ids = vec!["q,1".to_string(), "q\"2".to_string(), "q\n3".to_string()];
+        let columns = vec![
+            TableColumn::Text("topic,id".to_string(), topic_ids.clone()),
+            TableColumn::Real("ap".to_string(), vec![0.5, 0.25, 0.125]),
+        ];
+        let mut data = vec![];
+        write_table(&mut data, &columns, b',', None).unwrap();
+        let matrix = ScoreMatrix::from_csv_str(std::str::from_utf8(&data).unwrap(), b',').unwrap();
+        assert_eq!(matrix.topic_header(), "topic,id");
+        assert_eq!(matrix.metrics(), &["ap".to_string()]);
+        assert_eq!(
+            matrix.score_map("ap").unwrap(),
+            BTreeMap::from([
+                (topic_ids[0].clone(), 0.5),
+                (topic_ids[1].clone(), 0.25),
+                (topic_ids[2].clone(), 0.125),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_score_matrix_from_long_csv_str() {
+        let data = "topic,metric,score\nq_1,ap,0.5\nq_2,ap,\nq_1,ndcg,0.25\n";
+        let matrix = ScoreMatrix::from_long_csv_str(data, b',').unwrap();
+        assert_eq!(matrix.topic_header(), "topic");
+        assert_eq!(matrix.metrics(), &["ap".to_string(), "ndcg".to_string()]);
+        assert_eq!(matrix.n_topics(), 2);
+        assert_eq!(
+            matrix.score_map("ap").unwrap(),
+            BTreeMap::from([("q_1".to_string(), 0.5)])
+        );
+        assert_eq!(
+            matrix.score_map("ndcg").unwrap(),
+            BTreeMap::from([("q_1".to_string(), 0.25)])
+        );
+    }
+
+    #[test]
+    fn test_score_matrix_from_long_csv_str_duplicated() {
+        for data in [
+            "topic,metric,score\nq_1,ap,0.5\nq_1,ap,0.25\n",
+            "topic,metric,score\nq_1,ap,\nq_1,ap,0.25\n",
+            "topic,metric,score\nq_1,ap,0.5\nq_1,ap,\n",
+        ] {
+            let result = ScoreMatrix::from_long_csv_str(data, b',');
+            assert_eq!(
+                result.err().unwrap().to_string(),
+                "The pair of q_1 and ap is duplicated in the record 2."
+            );
+        }
+    }
+
+    #[test]
+    fn test_score_matrix_from_csv_str_invalid_record() {
+        let result = ScoreMatrix::from_csv_str("topic,ap,ndcg\nq_1,0.5\n", b',');
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "The record 1 must have 3 fields, but got 2."
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! R bindings of elinor, exposed through [extendr](https://extendr.github.io/).
+//!
+//! The functions take flat vectors so that they can be called with columns of R data frames.
+//! The R functions in `R/elinor.R` wrap them to return data frames and matrices.
+use elinor::statistical_tests::bootstrap_test::BootstrapTester;
+use elinor::statistical_tests::fisher_randomization_test::FisherRandomizationTester;
+use elinor::statistical_tests::randomized_tukey_hsd_test::RandomizedTukeyHsdTester;
+use elinor::statistical_tests::student_t_test::StudentTTester;
+use elinor::statistical_tests::Alternative;
+use elinor::statistical_tests::SignTest;
+use elinor::statistical_tests::TukeyHsdTest;
+use elinor::statistical_tests::TwoWayAnovaWithoutReplication;
+use elinor::statistical_tests::WilcoxonSignedRankTest;
+use elinor::Metric;
+use elinor::PredRelStoreBuilder;
+use elinor::TrueRelStoreBuilder;
+use extendr_api::prelude::*;
+
+fn to_r_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::Other(e.to_string())
+}
+
+fn check_lengths(name: &str, lengths: &[usize]) -> Result<()> {
+    if lengths.windows(2).any(|w| w[0] != w[1]) {
+        return Err(Error::Other(format!(
+            "The vectors of {name} must have the same length."
+        )));
+    }
+    Ok(())
+}
+
+fn paired_samples(x: &[f64], y: &[f64]) -> Result<Vec<(f64, f64)>> {
+    check_lengths("paired scores", &[x.len(), y.len()])?;
+    Ok(x.iter().copied().zip(y.iter().copied()).collect())
+}
+
+fn tupled_samples(scores: &[f64], n_systems: i32) -> Result<Vec<Vec<f64>>> {
+    if n_systems <= 0 || scores.len() % n_systems as usize != 0 {
+        return Err(Error::Other(
+            "The scores must be a matrix of topics by systems.".to_string(),
+        ));
+    }
+    Ok(scores
+        .chunks(n_systems as usize)
+        .map(|row| row.to_vec())
+        .collect())
+}
+
+fn alternative(alternative: &str) -> Result<Alternative> {
+    alternative.parse().map_err(to_r_error)
+}
+
+fn random_state(random_state: f64) -> Result<u64> {
+    if random_state < 0.0 || random_state.fract() != 0.0 {
+        return Err(Error::Other(
+            "The random state must be a non-negative integer.".to_string(),
+        ));
+    }
+    Ok(random_state as u64)
+}
+
+fn positive(name: &str, value: i32) -> Result<usize> {
+    if value <= 0 {
+        return Err(Error::Other(format!("The {name} must be positive.")));
+    }
+    Ok(value as usize)
+}
+
+/// Evaluates the predicted relevance scores against the true relevance scores.
+///
+/// Returns a list of the query ids and the per-query scores of the metric.
+#[allow(clippy::too_many_arguments)]
+#[extendr]
+fn evaluate_impl(
+    true_query_ids: Vec<String>,
+    true_doc_ids: Vec<String>,
+    true_scores: Vec<i32>,
+    pred_query_ids: Vec<String>,
+    pred_doc_ids: Vec<String>,
+    pred_scores: Vec<f64>,
+    metric: &str,
+) -> Result<List> {
+    check_lengths(
+        "true relevance",
+        &[true_query_ids.len(), true_doc_ids.len(), true_scores.len()],
+    )?;
+    check_lengths(
+        "predicted relevance",
+        &[pred_query_ids.len(), pred_doc_ids.len(), pred_scores.len()],
+    )?;
+    let metric: Metric = metric.parse().map_err(to_r_error)?;
+
+    let mut b = TrueRelStoreBuilder::new();
+    for ((query_id, doc_id), score) in true_query_ids
+        .into_iter()
+        .zip(true_doc_ids)
+        .zip(true_scores)
+    {
+        let score = u32::try_from(score).map_err(|_| {
+            Error::Other("The true relevance scores must be non-negative.".to_string())
+        })?;
+        b.add_record(query_id, doc_id, score).map_err(to_r_error)?;
+    }
+    let true_rels = b.build();
+
+    let mut b = PredRelStoreBuilder::new();
+    for ((query_id, doc_id), score) in pred_query_ids
+        .into_iter()
+        .zip(pred_doc_ids)
+        .zip(pred_scores)
+    {
+        b.add_record(query_id, doc_id, score.into())
+            .map_err(to_r_error)?;
+    }
+    let pred_rels = b.build();
+
+    let result = elinor::evaluate(&true_rels, &pred_rels, metric).map_err(to_r_error)?;
+    let (query_ids, scores): (Vec<String>, Vec<f64>) = result
+        .scores()
+        .iter()
+        .map(|(query_id, &score)| (query_id.clone(), score))
+        .unzip();
+    Ok(list!(query_id = query_ids, score = scores))
+}
+
+/// Performs a paired Student's t-test.
+#[extendr]
+fn student_t_test_impl(
+    x: &[f64],
+    y: &[f64],
+    alternative: &str,
+    significance_level: f64,
+) -> Result<List> {
+    let result = StudentTTester::new()
+        .with_alternative(self::alternative(alternative)?)
+        .test(paired_samples(x, y)?)
+        .map_err(to_r_error)?;
+    let (ci_btm, ci_top) = result
+        .confidence_interval(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_topics = result.n_topics() as f64,
+        mean = result.mean(),
+        variance = result.variance(),
+        effect_size = result.effect_size(),
+        t_stat = result.t_stat(),
+        p_value = result.p_value(),
+        conf_int = vec![ci_btm, ci_top]
+    ))
+}
+
+/// Performs a paired bootstrap test.
+#[extendr]
+fn bootstrap_test_impl(
+    x: &[f64],
+    y: &[f64],
+    alternative: &str,
+    n_resamples: i32,
+    random_state: f64,
+    significance_level: f64,
+) -> Result<List> {
+    let result = BootstrapTester::new()
+        .with_alternative(self::alternative(alternative)?)
+        .with_n_resamples(positive("number of resamples", n_resamples)?)
+        .with_random_state(self::random_state(random_state)?)
+        .test(paired_samples(x, y)?)
+        .map_err(to_r_error)?;
+    let (ci_btm, ci_top) = result
+        .confidence_interval(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_topics = result.n_topics() as f64,
+        mean = result.mean(),
+        variance = result.variance(),
+        effect_size = result.effect_size(),
+        p_value = result.p_value(),
+        conf_int = vec![ci_btm, ci_top]
+    ))
+}
+
+/// Performs a paired Fisher's randomization test.
+#[extendr]
+fn fisher_randomization_test_impl(
+    x: &[f64],
+    y: &[f64],
+    alternative: &str,
+    n_iters: i32,
+    random_state: f64,
+    significance_level: f64,
+) -> Result<List> {
+    let result = FisherRandomizationTester::new()
+        .with_alternative(self::alternative(alternative)?)
+        .with_n_iters(positive("number of iterations", n_iters)?)
+        .with_random_state(self::random_state(random_state)?)
+        .test(paired_samples(x, y)?)
+        .map_err(to_r_error)?;
+    let (ci_btm, ci_top) = result
+        .confidence_interval(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_topics = result.n_topics() as f64,
+        mean = result.mean(),
+        effect_size = result.effect_size(),
+        p_value = result.p_value(),
+        conf_int = vec![ci_btm, ci_top]
+    ))
+}
+
+/// Performs the Wilcoxon signed-rank test.
+#[extendr]
+fn wilcoxon_signed_rank_test_impl(x: &[f64], y: &[f64], significance_level: f64) -> Result<List> {
+    let result =
+        WilcoxonSignedRankTest::from_paired_samples(paired_samples(x, y)?).map_err(to_r_error)?;
+    let (ci_btm, ci_top) = result
+        .confidence_interval(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_topics = result.n_topics() as f64,
+        n_nonzero = result.n_nonzero() as f64,
+        w_stat = result.w_stat(),
+        z_stat = result.z_stat(),
+        p_value = result.p_value(),
+        is_exact = result.is_exact(),
+        effect_size = result.effect_size(),
+        estimate = result.hodges_lehmann_estimate(),
+        conf_int = vec![ci_btm, ci_top]
+    ))
+}
+
+/// Performs the sign test.
+#[extendr]
+fn sign_test_impl(x: &[f64], y: &[f64], significance_level: f64) -> Result<List> {
+    let result = SignTest::from_paired_samples(paired_samples(x, y)?).map_err(to_r_error)?;
+    let (ci_btm, ci_top) = result
+        .confidence_interval(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_topics = result.n_topics() as f64,
+        n_wins = result.n_wins() as f64,
+        n_losses = result.n_losses() as f64,
+        n_ties = result.n_ties() as f64,
+        p_value = result.p_value(),
+        effect_size = result.effect_size(),
+        conf_int = vec![ci_btm, ci_top]
+    ))
+}
+
+/// Performs two-way ANOVA without replication on the topic-major scores of the systems.
+#[extendr]
+fn two_way_anova_without_replication_impl(
+    scores: &[f64],
+    n_systems: i32,
+    significance_level: f64,
+) -> Result<List> {
+    let samples = tupled_samples(scores, n_systems)?;
+    let result = TwoWayAnovaWithoutReplication::from_tupled_samples(samples, n_systems as usize)
+        .map_err(to_r_error)?;
+    let moe = result
+        .margin_of_error(significance_level)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_systems = result.n_systems() as f64,
+        n_topics = result.n_topics() as f64,
+        system_means = result.system_means(),
+        topic_means = result.topic_means(),
+        between_system_variance = result.between_system_variance(),
+        between_topic_variance = result.between_topic_variance(),
+        residual_variance = result.residual_variance(),
+        between_system_f_stat = result.between_system_f_stat(),
+        between_topic_f_stat = result.between_topic_f_stat(),
+        between_system_p_value = result.between_system_p_value(),
+        between_topic_p_value = result.between_topic_p_value(),
+        margin_of_error = moe
+    ))
+}
+
+/// Computes the effect sizes of the Tukey HSD test on the topic-major scores of the systems.
+///
+/// The effect sizes are returned in the row-major order of the systems-by-systems matrix.
+#[extendr]
+fn tukey_hsd_test_impl(scores: &[f64], n_systems: i32) -> Result<List> {
+    let samples = tupled_samples(scores, n_systems)?;
+    let result =
+        TukeyHsdTest::from_tupled_samples(samples, n_systems as usize).map_err(to_r_error)?;
+    Ok(list!(
+        n_systems = result.n_systems() as f64,
+        n_topics = result.n_topics() as f64,
+        effect_sizes = result.effect_sizes().concat()
+    ))
+}
+
+/// Performs the randomized Tukey HSD test on the topic-major scores of the systems.
+///
+/// The p-values and effect sizes are returned in the row-major order of the systems-by-systems matrices.
+#[extendr]
+fn randomized_tukey_hsd_test_impl(
+    scores: &[f64],
+    n_systems: i32,
+    n_iters: i32,
+    random_state: f64,
+) -> Result<List> {
+    let samples = tupled_samples(scores, n_systems)?;
+    let result = RandomizedTukeyHsdTester::new(n_systems as usize)
+        .with_n_iters(positive("number of iterations", n_iters)?)
+        .with_random_state(self::random_state(random_state)?)
+        .test(samples)
+        .map_err(to_r_error)?;
+    Ok(list!(
+        n_systems = result.n_systems() as f64,
+        n_topics = result.n_topics() as f64,
+        p_values = result.p_values().concat(),
+        effect_sizes = result.effect_sizes().concat()
+    ))
+}
+
+extendr_module! {
+    mod elinor;
+    fn evaluate_impl;
+    fn student_t_test_impl;
+    fn bootstrap_test_impl;
+    fn fisher_randomization_test_impl;
+    fn wilcoxon_signed_rank_test_impl;
+    fn sign_test_impl;
+    fn two_way_anova_without_replication_impl;
+    fn tukey_hsd_test_impl;
+    fn randomized_tukey_hsd_test_impl;
+}
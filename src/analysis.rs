@@ -0,0 +1,232 @@
+//! Per-topic failure analysis across systems.
+//!
+//! Besides mean scores, IR papers often report which topics are hard or easy for all the systems
+//! and how often a system beats another one topic by topic.
+//! This module provides [`analyze_topics`] to compute, from evaluations of several systems
+//! over the same topics, the mean and variance of the scores across the systems for each topic,
+//! and the win/loss/tie counts of each pair of systems.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use approx::assert_abs_diff_eq;
+//! use elinor::{Evaluation, Metric};
+//! use elinor::analysis::analyze_topics;
+//!
+//! let metric = Metric::AP { k: 0 };
+//! let evaluations = [
+//!     Evaluation::from_scores(metric, [("q_1", 0.9), ("q_2", 0.1), ("q_3", 0.5)].into()),
+//!     Evaluation::from_scores(metric, [("q_1", 0.7), ("q_2", 0.3), ("q_3", 0.5)].into()),
+//! ];
+//! let analysis = analyze_topics(&evaluations)?;
+//!
+//! let hardest = analysis.hardest(1);
+//! assert_eq!(hardest[0].topic_id(), &"q_2");
+//! assert_abs_diff_eq!(hardest[0].mean(), 0.2);
+//! assert_abs_diff_eq!(hardest[0].variance(), 0.02);
+//! assert_eq!(analysis.easiest(1)[0].topic_id(), &"q_1");
+//!
+//! // System 0 wins q_1, loses q_2, and ties q_3 against system 1.
+//! assert_eq!(analysis.win_loss_tie(0, 1), (1, 1, 1));
+//! # Ok(())
+//! # }
+//! ```
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::tuples_from_evaluations;
+use crate::Evaluation;
+
+/// Difficulty of a topic, i.e., statistics of the scores across the systems.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicDifficulty<K> {
+    topic_id: K,
+    mean: f64,
+    variance: f64,
+}
+
+impl<K> TopicDifficulty<K> {
+    /// Topic id.
+    pub const fn topic_id(&self) -> &K {
+        &self.topic_id
+    }
+
+    /// Mean score across the systems, where a smaller value means a harder topic.
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased variance of the scores across the systems,
+    /// where a larger value means a topic discriminating the systems more.
+    pub const fn variance(&self) -> f64 {
+        self.variance
+    }
+}
+
+/// Result of [`analyze_topics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopicAnalysis<K> {
+    topics: Vec<TopicDifficulty<K>>,
+    n_wins: Vec<Vec<usize>>,
+}
+
+impl<K> TopicAnalysis<K> {
+    /// Number of systems.
+    pub fn n_systems(&self) -> usize {
+        self.n_wins.len()
+    }
+
+    /// Number of topics.
+    pub fn n_topics(&self) -> usize {
+        self.topics.len()
+    }
+
+    /// Difficulties of the topics, sorted by the topic ids.
+    pub fn topics(&self) -> &[TopicDifficulty<K>] {
+        &self.topics
+    }
+
+    /// Returns the `n` hardest topics in ascending order of the mean scores,
+    /// where ties are broken by the topic ids.
+    pub fn hardest(&self, n: usize) -> Vec<&TopicDifficulty<K>> {
+        let mut topics = self.topics.iter().collect::<Vec<_>>();
+        topics.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        topics.truncate(n);
+        topics
+    }
+
+    /// Returns the `n` easiest topics in descending order of the mean scores,
+    /// where ties are broken by the topic ids.
+    pub fn easiest(&self, n: usize) -> Vec<&TopicDifficulty<K>> {
+        let mut topics = self.topics.iter().collect::<Vec<_>>();
+        topics.sort_by(|a, b| b.mean.total_cmp(&a.mean));
+        topics.truncate(n);
+        topics
+    }
+
+    /// Returns the numbers of topics where the $`i`$-th system wins, loses, and ties
+    /// against the $`j`$-th system.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` or `j` is out of bounds.
+    pub fn win_loss_tie(&self, i: usize, j: usize) -> (usize, usize, usize) {
+        let n_wins = self.n_wins[i][j];
+        let n_losses = self.n_wins[j][i];
+        (n_wins, n_losses, self.topics.len() - n_wins - n_losses)
+    }
+}
+
+/// Analyzes the topics with the evaluations of systems, paired by the topic ids.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the evaluations do not have at least two systems.
+/// * [`ElinorError::InvalidArgument`] if the evaluations have different sets of topic ids.
+pub fn analyze_topics<'a, I, K>(evaluations: I) -> Result<TopicAnalysis<K>>
+where
+    I: IntoIterator<Item = &'a Evaluation<K>>,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    let evaluations = evaluations.into_iter().collect::<Vec<_>>();
+    let n_systems = evaluations.len();
+    if n_systems < 2 {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least two systems.".to_string(),
+        ));
+    }
+    let tuples = tuples_from_evaluations(evaluations.iter().copied())?;
+    let topic_ids = evaluations[0].scores().keys();
+
+    let mut topics = vec![];
+    let mut n_wins = vec![vec![0; n_systems]; n_systems];
+    for (topic_id, tuple) in topic_ids.zip(&tuples) {
+        let mean = tuple.iter().sum::<f64>() / n_systems as f64;
+        let variance =
+            tuple.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n_systems - 1) as f64;
+        topics.push(TopicDifficulty {
+            topic_id: topic_id.clone(),
+            mean,
+            variance,
+        });
+        for i in 0..n_systems {
+            for j in 0..n_systems {
+                if tuple[i] > tuple[j] {
+                    n_wins[i][j] += 1;
+                }
+            }
+        }
+    }
+    Ok(TopicAnalysis { topics, n_wins })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metric;
+    use approx::assert_abs_diff_eq;
+
+    fn example_evaluations() -> Vec<Evaluation<&'static str>> {
+        let metric = Metric::AP { k: 0 };
+        vec![
+            Evaluation::from_scores(
+                metric,
+                [("a", 0.6), ("b", 0.2), ("c", 0.9), ("d", 0.4)].into(),
+            ),
+            Evaluation::from_scores(
+                metric,
+                [("a", 0.4), ("b", 0.2), ("c", 0.8), ("d", 0.4)].into(),
+            ),
+            Evaluation::from_scores(
+                metric,
+                [("a", 0.2), ("b", 0.0), ("c", 1.0), ("d", 0.7)].into(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_analyze_topics() {
+        let analysis = analyze_topics(&example_evaluations()).unwrap();
+        assert_eq!(analysis.n_systems(), 3);
+        assert_eq!(analysis.n_topics(), 4);
+
+        let topics = analysis.topics();
+        assert_eq!(topics[0].topic_id(), &"a");
+        assert_abs_diff_eq!(topics[0].mean(), 0.4);
+        assert_abs_diff_eq!(topics[0].variance(), 0.04, epsilon = 1e-10);
+        assert_eq!(topics[3].topic_id(), &"d");
+        assert_abs_diff_eq!(topics[3].mean(), 0.5);
+        assert_abs_diff_eq!(topics[3].variance(), 0.03, epsilon = 1e-10);
+
+        let hardest = analysis.hardest(2);
+        assert_eq!(
+            hardest.iter().map(|t| *t.topic_id()).collect::<Vec<_>>(),
+            ["b", "a"]
+        );
+        let easiest = analysis.easiest(10);
+        assert_eq!(
+            easiest.iter().map(|t| *t.topic_id()).collect::<Vec<_>>(),
+            ["c", "d", "a", "b"]
+        );
+
+        assert_eq!(analysis.win_loss_tie(0, 1), (2, 0, 2));
+        assert_eq!(analysis.win_loss_tie(1, 0), (0, 2, 2));
+        assert_eq!(analysis.win_loss_tie(0, 2), (2, 2, 0));
+        assert_eq!(analysis.win_loss_tie(2, 2), (0, 0, 4));
+    }
+
+    #[test]
+    fn test_analyze_topics_invalid() {
+        let evaluations = example_evaluations();
+        assert_eq!(
+            analyze_topics(&evaluations[..1]),
+            Err(ElinorError::InvalidArgument(
+                "The input must have at least two systems.".to_string()
+            ))
+        );
+
+        let metric = Metric::AP { k: 0 };
+        let other = Evaluation::from_scores(metric, [("a", 0.5), ("e", 0.5)].into());
+        assert!(analyze_topics([&evaluations[0], &other]).is_err());
+    }
+}
@@ -0,0 +1,404 @@
+//! Loading relevance stores from Polars DataFrames and Parquet files.
+use std::io::Read;
+
+use polars::datatypes::DataType;
+use polars::frame::DataFrame;
+use polars::prelude::PlSmallStr;
+use polars::series::Series;
+use polars_parquet::read;
+use polars_utils::mmap::MemReader;
+use polars_utils::mmap::MemSlice;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::PredRelStore;
+use crate::PredRelStoreBuilder;
+use crate::PredScore;
+use crate::TrueRelStore;
+use crate::TrueRelStoreBuilder;
+use crate::TrueScore;
+
+/// Names of the columns of relevance scores.
+const COLUMNS: [&str; 3] = ["query_id", "doc_id", "score"];
+
+impl TrueRelStore<String> {
+    /// Creates an instance from a DataFrame with the columns of `query_id`, `doc_id`, and `score`,
+    /// where the ids are strings or integers and the scores are non-negative integers.
+    ///
+    /// Integer ids are converted into their decimal strings.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if a column is missing, of an unsupported type, or has null values.
+    /// * [`ElinorError::DuplicateEntry`] if a query-document pair appears more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::TrueRelStore;
+    /// use polars::df;
+    ///
+    /// let df = df!(
+    ///     "query_id" => ["q_1", "q_1", "q_2"],
+    ///     "doc_id" => [1, 2, 1],
+    ///     "score" => [1, 0, 2],
+    /// )?;
+    /// let true_rels = TrueRelStore::from_dataframe(&df)?;
+    /// assert_eq!(true_rels.n_queries(), 2);
+    /// assert_eq!(true_rels.get_score("q_2", "1"), Some(&2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dataframe(df: &DataFrame) -> Result<Self> {
+        let mut b = TrueRelStoreBuilder::new();
+        add_dataframe(df, true_scores, |query_id, doc_id, score| {
+            b.add_record(query_id, doc_id, score)
+        })?;
+        Ok(b.build())
+    }
+
+    /// Creates an instance from a Parquet file with the same columns as [`Self::from_dataframe`].
+    ///
+    /// The file is read into memory, and only the three columns are decoded.
+    /// The supported compressions are gzip, lz4, and zstd.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if the file cannot be read as Parquet.
+    /// * See [`Self::from_dataframe`] for the other errors.
+    pub fn from_parquet<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut b = TrueRelStoreBuilder::new();
+        for df in read_parquet(reader)? {
+            add_dataframe(&df, true_scores, |query_id, doc_id, score| {
+                b.add_record(query_id, doc_id, score)
+            })?;
+        }
+        Ok(b.build())
+    }
+}
+
+impl PredRelStore<String> {
+    /// Creates an instance from a DataFrame with the columns of `query_id`, `doc_id`, and `score`,
+    /// where the ids are strings or integers and the scores are numbers.
+    ///
+    /// Integer ids are converted into their decimal strings.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if a column is missing, of an unsupported type, or has null values.
+    /// * [`ElinorError::DuplicateEntry`] if a query-document pair appears more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::PredRelStore;
+    /// use polars::df;
+    ///
+    /// let df = df!(
+    ///     "query_id" => ["q_1", "q_1", "q_2"],
+    ///     "doc_id" => ["d_1", "d_2", "d_1"],
+    ///     "score" => [0.5, 0.4, 0.3],
+    /// )?;
+    /// let pred_rels = PredRelStore::from_dataframe(&df)?;
+    /// assert_eq!(pred_rels.n_queries(), 2);
+    /// assert_eq!(pred_rels.get_score("q_1", "d_2"), Some(&0.4.into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dataframe(df: &DataFrame) -> Result<Self> {
+        let mut b = PredRelStoreBuilder::new();
+        add_dataframe(df, pred_scores, |query_id, doc_id, score| {
+            b.add_record(query_id, doc_id, score)
+        })?;
+        Ok(b.build())
+    }
+
+    /// Creates an instance from a Parquet file with the same columns as [`Self::from_dataframe`],
+    /// such as a large run of tens of millions of rows.
+    ///
+    /// The file is read into memory, and only the three columns are decoded.
+    /// The supported compressions are gzip, lz4, and zstd.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if the file cannot be read as Parquet.
+    /// * See [`Self::from_dataframe`] for the other errors.
+    pub fn from_parquet<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut b = PredRelStoreBuilder::new();
+        for df in read_parquet(reader)? {
+            add_dataframe(&df, pred_scores, |query_id, doc_id, score| {
+                b.add_record(query_id, doc_id, score)
+            })?;
+        }
+        Ok(b.build())
+    }
+}
+
+/// Adds the rows of the DataFrame with the scores converted by `scores`.
+fn add_dataframe<T, S, F>(df: &DataFrame, scores: S, mut add: F) -> Result<()>
+where
+    S: Fn(&Series) -> Result<Vec<T>>,
+    F: FnMut(String, String, T) -> Result<()>,
+{
+    let query_ids = ids(column(df, COLUMNS[0])?)?;
+    let doc_ids = ids(column(df, COLUMNS[1])?)?;
+    let scores = scores(column(df, COLUMNS[2])?)?;
+    for ((query_id, doc_id), score) in query_ids.into_iter().zip(doc_ids).zip(scores) {
+        add(query_id, doc_id, score)?;
+    }
+    Ok(())
+}
+
+fn column<'a>(df: &'a DataFrame, name: &str) -> Result<&'a Series> {
+    df.column(name)
+        .map_err(|_| ElinorError::InvalidFormat(format!("The column {name} is missing")))
+}
+
+/// Returns the ids in strings, converting integer ids into their decimal strings.
+fn ids(series: &Series) -> Result<Vec<String>> {
+    let dtype = series.dtype();
+    if !(dtype.is_integer() || matches!(dtype, DataType::String)) {
+        return Err(unsupported_type(series));
+    }
+    let strings = series
+        .cast(&DataType::String)
+        .map_err(|e| ElinorError::InvalidFormat(e.to_string()))?;
+    strings
+        .str()
+        .map_err(|e| ElinorError::InvalidFormat(e.to_string()))?
+        .into_iter()
+        .map(|id| id.map(str::to_string).ok_or_else(|| null_value(series)))
+        .collect()
+}
+
+fn true_scores(series: &Series) -> Result<Vec<TrueScore>> {
+    if !series.dtype().is_integer() {
+        return Err(unsupported_type(series));
+    }
+    // The strict cast fails for negative or too large scores.
+    let scores = series.strict_cast(&DataType::UInt32).map_err(|_| {
+        ElinorError::InvalidFormat(format!(
+            "The column {} must have non-negative integers that fit in u32",
+            series.name()
+        ))
+    })?;
+    scores
+        .u32()
+        .map_err(|e| ElinorError::InvalidFormat(e.to_string()))?
+        .into_iter()
+        .map(|score| score.ok_or_else(|| null_value(series)))
+        .collect()
+}
+
+fn pred_scores(series: &Series) -> Result<Vec<PredScore>> {
+    if !series.dtype().is_numeric() {
+        return Err(unsupported_type(series));
+    }
+    let scores = series
+        .cast(&DataType::Float64)
+        .map_err(|e| ElinorError::InvalidFormat(e.to_string()))?;
+    scores
+        .f64()
+        .map_err(|e| ElinorError::InvalidFormat(e.to_string()))?
+        .into_iter()
+        .map(|score| score.map(PredScore::from).ok_or_else(|| null_value(series)))
+        .collect()
+}
+
+fn unsupported_type(series: &Series) -> ElinorError {
+    ElinorError::InvalidFormat(format!(
+        "Unsupported type of the column {}: {}",
+        series.name(),
+        series.dtype()
+    ))
+}
+
+fn null_value(series: &Series) -> ElinorError {
+    ElinorError::InvalidFormat(format!(
+        "The column {} must not have null values",
+        series.name()
+    ))
+}
+
+/// Reads the three columns of the Parquet file into a DataFrame for each row group.
+fn read_parquet<R>(mut reader: R) -> Result<Vec<DataFrame>>
+where
+    R: Read,
+{
+    let to_error = |e: &dyn std::fmt::Display| {
+        ElinorError::InvalidFormat(format!("Failed to read the Parquet file: {e}"))
+    };
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes).map_err(|e| to_error(&e))?;
+    let data = MemSlice::from_vec(bytes);
+    let metadata =
+        read::read_metadata(&mut MemReader::new(data.clone())).map_err(|e| to_error(&e))?;
+    let schema = read::infer_schema(&metadata).map_err(|e| to_error(&e))?;
+
+    let mut dfs = vec![];
+    for row_group in &metadata.row_groups {
+        let mut columns = vec![];
+        for name in COLUMNS {
+            let field = schema
+                .get(name)
+                .ok_or_else(|| ElinorError::InvalidFormat(format!("The column {name} is missing")))?
+                .clone();
+            let (pages, types): (Vec<_>, Vec<_>) = row_group
+                .columns_under_root_iter(name)
+                .map(|meta| {
+                    let range = meta.byte_range();
+                    let chunk = data.slice(range.start as usize..range.end as usize);
+                    let pages =
+                        read::PageReader::new(MemReader::new(chunk), meta, vec![], usize::MAX);
+                    (
+                        read::BasicDecompressor::new(pages, vec![]),
+                        &meta.descriptor().descriptor.primitive_type,
+                    )
+                })
+                .unzip();
+            let array =
+                read::column_iter_to_arrays(pages, types, field, None).map_err(|e| to_error(&e))?;
+            let series =
+                Series::try_from((PlSmallStr::from(name), array)).map_err(|e| to_error(&e))?;
+            columns.push(series);
+        }
+        dfs.push(DataFrame::new(columns).map_err(|e| to_error(&e))?);
+    }
+    Ok(dfs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::df;
+    use polars::prelude::CompatLevel;
+    use polars::prelude::SchemaExt;
+    use polars_parquet::write::CompressionOptions;
+    use polars_parquet::write::Encoding;
+    use polars_parquet::write::FileWriter;
+    use polars_parquet::write::RowGroupIterator;
+    use polars_parquet::write::StatisticsOptions;
+    use polars_parquet::write::Version;
+    use polars_parquet::write::WriteOptions;
+
+    fn write_parquet(df: &DataFrame, compression: CompressionOptions) -> Vec<u8> {
+        let schema = df.schema().to_arrow(CompatLevel::newest());
+        let options = WriteOptions {
+            statistics: StatisticsOptions::empty(),
+            compression,
+            version: Version::V2,
+            data_page_size: None,
+        };
+        let encodings = schema
+            .iter_values()
+            .map(|field| polars_parquet::write::transverse(&field.dtype, |_| Encoding::Plain))
+            .collect();
+        let chunks = df
+            .iter_chunks(CompatLevel::newest(), false)
+            .map(Ok)
+            .collect::<Vec<_>>();
+        let row_groups =
+            RowGroupIterator::try_new(chunks.into_iter(), &schema, options, encodings).unwrap();
+        let mut bytes = vec![];
+        let mut writer = FileWriter::try_new(&mut bytes, schema, options).unwrap();
+        for row_group in row_groups {
+            writer.write(row_group.unwrap()).unwrap();
+        }
+        writer.end(None).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_pred_rel_store_from_parquet() {
+        let df = df!(
+            "query_id" => [1i64, 1, 2],
+            "doc_id" => ["d_1", "d_2", "d_1"],
+            "score" => [0.5f32, 0.25, 1.0],
+            "rank" => [1u32, 2, 1],
+        )
+        .unwrap();
+        for compression in [
+            CompressionOptions::Uncompressed,
+            CompressionOptions::Gzip(None),
+            CompressionOptions::Lz4Raw,
+            CompressionOptions::Zstd(None),
+        ] {
+            let bytes = write_parquet(&df, compression);
+            let pred_rels = PredRelStore::from_parquet(bytes.as_slice()).unwrap();
+            assert_eq!(
+                pred_rels.records(),
+                PredRelStore::from_dataframe(&df).unwrap().records()
+            );
+            assert_eq!(pred_rels.n_docs(), 3);
+            assert_eq!(pred_rels.get_score("1", "d_2"), Some(&0.25.into()));
+        }
+    }
+
+    #[test]
+    fn test_true_rel_store_from_parquet() {
+        let df = df!(
+            "query_id" => ["q_1", "q_1", "q_2"],
+            "doc_id" => [10u64, 20, 10],
+            "score" => [1i32, 0, 2],
+        )
+        .unwrap();
+        let bytes = write_parquet(&df, CompressionOptions::Zstd(None));
+        let true_rels = TrueRelStore::from_parquet(bytes.as_slice()).unwrap();
+        assert_eq!(true_rels.n_queries(), 2);
+        assert_eq!(true_rels.get_score("q_2", "10"), Some(&2));
+    }
+
+    #[test]
+    fn test_from_dataframe_invalid() {
+        let df = df!("query_id" => ["q_1"], "score" => [1]).unwrap();
+        assert_eq!(
+            TrueRelStore::from_dataframe(&df).err(),
+            Some(ElinorError::InvalidFormat(
+                "The column doc_id is missing".to_string()
+            ))
+        );
+        let df = df!("query_id" => ["q_1"], "doc_id" => ["d_1"], "score" => [-1]).unwrap();
+        assert_eq!(
+            TrueRelStore::from_dataframe(&df).err(),
+            Some(ElinorError::InvalidFormat(
+                "The column score must have non-negative integers that fit in u32".to_string()
+            ))
+        );
+        let df = df!("query_id" => ["q_1"], "doc_id" => ["d_1"], "score" => [0.5]).unwrap();
+        assert_eq!(
+            TrueRelStore::from_dataframe(&df).err(),
+            Some(ElinorError::InvalidFormat(
+                "Unsupported type of the column score: f64".to_string()
+            ))
+        );
+        let df = df!("query_id" => ["q_1"], "doc_id" => [None::<&str>], "score" => [0.5]).unwrap();
+        assert_eq!(
+            PredRelStore::from_dataframe(&df).err(),
+            Some(ElinorError::InvalidFormat(
+                "The column doc_id must not have null values".to_string()
+            ))
+        );
+        let df = df!(
+            "query_id" => ["q_1", "q_1"],
+            "doc_id" => ["d_1", "d_1"],
+            "score" => [0.5, 0.4],
+        )
+        .unwrap();
+        assert!(matches!(
+            PredRelStore::from_dataframe(&df),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+        assert!(matches!(
+            PredRelStore::from_parquet(&b"not parquet"[..]),
+            Err(ElinorError::InvalidFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,384 @@
+//! Assessor simulation by random perturbation of true relevance judgments.
+//!
+//! Human assessors do not always agree with each other.
+//! This module simulates such assessor noise by perturbing the grades of true relevance scores
+//! according to a confusion matrix, and reports how sensitive the evaluation results
+//! (i.e., the mean scores and the pairwise significance between systems) are to the noise.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::assessor_simulation::AssessorSimulator;
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_1", "d_2", 0)?;
+//! b.add_record("q_2", "d_1", 1)?;
+//! b.add_record("q_2", "d_2", 0)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_1", 0.5.into())?;
+//! let pred_rels = b.build();
+//!
+//! // Relevant documents are judged as non-relevant with a probability of 0.2, and vice versa.
+//! let simulator = AssessorSimulator::new(vec![vec![0.8, 0.2], vec![0.2, 0.8]])?
+//!     .with_n_trials(100)
+//!     .with_random_state(42);
+//! let result = simulator.simulate(&true_rels, [&pred_rels], Metric::Precision { k: 1 })?;
+//! assert_eq!(result.original_means(), vec![1.0]);
+//! assert_eq!(result.trial_means()[0].len(), 100);
+//! assert!(result.std_devs()[0] > 0.0);
+//! # Ok(())
+//! # }
+//! ```
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::StudentTTest;
+use crate::Evaluation;
+use crate::Metric;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+use crate::TrueRelStoreBuilder;
+use crate::TrueScore;
+
+/// Simulator of assessor noise.
+///
+/// # Default parameters
+///
+/// * `n_trials`: `1000`
+/// * `significance_level`: `0.05`
+/// * `random_state`: `None`
+#[derive(Debug, Clone)]
+pub struct AssessorSimulator {
+    confusion_matrix: Vec<Vec<f64>>,
+    n_trials: usize,
+    significance_level: f64,
+    random_state: Option<u64>,
+}
+
+impl AssessorSimulator {
+    /// Creates a new simulator with a confusion matrix of grades.
+    ///
+    /// The $`(i, j)`$-th element is the probability that a document of grade $`i`$
+    /// is judged as grade $`j`$. Grades out of the matrix are never perturbed.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the matrix is not square.
+    /// * [`ElinorError::InvalidArgument`] if a row is not a probability distribution.
+    pub fn new(confusion_matrix: Vec<Vec<f64>>) -> Result<Self> {
+        let n_grades = confusion_matrix.len();
+        for row in &confusion_matrix {
+            if row.len() != n_grades {
+                return Err(ElinorError::InvalidArgument(
+                    "The confusion matrix must be square.".to_string(),
+                ));
+            }
+            if row.iter().any(|&p| !(0.0..=1.0).contains(&p))
+                || (row.iter().sum::<f64>() - 1.0).abs() > 1e-6
+            {
+                return Err(ElinorError::InvalidArgument(
+                    "Each row of the confusion matrix must be a probability distribution."
+                        .to_string(),
+                ));
+            }
+        }
+        Ok(Self {
+            confusion_matrix,
+            n_trials: 1000,
+            significance_level: 0.05,
+            random_state: None,
+        })
+    }
+
+    /// Sets the number of trials.
+    ///
+    /// If the input is less than `1`, it is modified to `1`.
+    pub fn with_n_trials(mut self, n_trials: usize) -> Self {
+        self.n_trials = n_trials.max(1);
+        self
+    }
+
+    /// Sets the significance level used to judge the pairwise significance
+    /// with the two-sided paired Student's t-test.
+    pub const fn with_significance_level(mut self, significance_level: f64) -> Self {
+        self.significance_level = significance_level;
+        self
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Perturbs the true relevance scores once.
+    pub fn perturb<K, R>(&self, true_rels: &TrueRelStore<K>, rng: &mut R) -> TrueRelStore<K>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+        R: Rng,
+    {
+        let mut b = TrueRelStoreBuilder::new();
+        for record in true_rels.records() {
+            let score = self.perturb_grade(record.score, rng);
+            // The pair is unique because it comes from the store.
+            b.add_record(record.query_id, record.doc_id, score).unwrap();
+        }
+        b.build()
+    }
+
+    fn perturb_grade<R: Rng>(&self, grade: TrueScore, rng: &mut R) -> TrueScore {
+        let Some(row) = self.confusion_matrix.get(grade as usize) else {
+            return grade;
+        };
+        let x = rng.gen::<f64>();
+        let mut cum = 0.0;
+        for (j, &p) in row.iter().enumerate() {
+            cum += p;
+            if x < cum {
+                return j as TrueScore;
+            }
+        }
+        // Fallback for rounding errors.
+        grade
+    }
+
+    /// Evaluates systems with perturbed true relevance scores repeatedly.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if `pred_rels` is empty.
+    ///
+    /// See [`metrics::compute_metric`](crate::metrics::compute_metric) for the other possible errors.
+    pub fn simulate<'a, I, K>(
+        &self,
+        true_rels: &TrueRelStore<K>,
+        pred_rels: I,
+        metric: Metric,
+    ) -> Result<AssessorSimulation>
+    where
+        I: IntoIterator<Item = &'a PredRelStore<K>>,
+        K: Clone + Eq + Ord + std::fmt::Display + 'a,
+    {
+        let pred_rels = pred_rels.into_iter().collect::<Vec<_>>();
+        if pred_rels.is_empty() {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least one system.".to_string(),
+            ));
+        }
+        let n_systems = pred_rels.len();
+
+        // Prepare the random number generator.
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+
+        let original = pred_rels
+            .iter()
+            .map(|p| crate::evaluate(true_rels, p, metric))
+            .collect::<Result<Vec<_>>>()?;
+        let original_significance = self.pairwise_significance(&original)?;
+
+        let mut trial_means = vec![Vec::with_capacity(self.n_trials); n_systems];
+        let mut agreements = vec![vec![0_usize; n_systems]; n_systems];
+        for _ in 0..self.n_trials {
+            let perturbed = self.perturb(true_rels, &mut rng);
+            let evaluations = pred_rels
+                .iter()
+                .map(|p| crate::evaluate(&perturbed, p, metric))
+                .collect::<Result<Vec<_>>>()?;
+            for (i, evaluation) in evaluations.iter().enumerate() {
+                trial_means[i].push(evaluation.mean());
+            }
+            let significance = self.pairwise_significance(&evaluations)?;
+            for i in 0..n_systems {
+                for j in 0..n_systems {
+                    if significance[i][j] == original_significance[i][j] {
+                        agreements[i][j] += 1;
+                    }
+                }
+            }
+        }
+
+        let significance_agreements = agreements
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|c| c as f64 / self.n_trials as f64)
+                    .collect()
+            })
+            .collect();
+
+        Ok(AssessorSimulation {
+            n_trials: self.n_trials,
+            random_state,
+            original_means: original.iter().map(|e| e.mean()).collect(),
+            trial_means,
+            significance_agreements,
+        })
+    }
+
+    /// Judges the significance for all pairs of systems.
+    /// If the t-test is uncomputable, the pair is regarded as not significant.
+    fn pairwise_significance<K>(&self, evaluations: &[Evaluation<K>]) -> Result<Vec<Vec<bool>>>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        let n_systems = evaluations.len();
+        let mut significance = vec![vec![false; n_systems]; n_systems];
+        for i in 0..n_systems {
+            for j in (i + 1)..n_systems {
+                let pairs = pairs_from_evaluations(&evaluations[i], &evaluations[j])?;
+                let is_significant = StudentTTest::from_paired_samples(pairs)
+                    .map_or(false, |stat| stat.p_value() < self.significance_level);
+                significance[i][j] = is_significant;
+                significance[j][i] = is_significant;
+            }
+        }
+        Ok(significance)
+    }
+}
+
+/// Result of the assessor simulation.
+#[derive(Debug, Clone)]
+pub struct AssessorSimulation {
+    n_trials: usize,
+    random_state: u64,
+    original_means: Vec<f64>,
+    trial_means: Vec<Vec<f64>>,
+    significance_agreements: Vec<Vec<f64>>,
+}
+
+impl AssessorSimulation {
+    /// Number of trials.
+    pub const fn n_trials(&self) -> usize {
+        self.n_trials
+    }
+
+    /// Random state.
+    pub const fn random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    /// Mean scores of the systems with the original true relevance scores.
+    pub fn original_means(&self) -> Vec<f64> {
+        self.original_means.clone()
+    }
+
+    /// Mean scores of the systems in each trial,
+    /// returning a matrix of size $`m \times T`$ for $`m`$ systems and $`T`$ trials.
+    pub fn trial_means(&self) -> Vec<Vec<f64>> {
+        self.trial_means.clone()
+    }
+
+    /// Standard deviations of the mean scores across trials for each system.
+    pub fn std_devs(&self) -> Vec<f64> {
+        self.trial_means
+            .iter()
+            .map(|means| {
+                let n = means.len() as f64;
+                let mean = means.iter().sum::<f64>() / n;
+                (means.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / n).sqrt()
+            })
+            .collect()
+    }
+
+    /// Agreement rates of the pairwise significance with the original one,
+    /// returning a matrix of size $`m \times m`$ for $`m`$ systems.
+    ///
+    /// The $`(i, j)`$-th element is the ratio of trials in which the significance
+    /// between the $`i`$-th and $`j`$-th systems is the same as that with the original true relevance scores.
+    /// The diagonal elements are always one.
+    pub fn significance_agreements(&self) -> Vec<Vec<f64>> {
+        self.significance_agreements.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PredRelStoreBuilder;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_assessor_simulator_new_not_square() {
+        let result = AssessorSimulator::new(vec![vec![1.0, 0.0]]);
+        assert_eq!(
+            result.err().map(|e| e.to_string()),
+            Some("The confusion matrix must be square.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_assessor_simulator_new_not_distribution() {
+        let result = AssessorSimulator::new(vec![vec![0.5, 0.0], vec![0.0, 1.0]]);
+        assert_eq!(
+            result.err().map(|e| e.to_string()),
+            Some(
+                "Each row of the confusion matrix must be a probability distribution.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_assessor_simulator_identity() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('a', 'y', 0).unwrap();
+        b.add_record('b', 'x', 2).unwrap();
+        let true_rels = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 0.5.into()).unwrap();
+        b.add_record('b', 'y', 0.5.into()).unwrap();
+        let pred_rels_1 = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'y', 0.5.into()).unwrap();
+        b.add_record('b', 'x', 0.5.into()).unwrap();
+        let pred_rels_2 = b.build();
+
+        // Grade 2 is out of the matrix and never perturbed.
+        let simulator = AssessorSimulator::new(vec![vec![1.0, 0.0], vec![0.0, 1.0]])
+            .unwrap()
+            .with_n_trials(10)
+            .with_random_state(42);
+        let result = simulator
+            .simulate(
+                &true_rels,
+                [&pred_rels_1, &pred_rels_2],
+                Metric::Precision { k: 1 },
+            )
+            .unwrap();
+        assert_eq!(result.n_trials(), 10);
+        assert_eq!(result.random_state(), 42);
+        assert_eq!(result.original_means(), vec![0.5, 0.5]);
+        assert_eq!(result.trial_means(), vec![vec![0.5; 10], vec![0.5; 10]]);
+        assert_relative_eq!(result.std_devs()[0], 0.0);
+        assert_eq!(result.significance_agreements(), vec![vec![1.0; 2]; 2]);
+    }
+
+    #[test]
+    fn test_assessor_simulator_perturb_all() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('a', 'y', 0).unwrap();
+        let true_rels = b.build();
+
+        let simulator = AssessorSimulator::new(vec![vec![0.0, 1.0], vec![1.0, 0.0]]).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let perturbed = simulator.perturb(&true_rels, &mut rng);
+        assert_eq!(perturbed.get_score(&'a', &'x'), Some(&0));
+        assert_eq!(perturbed.get_score(&'a', &'y'), Some(&1));
+    }
+}
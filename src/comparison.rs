@@ -0,0 +1,1152 @@
+//! Comparison of systems with statistical tests.
+//!
+//! This module provides [`compare_systems`] to evaluate systems with several metrics
+//! and to run the statistical tests appropriate to the number of systems at once:
+//!
+//! * For two systems, paired tests ([Student's t-test](crate::statistical_tests::StudentTTest),
+//!   [bootstrap test](crate::statistical_tests::BootstrapTest),
+//!   [Wilcoxon signed-rank test](crate::statistical_tests::WilcoxonSignedRankTest),
+//!   [sign test](crate::statistical_tests::SignTest), and [Fisher's randomization test](crate::statistical_tests::FisherRandomizationTest))
+//!   are run for each metric, and their p-values can be adjusted over the metrics.
+//! * For three or more systems, [two-way ANOVA without replication](crate::statistical_tests::TwoWayAnovaWithoutReplication),
+//!   [Tukey HSD test](crate::statistical_tests::TukeyHsdTest),
+//!   and [randomized Tukey HSD test](crate::statistical_tests::RandomizedTukeyHsdTest) are run for each metric.
+//!
+//! The results are returned as a [`ComparisonReport`],
+//! which is serializable with the `serde` feature.
+//! The JSON format is specified by the versioned JSON Schema
+//! `schemas/comparison-report.v1.json` in the repository,
+//! generated by [`crate::json_schema`].
+//! If the per-query scores are computed beforehand, use [`compare_score_maps`] instead.
+//! The report can be rendered in a custom format with the [`template`] module.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use approx::assert_abs_diff_eq;
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::comparison::{CompareOptions, ComparisonTest};
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_1", "d_2", 1)?;
+//! b.add_record("q_2", "d_1", 1)?;
+//! b.add_record("q_3", "d_2", 1)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_1", "d_2", 0.4.into())?;
+//! b.add_record("q_2", "d_1", 0.5.into())?;
+//! b.add_record("q_3", "d_1", 0.5.into())?;
+//! let pred_rels_a = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_2", 0.5.into())?;
+//! b.add_record("q_3", "d_1", 0.5.into())?;
+//! let pred_rels_b = b.build();
+//!
+//! let options = CompareOptions::new().with_tests(vec![ComparisonTest::StudentT]);
+//! let report = elinor::compare_systems(
+//!     &true_rels,
+//!     &[&pred_rels_a, &pred_rels_b],
+//!     &[Metric::Precision { k: 2 }, Metric::Recall { k: 2 }],
+//!     &options,
+//! )?;
+//! assert_eq!(report.n_systems, 2);
+//! assert_eq!(report.n_topics, 3);
+//!
+//! let comparison = &report.metrics[0];
+//! assert_eq!(comparison.metric, "precision@2");
+//! assert_abs_diff_eq!(comparison.means[0], 0.5);
+//! assert_abs_diff_eq!(comparison.means[1], 1.0 / 6.0);
+//! assert!(comparison.student_t.is_some());
+//! assert!(comparison.bootstrap.is_none());
+//! # Ok(())
+//! # }
+//! ```
+pub mod template;
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::bootstrap_test::BootstrapTester;
+use crate::statistical_tests::corrections::Correction;
+use crate::statistical_tests::effect_sizes::EffectSizeBands;
+use crate::statistical_tests::effect_sizes::EffectSizeLabel;
+use crate::statistical_tests::fisher_randomization_test::FisherRandomizationTester;
+use crate::statistical_tests::pairs_from_maps;
+use crate::statistical_tests::randomized_tukey_hsd_test::RandomizedTukeyHsdTester;
+use crate::statistical_tests::student_t_test::StudentTTester;
+use crate::statistical_tests::tuples_from_maps;
+use crate::statistical_tests::Alternative;
+use crate::statistical_tests::SignTest;
+use crate::statistical_tests::TukeyHsdTest;
+use crate::statistical_tests::TwoWayAnovaWithoutReplication;
+use crate::statistical_tests::WilcoxonSignedRankTest;
+use crate::timing::TimingReport;
+use crate::Metric;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+
+/// Statistical tests run in the comparison.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::comparison::ComparisonTest;
+///
+/// assert_eq!("t".parse(), Ok(ComparisonTest::StudentT));
+/// assert_eq!("randomization".parse(), Ok(ComparisonTest::Randomization));
+/// assert_eq!("hsd".parse(), Ok(ComparisonTest::TukeyHsd));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComparisonTest {
+    /// Student's t-test for two systems.
+    StudentT,
+
+    /// Bootstrap test for two systems.
+    Bootstrap,
+
+    /// Wilcoxon signed-rank test for two systems.
+    Wilcoxon,
+
+    /// Sign test for two systems.
+    Sign,
+
+    /// Randomized (Tukey HSD) test for two or more systems.
+    Randomization,
+
+    /// Two-way ANOVA without replication for three or more systems.
+    Anova,
+
+    /// Tukey HSD test for three or more systems.
+    TukeyHsd,
+}
+
+impl ComparisonTest {
+    /// All the tests.
+    pub const ALL: [Self; 7] = [
+        Self::StudentT,
+        Self::Bootstrap,
+        Self::Wilcoxon,
+        Self::Sign,
+        Self::Randomization,
+        Self::Anova,
+        Self::TukeyHsd,
+    ];
+}
+
+impl FromStr for ComparisonTest {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "t" => Ok(Self::StudentT),
+            "bootstrap" => Ok(Self::Bootstrap),
+            "wilcoxon" => Ok(Self::Wilcoxon),
+            "sign" => Ok(Self::Sign),
+            "randomization" => Ok(Self::Randomization),
+            "anova" => Ok(Self::Anova),
+            "hsd" => Ok(Self::TukeyHsd),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported comparison test: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for ComparisonTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::StudentT => write!(f, "t"),
+            Self::Bootstrap => write!(f, "bootstrap"),
+            Self::Wilcoxon => write!(f, "wilcoxon"),
+            Self::Sign => write!(f, "sign"),
+            Self::Randomization => write!(f, "randomization"),
+            Self::Anova => write!(f, "anova"),
+            Self::TukeyHsd => write!(f, "hsd"),
+        }
+    }
+}
+
+/// Options of [`compare_systems`] and [`compare_score_maps`].
+///
+/// # Default parameters
+///
+/// * `tests`: empty, meaning all the tests
+/// * `n_resamples`: `10000`
+/// * `n_iters`: `10000`
+/// * `random_state`: `None`
+/// * `alternative`: [`Alternative::TwoSided`]
+/// * `correction`: `None`
+/// * `effect_size_bands`: `None`
+/// * `correlation_bands`: `None`
+/// * `drop_missing_topics`: `false`
+/// * `min_topics`: `2`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareOptions {
+    tests: Vec<ComparisonTest>,
+    n_resamples: usize,
+    n_iters: usize,
+    random_state: Option<u64>,
+    alternative: Alternative,
+    correction: Option<Correction>,
+    effect_size_bands: Option<EffectSizeBands>,
+    correlation_bands: Option<EffectSizeBands>,
+    drop_missing_topics: bool,
+    min_topics: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompareOptions {
+    /// Creates a new set of options.
+    pub const fn new() -> Self {
+        Self {
+            tests: Vec::new(),
+            n_resamples: 10000,
+            n_iters: 10000,
+            random_state: None,
+            alternative: Alternative::TwoSided,
+            correction: None,
+            effect_size_bands: None,
+            correlation_bands: None,
+            drop_missing_topics: false,
+            min_topics: 2,
+        }
+    }
+
+    /// Sets the tests to run.
+    ///
+    /// If the input is empty, all the tests are run.
+    /// Tests not applicable to the number of systems are skipped.
+    pub fn with_tests(mut self, tests: Vec<ComparisonTest>) -> Self {
+        self.tests = tests;
+        self
+    }
+
+    /// Sets the number of resamples for the bootstrap test.
+    pub const fn with_n_resamples(mut self, n_resamples: usize) -> Self {
+        self.n_resamples = n_resamples;
+        self
+    }
+
+    /// Sets the number of iterations for the randomized tests.
+    pub const fn with_n_iters(mut self, n_iters: usize) -> Self {
+        self.n_iters = n_iters;
+        self
+    }
+
+    /// Sets the random state for the bootstrap and randomized tests.
+    ///
+    /// If not set, the random state is drawn from the entropy for each test.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Sets the alternative hypothesis on (System_1 - System_2) for the tests for two systems.
+    ///
+    /// It is applied to Student's t-test, bootstrap test, and Fisher's randomization test.
+    /// Wilcoxon signed-rank test and sign test are always two-sided.
+    pub const fn with_alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Sets the correction of the p-values over the metrics, applied to the tests for two systems.
+    pub const fn with_correction(mut self, correction: Correction) -> Self {
+        self.correction = Some(correction);
+        self
+    }
+
+    /// Sets the bands to label the standardized mean differences,
+    /// i.e., the effect sizes of Student's t-test and Tukey HSD test.
+    ///
+    /// [`EffectSizeBands::cohen_d`] is the conventional choice.
+    pub const fn with_effect_size_bands(mut self, bands: EffectSizeBands) -> Self {
+        self.effect_size_bands = Some(bands);
+        self
+    }
+
+    /// Sets the bands to label the correlation-like effect sizes,
+    /// i.e., those of Wilcoxon signed-rank test and sign test.
+    ///
+    /// [`EffectSizeBands::cohen_r`] is the conventional choice.
+    pub const fn with_correlation_bands(mut self, bands: EffectSizeBands) -> Self {
+        self.correlation_bands = Some(bands);
+        self
+    }
+
+    /// Sets whether to drop the topics missing in any of the systems for each metric,
+    /// instead of returning an error.
+    ///
+    /// The number of dropped topics is reported as [`MetricComparison::n_dropped_topics`].
+    /// This is useful for comparing systems evaluated in different runs with small topic mismatches.
+    pub const fn with_drop_missing_topics(mut self, drop_missing_topics: bool) -> Self {
+        self.drop_missing_topics = drop_missing_topics;
+        self
+    }
+
+    /// Sets the minimum number of topics remaining after dropping the missing ones,
+    /// below which the comparison is refused.
+    pub const fn with_min_topics(mut self, min_topics: usize) -> Self {
+        self.min_topics = min_topics;
+        self
+    }
+
+    /// Tests to run, where empty means all the tests.
+    pub fn tests(&self) -> &[ComparisonTest] {
+        &self.tests
+    }
+
+    /// Number of resamples for the bootstrap test.
+    pub const fn n_resamples(&self) -> usize {
+        self.n_resamples
+    }
+
+    /// Number of iterations for the randomized tests.
+    pub const fn n_iters(&self) -> usize {
+        self.n_iters
+    }
+
+    /// Random state for the bootstrap and randomized tests, or `None` if drawn from the entropy.
+    pub const fn random_state(&self) -> Option<u64> {
+        self.random_state
+    }
+
+    /// Alternative hypothesis for the tests for two systems.
+    pub const fn alternative(&self) -> Alternative {
+        self.alternative
+    }
+
+    /// Correction of the p-values over the metrics.
+    pub const fn correction(&self) -> Option<Correction> {
+        self.correction
+    }
+
+    /// Bands to label the standardized mean differences.
+    pub const fn effect_size_bands(&self) -> Option<EffectSizeBands> {
+        self.effect_size_bands
+    }
+
+    /// Bands to label the correlation-like effect sizes.
+    pub const fn correlation_bands(&self) -> Option<EffectSizeBands> {
+        self.correlation_bands
+    }
+
+    /// Whether to drop the topics missing in any of the systems.
+    pub const fn drop_missing_topics(&self) -> bool {
+        self.drop_missing_topics
+    }
+
+    /// Minimum number of topics remaining after dropping the missing ones.
+    pub const fn min_topics(&self) -> usize {
+        self.min_topics
+    }
+
+    /// Checks if the test is run.
+    pub fn runs(&self, test: ComparisonTest) -> bool {
+        self.tests.is_empty() || self.tests.contains(&test)
+    }
+}
+
+/// Report of the comparison of systems, returned by [`compare_systems`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonReport {
+    /// Number of systems.
+    pub n_systems: usize,
+
+    /// Number of topics, counted for the first metric of the first system
+    /// after dropping the missing topics.
+    pub n_topics: usize,
+
+    /// Results for each metric, in the input order.
+    pub metrics: Vec<MetricComparison>,
+}
+
+/// Results of the comparison for a metric.
+///
+/// The results of the tests not run are `None`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricComparison {
+    /// Name of the metric.
+    pub metric: String,
+
+    /// Mean scores of the systems.
+    pub means: Vec<f64>,
+
+    /// Number of topics dropped because they are missing in any of the systems,
+    /// which is always zero unless [`CompareOptions::with_drop_missing_topics`] is enabled.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub n_dropped_topics: usize,
+
+    /// Paired Student's t-test for (System_1 - System_2),
+    /// two-sided unless another alternative is specified in the options.
+    pub student_t: Option<StudentTReport>,
+
+    /// Paired bootstrap test for (System_1 - System_2),
+    /// two-sided unless another alternative is specified in the options.
+    pub bootstrap: Option<PValueReport>,
+
+    /// Two-sided Wilcoxon signed-rank test for (System_1 - System_2).
+    pub wilcoxon: Option<WilcoxonReport>,
+
+    /// Two-sided paired sign test for (System_1 - System_2).
+    pub sign: Option<SignReport>,
+
+    /// Fisher's randomized test for two systems,
+    /// two-sided unless another alternative is specified in the options.
+    pub randomization: Option<PValueReport>,
+
+    /// Two-way ANOVA without replication for three or more systems.
+    pub anova: Option<AnovaReport>,
+
+    /// Effect sizes of Tukey HSD test for three or more systems,
+    /// where the $`(i,j)`$-th element is for (System_i - System_j).
+    pub tukey_hsd_effect_sizes: Option<Vec<Vec<f64>>>,
+
+    /// Labels of [`Self::tukey_hsd_effect_sizes`], or `None` if no bands are specified.
+    pub tukey_hsd_effect_size_labels: Option<Vec<Vec<EffectSizeLabel>>>,
+
+    /// p-values of randomized Tukey HSD test for three or more systems,
+    /// where the $`(i,j)`$-th element is for System_i and System_j.
+    pub randomized_tukey_hsd_p_values: Option<Vec<Vec<f64>>>,
+}
+
+/// Results of a test only reporting p-values.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PValueReport {
+    /// p-value.
+    pub p_value: f64,
+
+    /// p-value adjusted over the metrics, or `None` if no correction is specified.
+    pub adj_p_value: Option<f64>,
+}
+
+/// Results of Student's t-test.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StudentTReport {
+    /// Mean of the differences.
+    pub mean: f64,
+
+    /// Unbiased variance of the differences.
+    pub variance: f64,
+
+    /// Effect size.
+    pub effect_size: f64,
+
+    /// Label of the effect size, or `None` if no bands are specified.
+    pub effect_size_label: Option<EffectSizeLabel>,
+
+    /// t-statistic.
+    pub t_stat: f64,
+
+    /// p-value.
+    pub p_value: f64,
+
+    /// p-value adjusted over the metrics, or `None` if no correction is specified.
+    pub adj_p_value: Option<f64>,
+
+    /// Margin of error at a 95% confidence level.
+    pub moe95: f64,
+}
+
+/// Results of Wilcoxon signed-rank test.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WilcoxonReport {
+    /// W-statistic.
+    pub w_stat: f64,
+
+    /// z-statistic.
+    pub z_stat: f64,
+
+    /// Effect size.
+    pub effect_size: f64,
+
+    /// Label of the effect size, or `None` if no bands are specified.
+    pub effect_size_label: Option<EffectSizeLabel>,
+
+    /// p-value.
+    pub p_value: f64,
+
+    /// p-value adjusted over the metrics, or `None` if no correction is specified.
+    pub adj_p_value: Option<f64>,
+}
+
+/// Results of sign test.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignReport {
+    /// Number of topics where System_1 wins.
+    pub n_wins: usize,
+
+    /// Number of topics where System_1 loses.
+    pub n_losses: usize,
+
+    /// Number of ties.
+    pub n_ties: usize,
+
+    /// Effect size.
+    pub effect_size: f64,
+
+    /// Label of the effect size, or `None` if no bands are specified.
+    pub effect_size_label: Option<EffectSizeLabel>,
+
+    /// p-value.
+    pub p_value: f64,
+
+    /// p-value adjusted over the metrics, or `None` if no correction is specified.
+    pub adj_p_value: Option<f64>,
+}
+
+/// Results of two-way ANOVA without replication.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnovaReport {
+    /// Number of topics.
+    pub n_topics: usize,
+
+    /// Mean scores of the systems.
+    pub system_means: Vec<f64>,
+
+    /// Margin of error of the system means at a 95% confidence level.
+    pub moe95: f64,
+
+    /// Between-system variation.
+    pub between_system_variation: f64,
+
+    /// Between-topic variation.
+    pub between_topic_variation: f64,
+
+    /// Residual variation.
+    pub residual_variation: f64,
+
+    /// Between-system variance.
+    pub between_system_variance: f64,
+
+    /// Between-topic variance.
+    pub between_topic_variance: f64,
+
+    /// Residual variance.
+    pub residual_variance: f64,
+
+    /// F-statistic of the between-system factor.
+    pub between_system_f_stat: f64,
+
+    /// F-statistic of the between-topic factor.
+    pub between_topic_f_stat: f64,
+
+    /// p-value of the between-system factor.
+    pub between_system_p_value: f64,
+
+    /// p-value of the between-topic factor.
+    pub between_topic_p_value: f64,
+}
+
+/// Evaluates the systems with the metrics and compares them with the statistical tests.
+///
+/// See the [module-level documentation](self) for the tests run.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `pred_rels` or `metrics` is empty.
+/// * See [`crate::evaluate`] for the errors in the evaluation.
+/// * See [`compare_score_maps`] for the errors in the tests.
+pub fn compare_systems<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &[&PredRelStore<K>],
+    metrics: &[Metric],
+    options: &CompareOptions,
+) -> Result<ComparisonReport>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if pred_rels.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one system.".to_string(),
+        ));
+    }
+    let mut score_maps = vec![];
+    for &metric in metrics {
+        let mut maps = vec![];
+        for &pred_rels in pred_rels {
+            let evaluation = crate::evaluate(true_rels, pred_rels, metric)?;
+            maps.push(evaluation.scores().clone());
+        }
+        score_maps.push((metric.to_string(), maps));
+    }
+    compare_score_maps(&score_maps, options)
+}
+
+/// Compares the systems with the statistical tests for the per-query scores computed beforehand.
+///
+/// Each element of `score_maps` is a pair of the metric name
+/// and the mappings from query ids to scores for the systems.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `score_maps` is empty.
+/// * [`ElinorError::InvalidArgument`] if the metrics have different numbers of systems or no systems.
+/// * [`ElinorError::InvalidArgument`] if the systems have different sets of query ids for a metric,
+///   unless [`CompareOptions::with_drop_missing_topics`] is enabled.
+/// * [`ElinorError::InvalidArgument`] if fewer topics than [`CompareOptions::with_min_topics`]
+///   remain after dropping the missing ones.
+/// * Errors in the statistical tests, such as [`ElinorError::Uncomputable`] for zero variances.
+pub fn compare_score_maps<K>(
+    score_maps: &[(String, Vec<BTreeMap<K, f64>>)],
+    options: &CompareOptions,
+) -> Result<ComparisonReport>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    compare_score_maps_with_timings(score_maps, options).map(|(report, _)| report)
+}
+
+/// Same as [`compare_score_maps`], but also returns the runtime of each test for each metric,
+/// labeled as `<metric>/<test>` (e.g., `ap/t`).
+///
+/// # Errors
+///
+/// See [`compare_score_maps`].
+pub fn compare_score_maps_with_timings<K>(
+    score_maps: &[(String, Vec<BTreeMap<K, f64>>)],
+    options: &CompareOptions,
+) -> Result<(ComparisonReport, TimingReport)>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let Some((_, first_maps)) = score_maps.first() else {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one metric.".to_string(),
+        ));
+    };
+    let n_systems = first_maps.len();
+    if n_systems == 0 || score_maps.iter().any(|(_, maps)| maps.len() != n_systems) {
+        return Err(ElinorError::InvalidArgument(
+            "All the metrics must have the same positive number of systems.".to_string(),
+        ));
+    }
+
+    let mut timings = TimingReport::new();
+    let mut metrics = vec![];
+    let mut n_topics = first_maps[0].len();
+    for (i, (metric, maps)) in score_maps.iter().enumerate() {
+        let (maps, n_dropped_topics) = if options.drop_missing_topics {
+            let (maps, n_dropped) = retain_common_topics(maps);
+            if maps[0].len() < options.min_topics {
+                return Err(ElinorError::InvalidArgument(format!(
+                    "Only {} topics are common to the systems for {metric}, fewer than the minimum {}.",
+                    maps[0].len(),
+                    options.min_topics
+                )));
+            }
+            if i == 0 {
+                n_topics = maps[0].len();
+            }
+            (std::borrow::Cow::Owned(maps), n_dropped)
+        } else {
+            (std::borrow::Cow::Borrowed(maps), 0)
+        };
+        let means = maps
+            .iter()
+            .map(|map| map.values().sum::<f64>() / map.len() as f64)
+            .collect();
+        let mut comparison = MetricComparison {
+            metric: metric.clone(),
+            means,
+            n_dropped_topics,
+            student_t: None,
+            bootstrap: None,
+            wilcoxon: None,
+            sign: None,
+            randomization: None,
+            anova: None,
+            tukey_hsd_effect_sizes: None,
+            tukey_hsd_effect_size_labels: None,
+            randomized_tukey_hsd_p_values: None,
+        };
+        if n_systems == 2 {
+            compare_two_systems(&mut comparison, &maps[0], &maps[1], options, &mut timings)?;
+        } else if n_systems > 2 {
+            compare_multiple_systems(&mut comparison, &maps, options, &mut timings)?;
+        }
+        metrics.push(comparison);
+    }
+    if let Some(correction) = options.correction {
+        adjust_p_values(&mut metrics, correction)?;
+    }
+
+    let report = ComparisonReport {
+        n_systems,
+        n_topics,
+        metrics,
+    };
+    Ok((report, timings))
+}
+
+/// Retains the topics present in all the maps, returning the maps and the number of dropped topics.
+fn retain_common_topics<K>(maps: &[BTreeMap<K, f64>]) -> (Vec<BTreeMap<K, f64>>, usize)
+where
+    K: Clone + Eq + Ord,
+{
+    let n_all_topics = maps
+        .iter()
+        .flat_map(|map| map.keys())
+        .collect::<std::collections::BTreeSet<_>>()
+        .len();
+    let common = maps
+        .iter()
+        .map(|map| {
+            map.iter()
+                .filter(|(topic, _)| maps.iter().all(|m| m.contains_key(*topic)))
+                .map(|(topic, &score)| (topic.clone(), score))
+                .collect::<BTreeMap<_, _>>()
+        })
+        .collect::<Vec<_>>();
+    let n_dropped = n_all_topics - common[0].len();
+    (common, n_dropped)
+}
+
+fn compare_two_systems<K>(
+    comparison: &mut MetricComparison,
+    map_1: &BTreeMap<K, f64>,
+    map_2: &BTreeMap<K, f64>,
+    options: &CompareOptions,
+    timings: &mut TimingReport,
+) -> Result<()>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let pairs = pairs_from_maps(map_1, map_2)?;
+    if options.runs(ComparisonTest::StudentT) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::StudentT), || {
+            StudentTTester::new()
+                .with_alternative(options.alternative)
+                .test(pairs.iter().copied())
+        })?;
+        comparison.student_t = Some(StudentTReport {
+            mean: stat.mean(),
+            variance: stat.variance(),
+            effect_size: stat.effect_size(),
+            effect_size_label: label_of(options.effect_size_bands, stat.effect_size()),
+            t_stat: stat.t_stat(),
+            p_value: stat.p_value(),
+            adj_p_value: None,
+            moe95: stat.margin_of_error(0.05)?,
+        });
+    }
+    if options.runs(ComparisonTest::Bootstrap) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::Bootstrap), || {
+            let mut tester = BootstrapTester::new()
+                .with_n_resamples(options.n_resamples)
+                .with_alternative(options.alternative);
+            if let Some(random_state) = options.random_state {
+                tester = tester.with_random_state(random_state);
+            }
+            tester.test(pairs.iter().copied())
+        })?;
+        comparison.bootstrap = Some(PValueReport {
+            p_value: stat.p_value(),
+            adj_p_value: None,
+        });
+    }
+    if options.runs(ComparisonTest::Wilcoxon) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::Wilcoxon), || {
+            WilcoxonSignedRankTest::from_paired_samples(pairs.iter().copied())
+        })?;
+        comparison.wilcoxon = Some(WilcoxonReport {
+            w_stat: stat.w_stat(),
+            z_stat: stat.z_stat(),
+            effect_size: stat.effect_size(),
+            effect_size_label: label_of(options.correlation_bands, stat.effect_size()),
+            p_value: stat.p_value(),
+            adj_p_value: None,
+        });
+    }
+    if options.runs(ComparisonTest::Sign) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::Sign), || {
+            SignTest::from_paired_samples(pairs.iter().copied())
+        })?;
+        comparison.sign = Some(SignReport {
+            n_wins: stat.n_wins(),
+            n_losses: stat.n_losses(),
+            n_ties: stat.n_ties(),
+            effect_size: stat.effect_size(),
+            effect_size_label: label_of(options.correlation_bands, stat.effect_size()),
+            p_value: stat.p_value(),
+            adj_p_value: None,
+        });
+    }
+    if options.runs(ComparisonTest::Randomization) {
+        let stat = timings.time(
+            test_label(comparison, ComparisonTest::Randomization),
+            || {
+                let mut tester = FisherRandomizationTester::new()
+                    .with_n_iters(options.n_iters)
+                    .with_alternative(options.alternative);
+                if let Some(random_state) = options.random_state {
+                    tester = tester.with_random_state(random_state);
+                }
+                tester.test(pairs.iter().copied())
+            },
+        )?;
+        comparison.randomization = Some(PValueReport {
+            p_value: stat.p_value(),
+            adj_p_value: None,
+        });
+    }
+    Ok(())
+}
+
+fn compare_multiple_systems<K>(
+    comparison: &mut MetricComparison,
+    maps: &[BTreeMap<K, f64>],
+    options: &CompareOptions,
+    timings: &mut TimingReport,
+) -> Result<()>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let n_systems = maps.len();
+    let tuples = tuples_from_maps(maps)?;
+    if options.runs(ComparisonTest::Anova) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::Anova), || {
+            TwoWayAnovaWithoutReplication::from_tupled_samples(tuples.iter(), n_systems)
+        })?;
+        comparison.anova = Some(AnovaReport {
+            n_topics: stat.n_topics(),
+            system_means: stat.system_means(),
+            moe95: stat.margin_of_error(0.05)?,
+            between_system_variation: stat.between_system_variation(),
+            between_topic_variation: stat.between_topic_variation(),
+            residual_variation: stat.residual_variation(),
+            between_system_variance: stat.between_system_variance(),
+            between_topic_variance: stat.between_topic_variance(),
+            residual_variance: stat.residual_variance(),
+            between_system_f_stat: stat.between_system_f_stat(),
+            between_topic_f_stat: stat.between_topic_f_stat(),
+            between_system_p_value: stat.between_system_p_value(),
+            between_topic_p_value: stat.between_topic_p_value(),
+        });
+    }
+    if options.runs(ComparisonTest::TukeyHsd) {
+        let stat = timings.time(test_label(comparison, ComparisonTest::TukeyHsd), || {
+            TukeyHsdTest::from_tupled_samples(tuples.iter(), n_systems)
+        })?;
+        let effect_sizes = stat.effect_sizes();
+        comparison.tukey_hsd_effect_size_labels = options.effect_size_bands.map(|bands| {
+            effect_sizes
+                .iter()
+                .map(|row| row.iter().map(|&es| bands.label(es)).collect())
+                .collect()
+        });
+        comparison.tukey_hsd_effect_sizes = Some(effect_sizes);
+    }
+    if options.runs(ComparisonTest::Randomization) {
+        let stat = timings.time(
+            test_label(comparison, ComparisonTest::Randomization),
+            || {
+                let mut tester =
+                    RandomizedTukeyHsdTester::new(n_systems).with_n_iters(options.n_iters);
+                if let Some(random_state) = options.random_state {
+                    tester = tester.with_random_state(random_state);
+                }
+                tester.test(tuples.iter())
+            },
+        )?;
+        comparison.randomized_tukey_hsd_p_values = Some(stat.p_values());
+    }
+    Ok(())
+}
+
+/// Returns the label of the test for the metric in the timing report, such as `ap/t`.
+fn test_label(comparison: &MetricComparison, test: ComparisonTest) -> String {
+    format!("{}/{test}", comparison.metric)
+}
+
+fn label_of(bands: Option<EffectSizeBands>, effect_size: f64) -> Option<EffectSizeLabel> {
+    bands.map(|bands| bands.label(effect_size))
+}
+
+/// Adjusts the p-values of each test for two systems over the metrics.
+fn adjust_p_values(metrics: &mut [MetricComparison], correction: Correction) -> Result<()> {
+    macro_rules! adjust {
+        ($field:ident) => {
+            let p_values = metrics
+                .iter()
+                .filter_map(|m| m.$field.as_ref().map(|r| r.p_value))
+                .collect::<Vec<_>>();
+            if !p_values.is_empty() {
+                let adjusted = correction.adjust(&p_values)?;
+                let reports = metrics.iter_mut().filter_map(|m| m.$field.as_mut());
+                for (report, adj_p_value) in reports.zip(adjusted) {
+                    report.adj_p_value = Some(adj_p_value);
+                }
+            }
+        };
+    }
+    adjust!(student_t);
+    adjust!(bootstrap);
+    adjust!(wilcoxon);
+    adjust!(sign);
+    adjust!(randomization);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistical_tests::StudentTTest;
+
+    fn example_score_maps() -> Vec<(String, Vec<BTreeMap<&'static str, f64>>)> {
+        let map_1 = BTreeMap::from([("a", 0.70), ("b", 0.30), ("c", 0.20), ("d", 0.60)]);
+        let map_2 = BTreeMap::from([("a", 0.50), ("b", 0.10), ("c", 0.00), ("d", 0.50)]);
+        let map_3 = BTreeMap::from([("a", 0.60), ("b", 0.40), ("c", 0.10), ("d", 0.20)]);
+        vec![
+            (
+                "x".to_string(),
+                vec![map_1.clone(), map_2.clone(), map_3.clone()],
+            ),
+            ("y".to_string(), vec![map_2, map_3, map_1]),
+        ]
+    }
+
+    #[test]
+    fn test_compare_two_systems() {
+        let score_maps = example_score_maps()
+            .into_iter()
+            .map(|(metric, maps)| (metric, maps[..2].to_vec()))
+            .collect::<Vec<_>>();
+        let options = CompareOptions::new()
+            .with_n_resamples(100)
+            .with_n_iters(100)
+            .with_correction(Correction::Bonferroni)
+            .with_effect_size_bands(EffectSizeBands::cohen_d());
+        let report = compare_score_maps(&score_maps, &options).unwrap();
+        assert_eq!(report.n_systems, 2);
+        assert_eq!(report.n_topics, 4);
+        assert_eq!(report.metrics.len(), 2);
+
+        let comparison = &report.metrics[0];
+        assert_eq!(comparison.metric, "x");
+        let pairs = pairs_from_maps(&score_maps[0].1[0], &score_maps[0].1[1]).unwrap();
+        let stat = StudentTTest::from_paired_samples(pairs).unwrap();
+        let student_t = comparison.student_t.unwrap();
+        assert_eq!(student_t.p_value, stat.p_value());
+        assert_eq!(student_t.adj_p_value, Some((stat.p_value() * 2.0).min(1.0)));
+        assert_eq!(
+            student_t.effect_size_label,
+            Some(EffectSizeBands::cohen_d().label(stat.effect_size()))
+        );
+        assert!(comparison.bootstrap.is_some());
+        assert_eq!(comparison.wilcoxon.unwrap().effect_size_label, None);
+        assert!(comparison.sign.is_some());
+        assert!(comparison.randomization.is_some());
+        assert!(comparison.anova.is_none());
+        assert!(comparison.tukey_hsd_effect_sizes.is_none());
+        assert!(comparison.randomized_tukey_hsd_p_values.is_none());
+    }
+
+    #[test]
+    fn test_compare_two_systems_random_state_and_alternative() {
+        let score_maps = example_score_maps()
+            .into_iter()
+            .map(|(metric, maps)| (metric, maps[..2].to_vec()))
+            .collect::<Vec<_>>();
+        let options = CompareOptions::new()
+            .with_n_resamples(100)
+            .with_n_iters(100)
+            .with_random_state(42)
+            .with_alternative(Alternative::Greater);
+        let report = compare_score_maps(&score_maps, &options).unwrap();
+        assert_eq!(report, compare_score_maps(&score_maps, &options).unwrap());
+
+        let comparison = &report.metrics[0];
+        let pairs = pairs_from_maps(&score_maps[0].1[0], &score_maps[0].1[1]).unwrap();
+        let student_t = StudentTTester::new()
+            .with_alternative(Alternative::Greater)
+            .test(pairs.iter().copied())
+            .unwrap();
+        assert_eq!(comparison.student_t.unwrap().p_value, student_t.p_value());
+        let bootstrap = BootstrapTester::new()
+            .with_n_resamples(100)
+            .with_random_state(42)
+            .with_alternative(Alternative::Greater)
+            .test(pairs.iter().copied())
+            .unwrap();
+        assert_eq!(comparison.bootstrap.unwrap().p_value, bootstrap.p_value());
+        let randomization = FisherRandomizationTester::new()
+            .with_n_iters(100)
+            .with_random_state(42)
+            .with_alternative(Alternative::Greater)
+            .test(pairs.iter().copied())
+            .unwrap();
+        assert_eq!(
+            comparison.randomization.unwrap().p_value,
+            randomization.p_value()
+        );
+    }
+
+    #[test]
+    fn test_compare_multiple_systems_random_state() {
+        let options = CompareOptions::new()
+            .with_tests(vec![ComparisonTest::Randomization])
+            .with_n_iters(100)
+            .with_random_state(42);
+        let report = compare_score_maps(&example_score_maps(), &options).unwrap();
+        assert_eq!(
+            report,
+            compare_score_maps(&example_score_maps(), &options).unwrap()
+        );
+        assert!(report.metrics[0].randomized_tukey_hsd_p_values.is_some());
+    }
+
+    #[test]
+    fn test_compare_multiple_systems() {
+        let options = CompareOptions::new()
+            .with_tests(vec![ComparisonTest::Anova, ComparisonTest::StudentT])
+            .with_n_iters(100);
+        let report = compare_score_maps(&example_score_maps(), &options).unwrap();
+        assert_eq!(report.n_systems, 3);
+
+        let comparison = &report.metrics[1];
+        assert_eq!(comparison.metric, "y");
+        assert_eq!(comparison.means.len(), 3);
+        let anova = comparison.anova.as_ref().unwrap();
+        assert_eq!(anova.n_topics, 4);
+        assert_eq!(anova.system_means.len(), 3);
+        assert!(comparison.student_t.is_none());
+        assert!(comparison.tukey_hsd_effect_sizes.is_none());
+        assert!(comparison.randomized_tukey_hsd_p_values.is_none());
+    }
+
+    #[test]
+    fn test_compare_multiple_systems_effect_size_labels() {
+        let bands = EffectSizeBands::new(0.1, 0.2, 0.3).unwrap();
+        let options = CompareOptions::new()
+            .with_tests(vec![ComparisonTest::TukeyHsd])
+            .with_effect_size_bands(bands);
+        let report = compare_score_maps(&example_score_maps(), &options).unwrap();
+        let comparison = &report.metrics[0];
+        let effect_sizes = comparison.tukey_hsd_effect_sizes.as_ref().unwrap();
+        let labels = comparison.tukey_hsd_effect_size_labels.as_ref().unwrap();
+        assert_eq!(labels[0][0], EffectSizeLabel::Negligible);
+        assert_eq!(labels[0][1], bands.label(effect_sizes[0][1]));
+        assert_eq!(labels[1][0], labels[0][1]);
+    }
+
+    #[test]
+    fn test_compare_score_maps_invalid() {
+        let options = CompareOptions::new();
+        let empty = Vec::<(String, Vec<BTreeMap<&str, f64>>)>::new();
+        assert_eq!(
+            compare_score_maps(&empty, &options),
+            Err(ElinorError::InvalidArgument(
+                "The input must have at least one metric.".to_string()
+            ))
+        );
+
+        let mut score_maps = example_score_maps();
+        score_maps[0].1.pop();
+        assert_eq!(
+            compare_score_maps(&score_maps, &options),
+            Err(ElinorError::InvalidArgument(
+                "All the metrics must have the same positive number of systems.".to_string()
+            ))
+        );
+
+        let mut score_maps = example_score_maps();
+        score_maps[0].1[1].remove("a");
+        assert!(compare_score_maps(&score_maps, &options).is_err());
+    }
+
+    #[test]
+    fn test_compare_score_maps_drop_missing_topics() {
+        let mut score_maps = example_score_maps();
+        score_maps[0].1[1].remove("a");
+        score_maps[0].1[2].insert("e", 0.30);
+        let options = CompareOptions::new()
+            .with_tests(vec![ComparisonTest::Anova])
+            .with_drop_missing_topics(true);
+        let report = compare_score_maps(&score_maps, &options).unwrap();
+        assert_eq!(report.n_topics, 3);
+
+        let comparison = &report.metrics[0];
+        assert_eq!(comparison.n_dropped_topics, 2);
+        assert_eq!(comparison.anova.as_ref().unwrap().n_topics, 3);
+        assert!((comparison.means[0] - (0.30 + 0.20 + 0.60) / 3.0).abs() < 1e-10);
+        assert_eq!(report.metrics[1].n_dropped_topics, 0);
+        assert_eq!(report.metrics[1].anova.as_ref().unwrap().n_topics, 4);
+
+        let options = options.with_min_topics(4);
+        assert_eq!(
+            compare_score_maps(&score_maps, &options),
+            Err(ElinorError::InvalidArgument(
+                "Only 3 topics are common to the systems for x, fewer than the minimum 4."
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compare_score_maps_with_timings() {
+        let score_maps = example_score_maps()
+            .into_iter()
+            .map(|(metric, maps)| (metric, maps[..2].to_vec()))
+            .collect::<Vec<_>>();
+        let options = CompareOptions::new()
+            .with_tests(vec![ComparisonTest::StudentT, ComparisonTest::Sign])
+            .with_n_resamples(100)
+            .with_n_iters(100);
+        let (report, timings) = compare_score_maps_with_timings(&score_maps, &options).unwrap();
+        assert_eq!(report, compare_score_maps(&score_maps, &options).unwrap());
+        let labels = timings
+            .entries()
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["x/t", "x/sign", "y/t", "y/sign"]);
+    }
+
+    #[test]
+    fn test_comparison_test_from_str() {
+        for test in ComparisonTest::ALL {
+            assert_eq!(test.to_string().parse(), Ok(test));
+        }
+        assert_eq!(
+            "x".parse::<ComparisonTest>(),
+            Err(ElinorError::InvalidFormat(
+                "Unsupported comparison test: x".to_string()
+            ))
+        );
+    }
+}
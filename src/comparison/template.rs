@@ -0,0 +1,516 @@
+//! Rendering of comparison reports with templates.
+//!
+//! Organizations often have a standardized format to write up experiments.
+//! This module provides [`ReportRenderer`], the interface to render a [`ComparisonReport`] as text,
+//! and [`ReportTemplate`], its minimal implementation with a Mustache-like syntax:
+//!
+//! * `{{name}}` is replaced with the value of the field `name`, e.g., `{{n_topics}}`.
+//!   Nested fields and list elements are accessed by dots, e.g., `{{student_t.p_value}}` or `{{means.0}}`.
+//!   The value of a missing field (e.g., a test not run) is empty.
+//! * `{{#name}}...{{/name}}` renders the inside for each element if `name` is a list,
+//!   once with `name` as the innermost scope if it is present, and nothing otherwise.
+//!   In a list, `{{.}}` is the current element and `{{@index}}` is its 1-origin index.
+//! * `{{^name}}...{{/name}}` renders the inside only if `name` is missing or an empty list.
+//! * `{{! comment }}` is ignored.
+//!
+//! The fields are the same as those of [`ComparisonReport`] and the nested structures.
+//! Real numbers are printed with a fixed number of decimal places (4 by default),
+//! and effect size labels are printed in lowercase.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::collections::BTreeMap;
+//!
+//! use elinor::comparison::template::{ReportRenderer, ReportTemplate};
+//! use elinor::comparison::{compare_score_maps, CompareOptions, ComparisonTest};
+//!
+//! let map_a = BTreeMap::from([("q_1", 0.70), ("q_2", 0.30), ("q_3", 0.20)]);
+//! let map_b = BTreeMap::from([("q_1", 0.50), ("q_2", 0.20), ("q_3", 0.00)]);
+//! let options = CompareOptions::new().with_tests(vec![ComparisonTest::StudentT]);
+//! let report = compare_score_maps(&[("ap".to_string(), vec![map_a, map_b])], &options)?;
+//!
+//! let template = ReportTemplate::parse(
+//!     "{{#metrics}}{{metric}}:{{#means}} S{{@index}}={{.}}{{/means}}{{#student_t}} (p={{p_value}}){{/student_t}}\n{{/metrics}}",
+//! )?
+//! .with_precision(2);
+//! assert_eq!(template.render(&report)?, "ap: S1=0.40 S2=0.23 (p=0.04)\n");
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+
+use crate::comparison::AnovaReport;
+use crate::comparison::ComparisonReport;
+use crate::comparison::MetricComparison;
+use crate::comparison::PValueReport;
+use crate::comparison::SignReport;
+use crate::comparison::StudentTReport;
+use crate::comparison::WilcoxonReport;
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::effect_sizes::EffectSizeLabel;
+
+/// Interface to render a [`ComparisonReport`] as text.
+///
+/// Implement it to produce a format that [`ReportTemplate`] cannot express.
+pub trait ReportRenderer {
+    /// Renders the report.
+    ///
+    /// # Errors
+    ///
+    /// Depends on the implementation.
+    fn render(&self, report: &ComparisonReport) -> Result<String>;
+}
+
+/// Value of a field accessible from templates.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Missing,
+    Text(String),
+    Count(usize),
+    Real(f64),
+    List(Vec<Self>),
+    Map(BTreeMap<&'static str, Self>),
+}
+
+impl Value {
+    fn is_falsy(&self) -> bool {
+        match self {
+            Self::Missing => true,
+            Self::List(values) => values.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Map(map) => map.get(key),
+            Self::List(values) => key.parse::<usize>().ok().and_then(|i| values.get(i)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Into<Self>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Missing, Into::into)
+    }
+}
+
+impl<T: Into<Self>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Self::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::Count(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<EffectSizeLabel> for Value {
+    fn from(label: EffectSizeLabel) -> Self {
+        Self::Text(label.to_string())
+    }
+}
+
+macro_rules! map_value {
+    ($($key:ident: $value:expr),* $(,)?) => {
+        Value::Map(BTreeMap::from([$((stringify!($key), Value::from($value))),*]))
+    };
+}
+
+impl From<&PValueReport> for Value {
+    fn from(r: &PValueReport) -> Self {
+        map_value! {
+            p_value: r.p_value,
+            adj_p_value: r.adj_p_value,
+        }
+    }
+}
+
+impl From<&StudentTReport> for Value {
+    fn from(r: &StudentTReport) -> Self {
+        map_value! {
+            mean: r.mean,
+            variance: r.variance,
+            effect_size: r.effect_size,
+            effect_size_label: r.effect_size_label,
+            t_stat: r.t_stat,
+            p_value: r.p_value,
+            adj_p_value: r.adj_p_value,
+            moe95: r.moe95,
+        }
+    }
+}
+
+impl From<&WilcoxonReport> for Value {
+    fn from(r: &WilcoxonReport) -> Self {
+        map_value! {
+            w_stat: r.w_stat,
+            z_stat: r.z_stat,
+            effect_size: r.effect_size,
+            effect_size_label: r.effect_size_label,
+            p_value: r.p_value,
+            adj_p_value: r.adj_p_value,
+        }
+    }
+}
+
+impl From<&SignReport> for Value {
+    fn from(r: &SignReport) -> Self {
+        map_value! {
+            n_wins: r.n_wins,
+            n_losses: r.n_losses,
+            n_ties: r.n_ties,
+            effect_size: r.effect_size,
+            effect_size_label: r.effect_size_label,
+            p_value: r.p_value,
+            adj_p_value: r.adj_p_value,
+        }
+    }
+}
+
+impl From<&AnovaReport> for Value {
+    fn from(r: &AnovaReport) -> Self {
+        map_value! {
+            n_topics: r.n_topics,
+            system_means: r.system_means.clone(),
+            moe95: r.moe95,
+            between_system_variation: r.between_system_variation,
+            between_topic_variation: r.between_topic_variation,
+            residual_variation: r.residual_variation,
+            between_system_variance: r.between_system_variance,
+            between_topic_variance: r.between_topic_variance,
+            residual_variance: r.residual_variance,
+            between_system_f_stat: r.between_system_f_stat,
+            between_topic_f_stat: r.between_topic_f_stat,
+            between_system_p_value: r.between_system_p_value,
+            between_topic_p_value: r.between_topic_p_value,
+        }
+    }
+}
+
+impl From<&MetricComparison> for Value {
+    fn from(m: &MetricComparison) -> Self {
+        map_value! {
+            metric: m.metric.clone(),
+            means: m.means.clone(),
+            n_dropped_topics: m.n_dropped_topics,
+            student_t: m.student_t.as_ref(),
+            bootstrap: m.bootstrap.as_ref(),
+            wilcoxon: m.wilcoxon.as_ref(),
+            sign: m.sign.as_ref(),
+            randomization: m.randomization.as_ref(),
+            anova: m.anova.as_ref(),
+            tukey_hsd_effect_sizes: m.tukey_hsd_effect_sizes.clone(),
+            tukey_hsd_effect_size_labels: m.tukey_hsd_effect_size_labels.clone(),
+            randomized_tukey_hsd_p_values: m.randomized_tukey_hsd_p_values.clone(),
+        }
+    }
+}
+
+impl From<&ComparisonReport> for Value {
+    fn from(r: &ComparisonReport) -> Self {
+        map_value! {
+            n_systems: r.n_systems,
+            n_topics: r.n_topics,
+            metrics: r.metrics.iter().map(Self::from).collect::<Vec<_>>(),
+        }
+    }
+}
+
+/// Node of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Self>,
+    },
+}
+
+/// Minimal template to render a [`ComparisonReport`].
+///
+/// See the [module-level documentation](self) for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTemplate {
+    nodes: Vec<Node>,
+    precision: usize,
+}
+
+impl ReportTemplate {
+    /// Parses a template.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if a tag is not closed by `}}`.
+    /// * [`ElinorError::InvalidFormat`] if a section is not closed or closed by a different name.
+    pub fn parse(source: &str) -> Result<Self> {
+        // Stack of the open sections, each of which is (name, inverted, parent nodes).
+        let mut stack: Vec<(String, bool, Vec<Node>)> = vec![];
+        let mut nodes = vec![];
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find("}}") else {
+                return Err(ElinorError::InvalidFormat(format!(
+                    "Unclosed tag: {}",
+                    &rest[start..]
+                )));
+            };
+            let tag = rest[start + 2..start + end].trim();
+            rest = &rest[start + end + 2..];
+            if let Some(name) = tag.strip_prefix('#') {
+                stack.push((name.trim().to_string(), false, std::mem::take(&mut nodes)));
+            } else if let Some(name) = tag.strip_prefix('^') {
+                stack.push((name.trim().to_string(), true, std::mem::take(&mut nodes)));
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                let Some((open, inverted, parent)) = stack.pop() else {
+                    return Err(ElinorError::InvalidFormat(format!(
+                        "Unopened section: {name}"
+                    )));
+                };
+                if open != name {
+                    return Err(ElinorError::InvalidFormat(format!(
+                        "Section {open} is closed by {name}"
+                    )));
+                }
+                let children = std::mem::replace(&mut nodes, parent);
+                nodes.push(Node::Section {
+                    name: open,
+                    inverted,
+                    children,
+                });
+            } else if !tag.starts_with('!') {
+                nodes.push(Node::Variable(tag.to_string()));
+            }
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        if let Some((open, _, _)) = stack.pop() {
+            return Err(ElinorError::InvalidFormat(format!(
+                "Unclosed section: {open}"
+            )));
+        }
+        Ok(Self {
+            nodes,
+            precision: 4,
+        })
+    }
+
+    /// Sets the number of decimal places of real numbers.
+    pub const fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    fn render_nodes(&self, nodes: &[Node], scopes: &mut Vec<Scope>, out: &mut String) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Variable(name) => {
+                    if let Some(value) = lookup(scopes, name) {
+                        self.write_value(&value, out);
+                    }
+                }
+                Node::Section {
+                    name,
+                    inverted,
+                    children,
+                } => {
+                    let value = lookup(scopes, name).unwrap_or(Value::Missing);
+                    if *inverted {
+                        if value.is_falsy() {
+                            self.render_nodes(children, scopes, out);
+                        }
+                    } else if let Value::List(values) = value {
+                        for (i, value) in values.into_iter().enumerate() {
+                            scopes.push(Scope {
+                                value,
+                                index: Some(i + 1),
+                            });
+                            self.render_nodes(children, scopes, out);
+                            scopes.pop();
+                        }
+                    } else if !value.is_falsy() {
+                        scopes.push(Scope { value, index: None });
+                        self.render_nodes(children, scopes, out);
+                        scopes.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_value(&self, value: &Value, out: &mut String) {
+        match value {
+            Value::Text(text) => out.push_str(text),
+            Value::Count(count) => out.push_str(&count.to_string()),
+            Value::Real(real) => out.push_str(&format!("{real:.0$}", self.precision)),
+            Value::Missing | Value::List(_) | Value::Map(_) => {}
+        }
+    }
+}
+
+impl ReportRenderer for ReportTemplate {
+    /// Renders the report with the template.
+    ///
+    /// It never fails; missing fields are rendered as empty.
+    fn render(&self, report: &ComparisonReport) -> Result<String> {
+        let mut scopes = vec![Scope {
+            value: Value::from(report),
+            index: None,
+        }];
+        let mut out = String::new();
+        self.render_nodes(&self.nodes, &mut scopes, &mut out);
+        Ok(out)
+    }
+}
+
+/// Scope of a section, with the 1-origin index if it is a list element.
+struct Scope {
+    value: Value,
+    index: Option<usize>,
+}
+
+/// Looks up the dotted name from the innermost scope.
+fn lookup(scopes: &[Scope], name: &str) -> Option<Value> {
+    let scope = scopes.last()?;
+    if name == "." {
+        return Some(scope.value.clone());
+    }
+    if name == "@index" {
+        return scopes.iter().rev().find_map(|s| s.index).map(Value::Count);
+    }
+    let mut keys = name.split('.');
+    let first = keys.next()?;
+    let mut value = scopes.iter().rev().find_map(|s| s.value.get(first))?;
+    for key in keys {
+        value = value.get(key)?;
+    }
+    Some(value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_report() -> ComparisonReport {
+        ComparisonReport {
+            n_systems: 2,
+            n_topics: 3,
+            metrics: vec![MetricComparison {
+                metric: "ap".to_string(),
+                means: vec![0.5, 0.25],
+                n_dropped_topics: 0,
+                student_t: None,
+                bootstrap: Some(PValueReport {
+                    p_value: 0.01,
+                    adj_p_value: None,
+                }),
+                wilcoxon: None,
+                sign: Some(SignReport {
+                    n_wins: 2,
+                    n_losses: 1,
+                    n_ties: 0,
+                    effect_size: 1.0 / 3.0,
+                    effect_size_label: Some(EffectSizeLabel::Medium),
+                    p_value: 1.0,
+                    adj_p_value: None,
+                }),
+                randomization: None,
+                anova: None,
+                tukey_hsd_effect_sizes: None,
+                tukey_hsd_effect_size_labels: None,
+                randomized_tukey_hsd_p_values: Some(vec![vec![1.0, 0.5], vec![0.5, 1.0]]),
+            }],
+        }
+    }
+
+    fn render(source: &str) -> String {
+        ReportTemplate::parse(source)
+            .unwrap()
+            .with_precision(2)
+            .render(&example_report())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_report_template_variables() {
+        assert_eq!(
+            render("{{n_systems}} systems, {{ n_topics }} topics"),
+            "2 systems, 3 topics"
+        );
+        assert_eq!(render("{{metrics.0.means.1}}"), "0.25");
+        assert_eq!(render("{{metrics.0.sign.effect_size_label}}"), "medium");
+        assert_eq!(render("[{{metrics.0.student_t.p_value}}{{unknown}}]"), "[]");
+        assert_eq!(render("a{{! comment }}b"), "ab");
+    }
+
+    #[test]
+    fn test_report_template_sections() {
+        assert_eq!(
+            render("{{#metrics}}{{metric}}{{#bootstrap}} p={{p_value}}{{/bootstrap}}{{/metrics}}"),
+            "ap p=0.01"
+        );
+        assert_eq!(
+            render("{{#metrics}}{{#student_t}}t{{/student_t}}{{^student_t}}no t{{/student_t}}{{/metrics}}"),
+            "no t"
+        );
+        assert_eq!(
+            render("{{#metrics}}{{#means}}{{@index}}:{{.}} {{/means}}{{/metrics}}"),
+            "1:0.50 2:0.25 "
+        );
+        // Outer fields are visible in sections.
+        assert_eq!(
+            render("{{#metrics}}{{#sign}}{{metric}}/{{n_topics}}/{{n_wins}}{{/sign}}{{/metrics}}"),
+            "ap/3/2"
+        );
+        assert_eq!(
+            render("{{#metrics}}{{#randomized_tukey_hsd_p_values}}{{#.}}{{.}},{{/.}};{{/randomized_tukey_hsd_p_values}}{{/metrics}}"),
+            "1.00,0.50,;0.50,1.00,;"
+        );
+    }
+
+    #[test]
+    fn test_report_template_parse_errors() {
+        assert_eq!(
+            ReportTemplate::parse("{{n_topics").unwrap_err(),
+            ElinorError::InvalidFormat("Unclosed tag: {{n_topics".to_string())
+        );
+        assert_eq!(
+            ReportTemplate::parse("{{#metrics}}").unwrap_err(),
+            ElinorError::InvalidFormat("Unclosed section: metrics".to_string())
+        );
+        assert_eq!(
+            ReportTemplate::parse("{{/metrics}}").unwrap_err(),
+            ElinorError::InvalidFormat("Unopened section: metrics".to_string())
+        );
+        assert_eq!(
+            ReportTemplate::parse("{{#metrics}}{{/means}}").unwrap_err(),
+            ElinorError::InvalidFormat("Section metrics is closed by means".to_string())
+        );
+    }
+}
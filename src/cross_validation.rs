@@ -0,0 +1,550 @@
+//! Cross-validation over topics.
+//!
+//! This module provides utilities to split topics into $`k`$ folds and
+//! to evaluate systems per fold, which is a standard practice when tuning
+//! parameters (e.g., fusion weights or rerankers) on true relevance scores.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::cross_validation::{KFoldSplitter, cross_validate};
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_2", "d_1", 1)?;
+//! b.add_record("q_3", "d_1", 1)?;
+//! b.add_record("q_4", "d_1", 1)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_2", 0.5.into())?;
+//! b.add_record("q_3", "d_1", 0.5.into())?;
+//! b.add_record("q_4", "d_2", 0.5.into())?;
+//! let pred_rels = b.build();
+//!
+//! // Split the topics into two folds.
+//! let splitter = KFoldSplitter::new(2).with_random_state(42);
+//! let folds = splitter.split(true_rels.query_ids().cloned())?;
+//! assert_eq!(folds.len(), 2);
+//! assert_eq!(folds[0].train().len(), 2);
+//! assert_eq!(folds[0].test().len(), 2);
+//!
+//! // Evaluate the system on the test topics of each fold.
+//! let result = cross_validate(&true_rels, &pred_rels, Metric::Success { k: 1 }, &folds)?;
+//! assert_eq!(result.evaluations().len(), 2);
+//! assert!((0.0..=1.0).contains(&result.mean()));
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::Evaluation;
+use crate::Metric;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+
+/// Split of topics into training and testing topics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicFold<K> {
+    train: Vec<K>,
+    test: Vec<K>,
+}
+
+impl<K> TopicFold<K> {
+    /// Creates a new fold from the training and testing topics.
+    ///
+    /// The topics are sorted in ascending order.
+    pub fn new(mut train: Vec<K>, mut test: Vec<K>) -> Self
+    where
+        K: Ord,
+    {
+        train.sort();
+        test.sort();
+        Self { train, test }
+    }
+
+    /// Topics for training, sorted in ascending order.
+    pub fn train(&self) -> &[K] {
+        &self.train
+    }
+
+    /// Topics for testing, sorted in ascending order.
+    pub fn test(&self) -> &[K] {
+        &self.test
+    }
+}
+
+/// K-fold splitter of topics.
+///
+/// # Default parameters
+///
+/// * `random_state`: `None`
+#[derive(Debug, Clone, Copy)]
+pub struct KFoldSplitter {
+    n_folds: usize,
+    random_state: Option<u64>,
+}
+
+impl KFoldSplitter {
+    /// Creates a new splitter with the number of folds.
+    pub const fn new(n_folds: usize) -> Self {
+        Self {
+            n_folds,
+            random_state: None,
+        }
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Number of folds.
+    pub const fn n_folds(&self) -> usize {
+        self.n_folds
+    }
+
+    /// Splits the topics into folds at random.
+    ///
+    /// Duplicated topics are merged.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the number of folds is less than two.
+    /// * [`ElinorError::InvalidArgument`] if the number of topics is less than the number of folds.
+    pub fn split<I, K>(&self, query_ids: I) -> Result<Vec<TopicFold<K>>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Clone + Ord,
+    {
+        let query_ids = query_ids.into_iter().collect::<BTreeSet<_>>();
+        self.split_groups(vec![query_ids.into_iter().collect()])
+    }
+
+    /// Splits the topics into folds at random,
+    /// while preserving the proportion of each stratum in every fold as much as possible.
+    ///
+    /// # Arguments
+    ///
+    /// * `strata` - Mapping from query ids to their strata (e.g., a metadata field of topics).
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the number of folds is less than two.
+    /// * [`ElinorError::InvalidArgument`] if the number of topics is less than the number of folds.
+    pub fn split_stratified<K, S>(&self, strata: &BTreeMap<K, S>) -> Result<Vec<TopicFold<K>>>
+    where
+        K: Clone + Ord,
+        S: Ord,
+    {
+        let mut groups: BTreeMap<&S, Vec<K>> = BTreeMap::new();
+        for (query_id, stratum) in strata {
+            groups.entry(stratum).or_default().push(query_id.clone());
+        }
+        self.split_groups(groups.into_values().collect())
+    }
+
+    fn split_groups<K>(&self, groups: Vec<Vec<K>>) -> Result<Vec<TopicFold<K>>>
+    where
+        K: Clone + Ord,
+    {
+        if self.n_folds < 2 {
+            return Err(ElinorError::InvalidArgument(
+                "The number of folds must be at least two.".to_string(),
+            ));
+        }
+        let n_topics = groups.iter().map(|group| group.len()).sum::<usize>();
+        if n_topics < self.n_folds {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The number of topics must be at least the number of folds, but got n_topics={n_topics} and n_folds={}.",
+                self.n_folds
+            )));
+        }
+
+        // Prepare the random number generator.
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+
+        // Assign the topics to the folds in a round-robin manner,
+        // continuing from the previous group so that the fold sizes are balanced.
+        let mut assigned = vec![vec![]; self.n_folds];
+        let mut cursor = 0;
+        for mut group in groups {
+            group.shuffle(&mut rng);
+            for query_id in group {
+                assigned[cursor].push(query_id);
+                cursor = (cursor + 1) % self.n_folds;
+            }
+        }
+
+        let folds = (0..self.n_folds)
+            .map(|i| {
+                let mut test = assigned[i].clone();
+                test.sort();
+                let mut train = assigned
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .flat_map(|(_, query_ids)| query_ids.iter().cloned())
+                    .collect::<Vec<_>>();
+                train.sort();
+                TopicFold { train, test }
+            })
+            .collect();
+        Ok(folds)
+    }
+}
+
+/// Partition of topics into training, development, and testing topics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicSplit<K> {
+    train: Vec<K>,
+    dev: Vec<K>,
+    test: Vec<K>,
+}
+
+impl<K> TopicSplit<K> {
+    /// Topics for training, sorted in ascending order.
+    pub fn train(&self) -> &[K] {
+        &self.train
+    }
+
+    /// Topics for development, sorted in ascending order.
+    pub fn dev(&self) -> &[K] {
+        &self.dev
+    }
+
+    /// Topics for testing, sorted in ascending order.
+    pub fn test(&self) -> &[K] {
+        &self.test
+    }
+}
+
+/// Holdout splitter of topics into training, development, and testing topics.
+///
+/// The numbers of training and development topics are the ratios of the total number rounded down,
+/// and the remaining topics are for testing.
+///
+/// # Default parameters
+///
+/// * `random_state`: `None`
+#[derive(Debug, Clone, Copy)]
+pub struct HoldoutSplitter {
+    train_ratio: f64,
+    dev_ratio: f64,
+    random_state: Option<u64>,
+}
+
+impl HoldoutSplitter {
+    /// Creates a new splitter with the ratios of training and development topics.
+    pub const fn new(train_ratio: f64, dev_ratio: f64) -> Self {
+        Self {
+            train_ratio,
+            dev_ratio,
+            random_state: None,
+        }
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Splits the topics at random.
+    ///
+    /// Duplicated topics are merged.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if a ratio is not in the range [0, 1] or their sum exceeds 1.
+    pub fn split<I, K>(&self, query_ids: I) -> Result<TopicSplit<K>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Clone + Ord,
+    {
+        let in_range = |r: f64| (0.0..=1.0).contains(&r);
+        if !in_range(self.train_ratio)
+            || !in_range(self.dev_ratio)
+            || self.train_ratio + self.dev_ratio > 1.0
+        {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The ratios must be in the range [0, 1] and sum to at most 1, but got train_ratio={} and dev_ratio={}.",
+                self.train_ratio, self.dev_ratio
+            )));
+        }
+
+        let mut query_ids = query_ids
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+        query_ids.shuffle(&mut rng);
+
+        let n_topics = query_ids.len() as f64;
+        let n_train = (n_topics * self.train_ratio) as usize;
+        let n_dev = (n_topics * self.dev_ratio) as usize;
+        let mut test = query_ids.split_off(n_train + n_dev);
+        let mut dev = query_ids.split_off(n_train);
+        let mut train = query_ids;
+        train.sort();
+        dev.sort();
+        test.sort();
+        Ok(TopicSplit { train, dev, test })
+    }
+}
+
+/// Struct to store the results of cross-validation.
+pub struct CrossValidation<K> {
+    evaluations: Vec<Evaluation<K>>,
+    mean: f64,
+    variance: f64,
+}
+
+impl<K> CrossValidation<K> {
+    /// Returns the evaluated results on the test topics of each fold.
+    pub fn evaluations(&self) -> &[Evaluation<K>] {
+        &self.evaluations
+    }
+
+    /// Returns the mean of the per-fold mean scores.
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the variance of the per-fold mean scores.
+    pub const fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    /// Returns the standard deviation of the per-fold mean scores.
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+/// Evaluates the given predicted relevance scores on the test topics of each fold.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `folds` is empty.
+/// * [`ElinorError::InvalidArgument`] if a fold has no test topics.
+/// * [`ElinorError::MissingEntry`] if a test topic is not in `pred_rels`.
+///
+/// See [`metrics::compute_metric`](crate::metrics::compute_metric) for the other possible errors.
+pub fn cross_validate<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    folds: &[TopicFold<K>],
+) -> Result<CrossValidation<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if folds.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The folds must not be empty.".to_string(),
+        ));
+    }
+    let scores = crate::metrics::compute_metric(true_rels, pred_rels, metric)?;
+    let mut evaluations = Vec::with_capacity(folds.len());
+    for fold in folds {
+        if fold.test().is_empty() {
+            return Err(ElinorError::InvalidArgument(
+                "The test topics of each fold must not be empty.".to_string(),
+            ));
+        }
+        let mut fold_scores = BTreeMap::new();
+        for query_id in fold.test() {
+            let score = scores.get(query_id).ok_or_else(|| {
+                ElinorError::MissingEntry(format!(
+                    "The test topics must be included in pred_rels, but {query_id} is missing"
+                ))
+            })?;
+            fold_scores.insert(query_id.clone(), *score);
+        }
+        evaluations.push(Evaluation::from_scores(metric, fold_scores));
+    }
+    let n_folds = evaluations.len() as f64;
+    let mean = evaluations.iter().map(|e| e.mean()).sum::<f64>() / n_folds;
+    let variance = evaluations
+        .iter()
+        .map(|e| (e.mean() - mean).powi(2))
+        .sum::<f64>()
+        / n_folds;
+    Ok(CrossValidation {
+        evaluations,
+        mean,
+        variance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PredRelStoreBuilder;
+    use crate::TrueRelStoreBuilder;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_kfold_splitter_split() {
+        let splitter = KFoldSplitter::new(3).with_random_state(42);
+        let folds = splitter.split(0..10).unwrap();
+        assert_eq!(folds.len(), 3);
+        let mut tests = folds
+            .iter()
+            .flat_map(|fold| fold.test().iter().copied())
+            .collect::<Vec<_>>();
+        tests.sort();
+        assert_eq!(tests, (0..10).collect::<Vec<_>>());
+        for fold in &folds {
+            assert!((3..=4).contains(&fold.test().len()));
+            assert_eq!(fold.train().len() + fold.test().len(), 10);
+            assert!(fold.test().iter().all(|q| !fold.train().contains(q)));
+        }
+    }
+
+    #[test]
+    fn test_kfold_splitter_split_random_state_consistency() {
+        let splitter = KFoldSplitter::new(3).with_random_state(42);
+        let folds = splitter.split(0..10).unwrap();
+        for _ in 0..10 {
+            assert_eq!(splitter.split(0..10).unwrap(), folds);
+        }
+    }
+
+    #[test]
+    fn test_kfold_splitter_split_stratified() {
+        let strata = (0..8)
+            .map(|i| (i, if i < 4 { 'a' } else { 'b' }))
+            .collect::<BTreeMap<_, _>>();
+        let splitter = KFoldSplitter::new(2).with_random_state(42);
+        let folds = splitter.split_stratified(&strata).unwrap();
+        for fold in &folds {
+            let n_a = fold.test().iter().filter(|q| strata[q] == 'a').count();
+            let n_b = fold.test().iter().filter(|q| strata[q] == 'b').count();
+            assert_eq!(n_a, 2);
+            assert_eq!(n_b, 2);
+        }
+    }
+
+    #[test]
+    fn test_holdout_splitter_split() {
+        let splitter = HoldoutSplitter::new(0.6, 0.2).with_random_state(42);
+        let split = splitter.split(0..10).unwrap();
+        assert_eq!(split.train().len(), 6);
+        assert_eq!(split.dev().len(), 2);
+        assert_eq!(split.test().len(), 2);
+        let mut all = [split.train(), split.dev(), split.test()].concat();
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+        assert_eq!(splitter.split(0..10).unwrap(), split);
+    }
+
+    #[test]
+    fn test_holdout_splitter_split_invalid_ratios() {
+        let splitter = HoldoutSplitter::new(0.8, 0.3);
+        assert_eq!(
+            splitter.split(0..10).err(),
+            Some(ElinorError::InvalidArgument(
+                "The ratios must be in the range [0, 1] and sum to at most 1, but got train_ratio=0.8 and dev_ratio=0.3.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_kfold_splitter_split_invalid_n_folds() {
+        let splitter = KFoldSplitter::new(1);
+        assert_eq!(
+            splitter.split(0..10),
+            Err(ElinorError::InvalidArgument(
+                "The number of folds must be at least two.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_kfold_splitter_split_too_few_topics() {
+        let splitter = KFoldSplitter::new(3);
+        assert_eq!(
+            splitter.split(0..2),
+            Err(ElinorError::InvalidArgument(
+                "The number of topics must be at least the number of folds, but got n_topics=2 and n_folds=3.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_cross_validate() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('b', 'x', 1).unwrap();
+        b.add_record('c', 'x', 1).unwrap();
+        b.add_record('d', 'x', 1).unwrap();
+        let true_rels = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 0.5.into()).unwrap();
+        b.add_record('b', 'x', 0.5.into()).unwrap();
+        b.add_record('c', 'y', 0.5.into()).unwrap();
+        b.add_record('d', 'y', 0.5.into()).unwrap();
+        let pred_rels = b.build();
+
+        let folds = vec![
+            TopicFold::new(vec!['d', 'c'], vec!['b', 'a']),
+            TopicFold::new(vec!['a', 'b'], vec!['c', 'd']),
+        ];
+        let result =
+            cross_validate(&true_rels, &pred_rels, Metric::Success { k: 0 }, &folds).unwrap();
+        assert_eq!(result.evaluations().len(), 2);
+        assert_relative_eq!(result.evaluations()[0].mean(), 1.0);
+        assert_relative_eq!(result.evaluations()[1].mean(), 0.0);
+        assert_relative_eq!(result.mean(), 0.5);
+        assert_relative_eq!(result.variance(), 0.25);
+        assert_relative_eq!(result.std_dev(), 0.5);
+    }
+
+    #[test]
+    fn test_topic_fold_new() {
+        let fold = TopicFold::new(vec![3, 1], vec![2, 0]);
+        assert_eq!(fold.train(), &[1, 3]);
+        assert_eq!(fold.test(), &[0, 2]);
+    }
+
+    #[test]
+    fn test_cross_validate_empty_test_topics() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        let true_rels = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 0.5.into()).unwrap();
+        let pred_rels = b.build();
+
+        let folds = vec![TopicFold::new(vec!['a'], vec![])];
+        assert_eq!(
+            cross_validate(&true_rels, &pred_rels, Metric::Success { k: 0 }, &folds).err(),
+            Some(ElinorError::InvalidArgument(
+                "The test topics of each fold must not be empty.".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! Discriminative power of a metric estimated by the swap method.
+//!
+//! A metric is useful for comparing systems only if its differences are consistent
+//! across topic sets, i.e., a difference observed on some topics is not reversed on other topics.
+//! This module provides [`discriminative_power`] to estimate, with the randomized swap method,
+//! the proportion of system pairs that a metric can distinguish on the user's own collection.
+//!
+//! # Swap method
+//!
+//! For each trial, two disjoint subsets of $`c`$ topics, $`Q_1`$ and $`Q_2`$, are randomly drawn.
+//! For every pair of systems, the mean differences $`d_1`$ on $`Q_1`$ and $`d_2`$ on $`Q_2`$ are computed,
+//! and a *swap* occurs if their signs are opposite.
+//! The pairs are binned by $`|d_1|`$, and the swap rate of each bin is the number of swaps divided by that of pairs.
+//!
+//! The required difference $`\Delta`$ is the lower edge of the smallest bin
+//! such that the swap rates of the bin and all the larger bins are at most a given level $`\alpha`$.
+//! The discriminative power is the proportion of system pairs
+//! whose mean differences over all the topics are at least $`\Delta`$.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{Evaluation, Metric};
+//! use elinor::discriminative_power::DiscriminativePowerEstimator;
+//!
+//! let metric = Metric::AP { k: 0 };
+//! let topics = (0..20).map(|i| format!("q_{i}")).collect::<Vec<_>>();
+//! let evaluations = (0..5)
+//!     .map(|j| {
+//!         let scores = topics
+//!             .iter()
+//!             .enumerate()
+//!             .map(|(i, t)| (t.clone(), (0.1 * j as f64 + 0.05 * (i % 4) as f64).min(1.0)))
+//!             .collect();
+//!         Evaluation::from_scores(metric, scores)
+//!     })
+//!     .collect::<Vec<_>>();
+//!
+//! let result = DiscriminativePowerEstimator::new()
+//!     .with_n_trials(100)
+//!     .with_random_state(42)
+//!     .estimate(&evaluations)?;
+//! assert_eq!(result.n_pairs(), 10);
+//! assert!((0.0..=1.0).contains(&result.discriminative_power()));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! * Ellen M. Voorhees and Chris Buckley.
+//!   [The effect of topic set size on retrieval experiment error](https://doi.org/10.1145/564376.564432).
+//!   SIGIR 2002.
+//! * Tetsuya Sakai.
+//!   [On the reliability of information retrieval metrics based on graded relevance](https://doi.org/10.1016/j.ipm.2006.07.020).
+//!   Information Processing & Management, 2007.
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::tuples_from_evaluations;
+use crate::Evaluation;
+
+/// Bin of the absolute mean differences in the swap method.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapBin {
+    /// Lower edge of the bin (inclusive).
+    pub lower: f64,
+
+    /// Number of system pairs observed in the bin over the trials.
+    pub n_pairs: usize,
+
+    /// Number of swaps observed in the bin over the trials.
+    pub n_swaps: usize,
+}
+
+impl SwapBin {
+    /// Swap rate of the bin, or NaN if no pair is observed.
+    pub fn swap_rate(&self) -> f64 {
+        self.n_swaps as f64 / self.n_pairs as f64
+    }
+}
+
+/// Result of the discriminative power estimation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscriminativePower {
+    n_systems: usize,
+    n_topics: usize,
+    subset_size: usize,
+    n_trials: usize,
+    random_state: u64,
+    significance_level: f64,
+    bins: Vec<SwapBin>,
+    required_difference: f64,
+    discriminative_power: f64,
+}
+
+impl DiscriminativePower {
+    /// Number of systems.
+    pub const fn n_systems(&self) -> usize {
+        self.n_systems
+    }
+
+    /// Number of system pairs.
+    pub const fn n_pairs(&self) -> usize {
+        self.n_systems * (self.n_systems - 1) / 2
+    }
+
+    /// Number of topics.
+    pub const fn n_topics(&self) -> usize {
+        self.n_topics
+    }
+
+    /// Number of topics in each subset, $`c`$.
+    pub const fn subset_size(&self) -> usize {
+        self.subset_size
+    }
+
+    /// Number of trials.
+    pub const fn n_trials(&self) -> usize {
+        self.n_trials
+    }
+
+    /// Random state used in the trials.
+    pub const fn random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    /// Maximum swap rate allowed, $`\alpha`$.
+    pub const fn significance_level(&self) -> f64 {
+        self.significance_level
+    }
+
+    /// Bins of the absolute mean differences in ascending order, excluding empty ones.
+    pub fn bins(&self) -> &[SwapBin] {
+        &self.bins
+    }
+
+    /// Required difference, $`\Delta`$, or infinity if no bin meets the swap rate.
+    pub const fn required_difference(&self) -> f64 {
+        self.required_difference
+    }
+
+    /// Discriminative power, i.e., the proportion of system pairs
+    /// whose mean differences over all the topics are at least the required difference.
+    pub const fn discriminative_power(&self) -> f64 {
+        self.discriminative_power
+    }
+}
+
+/// Estimator of the discriminative power with the swap method.
+///
+/// # Default parameters
+///
+/// * `n_trials`: `1000`
+/// * `subset_size`: `None`, meaning half the number of topics
+/// * `bin_width`: `0.01`
+/// * `significance_level`: `0.05`
+/// * `random_state`: `None`
+#[derive(Debug, Clone, Copy)]
+pub struct DiscriminativePowerEstimator {
+    n_trials: usize,
+    subset_size: Option<usize>,
+    bin_width: f64,
+    significance_level: f64,
+    random_state: Option<u64>,
+}
+
+impl Default for DiscriminativePowerEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiscriminativePowerEstimator {
+    /// Creates a new estimator.
+    pub const fn new() -> Self {
+        Self {
+            n_trials: 1000,
+            subset_size: None,
+            bin_width: 0.01,
+            significance_level: 0.05,
+            random_state: None,
+        }
+    }
+
+    /// Sets the number of trials.
+    ///
+    /// If the input is less than `1`, it is modified to `1`.
+    pub fn with_n_trials(mut self, n_trials: usize) -> Self {
+        self.n_trials = n_trials.max(1);
+        self
+    }
+
+    /// Sets the number of topics in each subset.
+    pub const fn with_subset_size(mut self, subset_size: usize) -> Self {
+        self.subset_size = Some(subset_size);
+        self
+    }
+
+    /// Sets the width of the bins of the absolute mean differences.
+    pub const fn with_bin_width(mut self, bin_width: f64) -> Self {
+        self.bin_width = bin_width;
+        self
+    }
+
+    /// Sets the maximum swap rate allowed.
+    pub const fn with_significance_level(mut self, significance_level: f64) -> Self {
+        self.significance_level = significance_level;
+        self
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Estimates the discriminative power for the evaluations of systems with a metric,
+    /// paired by the topic ids.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the evaluations have different sets of topic ids.
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two systems.
+    /// * [`ElinorError::InvalidArgument`] if the subset size is zero or more than half the number of topics.
+    /// * [`ElinorError::InvalidArgument`] if the bin width is not positive and finite.
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn estimate<'a, I, K>(&self, evaluations: I) -> Result<DiscriminativePower>
+    where
+        I: IntoIterator<Item = &'a Evaluation<K>>,
+        K: Clone + Eq + Ord + std::fmt::Display + 'a,
+    {
+        let evaluations = evaluations.into_iter().collect::<Vec<_>>();
+        let n_systems = evaluations.len();
+        if n_systems < 2 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two systems.".to_string(),
+            ));
+        }
+        let tuples = tuples_from_evaluations(evaluations)?;
+        let n_topics = tuples.len();
+        let subset_size = self.subset_size.unwrap_or(n_topics / 2);
+        if subset_size == 0 || subset_size * 2 > n_topics {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The subset size must be in the range [1, {}], but got {subset_size}.",
+                n_topics / 2
+            )));
+        }
+        if !(self.bin_width > 0.0 && self.bin_width.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The bin width must be positive and finite.".to_string(),
+            ));
+        }
+        if self.significance_level <= 0.0 || self.significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+
+        let subset_means = |topics: &[usize]| {
+            (0..n_systems)
+                .map(|j| topics.iter().map(|&i| tuples[i][j]).sum::<f64>() / topics.len() as f64)
+                .collect::<Vec<_>>()
+        };
+        let mut n_pairs_in_bins: Vec<usize> = vec![];
+        let mut n_swaps_in_bins: Vec<usize> = vec![];
+        let mut topics = (0..n_topics).collect::<Vec<_>>();
+        for _ in 0..self.n_trials {
+            topics.shuffle(&mut rng);
+            let means_1 = subset_means(&topics[..subset_size]);
+            let means_2 = subset_means(&topics[subset_size..subset_size * 2]);
+            for a in 0..n_systems {
+                for b in a + 1..n_systems {
+                    let d_1 = means_1[a] - means_1[b];
+                    let d_2 = means_2[a] - means_2[b];
+                    let bin = (d_1.abs() / self.bin_width).floor() as usize;
+                    if bin >= n_pairs_in_bins.len() {
+                        n_pairs_in_bins.resize(bin + 1, 0);
+                        n_swaps_in_bins.resize(bin + 1, 0);
+                    }
+                    n_pairs_in_bins[bin] += 1;
+                    if d_1 * d_2 < 0.0 {
+                        n_swaps_in_bins[bin] += 1;
+                    }
+                }
+            }
+        }
+        let bins = n_pairs_in_bins
+            .iter()
+            .zip(&n_swaps_in_bins)
+            .enumerate()
+            .filter(|(_, (&n_pairs, _))| n_pairs > 0)
+            .map(|(k, (&n_pairs, &n_swaps))| SwapBin {
+                lower: k as f64 * self.bin_width,
+                n_pairs,
+                n_swaps,
+            })
+            .collect::<Vec<_>>();
+
+        // The smallest bin such that it and all the larger bins meet the swap rate.
+        let mut required_difference = f64::INFINITY;
+        for bin in bins.iter().rev() {
+            if bin.swap_rate() > self.significance_level {
+                break;
+            }
+            required_difference = bin.lower;
+        }
+
+        let means = subset_means(&(0..n_topics).collect::<Vec<_>>());
+        let mut n_distinguished = 0;
+        for a in 0..n_systems {
+            for b in a + 1..n_systems {
+                if (means[a] - means[b]).abs() >= required_difference {
+                    n_distinguished += 1;
+                }
+            }
+        }
+        let n_pairs = n_systems * (n_systems - 1) / 2;
+
+        Ok(DiscriminativePower {
+            n_systems,
+            n_topics,
+            subset_size,
+            n_trials: self.n_trials,
+            random_state,
+            significance_level: self.significance_level,
+            bins,
+            required_difference,
+            discriminative_power: n_distinguished as f64 / n_pairs as f64,
+        })
+    }
+}
+
+/// Estimates the discriminative power with the default parameters of [`DiscriminativePowerEstimator`]
+/// except for the maximum swap rate allowed, $`\alpha`$.
+///
+/// # Errors
+///
+/// See [`DiscriminativePowerEstimator::estimate`].
+pub fn discriminative_power<'a, I, K>(
+    evaluations: I,
+    significance_level: f64,
+) -> Result<DiscriminativePower>
+where
+    I: IntoIterator<Item = &'a Evaluation<K>>,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    DiscriminativePowerEstimator::new()
+        .with_significance_level(significance_level)
+        .estimate(evaluations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metric;
+
+    fn evaluations(gaps: &[f64], n_topics: usize) -> Vec<Evaluation<usize>> {
+        let metric = Metric::AP { k: 0 };
+        gaps.iter()
+            .map(|&gap| {
+                let scores = (0..n_topics)
+                    .map(|i| (i, 0.1f64.mul_add((i % 5) as f64, gap)))
+                    .collect();
+                Evaluation::from_scores(metric, scores)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_discriminative_power_consistent_systems() {
+        // The differences are constant over the topics, so no swap occurs.
+        let result = DiscriminativePowerEstimator::new()
+            .with_n_trials(50)
+            .with_random_state(1)
+            .estimate(&evaluations(&[0.0, 0.1, 0.3], 10))
+            .unwrap();
+        assert_eq!(result.n_systems(), 3);
+        assert_eq!(result.n_pairs(), 3);
+        assert_eq!(result.n_topics(), 10);
+        assert_eq!(result.subset_size(), 5);
+        assert_eq!(result.n_trials(), 50);
+        assert!(result.bins().iter().all(|bin| bin.n_swaps == 0));
+        assert_eq!(
+            result.bins().iter().map(|bin| bin.n_pairs).sum::<usize>(),
+            150
+        );
+        assert!(result.required_difference() <= 0.1);
+        assert_eq!(result.discriminative_power(), 1.0);
+    }
+
+    #[test]
+    fn test_discriminative_power_noisy_systems() {
+        // The two systems alternately win by the same margin, so the mean difference is zero.
+        let metric = Metric::AP { k: 0 };
+        let a = (0..10).map(|i| (i, if i % 2 == 0 { 0.9 } else { 0.1 }));
+        let b = (0..10).map(|i| (i, if i % 2 == 0 { 0.1 } else { 0.9 }));
+        let evaluations = [
+            Evaluation::from_scores(metric, a.collect()),
+            Evaluation::from_scores(metric, b.collect()),
+        ];
+        let result = DiscriminativePowerEstimator::new()
+            .with_n_trials(200)
+            .with_random_state(1)
+            .estimate(&evaluations)
+            .unwrap();
+        assert!(result.bins().iter().any(|bin| bin.n_swaps > 0));
+        assert_eq!(result.discriminative_power(), 0.0);
+    }
+
+    #[test]
+    fn test_discriminative_power_reproducible() {
+        let evaluations = evaluations(&[0.0, 0.02, 0.05, 0.2], 12);
+        let estimator = DiscriminativePowerEstimator::new()
+            .with_n_trials(30)
+            .with_random_state(7);
+        assert_eq!(
+            estimator.estimate(&evaluations).unwrap(),
+            estimator.estimate(&evaluations).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_discriminative_power_invalid_arguments() {
+        let evals = evaluations(&[0.0, 0.1], 4);
+        assert_eq!(
+            discriminative_power(&evals[..1], 0.05),
+            Err(ElinorError::InvalidArgument(
+                "The input must have at least two systems.".to_string()
+            ))
+        );
+        assert_eq!(
+            DiscriminativePowerEstimator::new()
+                .with_subset_size(3)
+                .estimate(&evals),
+            Err(ElinorError::InvalidArgument(
+                "The subset size must be in the range [1, 2], but got 3.".to_string()
+            ))
+        );
+        assert_eq!(
+            DiscriminativePowerEstimator::new()
+                .with_bin_width(0.0)
+                .estimate(&evals),
+            Err(ElinorError::InvalidArgument(
+                "The bin width must be positive and finite.".to_string()
+            ))
+        );
+        assert_eq!(
+            discriminative_power(&evals, 0.0),
+            Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            ))
+        );
+    }
+}
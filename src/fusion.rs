@@ -0,0 +1,266 @@
+//! Rank fusion of predicted relevance scores.
+//!
+//! This module provides several methods to fuse multiple runs,
+//! i.e., [`PredRelStore`] instances, into a single run:
+//!
+//! * [`reciprocal_rank_fusion`] for Reciprocal Rank Fusion (RRF).
+//! * [`comb_sum`] for CombSUM.
+//! * [`comb_mnz`] for CombMNZ.
+//!
+//! The fused run is also a [`PredRelStore`], so it can be evaluated as it is.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::PredRelStoreBuilder;
+//! use elinor::fusion::reciprocal_rank_fusion;
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_1", "d_2", 0.4.into())?;
+//! let pred_rels_a = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_2", 0.9.into())?;
+//! b.add_record("q_1", "d_3", 0.8.into())?;
+//! let pred_rels_b = b.build();
+//!
+//! let fused = reciprocal_rank_fusion([&pred_rels_a, &pred_rels_b], 60.0)?;
+//! assert_eq!(fused.n_docs(), 3);
+//!
+//! // d_2 is ranked at 2nd and 1st, and thus placed at the top.
+//! let sorted = fused.get_sorted("q_1").unwrap();
+//! assert_eq!(sorted[0].doc_id, "d_2");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! * Gordon V. Cormack, Charles L. A. Clarke, and Stefan Buettcher.
+//!   [Reciprocal rank fusion outperforms condorcet and individual rank learning methods](https://doi.org/10.1145/1571941.1572114).
+//!   SIGIR 2009.
+//! * Edward A. Fox and Joseph A. Shaw.
+//!   Combination of multiple searches.
+//!   TREC-2, 1994.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::PredRelStore;
+use crate::PredRelStoreBuilder;
+use crate::PredScore;
+
+/// Fuses runs with Reciprocal Rank Fusion (RRF).
+///
+/// # Formula
+///
+/// ```math
+/// \text{RRF}(d) = \sum_{r \in R} \frac{1}{k + \text{rank}_r(d)}
+/// ```
+///
+/// where $`R`$ is the set of runs retrieving $`d`$,
+/// and $`\text{rank}_r(d)`$ is the 1-origin rank of $`d`$ in the run $`r`$.
+///
+/// # Arguments
+///
+/// * `pred_rels` - Runs to be fused.
+/// * `k` - Constant to mitigate the impact of high rankings, typically set to `60`.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `pred_rels` is empty.
+/// * [`ElinorError::InvalidArgument`] if `k` is negative or not finite.
+pub fn reciprocal_rank_fusion<'a, I, K>(pred_rels: I, k: f64) -> Result<PredRelStore<K>>
+where
+    I: IntoIterator<Item = &'a PredRelStore<K>>,
+    K: Eq + Ord + Clone + Display + 'a,
+{
+    if !k.is_finite() || k < 0.0 {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The constant k must be a non-negative finite value, but got {k}."
+        )));
+    }
+    fuse(pred_rels, |fused| {
+        fused.iter().map(|&(_, rank)| 1.0 / (k + rank as f64)).sum()
+    })
+}
+
+/// Fuses runs with CombSUM, the sum of the scores in the runs.
+///
+/// # Formula
+///
+/// ```math
+/// \text{CombSUM}(d) = \sum_{r \in R} s_r(d)
+/// ```
+///
+/// where $`R`$ is the set of runs retrieving $`d`$,
+/// and $`s_r(d)`$ is the score of $`d`$ in the run $`r`$.
+///
+/// # Notes
+///
+/// The scores are summed as they are.
+/// If the runs have different score ranges, normalize them beforehand
+/// with [`normalize_scores`](crate::normalization::normalize_scores).
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `pred_rels` is empty.
+pub fn comb_sum<'a, I, K>(pred_rels: I) -> Result<PredRelStore<K>>
+where
+    I: IntoIterator<Item = &'a PredRelStore<K>>,
+    K: Eq + Ord + Clone + Display + 'a,
+{
+    fuse(pred_rels, |fused| {
+        fused.iter().map(|&(score, _)| score).sum()
+    })
+}
+
+/// Fuses runs with CombMNZ, the CombSUM score multiplied by the number of runs retrieving the document.
+///
+/// # Formula
+///
+/// ```math
+/// \text{CombMNZ}(d) = |R| \times \text{CombSUM}(d)
+/// ```
+///
+/// where $`R`$ is the set of runs retrieving $`d`$.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `pred_rels` is empty.
+pub fn comb_mnz<'a, I, K>(pred_rels: I) -> Result<PredRelStore<K>>
+where
+    I: IntoIterator<Item = &'a PredRelStore<K>>,
+    K: Eq + Ord + Clone + Display + 'a,
+{
+    fuse(pred_rels, |fused| {
+        fused.len() as f64 * fused.iter().map(|&(score, _)| score).sum::<f64>()
+    })
+}
+
+/// Fuses runs with a given function that receives
+/// the list of (score, 1-origin rank) of a document in the runs retrieving it.
+fn fuse<'a, I, K, F>(pred_rels: I, fuse_fn: F) -> Result<PredRelStore<K>>
+where
+    I: IntoIterator<Item = &'a PredRelStore<K>>,
+    K: Eq + Ord + Clone + Display + 'a,
+    F: Fn(&[(f64, usize)]) -> f64,
+{
+    let pred_rels = pred_rels.into_iter().collect::<Vec<_>>();
+    if pred_rels.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one run.".to_string(),
+        ));
+    }
+    let mut fused: BTreeMap<K, BTreeMap<K, Vec<(f64, usize)>>> = BTreeMap::new();
+    for run in &pred_rels {
+        for query_id in run.query_ids() {
+            let docs = fused.entry(query_id.clone()).or_default();
+            for (i, rel) in run.get_sorted(query_id).unwrap().iter().enumerate() {
+                docs.entry(rel.doc_id.clone())
+                    .or_default()
+                    .push((rel.score.into_inner(), i + 1));
+            }
+        }
+    }
+    let mut b = PredRelStoreBuilder::new();
+    for (query_id, docs) in fused {
+        for (doc_id, entries) in docs {
+            let score = fuse_fn(&entries);
+            b.add_record(query_id.clone(), doc_id, PredScore::from(score))?;
+        }
+    }
+    Ok(b.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn runs() -> (PredRelStore<char>, PredRelStore<char>) {
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 0.5.into()).unwrap();
+        b.add_record('a', 'y', 0.4.into()).unwrap();
+        b.add_record('b', 'x', 0.1.into()).unwrap();
+        let run_1 = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'y', 0.9.into()).unwrap();
+        b.add_record('a', 'z', 0.8.into()).unwrap();
+        let run_2 = b.build();
+
+        (run_1, run_2)
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let (run_1, run_2) = runs();
+        let fused = reciprocal_rank_fusion([&run_1, &run_2], 60.0).unwrap();
+        assert_eq!(fused.n_queries(), 2);
+        assert_eq!(fused.n_docs(), 4);
+        assert_relative_eq!(
+            fused.get_score(&'a', &'x').unwrap().into_inner(),
+            1.0 / 61.0
+        );
+        assert_relative_eq!(
+            fused.get_score(&'a', &'y').unwrap().into_inner(),
+            1.0 / 62.0 + 1.0 / 61.0
+        );
+        assert_relative_eq!(
+            fused.get_score(&'a', &'z').unwrap().into_inner(),
+            1.0 / 62.0
+        );
+        assert_relative_eq!(
+            fused.get_score(&'b', &'x').unwrap().into_inner(),
+            1.0 / 61.0
+        );
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion_invalid_k() {
+        let (run_1, run_2) = runs();
+        assert_eq!(
+            reciprocal_rank_fusion([&run_1, &run_2], -1.0).err(),
+            Some(ElinorError::InvalidArgument(
+                "The constant k must be a non-negative finite value, but got -1.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_comb_sum() {
+        let (run_1, run_2) = runs();
+        let fused = comb_sum([&run_1, &run_2]).unwrap();
+        assert_relative_eq!(fused.get_score(&'a', &'x').unwrap().into_inner(), 0.5);
+        assert_relative_eq!(fused.get_score(&'a', &'y').unwrap().into_inner(), 0.4 + 0.9);
+        assert_relative_eq!(fused.get_score(&'a', &'z').unwrap().into_inner(), 0.8);
+        assert_relative_eq!(fused.get_score(&'b', &'x').unwrap().into_inner(), 0.1);
+    }
+
+    #[test]
+    fn test_comb_mnz() {
+        let (run_1, run_2) = runs();
+        let fused = comb_mnz([&run_1, &run_2]).unwrap();
+        assert_relative_eq!(fused.get_score(&'a', &'x').unwrap().into_inner(), 0.5);
+        assert_relative_eq!(
+            fused.get_score(&'a', &'y').unwrap().into_inner(),
+            2.0 * (0.4 + 0.9)
+        );
+        assert_relative_eq!(fused.get_score(&'a', &'z').unwrap().into_inner(), 0.8);
+    }
+
+    #[test]
+    fn test_fuse_empty() {
+        let runs: Vec<&PredRelStore<char>> = vec![];
+        assert_eq!(
+            comb_sum(runs).err(),
+            Some(ElinorError::InvalidArgument(
+                "The input must have at least one run.".to_string()
+            ))
+        );
+    }
+}
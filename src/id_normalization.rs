@@ -0,0 +1,384 @@
+//! Normalization of query and document ids.
+//!
+//! True and predicted relevance scores are often produced by different tools,
+//! whose ids can differ only in their surface forms (e.g., `D_1` and `d_1`, or
+//! `http://example.com/a#top` and `http://example.com/a`).
+//! Such mismatches silently lead to zero scores since no document is matched.
+//! This module provides [`IdNormalizer`] to apply the same [`IdNormalization`] steps
+//! to the ids of both relevance stores, and [`map_ids`] for custom callbacks.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::TrueRelStoreBuilder;
+//! use elinor::id_normalization::{IdNormalization, IdNormalizer};
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("Q_1".to_string(), "http://example.com/A#top".to_string(), 1)?;
+//! b.add_record("Q_1".to_string(), "http://example.com/B".to_string(), 0)?;
+//! let true_rels = b.build();
+//!
+//! let normalizer = IdNormalizer::new()
+//!     .with_query_steps(vec![IdNormalization::Lowercase])
+//!     .with_doc_steps(vec![IdNormalization::StripUrlFragment, IdNormalization::Lowercase]);
+//! let true_rels = normalizer.apply(&true_rels)?;
+//!
+//! assert_eq!(true_rels.get_score("q_1", "http://example.com/a"), Some(&1));
+//! assert_eq!(true_rels.get_score("q_1", "http://example.com/b"), Some(&0));
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::relevance::Record;
+use crate::relevance::RelevanceStore;
+
+/// Normalization step of ids.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::id_normalization::IdNormalization;
+///
+/// assert_eq!("lowercase".parse(), Ok(IdNormalization::Lowercase));
+/// assert_eq!("strip_fragment".parse(), Ok(IdNormalization::StripUrlFragment));
+/// assert_eq!("md5".parse(), Ok(IdNormalization::Md5));
+///
+/// assert_eq!(IdNormalization::Lowercase.normalize("D_1"), "d_1");
+/// assert_eq!(IdNormalization::StripUrlFragment.normalize("http://a.org/b#c"), "http://a.org/b");
+/// assert_eq!(IdNormalization::Md5.normalize("abc"), "900150983cd24fb0d6963f7d28e17f72");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdNormalization {
+    /// Converts the id into lowercase.
+    Lowercase,
+
+    /// Removes the URL fragment, i.e., the suffix starting with the first `#`.
+    StripUrlFragment,
+
+    /// Replaces the id with the lowercase hexadecimal MD5 digest of its UTF-8 bytes.
+    Md5,
+}
+
+impl IdNormalization {
+    /// Returns the normalized id.
+    pub fn normalize(&self, id: &str) -> String {
+        match self {
+            Self::Lowercase => id.to_lowercase(),
+            Self::StripUrlFragment => id.split('#').next().unwrap_or(id).to_string(),
+            Self::Md5 => md5_hex(id.as_bytes()),
+        }
+    }
+}
+
+impl FromStr for IdNormalization {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lowercase" => Ok(Self::Lowercase),
+            "strip_fragment" => Ok(Self::StripUrlFragment),
+            "md5" => Ok(Self::Md5),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported id normalization: {s}"
+            ))),
+        }
+    }
+}
+
+impl Display for IdNormalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Lowercase => write!(f, "lowercase"),
+            Self::StripUrlFragment => write!(f, "strip_fragment"),
+            Self::Md5 => write!(f, "md5"),
+        }
+    }
+}
+
+/// Normalizer of query and document ids, applying the steps in order.
+///
+/// # Default parameters
+///
+/// * `query_steps`: empty
+/// * `doc_steps`: empty
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IdNormalizer {
+    query_steps: Vec<IdNormalization>,
+    doc_steps: Vec<IdNormalization>,
+}
+
+impl IdNormalizer {
+    /// Creates a new normalizer, which keeps the ids as they are.
+    pub const fn new() -> Self {
+        Self {
+            query_steps: Vec::new(),
+            doc_steps: Vec::new(),
+        }
+    }
+
+    /// Sets the steps for query ids.
+    pub fn with_query_steps(mut self, steps: Vec<IdNormalization>) -> Self {
+        self.query_steps = steps;
+        self
+    }
+
+    /// Sets the steps for document ids.
+    pub fn with_doc_steps(mut self, steps: Vec<IdNormalization>) -> Self {
+        self.doc_steps = steps;
+        self
+    }
+
+    /// Returns `true` if no step is set.
+    pub fn is_identity(&self) -> bool {
+        self.query_steps.is_empty() && self.doc_steps.is_empty()
+    }
+
+    /// Returns the normalized query id.
+    pub fn normalize_query_id(&self, query_id: &str) -> String {
+        normalize_with_steps(&self.query_steps, query_id)
+    }
+
+    /// Returns the normalized document id.
+    pub fn normalize_doc_id(&self, doc_id: &str) -> String {
+        normalize_with_steps(&self.doc_steps, doc_id)
+    }
+
+    /// Normalizes the ids of the records.
+    ///
+    /// The output may have duplicate query-document pairs if different ids are normalized into the same one.
+    pub fn normalize_records<T, I>(&self, records: I) -> Vec<Record<String, T>>
+    where
+        I: IntoIterator<Item = Record<String, T>>,
+    {
+        records
+            .into_iter()
+            .map(|record| Record {
+                query_id: self.normalize_query_id(&record.query_id),
+                doc_id: self.normalize_doc_id(&record.doc_id),
+                score: record.score,
+            })
+            .collect()
+    }
+
+    /// Returns the relevance store with the normalized ids.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::DuplicateEntry`] if different query-document pairs are normalized into the same one.
+    pub fn apply<T>(&self, rels: &RelevanceStore<String, T>) -> Result<RelevanceStore<String, T>>
+    where
+        T: Ord + Clone,
+    {
+        map_ids(
+            rels,
+            |query_id| self.normalize_query_id(query_id),
+            |doc_id| self.normalize_doc_id(doc_id),
+        )
+    }
+}
+
+/// Returns the relevance store with the ids transformed by custom callbacks.
+///
+/// # Arguments
+///
+/// * `rels` - Relevance store.
+/// * `query_fn` - Callback for query ids.
+/// * `doc_fn` - Callback for document ids.
+///
+/// # Errors
+///
+/// * [`ElinorError::DuplicateEntry`] if different query-document pairs are transformed into the same one.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::PredRelStoreBuilder;
+/// use elinor::id_normalization::map_ids;
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1".to_string(), "doc-1 ".to_string(), 0.5.into())?;
+/// let pred_rels = b.build();
+///
+/// let pred_rels = map_ids(&pred_rels, |q| q.to_string(), |d| d.trim().replace('-', "_"))?;
+/// assert_eq!(pred_rels.get_score("q_1", "doc_1"), Some(&0.5.into()));
+/// # Ok(())
+/// # }
+/// ```
+pub fn map_ids<T, F, G>(
+    rels: &RelevanceStore<String, T>,
+    query_fn: F,
+    doc_fn: G,
+) -> Result<RelevanceStore<String, T>>
+where
+    T: Ord + Clone,
+    F: Fn(&str) -> String,
+    G: Fn(&str) -> String,
+{
+    let records = rels.records().into_iter().map(|record| Record {
+        query_id: query_fn(&record.query_id),
+        doc_id: doc_fn(&record.doc_id),
+        score: record.score,
+    });
+    RelevanceStore::from_records(records).map_err(|e| match e {
+        ElinorError::DuplicateEntry(msg) => ElinorError::DuplicateEntry(format!(
+            "Different ids were normalized into the same one. {msg}"
+        )),
+        e => e,
+    })
+}
+
+fn normalize_with_steps(steps: &[IdNormalization], id: &str) -> String {
+    steps
+        .iter()
+        .fold(id.to_string(), |id, step| step.normalize(&id))
+}
+
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, //
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, //
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, //
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, //
+    0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501, //
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, //
+    0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, //
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, //
+    0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8, //
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, //
+    0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, //
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, //
+    0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, //
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, //
+    0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, //
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, //
+    0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1, //
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, //
+    0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// Computes the MD5 digest (RFC 1321) in lowercase hexadecimal.
+fn md5_hex(data: &[u8]) -> String {
+    let mut message = data.to_vec();
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bit_len.to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for chunk in message.chunks(64) {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(chunk.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f
+                .wrapping_add(a)
+                .wrapping_add(MD5_CONSTANTS[i])
+                .wrapping_add(words[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_SHIFTS[i]));
+        }
+        for (s, x) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(x);
+        }
+    }
+    state
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrueRelStoreBuilder;
+
+    #[test]
+    fn test_md5_hex() {
+        // Test suite in RFC 1321.
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"a"), "0cc175b9c0f1b6a831c399e269772661");
+        assert_eq!(md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            md5_hex(b"message digest"),
+            "f96b697d7cb7938d525a2f31aaf161d0"
+        );
+        assert_eq!(
+            md5_hex(b"abcdefghijklmnopqrstuvwxyz"),
+            "c3fcd3d76192e4007dfb496cca67e13b"
+        );
+        assert_eq!(
+            md5_hex(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn test_strip_url_fragment() {
+        let step = IdNormalization::StripUrlFragment;
+        assert_eq!(step.normalize("http://a.org/b#c#d"), "http://a.org/b");
+        assert_eq!(step.normalize("http://a.org/b"), "http://a.org/b");
+        assert_eq!(step.normalize("#c"), "");
+    }
+
+    #[test]
+    fn test_id_normalization_from_str_invalid() {
+        assert_eq!(
+            "upper".parse::<IdNormalization>(),
+            Err(ElinorError::InvalidFormat(
+                "Unsupported id normalization: upper".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_normalizer_order_of_steps() {
+        let normalizer = IdNormalizer::new()
+            .with_doc_steps(vec![IdNormalization::Lowercase, IdNormalization::Md5]);
+        assert_eq!(
+            normalizer.normalize_doc_id("ABC"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(normalizer.normalize_query_id("ABC"), "ABC");
+        assert!(!normalizer.is_identity());
+        assert!(IdNormalizer::new().is_identity());
+    }
+
+    #[test]
+    fn test_normalizer_apply_collision() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record("q_1".to_string(), "D_1".to_string(), 1)
+            .unwrap();
+        b.add_record("q_1".to_string(), "d_1".to_string(), 0)
+            .unwrap();
+        let true_rels = b.build();
+        let normalizer = IdNormalizer::new().with_doc_steps(vec![IdNormalization::Lowercase]);
+        assert!(matches!(
+            normalizer.apply(&true_rels),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+    }
+}
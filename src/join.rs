@@ -0,0 +1,248 @@
+//! Join of true and predicted relevance scores by query.
+//!
+//! The metrics are computed from the predicted relevance scores of each query
+//! sorted in descending order, joined with the true relevance scores of the same query.
+//! This module provides [`joined_iter`] to iterate over the joined views of the queries,
+//! so that custom metrics, explanations, and exporters can reuse the validated join
+//! without re-implementing the checks of missing queries.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder};
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_1", "d_2", 0)?;
+//! b.add_record("q_2", "d_3", 2)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.3.into())?;
+//! b.add_record("q_1", "d_2", 0.5.into())?;
+//! b.add_record("q_2", "d_4", 0.4.into())?;
+//! let pred_rels = b.build();
+//!
+//! for query in elinor::joined_iter(&true_rels, &pred_rels)? {
+//!     let top = &query.sorted_preds()[0];
+//!     println!("{}: {} is judged as {:?}", query.query_id(), top.doc_id, query.true_score(&top.doc_id));
+//! }
+//!
+//! let queries = elinor::joined_iter(&true_rels, &pred_rels)?.collect::<Vec<_>>();
+//! assert_eq!(queries.len(), 2);
+//! assert_eq!(queries[0].query_id(), &"q_1");
+//! assert_eq!(queries[0].sorted_preds()[0].doc_id, "d_2");
+//! assert_eq!(queries[0].true_score(&"d_2"), Some(0));
+//! assert_eq!(queries[1].true_score(&"d_4"), None);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
+use crate::errors::ElinorError;
+use crate::relevance::RelevanceData;
+use crate::PredRelStore;
+use crate::PredScore;
+use crate::Relevance;
+use crate::TrueRelStore;
+use crate::TrueScore;
+
+/// View of a query joining the true and predicted relevance scores.
+#[derive(Debug)]
+pub struct JoinedQuery<'a, K> {
+    query_id: &'a K,
+    trues: &'a BTreeMap<K, TrueScore>,
+    sorted_trues: &'a [Relevance<K, TrueScore>],
+    sorted_preds: &'a [Relevance<K, PredScore>],
+}
+
+impl<K> Clone for JoinedQuery<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for JoinedQuery<'_, K> {}
+
+impl<'a, K> JoinedQuery<'a, K> {
+    /// Returns the query id.
+    pub const fn query_id(&self) -> &'a K {
+        self.query_id
+    }
+
+    /// Returns the mapping from document ids to the true relevance scores of the query.
+    pub const fn trues(&self) -> &'a BTreeMap<K, TrueScore> {
+        self.trues
+    }
+
+    /// Returns the true relevance scores of the query sorted in descending order.
+    pub const fn sorted_trues(&self) -> &'a [Relevance<K, TrueScore>] {
+        self.sorted_trues
+    }
+
+    /// Returns the predicted relevance scores of the query sorted in descending order.
+    pub const fn sorted_preds(&self) -> &'a [Relevance<K, PredScore>] {
+        self.sorted_preds
+    }
+
+    /// Returns the true relevance score of the document, or `None` if it is not judged.
+    pub fn true_score(&self, doc_id: &K) -> Option<TrueScore>
+    where
+        K: Ord,
+    {
+        self.trues.get(doc_id).copied()
+    }
+}
+
+/// Iterator over the queries joining the true and predicted relevance scores,
+/// created by [`joined_iter`].
+pub struct JoinedIter<'a, K> {
+    true_rels: &'a TrueRelStore<K>,
+    pred_rels: &'a PredRelStore<K>,
+    query_ids: btree_map::Keys<'a, K, RelevanceData<K, PredScore>>,
+}
+
+impl<'a, K> Iterator for JoinedIter<'a, K>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    type Item = JoinedQuery<'a, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let query_id = self.query_ids.next()?;
+        // The queries are checked to exist in both stores when the iterator is created.
+        Some(JoinedQuery {
+            query_id,
+            trues: self.true_rels.get_map(query_id).unwrap(),
+            sorted_trues: self.true_rels.get_sorted(query_id).unwrap(),
+            sorted_preds: self.pred_rels.get_sorted(query_id).unwrap(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.query_ids.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for JoinedIter<'_, K> where K: Clone + Eq + Ord + std::fmt::Display {}
+
+/// Returns an iterator over the queries in the predicted relevance scores in ascending order of query ids,
+/// joined with the true relevance scores of the same queries.
+///
+/// This is the join used by the metric computations such as [`crate::metrics::compute_metric`].
+///
+/// # Errors
+///
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+pub fn joined_iter<'a, K>(
+    true_rels: &'a TrueRelStore<K>,
+    pred_rels: &'a PredRelStore<K>,
+) -> Result<JoinedIter<'a, K>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    crate::metrics::check_queries(true_rels, pred_rels)?;
+    Ok(JoinedIter {
+        true_rels,
+        pred_rels,
+        query_ids: pred_rels.query_id_keys(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Record;
+
+    #[test]
+    fn test_joined_iter() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 2,
+            },
+            Record {
+                query_id: 'B',
+                doc_id: 'X',
+                score: 0,
+            },
+            Record {
+                query_id: 'C',
+                doc_id: 'X',
+                score: 1,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'B',
+                doc_id: 'Y',
+                score: 0.2.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 0.7.into(),
+            },
+        ])
+        .unwrap();
+        let iter = joined_iter(&true_rels, &pred_rels).unwrap();
+        assert_eq!(iter.len(), 2);
+        let queries = iter.collect::<Vec<_>>();
+        assert_eq!(queries[0].query_id(), &'A');
+        assert_eq!(
+            queries[0]
+                .sorted_preds()
+                .iter()
+                .map(|rel| rel.doc_id)
+                .collect::<Vec<_>>(),
+            vec!['Z', 'X']
+        );
+        assert_eq!(
+            queries[0]
+                .sorted_trues()
+                .iter()
+                .map(|rel| rel.doc_id)
+                .collect::<Vec<_>>(),
+            vec!['Y', 'X']
+        );
+        assert_eq!(queries[0].trues().len(), 2);
+        assert_eq!(queries[0].true_score(&'X'), Some(1));
+        assert_eq!(queries[0].true_score(&'Z'), None);
+        assert_eq!(queries[1].query_id(), &'B');
+        assert_eq!(queries[1].true_score(&'X'), Some(0));
+    }
+
+    #[test]
+    fn test_joined_iter_missing_query() {
+        let true_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 1,
+        }])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([Record {
+            query_id: 'B',
+            doc_id: 'X',
+            score: 0.5.into(),
+        }])
+        .unwrap();
+        assert!(matches!(
+            joined_iter(&true_rels, &pred_rels),
+            Err(ElinorError::MissingEntry(_))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! JSON Schemas of the serialized outputs, available with the `schemars` feature.
+//!
+//! The versioned schemas in `schemas/` of the repository are generated by [`schema_for`]
+//! from the types, and the tests fail if any of them is out of date.
+//! To regenerate them, run the tests with the `UPDATE_SCHEMAS` environment variable:
+//!
+//! ```sh
+//! UPDATE_SCHEMAS=1 cargo test --workspace --features schemars,elinor-cli/server schema
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use elinor::comparison::ComparisonReport;
+//! use elinor::json_schema::schema_for;
+//!
+//! let schema = schema_for::<ComparisonReport>();
+//! let json = serde_json::to_value(&schema).unwrap();
+//! assert_eq!(json["title"], "ComparisonReport");
+//!
+//! // NaN values are written as null by serde_json.
+//! let n_systems = &json["properties"]["n_systems"];
+//! assert_eq!(n_systems["type"], "integer");
+//! let p_value = &json["definitions"]["PValueReport"]["properties"]["p_value"];
+//! assert_eq!(p_value["type"], serde_json::json!(["number", "null"]));
+//! ```
+use schemars::gen::SchemaSettings;
+use schemars::schema::InstanceType;
+use schemars::schema::RootSchema;
+use schemars::schema::SchemaObject;
+use schemars::schema::SingleOrVec;
+use schemars::visit::visit_schema_object;
+use schemars::visit::Visitor;
+use schemars::JsonSchema;
+
+/// Generates the JSON Schema (draft-07) of `T`.
+///
+/// Numbers are nullable in the schema since `serde_json` writes NaN as null.
+pub fn schema_for<T: JsonSchema>() -> RootSchema {
+    SchemaSettings::draft07()
+        .with_visitor(NullableNumbers)
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
+
+/// Visitor to make the numbers nullable.
+#[derive(Debug, Clone)]
+struct NullableNumbers;
+
+impl Visitor for NullableNumbers {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        if schema.has_type(InstanceType::Number) && !schema.has_type(InstanceType::Null) {
+            schema.instance_type = Some(SingleOrVec::Vec(vec![
+                InstanceType::Number,
+                InstanceType::Null,
+            ]));
+        }
+        visit_schema_object(self, schema);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::comparison::ComparisonReport;
+    use crate::Evaluation;
+
+    /// Checks that `schemas/<name>` is the same as the generated schema,
+    /// or overwrites it if `UPDATE_SCHEMAS` is set.
+    fn check_schema(name: &str, schema: &RootSchema) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("schemas")
+            .join(name);
+        let generated = serde_json::to_string_pretty(schema).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let current = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            current == generated,
+            "{} is out of date; run the tests with UPDATE_SCHEMAS=1 to regenerate it.",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_comparison_report_schema() {
+        check_schema(
+            "comparison-report.v1.json",
+            &schema_for::<ComparisonReport>(),
+        );
+    }
+
+    #[test]
+    fn test_evaluation_schema() {
+        check_schema("evaluation.v1.json", &schema_for::<Evaluation<String>>());
+    }
+}
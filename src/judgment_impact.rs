@@ -0,0 +1,254 @@
+//! Impact analysis of changes in true relevance judgments.
+//!
+//! When true relevance judgments are updated (e.g., by additional assessments or corrections),
+//! collection maintainers want to know how the update moves the scores and the leaderboard of systems.
+//! This module provides [`analyze_judgment_impact`] to evaluate a set of systems
+//! under both the old and new judgments and to report per-system deltas
+//! with the significance of the change.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::judgment_impact::analyze_judgment_impact;
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_2", "d_1", 1)?;
+//! let old_true_rels = b.build();
+//!
+//! // d_1 is judged as non-relevant and d_2 is newly judged as relevant.
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0)?;
+//! b.add_record("q_1", "d_2", 1)?;
+//! b.add_record("q_2", "d_1", 0)?;
+//! b.add_record("q_2", "d_2", 1)?;
+//! let new_true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_1", 0.5.into())?;
+//! let pred_rels_a = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_2", 0.5.into())?;
+//! b.add_record("q_2", "d_2", 0.5.into())?;
+//! let pred_rels_b = b.build();
+//!
+//! let impacts = analyze_judgment_impact(
+//!     &old_true_rels,
+//!     &new_true_rels,
+//!     [&pred_rels_a, &pred_rels_b],
+//!     Metric::Success { k: 1 },
+//! )?;
+//! assert_eq!(impacts[0].delta(), -1.0);
+//! assert_eq!(impacts[1].delta(), 1.0);
+//! assert_eq!((impacts[0].old_rank(), impacts[0].new_rank()), (1, 2));
+//! assert_eq!((impacts[1].old_rank(), impacts[1].new_rank()), (2, 1));
+//! # Ok(())
+//! # }
+//! ```
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::StudentTTest;
+use crate::Evaluation;
+use crate::Metric;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+
+/// Impact of a judgment change on a system.
+pub struct JudgmentImpact<K> {
+    old_evaluation: Evaluation<K>,
+    new_evaluation: Evaluation<K>,
+    old_rank: usize,
+    new_rank: usize,
+    t_test: Option<StudentTTest>,
+}
+
+impl<K> JudgmentImpact<K> {
+    /// Evaluated result under the old judgments.
+    pub const fn old_evaluation(&self) -> &Evaluation<K> {
+        &self.old_evaluation
+    }
+
+    /// Evaluated result under the new judgments.
+    pub const fn new_evaluation(&self) -> &Evaluation<K> {
+        &self.new_evaluation
+    }
+
+    /// Difference of the mean scores, i.e., the new one minus the old one.
+    pub const fn delta(&self) -> f64 {
+        self.new_evaluation.mean() - self.old_evaluation.mean()
+    }
+
+    /// 1-origin rank of the system in the leaderboard under the old judgments.
+    pub const fn old_rank(&self) -> usize {
+        self.old_rank
+    }
+
+    /// 1-origin rank of the system in the leaderboard under the new judgments.
+    pub const fn new_rank(&self) -> usize {
+        self.new_rank
+    }
+
+    /// Two-sided paired Student's t-test for the per-topic scores (new - old).
+    ///
+    /// Returns `None` if the test is uncomputable,
+    /// e.g., when the judgment change does not affect the scores at all.
+    pub const fn t_test(&self) -> Option<&StudentTTest> {
+        self.t_test.as_ref()
+    }
+}
+
+/// Evaluates systems under the old and new judgments and reports the impact on each system.
+///
+/// The returned vector is in the same order as `pred_rels`.
+/// The leaderboard ranks are computed by the mean scores in descending order,
+/// where ties are broken by the input order.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `pred_rels` is empty.
+///
+/// See [`metrics::compute_metric`](crate::metrics::compute_metric) for the other possible errors.
+pub fn analyze_judgment_impact<'a, I, K>(
+    old_true_rels: &TrueRelStore<K>,
+    new_true_rels: &TrueRelStore<K>,
+    pred_rels: I,
+    metric: Metric,
+) -> Result<Vec<JudgmentImpact<K>>>
+where
+    I: IntoIterator<Item = &'a PredRelStore<K>>,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    let mut evaluations = vec![];
+    for pred_rels in pred_rels {
+        let old_evaluation = crate::evaluate(old_true_rels, pred_rels, metric)?;
+        let new_evaluation = crate::evaluate(new_true_rels, pred_rels, metric)?;
+        evaluations.push((old_evaluation, new_evaluation));
+    }
+    if evaluations.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one system.".to_string(),
+        ));
+    }
+    let old_ranks = compute_ranks(
+        &evaluations
+            .iter()
+            .map(|(e, _)| e.mean())
+            .collect::<Vec<_>>(),
+    );
+    let new_ranks = compute_ranks(
+        &evaluations
+            .iter()
+            .map(|(_, e)| e.mean())
+            .collect::<Vec<_>>(),
+    );
+    let mut impacts = vec![];
+    for (i, (old_evaluation, new_evaluation)) in evaluations.into_iter().enumerate() {
+        let pairs = pairs_from_evaluations(&new_evaluation, &old_evaluation)?;
+        let t_test = StudentTTest::from_paired_samples(pairs).ok();
+        impacts.push(JudgmentImpact {
+            old_evaluation,
+            new_evaluation,
+            old_rank: old_ranks[i],
+            new_rank: new_ranks[i],
+            t_test,
+        });
+    }
+    Ok(impacts)
+}
+
+/// Computes 1-origin ranks of the scores in descending order.
+fn compute_ranks(scores: &[f64]) -> Vec<usize> {
+    let mut indices = (0..scores.len()).collect::<Vec<_>>();
+    indices.sort_by(|&i, &j| scores[j].total_cmp(&scores[i]).then(i.cmp(&j)));
+    let mut ranks = vec![0; scores.len()];
+    for (rank, i) in indices.into_iter().enumerate() {
+        ranks[i] = rank + 1;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PredRelStoreBuilder;
+    use crate::TrueRelStoreBuilder;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_analyze_judgment_impact() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('b', 'x', 1).unwrap();
+        b.add_record('c', 'x', 1).unwrap();
+        let old_true_rels = b.build();
+
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('a', 'y', 1).unwrap();
+        b.add_record('b', 'x', 0).unwrap();
+        b.add_record('c', 'x', 1).unwrap();
+        let new_true_rels = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 0.5.into()).unwrap();
+        b.add_record('b', 'x', 0.5.into()).unwrap();
+        b.add_record('c', 'x', 0.5.into()).unwrap();
+        let pred_rels_1 = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'y', 0.5.into()).unwrap();
+        b.add_record('b', 'y', 0.5.into()).unwrap();
+        b.add_record('c', 'x', 0.5.into()).unwrap();
+        let pred_rels_2 = b.build();
+
+        let impacts = analyze_judgment_impact(
+            &old_true_rels,
+            &new_true_rels,
+            [&pred_rels_1, &pred_rels_2],
+            Metric::Precision { k: 1 },
+        )
+        .unwrap();
+        assert_eq!(impacts.len(), 2);
+
+        // pred_rels_1: [1, 1, 1] -> [1, 0, 1]
+        assert_relative_eq!(impacts[0].old_evaluation().mean(), 1.0);
+        assert_relative_eq!(impacts[0].new_evaluation().mean(), 2.0 / 3.0);
+        assert_relative_eq!(impacts[0].delta(), -1.0 / 3.0);
+        assert_eq!(impacts[0].old_rank(), 1);
+        assert_eq!(impacts[0].new_rank(), 1);
+        assert!(impacts[0].t_test().is_some());
+
+        // pred_rels_2: [0, 0, 1] -> [1, 0, 1]
+        assert_relative_eq!(impacts[1].delta(), 1.0 / 3.0);
+        assert_eq!(impacts[1].old_rank(), 2);
+        assert_eq!(impacts[1].new_rank(), 2);
+    }
+
+    #[test]
+    fn test_analyze_judgment_impact_empty() {
+        let true_rels = TrueRelStoreBuilder::<char>::new().build();
+        let pred_rels: Vec<&PredRelStore<char>> = vec![];
+        let result = analyze_judgment_impact(
+            &true_rels,
+            &true_rels,
+            pred_rels,
+            Metric::Precision { k: 1 },
+        );
+        assert_eq!(
+            result.err(),
+            Some(ElinorError::InvalidArgument(
+                "The input must have at least one system.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_ranks() {
+        assert_eq!(compute_ranks(&[0.1, 0.3, 0.2, 0.3]), vec![4, 1, 3, 2]);
+    }
+}
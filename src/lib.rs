@@ -5,16 +5,16 @@
 //! # Key features
 //!
 //! * **IR-specific design:**
-//!     Elinor is tailored specifically for evaluating IR systems, with an intuitive interface designed for IR engineers.
-//!     It offers a streamlined workflow that simplifies common IR evaluation tasks.
+//!   Elinor is tailored specifically for evaluating IR systems, with an intuitive interface designed for IR engineers.
+//!   It offers a streamlined workflow that simplifies common IR evaluation tasks.
 //! * **Comprehensive evaluation metrics:**
-//!     Elinor supports a wide range of key evaluation metrics, such as Precision, MAP, MRR, and nDCG.
-//!     The supported metrics are available in [`Metric`].
-//!     The evaluation results are validated against trec_eval to ensure accuracy and reliability.
+//!   Elinor supports a wide range of key evaluation metrics, such as Precision, MAP, MRR, and nDCG.
+//!   The supported metrics are available in [`Metric`].
+//!   The evaluation results are validated against trec_eval to ensure accuracy and reliability.
 //! * **In-depth statistical testing:**
-//!     Elinor includes several statistical tests, such as Student's t-test, Bootstrap test, and Randomized Tukey HSD test.
-//!     Not only p-values but also other important statistics, such as effect sizes and confidence intervals, are provided for thorough reporting.
-//!     See the [`statistical_tests`] module for more details.
+//!   Elinor includes several statistical tests, such as Student's t-test, Bootstrap test, and Randomized Tukey HSD test.
+//!   Not only p-values but also other important statistics, such as effect sizes and confidence intervals, are provided for thorough reporting.
+//!   See the [`statistical_tests`] module for more details.
 //!
 //! # Ubiquitous language
 //!
@@ -89,7 +89,7 @@
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use approx::assert_relative_eq;
 //! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
-//! use elinor::statistical_tests::{StudentTTest, pairs_from_maps};
+//! use elinor::statistical_tests::StudentTTest;
 //!
 //! // Prepare true relevance scores.
 //! let mut b = TrueRelStoreBuilder::new();
@@ -120,8 +120,7 @@
 //! let result_b = elinor::evaluate(&true_rels, &pred_rels_b, metric)?;
 //!
 //! // Perform two-sided paired Student's t-test.
-//! let pairs = pairs_from_maps(result_a.scores(), result_b.scores())?;
-//! let stat = StudentTTest::from_paired_samples(pairs)?;
+//! let stat = StudentTTest::from_evaluations(&result_a, &result_b)?;
 //!
 //! // Various statistics can be obtained from the t-test result.
 //! assert!(stat.mean() > 0.0);
@@ -153,7 +152,7 @@
 //! ```
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
-//! use elinor::statistical_tests::{RandomizedTukeyHsdTest, TukeyHsdTest, tuples_from_maps};
+//! use elinor::statistical_tests::{RandomizedTukeyHsdTest, TukeyHsdTest};
 //!
 //! // Prepare true relevance scores.
 //! let mut b = TrueRelStoreBuilder::new();
@@ -191,15 +190,14 @@
 //! let result_b = elinor::evaluate(&true_rels, &pred_rels_b, metric)?;
 //! let result_c = elinor::evaluate(&true_rels, &pred_rels_c, metric)?;
 //!
-//! // Prepare tupled scores for tests.
-//! let tupled_scores = tuples_from_maps([result_a.scores(), result_b.scores(), result_c.scores()])?;
+//! let results = [result_a, result_b, result_c];
 //!
 //! // Perform Tukey HSD test with paired observations.
-//! let hsd_stat = TukeyHsdTest::from_tupled_samples(tupled_scores.iter(), 3)?;
+//! let hsd_stat = TukeyHsdTest::from_evaluations(&results)?;
 //! let effect_sizes = hsd_stat.effect_sizes();
 //!
 //! // Perform randomized Tukey HSD test.
-//! let hsd_stat = RandomizedTukeyHsdTest::from_tupled_samples(tupled_scores.iter(), 3)?;
+//! let hsd_stat = RandomizedTukeyHsdTest::from_evaluations(&results)?;
 //! let p_values = hsd_stat.p_values();
 //! # Ok(())
 //! # }
@@ -266,9 +264,23 @@
 //! # }
 //! ```
 //!
+//! The relevance stores can also be read from and written to CSV records
+//! with the header of `query_id,doc_id,score`
+//! via `from_csv_reader` and `to_csv_writer` of [`RelevanceStore`](relevance::RelevanceStore).
+//!
 //! # Crate features
 //!
-//! * `serde` - Enables Serde for [`TrueRecord`] and [`PredRecord`].
+//! * `serde` - Enables Serde for [`TrueRecord`] and [`PredRecord`],
+//!   and reading and writing relevance stores in CSV, such as `TrueRelStore::from_csv_reader`.
+//! * `arrow` - Enables loading relevance stores from [Polars](https://pola.rs/) DataFrames and Parquet files,
+//!   such as `PredRelStore::from_dataframe` and `PredRelStore::from_parquet`.
+//! * `schemars` - Enables generating the JSON Schemas of the serialized outputs,
+//!   such as [`comparison::ComparisonReport`] and [`Evaluation`], in [`json_schema`].
+//!   It implies `serde`.
+//! * `parallel` - Enables multi-threaded parsing of the TREC format, such as `trec::parse_true_rels_in_trec_parallel`,
+//!   multi-threaded evaluation, such as `evaluate_parallel`,
+//!   and multi-threaded resampling in [`BootstrapTester`](statistical_tests::bootstrap_test::BootstrapTester)
+//!   and [`RandomizedTukeyHsdTester`](statistical_tests::randomized_tukey_hsd_test::RandomizedTukeyHsdTester).
 //!
 //! # Acknowledgments
 //!
@@ -284,18 +296,46 @@
 //! I recommend reading these books before using this library.
 #![deny(missing_docs)]
 
+pub mod analysis;
+#[cfg(feature = "arrow")]
+mod arrow;
+pub mod assessor_simulation;
+pub mod comparison;
+pub mod cross_validation;
+pub mod discriminative_power;
 pub mod errors;
+pub mod fusion;
+pub mod id_normalization;
+pub mod join;
+#[cfg(feature = "schemars")]
+pub mod json_schema;
+pub mod judgment_impact;
+pub mod metric_correlation;
 pub mod metrics;
+pub mod multi_collection;
+pub mod normalization;
+pub mod passage_aggregation;
+pub mod rank_positions;
 pub mod relevance;
 pub mod statistical_tests;
+pub mod timing;
 pub mod trec;
 
 use std::collections::BTreeMap;
 
 use ordered_float::OrderedFloat;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::Serialize;
 
+pub use comparison::compare_systems;
 pub use errors::ElinorError;
 pub use errors::Result;
+pub use join::joined_iter;
 pub use metrics::Metric;
 pub use relevance::Record;
 pub use relevance::Relevance;
@@ -326,18 +366,130 @@ pub type PredRelStore<K> = relevance::RelevanceStore<K, PredScore>;
 /// Builder for [`PredRelStore`].
 pub type PredRelStoreBuilder<K> = relevance::RelevanceStoreBuilder<K, PredScore>;
 
+/// Former name of [`TrueScore`].
+#[deprecated(since = "0.4.0", note = "Use `TrueScore` instead.")]
+pub type GoldScore = TrueScore;
+
+/// Former name of [`TrueRecord`].
+#[deprecated(since = "0.4.0", note = "Use `TrueRecord` instead.")]
+pub type GoldRecord<K> = TrueRecord<K>;
+
+/// Former name of [`TrueRelStore`].
+#[deprecated(since = "0.4.0", note = "Use `TrueRelStore` instead.")]
+pub type GoldRelStore<K> = TrueRelStore<K>;
+
+/// Former name of [`TrueRelStoreBuilder`].
+#[deprecated(since = "0.4.0", note = "Use `TrueRelStoreBuilder` instead.")]
+pub type GoldRelStoreBuilder<K> = TrueRelStoreBuilder<K>;
+
+/// Former name of [`Evaluation`].
+#[deprecated(since = "0.4.0", note = "Use `Evaluation` instead.")]
+pub type Evaluated<K> = Evaluation<K>;
+
+/// Converts a [`GoldRelStore`] into a [`TrueRelStore`].
+///
+/// The types are the same, so the conversion is free.
+/// It marks the boundaries between code using the former names and the current ones during migration.
+///
+/// # Examples
+///
+/// ```
+/// # #![allow(deprecated)]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::GoldRelStoreBuilder;
+/// use elinor::TrueRelStore;
+///
+/// let mut b = GoldRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// let true_rels: TrueRelStore<&str> = elinor::true_rels_from_gold(b.build());
+/// assert_eq!(true_rels.get_score("q_1", "d_1"), Some(&1));
+/// # Ok(())
+/// # }
+/// ```
+#[deprecated(since = "0.4.0", note = "Use `TrueRelStore` directly instead.")]
+#[allow(deprecated)]
+pub const fn true_rels_from_gold<K>(gold_rels: GoldRelStore<K>) -> TrueRelStore<K> {
+    gold_rels
+}
+
+/// Converts an [`Evaluated`] into an [`Evaluation`].
+///
+/// The types are the same, so the conversion is free.
+/// It marks the boundaries between code using the former names and the current ones during migration.
+///
+/// # Examples
+///
+/// ```
+/// # #![allow(deprecated)]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::collections::BTreeMap;
+///
+/// use elinor::Evaluated;
+/// use elinor::Evaluation;
+/// use elinor::Metric;
+///
+/// let scores = BTreeMap::from([("q_1", 0.5), ("q_2", 1.0)]);
+/// let evaluated: Evaluated<&str> = Evaluation::from_scores(Metric::Precision { k: 1 }, scores);
+/// let mean_score = evaluated.mean_score();
+/// let evaluation: Evaluation<&str> = elinor::evaluation_from_evaluated(evaluated);
+/// assert_eq!(evaluation.mean(), mean_score);
+/// # Ok(())
+/// # }
+/// ```
+#[deprecated(since = "0.4.0", note = "Use `Evaluation` directly instead.")]
+#[allow(deprecated)]
+pub const fn evaluation_from_evaluated<K>(evaluated: Evaluated<K>) -> Evaluation<K> {
+    evaluated
+}
+
 /// Struct to store evaluated results.
+///
+/// With the `serde` feature, it is serializable,
+/// where the metric is written as its name.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(rename = "Evaluation"))]
 pub struct Evaluation<K> {
-    metric: Metric,
+    /// Metric used for evaluation.
+    metric: metrics::MetricId,
+
+    /// Mapping from query ids to scores.
     scores: BTreeMap<K, f64>,
+
+    /// Macro-averaged score.
     mean: f64,
+
+    /// Variance of the scores.
     variance: f64,
 }
 
 impl<K> Evaluation<K> {
+    /// Creates an instance from the mapping from query ids to scores,
+    /// e.g., those computed beforehand.
+    ///
+    /// The metric is either a built-in [`Metric`] or a [`MetricId`](metrics::MetricId).
+    pub fn from_scores<M>(metric: M, scores: BTreeMap<K, f64>) -> Self
+    where
+        M: Into<metrics::MetricId>,
+    {
+        let metric = metric.into();
+        let mean = scores.values().sum::<f64>() / scores.len() as f64;
+        let variance = scores
+            .values()
+            .map(|&score| (score - mean).powi(2))
+            .sum::<f64>()
+            / scores.len() as f64;
+        Self {
+            metric,
+            scores,
+            mean,
+            variance,
+        }
+    }
+
     /// Returns the metric used for evaluation.
-    pub const fn metric(&self) -> Metric {
-        self.metric
+    pub const fn metric(&self) -> &metrics::MetricId {
+        &self.metric
     }
 
     /// Returns the reference to the mappping from query ids to scores.
@@ -350,6 +502,12 @@ impl<K> Evaluation<K> {
         self.mean
     }
 
+    /// Returns the macro-averaged score.
+    #[deprecated(since = "0.4.0", note = "Use `mean` instead.")]
+    pub const fn mean_score(&self) -> f64 {
+        self.mean
+    }
+
     /// Returns the variance of the scores.
     pub const fn variance(&self) -> f64 {
         self.variance
@@ -359,6 +517,337 @@ impl<K> Evaluation<K> {
     pub fn std_dev(&self) -> f64 {
         self.variance.sqrt()
     }
+
+    /// Returns the geometric mean of the scores,
+    /// where scores below [`GEOMETRIC_MEAN_EPSILON`](metrics::GEOMETRIC_MEAN_EPSILON) are raised to it to avoid zeros,
+    /// or NaN if there are no queries or a score is non-finite.
+    ///
+    /// For [`Metric::AP`], this is GMAP, the same as `gm_map` in trec_eval,
+    /// which emphasizes improvements on poorly performing queries as in the TREC Robust track.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use approx::assert_abs_diff_eq;
+    /// use elinor::{Evaluation, Metric};
+    ///
+    /// let scores = BTreeMap::from([("q_1", 0.5), ("q_2", 0.2), ("q_3", 0.0)]);
+    /// let evaluation = Evaluation::from_scores(Metric::AP { k: 0 }, scores);
+    /// let expected = (0.5_f64.ln() + 0.2_f64.ln() + 0.00001_f64.ln()) / 3.0;
+    /// assert_abs_diff_eq!(evaluation.geometric_mean(), expected.exp(), epsilon = 1e-10);
+    /// ```
+    pub fn geometric_mean(&self) -> f64 {
+        if self.scores.is_empty() || !self.scores.values().all(|score| score.is_finite()) {
+            return f64::NAN;
+        }
+        let log_sum = self
+            .scores
+            .values()
+            .map(|&score| score.max(metrics::GEOMETRIC_MEAN_EPSILON).ln())
+            .sum::<f64>();
+        (log_sum / self.scores.len() as f64).exp()
+    }
+
+    /// Returns the minimum of the scores, or NaN if there are no queries.
+    pub fn min(&self) -> f64 {
+        self.scores
+            .values()
+            .copied()
+            .reduce(f64::min)
+            .unwrap_or(f64::NAN)
+    }
+
+    /// Returns the maximum of the scores, or NaN if there are no queries.
+    pub fn max(&self) -> f64 {
+        self.scores
+            .values()
+            .copied()
+            .reduce(f64::max)
+            .unwrap_or(f64::NAN)
+    }
+
+    /// Returns the median of the scores, or NaN if there are no queries.
+    pub fn median(&self) -> f64 {
+        self.quantile(0.5).unwrap()
+    }
+
+    /// Returns the `q`-quantile of the scores, or NaN if there are no queries.
+    ///
+    /// The quantile is linearly interpolated between the closest ranks,
+    /// the same as the default of NumPy and R.
+    /// NaN scores (e.g., [`Metric::F1`] for queries without relevant documents) are ignored
+    /// in the same way as [`Evaluation::min`] and [`Evaluation::max`].
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if `q` is not in the range `[0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::collections::BTreeMap;
+    ///
+    /// use approx::assert_abs_diff_eq;
+    /// use elinor::{Evaluation, Metric};
+    ///
+    /// let scores = BTreeMap::from([("q_1", 0.4), ("q_2", 0.1), ("q_3", 0.2), ("q_4", 0.8)]);
+    /// let evaluation = Evaluation::from_scores(Metric::AP { k: 0 }, scores);
+    /// assert_eq!(evaluation.quantile(0.0)?, 0.1);
+    /// assert_abs_diff_eq!(evaluation.quantile(0.5)?, 0.3);
+    /// assert_eq!(evaluation.quantile(1.0)?, 0.8);
+    /// assert_eq!(evaluation.median(), evaluation.quantile(0.5)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quantile(&self, q: f64) -> Result<f64> {
+        if !(0.0..=1.0).contains(&q) {
+            return Err(ElinorError::InvalidArgument(
+                "The quantile must be in the range [0, 1].".to_string(),
+            ));
+        }
+        let mut sorted: Vec<f64> = self
+            .scores
+            .values()
+            .copied()
+            .filter(|score| !score.is_nan())
+            .collect();
+        if sorted.is_empty() {
+            return Ok(f64::NAN);
+        }
+        sorted.sort_by(f64::total_cmp);
+        Ok(statistical_tests::interpolated_quantile_of_sorted(
+            &sorted, q,
+        ))
+    }
+
+    /// Returns the summary of the score distribution.
+    pub fn summary(&self) -> EvaluationSummary {
+        EvaluationSummary {
+            n_queries: self.scores.len(),
+            mean: self.mean,
+            std_dev: self.std_dev(),
+            min: self.min(),
+            first_quartile: self.quantile(0.25).unwrap(),
+            median: self.median(),
+            third_quartile: self.quantile(0.75).unwrap(),
+            max: self.max(),
+        }
+    }
+}
+
+/// Summary of the score distribution in [`Evaluation`], obtained by [`Evaluation::summary`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvaluationSummary {
+    n_queries: usize,
+    mean: f64,
+    std_dev: f64,
+    min: f64,
+    first_quartile: f64,
+    median: f64,
+    third_quartile: f64,
+    max: f64,
+}
+
+impl EvaluationSummary {
+    /// Returns the number of queries.
+    pub const fn n_queries(&self) -> usize {
+        self.n_queries
+    }
+
+    /// Returns the mean of the scores.
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Returns the standard deviation of the scores.
+    pub const fn std_dev(&self) -> f64 {
+        self.std_dev
+    }
+
+    /// Returns the minimum of the scores.
+    pub const fn min(&self) -> f64 {
+        self.min
+    }
+
+    /// Returns the first quartile (i.e., the 0.25-quantile) of the scores.
+    pub const fn first_quartile(&self) -> f64 {
+        self.first_quartile
+    }
+
+    /// Returns the median of the scores.
+    pub const fn median(&self) -> f64 {
+        self.median
+    }
+
+    /// Returns the third quartile (i.e., the 0.75-quantile) of the scores.
+    pub const fn third_quartile(&self) -> f64 {
+        self.third_quartile
+    }
+
+    /// Returns the maximum of the scores.
+    pub const fn max(&self) -> f64 {
+        self.max
+    }
+}
+
+impl<K> Evaluation<K>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    /// Merges the evaluations of disjoint sets of queries, such as shards or folds, into one.
+    ///
+    /// The query maps are concatenated, and the mean and variance are recomputed over all the queries,
+    /// i.e., the variance is the pooled one.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if `evaluations` is empty.
+    /// * [`ElinorError::InvalidArgument`] if the evaluations have different metrics.
+    /// * [`ElinorError::DuplicateEntry`] if a query appears in more than one evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::{Evaluation, TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+    ///
+    /// let mut b = TrueRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 1)?;
+    /// b.add_record("q_2", "d_1", 1)?;
+    /// let true_rels = b.build();
+    ///
+    /// let mut b = PredRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 0.5.into())?;
+    /// let pred_rels_1 = b.build();
+    ///
+    /// let mut b = PredRelStoreBuilder::new();
+    /// b.add_record("q_2", "d_2", 0.5.into())?;
+    /// let pred_rels_2 = b.build();
+    ///
+    /// let metric = Metric::Precision { k: 1 };
+    /// let evaluation_1 = elinor::evaluate(&true_rels, &pred_rels_1, metric)?;
+    /// let evaluation_2 = elinor::evaluate(&true_rels, &pred_rels_2, metric)?;
+    /// let merged = Evaluation::merge_mean(&[evaluation_1, evaluation_2])?;
+    /// assert_eq!(merged.scores().len(), 2);
+    /// assert_eq!(merged.mean(), 0.5);
+    /// assert_eq!(merged.variance(), 0.25);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge_mean(evaluations: &[Self]) -> Result<Self> {
+        let Some(first) = evaluations.first() else {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least one evaluation.".to_string(),
+            ));
+        };
+        let metric = first.metric();
+        let mut scores = BTreeMap::new();
+        for evaluation in evaluations {
+            if evaluation.metric() != metric {
+                return Err(ElinorError::InvalidArgument(format!(
+                    "All the evaluations must have the same metric, but got {metric} and {}",
+                    evaluation.metric()
+                )));
+            }
+            for (query_id, &score) in evaluation.scores() {
+                if scores.insert(query_id.clone(), score).is_some() {
+                    return Err(ElinorError::DuplicateEntry(format!(
+                        "Query {query_id} appears in more than one evaluation."
+                    )));
+                }
+            }
+        }
+        Ok(Self::from_scores(metric.clone(), scores))
+    }
+}
+
+/// Struct to store evaluated results with the bootstrap distribution of the mean,
+/// obtained by resampling queries with replacement.
+pub struct BootstrapEvaluation<K> {
+    evaluation: Evaluation<K>,
+    n_resamples: usize,
+    random_state: u64,
+    resampled_means: Vec<f64>,
+}
+
+impl<K> BootstrapEvaluation<K> {
+    /// Resamples the queries of the evaluation `n_resamples` times.
+    ///
+    /// If `n_resamples` is less than `1`, it is modified to `1`.
+    /// NaN scores are ignored in the same way as [`Evaluation::quantile`].
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the evaluation has no queries with non-NaN scores.
+    pub fn new(evaluation: Evaluation<K>, n_resamples: usize, random_state: u64) -> Result<Self> {
+        let scores: Vec<f64> = evaluation
+            .scores()
+            .values()
+            .copied()
+            .filter(|score| !score.is_nan())
+            .collect();
+        if scores.is_empty() {
+            return Err(ElinorError::InvalidArgument(
+                "The evaluation must have at least one query.".to_string(),
+            ));
+        }
+        let n_resamples = n_resamples.max(1);
+        let mut rng = StdRng::seed_from_u64(random_state);
+        let mut resampled_means: Vec<f64> = (0..n_resamples)
+            .map(|_| {
+                (0..scores.len())
+                    .map(|_| scores[rng.gen_range(0..scores.len())])
+                    .sum::<f64>()
+                    / scores.len() as f64
+            })
+            .collect();
+        resampled_means.sort_by(f64::total_cmp);
+        Ok(Self {
+            evaluation,
+            n_resamples,
+            random_state,
+            resampled_means,
+        })
+    }
+
+    /// Returns the reference to the evaluation.
+    pub const fn evaluation(&self) -> &Evaluation<K> {
+        &self.evaluation
+    }
+
+    /// Returns the number of resamples.
+    pub const fn n_resamples(&self) -> usize {
+        self.n_resamples
+    }
+
+    /// Returns the random state used for the resampling.
+    pub const fn random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    /// Returns the percentile confidence interval of the mean at a given significance level $`\alpha`$,
+    /// formed by the $`\alpha/2`$ and $`1 - \alpha/2`$ quantiles of the resampled means.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        Ok((
+            statistical_tests::quantile_of_sorted(&self.resampled_means, significance_level / 2.0)?,
+            statistical_tests::quantile_of_sorted(
+                &self.resampled_means,
+                1.0 - significance_level / 2.0,
+            )?,
+        ))
+    }
 }
 
 /// Evaluates the given predicted relevance scores against the true relevance scores.
@@ -375,24 +864,272 @@ where
     K: Clone + Eq + Ord + std::fmt::Display,
 {
     let scores = metrics::compute_metric(true_rels, pred_rels, metric)?;
-    let mean = scores.values().sum::<f64>() / scores.len() as f64;
-    let variance = scores
-        .values()
-        .map(|&score| (score - mean).powi(2))
-        .sum::<f64>()
-        / scores.len() as f64;
-    Ok(Evaluation {
-        metric,
-        scores,
-        mean,
-        variance,
-    })
+    Ok(Evaluation::from_scores(metric, scores))
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores in parallel.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metric`] for the list of possible errors.
+#[cfg(feature = "parallel")]
+pub fn evaluate_parallel<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+) -> Result<Evaluation<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    let scores = metrics::compute_metric_parallel(true_rels, pred_rels, metric)?;
+    Ok(Evaluation::from_scores(metric, scores))
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// with a custom metric, whose results have [`MetricId::Custom`](metrics::MetricId::Custom).
+///
+/// See [`metrics::MetricFn`] for an example.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metric_with`] for the list of possible errors.
+pub fn evaluate_with<K, M>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric_fn: &M,
+) -> Result<Evaluation<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+    M: metrics::MetricFn<K> + ?Sized,
+{
+    let scores = metrics::compute_metric_with(true_rels, pred_rels, metric_fn)?;
+    let metric = metrics::MetricId::Custom(metric_fn.name().to_string());
+    Ok(Evaluation::from_scores(metric, scores))
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for multiple metrics in a single traversal of the rankings.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metrics`] for the list of possible errors.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_2", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_2", 0.4.into())?;
+/// b.add_record("q_2", "d_1", 0.5.into())?;
+/// b.add_record("q_2", "d_2", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let metrics = [Metric::Precision { k: 1 }, Metric::RR { k: 0 }];
+/// let results = elinor::evaluate_all(&true_rels, &pred_rels, &metrics)?;
+/// assert_eq!(results[&Metric::Precision { k: 1 }].mean(), 0.5);
+/// assert_eq!(results[&Metric::RR { k: 0 }].mean(), 0.75);
+/// # Ok(())
+/// # }
+/// ```
+pub fn evaluate_all<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    evaluate_all_with_options(true_rels, pred_rels, metrics, metrics::MetricOptions::new())
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for multiple metrics with the given options, e.g., a [`metrics::ShortRankingPolicy`].
+///
+/// # Errors
+///
+/// See [`metrics::compute_metrics_with_options`] for the list of possible errors.
+pub fn evaluate_all_with_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+    options: metrics::MetricOptions,
+) -> Result<BTreeMap<Metric, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let results = metrics::compute_metrics_with_options(true_rels, pred_rels, metrics, options)?;
+    Ok(results
+        .into_iter()
+        .map(|(metric, scores)| (metric, Evaluation::from_scores(metric, scores)))
+        .collect())
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for multiple metrics in parallel.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metrics_parallel`] for the list of possible errors.
+#[cfg(feature = "parallel")]
+pub fn evaluate_all_parallel<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    evaluate_all_parallel_with_options(true_rels, pred_rels, metrics, metrics::MetricOptions::new())
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for multiple metrics in parallel with the given options.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metrics_parallel_with_options`] for the list of possible errors.
+#[cfg(feature = "parallel")]
+pub fn evaluate_all_parallel_with_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+    options: metrics::MetricOptions,
+) -> Result<BTreeMap<Metric, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    let results =
+        metrics::compute_metrics_parallel_with_options(true_rels, pred_rels, metrics, options)?;
+    Ok(results
+        .into_iter()
+        .map(|(metric, scores)| (metric, Evaluation::from_scores(metric, scores)))
+        .collect())
+}
+
+/// Evaluates a stream of predicted relevance records against the true relevance scores
+/// for multiple metrics, keeping only the records of one query in memory at a time.
+///
+/// The records of each query must be contiguous in the stream, e.g., as in a run file sorted by query ids.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metrics_streaming`] for the list of possible errors.
+pub fn evaluate_streaming<K, I>(
+    true_rels: &TrueRelStore<K>,
+    pred_records: I,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+    I: IntoIterator<Item = PredRecord<K>>,
+{
+    let results = metrics::compute_metrics_streaming(true_rels, pred_records, metrics)?;
+    Ok(results
+        .into_iter()
+        .map(|(metric, scores)| (metric, Evaluation::from_scores(metric, scores)))
+        .collect())
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for each of the given relevance levels.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metric_at_rel_levels`] for the list of possible errors.
+pub fn evaluate_at_rel_levels<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    rel_levels: &[TrueScore],
+) -> Result<BTreeMap<TrueScore, Evaluation<K>>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let results = metrics::compute_metric_at_rel_levels(true_rels, pred_rels, metric, rel_levels)?;
+    Ok(results
+        .into_iter()
+        .map(|(rel_lvl, scores)| (rel_lvl, Evaluation::from_scores(metric, scores)))
+        .collect())
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores
+/// for a DCG-family metric with the given options, e.g., custom gains or another convention.
+///
+/// # Errors
+///
+/// See [`metrics::compute_metric_with_dcg_options`] for the list of possible errors.
+pub fn evaluate_with_dcg_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    options: &metrics::DcgOptions,
+) -> Result<Evaluation<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let scores = metrics::compute_metric_with_dcg_options(true_rels, pred_rels, metric, options)?;
+    Ok(Evaluation::from_scores(metric, scores))
+}
+
+/// Evaluates the given predicted relevance scores against the true relevance scores,
+/// together with the bootstrap distribution of the mean over queries.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_2", "d_1", 1)?;
+/// b.add_record("q_3", "d_1", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_2", "d_1", 0.5.into())?;
+/// b.add_record("q_3", "d_2", 0.5.into())?;
+/// let pred_rels = b.build();
+///
+/// let result = elinor::evaluate_with_bootstrap(&true_rels, &pred_rels, Metric::Precision { k: 1 }, 1000, 42)?;
+/// let mean = result.evaluation().mean();
+/// let (ci95_btm, ci95_top) = result.confidence_interval(0.05)?;
+/// assert!(ci95_btm <= mean && mean <= ci95_top);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// See [`metrics::compute_metric`] and [`BootstrapEvaluation::new`] for the list of possible errors.
+pub fn evaluate_with_bootstrap<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    n_resamples: usize,
+    random_state: u64,
+) -> Result<BootstrapEvaluation<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let evaluation = evaluate(true_rels, pred_rels, metric)?;
+    BootstrapEvaluation::new(evaluation, n_resamples, random_state)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use approx::assert_relative_eq;
+    use rstest::*;
 
     #[test]
     fn test_evaluate() {
@@ -414,17 +1151,229 @@ mod tests {
         let pred_rels = b.build();
 
         let evaluated = evaluate(&true_rels, &pred_rels, Metric::Precision { k: 3 }).unwrap();
-        assert_eq!(evaluated.metric(), Metric::Precision { k: 3 });
+        assert_eq!(evaluated.metric(), &Metric::Precision { k: 3 });
 
         let mean: f64 = (2. / 3. + 1. / 3.) / 2.;
-        let variance = ((2. / 3. - mean).powi(2) + (1. / 3. - mean).powi(2)) / 2.;
+        let variance = (2. / 3. - mean).mul_add(2. / 3. - mean, (1. / 3. - mean).powi(2)) / 2.;
         assert_relative_eq!(evaluated.mean(), mean);
         assert_relative_eq!(evaluated.variance(), variance);
         assert_relative_eq!(evaluated.std_dev(), variance.sqrt());
+        assert_relative_eq!(evaluated.geometric_mean(), (2_f64 / 3. * 1. / 3.).sqrt());
 
         let scores = evaluated.scores();
         assert_eq!(scores.len(), 2);
         assert_relative_eq!(scores["q_1"], 2. / 3.);
         assert_relative_eq!(scores["q_2"], 1. / 3.);
     }
+
+    #[test]
+    fn test_evaluation_summary() {
+        let metric = Metric::Precision { k: 1 };
+        let scores = BTreeMap::from([('a', 0.5), ('b', 0.0), ('c', 1.0), ('d', 0.25), ('e', 0.75)]);
+        let evaluation = Evaluation::from_scores(metric, scores);
+        let summary = evaluation.summary();
+        assert_eq!(summary.n_queries(), 5);
+        assert_relative_eq!(summary.mean(), 0.5);
+        assert_relative_eq!(summary.std_dev(), evaluation.std_dev());
+        assert_eq!(summary.min(), 0.0);
+        assert_eq!(summary.first_quartile(), 0.25);
+        assert_eq!(summary.median(), 0.5);
+        assert_eq!(summary.third_quartile(), 0.75);
+        assert_eq!(summary.max(), 1.0);
+        assert_relative_eq!(evaluation.quantile(0.1).unwrap(), 0.1);
+        assert_eq!(
+            evaluation.quantile(1.5),
+            Err(ElinorError::InvalidArgument(
+                "The quantile must be in the range [0, 1].".to_string()
+            ))
+        );
+
+        let evaluation = Evaluation::<char>::from_scores(metric, BTreeMap::new());
+        assert!(evaluation.median().is_nan());
+        assert!(evaluation.min().is_nan());
+        assert!(evaluation.max().is_nan());
+    }
+
+    #[test]
+    fn test_evaluation_quantile_with_nan() {
+        let metric = Metric::F1 { k: 0 };
+        let scores = BTreeMap::from([('a', 0.5), ('b', f64::NAN), ('c', 1.0), ('d', 0.0)]);
+        let evaluation = Evaluation::from_scores(metric, scores);
+        assert_eq!(evaluation.quantile(0.0).unwrap(), 0.0);
+        assert_eq!(evaluation.median(), 0.5);
+        assert_eq!(evaluation.quantile(1.0).unwrap(), 1.0);
+        assert_eq!(evaluation.min(), 0.0);
+        assert_eq!(evaluation.max(), 1.0);
+
+        let scores = BTreeMap::from([('a', f64::NAN)]);
+        let evaluation = Evaluation::from_scores(metric, scores);
+        assert!(evaluation.median().is_nan());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_evaluation_serialize() {
+        let metric = Metric::NDCG { k: 10 };
+        let scores = BTreeMap::from([("q_1", 0.5), ("q_2", 1.0)]);
+        let evaluation = Evaluation::from_scores(metric, scores);
+        assert_eq!(
+            serde_json::to_value(&evaluation).unwrap(),
+            serde_json::json!({
+                "metric": "ndcg@10",
+                "scores": {"q_1": 0.5, "q_2": 1.0},
+                "mean": 0.75,
+                "variance": 0.0625,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::zero(vec![0.5, 0.0], (0.5 * metrics::GEOMETRIC_MEAN_EPSILON).sqrt())]
+    #[case::negative(vec![0.5, -1.0], (0.5 * metrics::GEOMETRIC_MEAN_EPSILON).sqrt())]
+    #[case::nan(vec![0.5, f64::NAN], f64::NAN)]
+    #[case::infinity(vec![0.5, f64::INFINITY], f64::NAN)]
+    #[case::empty(vec![], f64::NAN)]
+    fn test_geometric_mean(#[case] scores: Vec<f64>, #[case] expected: f64) {
+        let scores = scores.into_iter().enumerate().collect::<BTreeMap<_, _>>();
+        let evaluation = Evaluation::from_scores(Metric::AP { k: 0 }, scores);
+        if expected.is_nan() {
+            assert!(evaluation.geometric_mean().is_nan());
+        } else {
+            assert_relative_eq!(evaluation.geometric_mean(), expected);
+        }
+    }
+
+    #[test]
+    fn test_merge_mean() {
+        let metric = Metric::Precision { k: 1 };
+        let evaluation_1 =
+            Evaluation::from_scores(metric, BTreeMap::from([('a', 1.0), ('b', 0.0)]));
+        let evaluation_2 = Evaluation::from_scores(metric, BTreeMap::from([('c', 0.5)]));
+        let merged = Evaluation::merge_mean(&[evaluation_1, evaluation_2]).unwrap();
+        assert_eq!(merged.metric(), &metric);
+        assert_eq!(merged.scores().len(), 3);
+        assert_relative_eq!(merged.mean(), 0.5);
+        assert_relative_eq!(merged.variance(), 0.5 / 3.0);
+    }
+
+    #[test]
+    fn test_merge_mean_custom_metric() {
+        let metric = metrics::MetricId::Custom("custom".to_string());
+        let evaluation_1 = Evaluation::from_scores(metric.clone(), BTreeMap::from([('a', 1.0)]));
+        let evaluation_2 = Evaluation::from_scores(metric.clone(), BTreeMap::from([('b', 0.0)]));
+        let merged = Evaluation::merge_mean(&[evaluation_1, evaluation_2]).unwrap();
+        assert_eq!(merged.metric(), &metric);
+        assert_eq!(merged.metric().to_string(), "custom");
+
+        let evaluation_3 =
+            Evaluation::from_scores(Metric::Precision { k: 1 }, BTreeMap::from([('c', 0.5)]));
+        assert_eq!(
+            Evaluation::merge_mean(&[merged, evaluation_3]).err(),
+            Some(ElinorError::InvalidArgument(
+                "All the evaluations must have the same metric, but got custom and precision@1"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_merge_mean_errors() {
+        let metric = Metric::Precision { k: 1 };
+        let evaluation =
+            |metric, query_id| Evaluation::from_scores(metric, BTreeMap::from([(query_id, 0.5)]));
+        assert_eq!(
+            Evaluation::<char>::merge_mean(&[]).err(),
+            Some(ElinorError::InvalidArgument(
+                "The input must have at least one evaluation.".to_string()
+            ))
+        );
+        assert_eq!(
+            Evaluation::merge_mean(&[evaluation(metric, 'a'), evaluation(metric, 'a')]).err(),
+            Some(ElinorError::DuplicateEntry(
+                "Query a appears in more than one evaluation.".to_string()
+            ))
+        );
+        assert_eq!(
+            Evaluation::merge_mean(&[
+                evaluation(metric, 'a'),
+                evaluation(Metric::RR { k: 0 }, 'b')
+            ])
+            .err(),
+            Some(ElinorError::InvalidArgument(
+                "All the evaluations must have the same metric, but got precision@1 and rr"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_evaluation() {
+        let metric = Metric::Precision { k: 1 };
+        let evaluation = Evaluation::from_scores(metric, BTreeMap::from([('a', 0.5), ('b', 0.5)]));
+        let result = BootstrapEvaluation::new(evaluation, 0, 42).unwrap();
+        assert_eq!(result.n_resamples(), 1);
+        assert_eq!(result.random_state(), 42);
+        assert_eq!(result.confidence_interval(0.05).unwrap(), (0.5, 0.5));
+        assert_eq!(
+            result.confidence_interval(0.0).err(),
+            Some(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_evaluation_with_nan() {
+        let metric = Metric::F1 { k: 0 };
+        let evaluation = Evaluation::from_scores(
+            metric,
+            BTreeMap::from([('a', 0.5), ('b', f64::NAN), ('c', 0.5)]),
+        );
+        let result = BootstrapEvaluation::new(evaluation, 10, 42).unwrap();
+        assert_eq!(result.confidence_interval(0.05).unwrap(), (0.5, 0.5));
+
+        let evaluation = Evaluation::from_scores(metric, BTreeMap::from([('a', f64::NAN)]));
+        assert_eq!(
+            BootstrapEvaluation::new(evaluation, 10, 42).err(),
+            Some(ElinorError::InvalidArgument(
+                "The evaluation must have at least one query.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_evaluation_empty() {
+        let evaluation = Evaluation::<char>::from_scores(Metric::RR { k: 0 }, BTreeMap::new());
+        assert_eq!(
+            BootstrapEvaluation::new(evaluation, 10, 42).err(),
+            Some(ElinorError::InvalidArgument(
+                "The evaluation must have at least one query.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_gold_aliases() {
+        let mut b = GoldRelStoreBuilder::new();
+        b.add_record("q_1", "d_1", 1).unwrap();
+        let true_rels: GoldRelStore<&str> = b.build();
+        let records: Vec<GoldRecord<&str>> = true_rels.records();
+        assert_eq!(records[0].score, 1 as GoldScore);
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record("q_1", "d_1", 0.5.into()).unwrap();
+        let pred_rels = b.build();
+
+        // The aliases are interchangeable with the current types.
+        let result: Evaluated<&str> =
+            evaluate(&true_rels, &pred_rels, Metric::Precision { k: 1 }).unwrap();
+        assert_eq!(result.mean_score(), result.mean());
+
+        let true_rels: TrueRelStore<&str> = true_rels_from_gold(true_rels);
+        assert_eq!(true_rels.get_score("q_1", "d_1"), Some(&1));
+        let mean_score = result.mean_score();
+        let result: Evaluation<&str> = evaluation_from_evaluated(result);
+        assert_eq!(result.mean(), mean_score);
+    }
 }
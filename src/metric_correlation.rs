@@ -0,0 +1,262 @@
+//! Correlation between metrics across queries.
+//!
+//! Reporting many metrics that behave almost identically adds little information.
+//! This module provides [`compute_metric_correlations`] to compute the correlation matrix
+//! between the per-query scores of metrics for a system,
+//! helping users to find redundant metrics in their reporting suite.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use approx::assert_abs_diff_eq;
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::metric_correlation::{compute_metric_correlations, CorrelationMethod};
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_2", "d_2", 1)?;
+//! b.add_record("q_3", "d_1", 1)?;
+//! b.add_record("q_3", "d_2", 1)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_1", "d_2", 0.4.into())?;
+//! b.add_record("q_2", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_2", 0.4.into())?;
+//! b.add_record("q_3", "d_2", 0.5.into())?;
+//! let pred_rels = b.build();
+//!
+//! let evaluations = [
+//!     elinor::evaluate(&true_rels, &pred_rels, Metric::AP { k: 0 })?,
+//!     elinor::evaluate(&true_rels, &pred_rels, Metric::RR { k: 0 })?,
+//!     elinor::evaluate(&true_rels, &pred_rels, Metric::Recall { k: 0 })?,
+//! ];
+//! let matrix = compute_metric_correlations(&evaluations, CorrelationMethod::Pearson)?;
+//! assert_eq!(matrix.len(), 3);
+//! assert_abs_diff_eq!(matrix[0][0], 1.0);
+//! assert_abs_diff_eq!(matrix[0][1], matrix[1][0]);
+//! # Ok(())
+//! # }
+//! ```
+use std::str::FromStr;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::tuples_from_evaluations;
+use crate::Evaluation;
+
+/// Method of correlation coefficients.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::metric_correlation::CorrelationMethod;
+///
+/// assert_eq!("pearson".parse(), Ok(CorrelationMethod::Pearson));
+/// assert_eq!("spearman".parse(), Ok(CorrelationMethod::Spearman));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CorrelationMethod {
+    /// Pearson's product-moment correlation coefficient.
+    Pearson,
+
+    /// Spearman's rank correlation coefficient,
+    /// i.e., Pearson's one between the ranks, where tied values get their average ranks.
+    Spearman,
+}
+
+impl FromStr for CorrelationMethod {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pearson" => Ok(Self::Pearson),
+            "spearman" => Ok(Self::Spearman),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported correlation method: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for CorrelationMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Pearson => write!(f, "pearson"),
+            Self::Spearman => write!(f, "spearman"),
+        }
+    }
+}
+
+impl CorrelationMethod {
+    /// Computes the correlation coefficient between the paired values.
+    ///
+    /// Returns NaN if either of the values is constant.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the values have different lengths.
+    /// * [`ElinorError::InvalidArgument`] if the values do not have at least two elements.
+    pub fn correlation(&self, x: &[f64], y: &[f64]) -> Result<f64> {
+        if x.len() != y.len() {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The values must have the same length, but got {} and {}.",
+                x.len(),
+                y.len()
+            )));
+        }
+        if x.len() < 2 {
+            return Err(ElinorError::InvalidArgument(
+                "The values must have at least two elements.".to_string(),
+            ));
+        }
+        Ok(match self {
+            Self::Pearson => pearson(x, y),
+            Self::Spearman => pearson(&average_ranks(x), &average_ranks(y)),
+        })
+    }
+}
+
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let mut cov = 0.0;
+    let mut var_x = 0.0;
+    let mut var_y = 0.0;
+    for (&a, &b) in x.iter().zip(y) {
+        cov += (a - mean_x) * (b - mean_y);
+        var_x += (a - mean_x).powi(2);
+        var_y += (b - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return f64::NAN;
+    }
+    cov / (var_x * var_y).sqrt()
+}
+
+/// Returns the 1-origin ranks of the values, where tied values get their average ranks.
+fn average_ranks(values: &[f64]) -> Vec<f64> {
+    let mut indices = (0..values.len()).collect::<Vec<_>>();
+    indices.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < indices.len() {
+        let mut end = start + 1;
+        while end < indices.len() && values[indices[end]] == values[indices[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &indices[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Computes the correlation matrix between the per-query scores of the evaluations,
+/// where the $`(i,j)`$-th element is the correlation between the $`i`$-th and $`j`$-th evaluations.
+///
+/// The evaluations are typically of different metrics for the same system,
+/// and the scores are paired by query ids.
+/// The elements involving an evaluation with constant scores are NaN.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the evaluations have different sets of query ids.
+/// * [`ElinorError::InvalidArgument`] if the evaluations do not have at least two queries.
+pub fn compute_metric_correlations<'a, I, K>(
+    evaluations: I,
+    method: CorrelationMethod,
+) -> Result<Vec<Vec<f64>>>
+where
+    I: IntoIterator<Item = &'a Evaluation<K>>,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    let evaluations = evaluations.into_iter().collect::<Vec<_>>();
+    let n_metrics = evaluations.len();
+    let tuples = tuples_from_evaluations(evaluations)?;
+    let columns = (0..n_metrics)
+        .map(|j| tuples.iter().map(|tuple| tuple[j]).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut matrix = vec![vec![0.0; n_metrics]; n_metrics];
+    for i in 0..n_metrics {
+        for j in i..n_metrics {
+            let correlation = method.correlation(&columns[i], &columns[j])?;
+            matrix[i][j] = correlation;
+            matrix[j][i] = correlation;
+        }
+    }
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use std::collections::BTreeMap;
+
+    use crate::Metric;
+
+    #[test]
+    fn test_correlation() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let y = [1.0, 4.0, 9.0, 16.0];
+        assert_abs_diff_eq!(
+            CorrelationMethod::Pearson.correlation(&x, &y).unwrap(),
+            0.9843,
+            epsilon = 1e-4
+        );
+        assert_abs_diff_eq!(
+            CorrelationMethod::Spearman.correlation(&x, &y).unwrap(),
+            1.0
+        );
+        assert!(CorrelationMethod::Pearson
+            .correlation(&x, &[1.0; 4])
+            .unwrap()
+            .is_nan());
+        assert!(CorrelationMethod::Pearson.correlation(&x, &y[..3]).is_err());
+        assert!(CorrelationMethod::Pearson
+            .correlation(&x[..1], &y[..1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_average_ranks() {
+        assert_eq!(
+            average_ranks(&[0.5, 0.1, 0.5, 0.3]),
+            vec![3.5, 1.0, 3.5, 2.0]
+        );
+    }
+
+    #[test]
+    fn test_compute_metric_correlations() {
+        let evaluation_a = Evaluation::from_scores(
+            Metric::AP { k: 0 },
+            BTreeMap::from([("q_1", 0.1), ("q_2", 0.5), ("q_3", 0.4)]),
+        );
+        let evaluation_b = Evaluation::from_scores(
+            Metric::RR { k: 0 },
+            BTreeMap::from([("q_1", 0.2), ("q_2", 1.0), ("q_3", 0.5)]),
+        );
+        let matrix = compute_metric_correlations(
+            [&evaluation_a, &evaluation_b],
+            CorrelationMethod::Spearman,
+        )
+        .unwrap();
+        assert_eq!(matrix, vec![vec![1.0, 1.0], vec![1.0, 1.0]]);
+
+        let evaluation_c = Evaluation::from_scores(
+            Metric::RR { k: 0 },
+            BTreeMap::from([("q_1", 0.2), ("q_2", 1.0)]),
+        );
+        assert!(compute_metric_correlations(
+            [&evaluation_a, &evaluation_c],
+            CorrelationMethod::Pearson
+        )
+        .is_err());
+    }
+}
@@ -3,26 +3,64 @@ pub(crate) mod average_precision;
 pub(crate) mod bpref;
 pub(crate) mod f1;
 pub(crate) mod hits;
+pub(crate) mod infap;
+pub(crate) mod judged;
 pub(crate) mod ndcg;
 pub(crate) mod precision;
+pub(crate) mod q_measure;
 pub(crate) mod r_precision;
 pub(crate) mod recall;
 pub(crate) mod reciprocal_rank;
 pub(crate) mod success;
 
+pub use infap::SampledPool;
+pub use ndcg::DcgConvention;
+pub use ndcg::DcgDiscount;
+pub use ndcg::DcgOptions;
+pub use ndcg::DcgWeighting;
+pub use ndcg::GainMapping;
+pub use ndcg::IdealCutoff;
+
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::str::FromStr;
 
+use ordered_float::OrderedFloat;
 use regex::Regex;
+#[cfg(feature = "schemars")]
+use schemars::gen::SchemaGenerator;
+#[cfg(feature = "schemars")]
+use schemars::schema::Schema;
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serde")]
+use serde::Serializer;
 
 use crate::errors::ElinorError;
+use crate::join::joined_iter;
 use crate::PredRelStore;
+use crate::PredScore;
+use crate::Record;
+use crate::Relevance;
 use crate::TrueRelStore;
 use crate::TrueScore;
 
 pub(crate) const RELEVANT_LEVEL: TrueScore = 1;
 
+/// Maximum cutoff `k` of metrics, i.e., $`2^{53}`$,
+/// up to which the counts of documents are exactly represented in `f64` scores.
+///
+/// Counts are accumulated in `u64` and converted into `f64` only to compute scores,
+/// so metrics such as [`Metric::Hits`] are exact for rankings of any length up to this limit.
+pub const MAX_CUTOFF: u64 = 1 << 53;
+
+/// Lower bound of the scores in [`Evaluation::geometric_mean`](crate::Evaluation::geometric_mean),
+/// the same as trec_eval.
+pub const GEOMETRIC_MEAN_EPSILON: f64 = 0.00001;
+
 /// Metrics for evaluating information retrieval systems.
 ///
 /// # Supported metrics
@@ -37,16 +75,21 @@ pub(crate) const RELEVANT_LEVEL: TrueScore = 1;
 /// | [`Metric::RPrecision`] | `r_precision` | Binary |  |
 /// | [`Metric::AP`] | `ap` | Binary | ✔ |
 /// | [`Metric::RR`] | `rr` | Binary | ✔ |
-/// | [`Metric::Bpref`] | `bpref` | Binary | |
+/// | [`Metric::Bpref`] | `bpref`, `bpref_original` | Binary | ✔ |
+/// | [`Metric::InfAP`] | `infap` | Binary | |
+/// | [`Metric::XInfAP`] | `xinfap` | Binary | |
+/// | [`Metric::Judged`] | `judged` | Any | ✔ |
 /// | [`Metric::DCG`] | `dcg` | Multi | ✔ |
 /// | [`Metric::NDCG`] | `ndcg` | Multi | ✔ |
 /// | [`Metric::DCGBurges`] | `dcg_burges` | Multi | ✔ |
 /// | [`Metric::NDCGBurges`] | `ndcg_burges` | Multi | ✔ |
+/// | [`Metric::QMeasure`] | `q_measure` | Multi | ✔ |
 ///
 /// # Arguments
 ///
 /// * `k` - Number of top documents to consider.
 ///   If `k` is set to 0, all documents are considered.
+///   It must be at most [`MAX_CUTOFF`] to compute the metric.
 ///
 /// # Conversion from/into string representation
 ///
@@ -71,7 +114,7 @@ pub(crate) const RELEVANT_LEVEL: TrueScore = 1;
 /// assert_eq!(format!("{}", Metric::Hits { k: 0 }), "hits");
 /// assert_eq!(format!("{}", Metric::Hits { k: 3 }), "hits@3");
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Metric {
     /// Number of relevant documents retrieved:
     ///
@@ -180,7 +223,69 @@ pub enum Metric {
     /// * $`R`$ is the number of relevant documents,
     /// * $`N`$ is the number of irrelevant documents without unjudged documents, and
     /// * $`N_{r}`$ is the number of irrelevant documents ranked above $`r`$.
-    Bpref,
+    ///
+    /// The above is the definition of trec_eval, used if `trec_compat` is true (the string representation `bpref`).
+    /// It clamps the denominator to $`N`$ when $`R > N`$, whereas the original definition
+    /// always divides by $`R`$ (the string representation `bpref_original`):
+    ///
+    /// ```math
+    /// \text{Bpref}_\text{original} = \frac{1}{R} \sum_{r} \left( 1 - \frac{\min(R, N_{r})}{R} \right)
+    /// ```
+    ///
+    /// Both are the same when $`R \leq N`$.
+    Bpref {
+        /// See the [Arguments](enum.Metric.html#arguments) section.
+        k: usize,
+
+        /// Whether to follow trec_eval in clamping the denominator.
+        trec_compat: bool,
+    },
+
+    /// Inferred AP, an estimate of AP from judgments of a uniform random sample of the pool, proposed in
+    /// [Yilmaz and Aslam, CIKM 2006](https://doi.org/10.1145/1183614.1183633)
+    /// and implemented in trec_eval as `infAP`.
+    ///
+    /// ```math
+    /// \text{infAP} = \frac{1}{| \text{Rel} |} \sum_{r} \left( \frac{1}{i_r} + \frac{i_r - 1}{i_r} \cdot
+    /// \frac{| \text{Pool}_{<i_r} |}{i_r - 1} \cdot \frac{| \text{Rel}_{<i_r} | + \epsilon}{| \text{Judged}_{<i_r} | + 2 \epsilon} \right)
+    /// ```
+    ///
+    /// where:
+    ///
+    /// * $`r`$ is a retrieved relevant document at rank $`i_r`$ (the term is 1 if $`i_r = 1`$),
+    /// * $`\text{Pool}_{<i}`$, $`\text{Rel}_{<i}`$, and $`\text{Judged}_{<i}`$ are the sets of
+    ///   pooled, relevant, and judged documents ranked above $`i`$, and
+    /// * $`\epsilon = 10^{-5}`$.
+    ///
+    /// The pool is given by [`SampledPool`] with [`compute_metric_with_pool`].
+    /// Otherwise, the pool consists of the judged documents, and the score is close to that of [`Metric::AP`].
+    InfAP,
+
+    /// Extended inferred AP for stratified sampling of the pool, proposed in
+    /// [Yilmaz et al., SIGIR 2008](https://doi.org/10.1145/1390334.1390437).
+    ///
+    /// The expected precision at each relevant document is estimated per stratum as in [`Metric::InfAP`],
+    /// and both the precision and the number of relevant documents are weighted by
+    /// the inverse sampling rates of the strata.
+    /// The strata are given by [`SampledPool`] with [`compute_metric_with_pool`].
+    XInfAP,
+
+    /// Proportion of the retrieved documents that are judged, i.e., appear in the true relevance scores at any grade:
+    ///
+    /// ```math
+    /// \text{Judged} = \frac{| \text{Res} \cap \text{Judged} |}{| \text{Res} |}
+    /// ```
+    ///
+    /// where $`\text{Judged}`$ is the set of judged documents.
+    /// As with [`Metric::Precision`], the denominator is fixed to `k` when `k` is set.
+    ///
+    /// This is a diagnostic of incomplete judgments rather than of the system,
+    /// e.g., a low Judged@10 indicates that NDCG@10 may be underestimated.
+    /// See also [`compute_unjudged_counts`] for the per-query numbers of unjudged documents.
+    Judged {
+        /// See the [Arguments](enum.Metric.html#arguments) section.
+        k: usize,
+    },
 
     /// Discounted cumulative gain proposed in
     /// [Järvelin et al., TOIS 2002](https://dl.acm.org/doi/10.1145/582415.582418).
@@ -227,6 +332,133 @@ pub enum Metric {
         /// See the [Arguments](enum.Metric.html#arguments) section.
         k: usize,
     },
+
+    /// Q-measure proposed in
+    /// [Sakai, NTCIR 2004](https://research.nii.ac.jp/ntcir/workshop/OnlineProceedings4/OPEN/NTCIR4-OPEN-SakaiTrev.pdf),
+    /// a blended ratio of the cumulative gain and precision:
+    ///
+    /// ```math
+    /// \text{Q}@k = \frac{1}{\min(k, | \text{Rel} |)} \sum_{i=1}^{k}
+    /// \frac{\text{Hits}@i + \beta \, \text{CG}@i}{i + \beta \, \text{CG}^{*}@i} \times
+    /// \left\{ \begin{array}{ll} 1 & \text{if the } i \text{-th document is relevant} \\ 0 & \text{otherwise} \end{array} \right.
+    /// ```
+    ///
+    /// where:
+    ///
+    /// * $`\text{CG}@i`$ is the cumulative gain of the top-$`i`$ documents, using the relevance scores as gains.
+    /// * $`\text{CG}^{*}@i`$ is the ideal cumulative gain, i.e., the max possible cumulative gain.
+    ///
+    /// When `k` is set to 0, the denominator is $`| \text{Rel} |`$.
+    /// When $`\beta = 0`$, the score is equivalent to that of [`Metric::AP`] without `k`.
+    ///
+    /// The string representation is `q_measure` for $`\beta = 1`$ (the standard setting),
+    /// and `q_measure_beta<beta>` (e.g., `q_measure_beta0.5@10`) otherwise.
+    QMeasure {
+        /// See the [Arguments](enum.Metric.html#arguments) section.
+        k: usize,
+
+        /// Persistence parameter $`\beta`$ to weight the cumulative gain.
+        beta: OrderedFloat<f64>,
+    },
+}
+
+/// Kinds of true relevance scores that a metric assumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelevanceKind {
+    /// Binary relevance, where documents with scores of at least 1 are regarded as relevant.
+    Binary,
+
+    /// Graded relevance, where the scores are used as gains.
+    Graded,
+}
+
+impl Metric {
+    /// Returns the kind of true relevance scores that the metric assumes.
+    ///
+    /// Binary metrics regard graded scores of at least 1 as relevant,
+    /// and graded metrics regard binary scores as grades of 0 and 1.
+    /// Use [`validate_relevance_kind`] to detect such coercion.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use elinor::Metric;
+    /// use elinor::metrics::RelevanceKind;
+    ///
+    /// assert_eq!(Metric::AP { k: 0 }.relevance_kind(), RelevanceKind::Binary);
+    /// assert_eq!(Metric::NDCG { k: 10 }.relevance_kind(), RelevanceKind::Graded);
+    /// ```
+    pub const fn relevance_kind(&self) -> RelevanceKind {
+        match self {
+            Self::DCG { .. }
+            | Self::NDCG { .. }
+            | Self::DCGBurges { .. }
+            | Self::NDCGBurges { .. }
+            | Self::QMeasure { .. } => RelevanceKind::Graded,
+            _ => RelevanceKind::Binary,
+        }
+    }
+
+    /// Returns the name of the corresponding measure in trec_eval, if any.
+    ///
+    /// The measures are computed in the same way as trec_eval with the default options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use elinor::Metric;
+    ///
+    /// assert_eq!(Metric::AP { k: 0 }.trec_eval_name(), Some("map".to_string()));
+    /// assert_eq!(Metric::NDCG { k: 10 }.trec_eval_name(), Some("ndcg_cut_10".to_string()));
+    /// assert_eq!(Metric::F1 { k: 10 }.trec_eval_name(), None);
+    /// ```
+    pub fn trec_eval_name(&self) -> Option<String> {
+        let name = match *self {
+            Self::Success { k } if k != 0 => format!("success_{k}"),
+            Self::Precision { k: 0 } => "set_P".to_string(),
+            Self::Precision { k } => format!("P_{k}"),
+            Self::Recall { k: 0 } => "set_recall".to_string(),
+            Self::Recall { k } => format!("recall_{k}"),
+            Self::RPrecision => "Rprec".to_string(),
+            Self::AP { k: 0 } => "map".to_string(),
+            Self::AP { k } => format!("map_cut_{k}"),
+            Self::RR { k: 0 } => "recip_rank".to_string(),
+            Self::Bpref {
+                k: 0,
+                trec_compat: true,
+            } => "bpref".to_string(),
+            Self::NDCG { k: 0 } => "ndcg".to_string(),
+            Self::NDCG { k } => format!("ndcg_cut_{k}"),
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Returns the theoretical lower and upper bounds of the per-query scores,
+    /// where the upper bound is [`f64::INFINITY`] for unbounded metrics.
+    ///
+    /// The scores of built-in metrics can be checked against the bounds
+    /// with [`MetricOptions::with_bounds_check`].
+    /// [`Metric::XInfAP`] is unbounded because of the weighting by the inverse sampling rates.
+    /// The bounds assume the gains given by the grades, so they do not hold for DCG-family scores
+    /// with arbitrary (e.g., negative) gains given by [`DcgWeighting`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use elinor::Metric;
+    ///
+    /// assert_eq!(Metric::AP { k: 0 }.bounds(), (0.0, 1.0));
+    /// assert_eq!(Metric::DCG { k: 10 }.bounds(), (0.0, f64::INFINITY));
+    /// ```
+    pub const fn bounds(&self) -> (f64, f64) {
+        match self {
+            Self::Hits { .. } | Self::DCG { .. } | Self::DCGBurges { .. } | Self::XInfAP => {
+                (0.0, f64::INFINITY)
+            }
+            _ => (0.0, 1.0),
+        }
+    }
 }
 
 impl Display for Metric {
@@ -256,8 +488,21 @@ impl Display for Metric {
             Self::RR { k } => {
                 write!(f, "{}", format_metric("rr", *k))
             }
-            Self::Bpref => {
-                write!(f, "bpref")
+            Self::Bpref { k, trec_compat } => {
+                if *trec_compat {
+                    write!(f, "{}", format_metric("bpref", *k))
+                } else {
+                    write!(f, "{}", format_metric("bpref_original", *k))
+                }
+            }
+            Self::InfAP => {
+                write!(f, "infap")
+            }
+            Self::XInfAP => {
+                write!(f, "xinfap")
+            }
+            Self::Judged { k } => {
+                write!(f, "{}", format_metric("judged", *k))
             }
             Self::DCG { k } => {
                 write!(f, "{}", format_metric("dcg", *k))
@@ -271,6 +516,13 @@ impl Display for Metric {
             Self::NDCGBurges { k } => {
                 write!(f, "{}", format_metric("ndcg_burges", *k))
             }
+            Self::QMeasure { k, beta } => {
+                if *beta == 1.0 {
+                    write!(f, "{}", format_metric("q_measure", *k))
+                } else {
+                    write!(f, "{}", format_metric(&format!("q_measure_beta{beta}"), *k))
+                }
+            }
         }
     }
 }
@@ -287,7 +539,8 @@ impl FromStr for Metric {
     type Err = ElinorError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"^(?<metric>[a-z1-9_]+)(@(?<k>\d+))?$").unwrap();
+        let re = Regex::new(r"^(?<metric>[a-z1-9_]+?)(_beta(?<beta>\d+(\.\d+)?))?(@(?<k>\d+))?$")
+            .unwrap();
         let caps = re
             .captures(s)
             .ok_or_else(|| ElinorError::InvalidFormat(format!("Unsupported metric: {s}")))?;
@@ -298,6 +551,14 @@ impl FromStr for Metric {
             .transpose()
             .map_err(|_| ElinorError::InvalidFormat(s.to_string()))?
             .unwrap_or(0);
+        let beta = caps
+            .name("beta")
+            .map(|m| m.as_str().parse::<f64>())
+            .transpose()
+            .map_err(|_| ElinorError::InvalidFormat(s.to_string()))?;
+        if beta.is_some() && name != "q_measure" {
+            return Err(ElinorError::InvalidFormat(s.to_string()));
+        }
         match name {
             "hits" => Ok(Self::Hits { k }),
             "success" => Ok(Self::Success { k }),
@@ -307,20 +568,213 @@ impl FromStr for Metric {
             "r_precision" => Ok(Self::RPrecision),
             "ap" => Ok(Self::AP { k }),
             "rr" => Ok(Self::RR { k }),
-            "bpref" => Ok(Self::Bpref),
+            "bpref" => Ok(Self::Bpref {
+                k,
+                trec_compat: true,
+            }),
+            "bpref_original" => Ok(Self::Bpref {
+                k,
+                trec_compat: false,
+            }),
+            "infap" => Ok(Self::InfAP),
+            "xinfap" => Ok(Self::XInfAP),
+            "judged" => Ok(Self::Judged { k }),
             "dcg" => Ok(Self::DCG { k }),
             "ndcg" => Ok(Self::NDCG { k }),
             "dcg_burges" => Ok(Self::DCGBurges { k }),
             "ndcg_burges" => Ok(Self::NDCGBurges { k }),
+            "q_measure" => Ok(Self::QMeasure {
+                k,
+                beta: OrderedFloat(beta.unwrap_or(1.0)),
+            }),
             _ => Err(ElinorError::InvalidFormat(s.to_string())),
         }
     }
 }
 
+/// Serializes the metric as its name, e.g., `"ndcg@10"`.
+#[cfg(feature = "serde")]
+impl Serialize for Metric {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl JsonSchema for Metric {
+    fn schema_name() -> String {
+        "Metric".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// Identifier of the metric of evaluated results,
+/// which is either a built-in [`Metric`] or a custom metric implemented with [`MetricFn`].
+///
+/// The string representation is that of the built-in metric or the name of the custom metric.
+///
+/// # Examples
+///
+/// ```rust
+/// use elinor::Metric;
+/// use elinor::metrics::MetricId;
+///
+/// let id = MetricId::from(Metric::Precision { k: 3 });
+/// assert_eq!(id, Metric::Precision { k: 3 });
+/// assert_eq!(id.to_string(), "precision@3");
+///
+/// let id = MetricId::Custom("top_is_best".to_string());
+/// assert_eq!(id.builtin(), None);
+/// assert_eq!(id.to_string(), "top_is_best");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetricId {
+    /// Built-in metric.
+    Builtin(Metric),
+
+    /// Custom metric, identified by the name returned by [`MetricFn::name`].
+    Custom(String),
+}
+
+impl MetricId {
+    /// Returns the built-in metric, or `None` for a custom metric.
+    pub const fn builtin(&self) -> Option<Metric> {
+        match self {
+            Self::Builtin(metric) => Some(*metric),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+impl From<Metric> for MetricId {
+    fn from(metric: Metric) -> Self {
+        Self::Builtin(metric)
+    }
+}
+
+impl PartialEq<Metric> for MetricId {
+    fn eq(&self, other: &Metric) -> bool {
+        self.builtin() == Some(*other)
+    }
+}
+
+impl Display for MetricId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Builtin(metric) => metric.fmt(f),
+            Self::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for MetricId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl JsonSchema for MetricId {
+    fn schema_name() -> String {
+        "MetricId".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+/// Returns the kind of the given true relevance scores.
+///
+/// The scores are regarded as [`RelevanceKind::Binary`] if all of them are 0 or 1,
+/// and as [`RelevanceKind::Graded`] otherwise.
+pub fn relevance_kind_of<K>(true_rels: &TrueRelStore<K>) -> RelevanceKind
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if true_rels.records().iter().all(|r| r.score <= 1) {
+        RelevanceKind::Binary
+    } else {
+        RelevanceKind::Graded
+    }
+}
+
+/// Validates that the kind of the true relevance scores matches that assumed by the metric.
+///
+/// This is useful to avoid silent coercion, e.g., computing nDCG with binarized judgments
+/// while comparing it with graded measures of trec_eval.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the kinds mismatch.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, TrueRelStoreBuilder};
+/// use elinor::metrics::validate_relevance_kind;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 0)?;
+/// let true_rels = b.build();
+///
+/// assert!(validate_relevance_kind(&true_rels, Metric::AP { k: 0 }).is_ok());
+/// assert!(validate_relevance_kind(&true_rels, Metric::NDCG { k: 0 }).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub fn validate_relevance_kind<K>(
+    true_rels: &TrueRelStore<K>,
+    metric: Metric,
+) -> Result<(), ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if matches!(metric, Metric::Judged { .. }) {
+        // Judged does not depend on the grades.
+        return Ok(());
+    }
+    let expected = metric.relevance_kind();
+    let actual = relevance_kind_of(true_rels);
+    if expected == actual {
+        return Ok(());
+    }
+    let message = match expected {
+        RelevanceKind::Binary => format!(
+            "{metric} assumes binary relevance, but true_rels has graded scores (scores of at least 1 are regarded as relevant)"
+        ),
+        RelevanceKind::Graded => format!(
+            "{metric} assumes graded relevance, but true_rels has only binary scores"
+        ),
+    };
+    Err(ElinorError::InvalidArgument(message))
+}
+
 /// Computes the metric scores for the given true and predicted relevance scores.
 ///
 /// # Errors
 ///
+/// * [`ElinorError::InvalidArgument`] if `k` of `metric` is greater than [`MAX_CUTOFF`].
 /// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
 pub fn compute_metric<K>(
     true_rels: &TrueRelStore<K>,
@@ -330,90 +784,1372 @@ pub fn compute_metric<K>(
 where
     K: Clone + Eq + Ord + std::fmt::Display,
 {
-    for query_id in pred_rels.query_ids() {
-        if true_rels.get_map(query_id).is_none() {
-            return Err(ElinorError::MissingEntry(format!(
-                "The set of queries in true_rels must be a subset of that in pred_rels, but {} is missing",
-                query_id
-            )));
-        }
-    }
+    check_cutoff(metric)?;
     let mut results = BTreeMap::new();
-    for query_id in pred_rels.query_ids() {
-        let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
-        let trues = true_rels.get_map(query_id).unwrap();
-        let score = match metric {
-            Metric::Hits { k } => hits::compute_hits(trues, sorted_preds, k, RELEVANT_LEVEL),
-            Metric::Success { k } => {
-                success::compute_success(trues, sorted_preds, k, RELEVANT_LEVEL)
-            }
-            Metric::Precision { k } => {
-                precision::compute_precision(trues, sorted_preds, k, RELEVANT_LEVEL)
-            }
-            Metric::Recall { k } => recall::compute_recall(trues, sorted_preds, k, RELEVANT_LEVEL),
-            Metric::F1 { k } => f1::compute_f1(trues, sorted_preds, k, RELEVANT_LEVEL),
-            Metric::RPrecision => {
-                r_precision::compute_r_precision(trues, sorted_preds, RELEVANT_LEVEL)
-            }
-            Metric::AP { k } => {
-                average_precision::compute_average_precision(trues, sorted_preds, k, RELEVANT_LEVEL)
-            }
-            Metric::RR { k } => {
-                reciprocal_rank::compute_reciprocal_rank(trues, sorted_preds, k, RELEVANT_LEVEL)
-            }
-            Metric::Bpref => bpref::compute_bpref(trues, sorted_preds, RELEVANT_LEVEL),
-            Metric::DCG { k } => {
-                ndcg::compute_dcg(trues, sorted_preds, k, ndcg::DcgWeighting::Jarvelin)
-            }
-            Metric::NDCG { k } => {
-                let sorted_trues = true_rels.get_sorted(query_id).unwrap();
-                ndcg::compute_ndcg(
-                    trues,
-                    sorted_trues,
-                    sorted_preds,
-                    k,
-                    ndcg::DcgWeighting::Jarvelin,
-                )
-            }
-            Metric::DCGBurges { k } => {
-                ndcg::compute_dcg(trues, sorted_preds, k, ndcg::DcgWeighting::Burges)
-            }
-            Metric::NDCGBurges { k } => {
-                let sorted_trues = true_rels.get_sorted(query_id).unwrap();
-                ndcg::compute_ndcg(
-                    trues,
-                    sorted_trues,
-                    sorted_preds,
-                    k,
-                    ndcg::DcgWeighting::Burges,
-                )
-            }
-        };
-        results.insert(query_id.clone(), score);
+    for query in joined_iter(true_rels, pred_rels)? {
+        let score = compute_query_metric(
+            query.trues(),
+            query.sorted_trues(),
+            query.sorted_preds(),
+            metric,
+            RELEVANT_LEVEL,
+        );
+        results.insert(query.query_id().clone(), score);
     }
     Ok(results)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Record;
-    use approx::assert_relative_eq;
-    use maplit::btreemap;
-    use rstest::*;
+/// Computes the metric scores for the given true and predicted relevance scores in parallel.
+///
+/// The scores are the same as those of [`compute_metric`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `k` of `metric` is greater than [`MAX_CUTOFF`].
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+/// use elinor::metrics::compute_metric_parallel;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_2", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_2", "d_1", 0.5.into())?;
+/// let pred_rels = b.build();
+///
+/// let scores = compute_metric_parallel(&true_rels, &pred_rels, Metric::Precision { k: 1 })?;
+/// assert_eq!(scores["q_1"], 1.0);
+/// assert_eq!(scores["q_2"], 0.0);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "parallel")]
+pub fn compute_metric_parallel<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+) -> Result<BTreeMap<K, f64>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    use rayon::prelude::*;
 
-    const LOG_2_2: f64 = 1.0;
-    const LOG_2_3: f64 = 1.584962500721156;
-    const LOG_2_4: f64 = 2.0;
+    check_cutoff(metric)?;
+    check_queries(true_rels, pred_rels)?;
+    let query_ids = pred_rels.query_ids().collect::<Vec<_>>();
+    let results = query_ids
+        .par_iter()
+        .map(|&query_id| {
+            let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+            let trues = true_rels.get_map(query_id).unwrap();
+            let sorted_trues = true_rels.get_sorted(query_id).unwrap();
+            let score =
+                compute_query_metric(trues, sorted_trues, sorted_preds, metric, RELEVANT_LEVEL);
+            (query_id.clone(), score)
+        })
+        .collect::<Vec<_>>();
+    Ok(results.into_iter().collect())
+}
 
-    fn compare_hashmaps(a: &BTreeMap<char, f64>, b: &BTreeMap<char, f64>) {
-        assert_eq!(a.len(), b.len());
-        for (k, v) in a.iter() {
-            assert_relative_eq!(v, b.get(k).unwrap());
+/// Computes the metric scores for each of the given relevance levels,
+/// i.e., the minimum true relevance scores regarded as relevant.
+///
+/// The sorted lists of each query are shared across the levels, which is faster than
+/// computing the scores for each level separately.
+/// Note that [`Metric::DCG`], [`Metric::NDCG`], [`Metric::DCGBurges`], and [`Metric::NDCGBurges`]
+/// do not depend on the level because they use the relevance scores as gains.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `rel_levels` is empty or contains 0.
+/// * [`ElinorError::InvalidArgument`] if `k` of `metric` is greater than [`MAX_CUTOFF`].
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+/// use elinor::metrics::compute_metric_at_rel_levels;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 2)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_2", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let scores = compute_metric_at_rel_levels(&true_rels, &pred_rels, Metric::Precision { k: 2 }, &[1, 2])?;
+/// assert_eq!(scores[&1]["q_1"], 1.0);
+/// assert_eq!(scores[&2]["q_1"], 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metric_at_rel_levels<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    rel_levels: &[TrueScore],
+) -> Result<BTreeMap<TrueScore, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if rel_levels.is_empty() || rel_levels.contains(&0) {
+        return Err(ElinorError::InvalidArgument(
+            "The relevance levels must be non-empty and positive.".to_string(),
+        ));
+    }
+    check_cutoff(metric)?;
+    let queries = joined_iter(true_rels, pred_rels)?;
+    let mut results: BTreeMap<TrueScore, BTreeMap<K, f64>> = rel_levels
+        .iter()
+        .map(|&rel_lvl| (rel_lvl, BTreeMap::new()))
+        .collect();
+    for query in queries {
+        for (&rel_lvl, scores) in results.iter_mut() {
+            let score = compute_query_metric(
+                query.trues(),
+                query.sorted_trues(),
+                query.sorted_preds(),
+                metric,
+                rel_lvl,
+            );
+            scores.insert(query.query_id().clone(), score);
         }
     }
+    Ok(results)
+}
 
-    #[rstest]
+/// Computes the scores of multiple metrics for the given true and predicted relevance scores.
+///
+/// The ranking of each query is traversed only once, and the counts of relevant documents
+/// are shared among [`Metric::Hits`], [`Metric::Success`], [`Metric::Precision`], [`Metric::Recall`],
+/// [`Metric::F1`], [`Metric::RPrecision`], [`Metric::AP`], and [`Metric::RR`].
+/// The scores are the same as those of [`compute_metric`] for each metric.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `k` of a metric in `metrics` is greater than [`MAX_CUTOFF`].
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+/// use elinor::metrics::compute_metrics;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let metrics = [Metric::Precision { k: 2 }, Metric::Recall { k: 2 }];
+/// let scores = compute_metrics(&true_rels, &pred_rels, &metrics)?;
+/// assert_eq!(scores[&Metric::Precision { k: 2 }]["q_1"], 0.5);
+/// assert_eq!(scores[&Metric::Recall { k: 2 }]["q_1"], 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metrics<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    compute_metrics_with_options(true_rels, pred_rels, metrics, MetricOptions::new())
+}
+
+/// Computes the scores of multiple metrics with the given options.
+///
+/// Queries retrieving fewer than `k` documents are handled according to [`ShortRankingPolicy`],
+/// and the scores are the same as those of [`compute_metric_with_options`] for each metric.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the auxiliary values are requested in `options`,
+///   which are only available in [`compute_metric_with_options`].
+/// * [`ElinorError::InvalidArgument`] if the policy is [`ShortRankingPolicy::Reject`]
+///   and a query retrieves fewer than `k` documents.
+/// * [`ElinorError::Uncomputable`] if the bounds check is enabled and a score is out of [`Metric::bounds`].
+///
+/// See [`compute_metrics`] for the other errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+/// use elinor::metrics::{compute_metrics_with_options, MetricOptions, ShortRankingPolicy};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let metrics = [Metric::Precision { k: 4 }, Metric::Recall { k: 4 }];
+/// let options = MetricOptions::new().with_short_ranking_policy(ShortRankingPolicy::Normalize);
+/// let scores = compute_metrics_with_options(&true_rels, &pred_rels, &metrics, options)?;
+/// assert_eq!(scores[&Metric::Precision { k: 4 }]["q_1"], 0.5);
+/// assert_eq!(scores[&Metric::Recall { k: 4 }]["q_1"], 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metrics_with_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+    options: MetricOptions,
+) -> Result<BTreeMap<Metric, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    for &metric in metrics {
+        check_cutoff(metric)?;
+    }
+    check_no_auxiliary(options)?;
+    let queries = joined_iter(true_rels, pred_rels)?;
+    let mut results: BTreeMap<Metric, BTreeMap<K, f64>> = metrics
+        .iter()
+        .map(|&metric| (metric, BTreeMap::new()))
+        .collect();
+    for query in queries {
+        let (trues, sorted_trues, sorted_preds) =
+            (query.trues(), query.sorted_trues(), query.sorted_preds());
+        let hits = HitProfile::new(trues, sorted_preds, RELEVANT_LEVEL);
+        for (&metric, scores) in results.iter_mut() {
+            let score = hits.compute(metric).unwrap_or_else(|| {
+                compute_query_metric(trues, sorted_trues, sorted_preds, metric, RELEVANT_LEVEL)
+            });
+            let score = apply_short_ranking_policy(
+                query.query_id(),
+                trues,
+                sorted_preds,
+                metric,
+                options.short_ranking_policy,
+                score,
+            )?;
+            scores.insert(query.query_id().clone(), score);
+        }
+    }
+    if options.bounds_check {
+        for (&metric, scores) in &results {
+            check_bounds(metric, scores)?;
+        }
+    }
+    Ok(results)
+}
+
+/// Computes the scores of multiple metrics in parallel over the metrics.
+///
+/// The profiles of hits in [`compute_metrics`] are first built for each query in parallel,
+/// and then each metric is computed over all the queries in parallel with the other metrics.
+/// The scores are the same as those of [`compute_metrics`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `k` of a metric in `metrics` is greater than [`MAX_CUTOFF`].
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRelStoreBuilder, TrueRelStoreBuilder};
+/// use elinor::metrics::compute_metrics_parallel;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let metrics = [Metric::Precision { k: 2 }, Metric::Recall { k: 2 }];
+/// let scores = compute_metrics_parallel(&true_rels, &pred_rels, &metrics)?;
+/// assert_eq!(scores[&Metric::Precision { k: 2 }]["q_1"], 0.5);
+/// assert_eq!(scores[&Metric::Recall { k: 2 }]["q_1"], 0.5);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "parallel")]
+pub fn compute_metrics_parallel<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    compute_metrics_parallel_with_options(true_rels, pred_rels, metrics, MetricOptions::new())
+}
+
+/// Computes the scores of multiple metrics in parallel over the metrics with the given options.
+///
+/// The scores are the same as those of [`compute_metrics_with_options`].
+///
+/// # Errors
+///
+/// See [`compute_metrics_with_options`].
+#[cfg(feature = "parallel")]
+pub fn compute_metrics_parallel_with_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metrics: &[Metric],
+    options: MetricOptions,
+) -> Result<BTreeMap<Metric, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display + Send + Sync,
+{
+    use rayon::prelude::*;
+
+    for &metric in metrics {
+        check_cutoff(metric)?;
+    }
+    check_no_auxiliary(options)?;
+    check_queries(true_rels, pred_rels)?;
+    let query_ids = pred_rels.query_ids().collect::<Vec<_>>();
+    let queries = query_ids
+        .par_iter()
+        .map(|&query_id| {
+            let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+            let trues = true_rels.get_map(query_id).unwrap();
+            let sorted_trues = true_rels.get_sorted(query_id).unwrap();
+            let hits = HitProfile::new(trues, sorted_preds, RELEVANT_LEVEL);
+            (query_id, trues, sorted_trues, sorted_preds, hits)
+        })
+        .collect::<Vec<_>>();
+    let metrics = metrics
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let results = metrics
+        .par_iter()
+        .map(|&metric| {
+            let scores = queries
+                .iter()
+                .map(|(query_id, trues, sorted_trues, sorted_preds, hits)| {
+                    let score = hits.compute(metric).unwrap_or_else(|| {
+                        compute_query_metric(
+                            trues,
+                            sorted_trues,
+                            sorted_preds,
+                            metric,
+                            RELEVANT_LEVEL,
+                        )
+                    });
+                    let score = apply_short_ranking_policy(
+                        *query_id,
+                        trues,
+                        sorted_preds,
+                        metric,
+                        options.short_ranking_policy,
+                        score,
+                    )?;
+                    Ok(((*query_id).clone(), score))
+                })
+                .collect::<Result<BTreeMap<_, _>, ElinorError>>()?;
+            if options.bounds_check {
+                check_bounds(metric, &scores)?;
+            }
+            Ok((metric, scores))
+        })
+        .collect::<Result<Vec<_>, ElinorError>>()?;
+    Ok(results.into_iter().collect())
+}
+
+/// Computes the scores of multiple metrics from a stream of predicted relevance records,
+/// keeping only the records of one query in memory at a time.
+///
+/// The records of each query must be contiguous in the stream, e.g., as in a run file sorted by query ids,
+/// while the records in a query can be in any order.
+/// The scores are the same as those of [`compute_metrics`] for the store of all the records.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `k` of a metric in `metrics` is greater than [`MAX_CUTOFF`].
+/// * [`ElinorError::DuplicateEntry`] if the records of a query are not contiguous in the stream.
+/// * [`ElinorError::DuplicateEntry`] if a query-document pair appears more than once.
+/// * [`ElinorError::MissingEntry`] if a query in the stream is missing in `true_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, PredRecord, TrueRelStoreBuilder};
+/// use elinor::metrics::compute_metrics_streaming;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_2", "d_2", 1)?;
+/// let true_rels = b.build();
+///
+/// let pred_records = [
+///     ("q_1", "d_2", 0.4),
+///     ("q_1", "d_1", 0.5),
+///     ("q_2", "d_1", 0.5),
+///     ("q_2", "d_2", 0.4),
+/// ]
+/// .into_iter()
+/// .map(|(query_id, doc_id, score)| PredRecord { query_id, doc_id, score: score.into() });
+///
+/// let metrics = [Metric::Precision { k: 1 }, Metric::RR { k: 0 }];
+/// let scores = compute_metrics_streaming(&true_rels, pred_records, &metrics)?;
+/// assert_eq!(scores[&Metric::Precision { k: 1 }]["q_1"], 1.0);
+/// assert_eq!(scores[&Metric::RR { k: 0 }]["q_2"], 0.5);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metrics_streaming<K, I>(
+    true_rels: &TrueRelStore<K>,
+    pred_records: I,
+    metrics: &[Metric],
+) -> Result<BTreeMap<Metric, BTreeMap<K, f64>>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+    I: IntoIterator<Item = Record<K, PredScore>>,
+{
+    for &metric in metrics {
+        check_cutoff(metric)?;
+    }
+    let mut results: BTreeMap<Metric, BTreeMap<K, f64>> = metrics
+        .iter()
+        .map(|&metric| (metric, BTreeMap::new()))
+        .collect();
+    let mut seen_query_ids = BTreeSet::new();
+    let mut flush = |query_id: K, docs: Vec<(K, PredScore)>| -> Result<(), ElinorError> {
+        if !seen_query_ids.insert(query_id.clone()) {
+            return Err(ElinorError::DuplicateEntry(format!(
+                "The records of each query must be contiguous in the stream, but got query_id={query_id} again"
+            )));
+        }
+        let pred_rels = PredRelStore::from_groups([(query_id, docs)])?;
+        for (metric, scores) in compute_metrics(true_rels, &pred_rels, metrics)? {
+            results.get_mut(&metric).unwrap().extend(scores);
+        }
+        Ok(())
+    };
+
+    let mut current: Option<(K, Vec<(K, PredScore)>)> = None;
+    for record in pred_records {
+        match &mut current {
+            Some((query_id, docs)) if *query_id == record.query_id => {
+                docs.push((record.doc_id, record.score));
+            }
+            _ => {
+                let next = (record.query_id, vec![(record.doc_id, record.score)]);
+                if let Some((query_id, docs)) = current.replace(next) {
+                    flush(query_id, docs)?;
+                }
+            }
+        }
+    }
+    if let Some((query_id, docs)) = current {
+        flush(query_id, docs)?;
+    }
+    Ok(results)
+}
+
+/// Counts of relevant documents in a ranking, shared among the metrics based on hits.
+struct HitProfile {
+    /// `cum_hits[i]` is the number of relevant documents in the top-i.
+    cum_hits: Vec<usize>,
+    /// Number of relevant documents in the true relevance scores.
+    n_rels: usize,
+}
+
+impl HitProfile {
+    fn new<K>(
+        trues: &BTreeMap<K, TrueScore>,
+        sorted_preds: &[Relevance<K, PredScore>],
+        rel_lvl: TrueScore,
+    ) -> Self
+    where
+        K: Eq + Ord,
+    {
+        let mut cum_hits = Vec::with_capacity(sorted_preds.len() + 1);
+        cum_hits.push(0);
+        for pred in sorted_preds {
+            let is_rel = trues.get(&pred.doc_id).map_or(false, |&rel| rel >= rel_lvl);
+            cum_hits.push(cum_hits[cum_hits.len() - 1] + usize::from(is_rel));
+        }
+        let n_rels = trues.values().filter(|&&rel| rel >= rel_lvl).count();
+        Self { cum_hits, n_rels }
+    }
+
+    fn n_retrieved(&self) -> usize {
+        self.cum_hits.len() - 1
+    }
+
+    /// Returns the number of relevant documents in the top-k, where k = 0 means all.
+    fn hits(&self, k: usize) -> usize {
+        let k = if k == 0 { self.n_retrieved() } else { k };
+        self.cum_hits[k.min(self.n_retrieved())]
+    }
+
+    fn precision(&self, k: usize) -> f64 {
+        let k = if k == 0 { self.n_retrieved() } else { k };
+        if k == 0 {
+            0.0
+        } else {
+            self.hits(k) as f64 / k as f64
+        }
+    }
+
+    /// Returns the score of the metric, or `None` if the metric is not based on hits.
+    /// The formulas are the same as those of the individual metric modules.
+    fn compute(&self, metric: Metric) -> Option<f64> {
+        let score = match metric {
+            Metric::Hits { k } => self.hits(k) as f64,
+            Metric::Success { k } => {
+                if self.hits(k) > 0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Metric::Precision { k } => self.precision(k),
+            Metric::Recall { k } => {
+                let k = if k == 0 { self.n_retrieved() } else { k };
+                if k == 0 || self.n_rels == 0 {
+                    0.0
+                } else {
+                    self.hits(k) as f64 / self.n_rels as f64
+                }
+            }
+            Metric::F1 { k } => {
+                let k = if k == 0 { self.n_retrieved() } else { k };
+                if k == 0 {
+                    return Some(0.0);
+                }
+                let hits = self.hits(k) as f64;
+                let precision = hits / k as f64;
+                let recall = hits / self.n_rels as f64;
+                if precision + recall == 0.0 {
+                    0.0
+                } else {
+                    2.0 * (precision * recall) / (precision + recall)
+                }
+            }
+            Metric::RPrecision => {
+                if self.n_rels == 0 {
+                    0.0
+                } else {
+                    self.precision(self.n_rels)
+                }
+            }
+            Metric::AP { k } => {
+                let k = if k == 0 { self.n_retrieved() } else { k };
+                if k == 0 || self.n_rels == 0 {
+                    return Some(0.0);
+                }
+                let mut sum = 0.0;
+                for i in 0..k.min(self.n_retrieved()) {
+                    if self.cum_hits[i + 1] > self.cum_hits[i] {
+                        sum += self.precision(i + 1);
+                    }
+                }
+                sum / self.n_rels as f64
+            }
+            Metric::RR { k } => {
+                let k = if k == 0 { self.n_retrieved() } else { k };
+                let depth = k.min(self.n_retrieved());
+                self.cum_hits[1..=depth]
+                    .iter()
+                    .position(|&h| h > 0)
+                    .map_or(0.0, |i| 1.0 / (i as f64 + 1.0))
+            }
+            _ => return None,
+        };
+        Some(score)
+    }
+}
+
+fn check_cutoff(metric: Metric) -> Result<(), ElinorError> {
+    let k = cutoff_of(metric);
+    if k as u64 > MAX_CUTOFF {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The cutoff k must be at most {MAX_CUTOFF}, but got {k}"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_queries<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+) -> Result<(), ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    for query_id in pred_rels.query_ids() {
+        if true_rels.get_map(query_id).is_none() {
+            return Err(ElinorError::MissingEntry(format!(
+                "The set of queries in true_rels must be a subset of that in pred_rels, but {} is missing",
+                query_id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn compute_query_metric<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_trues: &[Relevance<K, TrueScore>],
+    sorted_preds: &[Relevance<K, PredScore>],
+    metric: Metric,
+    rel_lvl: TrueScore,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    match metric {
+        Metric::Hits { k } => hits::compute_hits(trues, sorted_preds, k, rel_lvl),
+        Metric::Success { k } => success::compute_success(trues, sorted_preds, k, rel_lvl),
+        Metric::Precision { k } => precision::compute_precision(trues, sorted_preds, k, rel_lvl),
+        Metric::Recall { k } => recall::compute_recall(trues, sorted_preds, k, rel_lvl),
+        Metric::F1 { k } => f1::compute_f1(trues, sorted_preds, k, rel_lvl),
+        Metric::RPrecision => r_precision::compute_r_precision(trues, sorted_preds, rel_lvl),
+        Metric::AP { k } => {
+            average_precision::compute_average_precision(trues, sorted_preds, k, rel_lvl)
+        }
+        Metric::RR { k } => {
+            reciprocal_rank::compute_reciprocal_rank(trues, sorted_preds, k, rel_lvl)
+        }
+        Metric::Bpref { k, trec_compat } => {
+            bpref::compute_bpref(trues, sorted_preds, k, trec_compat, rel_lvl)
+        }
+        Metric::InfAP => infap::compute_infap(trues, sorted_preds, None, rel_lvl),
+        Metric::XInfAP => infap::compute_xinfap(trues, sorted_preds, None, rel_lvl),
+        Metric::Judged { k } => judged::compute_judged(trues, sorted_preds, k),
+        Metric::DCG { k } => ndcg::compute_dcg(trues, sorted_preds, k, &DcgWeighting::Jarvelin),
+        Metric::NDCG { k } => ndcg::compute_ndcg(
+            trues,
+            sorted_trues,
+            sorted_preds,
+            k,
+            &DcgWeighting::Jarvelin,
+        ),
+        Metric::DCGBurges { k } => ndcg::compute_dcg(trues, sorted_preds, k, &DcgWeighting::Burges),
+        Metric::NDCGBurges { k } => {
+            ndcg::compute_ndcg(trues, sorted_trues, sorted_preds, k, &DcgWeighting::Burges)
+        }
+        Metric::QMeasure { k, beta } => q_measure::compute_q_measure(
+            trues,
+            sorted_trues,
+            sorted_preds,
+            k,
+            beta.into_inner(),
+            rel_lvl,
+        ),
+    }
+}
+
+/// Checks if the score is within the bounds of the metric, allowing rounding errors.
+///
+/// NaN is regarded as within the bounds because it denotes an undefined score,
+/// e.g., [`Metric::F1`] for a query without relevant documents.
+fn is_within_bounds(metric: Metric, score: f64) -> bool {
+    const TOLERANCE: f64 = 1e-9;
+    let (lower, upper) = metric.bounds();
+    score.is_nan() || (lower - TOLERANCE <= score && score <= upper + TOLERANCE)
+}
+
+/// Interface of custom metrics, which can be evaluated by [`compute_metric_with`]
+/// and [`evaluate_with`](crate::evaluate_with) without forking the crate.
+///
+/// The results have [`MetricId::Custom`] with the name,
+/// so that they flow through [`Evaluation`](crate::Evaluation) and the statistical tests
+/// in the same way as the built-in metrics.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::collections::BTreeMap;
+///
+/// use elinor::metrics::MetricFn;
+/// use elinor::metrics::MetricId;
+/// use elinor::{PredRelStoreBuilder, PredScore, Relevance, TrueRelStoreBuilder, TrueScore};
+///
+/// /// Whether the top document has the highest relevance score.
+/// struct TopIsBest;
+///
+/// impl<K: Ord> MetricFn<K> for TopIsBest {
+///     fn name(&self) -> &'static str {
+///         "top_is_best"
+///     }
+///
+///     fn compute(&self, trues: &BTreeMap<K, TrueScore>, sorted_preds: &[Relevance<K, PredScore>]) -> f64 {
+///         let best = trues.values().max().copied().unwrap_or(0);
+///         let top = sorted_preds.first().and_then(|pred| trues.get(&pred.doc_id));
+///         if top == Some(&best) { 1.0 } else { 0.0 }
+///     }
+/// }
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 2)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_2", 0.4.into())?;
+/// let pred_rels = b.build();
+///
+/// let result = elinor::evaluate_with(&true_rels, &pred_rels, &TopIsBest)?;
+/// assert_eq!(result.metric(), &MetricId::Custom("top_is_best".to_string()));
+/// assert_eq!(result.mean(), 0.0);
+/// # Ok(())
+/// # }
+/// ```
+pub trait MetricFn<K> {
+    /// Returns the name of the metric.
+    fn name(&self) -> &'static str;
+
+    /// Computes the score of a query.
+    ///
+    /// # Arguments
+    ///
+    /// * `trues` - Mapping from document ids to true relevance scores of the query.
+    /// * `sorted_preds` - Predicted relevance scores of the query sorted in descending order.
+    fn compute(
+        &self,
+        trues: &BTreeMap<K, TrueScore>,
+        sorted_preds: &[Relevance<K, PredScore>],
+    ) -> f64;
+}
+
+/// Computes the scores of a custom metric for the given true and predicted relevance scores.
+///
+/// # Errors
+///
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+pub fn compute_metric_with<K, M>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric_fn: &M,
+) -> Result<BTreeMap<K, f64>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+    M: MetricFn<K> + ?Sized,
+{
+    let mut results = BTreeMap::new();
+    for query in joined_iter(true_rels, pred_rels)? {
+        results.insert(
+            query.query_id().clone(),
+            metric_fn.compute(query.trues(), query.sorted_preds()),
+        );
+    }
+    Ok(results)
+}
+
+/// Per-query auxiliary values computed along with metric scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryAuxiliary {
+    /// Number of retrieved documents, i.e., the length of the sorted predictions.
+    pub n_retrieved: usize,
+
+    /// Number of retrieved documents in the top-k.
+    pub n_retrieved_at_k: usize,
+
+    /// Number of judged documents, i.e., those in the true relevance scores, in the top-k.
+    pub n_judged_at_k: usize,
+}
+
+/// Policy to handle queries retrieving fewer than `k` documents.
+///
+/// # Conversion from string representation
+///
+/// ```rust
+/// use elinor::metrics::ShortRankingPolicy;
+///
+/// assert_eq!("pad".parse(), Ok(ShortRankingPolicy::Pad));
+/// assert_eq!("normalize".parse(), Ok(ShortRankingPolicy::Normalize));
+/// assert_eq!("reject".parse(), Ok(ShortRankingPolicy::Reject));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ShortRankingPolicy {
+    /// Pads the ranking with zero-gain slots up to `k`,
+    /// i.e., the denominators of [`Metric::Precision`], [`Metric::F1`], and [`Metric::Judged`] are fixed to `k`.
+    #[default]
+    Pad,
+
+    /// Normalizes [`Metric::Precision`], [`Metric::F1`], and [`Metric::Judged`] by the number of retrieved documents
+    /// instead of `k`. The other metrics are not affected.
+    Normalize,
+
+    /// Rejects the query with an error, requiring at least `k` retrieved documents for any metric with `k`.
+    Reject,
+}
+
+impl FromStr for ShortRankingPolicy {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pad" => Ok(Self::Pad),
+            "normalize" => Ok(Self::Normalize),
+            "reject" => Ok(Self::Reject),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported short ranking policy: {s}"
+            ))),
+        }
+    }
+}
+
+impl Display for ShortRankingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Pad => write!(f, "pad"),
+            Self::Normalize => write!(f, "normalize"),
+            Self::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// Options for [`compute_metric_with_options`] and [`compute_metrics_with_options`].
+///
+/// # Default parameters
+///
+/// * `auxiliary`: `false`
+/// * `short_ranking_policy`: [`ShortRankingPolicy::Pad`]
+/// * `bounds_check`: `false`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricOptions {
+    auxiliary: bool,
+    short_ranking_policy: ShortRankingPolicy,
+    bounds_check: bool,
+}
+
+impl MetricOptions {
+    /// Creates new options.
+    pub const fn new() -> Self {
+        Self {
+            auxiliary: false,
+            short_ranking_policy: ShortRankingPolicy::Pad,
+            bounds_check: false,
+        }
+    }
+
+    /// Sets whether to compute the per-query auxiliary values.
+    pub const fn with_auxiliary(mut self, auxiliary: bool) -> Self {
+        self.auxiliary = auxiliary;
+        self
+    }
+
+    /// Sets the policy for queries retrieving fewer than `k` documents.
+    pub const fn with_short_ranking_policy(mut self, policy: ShortRankingPolicy) -> Self {
+        self.short_ranking_policy = policy;
+        self
+    }
+
+    /// Sets whether to check the scores against [`Metric::bounds`],
+    /// where NaN scores (e.g., [`Metric::F1`] for queries without relevant documents) are accepted.
+    pub const fn with_bounds_check(mut self, bounds_check: bool) -> Self {
+        self.bounds_check = bounds_check;
+        self
+    }
+}
+
+/// Output of [`compute_metric_with_options`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricOutput<K> {
+    scores: BTreeMap<K, f64>,
+    auxiliary: Option<BTreeMap<K, QueryAuxiliary>>,
+}
+
+impl<K> MetricOutput<K> {
+    /// Returns the reference to the mapping from query ids to scores.
+    pub const fn scores(&self) -> &BTreeMap<K, f64> {
+        &self.scores
+    }
+
+    /// Returns the reference to the mapping from query ids to auxiliary values,
+    /// or `None` if not requested in the options.
+    pub const fn auxiliary(&self) -> Option<&BTreeMap<K, QueryAuxiliary>> {
+        self.auxiliary.as_ref()
+    }
+
+    /// Converts into the mapping from query ids to scores.
+    pub fn into_scores(self) -> BTreeMap<K, f64> {
+        self.scores
+    }
+}
+
+/// Returns the cutoff `k` of the metric, or 0 if the metric has no cutoff.
+const fn cutoff_of(metric: Metric) -> usize {
+    match metric {
+        Metric::RPrecision | Metric::InfAP | Metric::XInfAP => 0,
+        Metric::Hits { k }
+        | Metric::Success { k }
+        | Metric::Precision { k }
+        | Metric::Recall { k }
+        | Metric::F1 { k }
+        | Metric::AP { k }
+        | Metric::RR { k }
+        | Metric::Bpref { k, .. }
+        | Metric::Judged { k }
+        | Metric::DCG { k }
+        | Metric::NDCG { k }
+        | Metric::DCGBurges { k }
+        | Metric::NDCGBurges { k }
+        | Metric::QMeasure { k, .. } => k,
+    }
+}
+
+/// Computes the metric scores, together with the per-query auxiliary values if requested.
+///
+/// For metrics without `k`, such as [`Metric::RPrecision`] and [`Metric::InfAP`],
+/// the auxiliary values at `k` are computed over all the retrieved documents.
+/// Queries retrieving fewer than `k` documents are handled according to [`ShortRankingPolicy`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the policy is [`ShortRankingPolicy::Reject`]
+///   and a query retrieves fewer than `k` documents.
+/// * [`ElinorError::Uncomputable`] if the bounds check is enabled and a score is out of [`Metric::bounds`].
+///
+/// See [`compute_metric`] for the other errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, TrueRelStoreBuilder, PredRelStoreBuilder};
+/// use elinor::metrics::{compute_metric_with_options, MetricOptions};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 0)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// b.add_record("q_1", "d_2", 0.3.into())?;
+/// let pred_rels = b.build();
+///
+/// let options = MetricOptions::new().with_auxiliary(true);
+/// let output = compute_metric_with_options(&true_rels, &pred_rels, Metric::Precision { k: 2 }, options)?;
+/// let auxiliary = output.auxiliary().unwrap()["q_1"];
+/// assert_eq!(auxiliary.n_retrieved, 3);
+/// assert_eq!(auxiliary.n_retrieved_at_k, 2);
+/// assert_eq!(auxiliary.n_judged_at_k, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metric_with_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    options: MetricOptions,
+) -> Result<MetricOutput<K>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let mut scores = compute_metric(true_rels, pred_rels, metric)?;
+    for (query_id, score) in scores.iter_mut() {
+        let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+        let trues = true_rels.get_map(query_id).unwrap();
+        *score = apply_short_ranking_policy(
+            query_id,
+            trues,
+            sorted_preds,
+            metric,
+            options.short_ranking_policy,
+            *score,
+        )?;
+    }
+    if options.bounds_check {
+        check_bounds(metric, &scores)?;
+    }
+    let auxiliary = options.auxiliary.then(|| {
+        let k = cutoff_of(metric);
+        pred_rels
+            .query_ids()
+            .map(|query_id| {
+                let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+                let trues = true_rels.get_map(query_id).unwrap();
+                let k = if k == 0 { sorted_preds.len() } else { k };
+                let top_k = &sorted_preds[..k.min(sorted_preds.len())];
+                let auxiliary = QueryAuxiliary {
+                    n_retrieved: sorted_preds.len(),
+                    n_retrieved_at_k: top_k.len(),
+                    n_judged_at_k: top_k
+                        .iter()
+                        .filter(|pred| trues.contains_key(&pred.doc_id))
+                        .count(),
+                };
+                (query_id.clone(), auxiliary)
+            })
+            .collect()
+    });
+    Ok(MetricOutput { scores, auxiliary })
+}
+
+/// Applies the policy to the score of a query computed as if the ranking were padded to `k`.
+fn apply_short_ranking_policy<K>(
+    query_id: &K,
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    metric: Metric,
+    policy: ShortRankingPolicy,
+    score: f64,
+) -> Result<f64, ElinorError>
+where
+    K: Eq + Ord + std::fmt::Display,
+{
+    let cutoff = cutoff_of(metric);
+    let n_retrieved = sorted_preds.len();
+    if cutoff == 0 || n_retrieved >= cutoff {
+        return Ok(score);
+    }
+    match policy {
+        ShortRankingPolicy::Pad => Ok(score),
+        ShortRankingPolicy::Normalize => Ok(match metric {
+            Metric::Precision { .. } => {
+                precision::compute_precision(trues, sorted_preds, n_retrieved, RELEVANT_LEVEL)
+            }
+            Metric::F1 { .. } => f1::compute_f1(trues, sorted_preds, n_retrieved, RELEVANT_LEVEL),
+            Metric::Judged { .. } => judged::compute_judged(trues, sorted_preds, n_retrieved),
+            _ => score,
+        }),
+        ShortRankingPolicy::Reject => Err(ElinorError::InvalidArgument(format!(
+            "Query {query_id} retrieves {n_retrieved} documents, fewer than k={cutoff} of {metric}"
+        ))),
+    }
+}
+
+/// Checks if the scores are within the bounds of the metric.
+fn check_bounds<K>(metric: Metric, scores: &BTreeMap<K, f64>) -> Result<(), ElinorError>
+where
+    K: std::fmt::Display,
+{
+    if let Some((query_id, score)) = scores
+        .iter()
+        .find(|(_, &score)| !is_within_bounds(metric, score))
+    {
+        return Err(ElinorError::Uncomputable(format!(
+            "The score of {metric} for query {query_id} is out of its bounds {:?}: {score}",
+            metric.bounds()
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the auxiliary values are not requested in the options of multiple metrics.
+fn check_no_auxiliary(options: MetricOptions) -> Result<(), ElinorError> {
+    if options.auxiliary {
+        return Err(ElinorError::InvalidArgument(
+            "The auxiliary values are only available in compute_metric_with_options.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Computes the number of unjudged documents in the top-k for each query,
+/// i.e., the retrieved documents that do not appear in the true relevance scores.
+///
+/// This is the companion report of [`Metric::Judged`] to quantify incomplete judgments.
+/// If `k` is set to 0, all the retrieved documents are considered.
+///
+/// # Errors
+///
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, TrueRelStoreBuilder, PredRelStoreBuilder};
+/// use elinor::metrics::{compute_metric, compute_unjudged_counts};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 0)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// b.add_record("q_1", "d_2", 0.3.into())?;
+/// let pred_rels = b.build();
+///
+/// let judged = compute_metric(&true_rels, &pred_rels, Metric::Judged { k: 2 })?;
+/// assert_eq!(judged["q_1"], 0.5);
+///
+/// let counts = compute_unjudged_counts(&true_rels, &pred_rels, 2)?;
+/// assert_eq!(counts["q_1"], 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_unjudged_counts<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    k: usize,
+) -> Result<BTreeMap<K, usize>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    check_queries(true_rels, pred_rels)?;
+    let mut results = BTreeMap::new();
+    for query_id in pred_rels.query_ids() {
+        let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+        let trues = true_rels.get_map(query_id).unwrap();
+        results.insert(
+            query_id.clone(),
+            judged::compute_n_unjudged(trues, sorted_preds, k),
+        );
+    }
+    Ok(results)
+}
+
+/// Computes the scores of [`Metric::InfAP`] or [`Metric::XInfAP`] with the metadata of the sampled pool.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `metric` is neither [`Metric::InfAP`] nor [`Metric::XInfAP`].
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::{Metric, TrueRelStoreBuilder, PredRelStoreBuilder};
+/// use elinor::metrics::{compute_metric_with_pool, SampledPool};
+///
+/// // d_2 is pooled but not sampled for judgment.
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0)?;
+/// b.add_record("q_1", "d_3", 1)?;
+/// let true_rels = b.build();
+///
+/// let mut pool = SampledPool::new();
+/// for doc_id in ["d_1", "d_2", "d_3"] {
+///     pool.add_doc("q_1", doc_id, 0)?;
+/// }
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_2", 0.4.into())?;
+/// b.add_record("q_1", "d_3", 0.3.into())?;
+/// let pred_rels = b.build();
+///
+/// let scores = compute_metric_with_pool(&true_rels, &pred_rels, Metric::InfAP, &pool)?;
+/// assert!((scores["q_1"] - 1.0 / 3.0).abs() < 1e-4);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metric_with_pool<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    pool: &SampledPool<K>,
+) -> Result<BTreeMap<K, f64>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    if !matches!(metric, Metric::InfAP | Metric::XInfAP) {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The pool is only available for infap and xinfap, but got {metric}"
+        )));
+    }
+    check_queries(true_rels, pred_rels)?;
+    let mut results = BTreeMap::new();
+    for query_id in pred_rels.query_ids() {
+        let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+        let trues = true_rels.get_map(query_id).unwrap();
+        let docs = pool.get_map(query_id);
+        let score = match metric {
+            Metric::InfAP => infap::compute_infap(trues, sorted_preds, docs, RELEVANT_LEVEL),
+            Metric::XInfAP => infap::compute_xinfap(trues, sorted_preds, docs, RELEVANT_LEVEL),
+            _ => unreachable!(),
+        };
+        results.insert(query_id.clone(), score);
+    }
+    Ok(results)
+}
+
+/// Computes the DCG-family metric scores with the given options,
+/// e.g., with custom gains or in the convention of other toolkits.
+///
+/// The weighting in `options` replaces that of [`Metric::DCG`] or [`Metric::NDCG`],
+/// e.g., [`DcgWeighting::Burges`] gives the same scores as [`Metric::DCGBurges`] and [`Metric::NDCGBurges`].
+/// With [`DcgWeighting::Custom`], the grades not in the mapping have zero gains.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `metric` is none of [`Metric::DCG`], [`Metric::NDCG`],
+///   [`Metric::DCGBurges`], and [`Metric::NDCGBurges`].
+/// * [`ElinorError::InvalidArgument`] if the weighting is set and `metric` is neither [`Metric::DCG`] nor [`Metric::NDCG`].
+/// * [`ElinorError::InvalidArgument`] if the log base of the convention is not finite or not greater than 1.
+/// * [`ElinorError::MissingEntry`] if the set of queries in `true_rels` is not a subset of that in `pred_rels`.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::sync::Arc;
+///
+/// use approx::assert_abs_diff_eq;
+/// use elinor::{Metric, TrueRelStoreBuilder, PredRelStoreBuilder};
+/// use elinor::metrics::{compute_metric_with_dcg_options, DcgConvention, DcgDiscount, DcgOptions, DcgWeighting};
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_1", "d_2", 2)?;
+/// let true_rels = b.build();
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.5.into())?;
+/// b.add_record("q_1", "d_3", 0.4.into())?;
+/// b.add_record("q_1", "d_2", 0.3.into())?;
+/// let pred_rels = b.build();
+///
+/// // Gains given by a closure with the discount of log10.
+/// let gain = |grade| if grade >= 2 { 5.0 } else { grade as f64 };
+/// let options = DcgOptions::new()
+///     .with_weighting(DcgWeighting::Function(Arc::new(gain)))
+///     .with_convention(DcgConvention::new().with_log_base(10.0));
+/// let scores = compute_metric_with_dcg_options(&true_rels, &pred_rels, Metric::DCG { k: 0 }, &options)?;
+/// assert_abs_diff_eq!(
+///     scores["q_1"],
+///     1.0 / 2.0_f64.log10() + 5.0 / 4.0_f64.log10(),
+///     epsilon = 1e-10
+/// );
+///
+/// // The gain at rank 2 is not discounted.
+/// let convention = DcgConvention::new().with_discount(DcgDiscount::Original);
+/// let options = DcgOptions::new().with_convention(convention);
+/// let scores = compute_metric_with_dcg_options(&true_rels, &pred_rels, Metric::DCG { k: 0 }, &options)?;
+/// assert_abs_diff_eq!(scores["q_1"], 1.0 + 2.0 / 3.0_f64.log2(), epsilon = 1e-10);
+/// # Ok(())
+/// # }
+/// ```
+pub fn compute_metric_with_dcg_options<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    options: &DcgOptions,
+) -> Result<BTreeMap<K, f64>, ElinorError>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let (dcg_metric, default_weighting) = match metric {
+        Metric::DCG { k } => (Metric::DCG { k }, DcgWeighting::Jarvelin),
+        Metric::NDCG { k } => (Metric::NDCG { k }, DcgWeighting::Jarvelin),
+        Metric::DCGBurges { k } => (Metric::DCG { k }, DcgWeighting::Burges),
+        Metric::NDCGBurges { k } => (Metric::NDCG { k }, DcgWeighting::Burges),
+        _ => {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The DCG options are only available for dcg, ndcg, dcg_burges, and ndcg_burges, but got {metric}"
+            )))
+        }
+    };
+    let weighting = match options.weighting() {
+        Some(_) if default_weighting == DcgWeighting::Burges => {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The weighting is only available for dcg and ndcg, but got {metric}"
+            )))
+        }
+        Some(weighting) => weighting,
+        None => &default_weighting,
+    };
+    let convention = options.convention();
+    convention.validate()?;
+    check_cutoff(dcg_metric)?;
+    check_queries(true_rels, pred_rels)?;
+    Ok(compute_dcg_scores(
+        true_rels, pred_rels, dcg_metric, weighting, convention,
+    ))
+}
+
+/// Computes the scores of [`Metric::DCG`] or [`Metric::NDCG`] after validation.
+fn compute_dcg_scores<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+    metric: Metric,
+    weighting: &DcgWeighting,
+    convention: DcgConvention,
+) -> BTreeMap<K, f64>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let mut results = BTreeMap::new();
+    for query_id in pred_rels.query_ids() {
+        let sorted_preds = pred_rels.get_sorted(query_id).unwrap();
+        let trues = true_rels.get_map(query_id).unwrap();
+        let score = match metric {
+            Metric::DCG { k } => {
+                ndcg::compute_dcg_with_convention(trues, sorted_preds, k, weighting, convention)
+            }
+            Metric::NDCG { k } => {
+                let sorted_trues = true_rels.get_sorted(query_id).unwrap();
+                ndcg::compute_ndcg_with_convention(
+                    trues,
+                    sorted_trues,
+                    sorted_preds,
+                    k,
+                    weighting,
+                    convention,
+                )
+            }
+            _ => unreachable!(),
+        };
+        results.insert(query_id.clone(), score);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Record;
+    use approx::assert_relative_eq;
+    use maplit::btreemap;
+    use rstest::*;
+
+    const LOG_2_2: f64 = 1.0;
+    const LOG_2_3: f64 = 1.584962500721156;
+    const LOG_2_4: f64 = 2.0;
+
+    fn compare_hashmaps(a: &BTreeMap<char, f64>, b: &BTreeMap<char, f64>) {
+        assert_eq!(a.len(), b.len());
+        for (k, v) in a.iter() {
+            assert_relative_eq!(v, b.get(k).unwrap());
+        }
+    }
+
+    #[rstest]
     // Hits
     #[case::hits_k_0(Metric::Hits { k: 0 }, btreemap! { 'A' => 2.0 })]
     #[case::hits_k_1(Metric::Hits { k: 1 }, btreemap! { 'A' => 1.0 })]
@@ -466,7 +2202,16 @@ mod tests {
     #[case::reciprocal_rank_k_4(Metric::RR { k: 4 }, btreemap! { 'A' => 1.0 / 1.0 })]
     #[case::reciprocal_rank_k_5(Metric::RR { k: 5 }, btreemap! { 'A' => 1.0 / 1.0 })]
     // Bpref
-    #[case::bpref(Metric::Bpref, btreemap! { 'A' => (1.0 + (1.0 - 1.0 / 1.0)) / 2.0 })]
+    #[case::bpref(Metric::Bpref { k: 0, trec_compat: true }, btreemap! { 'A' => (1.0 + (1.0 - 1.0 / 1.0)) / 2.0 })]
+    #[case::bpref_k_1(Metric::Bpref { k: 1, trec_compat: true }, btreemap! { 'A' => 1.0 / 2.0 })]
+    #[case::bpref_k_2(Metric::Bpref { k: 2, trec_compat: true }, btreemap! { 'A' => 1.0 / 2.0 })]
+    #[case::bpref_original(Metric::Bpref { k: 0, trec_compat: false }, btreemap! { 'A' => (1.0 + (1.0 - 1.0 / 2.0)) / 2.0 })]
+    // Judged
+    #[case::judged_k_0(Metric::Judged { k: 0 }, btreemap! { 'A' => 3.0 / 4.0 })]
+    #[case::judged_k_1(Metric::Judged { k: 1 }, btreemap! { 'A' => 1.0 / 1.0 })]
+    #[case::judged_k_3(Metric::Judged { k: 3 }, btreemap! { 'A' => 3.0 / 3.0 })]
+    #[case::judged_k_4(Metric::Judged { k: 4 }, btreemap! { 'A' => 3.0 / 4.0 })]
+    #[case::judged_k_5(Metric::Judged { k: 5 }, btreemap! { 'A' => 3.0 / 5.0 })]
     // DCG (Jarvelin)
     #[case::dcg_k_0_jarvelin(Metric::DCG { k: 0 }, btreemap! { 'A' => 1.0 / LOG_2_2 + 2.0 / LOG_2_4 })]
     #[case::dcg_k_1_jarvelin(Metric::DCG { k: 1 }, btreemap! { 'A' => 1.0 / LOG_2_2 })]
@@ -495,6 +2240,14 @@ mod tests {
     #[case::ndcg_k_3_burges(Metric::NDCGBurges { k: 3 }, btreemap! { 'A' => (1.0 / LOG_2_2 + 3.0 / LOG_2_4) / (3.0 / LOG_2_2 + 1.0 / LOG_2_3) })]
     #[case::ndcg_k_4_burges(Metric::NDCGBurges { k: 4 }, btreemap! { 'A' => (1.0 / LOG_2_2 + 3.0 / LOG_2_4) / (3.0 / LOG_2_2 + 1.0 / LOG_2_3) })]
     #[case::ndcg_k_5_burges(Metric::NDCGBurges { k: 5 }, btreemap! { 'A' => (1.0 / LOG_2_2 + 3.0 / LOG_2_4) / (3.0 / LOG_2_2 + 1.0 / LOG_2_3) })]
+    // Q-measure
+    #[case::q_measure_k_0(Metric::QMeasure { k: 0, beta: OrderedFloat(1.0) }, btreemap! { 'A' => (2.0 / 3.0 + 5.0 / 6.0) / 2.0 })]
+    #[case::q_measure_k_1(Metric::QMeasure { k: 1, beta: OrderedFloat(1.0) }, btreemap! { 'A' => (2.0 / 3.0) / 1.0 })]
+    #[case::q_measure_k_2(Metric::QMeasure { k: 2, beta: OrderedFloat(1.0) }, btreemap! { 'A' => (2.0 / 3.0) / 2.0 })]
+    #[case::q_measure_k_3(Metric::QMeasure { k: 3, beta: OrderedFloat(1.0) }, btreemap! { 'A' => (2.0 / 3.0 + 5.0 / 6.0) / 2.0 })]
+    #[case::q_measure_k_5(Metric::QMeasure { k: 5, beta: OrderedFloat(1.0) }, btreemap! { 'A' => (2.0 / 3.0 + 5.0 / 6.0) / 2.0 })]
+    #[case::q_measure_k_0_beta_0(Metric::QMeasure { k: 0, beta: OrderedFloat(0.0) }, btreemap! { 'A' => ((1.0 / 1.0) + (2.0 / 3.0)) / 2.0 })]
+    #[case::q_measure_k_0_beta_2(Metric::QMeasure { k: 0, beta: OrderedFloat(2.0) }, btreemap! { 'A' => ((1.0 + 2.0) / (1.0 + 4.0) + (2.0 + 6.0) / (3.0 + 6.0)) / 2.0 })]
     fn test_compute_metric(#[case] metric: Metric, #[case] expected: BTreeMap<char, f64>) {
         let true_rels = TrueRelStore::from_records([
             Record {
@@ -532,13 +2285,1209 @@ mod tests {
             },
             Record {
                 query_id: 'A',
-                doc_id: 'W',
-                score: 0.2.into(),
+                doc_id: 'W',
+                score: 0.2.into(),
+            },
+        ])
+        .unwrap();
+        let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+        compare_hashmaps(&results, &expected);
+    }
+
+    // Hand-computed values of the Q-measure following the definition of NTCIREVAL,
+    // Q@l = (1 / min(l, R)) * sum_{r <= l} I(r) (beta * cg(r) + count(r)) / (beta * cg*(r) + r),
+    // with the gains of the relevance levels L1, L2, and L3 being 1, 2, and 3.
+    // They are exact fractions worked out by hand, not output of NTCIREVAL itself;
+    // beta = 0 gives AP = (1/2 + 2/4 + 3/5 + 4/6) / 5 as a sanity check.
+    #[rstest]
+    #[case::beta_1(Metric::QMeasure { k: 0, beta: OrderedFloat(1.0) }, 1003.0 / 2100.0)]
+    #[case::beta_1_k_3(Metric::QMeasure { k: 3, beta: OrderedFloat(1.0) }, 1.0 / 7.0)]
+    #[case::beta_1_k_5(Metric::QMeasure { k: 5, beta: OrderedFloat(1.0) }, 139.0 / 420.0)]
+    #[case::beta_0_5(Metric::QMeasure { k: 0, beta: OrderedFloat(0.5) }, 45061.0 / 95760.0)]
+    #[case::beta_0(Metric::QMeasure { k: 0, beta: OrderedFloat(0.0) }, 34.0 / 75.0)]
+    #[case::beta_10(Metric::QMeasure { k: 0, beta: OrderedFloat(10.0) }, 1019509.0 / 2074800.0)]
+    fn test_q_measure_hand_computed_values(#[case] metric: Metric, #[case] expected: f64) {
+        let true_rels = TrueRelStore::from_records(
+            [('a', 3), ('b', 2), ('c', 1), ('d', 0), ('e', 2), ('f', 1)]
+                .into_iter()
+                .map(|(doc_id, score)| Record {
+                    query_id: 'A',
+                    doc_id,
+                    score,
+                }),
+        )
+        .unwrap();
+        // The relevant document e is not retrieved, and g is not judged.
+        let pred_rels =
+            PredRelStore::from_records(['d', 'b', 'g', 'a', 'c', 'f'].into_iter().enumerate().map(
+                |(i, doc_id)| Record {
+                    query_id: 'A',
+                    doc_id,
+                    score: (1.0 - i as f64 / 10.0).into(),
+                },
+            ))
+            .unwrap();
+        let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+        assert_relative_eq!(results[&'A'], expected, max_relative = 1e-12);
+    }
+
+    // Only one of the four relevant documents is retrieved,
+    // so the denominator is still min(k, R) rather than the number of retrieved documents.
+    #[rstest]
+    #[case::beta_0(Metric::QMeasure { k: 0, beta: OrderedFloat(0.0) }, 1.0 / 4.0)]
+    #[case::beta_1(Metric::QMeasure { k: 0, beta: OrderedFloat(1.0) }, 1.0 / 4.0)]
+    #[case::beta_1_k_2(Metric::QMeasure { k: 2, beta: OrderedFloat(1.0) }, 1.0 / 2.0)]
+    #[case::beta_1_k_10(Metric::QMeasure { k: 10, beta: OrderedFloat(1.0) }, 1.0 / 4.0)]
+    #[case::ap(Metric::AP { k: 0 }, 1.0 / 4.0)]
+    fn test_q_measure_with_fewer_retrieved_than_relevant(
+        #[case] metric: Metric,
+        #[case] expected: f64,
+    ) {
+        let true_rels =
+            TrueRelStore::from_records(['a', 'b', 'c', 'd'].into_iter().map(|doc_id| Record {
+                query_id: 'A',
+                doc_id,
+                score: 1,
+            }))
+            .unwrap();
+        let pred_rels = PredRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'a',
+            score: 1.0.into(),
+        }])
+        .unwrap();
+        let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+        assert_relative_eq!(results[&'A'], expected, max_relative = 1e-12);
+    }
+
+    struct PrecisionAt1;
+
+    impl<K: Ord> MetricFn<K> for PrecisionAt1 {
+        fn name(&self) -> &'static str {
+            "precision_at_1"
+        }
+
+        fn compute(
+            &self,
+            trues: &BTreeMap<K, TrueScore>,
+            sorted_preds: &[Relevance<K, PredScore>],
+        ) -> f64 {
+            precision::compute_precision(trues, sorted_preds, 1, RELEVANT_LEVEL)
+        }
+    }
+
+    #[test]
+    fn test_compute_metric_with() {
+        let true_rels = TrueRelStore::from_records((0..10).flat_map(|q| {
+            (0..5).map(move |d| Record {
+                query_id: q,
+                doc_id: d,
+                score: (q + d) % 2,
+            })
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..10).flat_map(|q| {
+            (0..5).map(move |d| Record {
+                query_id: q,
+                doc_id: d,
+                score: ((d * q % 5) as f64).into(),
+            })
+        }))
+        .unwrap();
+        let expected = compute_metric(&true_rels, &pred_rels, Metric::Precision { k: 1 }).unwrap();
+        let actual = compute_metric_with(&true_rels, &pred_rels, &PrecisionAt1).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_compute_metric_cutoff_limit() {
+        let true_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 1,
+        }])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 0.5.into(),
+        }])
+        .unwrap();
+        let k = MAX_CUTOFF as usize;
+        let results = compute_metric(&true_rels, &pred_rels, Metric::Hits { k }).unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 });
+        let k = k + 1;
+        assert_eq!(
+            compute_metric(&true_rels, &pred_rels, Metric::Precision { k }),
+            Err(ElinorError::InvalidArgument(format!(
+                "The cutoff k must be at most {MAX_CUTOFF}, but got {k}"
+            )))
+        );
+        assert!(compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::NDCG { k },
+            &DcgOptions::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_compute_metrics_at_extreme_scale() {
+        // A query retrieving a quarter million documents, where every other document is relevant.
+        const N_DOCS: u32 = 1 << 18;
+        let true_rels = TrueRelStore::from_records((0..N_DOCS).map(|d| Record {
+            query_id: 0,
+            doc_id: d,
+            score: (d + 1) % 2,
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..N_DOCS).map(|d| Record {
+            query_id: 0,
+            doc_id: d,
+            score: f64::from(N_DOCS - d).into(),
+        }))
+        .unwrap();
+        let n_rels = f64::from(N_DOCS / 2);
+        let expected_ap = (0..N_DOCS / 2)
+            .map(|j| f64::from(j + 1) / f64::from(2 * j + 1))
+            .sum::<f64>()
+            / n_rels;
+        for (metric, expected) in [
+            (Metric::Hits { k: 0 }, n_rels),
+            (Metric::Hits { k: 1000 }, 500.0),
+            (Metric::Success { k: 0 }, 1.0),
+            (Metric::Precision { k: 0 }, 0.5),
+            (Metric::Recall { k: 0 }, 1.0),
+            (Metric::RPrecision, 0.5),
+            (Metric::Judged { k: 0 }, 1.0),
+            (Metric::AP { k: 0 }, expected_ap),
+        ] {
+            let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            assert_eq!(results[&0], expected, "{metric}");
+        }
+    }
+
+    #[test]
+    fn test_compute_metrics() {
+        // Query 0 has no relevant documents, and query 1 has no retrieved relevant documents.
+        let true_rels = TrueRelStore::from_records((0..30).flat_map(|q| {
+            (0..10).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: if q == 0 { 0 } else { (q + d) % 3 },
+            })
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..30).flat_map(|q| {
+            let offset = if q == 1 { 100 } else { 0 };
+            (0..(q % 15)).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d + offset,
+                score: (((q * d) % 7) as f64).into(),
+            })
+        }))
+        .unwrap();
+        let mut metrics = vec![
+            Metric::RPrecision,
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+        ];
+        for k in [0, 1, 5, 20] {
+            metrics.extend([
+                Metric::Hits { k },
+                Metric::Success { k },
+                Metric::Precision { k },
+                Metric::Recall { k },
+                Metric::F1 { k },
+                Metric::AP { k },
+                Metric::RR { k },
+                Metric::Judged { k },
+                Metric::NDCG { k },
+            ]);
+        }
+        let results = compute_metrics(&true_rels, &pred_rels, &metrics).unwrap();
+        assert_eq!(results.len(), metrics.len());
+        for metric in metrics {
+            let expected = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            let actual = &results[&metric];
+            assert_eq!(actual.len(), expected.len());
+            for (query_id, score) in expected {
+                let other = actual[&query_id];
+                assert!(
+                    score == other || (score.is_nan() && other.is_nan()),
+                    "{metric} for {query_id}: {score} != {other}"
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_compute_metric_parallel() {
+        let true_rels = TrueRelStore::from_records((0..100).flat_map(|q| {
+            (0..10).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (q + d) % 3,
+            })
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..100).flat_map(|q| {
+            (0..20).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (((q * d) % 7) as f64).into(),
+            })
+        }))
+        .unwrap();
+        for metric in [
+            Metric::AP { k: 0 },
+            Metric::NDCG { k: 5 },
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+        ] {
+            let expected = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            let actual = compute_metric_parallel(&true_rels, &pred_rels, metric).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_compute_metrics_streaming() {
+        let true_rels = TrueRelStore::from_records((0..50).flat_map(|q| {
+            (0..10).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (q + d) % 3,
+            })
+        }))
+        .unwrap();
+        let pred_records = (0..50)
+            .flat_map(|q| {
+                (0..20).map(move |d| Record {
+                    query_id: q,
+                    doc_id: q * 10 + d,
+                    score: (((q * d) % 7) as f64).into(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let pred_rels = PredRelStore::from_records(pred_records.clone()).unwrap();
+        let metrics = [
+            Metric::Precision { k: 10 },
+            Metric::AP { k: 0 },
+            Metric::NDCG { k: 5 },
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+        ];
+        let expected = compute_metrics(&true_rels, &pred_rels, &metrics).unwrap();
+        let actual = compute_metrics_streaming(&true_rels, pred_records, &metrics).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_compute_metrics_streaming_errors() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'B',
+                doc_id: 'X',
+                score: 1,
+            },
+        ])
+        .unwrap();
+        let record = |query_id, doc_id| Record {
+            query_id,
+            doc_id,
+            score: 0.5.into(),
+        };
+        let metrics = [Metric::AP { k: 0 }];
+        let non_contiguous = [record('A', 'X'), record('B', 'X'), record('A', 'Y')];
+        assert!(matches!(
+            compute_metrics_streaming(&true_rels, non_contiguous, &metrics),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+        let duplicate = [record('A', 'X'), record('A', 'X')];
+        assert!(matches!(
+            compute_metrics_streaming(&true_rels, duplicate, &metrics),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+        let missing = [record('A', 'X'), record('C', 'X')];
+        assert!(matches!(
+            compute_metrics_streaming(&true_rels, missing, &metrics),
+            Err(ElinorError::MissingEntry(_))
+        ));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_compute_metrics_parallel() {
+        let true_rels = TrueRelStore::from_records((0..100).flat_map(|q| {
+            (0..10).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (q + d) % 3,
+            })
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..100).flat_map(|q| {
+            (0..20).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (((q * d) % 7) as f64).into(),
+            })
+        }))
+        .unwrap();
+        let metrics = [
+            Metric::Success { k: 5 },
+            Metric::Precision { k: 10 },
+            Metric::AP { k: 0 },
+            Metric::RR { k: 0 },
+            Metric::NDCG { k: 5 },
+            Metric::NDCG { k: 5 },
+        ];
+        let expected = compute_metrics(&true_rels, &pred_rels, &metrics).unwrap();
+        let actual = compute_metrics_parallel(&true_rels, &pred_rels, &metrics).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    #[case::dcg_k_0(Metric::DCG { k: 0 }, btreemap! { 'A' => 1.0 / LOG_2_2 + 7.0 / LOG_2_4 })]
+    #[case::dcg_k_1(Metric::DCG { k: 1 }, btreemap! { 'A' => 1.0 / LOG_2_2 })]
+    #[case::ndcg_k_0(Metric::NDCG { k: 0 }, btreemap! { 'A' => (1.0 / LOG_2_2 + 7.0 / LOG_2_4) / (7.0 / LOG_2_2 + 1.0 / LOG_2_3) })]
+    #[case::ndcg_k_1(Metric::NDCG { k: 1 }, btreemap! { 'A' => (1.0 / LOG_2_2) / (7.0 / LOG_2_2) })]
+    fn test_compute_metric_with_dcg_options_gains(
+        #[case] metric: Metric,
+        #[case] expected: BTreeMap<char, f64>,
+    ) {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 2,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.4.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 0.3.into(),
+            },
+        ])
+        .unwrap();
+        let gains = GainMapping::new([(0, 0.0), (1, 1.0), (2, 7.0)]);
+        let options = DcgOptions::new().with_weighting(DcgWeighting::Custom(gains));
+        let results =
+            compute_metric_with_dcg_options(&true_rels, &pred_rels, metric, &options).unwrap();
+        compare_hashmaps(&results, &expected);
+    }
+
+    #[test]
+    fn test_compute_metric_with_dcg_options_unmapped_grade() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 3,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.4.into(),
+            },
+        ])
+        .unwrap();
+        let gains = GainMapping::new([(0, 0.0), (1, 1.0)]);
+        let options = DcgOptions::new().with_weighting(DcgWeighting::Custom(gains));
+        let results =
+            compute_metric_with_dcg_options(&true_rels, &pred_rels, Metric::DCG { k: 0 }, &options)
+                .unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 / LOG_2_3 });
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::NDCG { k: 0 },
+            &options,
+        )
+        .unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 / LOG_2_3 });
+    }
+
+    #[test]
+    fn test_compute_metric_with_dcg_options_weighting() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 3,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.4.into(),
+            },
+        ])
+        .unwrap();
+        for (metric, burges) in [
+            (Metric::DCG { k: 0 }, Metric::DCGBurges { k: 0 }),
+            (Metric::NDCG { k: 1 }, Metric::NDCGBurges { k: 1 }),
+        ] {
+            let options = DcgOptions::new().with_weighting(DcgWeighting::Burges);
+            let results =
+                compute_metric_with_dcg_options(&true_rels, &pred_rels, metric, &options).unwrap();
+            let expected = compute_metric(&true_rels, &pred_rels, burges).unwrap();
+            compare_hashmaps(&results, &expected);
+            let options = DcgOptions::new().with_weighting(DcgWeighting::Jarvelin);
+            let results =
+                compute_metric_with_dcg_options(&true_rels, &pred_rels, metric, &options).unwrap();
+            let expected = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            compare_hashmaps(&results, &expected);
+        }
+        let options = DcgOptions::new().with_weighting(DcgWeighting::Burges);
+        assert_eq!(
+            compute_metric_with_dcg_options(
+                &true_rels,
+                &pred_rels,
+                Metric::DCGBurges { k: 0 },
+                &options
+            ),
+            Err(ElinorError::InvalidArgument(
+                "The weighting is only available for dcg and ndcg, but got dcg_burges".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_metric_with_dcg_options_convention() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 2,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.4.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 0.3.into(),
+            },
+        ])
+        .unwrap();
+
+        // The default convention is the same as the standard metrics.
+        for metric in [
+            Metric::DCG { k: 2 },
+            Metric::NDCG { k: 2 },
+            Metric::DCGBurges { k: 0 },
+            Metric::NDCGBurges { k: 0 },
+        ] {
+            let results =
+                compute_metric_with_dcg_options(&true_rels, &pred_rels, metric, &DcgOptions::new())
+                    .unwrap();
+            let expected = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            compare_hashmaps(&results, &expected);
+        }
+
+        let convention = DcgConvention::new().with_discount(DcgDiscount::Original);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::DCG { k: 0 },
+            &DcgOptions::new().with_convention(convention),
+        )
+        .unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 + 2.0 / LOG_2_3 });
+
+        let convention = DcgConvention::new().with_ideal_cutoff(IdealCutoff::AllRelevant);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::NDCG { k: 1 },
+            &DcgOptions::new().with_convention(convention),
+        )
+        .unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 / (2.0 + 1.0 / LOG_2_3) });
+
+        // Ranks up to the log base are not discounted in the original discount.
+        let convention = DcgConvention::new()
+            .with_discount(DcgDiscount::Original)
+            .with_log_base(3.0);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::DCG { k: 0 },
+            &DcgOptions::new().with_convention(convention),
+        )
+        .unwrap();
+        compare_hashmaps(&results, &btreemap! { 'A' => 1.0 + 2.0 });
+
+        // The log base cancels out in NDCG with the standard discount.
+        let convention = DcgConvention::new().with_log_base(10.0);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::NDCG { k: 0 },
+            &DcgOptions::new().with_convention(convention),
+        )
+        .unwrap();
+        let expected = compute_metric(&true_rels, &pred_rels, Metric::NDCG { k: 0 }).unwrap();
+        compare_hashmaps(&results, &expected);
+
+        for log_base in [1.0, 0.5, f64::INFINITY, f64::NAN] {
+            let convention = DcgConvention::new().with_log_base(log_base);
+            assert!(matches!(
+                compute_metric_with_dcg_options(
+                    &true_rels,
+                    &pred_rels,
+                    Metric::DCG { k: 0 },
+                    &DcgOptions::new().with_convention(convention),
+                ),
+                Err(ElinorError::InvalidArgument(_))
+            ));
+        }
+
+        assert_eq!(
+            compute_metric_with_dcg_options(
+                &true_rels,
+                &pred_rels,
+                Metric::AP { k: 0 },
+                &DcgOptions::new()
+            ),
+            Err(ElinorError::InvalidArgument(
+                "The DCG options are only available for dcg, ndcg, dcg_burges, and ndcg_burges, but got ap"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_metric_with_dcg_options_weighting_and_convention() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 2,
             },
         ])
         .unwrap();
-        let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
-        compare_hashmaps(&results, &expected);
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.4.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 0.3.into(),
+            },
+        ])
+        .unwrap();
+
+        // A gain function equivalent to the Burges weighting.
+        let gain = |grade: TrueScore| 2.0_f64.powi(grade as i32) - 1.0;
+        for (metric, burges) in [
+            (Metric::DCG { k: 0 }, Metric::DCGBurges { k: 0 }),
+            (Metric::NDCG { k: 2 }, Metric::NDCGBurges { k: 2 }),
+        ] {
+            let results = compute_metric_with_dcg_options(
+                &true_rels,
+                &pred_rels,
+                metric,
+                &DcgOptions::new()
+                    .with_weighting(DcgWeighting::Function(std::sync::Arc::new(gain)))
+                    .with_convention(DcgConvention::new()),
+            )
+            .unwrap();
+            let expected = compute_metric(&true_rels, &pred_rels, burges).unwrap();
+            compare_hashmaps(&results, &expected);
+        }
+
+        let gains = GainMapping::new([(0, 0.0), (1, 1.0), (2, 10.0)]);
+        let convention = DcgConvention::new().with_log_base(10.0);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::DCG { k: 0 },
+            &DcgOptions::new()
+                .with_weighting(DcgWeighting::Custom(gains))
+                .with_convention(convention),
+        )
+        .unwrap();
+        compare_hashmaps(
+            &results,
+            &btreemap! { 'A' => 1.0 / 2.0_f64.log10() + 10.0 / 4.0_f64.log10() },
+        );
+
+        let gains = GainMapping::new([(0, 0.0), (1, 1.0)]);
+        let results = compute_metric_with_dcg_options(
+            &true_rels,
+            &pred_rels,
+            Metric::DCG { k: 0 },
+            &DcgOptions::new()
+                .with_weighting(DcgWeighting::Custom(gains))
+                .with_convention(convention),
+        )
+        .unwrap();
+        assert_relative_eq!(results[&'A'], 1.0 / 2.0_f64.log10(), epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_compute_infap_complete_judgments() {
+        let true_rels = TrueRelStore::from_records((0..10).flat_map(|q| {
+            (0..8).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (q + d) % 3 / 2,
+            })
+        }))
+        .unwrap();
+        let pred_rels = PredRelStore::from_records((0..10).flat_map(|q| {
+            (0..10).map(move |d| Record {
+                query_id: q,
+                doc_id: q * 10 + d,
+                score: (((q * d) % 7) as f64).into(),
+            })
+        }))
+        .unwrap();
+        // Without unjudged documents in the pool, the scores are close to AP.
+        let expected = compute_metric(&true_rels, &pred_rels, Metric::AP { k: 0 }).unwrap();
+        for metric in [Metric::InfAP, Metric::XInfAP] {
+            let results = compute_metric(&true_rels, &pred_rels, metric).unwrap();
+            for (query_id, score) in &expected {
+                assert_relative_eq!(results[query_id], score, epsilon = 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_metric_with_pool() {
+        // Stratum 0 has d_1 (relevant), d_2 (non-relevant), and d_4 (unjudged),
+        // and stratum 1 has d_3 (relevant).
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: '1',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: '2',
+                score: 0,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: '3',
+                score: 1,
+            },
+        ])
+        .unwrap();
+        let mut pool = SampledPool::new();
+        pool.add_doc('A', '1', 0).unwrap();
+        pool.add_doc('A', '2', 0).unwrap();
+        pool.add_doc('A', '4', 0).unwrap();
+        pool.add_doc('A', '3', 1).unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: '1',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: '2',
+                score: 0.4.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: '3',
+                score: 0.3.into(),
+            },
+        ])
+        .unwrap();
+
+        let results =
+            compute_metric_with_pool(&true_rels, &pred_rels, Metric::InfAP, &pool).unwrap();
+        assert_relative_eq!(results[&'A'], (1.0 + 2.0 / 3.0) / 2.0, epsilon = 1e-4);
+
+        // The sampling rates are 2/3 and 1.
+        let results =
+            compute_metric_with_pool(&true_rels, &pred_rels, Metric::XInfAP, &pool).unwrap();
+        assert_relative_eq!(
+            results[&'A'],
+            (1.5 + 2.0 / 3.0) / (1.5 + 1.0),
+            epsilon = 1e-4
+        );
+
+        assert_eq!(
+            compute_metric_with_pool(&true_rels, &pred_rels, Metric::AP { k: 0 }, &pool),
+            Err(ElinorError::InvalidArgument(
+                "The pool is only available for infap and xinfap, but got ap".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_bpref_without_non_relevant() {
+        let true_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 1,
+        }])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.4.into(),
+            },
+        ])
+        .unwrap();
+        let results = compute_metric(
+            &true_rels,
+            &pred_rels,
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(results[&'A'], 1.0);
+    }
+
+    #[test]
+    fn test_compute_metric_with_options() {
+        let true_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 1,
+        }])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0.4.into(),
+            },
+        ])
+        .unwrap();
+
+        let output = compute_metric_with_options(
+            &true_rels,
+            &pred_rels,
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+            MetricOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(output.auxiliary(), None);
+
+        let options = MetricOptions::new().with_auxiliary(true);
+        let output = compute_metric_with_options(
+            &true_rels,
+            &pred_rels,
+            Metric::Bpref {
+                k: 0,
+                trec_compat: true,
+            },
+            options,
+        )
+        .unwrap();
+        assert_eq!(
+            output.auxiliary().unwrap()[&'A'],
+            QueryAuxiliary {
+                n_retrieved: 2,
+                n_retrieved_at_k: 2,
+                n_judged_at_k: 1,
+            }
+        );
+        let output =
+            compute_metric_with_options(&true_rels, &pred_rels, Metric::Hits { k: 5 }, options)
+                .unwrap();
+        assert_eq!(output.auxiliary().unwrap()[&'A'].n_retrieved_at_k, 2);
+        assert_eq!(output.into_scores()[&'A'], 1.0);
+    }
+
+    #[test]
+    fn test_compute_metric_with_short_ranking_policy() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Y',
+                score: 0,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 0.5.into(),
+            },
+            Record {
+                query_id: 'A',
+                doc_id: 'Z',
+                score: 0.4.into(),
+            },
+        ])
+        .unwrap();
+
+        let pad = MetricOptions::new();
+        let normalize =
+            MetricOptions::new().with_short_ranking_policy(ShortRankingPolicy::Normalize);
+        let reject = MetricOptions::new().with_short_ranking_policy(ShortRankingPolicy::Reject);
+        for (metric, padded, normalized) in [
+            (Metric::Precision { k: 4 }, 1.0 / 4.0, 1.0 / 2.0),
+            (Metric::Judged { k: 4 }, 1.0 / 4.0, 1.0 / 2.0),
+            (
+                Metric::F1 { k: 4 },
+                2.0 * 0.25 / (0.25 + 1.0),
+                2.0 * 0.5 / (0.5 + 1.0),
+            ),
+            (Metric::Recall { k: 4 }, 1.0, 1.0),
+            (Metric::Precision { k: 2 }, 1.0 / 2.0, 1.0 / 2.0),
+        ] {
+            let output = compute_metric_with_options(&true_rels, &pred_rels, metric, pad).unwrap();
+            assert_relative_eq!(output.scores()[&'A'], padded);
+            let output =
+                compute_metric_with_options(&true_rels, &pred_rels, metric, normalize).unwrap();
+            assert_relative_eq!(output.scores()[&'A'], normalized);
+        }
+
+        assert!(compute_metric_with_options(
+            &true_rels,
+            &pred_rels,
+            Metric::Precision { k: 2 },
+            reject
+        )
+        .is_ok());
+        assert_eq!(
+            compute_metric_with_options(&true_rels, &pred_rels, Metric::NDCG { k: 3 }, reject),
+            Err(ElinorError::InvalidArgument(
+                "Query A retrieves 2 documents, fewer than k=3 of ndcg@3".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_metrics_with_short_ranking_policy() {
+        let true_rels = TrueRelStore::from_records([
+            Record {
+                query_id: 'A',
+                doc_id: 'X',
+                score: 1,
+            },
+            Record {
+                query_id: 'B',
+                doc_id: 'X',
+                score: 1,
+            },
+        ])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records(
+            [
+                ('A', 'X'),
+                ('A', 'Y'),
+                ('B', 'Y'),
+                ('B', 'X'),
+                ('B', 'Z'),
+                ('B', 'W'),
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (query_id, doc_id))| Record {
+                query_id,
+                doc_id,
+                score: (10.0 - i as f64).into(),
+            }),
+        )
+        .unwrap();
+        let metrics = [
+            Metric::Precision { k: 4 },
+            Metric::F1 { k: 4 },
+            Metric::Judged { k: 4 },
+            Metric::Recall { k: 4 },
+            Metric::NDCG { k: 4 },
+        ];
+        for policy in [ShortRankingPolicy::Pad, ShortRankingPolicy::Normalize] {
+            let options = MetricOptions::new().with_short_ranking_policy(policy);
+            let results =
+                compute_metrics_with_options(&true_rels, &pred_rels, &metrics, options).unwrap();
+            for metric in metrics {
+                let expected =
+                    compute_metric_with_options(&true_rels, &pred_rels, metric, options).unwrap();
+                compare_hashmaps(&results[&metric], expected.scores());
+            }
+            #[cfg(feature = "parallel")]
+            assert_eq!(
+                compute_metrics_parallel_with_options(&true_rels, &pred_rels, &metrics, options)
+                    .unwrap(),
+                results
+            );
+        }
+
+        let options = MetricOptions::new().with_short_ranking_policy(ShortRankingPolicy::Reject);
+        assert_eq!(
+            compute_metrics_with_options(&true_rels, &pred_rels, &metrics, options),
+            Err(ElinorError::InvalidArgument(
+                "Query A retrieves 2 documents, fewer than k=4 of precision@4".to_string()
+            ))
+        );
+        let options = MetricOptions::new().with_auxiliary(true);
+        assert_eq!(
+            compute_metrics_with_options(&true_rels, &pred_rels, &metrics, options),
+            Err(ElinorError::InvalidArgument(
+                "The auxiliary values are only available in compute_metric_with_options."
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compute_metric_with_bounds_check_without_relevant_documents() {
+        let true_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 0,
+        }])
+        .unwrap();
+        let pred_rels = PredRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 0.5.into(),
+        }])
+        .unwrap();
+        let options = MetricOptions::new().with_bounds_check(true);
+        for metric in [
+            Metric::F1 { k: 0 },
+            Metric::AP { k: 0 },
+            Metric::NDCG { k: 0 },
+            Metric::QMeasure {
+                k: 0,
+                beta: OrderedFloat(1.0),
+            },
+        ] {
+            let output =
+                compute_metric_with_options(&true_rels, &pred_rels, metric, options).unwrap();
+            // F1 is NaN (undefined), which is not regarded as out of bounds.
+            assert_eq!(
+                output.scores()[&'A'].is_nan(),
+                matches!(metric, Metric::F1 { .. }),
+                "{metric}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::in_bounds(Metric::AP { k: 0 }, 1.0, true)]
+    #[case::rounding_error(Metric::AP { k: 0 }, 1.0 + 1e-12, true)]
+    #[case::above(Metric::AP { k: 0 }, 1.5, false)]
+    #[case::negative(Metric::AP { k: 0 }, -0.5, false)]
+    #[case::nan(Metric::F1 { k: 0 }, f64::NAN, true)]
+    #[case::unbounded(Metric::DCG { k: 0 }, 100.0, true)]
+    #[case::negative_dcg(Metric::DCG { k: 0 }, -1.0, false)]
+    fn test_is_within_bounds(#[case] metric: Metric, #[case] score: f64, #[case] expected: bool) {
+        assert_eq!(is_within_bounds(metric, score), expected);
+    }
+
+    #[test]
+    fn test_validate_relevance_kind() {
+        let binary_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 1,
+        }])
+        .unwrap();
+        let graded_rels = TrueRelStore::from_records([Record {
+            query_id: 'A',
+            doc_id: 'X',
+            score: 2,
+        }])
+        .unwrap();
+        assert_eq!(relevance_kind_of(&binary_rels), RelevanceKind::Binary);
+        assert_eq!(relevance_kind_of(&graded_rels), RelevanceKind::Graded);
+        assert!(validate_relevance_kind(&binary_rels, Metric::Precision { k: 1 }).is_ok());
+        assert!(validate_relevance_kind(&graded_rels, Metric::NDCG { k: 1 }).is_ok());
+        assert_eq!(
+            validate_relevance_kind(&binary_rels, Metric::NDCG { k: 1 }),
+            Err(ElinorError::InvalidArgument(
+                "ndcg@1 assumes graded relevance, but true_rels has only binary scores".to_string()
+            ))
+        );
+        assert!(validate_relevance_kind(&graded_rels, Metric::AP { k: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_gain_mapping_from_str() {
+        let gains: GainMapping = "0:0, 1:1, 2:3".parse().unwrap();
+        assert_eq!(gains, GainMapping::new([(0, 0.0), (1, 1.0), (2, 3.0)]));
+        assert!("0:0,1".parse::<GainMapping>().is_err());
+        assert!("0:0,0:1".parse::<GainMapping>().is_err());
+    }
+
+    #[test]
+    fn test_compute_metric_at_rel_levels() {
+        let true_rels = TrueRelStore::from_records(
+            [('A', 'X', 1), ('A', 'Y', 0), ('A', 'Z', 2)].map(|(query_id, doc_id, score)| Record {
+                query_id,
+                doc_id,
+                score,
+            }),
+        )
+        .unwrap();
+        let pred_rels =
+            PredRelStore::from_records([('A', 'X', 0.5), ('A', 'Y', 0.4), ('A', 'Z', 0.3)].map(
+                |(query_id, doc_id, score)| Record {
+                    query_id,
+                    doc_id,
+                    score: score.into(),
+                },
+            ))
+            .unwrap();
+        let metric = Metric::AP { k: 0 };
+        let results =
+            compute_metric_at_rel_levels(&true_rels, &pred_rels, metric, &[2, 1]).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[&1],
+            compute_metric(&true_rels, &pred_rels, metric).unwrap()
+        );
+        assert_relative_eq!(results[&2][&'A'], 1.0 / 3.0);
+
+        assert_eq!(
+            compute_metric_at_rel_levels(&true_rels, &pred_rels, metric, &[0, 1]).err(),
+            Some(ElinorError::InvalidArgument(
+                "The relevance levels must be non-empty and positive.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_metric_display_q_measure() {
+        let metric = Metric::QMeasure {
+            k: 10,
+            beta: OrderedFloat(1.0),
+        };
+        assert_eq!(metric.to_string(), "q_measure@10");
+        let metric = Metric::QMeasure {
+            k: 10,
+            beta: OrderedFloat(0.5),
+        };
+        assert_eq!(metric.to_string(), "q_measure_beta0.5@10");
+        assert_eq!(metric.to_string().parse::<Metric>(), Ok(metric));
+        assert!("ap_beta0.5".parse::<Metric>().is_err());
     }
 
     #[rstest]
@@ -571,7 +3520,13 @@ mod tests {
     #[case::reciprocal_rank_k0("rr@0", Metric::RR { k: 0 })]
     #[case::reciprocal_rank_k1("rr@1", Metric::RR { k: 1 })]
     #[case::reciprocal_rank_k100("rr@100", Metric::RR { k: 100 })]
-    #[case::bpref("bpref", Metric::Bpref)]
+    #[case::bpref("bpref", Metric::Bpref { k: 0, trec_compat: true })]
+    #[case::bpref_k10("bpref@10", Metric::Bpref { k: 10, trec_compat: true })]
+    #[case::bpref_original("bpref_original", Metric::Bpref { k: 0, trec_compat: false })]
+    #[case::infap("infap", Metric::InfAP)]
+    #[case::xinfap("xinfap", Metric::XInfAP)]
+    #[case::judged("judged", Metric::Judged { k: 0 })]
+    #[case::judged_k10("judged@10", Metric::Judged { k: 10 })]
     #[case::dcg("dcg", Metric::DCG { k: 0 })]
     #[case::dcg_k0("dcg@0", Metric::DCG { k: 0 })]
     #[case::dcg_k1("dcg@1", Metric::DCG { k: 1 })]
@@ -588,6 +3543,10 @@ mod tests {
     #[case::ndcg_burges_k0("ndcg_burges@0", Metric::NDCGBurges { k: 0 })]
     #[case::ndcg_burges_k1("ndcg_burges@1", Metric::NDCGBurges { k: 1 })]
     #[case::ndcg_burges_k100("ndcg_burges@100", Metric::NDCGBurges { k: 100 })]
+    #[case::q_measure("q_measure", Metric::QMeasure { k: 0, beta: OrderedFloat(1.0) })]
+    #[case::q_measure_k1("q_measure@1", Metric::QMeasure { k: 1, beta: OrderedFloat(1.0) })]
+    #[case::q_measure_beta("q_measure_beta0.5", Metric::QMeasure { k: 0, beta: OrderedFloat(0.5) })]
+    #[case::q_measure_beta_k100("q_measure_beta10@100", Metric::QMeasure { k: 100, beta: OrderedFloat(10.0) })]
     fn test_metric_from_str(#[case] input: &str, #[case] expected: Metric) {
         let metric = Metric::from_str(input).unwrap();
         assert_eq!(metric, expected);
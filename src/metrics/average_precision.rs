@@ -1,6 +1,5 @@
 use std::collections::BTreeMap;
 
-use crate::metrics::precision::compute_precision;
 use crate::PredScore;
 use crate::Relevance;
 use crate::TrueScore;
@@ -24,10 +23,13 @@ where
         return 0.0;
     }
     let mut sum = 0.0;
+    let mut n_hits: u64 = 0;
     for (i, pred) in sorted_preds.iter().enumerate().take(k) {
         if let Some(&rel) = trues.get(&pred.doc_id) {
             if rel >= rel_lvl {
-                sum += compute_precision(trues, sorted_preds, i + 1, rel_lvl);
+                // Precision at rank i + 1, accumulated in one pass over the ranking.
+                n_hits += 1;
+                sum += n_hits as f64 / (i + 1) as f64;
             }
         }
     }
@@ -4,25 +4,41 @@ use crate::PredScore;
 use crate::Relevance;
 use crate::TrueScore;
 
-/// Computes the Bpref.
+/// Computes the Bpref at k.
+///
+/// If `trec_compat` is true, the number of irrelevant documents ranked above each relevant document
+/// is divided by $`\min(R, N)`$ as in trec_eval. Otherwise, it is divided by $`R`$ as in the original definition.
 pub fn compute_bpref<K>(
     trues: &BTreeMap<K, TrueScore>,
     sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+    trec_compat: bool,
     rel_lvl: TrueScore,
 ) -> f64
 where
     K: Eq + Ord,
 {
+    let k = if k == 0 { sorted_preds.len() } else { k };
     let n_rels = trues.values().filter(|&&rel| rel >= rel_lvl).count() as f64;
     let n_non_rels = trues.len() as f64 - n_rels;
+    let denominator = if trec_compat {
+        n_non_rels.min(n_rels)
+    } else {
+        n_rels
+    };
 
     let mut bpref = 0.0;
     let mut n_non_rels_so_far = 0.0_f64;
 
-    for pred in sorted_preds {
+    for pred in sorted_preds.iter().take(k) {
         if let Some(&rel) = trues.get(&pred.doc_id) {
             if rel >= rel_lvl {
-                bpref += 1.0 - n_non_rels_so_far.min(n_rels) / n_non_rels.min(n_rels);
+                // Following trec_eval, which avoids 0/0 when no non-relevant documents are judged.
+                bpref += if n_non_rels_so_far == 0.0 {
+                    1.0
+                } else {
+                    1.0 - n_non_rels_so_far.min(n_rels) / denominator
+                };
             } else {
                 n_non_rels_so_far += 1.0;
             }
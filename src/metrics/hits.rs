@@ -15,7 +15,7 @@ where
     K: Eq + Ord,
 {
     let k = if k == 0 { sorted_preds.len() } else { k };
-    let mut hits = 0;
+    let mut hits: u64 = 0;
     for pred in sorted_preds.iter().take(k) {
         if let Some(&rel) = trues.get(&pred.doc_id) {
             if rel >= rel_lvl {
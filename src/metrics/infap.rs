@@ -0,0 +1,218 @@
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::errors::ElinorError;
+use crate::PredScore;
+use crate::Relevance;
+use crate::TrueScore;
+
+/// Small constant to avoid zero divisions in the inferred precision, the same as trec_eval.
+const INFAP_EPSILON: f64 = 0.00001;
+
+/// Metadata of judgment pools built by (stratified) sampling,
+/// used with [`Metric::InfAP`](crate::Metric::InfAP) and [`Metric::XInfAP`](crate::Metric::XInfAP).
+///
+/// A pool holds the documents assessed as candidates for each query, with their strata.
+/// Pooled documents not in the true relevance scores are regarded as unsampled (i.e., unjudged),
+/// and the sampling rate of each stratum is estimated as the ratio of judged documents.
+/// Judged documents not in the pool are regarded as pooled in stratum 0.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::metrics::SampledPool;
+///
+/// let mut pool = SampledPool::new();
+/// pool.add_doc("q_1", "d_1", 0)?;
+/// pool.add_doc("q_1", "d_2", 1)?;
+/// assert_eq!(pool.get_stratum("q_1", "d_2"), Some(1));
+/// assert_eq!(pool.get_stratum("q_1", "d_3"), None);
+/// assert!(pool.add_doc("q_1", "d_1", 1).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampledPool<K> {
+    map: BTreeMap<K, BTreeMap<K, usize>>,
+}
+
+impl<K> Default for SampledPool<K> {
+    fn default() -> Self {
+        Self {
+            map: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K> SampledPool<K>
+where
+    K: Eq + Ord + Clone + Display,
+{
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pooled document with its stratum.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::DuplicateEntry`] if the query-document pair already exists.
+    pub fn add_doc(&mut self, query_id: K, doc_id: K, stratum: usize) -> Result<(), ElinorError> {
+        let docs = self.map.entry(query_id.clone()).or_default();
+        if docs.contains_key(&doc_id) {
+            return Err(ElinorError::DuplicateEntry(format!(
+                "Query-document pair ({query_id}, {doc_id}) already exists in the pool"
+            )));
+        }
+        docs.insert(doc_id, stratum);
+        Ok(())
+    }
+
+    /// Returns the stratum of the pooled document, or `None` if not pooled.
+    pub fn get_stratum<Q>(&self, query_id: &Q, doc_id: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Ord + ?Sized,
+    {
+        self.map.get(query_id)?.get(doc_id).copied()
+    }
+
+    /// Returns the mapping from pooled documents to strata for the query.
+    pub fn get_map<Q>(&self, query_id: &Q) -> Option<&BTreeMap<K, usize>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Ord + ?Sized,
+    {
+        self.map.get(query_id)
+    }
+
+    /// Returns the number of pooled documents over all queries.
+    pub fn n_docs(&self) -> usize {
+        self.map.values().map(|docs| docs.len()).sum()
+    }
+}
+
+/// Returns the stratum of the document, or `None` if not pooled.
+fn stratum_of<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    pool: Option<&BTreeMap<K, usize>>,
+    doc_id: &K,
+) -> Option<usize>
+where
+    K: Eq + Ord,
+{
+    pool.and_then(|pool| pool.get(doc_id).copied())
+        .or_else(|| trues.contains_key(doc_id).then_some(0))
+}
+
+/// Computes the inferred AP as in trec_eval.
+pub fn compute_infap<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    pool: Option<&BTreeMap<K, usize>>,
+    rel_lvl: TrueScore,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    let n_rels = trues.values().filter(|&&rel| rel >= rel_lvl).count();
+    if n_rels == 0 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    let mut n_pooled_above = 0.0;
+    let mut n_rels_above = 0.0;
+    let mut n_non_rels_above = 0.0;
+    for (i, pred) in sorted_preds.iter().enumerate() {
+        if let Some(&rel) = trues.get(&pred.doc_id) {
+            if rel >= rel_lvl {
+                sum += if i == 0 {
+                    1.0
+                } else {
+                    let rank = i as f64 + 1.0;
+                    // Expected precision above the rank, estimated from the judged documents.
+                    let rel_rate = (n_rels_above + INFAP_EPSILON)
+                        / 2.0f64.mul_add(INFAP_EPSILON, n_rels_above + n_non_rels_above);
+                    let pooled_rate = (rank - 1.0) / rank * (n_pooled_above / (rank - 1.0));
+                    pooled_rate.mul_add(rel_rate, 1.0 / rank)
+                };
+                n_rels_above += 1.0;
+            } else {
+                n_non_rels_above += 1.0;
+            }
+        }
+        if stratum_of(trues, pool, &pred.doc_id).is_some() {
+            n_pooled_above += 1.0;
+        }
+    }
+    sum / n_rels as f64
+}
+
+/// Computes the extended inferred AP for stratified sampling.
+pub fn compute_xinfap<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    pool: Option<&BTreeMap<K, usize>>,
+    rel_lvl: TrueScore,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    // Sampling rates of strata, estimated by the ratios of judged documents.
+    let mut n_pooled = BTreeMap::<usize, f64>::new();
+    let mut n_judged = BTreeMap::<usize, f64>::new();
+    for doc_id in trues.keys() {
+        let stratum = stratum_of(trues, pool, doc_id).unwrap();
+        *n_judged.entry(stratum).or_default() += 1.0;
+    }
+    for (doc_id, &stratum) in pool.into_iter().flatten() {
+        if !trues.contains_key(doc_id) {
+            *n_pooled.entry(stratum).or_default() += 1.0;
+        }
+    }
+    let rate = |stratum: usize| {
+        let judged = n_judged[&stratum];
+        judged / (judged + n_pooled.get(&stratum).copied().unwrap_or(0.0))
+    };
+
+    let n_rels = trues
+        .iter()
+        .filter(|(_, &rel)| rel >= rel_lvl)
+        .map(|(doc_id, _)| 1.0 / rate(stratum_of(trues, pool, doc_id).unwrap()))
+        .sum::<f64>();
+    if n_rels == 0.0 {
+        return 0.0;
+    }
+
+    // Numbers of pooled, relevant, and non-relevant documents above the rank in each stratum.
+    let mut above = BTreeMap::<usize, (f64, f64, f64)>::new();
+    let mut sum = 0.0;
+    for (i, pred) in sorted_preds.iter().enumerate() {
+        let Some(stratum) = stratum_of(trues, pool, &pred.doc_id) else {
+            continue;
+        };
+        let rel = trues.get(&pred.doc_id).copied();
+        if matches!(rel, Some(rel) if rel >= rel_lvl) {
+            let rank = i as f64 + 1.0;
+            let n_rels_above = above
+                .values()
+                .map(|&(n_pooled, n_rels, n_non_rels)| {
+                    n_pooled * (n_rels + INFAP_EPSILON)
+                        / 2.0f64.mul_add(INFAP_EPSILON, n_rels + n_non_rels)
+                })
+                .sum::<f64>();
+            sum += (1.0 + n_rels_above) / rank / rate(stratum);
+        }
+        let entry = above.entry(stratum).or_default();
+        entry.0 += 1.0;
+        match rel {
+            Some(rel) if rel >= rel_lvl => entry.1 += 1.0,
+            Some(_) => entry.2 += 1.0,
+            None => {}
+        }
+    }
+    sum / n_rels
+}
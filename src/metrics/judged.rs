@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+
+use crate::PredScore;
+use crate::Relevance;
+use crate::TrueScore;
+
+/// Computes the number of unjudged documents at k.
+pub fn compute_n_unjudged<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+) -> usize
+where
+    K: Eq + Ord,
+{
+    let k = if k == 0 { sorted_preds.len() } else { k };
+    sorted_preds
+        .iter()
+        .take(k)
+        .filter(|pred| !trues.contains_key(&pred.doc_id))
+        .count()
+}
+
+/// Computes the fraction of judged documents at k.
+pub fn compute_judged<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    let k = if k == 0 { sorted_preds.len() } else { k };
+    if k == 0 {
+        0.0
+    } else {
+        let n_retrieved = sorted_preds.len().min(k);
+        let n_unjudged = compute_n_unjudged(trues, sorted_preds, k);
+        (n_retrieved - n_unjudged) as f64 / k as f64
+    }
+}
@@ -1,19 +1,437 @@
 use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
 
+use ordered_float::OrderedFloat;
+
+use crate::errors::ElinorError;
 use crate::PredScore;
 use crate::Relevance;
 use crate::TrueScore;
 
-#[derive(Clone, Copy, Debug)]
+/// Weighting of true relevance scores (grades) into gains for DCG-family metrics.
+///
+/// # Conversion from/into string representation
+///
+/// The built-in weightings can be converted from/into their names,
+/// whereas [`DcgWeighting::Custom`] is shown as its gain mapping:
+///
+/// ```rust
+/// use elinor::metrics::DcgWeighting;
+///
+/// assert_eq!("jarvelin".parse(), Ok(DcgWeighting::Jarvelin));
+/// assert_eq!("burges".parse(), Ok(DcgWeighting::Burges));
+/// assert_eq!(DcgWeighting::Burges.to_string(), "burges");
+/// ```
+///
+/// # Gain functions
+///
+/// Arbitrary gains can be given by a closure with [`DcgWeighting::Function`]:
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use elinor::metrics::DcgWeighting;
+///
+/// let weighting = DcgWeighting::Function(Arc::new(|grade| if grade >= 2 { 10.0 } else { grade as f64 }));
+/// assert_eq!(weighting.gain(1), 1.0);
+/// assert_eq!(weighting.gain(3), 10.0);
+/// ```
+#[derive(Clone)]
 pub enum DcgWeighting {
+    /// Grades as gains, used in [`Metric::DCG`](crate::Metric::DCG).
     Jarvelin,
+
+    /// Exponential gains of $`2^{\text{rel}} - 1`$, used in [`Metric::DCGBurges`](crate::Metric::DCGBurges).
     Burges,
+
+    /// Gains given by the mapping, where unmapped grades have zero gains.
+    Custom(GainMapping),
+
+    /// Gains given by the function of grades, shown as `function` in the string representation.
+    Function(Arc<dyn Fn(TrueScore) -> f64 + Send + Sync>),
+}
+
+impl std::fmt::Debug for DcgWeighting {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Jarvelin => write!(f, "Jarvelin"),
+            Self::Burges => write!(f, "Burges"),
+            Self::Custom(gains) => f.debug_tuple("Custom").field(gains).finish(),
+            Self::Function(_) => write!(f, "Function(..)"),
+        }
+    }
+}
+
+impl PartialEq for DcgWeighting {
+    /// Compares the weightings, where functions are equal only if they share the same allocation.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Jarvelin, Self::Jarvelin) | (Self::Burges, Self::Burges) => true,
+            (Self::Custom(a), Self::Custom(b)) => a == b,
+            (Self::Function(a), Self::Function(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl DcgWeighting {
+    /// Returns the gain for a given grade.
+    pub fn gain(&self, grade: TrueScore) -> f64 {
+        match self {
+            Self::Jarvelin => grade as f64,
+            Self::Burges => 2.0_f64.powi(grade as i32) - 1.0,
+            Self::Custom(gains) => gains.gain(grade).unwrap_or(0.0),
+            Self::Function(gain) => gain(grade),
+        }
+    }
+}
+
+impl FromStr for DcgWeighting {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jarvelin" => Ok(Self::Jarvelin),
+            "burges" => Ok(Self::Burges),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported DCG weighting: {s}"
+            ))),
+        }
+    }
+}
+
+impl Display for DcgWeighting {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Jarvelin => write!(f, "jarvelin"),
+            Self::Burges => write!(f, "burges"),
+            Self::Custom(gains) => write!(f, "{gains}"),
+            Self::Function(_) => write!(f, "function"),
+        }
+    }
+}
+
+/// Mapping from true relevance scores (grades) to gains for DCG-family metrics.
+///
+/// Several shared tasks define their own gains instead of the formulas
+/// of [`Metric::DCG`](crate::Metric::DCG) and [`Metric::DCGBurges`](crate::Metric::DCGBurges).
+/// The mapping can be used with [`DcgWeighting::Custom`] in [`DcgOptions`].
+///
+/// # Conversion from/into string representation
+///
+/// The string representation is a comma-separated list of `<grade>:<gain>` pairs,
+/// as follows:
+///
+/// ```rust
+/// use elinor::metrics::GainMapping;
+///
+/// let gains: GainMapping = "0:0,1:1,2:3,3:7".parse().unwrap();
+/// assert_eq!(gains.gain(2), Some(3.0));
+/// assert_eq!(gains.gain(4), None);
+/// assert_eq!(gains.to_string(), "0:0,1:1,2:3,3:7");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GainMapping {
+    map: BTreeMap<TrueScore, f64>,
+}
+
+impl GainMapping {
+    /// Creates a new mapping from grades to gains.
+    pub fn new<I>(gains: I) -> Self
+    where
+        I: IntoIterator<Item = (TrueScore, f64)>,
+    {
+        Self {
+            map: gains.into_iter().collect(),
+        }
+    }
+
+    /// Returns the gain for a given grade, or `None` if the grade is not mapped.
+    pub fn gain(&self, grade: TrueScore) -> Option<f64> {
+        self.map.get(&grade).copied()
+    }
+
+    /// Returns the reference to the mapping from grades to gains.
+    pub const fn map(&self) -> &BTreeMap<TrueScore, f64> {
+        &self.map
+    }
+}
+
+impl Display for GainMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let pairs = self
+            .map
+            .iter()
+            .map(|(grade, gain)| format!("{grade}:{gain}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl FromStr for GainMapping {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = BTreeMap::new();
+        for pair in s.split(',') {
+            let (grade, gain) = pair.split_once(':').ok_or_else(|| {
+                ElinorError::InvalidFormat(format!(
+                    "Each gain must be in the form of <grade>:<gain>, but got {pair}"
+                ))
+            })?;
+            let grade = grade
+                .trim()
+                .parse::<TrueScore>()
+                .map_err(|_| ElinorError::InvalidFormat(format!("Invalid grade: {grade}")))?;
+            let gain = gain
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| ElinorError::InvalidFormat(format!("Invalid gain: {gain}")))?;
+            if map.insert(grade, gain).is_some() {
+                return Err(ElinorError::DuplicateEntry(format!(
+                    "Each grade must be unique, but got {grade} twice"
+                )));
+            }
+        }
+        Ok(Self { map })
+    }
+}
+
+/// Discount of gains by rank positions in DCG-family metrics.
+///
+/// # Conversion from/into string representation
+///
+/// ```rust
+/// use elinor::metrics::DcgDiscount;
+///
+/// assert_eq!("standard".parse(), Ok(DcgDiscount::Standard));
+/// assert_eq!("original".parse(), Ok(DcgDiscount::Original));
+/// assert_eq!(DcgDiscount::Original.to_string(), "original");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DcgDiscount {
+    /// Discount of $`\log_b(i + 1)`$ at rank $`i`$ with the log base $`b = 2`$ by default,
+    /// used in trec_eval, pytrec_eval, and ir-measures.
+    #[default]
+    Standard,
+
+    /// Discount of $`\max(1, \log_b i)`$ at rank $`i`$ with the log base $`b = 2`$ by default,
+    /// the original formulation of [Järvelin et al., TOIS 2002](https://dl.acm.org/doi/10.1145/582415.582418),
+    /// where the gains at ranks up to $`b`$ are not discounted.
+    Original,
+}
+
+impl DcgDiscount {
+    /// Returns the divisor of the gain at the 0-origin position with the log base.
+    fn divisor(&self, position: usize, log_base: f64) -> f64 {
+        match self {
+            Self::Standard => (position as f64 + 2.0).log(log_base),
+            Self::Original => (position as f64 + 1.0).log(log_base).max(1.0),
+        }
+    }
+}
+
+impl FromStr for DcgDiscount {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Self::Standard),
+            "original" => Ok(Self::Original),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported DCG discount: {s}"
+            ))),
+        }
+    }
+}
+
+impl Display for DcgDiscount {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Standard => write!(f, "standard"),
+            Self::Original => write!(f, "original"),
+        }
+    }
+}
+
+/// Cutoff of the ideal ranking in the IDCG of NDCG-family metrics at `k`.
+///
+/// # Conversion from/into string representation
+///
+/// ```rust
+/// use elinor::metrics::IdealCutoff;
+///
+/// assert_eq!("k".parse(), Ok(IdealCutoff::AtK));
+/// assert_eq!("all".parse(), Ok(IdealCutoff::AllRelevant));
+/// assert_eq!(IdealCutoff::AllRelevant.to_string(), "all");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IdealCutoff {
+    /// The ideal ranking is cut at `k`, as in trec_eval, pytrec_eval, and ir-measures.
+    #[default]
+    AtK,
+
+    /// The ideal ranking includes all the judged documents regardless of `k`,
+    /// so that the score is lower than one if there are more than `k` relevant documents.
+    AllRelevant,
+}
+
+impl FromStr for IdealCutoff {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "k" => Ok(Self::AtK),
+            "all" => Ok(Self::AllRelevant),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported ideal cutoff: {s}"
+            ))),
+        }
+    }
+}
+
+impl Display for IdealCutoff {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::AtK => write!(f, "k"),
+            Self::AllRelevant => write!(f, "all"),
+        }
+    }
+}
+
+/// Conventions of DCG-family metrics, on which toolkits disagree.
+///
+/// The default is the convention of trec_eval, pytrec_eval, and ir-measures.
+/// The conventions can be used with [`DcgOptions`] to match the scores of other toolkits exactly.
+///
+/// The log base of the discount does not change NDCG with [`DcgDiscount::Standard`],
+/// since it scales DCG and IDCG by the same factor,
+/// whereas it changes the undiscounted top ranks with [`DcgDiscount::Original`]
+/// (e.g., $`b = 10`$ models an impatient user in Järvelin et al.).
+///
+/// # Default parameters
+///
+/// * `discount`: [`DcgDiscount::Standard`]
+/// * `ideal_cutoff`: [`IdealCutoff::AtK`]
+/// * `log_base`: `2.0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DcgConvention {
+    discount: DcgDiscount,
+    ideal_cutoff: IdealCutoff,
+    log_base: OrderedFloat<f64>,
+}
+
+impl Default for DcgConvention {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DcgConvention {
+    /// Creates a new convention.
+    pub const fn new() -> Self {
+        Self {
+            discount: DcgDiscount::Standard,
+            ideal_cutoff: IdealCutoff::AtK,
+            log_base: OrderedFloat(2.0),
+        }
+    }
+
+    /// Sets the discount of gains.
+    pub const fn with_discount(mut self, discount: DcgDiscount) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    /// Sets the cutoff of the ideal ranking.
+    pub const fn with_ideal_cutoff(mut self, ideal_cutoff: IdealCutoff) -> Self {
+        self.ideal_cutoff = ideal_cutoff;
+        self
+    }
+
+    /// Sets the log base of the discount, which must be finite and greater than 1.
+    pub const fn with_log_base(mut self, log_base: f64) -> Self {
+        self.log_base = OrderedFloat(log_base);
+        self
+    }
+
+    /// Returns the discount of gains.
+    pub const fn discount(&self) -> DcgDiscount {
+        self.discount
+    }
+
+    /// Returns the cutoff of the ideal ranking.
+    pub const fn ideal_cutoff(&self) -> IdealCutoff {
+        self.ideal_cutoff
+    }
+
+    /// Returns the log base of the discount.
+    pub const fn log_base(&self) -> f64 {
+        self.log_base.0
+    }
+
+    /// Validates the convention.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the log base is not finite or not greater than 1.
+    pub fn validate(&self) -> Result<(), ElinorError> {
+        let log_base = self.log_base.0;
+        if !(log_base.is_finite() && log_base > 1.0) {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The log base must be finite and greater than 1, but got {log_base}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Options of DCG-family metrics for [`evaluate_with_dcg_options`](crate::evaluate_with_dcg_options)
+/// and [`compute_metric_with_dcg_options`](crate::metrics::compute_metric_with_dcg_options).
+///
+/// # Default parameters
+///
+/// * `weighting`: `None`, i.e., the weighting of the metric
+///   ([`DcgWeighting::Jarvelin`] for [`Metric::DCG`](crate::Metric::DCG) and [`Metric::NDCG`](crate::Metric::NDCG),
+///   and [`DcgWeighting::Burges`] for [`Metric::DCGBurges`](crate::Metric::DCGBurges) and [`Metric::NDCGBurges`](crate::Metric::NDCGBurges))
+/// * `convention`: [`DcgConvention::new`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DcgOptions {
+    weighting: Option<DcgWeighting>,
+    convention: DcgConvention,
 }
 
-fn weighted_score(rel: TrueScore, weighting: DcgWeighting) -> f64 {
-    match weighting {
-        DcgWeighting::Jarvelin => rel as f64,
-        DcgWeighting::Burges => 2.0_f64.powi(rel as i32) - 1.0,
+impl DcgOptions {
+    /// Creates new options.
+    pub const fn new() -> Self {
+        Self {
+            weighting: None,
+            convention: DcgConvention::new(),
+        }
+    }
+
+    /// Sets the weighting replacing that of the metric.
+    pub fn with_weighting(mut self, weighting: DcgWeighting) -> Self {
+        self.weighting = Some(weighting);
+        self
+    }
+
+    /// Sets the convention.
+    pub const fn with_convention(mut self, convention: DcgConvention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    /// Returns the weighting replacing that of the metric, if set.
+    pub const fn weighting(&self) -> Option<&DcgWeighting> {
+        self.weighting.as_ref()
+    }
+
+    /// Returns the convention.
+    pub const fn convention(&self) -> DcgConvention {
+        self.convention
     }
 }
 
@@ -22,7 +440,21 @@ pub fn compute_dcg<K>(
     trues: &BTreeMap<K, TrueScore>,
     sorted_preds: &[Relevance<K, PredScore>],
     k: usize,
-    weighting: DcgWeighting,
+    weighting: &DcgWeighting,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    compute_dcg_with_convention(trues, sorted_preds, k, weighting, DcgConvention::new())
+}
+
+/// Computes the DCG at k in the convention.
+pub fn compute_dcg_with_convention<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+    weighting: &DcgWeighting,
+    convention: DcgConvention,
 ) -> f64
 where
     K: Eq + Ord,
@@ -31,32 +463,72 @@ where
     let mut dcg = 0.0;
     for (i, pred) in sorted_preds.iter().take(k).enumerate() {
         if let Some(&rel) = trues.get(&pred.doc_id) {
-            dcg += weighted_score(rel, weighting) / (i as f64 + 2.0).log2();
+            dcg += weighting.gain(rel) / convention.discount.divisor(i, convention.log_base());
         }
     }
     dcg
 }
 
+/// Computes the ideal DCG at k, sorting the documents by their gains.
+fn compute_idcg<K>(
+    sorted_trues: &[Relevance<K, TrueScore>],
+    k: usize,
+    weighting: &DcgWeighting,
+    convention: DcgConvention,
+) -> f64 {
+    let mut gains = sorted_trues
+        .iter()
+        .map(|r| weighting.gain(r.score))
+        .collect::<Vec<_>>();
+    gains.sort_by(|a, b| b.total_cmp(a));
+    let k = if k == 0 || convention.ideal_cutoff == IdealCutoff::AllRelevant {
+        gains.len()
+    } else {
+        k
+    };
+    gains
+        .iter()
+        .take(k)
+        .enumerate()
+        .map(|(i, gain)| gain / convention.discount.divisor(i, convention.log_base()))
+        .sum()
+}
+
 /// Computes the NDCG at k.
 pub fn compute_ndcg<K>(
     trues: &BTreeMap<K, TrueScore>,
     sorted_trues: &[Relevance<K, TrueScore>],
     sorted_preds: &[Relevance<K, PredScore>],
     k: usize,
-    weighting: DcgWeighting,
+    weighting: &DcgWeighting,
 ) -> f64
 where
-    K: Eq + Ord + Clone,
+    K: Eq + Ord,
 {
-    let sorted_trues = sorted_trues
-        .iter()
-        .map(|r| Relevance {
-            doc_id: r.doc_id.clone(),
-            score: PredScore::from(r.score),
-        })
-        .collect::<Vec<_>>();
-    let dcg = compute_dcg(trues, sorted_preds, k, weighting);
-    let idcg = compute_dcg(trues, &sorted_trues, k, weighting);
+    compute_ndcg_with_convention(
+        trues,
+        sorted_trues,
+        sorted_preds,
+        k,
+        weighting,
+        DcgConvention::new(),
+    )
+}
+
+/// Computes the NDCG at k in the convention.
+pub fn compute_ndcg_with_convention<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_trues: &[Relevance<K, TrueScore>],
+    sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+    weighting: &DcgWeighting,
+    convention: DcgConvention,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    let dcg = compute_dcg_with_convention(trues, sorted_preds, k, weighting, convention);
+    let idcg = compute_idcg(sorted_trues, k, weighting, convention);
     if idcg == 0.0 {
         1.0
     } else {
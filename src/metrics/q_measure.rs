@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+use crate::PredScore;
+use crate::Relevance;
+use crate::TrueScore;
+
+/// Computes the Q-measure at k.
+///
+/// The true relevance scores are used as gains, and documents with scores of at least `rel_lvl` are relevant.
+pub fn compute_q_measure<K>(
+    trues: &BTreeMap<K, TrueScore>,
+    sorted_trues: &[Relevance<K, TrueScore>],
+    sorted_preds: &[Relevance<K, PredScore>],
+    k: usize,
+    beta: f64,
+    rel_lvl: TrueScore,
+) -> f64
+where
+    K: Eq + Ord,
+{
+    let n_rels = trues.values().filter(|&&rel| rel >= rel_lvl).count();
+    // The denominator is min(k, R) even if fewer than k documents are retrieved.
+    let denom = if k == 0 { n_rels } else { n_rels.min(k) };
+    if denom == 0 {
+        return 0.0;
+    }
+    let k = if k == 0 { sorted_preds.len() } else { k };
+    let mut sum = 0.0;
+    let mut n_hits: u64 = 0;
+    let mut gain = 0.0;
+    let mut ideal_gain = 0.0;
+    for (i, pred) in sorted_preds.iter().enumerate().take(k) {
+        if let Some(ideal) = sorted_trues.get(i) {
+            ideal_gain += f64::from(ideal.score);
+        }
+        if let Some(&rel) = trues.get(&pred.doc_id) {
+            gain += f64::from(rel);
+            if rel >= rel_lvl {
+                n_hits += 1;
+                // Blended ratio of the cumulative gain and precision at rank i + 1.
+                sum += beta.mul_add(gain, n_hits as f64) / beta.mul_add(ideal_gain, (i + 1) as f64);
+            }
+        }
+    }
+    sum / denom as f64
+}
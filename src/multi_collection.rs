@@ -0,0 +1,235 @@
+//! Evaluation of a system over multiple test collections.
+//!
+//! Benchmarks such as BEIR report a system by its scores on several collections,
+//! each of which has its own true relevance scores and query set.
+//! This module provides [`evaluate_collections`] to evaluate the system on each collection
+//! and to aggregate the per-collection means into the macro-averaged score
+//! with the between-collection variance.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::multi_collection::evaluate_collections;
+//!
+//! // Collection A with two queries.
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_2", "d_1", 1)?;
+//! let true_rels_a = b.build();
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! b.add_record("q_2", "d_2", 0.5.into())?;
+//! let pred_rels_a = b.build();
+//!
+//! // Collection B with one query.
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! let true_rels_b = b.build();
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.5.into())?;
+//! let pred_rels_b = b.build();
+//!
+//! let result = evaluate_collections(
+//!     [("a", &true_rels_a, &pred_rels_a), ("b", &true_rels_b, &pred_rels_b)],
+//!     Metric::Success { k: 1 },
+//! )?;
+//! assert_eq!(result.get(&"a").unwrap().mean(), 0.5);
+//! assert_eq!(result.get(&"b").unwrap().mean(), 1.0);
+//!
+//! // Each collection equally contributes to the macro-averaged score.
+//! assert_eq!(result.macro_mean(), 0.75);
+//! assert_eq!(result.between_variance(), 0.0625);
+//!
+//! // Each query equally contributes to the micro-averaged score.
+//! assert_eq!(result.micro_mean(), 2.0 / 3.0);
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeSet;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::metrics::MetricId;
+use crate::Evaluation;
+use crate::Metric;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+
+/// Evaluated results of a system over multiple collections.
+pub struct MultiCollectionEvaluation<C, K> {
+    metric: MetricId,
+    evaluations: Vec<(C, Evaluation<K>)>,
+    macro_mean: f64,
+    between_variance: f64,
+}
+
+impl<C, K> MultiCollectionEvaluation<C, K>
+where
+    C: Clone + Eq + Ord + std::fmt::Display,
+{
+    /// Creates an instance from the per-collection evaluations, e.g., those computed beforehand.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if `evaluations` is empty.
+    /// * [`ElinorError::InvalidArgument`] if the evaluations have different metrics.
+    /// * [`ElinorError::DuplicateEntry`] if a collection name appears more than once.
+    pub fn from_evaluations(evaluations: Vec<(C, Evaluation<K>)>) -> Result<Self> {
+        let Some((_, first)) = evaluations.first() else {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least one collection.".to_string(),
+            ));
+        };
+        let metric = first.metric().clone();
+        let mut names = BTreeSet::new();
+        for (name, evaluation) in &evaluations {
+            if evaluation.metric() != &metric {
+                return Err(ElinorError::InvalidArgument(format!(
+                    "All the evaluations must have the same metric, but got {metric} and {}",
+                    evaluation.metric()
+                )));
+            }
+            if !names.insert(name.clone()) {
+                return Err(ElinorError::DuplicateEntry(format!(
+                    "Collection {name} appears more than once."
+                )));
+            }
+        }
+        let n_collections = evaluations.len() as f64;
+        let macro_mean = evaluations.iter().map(|(_, e)| e.mean()).sum::<f64>() / n_collections;
+        let between_variance = evaluations
+            .iter()
+            .map(|(_, e)| (e.mean() - macro_mean).powi(2))
+            .sum::<f64>()
+            / n_collections;
+        Ok(Self {
+            metric,
+            evaluations,
+            macro_mean,
+            between_variance,
+        })
+    }
+
+    /// Returns the evaluation of the given collection.
+    pub fn get(&self, name: &C) -> Option<&Evaluation<K>> {
+        self.evaluations
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, e)| e)
+    }
+}
+
+impl<C, K> MultiCollectionEvaluation<C, K> {
+    /// Returns the metric used for evaluation.
+    pub const fn metric(&self) -> &MetricId {
+        &self.metric
+    }
+
+    /// Returns the pairs of collection names and evaluations in the input order.
+    pub fn evaluations(&self) -> &[(C, Evaluation<K>)] {
+        &self.evaluations
+    }
+
+    /// Returns the number of collections.
+    pub fn n_collections(&self) -> usize {
+        self.evaluations.len()
+    }
+
+    /// Returns the macro-averaged score, i.e., the mean of the per-collection means.
+    pub const fn macro_mean(&self) -> f64 {
+        self.macro_mean
+    }
+
+    /// Returns the micro-averaged score, i.e., the mean over the queries of all the collections.
+    pub fn micro_mean(&self) -> f64 {
+        let (sum, n_queries) = self.evaluations.iter().fold((0.0, 0), |(sum, n), (_, e)| {
+            (sum + e.scores().values().sum::<f64>(), n + e.scores().len())
+        });
+        sum / n_queries as f64
+    }
+
+    /// Returns the variance of the per-collection means.
+    pub const fn between_variance(&self) -> f64 {
+        self.between_variance
+    }
+
+    /// Returns the standard deviation of the per-collection means.
+    pub fn between_std_dev(&self) -> f64 {
+        self.between_variance.sqrt()
+    }
+}
+
+/// Evaluates a system on each collection given as a tuple of the name,
+/// the true relevance scores, and the predicted relevance scores.
+///
+/// # Errors
+///
+/// See [`MultiCollectionEvaluation::from_evaluations`] and
+/// [`metrics::compute_metric`](crate::metrics::compute_metric) for the list of possible errors.
+pub fn evaluate_collections<'a, I, C, K>(
+    collections: I,
+    metric: Metric,
+) -> Result<MultiCollectionEvaluation<C, K>>
+where
+    I: IntoIterator<Item = (C, &'a TrueRelStore<K>, &'a PredRelStore<K>)>,
+    C: Clone + Eq + Ord + std::fmt::Display,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    let mut evaluations = vec![];
+    for (name, true_rels, pred_rels) in collections {
+        evaluations.push((name, crate::evaluate(true_rels, pred_rels, metric)?));
+    }
+    MultiCollectionEvaluation::from_evaluations(evaluations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_from_evaluations_empty() {
+        let result = MultiCollectionEvaluation::<&str, &str>::from_evaluations(vec![]);
+        assert_eq!(
+            result.err().unwrap(),
+            ElinorError::InvalidArgument(
+                "The input must have at least one collection.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_evaluations_duplicate() {
+        let metric = Metric::Precision { k: 1 };
+        let scores = BTreeMap::from([("q_1", 1.0)]);
+        let result = MultiCollectionEvaluation::from_evaluations(vec![
+            ("a", Evaluation::from_scores(metric, scores.clone())),
+            ("a", Evaluation::from_scores(metric, scores)),
+        ]);
+        assert_eq!(
+            result.err().unwrap(),
+            ElinorError::DuplicateEntry("Collection a appears more than once.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_evaluations_different_metrics() {
+        let scores = BTreeMap::from([("q_1", 1.0)]);
+        let result = MultiCollectionEvaluation::from_evaluations(vec![
+            (
+                "a",
+                Evaluation::from_scores(Metric::Precision { k: 1 }, scores.clone()),
+            ),
+            ("b", Evaluation::from_scores(Metric::RR { k: 0 }, scores)),
+        ]);
+        assert_eq!(
+            result.err().unwrap(),
+            ElinorError::InvalidArgument(
+                "All the evaluations must have the same metric, but got precision@1 and rr"
+                    .to_string()
+            )
+        );
+    }
+}
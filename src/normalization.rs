@@ -0,0 +1,205 @@
+//! Per-query normalization of predicted relevance scores.
+//!
+//! Predicted relevance scores from different systems often have different ranges,
+//! which must be aligned before [fusion](crate::fusion) or calibration analyses.
+//! This module provides [`normalize_scores`] to transform the scores of each query
+//! with a method specified by [`Normalization`].
+//!
+//! All the methods are monotonically non-decreasing transforms,
+//! so the order of [`get_sorted`](crate::relevance::RelevanceStore::get_sorted)
+//! is preserved after the normalization.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::PredRelStoreBuilder;
+//! use elinor::normalization::{normalize_scores, Normalization};
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 3.0.into())?;
+//! b.add_record("q_1", "d_2", 2.0.into())?;
+//! b.add_record("q_1", "d_3", 1.0.into())?;
+//! let pred_rels = b.build();
+//!
+//! let normalized = normalize_scores(&pred_rels, Normalization::MinMax);
+//! assert_eq!(normalized.get_score("q_1", "d_1"), Some(&1.0.into()));
+//! assert_eq!(normalized.get_score("q_1", "d_2"), Some(&0.5.into()));
+//! assert_eq!(normalized.get_score("q_1", "d_3"), Some(&0.0.into()));
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::Display;
+
+use crate::PredRelStore;
+use crate::PredRelStoreBuilder;
+use crate::PredScore;
+
+/// Methods of per-query score normalization.
+///
+/// # Notations
+///
+/// * $`s_i`$: Score of the $`i`$-th document for a query.
+/// * $`n`$: Number of documents for the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Min-max normalization into the range $`[0, 1]`$:
+    ///
+    /// ```math
+    /// s'_i = \frac{s_i - \min_j s_j}{\max_j s_j - \min_j s_j}
+    /// ```
+    ///
+    /// If all the scores are the same, they are set to $`1`$.
+    MinMax,
+
+    /// Z-score normalization:
+    ///
+    /// ```math
+    /// s'_i = \frac{s_i - \mu}{\sigma}
+    /// ```
+    ///
+    /// where $`\mu`$ and $`\sigma`$ are the mean and the (population) standard deviation of the scores.
+    ///
+    /// If all the scores are the same, they are set to $`0`$.
+    ZScore,
+
+    /// Rank-based normalization into the range $`(0, 1]`$:
+    ///
+    /// ```math
+    /// s'_i = \frac{n - r_i + 1}{n}
+    /// ```
+    ///
+    /// where $`r_i`$ is the 1-origin rank of the $`i`$-th document
+    /// in the order of [`get_sorted`](crate::relevance::RelevanceStore::get_sorted).
+    Rank,
+}
+
+/// Normalizes the predicted relevance scores for each query.
+pub fn normalize_scores<K>(pred_rels: &PredRelStore<K>, method: Normalization) -> PredRelStore<K>
+where
+    K: Eq + Ord + Clone + Display,
+{
+    let mut b = PredRelStoreBuilder::new();
+    for query_id in pred_rels.query_ids() {
+        let sorted = pred_rels.get_sorted(query_id).unwrap();
+        let scores = sorted
+            .iter()
+            .map(|rel| rel.score.into_inner())
+            .collect::<Vec<_>>();
+        let normalized = match method {
+            Normalization::MinMax => normalize_min_max(&scores),
+            Normalization::ZScore => normalize_z_score(&scores),
+            Normalization::Rank => normalize_rank(&scores),
+        };
+        for (rel, score) in sorted.iter().zip(normalized) {
+            // The pair is unique because it comes from the store.
+            b.add_record(query_id.clone(), rel.doc_id.clone(), PredScore::from(score))
+                .unwrap();
+        }
+    }
+    b.build()
+}
+
+fn normalize_min_max(scores: &[f64]) -> Vec<f64> {
+    let min = scores.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    if range == 0.0 {
+        return vec![1.0; scores.len()];
+    }
+    scores.iter().map(|&s| (s - min) / range).collect()
+}
+
+fn normalize_z_score(scores: &[f64]) -> Vec<f64> {
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let std_dev = (scores.iter().map(|&s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+    if std_dev == 0.0 {
+        return vec![0.0; scores.len()];
+    }
+    scores.iter().map(|&s| (s - mean) / std_dev).collect()
+}
+
+fn normalize_rank(scores: &[f64]) -> Vec<f64> {
+    // The scores are given in the sorted order.
+    let n = scores.len() as f64;
+    (0..scores.len()).map(|i| (n - i as f64) / n).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn pred_rels() -> PredRelStore<char> {
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'x', 4.0.into()).unwrap();
+        b.add_record('a', 'y', 2.0.into()).unwrap();
+        b.add_record('a', 'z', 0.0.into()).unwrap();
+        b.add_record('b', 'x', 5.0.into()).unwrap();
+        b.add_record('b', 'y', 5.0.into()).unwrap();
+        b.build()
+    }
+
+    fn score(store: &PredRelStore<char>, query_id: char, doc_id: char) -> f64 {
+        store.get_score(&query_id, &doc_id).unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_normalize_scores_min_max() {
+        let normalized = normalize_scores(&pred_rels(), Normalization::MinMax);
+        assert_relative_eq!(score(&normalized, 'a', 'x'), 1.0);
+        assert_relative_eq!(score(&normalized, 'a', 'y'), 0.5);
+        assert_relative_eq!(score(&normalized, 'a', 'z'), 0.0);
+        assert_relative_eq!(score(&normalized, 'b', 'x'), 1.0);
+        assert_relative_eq!(score(&normalized, 'b', 'y'), 1.0);
+    }
+
+    #[test]
+    fn test_normalize_scores_z_score() {
+        let normalized = normalize_scores(&pred_rels(), Normalization::ZScore);
+        let std_dev = (8.0_f64 / 3.0).sqrt();
+        assert_relative_eq!(score(&normalized, 'a', 'x'), 2.0 / std_dev);
+        assert_relative_eq!(score(&normalized, 'a', 'y'), 0.0);
+        assert_relative_eq!(score(&normalized, 'a', 'z'), -2.0 / std_dev);
+        assert_relative_eq!(score(&normalized, 'b', 'x'), 0.0);
+        assert_relative_eq!(score(&normalized, 'b', 'y'), 0.0);
+    }
+
+    #[test]
+    fn test_normalize_scores_rank() {
+        let normalized = normalize_scores(&pred_rels(), Normalization::Rank);
+        assert_relative_eq!(score(&normalized, 'a', 'x'), 3.0 / 3.0);
+        assert_relative_eq!(score(&normalized, 'a', 'y'), 2.0 / 3.0);
+        assert_relative_eq!(score(&normalized, 'a', 'z'), 1.0 / 3.0);
+        assert_relative_eq!(score(&normalized, 'b', 'x'), 2.0 / 2.0);
+        assert_relative_eq!(score(&normalized, 'b', 'y'), 1.0 / 2.0);
+    }
+
+    #[test]
+    fn test_normalize_scores_preserves_order() {
+        let pred_rels = pred_rels();
+        for method in [
+            Normalization::MinMax,
+            Normalization::ZScore,
+            Normalization::Rank,
+        ] {
+            let normalized = normalize_scores(&pred_rels, method);
+            for query_id in pred_rels.query_ids() {
+                let expected = pred_rels
+                    .get_sorted(query_id)
+                    .unwrap()
+                    .iter()
+                    .map(|rel| rel.doc_id)
+                    .collect::<Vec<_>>();
+                let actual = normalized
+                    .get_sorted(query_id)
+                    .unwrap()
+                    .iter()
+                    .map(|rel| rel.doc_id)
+                    .collect::<Vec<_>>();
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
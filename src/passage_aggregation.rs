@@ -0,0 +1,125 @@
+//! Aggregation of passage-level predictions into document-level predictions.
+//!
+//! In passage-ranking collections (e.g., MS MARCO),
+//! systems often retrieve passages while true relevance scores are given for documents.
+//! This module provides [`aggregate_passages`] to convert passage-level predicted relevance scores
+//! into document-level ones with a method specified by [`PassageAggregation`],
+//! so that they can be evaluated against document-level true relevance scores.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::collections::BTreeMap;
+//! use elinor::PredRelStoreBuilder;
+//! use elinor::passage_aggregation::{aggregate_passages, PassageAggregation};
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1#p_1", 0.5.into())?;
+//! b.add_record("q_1", "d_1#p_2", 0.3.into())?;
+//! b.add_record("q_1", "d_2#p_1", 0.4.into())?;
+//! let pred_rels = b.build();
+//!
+//! let parent_ids = BTreeMap::from([
+//!     ("d_1#p_1", "d_1"),
+//!     ("d_1#p_2", "d_1"),
+//!     ("d_2#p_1", "d_2"),
+//! ]);
+//! let doc_rels = aggregate_passages(&pred_rels, &parent_ids, PassageAggregation::Max);
+//! assert_eq!(doc_rels.n_docs(), 2);
+//! assert_eq!(doc_rels.get_score("q_1", "d_1"), Some(&0.5.into()));
+//! assert_eq!(doc_rels.get_score("q_1", "d_2"), Some(&0.4.into()));
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::PredRelStore;
+use crate::PredRelStoreBuilder;
+use crate::PredScore;
+
+/// Methods of aggregating passage scores into a document score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PassageAggregation {
+    /// Maximum score of the passages in the document (MaxP).
+    Max,
+
+    /// Sum of the scores of the passages in the document.
+    Sum,
+}
+
+/// Aggregates passage-level predicted relevance scores into document-level ones.
+///
+/// # Arguments
+///
+/// * `pred_rels` - Passage-level predicted relevance scores.
+/// * `parent_ids` - Mapping from passage ids to their parent document ids.
+///   Passages not in the mapping are regarded as documents as they are.
+/// * `method` - Aggregation method.
+pub fn aggregate_passages<K>(
+    pred_rels: &PredRelStore<K>,
+    parent_ids: &BTreeMap<K, K>,
+    method: PassageAggregation,
+) -> PredRelStore<K>
+where
+    K: Eq + Ord + Clone + Display,
+{
+    let mut b = PredRelStoreBuilder::new();
+    for query_id in pred_rels.query_ids() {
+        let mut docs: BTreeMap<&K, f64> = BTreeMap::new();
+        for rel in pred_rels.get_sorted(query_id).unwrap() {
+            let doc_id = parent_ids.get(&rel.doc_id).unwrap_or(&rel.doc_id);
+            let score = rel.score.into_inner();
+            docs.entry(doc_id)
+                .and_modify(|agg| match method {
+                    PassageAggregation::Max => *agg = agg.max(score),
+                    PassageAggregation::Sum => *agg += score,
+                })
+                .or_insert(score);
+        }
+        for (doc_id, score) in docs {
+            // The pair is unique because docs is a map.
+            b.add_record(query_id.clone(), doc_id.clone(), PredScore::from(score))
+                .unwrap();
+        }
+    }
+    b.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn pred_rels() -> PredRelStore<&'static str> {
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record("a", "x_1", 0.5.into()).unwrap();
+        b.add_record("a", "x_2", 0.3.into()).unwrap();
+        b.add_record("a", "y_1", 0.4.into()).unwrap();
+        b.add_record("a", "z", 0.1.into()).unwrap();
+        b.build()
+    }
+
+    fn parent_ids() -> BTreeMap<&'static str, &'static str> {
+        BTreeMap::from([("x_1", "x"), ("x_2", "x"), ("y_1", "y")])
+    }
+
+    #[test]
+    fn test_aggregate_passages_max() {
+        let doc_rels = aggregate_passages(&pred_rels(), &parent_ids(), PassageAggregation::Max);
+        assert_eq!(doc_rels.n_docs(), 3);
+        assert_relative_eq!(doc_rels.get_score("a", "x").unwrap().into_inner(), 0.5);
+        assert_relative_eq!(doc_rels.get_score("a", "y").unwrap().into_inner(), 0.4);
+        assert_relative_eq!(doc_rels.get_score("a", "z").unwrap().into_inner(), 0.1);
+    }
+
+    #[test]
+    fn test_aggregate_passages_sum() {
+        let doc_rels = aggregate_passages(&pred_rels(), &parent_ids(), PassageAggregation::Sum);
+        assert_eq!(doc_rels.n_docs(), 3);
+        assert_relative_eq!(doc_rels.get_score("a", "x").unwrap().into_inner(), 0.8);
+        assert_relative_eq!(doc_rels.get_score("a", "y").unwrap().into_inner(), 0.4);
+        assert_relative_eq!(doc_rels.get_score("a", "z").unwrap().into_inner(), 0.1);
+    }
+}
@@ -0,0 +1,143 @@
+//! Export of rank positions of relevant documents.
+//!
+//! Rank-based metrics such as MRR and Recall are computed from the positions
+//! at which relevant documents appear in a ranking.
+//! This module provides [`extract_rank_positions`] to export these positions per query,
+//! so that downstream audits and custom plots can be made
+//! without re-implementing the join of rankings and true relevance scores.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder};
+//! use elinor::rank_positions::extract_rank_positions;
+//!
+//! let mut b = TrueRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 1)?;
+//! b.add_record("q_1", "d_2", 0)?;
+//! b.add_record("q_1", "d_3", 2)?;
+//! let true_rels = b.build();
+//!
+//! let mut b = PredRelStoreBuilder::new();
+//! b.add_record("q_1", "d_1", 0.3.into())?;
+//! b.add_record("q_1", "d_2", 0.5.into())?;
+//! b.add_record("q_1", "d_4", 0.4.into())?;
+//! let pred_rels = b.build();
+//!
+//! let positions = extract_rank_positions(&true_rels, &pred_rels);
+//! assert_eq!(positions.len(), 2);
+//! assert_eq!((positions[0].doc_id, positions[0].rank), ("d_1", Some(3)));
+//! assert_eq!((positions[1].doc_id, positions[1].rank), ("d_3", None));
+//! # Ok(())
+//! # }
+//! ```
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::metrics::RELEVANT_LEVEL;
+use crate::PredRelStore;
+use crate::TrueRelStore;
+use crate::TrueScore;
+
+/// Rank position of a relevant document.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RankPosition<K> {
+    /// Query id.
+    pub query_id: K,
+
+    /// Document id.
+    pub doc_id: K,
+
+    /// True relevance score of the document.
+    pub score: TrueScore,
+
+    /// 1-origin rank of the document in the predicted ranking,
+    /// or `None` if the document is not retrieved.
+    pub rank: Option<usize>,
+}
+
+/// Extracts the rank positions of all relevant documents for each query.
+///
+/// A document is regarded as relevant if its true relevance score is positive.
+/// The returned vector is sorted by query ids and then by document ids.
+/// Queries not in `pred_rels` are included with all ranks being `None`.
+pub fn extract_rank_positions<K>(
+    true_rels: &TrueRelStore<K>,
+    pred_rels: &PredRelStore<K>,
+) -> Vec<RankPosition<K>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let mut positions = vec![];
+    for query_id in true_rels.query_ids() {
+        let sorted_preds = pred_rels.get_sorted(query_id).unwrap_or_default();
+        for (doc_id, &score) in true_rels.get_map(query_id).unwrap() {
+            if score < RELEVANT_LEVEL {
+                continue;
+            }
+            let rank = sorted_preds
+                .iter()
+                .position(|pred| &pred.doc_id == doc_id)
+                .map(|i| i + 1);
+            positions.push(RankPosition {
+                query_id: query_id.clone(),
+                doc_id: doc_id.clone(),
+                score,
+                rank,
+            });
+        }
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PredRelStoreBuilder;
+    use crate::TrueRelStoreBuilder;
+
+    #[test]
+    fn test_extract_rank_positions() {
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('a', 'y', 0).unwrap();
+        b.add_record('a', 'z', 2).unwrap();
+        b.add_record('b', 'x', 1).unwrap();
+        let true_rels = b.build();
+
+        let mut b = PredRelStoreBuilder::new();
+        b.add_record('a', 'y', 0.9.into()).unwrap();
+        b.add_record('a', 'z', 0.8.into()).unwrap();
+        b.add_record('a', 'x', 0.7.into()).unwrap();
+        let pred_rels = b.build();
+
+        let positions = extract_rank_positions(&true_rels, &pred_rels);
+        assert_eq!(
+            positions,
+            vec![
+                RankPosition {
+                    query_id: 'a',
+                    doc_id: 'x',
+                    score: 1,
+                    rank: Some(3),
+                },
+                RankPosition {
+                    query_id: 'a',
+                    doc_id: 'z',
+                    score: 2,
+                    rank: Some(2),
+                },
+                RankPosition {
+                    query_id: 'b',
+                    doc_id: 'x',
+                    score: 1,
+                    rank: None,
+                },
+            ]
+        );
+    }
+}
@@ -1,8 +1,14 @@
 //! Data structures for storing relevance scores.
 use std::borrow::Borrow;
+use std::collections::btree_map;
 use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
 
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
 #[cfg(feature = "serde")]
 use serde::Deserialize;
 #[cfg(feature = "serde")]
@@ -10,6 +16,7 @@ use serde::Serialize;
 
 use crate::errors::ElinorError;
 use crate::errors::Result;
+use crate::PredScore;
 
 /// Record of a query-document pair.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -25,6 +32,150 @@ pub struct Record<K, T> {
     pub score: T,
 }
 
+/// Query or document id that preserves whether it is an integer or a string,
+/// for use as the key type `K` of records and relevance stores.
+///
+/// With the `serde` feature, ids are (de)serialized without tags,
+/// so `123` and `"123"` in JSON are kept apart and written back as they were read,
+/// instead of failing or being turned into strings with `String` keys.
+///
+/// Integer ids are ordered numerically and before all string ids.
+/// Since CSV fields have no types, ids read by
+/// [`RelevanceStore::from_csv_reader`] are always string ids (e.g., `007` is kept as it is),
+/// which can be turned into integer ids with [`RecordId::coerce_integer`].
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(not(feature = "serde"))]
+/// # fn main() {}
+/// #
+/// # #[cfg(feature = "serde")]
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::relevance::RecordId;
+/// use elinor::{TrueRecord, TrueRelStore};
+///
+/// let data = r#"
+/// {"query_id": 10, "doc_id": "d_1", "score": 1}
+/// {"query_id": 9, "doc_id": "d_2", "score": 0}
+/// {"query_id": "10", "doc_id": "d_2", "score": 2}
+/// "#.trim();
+///
+/// let records = data
+///     .lines()
+///     .map(|line| serde_json::from_str::<TrueRecord<RecordId>>(line))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let true_rels = TrueRelStore::from_records(records)?;
+/// assert_eq!(true_rels.n_queries(), 3);
+///
+/// let query_ids = true_rels.query_ids().cloned().collect::<Vec<_>>();
+/// assert_eq!(query_ids, vec![9.into(), 10.into(), "10".into()]);
+///
+/// let record = &true_rels.records()[0];
+/// assert_eq!(
+///     serde_json::to_string(record)?,
+///     r#"{"query_id":9,"doc_id":"d_2","score":0}"#
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RecordId {
+    /// Integer id.
+    Integer(i64),
+
+    /// String id.
+    String(String),
+}
+
+impl RecordId {
+    /// Returns the integer id, or `None` if the id is a string.
+    pub const fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(id) => Some(*id),
+            Self::String(_) => None,
+        }
+    }
+
+    /// Returns the string id, or `None` if the id is an integer.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Integer(_) => None,
+            Self::String(id) => Some(id),
+        }
+    }
+
+    /// Turns a string id into an integer id if it is written as an integer in the canonical form,
+    /// so that it is written back as it was read (e.g., `"7"` but not `"007"` or `"+7"`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use elinor::relevance::RecordId;
+    ///
+    /// assert_eq!(RecordId::from("7").coerce_integer(), RecordId::Integer(7));
+    /// assert_eq!(RecordId::from("-7").coerce_integer(), RecordId::Integer(-7));
+    /// assert_eq!(RecordId::from("007").coerce_integer(), RecordId::from("007"));
+    /// assert_eq!(RecordId::from("d_7").coerce_integer(), RecordId::from("d_7"));
+    /// ```
+    pub fn coerce_integer(self) -> Self {
+        match self {
+            Self::String(id) => match id.parse::<i64>() {
+                Ok(parsed) if parsed.to_string() == id => Self::Integer(parsed),
+                _ => Self::String(id),
+            },
+            id @ Self::Integer(_) => id,
+        }
+    }
+}
+
+impl FromStr for RecordId {
+    type Err = ElinorError;
+
+    /// Parses a string id, without coercing it into an integer id.
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self::String(s.to_string()))
+    }
+}
+
+impl Display for RecordId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Integer(id) => write!(f, "{id}"),
+            Self::String(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+impl From<i64> for RecordId {
+    fn from(id: i64) -> Self {
+        Self::Integer(id)
+    }
+}
+
+impl From<String> for RecordId {
+    fn from(id: String) -> Self {
+        Self::String(id)
+    }
+}
+
+impl From<&str> for RecordId {
+    fn from(id: &str) -> Self {
+        Self::String(id.to_string())
+    }
+}
+
+impl From<RecordId> for String {
+    fn from(id: RecordId) -> Self {
+        match id {
+            RecordId::Integer(id) => id.to_string(),
+            RecordId::String(id) => id,
+        }
+    }
+}
+
 /// Data to store a relevance score for a document.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Relevance<K, T> {
@@ -35,7 +186,7 @@ pub struct Relevance<K, T> {
     pub score: T,
 }
 
-struct RelevanceData<K, T> {
+pub(crate) struct RelevanceData<K, T> {
     sorted: Vec<Relevance<K, T>>,
     map: BTreeMap<K, T>,
 }
@@ -70,6 +221,54 @@ where
         Ok(b.build())
     }
 
+    /// Creates an instance from groups of relevance scores per query,
+    /// such as batches of results emitted by a ranking system.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::DuplicateEntry`] if a query appears in more than one group.
+    /// * [`ElinorError::DuplicateEntry`] if a document appears more than once in a group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::PredRelStore;
+    ///
+    /// let pred_rels = PredRelStore::from_groups([
+    ///     ("q_1", vec![("d_1", 0.5.into()), ("d_2", 0.4.into())]),
+    ///     ("q_2", vec![("d_1", 0.3.into())]),
+    /// ])?;
+    /// assert_eq!(pred_rels.n_queries(), 2);
+    /// assert_eq!(pred_rels.n_docs(), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_groups<I, D>(groups: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, D)>,
+        D: IntoIterator<Item = (K, T)>,
+    {
+        let mut map = BTreeMap::new();
+        for (query_id, rels) in groups {
+            let mut group = BTreeMap::new();
+            for (doc_id, score) in rels {
+                if group.insert(doc_id.clone(), score).is_some() {
+                    return Err(ElinorError::DuplicateEntry(format!(
+                        "Input query-doc pair must be unique, but got query_id={query_id}, doc_id={doc_id}"
+                    )));
+                }
+            }
+            if map.contains_key(&query_id) {
+                return Err(ElinorError::DuplicateEntry(format!(
+                    "Input query must be unique across groups, but got query_id={query_id}"
+                )));
+            }
+            map.insert(query_id, group);
+        }
+        Ok(RelevanceStoreBuilder { map }.build())
+    }
+
     /// Exports the relevance store into records.
     pub fn into_records(self) -> Vec<Record<K, T>> {
         self.map
@@ -125,6 +324,279 @@ where
     {
         self.map.get(query_id).map(|data| data.sorted.as_slice())
     }
+
+    /// Collapses duplicate documents into their canonical ids,
+    /// keeping the maximum score for each query and canonical id.
+    ///
+    /// This is useful for removing near-duplicate documents (e.g., URL dedup clusters),
+    /// which inflate precision-type metrics.
+    /// The same mapping should be applied to both true and predicted relevance stores.
+    ///
+    /// # Arguments
+    ///
+    /// * `canonical_ids` - Mapping from document ids to their canonical ids.
+    ///   Documents not in the mapping are kept as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::collections::BTreeMap;
+    /// use elinor::PredRelStoreBuilder;
+    ///
+    /// let mut b = PredRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 0.5.into())?;
+    /// b.add_record("q_1", "d_1_dup", 0.4.into())?;
+    /// b.add_record("q_1", "d_2", 0.3.into())?;
+    /// let pred_rels = b.build();
+    ///
+    /// let canonical_ids = BTreeMap::from([("d_1_dup", "d_1")]);
+    /// let collapsed = pred_rels.collapse_duplicates(&canonical_ids);
+    /// assert_eq!(collapsed.n_docs(), 2);
+    /// assert_eq!(collapsed.get_score("q_1", "d_1"), Some(&0.5.into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn collapse_duplicates(&self, canonical_ids: &BTreeMap<K, K>) -> Self {
+        let mut map: BTreeMap<K, BTreeMap<K, T>> = BTreeMap::new();
+        for (query_id, data) in &self.map {
+            let rels = map.entry(query_id.clone()).or_default();
+            for (doc_id, score) in &data.map {
+                let doc_id = canonical_ids.get(doc_id).unwrap_or(doc_id);
+                match rels.get_mut(doc_id) {
+                    Some(best) if *best >= *score => {}
+                    Some(best) => *best = score.clone(),
+                    None => {
+                        rels.insert(doc_id.clone(), score.clone());
+                    }
+                }
+            }
+        }
+        RelevanceStoreBuilder { map }.build()
+    }
+
+    /// Fills the scores missing from this store with those in a backoff store
+    /// (e.g., judgments from a previous year for recycled topics).
+    ///
+    /// Scores in this store always take precedence over those in the backoff store.
+    /// Queries only in the backoff store are also added.
+    ///
+    /// Returns the filled store and the mapping from query ids to
+    /// the number of scores taken from the backoff store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::TrueRelStoreBuilder;
+    ///
+    /// let mut b = TrueRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 1)?;
+    /// let true_rels = b.build();
+    ///
+    /// let mut b = TrueRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 0)?;
+    /// b.add_record("q_1", "d_2", 2)?;
+    /// let backoff_rels = b.build();
+    ///
+    /// let (filled, n_filled) = true_rels.fill_holes(&backoff_rels);
+    /// assert_eq!(filled.get_score("q_1", "d_1"), Some(&1));
+    /// assert_eq!(filled.get_score("q_1", "d_2"), Some(&2));
+    /// assert_eq!(n_filled["q_1"], 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fill_holes(&self, backoff: &Self) -> (Self, BTreeMap<K, usize>) {
+        let mut map: BTreeMap<K, BTreeMap<K, T>> = self
+            .map
+            .iter()
+            .map(|(query_id, data)| (query_id.clone(), data.map.clone()))
+            .collect();
+        let mut n_filled = BTreeMap::new();
+        for query_id in self.map.keys() {
+            n_filled.insert(query_id.clone(), 0);
+        }
+        for (query_id, data) in &backoff.map {
+            let rels = map.entry(query_id.clone()).or_default();
+            let count = n_filled.entry(query_id.clone()).or_insert(0);
+            for (doc_id, score) in &data.map {
+                if !rels.contains_key(doc_id) {
+                    rels.insert(doc_id.clone(), score.clone());
+                    *count += 1;
+                }
+            }
+        }
+        (RelevanceStoreBuilder { map }.build(), n_filled)
+    }
+
+    /// Returns a new store containing only the queries whose ids satisfy the predicate.
+    ///
+    /// This is useful for evaluating a subset of topics (e.g., hard topics or a fold)
+    /// without rebuilding the store from the records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::TrueRelStoreBuilder;
+    ///
+    /// let mut b = TrueRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 1)?;
+    /// b.add_record("q_2", "d_1", 1)?;
+    /// b.add_record("q_3", "d_2", 2)?;
+    /// let true_rels = b.build();
+    ///
+    /// let filtered = true_rels.filter_queries(|&query_id| query_id != "q_2");
+    /// assert_eq!(filtered.n_queries(), 2);
+    /// assert!(filtered.get_map("q_2").is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn filter_queries<F>(&self, mut predicate: F) -> Self
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let map = self
+            .map
+            .iter()
+            .filter(|(query_id, _)| predicate(query_id))
+            .map(|(query_id, data)| (query_id.clone(), data.map.clone()))
+            .collect();
+        RelevanceStoreBuilder { map }.build()
+    }
+
+    /// Returns a new store containing only the queries in the given set of ids.
+    ///
+    /// Query ids in the set but not in the store are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::collections::HashSet;
+    /// use elinor::PredRelStoreBuilder;
+    ///
+    /// let mut b = PredRelStoreBuilder::new();
+    /// b.add_record("q_1", "d_1", 0.5.into())?;
+    /// b.add_record("q_2", "d_1", 0.4.into())?;
+    /// let pred_rels = b.build();
+    ///
+    /// let retained = pred_rels.retain_queries(&HashSet::from(["q_2", "q_3"]));
+    /// assert_eq!(retained.n_queries(), 1);
+    /// assert_eq!(retained.get_score("q_2", "d_1"), Some(&0.4.into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn retain_queries(&self, query_ids: &HashSet<K>) -> Self
+    where
+        K: Hash,
+    {
+        self.filter_queries(|query_id| query_ids.contains(query_id))
+    }
+}
+
+/// Header of the CSV records of relevance scores.
+#[cfg(feature = "serde")]
+const CSV_HEADER: [&str; 3] = ["query_id", "doc_id", "score"];
+
+#[cfg(feature = "serde")]
+impl<K, T> RelevanceStore<K, T>
+where
+    K: Eq + Ord + Clone + Display + Serialize + DeserializeOwned,
+    T: Ord + Clone + Serialize + DeserializeOwned,
+{
+    /// Creates an instance from CSV records with the header of `query_id,doc_id,score`.
+    ///
+    /// The ids are parsed with [`FromStr`],
+    /// so [`RecordId`] ids are kept as strings (see [`RecordId::coerce_integer`]).
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if the header is not `query_id,doc_id,score`.
+    /// * [`ElinorError::InvalidFormat`] if a record or an id cannot be parsed.
+    /// * [`ElinorError::DuplicateEntry`] if a query-document pair appears more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::TrueRelStore;
+    ///
+    /// let data = "
+    /// query_id,doc_id,score
+    /// q_1,d_1,1
+    /// q_1,d_2,0
+    /// q_2,d_1,2
+    /// ".trim();
+    ///
+    /// let true_rels = TrueRelStore::<String>::from_csv_reader(data.as_bytes())?;
+    /// assert_eq!(true_rels.n_queries(), 2);
+    /// assert_eq!(true_rels.get_score("q_2", "d_1"), Some(&2));
+    ///
+    /// let mut buf = vec![];
+    /// true_rels.to_csv_writer(&mut buf)?;
+    /// assert_eq!(String::from_utf8(buf)?, "query_id,doc_id,score\nq_1,d_1,1\nq_1,d_2,0\nq_2,d_1,2\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_csv_reader<R>(reader: R) -> Result<Self>
+    where
+        R: std::io::Read,
+        K: FromStr,
+        K::Err: Display,
+    {
+        let mut reader = csv::Reader::from_reader(reader);
+        let header = reader.headers().map_err(|e| {
+            ElinorError::InvalidFormat(format!("Failed to read the CSV header: {e}"))
+        })?;
+        if header.iter().ne(CSV_HEADER) {
+            return Err(ElinorError::InvalidFormat(format!(
+                "The CSV header must be {}, but got {}",
+                CSV_HEADER.join(","),
+                header.iter().collect::<Vec<_>>().join(",")
+            )));
+        }
+        let mut b = RelevanceStoreBuilder::new();
+        let parse_id = |id: &str| {
+            id.parse::<K>().map_err(|e| {
+                ElinorError::InvalidFormat(format!("Failed to parse the CSV id {id:?}: {e}"))
+            })
+        };
+        for record in reader.deserialize::<Record<String, T>>() {
+            let record = record.map_err(|e| {
+                ElinorError::InvalidFormat(format!("Failed to parse the CSV record: {e}"))
+            })?;
+            let query_id = parse_id(&record.query_id)?;
+            let doc_id = parse_id(&record.doc_id)?;
+            b.add_record(query_id, doc_id, record.score)?;
+        }
+        Ok(b.build())
+    }
+
+    /// Writes the relevance store as CSV records with the header of `query_id,doc_id,score`,
+    /// where the records are sorted by query ids and then by scores in descending order.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidFormat`] if a record cannot be written.
+    pub fn to_csv_writer<W>(&self, writer: W) -> Result<()>
+    where
+        W: std::io::Write,
+    {
+        let to_error = |e: csv::Error| {
+            ElinorError::InvalidFormat(format!("Failed to write the CSV record: {e}"))
+        };
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(writer);
+        writer.write_record(CSV_HEADER).map_err(to_error)?;
+        for record in self.records() {
+            writer.serialize(record).map_err(to_error)?;
+        }
+        writer
+            .flush()
+            .map_err(|e| ElinorError::InvalidFormat(format!("Failed to write the CSV record: {e}")))
+    }
 }
 
 impl<K, T> RelevanceStore<K, T> {
@@ -142,6 +614,47 @@ impl<K, T> RelevanceStore<K, T> {
     pub fn query_ids(&self) -> impl Iterator<Item = &K> {
         self.map.keys()
     }
+
+    /// Returns the iterator over the query ids in ascending order, with the exact size.
+    pub(crate) fn query_id_keys(&self) -> btree_map::Keys<'_, K, RelevanceData<K, T>> {
+        self.map.keys()
+    }
+
+    /// Returns the number of documents for each relevance score,
+    /// e.g., the grade distribution of true relevance scores.
+    pub fn score_counts(&self) -> BTreeMap<T, usize>
+    where
+        T: Ord + Clone,
+    {
+        let mut counts = BTreeMap::new();
+        for data in self.map.values() {
+            for score in data.map.values() {
+                *counts.entry(score.clone()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the minimum and maximum relevance scores, or `None` if the store is empty.
+    pub fn score_range(&self) -> Option<(T, T)>
+    where
+        T: Ord + Clone,
+    {
+        // The sorted list is in descending order.
+        let min = self
+            .map
+            .values()
+            .filter_map(|data| data.sorted.last())
+            .map(|rel| &rel.score)
+            .min()?;
+        let max = self
+            .map
+            .values()
+            .filter_map(|data| data.sorted.first())
+            .map(|rel| &rel.score)
+            .max()?;
+        Some((min.clone(), max.clone()))
+    }
 }
 
 /// Builder for [`RelevanceStore`].
@@ -188,6 +701,29 @@ impl<K, T> RelevanceStoreBuilder<K, T> {
         Ok(())
     }
 
+    /// Merges the records of another builder into this builder.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::DuplicateEntry`] if a query-document pair exists in both builders.
+    pub fn merge(&mut self, other: Self) -> Result<()>
+    where
+        K: Eq + Ord + Clone + Display,
+    {
+        for (query_id, other_rels) in other.map {
+            let rels = self.map.entry(query_id.clone()).or_default();
+            for (doc_id, score) in other_rels {
+                if rels.contains_key(&doc_id) {
+                    return Err(ElinorError::DuplicateEntry(format!(
+                        "Input query-doc pair must be unique, but got query_id={query_id}, doc_id={doc_id}"
+                    )));
+                }
+                rels.insert(doc_id, score);
+            }
+        }
+        Ok(())
+    }
+
     /// Builds the relevance store.
     pub fn build(self) -> RelevanceStore<K, T>
     where
@@ -210,6 +746,180 @@ impl<K, T> RelevanceStoreBuilder<K, T> {
     }
 }
 
+/// Policy to handle non-finite (NaN or infinite) predicted scores at ingestion.
+///
+/// # Conversion from string representation
+///
+/// ```rust
+/// use elinor::relevance::NonFiniteScorePolicy;
+///
+/// assert_eq!("reject".parse(), Ok(NonFiniteScorePolicy::Reject));
+/// assert_eq!("drop".parse(), Ok(NonFiniteScorePolicy::Drop));
+/// assert_eq!("minimum".parse(), Ok(NonFiniteScorePolicy::TreatAsMinimum));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFiniteScorePolicy {
+    /// Rejects the score with an error.
+    #[default]
+    Reject,
+
+    /// Drops the record.
+    Drop,
+
+    /// Replaces the score with the minimum finite value, [`f64::MIN`],
+    /// so that the document is ranked last.
+    TreatAsMinimum,
+}
+
+impl FromStr for NonFiniteScorePolicy {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "drop" => Ok(Self::Drop),
+            "minimum" => Ok(Self::TreatAsMinimum),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported non-finite score policy: {s}"
+            ))),
+        }
+    }
+}
+
+/// Quantization of predicted scores to multiples of a step, such as `1e-6`.
+///
+/// Scores produced on different platforms can differ in the last digits,
+/// which changes tie-breaking and thus metric values.
+/// Rounding the scores at ingestion makes such scores equal.
+/// Non-finite scores are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::relevance::ScoreQuantization;
+/// use elinor::PredScore;
+///
+/// let quantization = ScoreQuantization::new(1e-6)?;
+/// let a = quantization.quantize(0.1234564999.into());
+/// let b = quantization.quantize(0.1234565001.into());
+/// assert_ne!(a, b);
+/// let a = quantization.quantize(0.12345670001.into());
+/// let b = quantization.quantize(0.12345669999.into());
+/// assert_eq!(a, b);
+///
+/// let quantization: ScoreQuantization = "0.5".parse()?;
+/// assert_eq!(quantization.quantize(1.3.into()), PredScore::from(1.5));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreQuantization {
+    step: f64,
+}
+
+impl ScoreQuantization {
+    /// Creates a quantization with the given step.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the step is not a positive finite number.
+    pub fn new(step: f64) -> Result<Self> {
+        if !step.is_finite() || step <= 0.0 {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The quantization step must be a positive finite number, but got {step}"
+            )));
+        }
+        Ok(Self { step })
+    }
+
+    /// Returns the step.
+    pub const fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Rounds the score to the nearest multiple of the step.
+    pub fn quantize(&self, score: PredScore) -> PredScore {
+        if !score.is_finite() {
+            return score;
+        }
+        ((score.into_inner() / self.step).round() * self.step).into()
+    }
+}
+
+impl FromStr for ScoreQuantization {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let step = s.parse::<f64>().map_err(|_| {
+            ElinorError::InvalidFormat(format!("Unsupported quantization step: {s}"))
+        })?;
+        Self::new(step)
+    }
+}
+
+impl Display for ScoreQuantization {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.step)
+    }
+}
+
+impl<K> RelevanceStoreBuilder<K, PredScore> {
+    /// Adds a predicted relevance score to the store,
+    /// handling a non-finite score according to the policy.
+    ///
+    /// Returns `true` if the score is non-finite, so that callers can count such scores.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the score is non-finite and the policy is [`NonFiniteScorePolicy::Reject`].
+    /// * [`ElinorError::DuplicateEntry`] if the query-document pair already exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use elinor::PredRelStoreBuilder;
+    /// use elinor::relevance::NonFiniteScorePolicy;
+    ///
+    /// let mut b = PredRelStoreBuilder::new();
+    /// let policy = NonFiniteScorePolicy::Drop;
+    /// assert!(!b.add_record_with_policy("q_1", "d_1", 0.5.into(), policy)?);
+    /// assert!(b.add_record_with_policy("q_1", "d_2", f64::NAN.into(), policy)?);
+    /// let pred_rels = b.build();
+    /// assert_eq!(pred_rels.n_docs(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_record_with_policy(
+        &mut self,
+        query_id: K,
+        doc_id: K,
+        score: PredScore,
+        policy: NonFiniteScorePolicy,
+    ) -> Result<bool>
+    where
+        K: Eq + Ord + Clone + Display,
+    {
+        if score.is_finite() {
+            self.add_record(query_id, doc_id, score)?;
+            return Ok(false);
+        }
+        match policy {
+            NonFiniteScorePolicy::Reject => {
+                return Err(ElinorError::InvalidArgument(format!(
+                    "Predicted scores must be finite, but got {score} for query_id={query_id}, doc_id={doc_id}"
+                )));
+            }
+            NonFiniteScorePolicy::Drop => {}
+            NonFiniteScorePolicy::TreatAsMinimum => {
+                self.add_record(query_id, doc_id, f64::MIN.into())?;
+            }
+        }
+        Ok(true)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -247,6 +957,90 @@ mod tests {
         assert_eq!(records, other);
     }
 
+    #[test]
+    fn test_add_record_with_policy() {
+        let mut b = RelevanceStoreBuilder::new();
+        assert_eq!(
+            b.add_record_with_policy('a', 'x', f64::NAN.into(), NonFiniteScorePolicy::Reject),
+            Err(ElinorError::InvalidArgument(
+                "Predicted scores must be finite, but got NaN for query_id=a, doc_id=x".to_string()
+            ))
+        );
+        assert_eq!(
+            b.add_record_with_policy(
+                'a',
+                'x',
+                f64::INFINITY.into(),
+                NonFiniteScorePolicy::TreatAsMinimum
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            b.add_record_with_policy('a', 'y', 0.5.into(), NonFiniteScorePolicy::TreatAsMinimum),
+            Ok(false)
+        );
+        let store = b.build();
+        assert_eq!(store.get_score(&'a', &'x'), Some(&f64::MIN.into()));
+        let sorted = store.get_sorted(&'a').unwrap();
+        assert_eq!(sorted[1].doc_id, 'x');
+    }
+
+    #[test]
+    fn test_relevance_store_from_groups() {
+        let store =
+            RelevanceStore::from_groups([('a', vec![('x', 1)]), ('b', vec![('x', 1), ('y', 2)])])
+                .unwrap();
+        let other = RelevanceStore::from_records(
+            [('a', 'x', 1), ('b', 'x', 1), ('b', 'y', 2)].map(|(query_id, doc_id, score)| Record {
+                query_id,
+                doc_id,
+                score,
+            }),
+        )
+        .unwrap();
+        assert_eq!(store.records(), other.records());
+    }
+
+    #[test]
+    fn test_relevance_store_from_groups_duplicates() {
+        assert_eq!(
+            RelevanceStore::from_groups([('a', vec![('x', 1), ('x', 2)])]).err(),
+            Some(ElinorError::DuplicateEntry(
+                "Input query-doc pair must be unique, but got query_id=a, doc_id=x".to_string()
+            ))
+        );
+        assert_eq!(
+            RelevanceStore::from_groups([('a', vec![('x', 1)]), ('a', vec![('y', 1)])]).err(),
+            Some(ElinorError::DuplicateEntry(
+                "Input query must be unique across groups, but got query_id=a".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_relevance_store_builder_merge() {
+        let mut b = RelevanceStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        let mut other = RelevanceStoreBuilder::new();
+        other.add_record('a', 'y', 2).unwrap();
+        other.add_record('b', 'x', 3).unwrap();
+        b.merge(other).unwrap();
+        let store = b.build();
+        assert_eq!(store.n_queries(), 2);
+        assert_eq!(store.n_docs(), 3);
+
+        let mut b = RelevanceStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        let mut other = RelevanceStoreBuilder::new();
+        other.add_record('a', 'x', 2).unwrap();
+        assert_eq!(
+            b.merge(other).err(),
+            Some(ElinorError::DuplicateEntry(
+                "Input query-doc pair must be unique, but got query_id=a, doc_id=x".to_string()
+            ))
+        );
+    }
+
     #[test]
     fn test_relevance_store_n_queries() {
         let store = RelevanceStore::from_records([
@@ -303,6 +1097,29 @@ mod tests {
         assert_eq!(store.n_docs(), 4);
     }
 
+    #[test]
+    fn test_relevance_store_score_counts_and_range() {
+        let store = RelevanceStore::from_records(
+            [('a', 'x', 1), ('b', 'x', 0), ('b', 'y', 2), ('c', 'x', 1)].map(
+                |(query_id, doc_id, score)| Record {
+                    query_id,
+                    doc_id,
+                    score,
+                },
+            ),
+        )
+        .unwrap();
+        let expected = [(0, 1), (1, 2), (2, 1)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(store.score_counts(), expected);
+        assert_eq!(store.score_range(), Some((0, 2)));
+
+        let store = RelevanceStore::<char, u32>::from_records([]).unwrap();
+        assert!(store.score_counts().is_empty());
+        assert_eq!(store.score_range(), None);
+    }
+
     #[test]
     fn test_relevance_store_get_score() {
         let store = RelevanceStore::from_records([Record {
@@ -389,6 +1206,109 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_relevance_store_collapse_duplicates() {
+        let store = RelevanceStore::from_records([
+            Record {
+                query_id: 'a',
+                doc_id: 'x',
+                score: 1,
+            },
+            Record {
+                query_id: 'a',
+                doc_id: 'y',
+                score: 2,
+            },
+            Record {
+                query_id: 'a',
+                doc_id: 'z',
+                score: 0,
+            },
+            Record {
+                query_id: 'b',
+                doc_id: 'z',
+                score: 3,
+            },
+        ])
+        .unwrap();
+        let canonical_ids = BTreeMap::from([('y', 'x'), ('z', 'x')]);
+        let collapsed = store.collapse_duplicates(&canonical_ids);
+        assert_eq!(collapsed.get_map(&'a'), Some(&[('x', 2)].into()));
+        assert_eq!(collapsed.get_map(&'b'), Some(&[('x', 3)].into()));
+    }
+
+    #[test]
+    fn test_relevance_store_fill_holes() {
+        let store = RelevanceStore::from_records([
+            Record {
+                query_id: 'a',
+                doc_id: 'x',
+                score: 1,
+            },
+            Record {
+                query_id: 'b',
+                doc_id: 'x',
+                score: 1,
+            },
+        ])
+        .unwrap();
+        let backoff = RelevanceStore::from_records([
+            Record {
+                query_id: 'a',
+                doc_id: 'x',
+                score: 0,
+            },
+            Record {
+                query_id: 'a',
+                doc_id: 'y',
+                score: 2,
+            },
+            Record {
+                query_id: 'c',
+                doc_id: 'x',
+                score: 3,
+            },
+        ])
+        .unwrap();
+        let (filled, n_filled) = store.fill_holes(&backoff);
+        assert_eq!(filled.get_map(&'a'), Some(&[('x', 1), ('y', 2)].into()));
+        assert_eq!(filled.get_map(&'b'), Some(&[('x', 1)].into()));
+        assert_eq!(filled.get_map(&'c'), Some(&[('x', 3)].into()));
+        assert_eq!(n_filled, [('a', 1), ('b', 0), ('c', 1)].into());
+    }
+
+    #[test]
+    fn test_relevance_store_filter_and_retain_queries() {
+        let mut b = RelevanceStoreBuilder::new();
+        b.add_record('a', 'x', 1).unwrap();
+        b.add_record('a', 'y', 2).unwrap();
+        b.add_record('b', 'x', 1).unwrap();
+        b.add_record('c', 'z', 3).unwrap();
+        let store = b.build();
+
+        let filtered = store.filter_queries(|&q| q != 'b');
+        assert_eq!(
+            filtered.query_ids().copied().collect::<Vec<_>>(),
+            ['a', 'c']
+        );
+        assert_eq!(filtered.get_map(&'a'), Some(&[('x', 1), ('y', 2)].into()));
+        assert_eq!(
+            filtered.get_sorted(&'a').unwrap()[0],
+            Relevance {
+                doc_id: 'y',
+                score: 2
+            }
+        );
+
+        let retained = store.retain_queries(&HashSet::from(['b', 'd']));
+        assert_eq!(retained.n_queries(), 1);
+        assert_eq!(retained.n_docs(), 1);
+        assert_eq!(retained.get_map(&'b'), Some(&[('x', 1)].into()));
+
+        let empty = store.filter_queries(|_| false);
+        assert_eq!(empty.n_queries(), 0);
+    }
+
     #[test]
     fn test_relevance_store_builder() {
         let mut b = RelevanceStoreBuilder::new();
@@ -412,4 +1332,162 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn test_score_quantization() {
+        let quantization = ScoreQuantization::new(1e-6).unwrap();
+        assert_eq!(
+            quantization.quantize(0.300000000000001.into()),
+            quantization.quantize(0.3.into())
+        );
+        assert_eq!(
+            quantization.quantize((-0.25).into()),
+            PredScore::from(-0.25)
+        );
+        assert!(quantization.quantize(f64::NAN.into()).is_nan());
+        assert_eq!(
+            quantization.quantize(f64::INFINITY.into()),
+            PredScore::from(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_score_quantization_invalid_step() {
+        assert!(ScoreQuantization::new(0.0).is_err());
+        assert!(ScoreQuantization::new(-1.0).is_err());
+        assert!(ScoreQuantization::new(f64::NAN).is_err());
+        assert!(ScoreQuantization::new(f64::INFINITY).is_err());
+        assert_eq!(
+            "abc".parse::<ScoreQuantization>(),
+            Err(ElinorError::InvalidFormat(
+                "Unsupported quantization step: abc".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_record_id_order() {
+        let mut ids: Vec<RecordId> =
+            vec!["a".into(), 10.into(), "10".into(), (-1).into(), 9.into()];
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                RecordId::Integer(-1),
+                RecordId::Integer(9),
+                RecordId::Integer(10),
+                RecordId::String("10".to_string()),
+                RecordId::String("a".to_string()),
+            ]
+        );
+        assert_eq!(RecordId::Integer(10).to_string(), "10");
+        assert_eq!(String::from(RecordId::Integer(10)), "10");
+        assert_eq!(RecordId::from("10").as_integer(), None);
+        assert_eq!("10".parse(), Ok(RecordId::String("10".to_string())));
+        assert_eq!(RecordId::from(10).as_str(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_record_id_csv_round_trip() {
+        let data = "query_id,doc_id,score\n1,d_1,1\nq_2,7,0\n";
+        let true_rels = RelevanceStore::<RecordId, u32>::from_csv_reader(data.as_bytes()).unwrap();
+        assert_eq!(true_rels.get_score(&"1".into(), &"d_1".into()), Some(&1));
+        assert_eq!(true_rels.get_score(&"q_2".into(), &"7".into()), Some(&0));
+        let mut buf = vec![];
+        true_rels.to_csv_writer(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_record_id_csv_round_trip_leading_zeros() {
+        let data = "query_id,doc_id,score\n007,0042,1\n7,42,0\n";
+        let true_rels = RelevanceStore::<RecordId, u32>::from_csv_reader(data.as_bytes()).unwrap();
+        assert_eq!(true_rels.n_queries(), 2);
+        assert_eq!(true_rels.get_score(&"007".into(), &"0042".into()), Some(&1));
+        assert_eq!(true_rels.get_score(&"7".into(), &"42".into()), Some(&0));
+        assert_eq!(true_rels.get_score(&7.into(), &42.into()), None);
+        let mut buf = vec![];
+        true_rels.to_csv_writer(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_record_id_csv_coerce_integer() {
+        let data = "query_id,doc_id,score\n007,0042,1\n7,42,0\n";
+        let true_rels = RelevanceStore::<RecordId, u32>::from_csv_reader(data.as_bytes()).unwrap();
+        let records = true_rels.into_records().into_iter().map(|record| Record {
+            query_id: record.query_id.coerce_integer(),
+            doc_id: record.doc_id.coerce_integer(),
+            score: record.score,
+        });
+        let true_rels = RelevanceStore::from_records(records).unwrap();
+        assert_eq!(true_rels.get_score(&"007".into(), &"0042".into()), Some(&1));
+        assert_eq!(true_rels.get_score(&7.into(), &42.into()), Some(&0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_integer_ids_from_csv_reader() {
+        let data = "query_id,doc_id,score\n1,2,1\n";
+        let true_rels = RelevanceStore::<u32, u32>::from_csv_reader(data.as_bytes()).unwrap();
+        assert_eq!(true_rels.get_score(&1, &2), Some(&1));
+        let data = "query_id,doc_id,score\nq_1,2,1\n";
+        assert!(matches!(
+            RelevanceStore::<u32, u32>::from_csv_reader(data.as_bytes()),
+            Err(ElinorError::InvalidFormat(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_pred_rel_store_csv_round_trip() {
+        let data = "query_id,doc_id,score\nq_1,d_2,0.25\nq_1,d_1,0.5\nq_2,d_1,-1\n";
+        let pred_rels =
+            RelevanceStore::<String, PredScore>::from_csv_reader(data.as_bytes()).unwrap();
+        assert_eq!(pred_rels.n_queries(), 2);
+        assert_eq!(
+            pred_rels.get_score("q_1", "d_1"),
+            Some(&PredScore::from(0.5))
+        );
+
+        let mut buf = vec![];
+        pred_rels.to_csv_writer(&mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "query_id,doc_id,score\nq_1,d_1,0.5\nq_1,d_2,0.25\nq_2,d_1,-1.0\n"
+        );
+
+        let empty = RelevanceStore::<String, PredScore>::from_records([]).unwrap();
+        let mut buf = vec![];
+        empty.to_csv_writer(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "query_id,doc_id,score\n");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_true_rel_store_from_csv_reader_invalid() {
+        let from_csv = |data: &str| RelevanceStore::<String, u32>::from_csv_reader(data.as_bytes());
+        assert_eq!(
+            from_csv("query_id,score,doc_id\nq_1,1,d_1\n").err(),
+            Some(ElinorError::InvalidFormat(
+                "The CSV header must be query_id,doc_id,score, but got query_id,score,doc_id"
+                    .to_string()
+            ))
+        );
+        assert!(matches!(
+            from_csv("query_id,doc_id,score\nq_1,d_1,-1\n"),
+            Err(ElinorError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            from_csv("query_id,doc_id,score\nq_1,d_1\n"),
+            Err(ElinorError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            from_csv("query_id,doc_id,score\nq_1,d_1,1\nq_1,d_1,2\n"),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+    }
 }
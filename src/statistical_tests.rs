@@ -4,25 +4,329 @@
 //!
 //! * [Student's t-test](StudentTTest) for comparing two systems.
 //! * [Bootstrap test](BootstrapTest) for comparing two systems.
+//! * [Wilcoxon signed-rank test](WilcoxonSignedRankTest) for comparing two systems.
+//! * [Sign test](SignTest) for comparing two systems.
+//! * [Fisher's randomization test](FisherRandomizationTest) for comparing two systems.
+//! * [Levene's test](LeveneTest) for comparing the variances of two systems.
+//! * [Quantile bootstrap test](QuantileBootstrapTest) for comparing the quantiles of two systems.
 //! * [Two-way ANOVA without replication](TwoWayAnovaWithoutReplication) for comparing three or more systems.
 //! * [Tukey HSD test](TukeyHsdTest) for comparing three or more systems.
 //! * [Randomized Tukey HSD test](RandomizedTukeyHsdTest) for comparing two or more systems.
+//!
+//! The tests for comparing the means of two systems implement [`SignificanceTest`],
+//! so that reporting code can handle them uniformly.
+//! The tests are two-sided by default,
+//! and some of them can be made one-sided with [`Alternative`] for superiority testing against a baseline.
+//! The p-values of many tests can be adjusted by the [`corrections`] module,
+//! and the results over multiple collections can be combined by the [`meta_analysis`] module.
+//! Student's t-test, bootstrap test, and Fisher's randomization test also accept per-topic weights
+//! (e.g., traffic weights) via `test_weighted` of their testers,
+//! to be consistent with weighted aggregate metrics.
+//! Per-topic scores can be standardized by [`standardize_scores`] before testing,
+//! so that every topic contributes equally regardless of its difficulty.
+//! The number of topics required for a test can be determined by the [`power`] module,
+//! and effect sizes can be labeled qualitatively by the [`effect_sizes`] module.
 pub mod bootstrap_test;
+pub mod corrections;
+pub mod effect_sizes;
+pub mod fisher_randomization_test;
+pub mod levene_test;
+pub mod meta_analysis;
+pub mod power;
+pub mod quantile_bootstrap_test;
 pub mod randomized_tukey_hsd_test;
+pub mod sign_test;
 pub mod student_t_test;
 pub mod tukey_hsd_test;
 pub mod two_way_anova_without_replication;
+pub mod wilcoxon_signed_rank_test;
 
 pub use bootstrap_test::BootstrapTest;
+pub use fisher_randomization_test::FisherRandomizationTest;
+pub use levene_test::LeveneTest;
+pub use quantile_bootstrap_test::QuantileBootstrapTest;
 pub use randomized_tukey_hsd_test::RandomizedTukeyHsdTest;
+pub use sign_test::SignTest;
 pub use student_t_test::StudentTTest;
 pub use tukey_hsd_test::TukeyHsdTest;
 pub use two_way_anova_without_replication::TwoWayAnovaWithoutReplication;
+pub use wilcoxon_signed_rank_test::WilcoxonSignedRankTest;
 
 use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::Normal;
+use statrs::function::gamma::ln_gamma;
 
 use crate::errors::ElinorError;
 use crate::errors::Result;
+use crate::Evaluation;
+
+/// Alternative hypothesis for comparing two systems, $`A`$ and $`B`$,
+/// on the mean of the paired differences $`A - B`$.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::Alternative;
+///
+/// assert_eq!("two-sided".parse(), Ok(Alternative::TwoSided));
+/// assert_eq!("greater".parse(), Ok(Alternative::Greater));
+/// assert_eq!("less".parse(), Ok(Alternative::Less));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Alternative {
+    /// The mean difference is not zero.
+    #[default]
+    TwoSided,
+
+    /// The mean difference is greater than zero, i.e., $`A`$ is better than $`B`$.
+    Greater,
+
+    /// The mean difference is less than zero, i.e., $`A`$ is worse than $`B`$.
+    Less,
+}
+
+impl FromStr for Alternative {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "two-sided" => Ok(Self::TwoSided),
+            "greater" => Ok(Self::Greater),
+            "less" => Ok(Self::Less),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported alternative: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Alternative {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::TwoSided => write!(f, "two-sided"),
+            Self::Greater => write!(f, "greater"),
+            Self::Less => write!(f, "less"),
+        }
+    }
+}
+
+impl Alternative {
+    /// Orients a signed statistic so that larger values are more extreme under the alternative,
+    /// i.e., $`|t|`$ for two-sided, $`t`$ for greater, and $`-t`$ for less.
+    pub(crate) fn orient(self, stat: f64) -> f64 {
+        match self {
+            Self::TwoSided => stat.abs(),
+            Self::Greater => stat,
+            Self::Less => -stat,
+        }
+    }
+}
+
+/// Common interface of statistical tests for comparing two systems.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::statistical_tests::{BootstrapTest, SignificanceTest, StudentTTest};
+///
+/// let a = vec![0.70, 0.30, 0.20, 0.60, 0.40];
+/// let b = vec![0.50, 0.10, 0.00, 0.20, 0.40];
+/// let samples = a.into_iter().zip(b).collect::<Vec<_>>();
+///
+/// let tests: Vec<Box<dyn SignificanceTest>> = vec![
+///     Box::new(StudentTTest::from_paired_samples(samples.clone())?),
+///     Box::new(BootstrapTest::from_paired_samples(samples)?),
+/// ];
+/// for test in &tests {
+///     let (ci95_btm, ci95_top) = test.confidence_interval(0.05)?;
+///     assert!((0.0..=1.0).contains(&test.p_value()));
+///     assert!(ci95_btm <= ci95_top);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait SignificanceTest {
+    /// Name of the test method.
+    fn method_name(&self) -> &'static str;
+
+    /// p-value under the alternative hypothesis of the test.
+    fn p_value(&self) -> f64;
+
+    /// Sample effect size.
+    fn effect_size(&self) -> f64;
+
+    /// Confidence interval of the mean difference at a given significance level $`\alpha`$.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)>;
+}
+
+/// Methods of topic-wise score standardization for [`standardize_scores`].
+///
+/// # Notations
+///
+/// * $`x_{ij}`$: Raw score of the $`j`$-th system for the $`i`$-th topic.
+/// * $`\mu_i`$ and $`\sigma_i`$: Mean and unbiased standard deviation of the raw scores
+///   across the systems for the $`i`$-th topic.
+/// * $`z_{ij} = (x_{ij} - \mu_i) / \sigma_i`$: z-score, which is $`0`$ if $`\sigma_i`$ is zero or undefined.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::Standardization;
+///
+/// assert_eq!("z".parse(), Ok(Standardization::ZScore));
+/// assert_eq!("cdf".parse(), Ok(Standardization::Cdf));
+/// assert_eq!("ab".parse(), Ok(Standardization::LinearAb));
+/// ```
+///
+/// # References
+///
+/// * William Webber, Alistair Moffat, and Justin Zobel.
+///   [Score standardization for inter-collection comparison of retrieval systems](https://doi.org/10.1145/1390334.1390348).
+///   SIGIR 2008.
+/// * Tetsuya Sakai.
+///   [A simple and effective approach to score standardisation](https://doi.org/10.1145/2970398.2970399).
+///   ICTIR 2016.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Standardization {
+    /// z-score, $`z_{ij}`$.
+    ZScore,
+
+    /// Cumulative density of the standard normal distribution, $`\Phi(z_{ij})`$,
+    /// bounded to $`[0, 1]`$ (Webber et al.).
+    Cdf,
+
+    /// Linear transform, $`0.15 z_{ij} + 0.5`$,
+    /// mostly falling into $`[0, 1]`$ and keeping the differences in proportion to the z-scores (Sakai's std-AB).
+    LinearAb,
+}
+
+impl FromStr for Standardization {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "z" => Ok(Self::ZScore),
+            "cdf" => Ok(Self::Cdf),
+            "ab" => Ok(Self::LinearAb),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported standardization: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Standardization {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::ZScore => write!(f, "z"),
+            Self::Cdf => write!(f, "cdf"),
+            Self::LinearAb => write!(f, "ab"),
+        }
+    }
+}
+
+impl Standardization {
+    fn transform(self, z: f64) -> f64 {
+        match self {
+            Self::ZScore => z,
+            Self::Cdf => Normal::new(0.0, 1.0).unwrap().cdf(z),
+            Self::LinearAb => 0.15f64.mul_add(z, 0.5),
+        }
+    }
+}
+
+/// Standardizes the per-topic scores of the systems topic by topic,
+/// using the mean and standard deviation of the scores across the systems for each topic.
+///
+/// Raw scores of hard topics are close to zero for all the systems,
+/// so easy topics with large variances dominate the tests on raw scores.
+/// Standardization makes every topic contribute equally to the tests.
+///
+/// The statistics of a topic are computed over the systems having the topic,
+/// and the z-scores are zero for topics with a zero or undefined standard deviation.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::{standardize_scores, Standardization};
+///
+/// let map_a = [("q_1", 0.10), ("q_2", 0.90)].into();
+/// let map_b = [("q_1", 0.00), ("q_2", 0.50)].into();
+/// let map_c = [("q_1", 0.05), ("q_2", 0.70)].into();
+/// let standardized = standardize_scores(&[map_a, map_b, map_c], Standardization::ZScore)?;
+///
+/// // Both topics have the same z-scores, although the raw differences are much larger for q_2.
+/// assert_abs_diff_eq!(standardized[0]["q_1"], 1.0, epsilon = 1e-10);
+/// assert_abs_diff_eq!(standardized[0]["q_2"], 1.0, epsilon = 1e-10);
+/// assert_abs_diff_eq!(standardized[1]["q_1"], -1.0, epsilon = 1e-10);
+/// assert_abs_diff_eq!(standardized[2]["q_2"], 0.0, epsilon = 1e-10);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the input does not have at least two systems.
+pub fn standardize_scores<K>(
+    maps: &[BTreeMap<K, f64>],
+    method: Standardization,
+) -> Result<Vec<BTreeMap<K, f64>>>
+where
+    K: Clone + Eq + Ord,
+{
+    if maps.len() < 2 {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least two systems.".to_string(),
+        ));
+    }
+    let mut stats: BTreeMap<&K, (f64, f64)> = BTreeMap::new();
+    for topic in maps.iter().flat_map(|map| map.keys()) {
+        if stats.contains_key(topic) {
+            continue;
+        }
+        let scores = maps
+            .iter()
+            .filter_map(|map| map.get(topic).copied())
+            .collect::<Vec<_>>();
+        let n = scores.len() as f64;
+        let mean = scores.iter().sum::<f64>() / n;
+        let stddev = if scores.len() < 2 {
+            0.0
+        } else {
+            (scores.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        };
+        stats.insert(topic, (mean, stddev));
+    }
+    Ok(maps
+        .iter()
+        .map(|map| {
+            map.iter()
+                .map(|(topic, &x)| {
+                    let (mean, stddev) = stats[topic];
+                    let z = if stddev > 0.0 {
+                        (x - mean) / stddev
+                    } else {
+                        0.0
+                    };
+                    (topic.clone(), method.transform(z))
+                })
+                .collect()
+        })
+        .collect())
+}
 
 /// Converts two maps of scores, $`A`$ and $`B`$, into a vector of paired scores $`X`$:
 ///
@@ -89,6 +393,9 @@ where
     K: Clone + Eq + Ord + std::fmt::Display + 'a,
 {
     let maps = maps.into_iter().collect::<Vec<_>>();
+    if maps.is_empty() {
+        return Ok(vec![]);
+    }
     for i in 1..maps.len() {
         if maps[0].len() != maps[i].len() {
             return Err(ElinorError::InvalidArgument(format!(
@@ -115,9 +422,309 @@ where
     Ok(tuples)
 }
 
+/// Converts maps of scores into a vector of tupled scores, dropping the keys missing in any of the maps.
+///
+/// Unlike [`tuples_from_maps`], which returns an error for the missing keys,
+/// this performs listwise deletion (i.e., pairwise deletion for two maps).
+///
+/// Returns the tupled scores and the number of dropped keys.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::tuples_from_common_keys;
+///
+/// let map_a = [("a", 0.70), ("b", 0.30), ("c", 0.20)].into();
+/// let map_b = [("a", 0.50), ("c", 0.00), ("d", 0.40)].into();
+/// let (tuples, n_dropped) = tuples_from_common_keys([&map_a, &map_b]);
+/// assert_eq!(tuples, vec![vec![0.70, 0.50], vec![0.20, 0.00]]);
+/// assert_eq!(n_dropped, 2);
+/// ```
+pub fn tuples_from_common_keys<'a, I, K>(maps: I) -> (Vec<Vec<f64>>, usize)
+where
+    I: IntoIterator<Item = &'a BTreeMap<K, f64>>,
+    K: Clone + Eq + Ord + 'a,
+{
+    let maps = maps.into_iter().collect::<Vec<_>>();
+    let all_keys = maps
+        .iter()
+        .flat_map(|map| map.keys())
+        .collect::<std::collections::BTreeSet<_>>();
+    let mut tuples = vec![];
+    for key in &all_keys {
+        let tuple = maps
+            .iter()
+            .map(|map| map.get(*key).copied())
+            .collect::<Option<Vec<_>>>();
+        if let Some(tuple) = tuple {
+            tuples.push(tuple);
+        }
+    }
+    let n_dropped = all_keys.len() - tuples.len();
+    (tuples, n_dropped)
+}
+
+/// Converts two maps of scores and a map of weights into a vector of weighted paired scores.
+///
+/// The output is $`[(v^A_1, v^B_1, w_1), (v^A_2, v^B_2, w_2), \dots, (v^A_n, v^B_n, w_n)]`$,
+/// aligned by the keys as in [`pairs_from_maps`].
+///
+/// The output can be passed to `test_weighted` of
+/// [`StudentTTester`](student_t_test::StudentTTester),
+/// [`BootstrapTester`](bootstrap_test::BootstrapTester), and
+/// [`FisherRandomizationTester`](fisher_randomization_test::FisherRandomizationTester).
+/// Keys only in the map of weights are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::weighted_pairs_from_maps;
+///
+/// let map_a = [("a", 0.70), ("b", 0.30)].into();
+/// let map_b = [("a", 0.50), ("b", 0.10)].into();
+/// let weights = [("a", 2.0), ("b", 1.0), ("c", 1.0)].into();
+/// let samples = weighted_pairs_from_maps(&map_a, &map_b, &weights).unwrap();
+/// assert_eq!(samples, vec![(0.70, 0.50, 2.0), (0.30, 0.10, 1.0)]);
+/// ```
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if maps have different sets of keys.
+/// * [`ElinorError::MissingEntry`] if a key is missing in the map of weights.
+pub fn weighted_pairs_from_maps<K>(
+    map_a: &BTreeMap<K, f64>,
+    map_b: &BTreeMap<K, f64>,
+    weights: &BTreeMap<K, f64>,
+) -> Result<Vec<(f64, f64, f64)>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    let pairs = pairs_from_maps(map_a, map_b)?;
+    map_a
+        .keys()
+        .zip(pairs)
+        .map(|(key, (a, b))| {
+            weights.get(key).map(|&w| (a, b, w)).ok_or_else(|| {
+                ElinorError::MissingEntry(format!("The weight of {key} is missing."))
+            })
+        })
+        .collect()
+}
+
+/// Splits weighted paired samples into the differences and the weights,
+/// checking that the weights are finite, non-negative, and not all zero.
+pub(crate) fn weighted_differences<I>(samples: I) -> Result<(Vec<f64>, Vec<f64>)>
+where
+    I: IntoIterator<Item = (f64, f64, f64)>,
+{
+    let (diffs, weights): (Vec<f64>, Vec<f64>) =
+        samples.into_iter().map(|(x, y, w)| (x - y, w)).unzip();
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err(ElinorError::InvalidArgument(
+            "The weights must be finite and non-negative.".to_string(),
+        ));
+    }
+    if !diffs.is_empty() && weights.iter().all(|&w| w == 0.0) {
+        return Err(ElinorError::InvalidArgument(
+            "The weights must not be all zero.".to_string(),
+        ));
+    }
+    Ok((diffs, weights))
+}
+
+/// Returns the effective sample size of the weights, $`(\sum_i w_i)^2 / \sum_i w_i^2`$,
+/// which is the number of samples for equal weights.
+pub(crate) fn effective_sample_size(weights: &[f64]) -> f64 {
+    let sum = weights.iter().sum::<f64>();
+    let sum_sq = weights.iter().map(|w| w * w).sum::<f64>();
+    sum * sum / sum_sq
+}
+
+/// Converts two evaluations into a vector of paired scores,
+/// aligning the per-query scores by query ids as in [`pairs_from_maps`].
+///
+/// # Examples
+///
+/// ```
+/// use elinor::{Evaluation, Metric};
+/// use elinor::statistical_tests::pairs_from_evaluations;
+///
+/// let metric = Metric::AP { k: 0 };
+/// let eval_a = Evaluation::from_scores(metric, [("a", 0.70), ("b", 0.30)].into());
+/// let eval_b = Evaluation::from_scores(metric, [("a", 0.50), ("b", 0.10)].into());
+/// let pairs = pairs_from_evaluations(&eval_a, &eval_b).unwrap();
+/// assert_eq!(pairs, vec![(0.70, 0.50), (0.30, 0.10)]);
+/// ```
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if evaluations have different sets of query ids.
+pub fn pairs_from_evaluations<K>(
+    evaluation_a: &Evaluation<K>,
+    evaluation_b: &Evaluation<K>,
+) -> Result<Vec<(f64, f64)>>
+where
+    K: Clone + Eq + Ord + std::fmt::Display,
+{
+    pairs_from_maps(evaluation_a.scores(), evaluation_b.scores())
+}
+
+/// Converts evaluations into a vector of tupled scores,
+/// aligning the per-query scores by query ids as in [`tuples_from_maps`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if evaluations have different sets of query ids.
+pub fn tuples_from_evaluations<'a, I, K>(evaluations: I) -> Result<Vec<Vec<f64>>>
+where
+    I: IntoIterator<Item = &'a Evaluation<K>>,
+    K: Clone + Eq + Ord + std::fmt::Display + 'a,
+{
+    tuples_from_maps(evaluations.into_iter().map(|e| e.scores()))
+}
+
+/// Returns the `q`-quantile of sorted values by the nearest-rank method.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `q` is not in the range `[0, 1]`.
+pub(crate) fn quantile_of_sorted(sorted: &[f64], q: f64) -> Result<f64> {
+    if !(0.0..=1.0).contains(&q) {
+        return Err(ElinorError::InvalidArgument(
+            "The quantile must be in the range [0, 1].".to_string(),
+        ));
+    }
+    let i = ((sorted.len() - 1) as f64 * q).round() as usize;
+    Ok(sorted[i])
+}
+
+/// Returns the `q`-quantile of non-empty sorted values,
+/// linearly interpolated between the closest ranks.
+///
+/// `q` must be in the range `[0, 1]`.
+pub(crate) fn interpolated_quantile_of_sorted(sorted: &[f64], q: f64) -> f64 {
+    let pos = (sorted.len() - 1) as f64 * q;
+    let (i, j) = (pos.floor() as usize, pos.ceil() as usize);
+    (sorted[j] - sorted[i]).mul_add(pos - i as f64, sorted[i])
+}
+
+/// Returns $`P(T \le t)`$ for $`T`$ following the noncentral t-distribution
+/// with `df` degrees of freedom and noncentrality parameter `ncp`.
+///
+/// It integrates $`\Phi(t w / \sqrt{\nu} - \lambda)`$ over the chi distribution of $`w`$
+/// with $`\nu`$ degrees of freedom by Simpson's rule.
+pub(crate) fn noncentral_t_cdf(t: f64, df: f64, ncp: f64) -> f64 {
+    const N_INTERVALS: usize = 2000;
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let center = df.sqrt();
+    let lower = (center - 10.0).max(0.0);
+    let upper = center + 10.0;
+    let width = (upper - lower) / N_INTERVALS as f64;
+    let log_norm = (df / 2.0 - 1.0).mul_add(std::f64::consts::LN_2, ln_gamma(df / 2.0));
+    let integrand = |w: f64| {
+        // The density at zero is positive only for one degree of freedom.
+        let density = if w > 0.0 {
+            ((df - 1.0).mul_add(w.ln(), -(w * w / 2.0)) - log_norm).exp()
+        } else if df == 1.0 {
+            (-log_norm).exp()
+        } else {
+            0.0
+        };
+        normal.cdf(t * w / center - ncp) * density
+    };
+    let mut sum = integrand(lower) + integrand(upper);
+    for i in 1..N_INTERVALS {
+        let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * integrand(lower + width * i as f64);
+    }
+    (sum * width / 3.0).clamp(0.0, 1.0)
+}
+
+/// Maximum number of doublings to bracket the noncentrality parameter.
+const MAX_BRACKET_DOUBLINGS: usize = 64;
+
+/// Returns the two-sided confidence interval of the noncentrality parameter
+/// at a significance level $`\alpha`$, given an observed t-statistic with `df` degrees of freedom.
+///
+/// The bounds $`\lambda_L`$ and $`\lambda_U`$ are found by bisection so that
+/// $`P(T \le t \mid \lambda_L) = 1 - \alpha/2`$ and $`P(T \le t \mid \lambda_U) = \alpha/2`$.
+pub(crate) fn noncentrality_confidence_interval(
+    t_stat: f64,
+    df: f64,
+    significance_level: f64,
+) -> (f64, f64) {
+    let solve = |target: f64| {
+        // The CDF is decreasing in the noncentrality parameter.
+        // Widens the bracket by doubling, which reaches any finite bound within the iterations.
+        let (mut lower, mut upper) = (t_stat - 1.0, t_stat + 1.0);
+        for _ in 0..MAX_BRACKET_DOUBLINGS {
+            if noncentral_t_cdf(t_stat, df, lower) >= target {
+                break;
+            }
+            lower -= 2.0 * (upper - lower);
+        }
+        for _ in 0..MAX_BRACKET_DOUBLINGS {
+            if noncentral_t_cdf(t_stat, df, upper) <= target {
+                break;
+            }
+            upper += 2.0 * (upper - lower);
+        }
+        for _ in 0..100 {
+            let mid = (lower + upper) / 2.0;
+            if noncentral_t_cdf(t_stat, df, mid) > target {
+                lower = mid;
+            } else {
+                upper = mid;
+            }
+            if upper - lower < 1e-10 {
+                break;
+            }
+        }
+        (lower + upper) / 2.0
+    };
+    (
+        solve(1.0 - significance_level / 2.0),
+        solve(significance_level / 2.0),
+    )
+}
+
+/// Number of resamples sharing a random number generator in [`map_resamples`].
+const RESAMPLES_PER_RNG: usize = 256;
+
+/// Maps `f` over `n_resamples` resamples, in parallel if the `parallel` feature is enabled.
+///
+/// The resamples are split into chunks of a fixed size, each of which has its own generator
+/// seeded from `rng`, so that the results are the same regardless of the feature and the threads.
+pub(crate) fn map_resamples<T, F>(n_resamples: usize, rng: &mut StdRng, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&mut StdRng) -> T + Sync,
+{
+    let n_chunks = (n_resamples + RESAMPLES_PER_RNG - 1) / RESAMPLES_PER_RNG;
+    let seeds: Vec<(usize, u64)> = (0..n_chunks)
+        .map(|i| {
+            let size = RESAMPLES_PER_RNG.min(n_resamples - i * RESAMPLES_PER_RNG);
+            (size, rng.gen())
+        })
+        .collect();
+    let map_chunk = |&(size, seed): &(usize, u64)| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..size).map(|_| f(&mut rng)).collect::<Vec<_>>()
+    };
+    #[cfg(feature = "parallel")]
+    let chunks: Vec<Vec<T>> = {
+        use rayon::prelude::*;
+        seeds.par_iter().map(map_chunk).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let chunks: Vec<Vec<T>> = seeds.iter().map(map_chunk).collect();
+    chunks.into_iter().flatten().collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_abs_diff_eq;
 
     #[test]
     fn test_pairs_from_maps_different_keys() {
@@ -144,6 +751,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tuples_from_common_keys() {
+        let map_a = [("a", 0.70), ("b", 0.30), ("c", 0.20)].into();
+        let map_b = [("a", 0.50), ("b", 0.10), ("d", 0.00)].into();
+        let map_c = [("a", 0.60), ("b", 0.20), ("c", 0.10)].into();
+        let (tuples, n_dropped) = tuples_from_common_keys([&map_a, &map_b, &map_c]);
+        assert_eq!(tuples, vec![vec![0.70, 0.50, 0.60], vec![0.30, 0.10, 0.20]]);
+        assert_eq!(n_dropped, 2);
+
+        let (tuples, n_dropped) = tuples_from_common_keys([&map_a, &map_c]);
+        assert_eq!(tuples.len(), 3);
+        assert_eq!(n_dropped, 0);
+
+        let (tuples, n_dropped) = tuples_from_common_keys::<_, &str>([]);
+        assert!(tuples.is_empty());
+        assert_eq!(n_dropped, 0);
+    }
+
+    #[test]
+    fn test_weighted_pairs_from_maps_missing_weight() {
+        let map_a = [("a", 0.70), ("b", 0.30)].into();
+        let map_b = [("a", 0.50), ("b", 0.10)].into();
+        let weights = [("a", 1.0)].into();
+        assert_eq!(
+            weighted_pairs_from_maps(&map_a, &map_b, &weights),
+            Err(ElinorError::MissingEntry(
+                "The weight of b is missing.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_standardize_scores() {
+        let map_a = BTreeMap::from([("a", 0.70), ("b", 0.30), ("c", 0.20)]);
+        let map_b = BTreeMap::from([("a", 0.50), ("b", 0.30)]);
+        let map_c = BTreeMap::from([("a", 0.60), ("b", 0.30), ("c", 0.40)]);
+        let maps = [map_a, map_b, map_c];
+
+        let z = standardize_scores(&maps, Standardization::ZScore).unwrap();
+        assert_abs_diff_eq!(z[0]["a"], 1.0, epsilon = 1e-10);
+        assert_abs_diff_eq!(z[1]["a"], -1.0, epsilon = 1e-10);
+        assert_abs_diff_eq!(z[2]["a"], 0.0, epsilon = 1e-10);
+        // Constant scores.
+        assert_eq!(z[0]["b"], 0.0);
+        // Missing in the second system.
+        assert!(!z[1].contains_key("c"));
+        assert_abs_diff_eq!(z[0]["c"], -0.5f64.sqrt(), epsilon = 1e-10);
+        assert_abs_diff_eq!(z[2]["c"], 0.5f64.sqrt(), epsilon = 1e-10);
+
+        let cdf = standardize_scores(&maps, Standardization::Cdf).unwrap();
+        assert_abs_diff_eq!(cdf[0]["a"], 0.841344746, epsilon = 1e-6);
+        assert_abs_diff_eq!(cdf[1]["a"], 1.0 - 0.841344746, epsilon = 1e-6);
+        assert_abs_diff_eq!(cdf[0]["b"], 0.5);
+
+        let ab = standardize_scores(&maps, Standardization::LinearAb).unwrap();
+        assert_abs_diff_eq!(ab[0]["a"], 0.65, epsilon = 1e-10);
+        assert_abs_diff_eq!(ab[1]["a"], 0.35, epsilon = 1e-10);
+        assert_abs_diff_eq!(ab[0]["b"], 0.5);
+    }
+
+    #[test]
+    fn test_standardize_scores_single_system() {
+        let maps = [BTreeMap::from([("a", 0.70)])];
+        assert_eq!(
+            standardize_scores(&maps, Standardization::ZScore),
+            Err(ElinorError::InvalidArgument(
+                "The input must have at least two systems.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_standardization_from_str() {
+        for method in [
+            Standardization::ZScore,
+            Standardization::Cdf,
+            Standardization::LinearAb,
+        ] {
+            assert_eq!(method.to_string().parse(), Ok(method));
+        }
+        assert_eq!(
+            "t".parse::<Standardization>(),
+            Err(ElinorError::InvalidFormat(
+                "Unsupported standardization: t".to_string()
+            ))
+        );
+    }
+
     #[test]
     fn test_tuples_from_maps_single_map() {
         let map_a = [("a", 0.70), ("b", 0.30), ("c", 0.20)].into();
@@ -152,4 +847,95 @@ mod tests {
             Ok(vec![vec![0.70], vec![0.30], vec![0.20]])
         );
     }
+
+    #[test]
+    fn test_tuples_from_maps_empty() {
+        let maps: [&BTreeMap<&str, f64>; 0] = [];
+        assert_eq!(tuples_from_maps(maps), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_from_evaluations() {
+        let metric = crate::Metric::AP { k: 0 };
+        let eval_a =
+            Evaluation::from_scores(metric, [("b", 0.30), ("a", 0.70), ("c", 0.20)].into());
+        let eval_b =
+            Evaluation::from_scores(metric, [("c", 0.00), ("b", 0.10), ("a", 0.60)].into());
+        let eval_c =
+            Evaluation::from_scores(metric, [("a", 0.50), ("b", 0.20), ("c", 0.10)].into());
+        let pairs = pairs_from_evaluations(&eval_a, &eval_b).unwrap();
+        assert_eq!(
+            StudentTTest::from_evaluations(&eval_a, &eval_b)
+                .unwrap()
+                .mean(),
+            StudentTTest::from_paired_samples(pairs).unwrap().mean()
+        );
+        let evaluations = [eval_a, eval_b, eval_c];
+        let tuples = tuples_from_evaluations(&evaluations).unwrap();
+        assert_eq!(
+            TukeyHsdTest::from_evaluations(&evaluations)
+                .unwrap()
+                .effect_sizes(),
+            TukeyHsdTest::from_tupled_samples(tuples, 3)
+                .unwrap()
+                .effect_sizes()
+        );
+
+        let eval_d = Evaluation::from_scores(metric, [("a", 0.50), ("d", 0.20)].into());
+        assert!(StudentTTest::from_evaluations(&evaluations[0], &eval_d).is_err());
+        assert!(WilcoxonSignedRankTest::from_evaluations(&evaluations[0], &eval_d).is_err());
+    }
+
+    #[test]
+    fn test_map_resamples() {
+        for n_resamples in [0, 1, RESAMPLES_PER_RNG, RESAMPLES_PER_RNG * 2 + 1] {
+            let mut rng = StdRng::seed_from_u64(42);
+            let values = map_resamples(n_resamples, &mut rng, |rng| rng.gen::<u64>());
+            assert_eq!(values.len(), n_resamples);
+            let mut rng = StdRng::seed_from_u64(42);
+            let other = map_resamples(n_resamples, &mut rng, |rng| rng.gen::<u64>());
+            assert_eq!(values, other);
+        }
+    }
+
+    #[test]
+    fn test_alternative() {
+        for alternative in [
+            Alternative::TwoSided,
+            Alternative::Greater,
+            Alternative::Less,
+        ] {
+            assert_eq!(alternative.to_string().parse(), Ok(alternative));
+        }
+        assert!("two_sided".parse::<Alternative>().is_err());
+        assert_eq!(Alternative::TwoSided.orient(-1.5), 1.5);
+        assert_eq!(Alternative::Greater.orient(-1.5), -1.5);
+        assert_eq!(Alternative::Less.orient(-1.5), 1.5);
+    }
+
+    #[test]
+    fn test_noncentral_t_cdf() {
+        // Compared with the values computed by the numerical integration of mpmath.
+        assert_abs_diff_eq!(noncentral_t_cdf(2.0, 10.0, 1.0), 0.8076, epsilon = 1e-4);
+        assert_abs_diff_eq!(noncentral_t_cdf(-1.5, 3.0, 0.5), 0.0501, epsilon = 1e-4);
+        assert_abs_diff_eq!(noncentral_t_cdf(2.116, 19.0, 2.116), 0.4892, epsilon = 1e-4);
+
+        // The central case is the Student's t-distribution.
+        let t_dist = statrs::distribution::StudentsT::new(0.0, 1.0, 1.0).unwrap();
+        assert_abs_diff_eq!(
+            noncentral_t_cdf(1.5, 1.0, 0.0),
+            t_dist.cdf(1.5),
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn test_noncentrality_confidence_interval() {
+        let (lower, upper) = noncentrality_confidence_interval(2.0, 10.0, 0.05);
+        assert_abs_diff_eq!(noncentral_t_cdf(2.0, 10.0, lower), 0.975, epsilon = 1e-8);
+        assert_abs_diff_eq!(noncentral_t_cdf(2.0, 10.0, upper), 0.025, epsilon = 1e-8);
+        let (neg_lower, neg_upper) = noncentrality_confidence_interval(-2.0, 10.0, 0.05);
+        assert_abs_diff_eq!(neg_lower, -upper, epsilon = 1e-6);
+        assert_abs_diff_eq!(neg_upper, -lower, epsilon = 1e-6);
+    }
 }
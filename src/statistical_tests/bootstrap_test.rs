@@ -1,13 +1,23 @@
-//! Two-sided paired Bootstrap test.
+//! Paired Bootstrap test.
 use rand::rngs::StdRng;
 use rand::Rng;
 use rand::SeedableRng;
 
 use crate::errors::ElinorError;
 use crate::errors::Result;
+use crate::statistical_tests::map_resamples;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::quantile_of_sorted;
 use crate::statistical_tests::student_t_test::compute_t_stat;
+use crate::statistical_tests::student_t_test::compute_weighted_t_stat;
+use crate::statistical_tests::weighted_differences;
+use crate::statistical_tests::Alternative;
+use crate::statistical_tests::SignificanceTest;
+use crate::Evaluation;
 
-/// Two-sided paired Bootstrap test.
+/// Paired Bootstrap test.
+///
+/// It is two-sided by default. Use [`BootstrapTester`] to specify an [`Alternative`].
 ///
 /// # Examples
 ///
@@ -24,6 +34,10 @@ use crate::statistical_tests::student_t_test::compute_t_stat;
 /// let samples = a.into_iter().zip(b.into_iter());
 /// let result = BootstrapTest::from_paired_samples(samples)?;
 /// assert!((0.0..=1.0).contains(&result.p_value()));
+///
+/// // Percentile confidence interval at a 95% confidence level.
+/// let (ci95_btm, ci95_top) = result.confidence_interval(0.05)?;
+/// assert!(ci95_btm <= result.mean() && result.mean() <= ci95_top);
 /// # Ok(())
 /// # }
 /// ```
@@ -36,12 +50,17 @@ use crate::statistical_tests::student_t_test::compute_t_stat;
 /// * Tetsuya Sakai.
 ///   [Evaluating evaluation metrics based on the bootstrap](https://doi.org/10.1145/1148170.1148261).
 ///   SIGIR 2006.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct BootstrapTest {
     n_topics: usize,
     n_resamples: usize,
     random_state: u64,
+    alternative: Alternative,
+    mean: f64,
+    variance: f64,
     p_value: f64,
+    resampled_means: Vec<f64>,
+    null_t_stats: Vec<f64>,
 }
 
 impl BootstrapTest {
@@ -61,6 +80,22 @@ impl BootstrapTest {
         BootstrapTester::new().test(samples)
     }
 
+    /// Computes a bootstrap test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
     /// Number of topics, $`n`$.
     pub const fn n_topics(&self) -> usize {
         self.n_topics
@@ -76,22 +111,116 @@ impl BootstrapTest {
         self.random_state
     }
 
-    /// p-value for the two-sided test.
+    /// Alternative hypothesis of the test.
+    pub const fn alternative(&self) -> Alternative {
+        self.alternative
+    }
+
+    /// Mean of the samples.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \bar{x} = \frac{1}{n} \sum_{i=1}^{n} x_{i}
+    /// ```
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased population variance.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// V = \frac{1}{n-1} \sum_{i=1}^{n} (x_{i} - \bar{x})^{2}
+    /// ```
+    pub const fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    /// Sample effect size.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{ES} = \frac{\bar{x}}{\sqrt{V}}
+    /// ```
+    pub fn effect_size(&self) -> f64 {
+        self.mean / self.variance.sqrt()
+    }
+
+    /// p-value under the alternative hypothesis.
     pub const fn p_value(&self) -> f64 {
         self.p_value
     }
+
+    /// Percentile confidence interval at a given significance level $`\alpha`$,
+    /// formed by the $`\alpha/2`$ and $`1 - \alpha/2`$ quantiles of the resampled means.
+    ///
+    /// It is always two-sided, regardless of the alternative hypothesis.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        Ok((
+            quantile_of_sorted(&self.resampled_means, significance_level / 2.0)?,
+            quantile_of_sorted(&self.resampled_means, 1.0 - significance_level / 2.0)?,
+        ))
+    }
+
+    /// Quantile of the empirical null distribution of the t-statistics of the resamples,
+    /// oriented by the alternative hypothesis:
+    /// $`|t^{*}|`$ for two-sided, $`t^{*}`$ for greater, and $`-t^{*}`$ for less.
+    ///
+    /// For example, `null_quantile(0.95)` gives the critical value at a 5% significance level.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the quantile is not in the range `[0, 1]`.
+    pub fn null_quantile(&self, q: f64) -> Result<f64> {
+        quantile_of_sorted(&self.null_t_stats, q)
+    }
+}
+
+impl SignificanceTest for BootstrapTest {
+    fn method_name(&self) -> &'static str {
+        match self.alternative {
+            Alternative::TwoSided => "Two-sided paired Bootstrap test",
+            Alternative::Greater | Alternative::Less => "One-sided paired Bootstrap test",
+        }
+    }
+
+    fn p_value(&self) -> f64 {
+        Self::p_value(self)
+    }
+
+    fn effect_size(&self) -> f64 {
+        Self::effect_size(self)
+    }
+
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        Self::confidence_interval(self, significance_level)
+    }
 }
 
-/// Two-sided Bootstrap tester.
+/// Bootstrap tester.
 ///
 /// # Default parameters
 ///
 /// * `n_resamples`: `10000`
 /// * `random_state`: `None`
+/// * `alternative`: [`Alternative::TwoSided`]
 #[derive(Debug, Clone, Copy)]
 pub struct BootstrapTester {
     n_resamples: usize,
     random_state: Option<u64>,
+    alternative: Alternative,
 }
 
 impl Default for BootstrapTester {
@@ -106,6 +235,7 @@ impl BootstrapTester {
         Self {
             n_resamples: 10000,
             random_state: None,
+            alternative: Alternative::TwoSided,
         }
     }
 
@@ -123,57 +253,173 @@ impl BootstrapTester {
         self
     }
 
+    /// Sets the alternative hypothesis.
+    pub const fn with_alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
     /// Computes a bootstrap test for the samples.
     ///
     /// # Errors
     ///
     /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
     /// * [`ElinorError::Uncomputable`] if the variance is zero.
     pub fn test<I>(&self, samples: I) -> Result<BootstrapTest>
     where
         I: IntoIterator<Item = (f64, f64)>,
     {
         let samples: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
+        self.test_differences(samples, None)
+    }
+
+    /// Computes a bootstrap test for the samples weighted per topic (e.g., by traffic),
+    /// where each sample is a triple $`(a_{i}, b_{i}, w_{i})`$ of the paired scores and the weight.
+    ///
+    /// The topics are resampled together with their weights,
+    /// and the t-statistics and means of the resamples are weighted ones
+    /// as in [`StudentTTester::test_weighted`](crate::statistical_tests::student_t_test::StudentTTester::test_weighted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use approx::assert_abs_diff_eq;
+    /// use elinor::statistical_tests::bootstrap_test::BootstrapTester;
+    ///
+    /// let samples = vec![
+    ///     (0.70, 0.50, 3.0),
+    ///     (0.30, 0.10, 1.0),
+    ///     (0.20, 0.00, 1.0),
+    ///     (0.60, 0.20, 1.0),
+    ///     (0.40, 0.40, 2.0),
+    /// ];
+    /// let result = BootstrapTester::new().with_random_state(42).test_weighted(samples)?;
+    /// assert_abs_diff_eq!(result.mean(), (0.20 * 3.0 + 0.20 + 0.20 + 0.40 + 0.00) / 8.0);
+    /// assert!((0.0..=1.0).contains(&result.p_value()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the weights are negative, non-finite, or all zero.
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    /// * [`ElinorError::Uncomputable`] if the variance is zero.
+    pub fn test_weighted<I>(&self, samples: I) -> Result<BootstrapTest>
+    where
+        I: IntoIterator<Item = (f64, f64, f64)>,
+    {
+        let (samples, weights) = weighted_differences(samples)?;
+        self.test_differences(samples, Some(weights))
+    }
+
+    fn test_differences(
+        &self,
+        samples: Vec<f64>,
+        weights: Option<Vec<f64>>,
+    ) -> Result<BootstrapTest> {
         if samples.len() <= 1 {
             return Err(ElinorError::InvalidArgument(
                 "The input must have at least two samples.".to_string(),
             ));
         }
+        if !samples.iter().all(|x| x.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
 
         // Prepare the random number generator.
         let random_state = self
             .random_state
-            .map_or_else(|| rand::thread_rng().gen(), |seed| seed);
+            .unwrap_or_else(|| rand::thread_rng().gen());
         let mut rng = StdRng::seed_from_u64(random_state);
 
         // Compute the t-statistic for the original samples.
-        let (t_stat, mean, _) = compute_t_stat(&samples)?;
+        let (t_stat, mean, variance) = match &weights {
+            Some(weights) => compute_weighted_t_stat(&samples, weights)?,
+            None => compute_t_stat(&samples)?,
+        };
 
         // Shift the samples to have a mean of zero.
         let samples: Vec<f64> = samples.iter().map(|x| x - mean).collect();
 
-        // Perform the bootstrap test.
-        let mut count: usize = 0;
-        for _ in 0..self.n_resamples {
-            let resampled: Vec<f64> = (0..samples.len())
-                .map(|_| samples[rng.gen_range(0..samples.len())])
+        let results = map_resamples(self.n_resamples, &mut rng, |rng| {
+            let indices: Vec<usize> = (0..samples.len())
+                .map(|_| rng.gen_range(0..samples.len()))
                 .collect();
+            let resampled: Vec<f64> = indices.iter().map(|&i| samples[i]).collect();
             // If samples.len() is small, the variance may be zero.
             // In that unfortunate case, we skip the counting.
-            let (resampled_t_stat, _, _) = compute_t_stat(&resampled).unwrap_or((0.0, 0.0, 0.0));
-            if resampled_t_stat.abs() >= t_stat.abs() {
-                count += 1;
-            }
-        }
+            let (resampled_t_stat, resampled_mean) = weights.as_ref().map_or_else(
+                || {
+                    let (t_stat, _, _) = compute_t_stat(&resampled).unwrap_or((0.0, 0.0, 0.0));
+                    (t_stat, resampled.iter().sum::<f64>() / samples.len() as f64)
+                },
+                |weights| {
+                    let weights: Vec<f64> = indices.iter().map(|&i| weights[i]).collect();
+                    let (t_stat, _, _) =
+                        compute_weighted_t_stat(&resampled, &weights).unwrap_or((0.0, 0.0, 0.0));
+                    let sum = weights.iter().sum::<f64>();
+                    let weighted_sum = resampled
+                        .iter()
+                        .zip(&weights)
+                        .map(|(x, w)| x * w)
+                        .sum::<f64>();
+                    (t_stat, if sum > 0.0 { weighted_sum / sum } else { 0.0 })
+                },
+            );
+            // Shift back the resampled mean to estimate the confidence interval.
+            (
+                self.alternative.orient(resampled_t_stat),
+                resampled_mean + mean,
+            )
+        });
+
+        // Perform the bootstrap test.
+        let t_stat = self.alternative.orient(t_stat);
+        let count = results
+            .iter()
+            .filter(|&&(null_t_stat, _)| null_t_stat >= t_stat)
+            .count();
+        let (mut null_t_stats, mut resampled_means): (Vec<f64>, Vec<f64>) =
+            results.into_iter().unzip();
         let p_value = count as f64 / self.n_resamples as f64;
+        resampled_means.sort_by(f64::total_cmp);
+        null_t_stats.sort_by(f64::total_cmp);
 
         Ok(BootstrapTest {
             n_topics: samples.len(),
             n_resamples: self.n_resamples,
             random_state,
+            alternative: self.alternative,
+            mean,
+            variance,
             p_value,
+            resampled_means,
+            null_t_stats,
         })
     }
+
+    /// Computes a bootstrap test for the per-query scores of two evaluations,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<BootstrapTest>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
 }
 
 #[cfg(test)]
@@ -182,6 +428,7 @@ mod tests {
 
     use super::*;
     use approx::relative_eq;
+    use rstest::*;
 
     #[test]
     fn test_bootstrap_test_from_samples_empty() {
@@ -203,6 +450,18 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case::nan_a(vec![(f64::NAN, 0.0), (1.0, 0.0)])]
+    #[case::nan_b(vec![(1.0, f64::NAN), (1.0, 0.0)])]
+    #[case::infinity(vec![(f64::INFINITY, 0.0), (1.0, 0.0)])]
+    fn test_bootstrap_test_from_samples_non_finite(#[case] samples: Vec<(f64, f64)>) {
+        let result = BootstrapTest::from_paired_samples(samples);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite samples.".to_string())
+        );
+    }
+
     #[test]
     fn test_bootstrap_test_from_samples_zero_variance() {
         let samples = vec![(1.0, 0.0), (1.0, 0.0)];
@@ -224,6 +483,39 @@ mod tests {
         assert_eq!(result.random_state(), 42);
     }
 
+    #[test]
+    fn test_bootstrap_test_confidence_interval() {
+        let tester = BootstrapTester::new().with_random_state(42);
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let result = tester.test(samples).unwrap();
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert!(ci95_btm <= result.mean() && result.mean() <= ci95_top);
+        let (ci99_btm, ci99_top) = result.confidence_interval(0.01).unwrap();
+        assert!(ci99_btm <= ci95_btm && ci95_top <= ci99_top);
+        assert_eq!(
+            result.confidence_interval(0.0).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_test_null_quantile() {
+        let tester = BootstrapTester::new().with_random_state(42);
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let result = tester.test(samples).unwrap();
+        let t_stat = compute_t_stat(&[0.2, 0.2, 0.2, 0.4, 0.0]).unwrap().0;
+        // The observed t-statistic exceeds the critical value iff p < alpha.
+        let critical = result.null_quantile(0.95).unwrap();
+        assert_eq!(t_stat > critical, result.p_value() < 0.05);
+        assert!(result.null_quantile(0.0).unwrap() <= critical);
+        assert_eq!(
+            result.null_quantile(1.1).unwrap_err(),
+            ElinorError::InvalidArgument("The quantile must be in the range [0, 1].".to_string())
+        );
+    }
+
     #[test]
     fn test_bootstrap_tester_with_random_state_consistency() {
         let samples = vec![(1.0, 0.0), (0.0, 1.0), (1.0, 3.0)];
@@ -237,4 +529,61 @@ mod tests {
         let x = p_values[0];
         assert!(p_values.iter().all(|&y| relative_eq!(x, y)));
     }
+
+    #[test]
+    fn test_bootstrap_tester_alternative() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let test = |alternative| {
+            BootstrapTester::new()
+                .with_random_state(42)
+                .with_alternative(alternative)
+                .test(samples.clone())
+                .unwrap()
+        };
+        let two_sided = test(Alternative::TwoSided);
+        let greater = test(Alternative::Greater);
+        let less = test(Alternative::Less);
+        assert_eq!(greater.alternative(), Alternative::Greater);
+        // The mean difference is positive.
+        assert!(greater.p_value() <= two_sided.p_value());
+        assert!(greater.p_value() < less.p_value());
+        assert_eq!(
+            SignificanceTest::method_name(&less),
+            "One-sided paired Bootstrap test"
+        );
+    }
+
+    #[test]
+    fn test_bootstrap_tester_weighted_equal_weights() {
+        let a = vec![0.70, 0.30, 0.20, 0.60, 0.40, 0.50];
+        let b = vec![0.50, 0.10, 0.00, 0.20, 0.40, 0.60];
+        let tester = BootstrapTester::new().with_random_state(42);
+        let unweighted = tester.test(a.iter().copied().zip(b.clone())).unwrap();
+        let samples = a.into_iter().zip(b).map(|(x, y)| (x, y, 2.0));
+        let weighted = tester.test_weighted(samples).unwrap();
+        assert!(relative_eq!(weighted.mean(), unweighted.mean()));
+        assert!(relative_eq!(weighted.variance(), unweighted.variance()));
+        // Rounding errors can flip the counting of resamples whose t-statistics tie with the original one.
+        assert!(relative_eq!(
+            weighted.p_value(),
+            unweighted.p_value(),
+            epsilon = 1e-3
+        ));
+        let (btm, top) = weighted.confidence_interval(0.05).unwrap();
+        let (expected_btm, expected_top) = unweighted.confidence_interval(0.05).unwrap();
+        assert!(relative_eq!(btm, expected_btm, epsilon = 1e-10));
+        assert!(relative_eq!(top, expected_top, epsilon = 1e-10));
+    }
+
+    #[test]
+    fn test_bootstrap_tester_weighted_invalid() {
+        assert_eq!(
+            BootstrapTester::new()
+                .test_weighted(vec![(0.1, 0.0, 1.0), (0.2, 0.0, f64::NAN)])
+                .unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The weights must be finite and non-negative.".to_string()
+            )
+        );
+    }
 }
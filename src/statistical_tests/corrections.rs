@@ -0,0 +1,305 @@
+//! Multiple-comparison corrections of p-values.
+//!
+//! When many hypotheses are tested at once (e.g., many metrics or many pairs of systems),
+//! some of them are likely to be significant by chance.
+//! This module provides adjustments of p-values to control the family-wise error rate
+//! ([Bonferroni](Correction::Bonferroni) and [Holm](Correction::Holm))
+//! or the false discovery rate ([Benjamini-Hochberg](Correction::BenjaminiHochberg)).
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use approx::assert_abs_diff_eq;
+//! use elinor::statistical_tests::corrections::Correction;
+//!
+//! let p_values = vec![0.01, 0.04, 0.03];
+//!
+//! let adjusted = Correction::Bonferroni.adjust(&p_values)?;
+//! assert_abs_diff_eq!(adjusted[0], 0.03, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[1], 0.12, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[2], 0.09, epsilon = 1e-10);
+//!
+//! let adjusted = Correction::Holm.adjust(&p_values)?;
+//! assert_abs_diff_eq!(adjusted[0], 0.03, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[1], 0.06, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[2], 0.06, epsilon = 1e-10);
+//!
+//! let adjusted = Correction::BenjaminiHochberg.adjust(&p_values)?;
+//! assert_abs_diff_eq!(adjusted[0], 0.03, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[1], 0.04, epsilon = 1e-10);
+//! assert_abs_diff_eq!(adjusted[2], 0.04, epsilon = 1e-10);
+//! # Ok(())
+//! # }
+//! ```
+use std::str::FromStr;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+
+/// Method of multiple-comparison corrections.
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::corrections::Correction;
+///
+/// assert_eq!("bonferroni".parse(), Ok(Correction::Bonferroni));
+/// assert_eq!("holm".parse(), Ok(Correction::Holm));
+/// assert_eq!("bh".parse(), Ok(Correction::BenjaminiHochberg));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    /// Bonferroni correction, controlling the family-wise error rate.
+    Bonferroni,
+
+    /// Holm's step-down correction, controlling the family-wise error rate
+    /// and uniformly more powerful than Bonferroni.
+    Holm,
+
+    /// Benjamini-Hochberg correction, controlling the false discovery rate.
+    BenjaminiHochberg,
+}
+
+impl FromStr for Correction {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bonferroni" => Ok(Self::Bonferroni),
+            "holm" => Ok(Self::Holm),
+            "bh" => Ok(Self::BenjaminiHochberg),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported correction: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Correction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Bonferroni => write!(f, "bonferroni"),
+            Self::Holm => write!(f, "holm"),
+            Self::BenjaminiHochberg => write!(f, "bh"),
+        }
+    }
+}
+
+impl Correction {
+    /// Adjusts the p-values, returning them in the input order.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `[0, 1]`.
+    pub fn adjust(&self, p_values: &[f64]) -> Result<Vec<f64>> {
+        match self {
+            Self::Bonferroni => bonferroni(p_values),
+            Self::Holm => holm(p_values),
+            Self::BenjaminiHochberg => benjamini_hochberg(p_values),
+        }
+    }
+
+    /// Adjusts the p-values in a symmetric matrix for all combinations of systems,
+    /// such as [`RandomizedTukeyHsdTest::p_values`](crate::statistical_tests::RandomizedTukeyHsdTest::p_values).
+    ///
+    /// The hypotheses are the elements above the diagonal,
+    /// and the adjusted values are mirrored below the diagonal.
+    /// The diagonal elements are kept.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the matrix is not square.
+    /// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `[0, 1]`.
+    pub fn adjust_matrix(&self, p_values: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+        let m = p_values.len();
+        if p_values.iter().any(|row| row.len() != m) {
+            return Err(ElinorError::InvalidArgument(
+                "The matrix of p-values must be square.".to_string(),
+            ));
+        }
+        let indices = (0..m)
+            .flat_map(|i| (i + 1..m).map(move |j| (i, j)))
+            .collect::<Vec<_>>();
+        let flat = indices
+            .iter()
+            .map(|&(i, j)| p_values[i][j])
+            .collect::<Vec<_>>();
+        let adjusted = self.adjust(&flat)?;
+        let mut matrix = p_values.to_vec();
+        for ((i, j), p) in indices.into_iter().zip(adjusted) {
+            matrix[i][j] = p;
+            matrix[j][i] = p;
+        }
+        Ok(matrix)
+    }
+}
+
+/// Adjusts the p-values by the Bonferroni correction.
+///
+/// # Formula
+///
+/// ```math
+/// \tilde{p}_i = \min(1, m p_i)
+/// ```
+///
+/// where $`m`$ is the number of p-values.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `[0, 1]`.
+pub fn bonferroni(p_values: &[f64]) -> Result<Vec<f64>> {
+    validate_p_values(p_values)?;
+    let m = p_values.len() as f64;
+    Ok(p_values.iter().map(|&p| (p * m).min(1.0)).collect())
+}
+
+/// Adjusts the p-values by Holm's step-down correction.
+///
+/// # Formula
+///
+/// ```math
+/// \tilde{p}_{(i)} = \max_{j \le i} \min(1, (m - j + 1) p_{(j)})
+/// ```
+///
+/// where $`p_{(1)} \le p_{(2)} \le \dots \le p_{(m)}`$ are the sorted p-values.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `[0, 1]`.
+pub fn holm(p_values: &[f64]) -> Result<Vec<f64>> {
+    validate_p_values(p_values)?;
+    let m = p_values.len();
+    let order = sorted_indices(p_values);
+    let mut adjusted = vec![0.0; m];
+    let mut running_max: f64 = 0.0;
+    for (rank, &i) in order.iter().enumerate() {
+        running_max = running_max.max(((m - rank) as f64 * p_values[i]).min(1.0));
+        adjusted[i] = running_max;
+    }
+    Ok(adjusted)
+}
+
+/// Adjusts the p-values by the Benjamini-Hochberg correction.
+///
+/// # Formula
+///
+/// ```math
+/// \tilde{p}_{(i)} = \min_{j \ge i} \min\left(1, \frac{m}{j} p_{(j)}\right)
+/// ```
+///
+/// where $`p_{(1)} \le p_{(2)} \le \dots \le p_{(m)}`$ are the sorted p-values.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `[0, 1]`.
+pub fn benjamini_hochberg(p_values: &[f64]) -> Result<Vec<f64>> {
+    validate_p_values(p_values)?;
+    let m = p_values.len();
+    let order = sorted_indices(p_values);
+    let mut adjusted = vec![0.0; m];
+    let mut running_min: f64 = 1.0;
+    for (rank, &i) in order.iter().enumerate().rev() {
+        running_min = running_min.min(m as f64 / (rank + 1) as f64 * p_values[i]);
+        adjusted[i] = running_min;
+    }
+    Ok(adjusted)
+}
+
+fn validate_p_values(p_values: &[f64]) -> Result<()> {
+    if p_values.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        return Err(ElinorError::InvalidArgument(
+            "The p-values must be in the range [0, 1].".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the indices of the p-values in ascending order, where ties keep the input order.
+fn sorted_indices(p_values: &[f64]) -> Vec<usize> {
+    let mut order = (0..p_values.len()).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| p_values[i].total_cmp(&p_values[j]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    // Compared with p.adjust in R.
+    const P_VALUES: [f64; 6] = [0.01, 0.02, 0.03, 0.04, 0.05, 0.5];
+
+    #[test]
+    fn test_bonferroni() {
+        let adjusted = bonferroni(&P_VALUES).unwrap();
+        let expected = [0.06, 0.12, 0.18, 0.24, 0.30, 1.00];
+        for (a, e) in adjusted.iter().zip(expected) {
+            assert_abs_diff_eq!(*a, e, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_holm() {
+        let adjusted = holm(&P_VALUES).unwrap();
+        let expected = [0.06, 0.10, 0.12, 0.12, 0.12, 0.50];
+        for (a, e) in adjusted.iter().zip(expected) {
+            assert_abs_diff_eq!(*a, e, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_benjamini_hochberg() {
+        let adjusted = benjamini_hochberg(&P_VALUES).unwrap();
+        let expected = [0.06, 0.06, 0.06, 0.06, 0.06, 0.50];
+        for (a, e) in adjusted.iter().zip(expected) {
+            assert_abs_diff_eq!(*a, e, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_adjust_empty() {
+        assert_eq!(Correction::Holm.adjust(&[]).unwrap(), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn test_adjust_invalid_p_value() {
+        assert_eq!(
+            Correction::Bonferroni.adjust(&[0.5, 1.5]),
+            Err(ElinorError::InvalidArgument(
+                "The p-values must be in the range [0, 1].".to_string()
+            ))
+        );
+        assert!(Correction::Holm.adjust(&[f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn test_adjust_matrix() {
+        let p_values = vec![
+            vec![1.00, 0.01, 0.04],
+            vec![0.01, 1.00, 0.03],
+            vec![0.04, 0.03, 1.00],
+        ];
+        let adjusted = Correction::Holm.adjust_matrix(&p_values).unwrap();
+        let expected = vec![
+            vec![1.00, 0.03, 0.06],
+            vec![0.03, 1.00, 0.06],
+            vec![0.06, 0.06, 1.00],
+        ];
+        for (row, expected_row) in adjusted.iter().zip(expected) {
+            for (a, e) in row.iter().zip(expected_row) {
+                assert_abs_diff_eq!(*a, e, epsilon = 1e-10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adjust_matrix_not_square() {
+        assert_eq!(
+            Correction::Holm.adjust_matrix(&[vec![1.0, 0.5]]),
+            Err(ElinorError::InvalidArgument(
+                "The matrix of p-values must be square.".to_string()
+            ))
+        );
+    }
+}
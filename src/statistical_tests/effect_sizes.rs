@@ -0,0 +1,254 @@
+//! Qualitative interpretation of effect sizes.
+//!
+//! Effect sizes are often reported with qualitative labels, such as "small" or "large",
+//! following the conventional thresholds by Cohen.
+//! This module provides [`EffectSizeBands`] to classify effect sizes into [`EffectSizeLabel`]s
+//! with configurable thresholds.
+//!
+//! Note that the thresholds are conventions for the behavioral sciences,
+//! and what effect size is meaningful depends on the task and the metric.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::statistical_tests::effect_sizes::{EffectSizeBands, EffectSizeLabel};
+//!
+//! let bands = EffectSizeBands::cohen_d();
+//! assert_eq!(bands.label(0.1), EffectSizeLabel::Negligible);
+//! assert_eq!(bands.label(0.3), EffectSizeLabel::Small);
+//! assert_eq!(bands.label(-0.6), EffectSizeLabel::Medium);
+//! assert_eq!(bands.label(1.2), EffectSizeLabel::Large);
+//!
+//! let bands = EffectSizeBands::new(0.1, 0.2, 0.3)?;
+//! assert_eq!(bands.label(0.3), EffectSizeLabel::Large);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! * Jacob Cohen.
+//!   Statistical Power Analysis for the Behavioral Sciences (2nd ed.).
+//!   Lawrence Erlbaum Associates, 1988.
+use std::str::FromStr;
+
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+
+/// Qualitative label of an effect size.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EffectSizeLabel {
+    /// Below the small threshold.
+    Negligible,
+
+    /// At least the small threshold.
+    Small,
+
+    /// At least the medium threshold.
+    Medium,
+
+    /// At least the large threshold.
+    Large,
+}
+
+impl FromStr for EffectSizeLabel {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "negligible" => Ok(Self::Negligible),
+            "small" => Ok(Self::Small),
+            "medium" => Ok(Self::Medium),
+            "large" => Ok(Self::Large),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported effect size label: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for EffectSizeLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Negligible => write!(f, "negligible"),
+            Self::Small => write!(f, "small"),
+            Self::Medium => write!(f, "medium"),
+            Self::Large => write!(f, "large"),
+        }
+    }
+}
+
+/// Thresholds of the absolute effect sizes for the small, medium, and large labels.
+///
+/// It can be parsed from and displayed as three comma-separated thresholds, e.g., `0.2,0.5,0.8`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectSizeBands {
+    small: f64,
+    medium: f64,
+    large: f64,
+}
+
+impl Default for EffectSizeBands {
+    fn default() -> Self {
+        Self::cohen_d()
+    }
+}
+
+impl EffectSizeBands {
+    /// Creates bands with the thresholds.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the thresholds are not finite or not in `0 < small < medium < large`.
+    pub fn new(small: f64, medium: f64, large: f64) -> Result<Self> {
+        if !(large.is_finite() && 0.0 < small && small < medium && medium < large) {
+            return Err(ElinorError::InvalidArgument(format!(
+                "The thresholds must be finite and satisfy 0 < small < medium < large, but got {small}, {medium}, and {large}."
+            )));
+        }
+        Ok(Self {
+            small,
+            medium,
+            large,
+        })
+    }
+
+    /// Cohen's thresholds for standardized mean differences (d): 0.2, 0.5, and 0.8.
+    ///
+    /// They are suitable for the effect sizes of [`StudentTTest`](crate::statistical_tests::StudentTTest)
+    /// and [`TukeyHsdTest`](crate::statistical_tests::TukeyHsdTest).
+    pub const fn cohen_d() -> Self {
+        Self {
+            small: 0.2,
+            medium: 0.5,
+            large: 0.8,
+        }
+    }
+
+    /// Cohen's thresholds for correlations (r): 0.1, 0.3, and 0.5.
+    ///
+    /// They are suitable for the effect sizes of
+    /// [`WilcoxonSignedRankTest`](crate::statistical_tests::WilcoxonSignedRankTest)
+    /// and [`SignTest`](crate::statistical_tests::SignTest).
+    pub const fn cohen_r() -> Self {
+        Self {
+            small: 0.1,
+            medium: 0.3,
+            large: 0.5,
+        }
+    }
+
+    /// Threshold for the small label.
+    pub const fn small(&self) -> f64 {
+        self.small
+    }
+
+    /// Threshold for the medium label.
+    pub const fn medium(&self) -> f64 {
+        self.medium
+    }
+
+    /// Threshold for the large label.
+    pub const fn large(&self) -> f64 {
+        self.large
+    }
+
+    /// Returns the label of the effect size, judged by its absolute value.
+    ///
+    /// NaN is labeled as [`EffectSizeLabel::Negligible`].
+    pub fn label(&self, effect_size: f64) -> EffectSizeLabel {
+        let effect_size = effect_size.abs();
+        if effect_size >= self.large {
+            EffectSizeLabel::Large
+        } else if effect_size >= self.medium {
+            EffectSizeLabel::Medium
+        } else if effect_size >= self.small {
+            EffectSizeLabel::Small
+        } else {
+            EffectSizeLabel::Negligible
+        }
+    }
+}
+
+impl FromStr for EffectSizeBands {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let thresholds = s
+            .split(',')
+            .map(|t| t.trim().parse::<f64>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| ElinorError::InvalidFormat(format!("Invalid effect size bands: {s}")))?;
+        let [small, medium, large] = thresholds[..] else {
+            return Err(ElinorError::InvalidFormat(format!(
+                "Effect size bands must have three thresholds, but got: {s}"
+            )));
+        };
+        Self::new(small, medium, large)
+    }
+}
+
+impl std::fmt::Display for EffectSizeBands {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.small, self.medium, self.large)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effect_size_bands_label() {
+        let bands = EffectSizeBands::cohen_r();
+        assert_eq!(bands.label(0.0), EffectSizeLabel::Negligible);
+        assert_eq!(bands.label(0.1), EffectSizeLabel::Small);
+        assert_eq!(bands.label(-0.3), EffectSizeLabel::Medium);
+        assert_eq!(bands.label(0.5), EffectSizeLabel::Large);
+        assert_eq!(bands.label(f64::NAN), EffectSizeLabel::Negligible);
+    }
+
+    #[test]
+    fn test_effect_size_bands_new_invalid() {
+        assert!(EffectSizeBands::new(0.0, 0.5, 0.8).is_err());
+        assert!(EffectSizeBands::new(0.5, 0.5, 0.8).is_err());
+        assert!(EffectSizeBands::new(0.2, 0.5, f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_effect_size_bands_from_str() {
+        assert_eq!("0.2,0.5,0.8".parse(), Ok(EffectSizeBands::cohen_d()));
+        assert_eq!(EffectSizeBands::cohen_r().to_string(), "0.1,0.3,0.5");
+        assert_eq!(
+            "0.2,0.5".parse::<EffectSizeBands>(),
+            Err(ElinorError::InvalidFormat(
+                "Effect size bands must have three thresholds, but got: 0.2,0.5".to_string()
+            ))
+        );
+        assert!("a,b,c".parse::<EffectSizeBands>().is_err());
+        assert!("0.8,0.5,0.2".parse::<EffectSizeBands>().is_err());
+    }
+
+    #[test]
+    fn test_effect_size_label_from_str() {
+        for label in [
+            EffectSizeLabel::Negligible,
+            EffectSizeLabel::Small,
+            EffectSizeLabel::Medium,
+            EffectSizeLabel::Large,
+        ] {
+            assert_eq!(label.to_string().parse(), Ok(label));
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! Paired Fisher's randomization test.
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::map_resamples;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::quantile_of_sorted;
+use crate::statistical_tests::weighted_differences;
+use crate::statistical_tests::Alternative;
+use crate::statistical_tests::SignificanceTest;
+use crate::Evaluation;
+
+/// Paired Fisher's randomization test.
+///
+/// Under the null hypothesis, the labels of the two systems are exchangeable for each topic,
+/// i.e., the sign of each paired difference is flipped with probability 0.5.
+/// It is two-sided by default. Use [`FisherRandomizationTester`] to specify an [`Alternative`].
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::Alternative;
+/// use elinor::statistical_tests::fisher_randomization_test::FisherRandomizationTester;
+///
+/// let a = vec![0.70, 0.30, 0.20, 0.60, 0.40];
+/// let b = vec![0.50, 0.10, 0.00, 0.20, 0.40];
+///
+/// let samples = a.into_iter().zip(b);
+/// let result = FisherRandomizationTester::new()
+///     .with_n_iters(1000)
+///     .with_random_state(42)
+///     .with_alternative(Alternative::Greater)
+///     .test(samples)?;
+/// assert_eq!(result.n_topics(), 5);
+/// assert_abs_diff_eq!(result.mean(), 0.2);
+/// assert!((0.0..=1.0).contains(&result.p_value()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # References
+///
+/// * Mark D. Smucker, James Allan, and Ben Carterette.
+///   [A comparison of statistical significance tests for information retrieval evaluation](https://doi.org/10.1145/1321440.1321528).
+///   CIKM 2007.
+#[derive(Debug, Clone)]
+pub struct FisherRandomizationTest {
+    n_topics: usize,
+    n_iters: usize,
+    random_state: u64,
+    alternative: Alternative,
+    mean: f64,
+    null_stddev: f64,
+    p_value: f64,
+    null_means: Vec<f64>,
+}
+
+impl FisherRandomizationTest {
+    /// Computes a randomization test for $`n`$ samples $`x_{1},x_{2},\dots,x_{n}`$,
+    /// where $`x_{i} = a_{i} - b_{i}`$ for given paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
+    ///
+    /// It uses the default parameters defined in [`FisherRandomizationTester`].
+    /// To customize the parameters, use [`FisherRandomizationTester`].
+    ///
+    /// # Errors
+    ///
+    /// See [`FisherRandomizationTester::test`].
+    pub fn from_paired_samples<I>(samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        FisherRandomizationTester::new().test(samples)
+    }
+
+    /// Computes a randomization test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
+    /// Number of topics, $`n`$.
+    pub const fn n_topics(&self) -> usize {
+        self.n_topics
+    }
+
+    /// Number of iterations.
+    pub const fn n_iters(&self) -> usize {
+        self.n_iters
+    }
+
+    /// Random state used for the randomization.
+    pub const fn random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    /// Alternative hypothesis of the test.
+    pub const fn alternative(&self) -> Alternative {
+        self.alternative
+    }
+
+    /// Observed mean difference.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \bar{x} = \frac{1}{n} \sum_{i=1}^{n} x_{i}
+    /// ```
+    pub const fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Sample effect size, standardized by the randomization distribution.
+    ///
+    /// If all the differences are zero, it is not finite.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{ES} = \frac{\bar{x}}{\sigma^{*}}, \quad \sigma^{*} = \frac{1}{n} \sqrt{\sum_{i=1}^{n} x_{i}^{2}}
+    /// ```
+    ///
+    /// where $`\sigma^{*}`$ is the standard deviation of the mean difference under random sign flips.
+    pub fn effect_size(&self) -> f64 {
+        self.mean / self.null_stddev
+    }
+
+    /// p-value under the alternative hypothesis,
+    /// i.e., the fraction of the iterations whose mean difference is at least as extreme as the observed one.
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Confidence interval of the mean difference at a given significance level $`\alpha`$,
+    /// formed by $`\bar{x} \pm d^{*}_{1-\alpha}`$,
+    /// where $`d^{*}_{1-\alpha}`$ is the $`1-\alpha`$ quantile of the absolute mean differences over the iterations.
+    ///
+    /// It is always two-sided, regardless of the alternative hypothesis.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let critical = quantile_of_sorted(
+            &self.oriented_null_means(Alternative::TwoSided),
+            1.0 - significance_level,
+        )?;
+        Ok((self.mean - critical, self.mean + critical))
+    }
+
+    /// Quantile of the randomization distribution of the mean differences,
+    /// oriented by the alternative hypothesis:
+    /// $`|\bar{x}^{*}|`$ for two-sided, $`\bar{x}^{*}`$ for greater, and $`-\bar{x}^{*}`$ for less.
+    ///
+    /// For example, `null_quantile(0.95)` gives the critical difference at a 5% significance level.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the quantile is not in the range `[0, 1]`.
+    pub fn null_quantile(&self, q: f64) -> Result<f64> {
+        quantile_of_sorted(&self.oriented_null_means(self.alternative), q)
+    }
+
+    fn oriented_null_means(&self, alternative: Alternative) -> Vec<f64> {
+        let mut null_means: Vec<f64> = self
+            .null_means
+            .iter()
+            .map(|&x| alternative.orient(x))
+            .collect();
+        null_means.sort_by(f64::total_cmp);
+        null_means
+    }
+}
+
+impl SignificanceTest for FisherRandomizationTest {
+    fn method_name(&self) -> &'static str {
+        match self.alternative {
+            Alternative::TwoSided => "Two-sided paired Fisher's randomization test",
+            Alternative::Greater | Alternative::Less => {
+                "One-sided paired Fisher's randomization test"
+            }
+        }
+    }
+
+    fn p_value(&self) -> f64 {
+        Self::p_value(self)
+    }
+
+    fn effect_size(&self) -> f64 {
+        Self::effect_size(self)
+    }
+
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        Self::confidence_interval(self, significance_level)
+    }
+}
+
+/// Fisher's randomization tester.
+///
+/// # Default parameters
+///
+/// * `n_iters`: `10000`
+/// * `random_state`: `None`
+/// * `alternative`: [`Alternative::TwoSided`]
+#[derive(Debug, Clone, Copy)]
+pub struct FisherRandomizationTester {
+    n_iters: usize,
+    random_state: Option<u64>,
+    alternative: Alternative,
+}
+
+impl Default for FisherRandomizationTester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FisherRandomizationTester {
+    /// Creates a new randomization tester.
+    pub const fn new() -> Self {
+        Self {
+            n_iters: 10000,
+            random_state: None,
+            alternative: Alternative::TwoSided,
+        }
+    }
+
+    /// Sets the number of iterations.
+    ///
+    /// If the input is less than `1`, it is modified to `1`.
+    pub fn with_n_iters(mut self, n_iters: usize) -> Self {
+        self.n_iters = n_iters.max(1);
+        self
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Sets the alternative hypothesis.
+    pub const fn with_alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Computes a randomization test for the samples.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least one sample.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    pub fn test<I>(&self, samples: I) -> Result<FisherRandomizationTest>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let samples: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
+        let n = samples.len() as f64;
+        self.test_differences(samples, vec![1.0; n as usize], n)
+    }
+
+    /// Computes a randomization test for the samples weighted per topic (e.g., by traffic),
+    /// where each sample is a triple $`(a_{i}, b_{i}, w_{i})`$ of the paired scores and the weight.
+    ///
+    /// The statistic is the weighted mean difference $`\sum_{i} w_{i} x_{i} / \sum_{i} w_{i}`$,
+    /// whose signs of the terms are randomly flipped under the null hypothesis.
+    /// With equal weights, the result is the same as that of [`Self::test`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use approx::assert_abs_diff_eq;
+    /// use elinor::statistical_tests::fisher_randomization_test::FisherRandomizationTester;
+    ///
+    /// let samples = vec![(0.70, 0.50, 3.0), (0.30, 0.10, 1.0), (0.20, 0.00, 1.0)];
+    /// let result = FisherRandomizationTester::new()
+    ///     .with_random_state(42)
+    ///     .test_weighted(samples)?;
+    /// assert_abs_diff_eq!(result.mean(), 0.20);
+    /// assert!((0.0..=1.0).contains(&result.p_value()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the weights are negative, non-finite, or all zero.
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least one sample.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    pub fn test_weighted<I>(&self, samples: I) -> Result<FisherRandomizationTest>
+    where
+        I: IntoIterator<Item = (f64, f64, f64)>,
+    {
+        let (samples, weights) = weighted_differences(samples)?;
+        let sum = weights.iter().sum::<f64>();
+        self.test_differences(samples, weights, sum)
+    }
+
+    /// Runs the test for the differences and weights, where `n` is the sum of the weights.
+    fn test_differences(
+        &self,
+        samples: Vec<f64>,
+        weights: Vec<f64>,
+        n: f64,
+    ) -> Result<FisherRandomizationTest> {
+        if samples.is_empty() {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least one sample.".to_string(),
+            ));
+        }
+        if !samples.iter().all(|x| x.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
+        let samples: Vec<f64> = samples.iter().zip(&weights).map(|(x, w)| x * w).collect();
+
+        // Prepare the random number generator.
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+
+        let mean = samples.iter().sum::<f64>() / n;
+        let null_stddev = samples.iter().map(|x| x * x).sum::<f64>().sqrt() / n;
+
+        let mut null_means = map_resamples(self.n_iters, &mut rng, |rng| {
+            samples
+                .iter()
+                .map(|&x| if rng.gen::<bool>() { -x } else { x })
+                .sum::<f64>()
+                / n
+        });
+
+        let observed = self.alternative.orient(mean);
+        let count = null_means
+            .iter()
+            .filter(|&&x| self.alternative.orient(x) >= observed)
+            .count();
+        let p_value = count as f64 / self.n_iters as f64;
+        null_means.sort_by(f64::total_cmp);
+
+        Ok(FisherRandomizationTest {
+            n_topics: samples.len(),
+            n_iters: self.n_iters,
+            random_state,
+            alternative: self.alternative,
+            mean,
+            null_stddev,
+            p_value,
+            null_means,
+        })
+    }
+
+    /// Computes a randomization test for the per-query scores of two evaluations,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<FisherRandomizationTest>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::*;
+
+    use crate::statistical_tests::randomized_tukey_hsd_test::PermutationScheme;
+    use crate::statistical_tests::randomized_tukey_hsd_test::RandomizedTukeyHsdTester;
+
+    #[test]
+    fn test_fisher_randomization_test_from_paired_samples_empty() {
+        let result = FisherRandomizationTest::from_paired_samples(vec![]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must have at least one sample.".to_string())
+        );
+    }
+
+    #[rstest]
+    #[case::nan_a(vec![(f64::NAN, 0.0), (1.0, 0.0)])]
+    #[case::nan_b(vec![(1.0, f64::NAN), (1.0, 0.0)])]
+    #[case::infinity(vec![(f64::INFINITY, 0.0), (1.0, 0.0)])]
+    fn test_fisher_randomization_test_from_paired_samples_non_finite(
+        #[case] samples: Vec<(f64, f64)>,
+    ) {
+        let result = FisherRandomizationTest::from_paired_samples(samples);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fisher_randomization_tester_with_parameters() {
+        let tester = FisherRandomizationTester::new()
+            .with_n_iters(334)
+            .with_random_state(42)
+            .with_alternative(Alternative::Less);
+        let result = tester
+            .test(vec![(1.0, 0.0), (0.0, 1.0), (1.0, 3.0)])
+            .unwrap();
+        assert_eq!(result.n_iters(), 334);
+        assert_eq!(result.random_state(), 42);
+        assert_eq!(result.alternative(), Alternative::Less);
+        assert_abs_diff_eq!(result.mean(), -2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_fisher_randomization_test_alternative() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let test = |alternative| {
+            FisherRandomizationTester::new()
+                .with_random_state(42)
+                .with_alternative(alternative)
+                .test(samples.clone())
+                .unwrap()
+        };
+        let two_sided = test(Alternative::TwoSided);
+        let greater = test(Alternative::Greater);
+        let less = test(Alternative::Less);
+        // Only the identity and the flips of the zero difference reproduce the observed mean,
+        // so the exact p-values are 2/32 for greater and 4/32 for two-sided.
+        assert_abs_diff_eq!(greater.p_value(), 2.0 / 32.0, epsilon = 1e-2);
+        assert_abs_diff_eq!(two_sided.p_value(), 4.0 / 32.0, epsilon = 1e-2);
+        // All differences are non-negative, so no flip increases the observed mean.
+        assert_eq!(less.p_value(), 1.0);
+    }
+
+    #[test]
+    fn test_fisher_randomization_test_consistent_with_randomized_tukey_hsd_test() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let fisher = FisherRandomizationTester::new()
+            .with_n_iters(100000)
+            .with_random_state(42)
+            .test(samples.clone())
+            .unwrap();
+        let hsd = RandomizedTukeyHsdTester::new(2)
+            .with_n_iters(100000)
+            .with_random_state(42)
+            .with_permutation_scheme(PermutationScheme::SignFlip)
+            .test(samples.iter().map(|&(a, b)| [a, b]))
+            .unwrap();
+        assert_abs_diff_eq!(fisher.p_value(), hsd.p_values()[0][1], epsilon = 1e-2);
+        assert_abs_diff_eq!(
+            fisher.effect_size(),
+            hsd.effect_sizes()[0][1],
+            epsilon = 1e-2
+        );
+    }
+
+    #[test]
+    fn test_fisher_randomization_test_confidence_interval() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let result = FisherRandomizationTester::new()
+            .with_random_state(42)
+            .test(samples)
+            .unwrap();
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert_abs_diff_eq!(result.mean() - ci95_btm, ci95_top - result.mean());
+        let (ci99_btm, ci99_top) = result.confidence_interval(0.01).unwrap();
+        assert!(ci99_btm <= ci95_btm && ci95_top <= ci99_top);
+        assert_eq!(
+            result.confidence_interval(0.0).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_fisher_randomization_test_null_quantile() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let result = FisherRandomizationTester::new()
+            .with_random_state(42)
+            .test(samples)
+            .unwrap();
+        // The observed mean difference exceeds the critical difference iff p < alpha.
+        let critical = result.null_quantile(0.95).unwrap();
+        assert_eq!(result.mean() > critical, result.p_value() < 0.05);
+        assert!(result.null_quantile(1.0).unwrap() <= 0.2 + 1e-10);
+        assert_eq!(
+            result.null_quantile(1.1).unwrap_err(),
+            ElinorError::InvalidArgument("The quantile must be in the range [0, 1].".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fisher_randomization_tester_weighted_equal_weights() {
+        let a = vec![0.70, 0.30, 0.20, 0.60, 0.40];
+        let b = vec![0.50, 0.10, 0.00, 0.20, 0.40];
+        let tester = FisherRandomizationTester::new().with_random_state(42);
+        let unweighted = tester.test(a.iter().copied().zip(b.clone())).unwrap();
+        let samples = a.into_iter().zip(b).map(|(x, y)| (x, y, 2.0));
+        let weighted = tester.test_weighted(samples).unwrap();
+        assert_eq!(weighted.mean(), unweighted.mean());
+        assert_eq!(weighted.effect_size(), unweighted.effect_size());
+        assert_eq!(weighted.p_value(), unweighted.p_value());
+    }
+
+    #[test]
+    fn test_fisher_randomization_tester_weighted() {
+        // Zero weights are equivalent to removing the topics.
+        let tester = FisherRandomizationTester::new().with_random_state(42);
+        let samples = vec![(0.70, 0.50, 1.0), (0.30, 0.10, 0.0), (0.20, 0.40, 3.0)];
+        let result = tester.test_weighted(samples).unwrap();
+        assert_abs_diff_eq!(
+            result.mean(),
+            0.20f64.mul_add(-3.0, 0.20) / 4.0,
+            epsilon = 1e-10
+        );
+        assert_abs_diff_eq!(
+            result.effect_size(),
+            result.mean() / ((0.04 + 0.36f64).sqrt() / 4.0),
+            epsilon = 1e-10
+        );
+        assert!((0.0..=1.0).contains(&result.p_value()));
+    }
+}
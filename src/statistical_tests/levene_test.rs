@@ -0,0 +1,376 @@
+//! Levene's test for the equality of variances.
+use std::str::FromStr;
+
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::FisherSnedecor;
+use statrs::statistics::Statistics;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::Evaluation;
+
+/// Center of the per-topic scores used to compute the absolute deviations in [`LeveneTest`].
+///
+/// # Examples
+///
+/// ```
+/// use elinor::statistical_tests::levene_test::LeveneCenter;
+///
+/// assert_eq!("mean".parse(), Ok(LeveneCenter::Mean));
+/// assert_eq!("median".parse(), Ok(LeveneCenter::Median));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LeveneCenter {
+    /// Mean, i.e., the original Levene's test.
+    Mean,
+
+    /// Median, i.e., the Brown-Forsythe test, which is robust to skewed scores.
+    #[default]
+    Median,
+}
+
+impl FromStr for LeveneCenter {
+    type Err = ElinorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mean" => Ok(Self::Mean),
+            "median" => Ok(Self::Median),
+            _ => Err(ElinorError::InvalidFormat(format!(
+                "Unsupported Levene center: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for LeveneCenter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Mean => write!(f, "mean"),
+            Self::Median => write!(f, "median"),
+        }
+    }
+}
+
+impl LeveneCenter {
+    fn center(&self, values: &[f64]) -> f64 {
+        match self {
+            Self::Mean => Statistics::mean(values),
+            Self::Median => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let n = sorted.len();
+                if n % 2 == 0 {
+                    (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+                } else {
+                    sorted[n / 2]
+                }
+            }
+        }
+    }
+}
+
+/// Levene's test for the equality of the variances of two systems' per-topic scores.
+///
+/// It tells whether one system is more consistent than the other over the topics,
+/// which is often as important as being better on average.
+/// The absolute deviations of the scores from the [`LeveneCenter`] of each system
+/// are compared by one-way ANOVA.
+/// The default center is the median, i.e., the Brown-Forsythe test.
+///
+/// Note that the test regards the scores of the two systems as independent groups,
+/// ignoring the pairing by topics.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::LeveneTest;
+///
+/// let a = vec![0.90, 0.10, 0.50, 0.80, 0.20, 0.60];
+/// let b = vec![0.50, 0.40, 0.45, 0.55, 0.50, 0.60];
+///
+/// let samples = a.into_iter().zip(b);
+/// let result = LeveneTest::from_paired_samples(samples)?;
+/// assert_eq!(result.n_topics(), 6);
+/// assert!(result.variance_a() > result.variance_b());
+/// assert_abs_diff_eq!(result.f_stat(), 8.0, epsilon = 1e-10);
+/// assert!(result.p_value() < 0.05);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # References
+///
+/// * Howard Levene.
+///   Robust tests for equality of variances.
+///   Contributions to Probability and Statistics, Stanford University Press, 1960.
+/// * Morton B. Brown and Alan B. Forsythe.
+///   [Robust tests for the equality of variances](https://doi.org/10.1080/01621459.1974.10482955).
+///   Journal of the American Statistical Association, 1974.
+#[derive(Debug, Clone)]
+pub struct LeveneTest {
+    n_topics: usize,
+    center: LeveneCenter,
+    variance_a: f64,
+    variance_b: f64,
+    f_stat: f64,
+    p_value: f64,
+}
+
+impl LeveneTest {
+    /// Computes Levene's test for the per-topic scores of two systems,
+    /// given as paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
+    ///
+    /// It uses the default parameters defined in [`LeveneTester`].
+    /// To customize the parameters, use [`LeveneTester`].
+    ///
+    /// # Errors
+    ///
+    /// See [`LeveneTester::test`].
+    pub fn from_paired_samples<I>(samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        LeveneTester::new().test(samples)
+    }
+
+    /// Computes Levene's test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
+    /// Number of topics, $`n`$.
+    pub const fn n_topics(&self) -> usize {
+        self.n_topics
+    }
+
+    /// Center used to compute the absolute deviations.
+    pub const fn center(&self) -> LeveneCenter {
+        self.center
+    }
+
+    /// Unbiased population variance of the scores of system $`A`$.
+    pub const fn variance_a(&self) -> f64 {
+        self.variance_a
+    }
+
+    /// Unbiased population variance of the scores of system $`B`$.
+    pub const fn variance_b(&self) -> f64 {
+        self.variance_b
+    }
+
+    /// Ratio of the variances, $`V_A / V_B`$.
+    pub fn variance_ratio(&self) -> f64 {
+        self.variance_a / self.variance_b
+    }
+
+    /// F-statistic.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// F = \frac{(2n-2) \sum_{g \in \{A,B\}} n (\bar{z}_{g} - \bar{z})^{2}}{\sum_{g \in \{A,B\}} \sum_{i=1}^{n} (z_{gi} - \bar{z}_{g})^{2}}
+    /// ```
+    ///
+    /// where
+    ///
+    /// * $`z_{gi} = |x_{gi} - c_{g}|`$ is the absolute deviation of the $`i`$-th score of system $`g`$
+    ///   from its center $`c_{g}`$,
+    /// * $`\bar{z}_{g}`$ is the mean of $`z_{gi}`$ over the topics, and
+    /// * $`\bar{z}`$ is the mean of all $`z_{gi}`$.
+    pub const fn f_stat(&self) -> f64 {
+        self.f_stat
+    }
+
+    /// p-value, i.e., $`P(F(1, 2n-2) > F)`$.
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+}
+
+/// Levene's tester.
+///
+/// # Default parameters
+///
+/// * `center`: [`LeveneCenter::Median`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeveneTester {
+    center: LeveneCenter,
+}
+
+impl LeveneTester {
+    /// Creates a new Levene's tester.
+    pub const fn new() -> Self {
+        Self {
+            center: LeveneCenter::Median,
+        }
+    }
+
+    /// Sets the center to compute the absolute deviations.
+    pub const fn with_center(mut self, center: LeveneCenter) -> Self {
+        self.center = center;
+        self
+    }
+
+    /// Computes Levene's test for the samples.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    /// * [`ElinorError::Uncomputable`] if the absolute deviations are constant within each system.
+    pub fn test<I>(&self, samples: I) -> Result<LeveneTest>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let (a, b): (Vec<f64>, Vec<f64>) = samples.into_iter().unzip();
+        if a.len() <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two samples.".to_string(),
+            ));
+        }
+        if !a.iter().chain(&b).all(|x| x.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
+        let n = a.len() as f64;
+
+        let deviations = |values: &[f64]| {
+            let center = self.center.center(values);
+            values
+                .iter()
+                .map(|x| (x - center).abs())
+                .collect::<Vec<_>>()
+        };
+        let z_a = deviations(&a);
+        let z_b = deviations(&b);
+        let mean_a = Statistics::mean(&z_a);
+        let mean_b = Statistics::mean(&z_b);
+        let mean = (mean_a + mean_b) / 2.0;
+
+        let (dev_a, dev_b) = (mean_a - mean, mean_b - mean);
+        let between = n * dev_a.mul_add(dev_a, dev_b * dev_b);
+        let within = z_a.iter().map(|z| (z - mean_a).powi(2)).sum::<f64>()
+            + z_b.iter().map(|z| (z - mean_b).powi(2)).sum::<f64>();
+        if within == 0.0 {
+            return Err(ElinorError::Uncomputable(
+                "The absolute deviations are constant within each system.".to_string(),
+            ));
+        }
+
+        let within_freedom = 2.0f64.mul_add(n, -2.0);
+        let f_stat = within_freedom * between / within;
+        let f_dist = FisherSnedecor::new(1.0, within_freedom).unwrap();
+        let p_value = f_dist.sf(f_stat);
+
+        Ok(LeveneTest {
+            n_topics: a.len(),
+            center: self.center,
+            variance_a: Statistics::variance(&a),
+            variance_b: Statistics::variance(&b),
+            f_stat,
+            p_value,
+        })
+    }
+
+    /// Computes Levene's test for the per-query scores of two evaluations,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<LeveneTest>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::*;
+
+    #[test]
+    fn test_levene_test_one_sample() {
+        let result = LeveneTest::from_paired_samples(vec![(0.0, 1.0)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must have at least two samples.".to_string())
+        );
+    }
+
+    #[rstest]
+    #[case::nan_a(vec![(f64::NAN, 0.0), (1.0, 0.5), (0.5, 1.0)])]
+    #[case::nan_b(vec![(1.0, f64::NAN), (1.0, 0.5), (0.5, 1.0)])]
+    #[case::infinity(vec![(f64::INFINITY, 0.0), (1.0, 0.5), (0.5, 1.0)])]
+    fn test_levene_test_non_finite(#[case] samples: Vec<(f64, f64)>) {
+        let result = LeveneTest::from_paired_samples(samples);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_levene_test_constant_deviations() {
+        let result = LeveneTest::from_paired_samples(vec![(0.0, 1.0), (1.0, 0.0)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::Uncomputable(
+                "The absolute deviations are constant within each system.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_levene_test_centers() {
+        let a = vec![0.90, 0.10, 0.50, 0.80, 0.20, 0.60];
+        let b = vec![0.50, 0.40, 0.45, 0.55, 0.50, 0.60];
+        let samples = a.into_iter().zip(b).collect::<Vec<_>>();
+
+        let result = LeveneTester::new().test(samples.clone()).unwrap();
+        assert_eq!(result.center(), LeveneCenter::Median);
+        assert_abs_diff_eq!(result.f_stat(), 8.0, epsilon = 1e-10);
+        assert_abs_diff_eq!(result.p_value(), 0.0179, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.variance_a(), 0.1017, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.variance_b(), 0.005, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.variance_ratio(), 20.3333, epsilon = 1e-4);
+
+        let result = LeveneTester::new()
+            .with_center(LeveneCenter::Mean)
+            .test(samples)
+            .unwrap();
+        assert_abs_diff_eq!(result.f_stat(), 8.3721, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.p_value(), 0.0160, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_levene_center_from_str() {
+        for center in [LeveneCenter::Mean, LeveneCenter::Median] {
+            assert_eq!(center.to_string().parse(), Ok(center));
+        }
+        assert!("trimmed".parse::<LeveneCenter>().is_err());
+    }
+}
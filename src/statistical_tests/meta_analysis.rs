@@ -0,0 +1,442 @@
+//! Meta-analysis for comparing two systems across multiple collections.
+//!
+//! When the same two systems are compared on several collections,
+//! the per-collection results can be combined into a single statistic:
+//!
+//! * [`fisher_method`] and [`stouffer_method`] combine the per-collection p-values.
+//! * [`RandomEffectsMetaAnalysis`] estimates the mean difference over the collections,
+//!   allowing the true difference to vary from collection to collection.
+use statrs::distribution::ChiSquared;
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::Normal;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+
+/// Combines independent p-values by Fisher's method.
+///
+/// # Formula
+///
+/// ```math
+/// X = -2 \sum_{i=1}^{k} \ln p_i
+/// ```
+///
+/// follows the $`\chi^2`$ distribution with $`2k`$ degrees of freedom under the null hypothesis,
+/// and the returned p-value is $`P(\chi^2_{2k} \ge X)`$.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `p_values` is empty.
+/// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `(0, 1]`.
+///
+/// # Examples
+///
+/// ```
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::meta_analysis::fisher_method;
+///
+/// // X = -4 ln 0.05, and P(X' >= X) = 0.05^2 (1 - 2 ln 0.05) for X' ~ chi2(4).
+/// let p_value = fisher_method(&[0.05, 0.05]).unwrap();
+/// assert_abs_diff_eq!(p_value, 0.0025 * (1.0 - 2.0 * 0.05_f64.ln()), epsilon = 1e-10);
+/// ```
+pub fn fisher_method(p_values: &[f64]) -> Result<f64> {
+    if p_values.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one p-value.".to_string(),
+        ));
+    }
+    if p_values.iter().any(|&p| !(p > 0.0 && p <= 1.0)) {
+        return Err(ElinorError::InvalidArgument(
+            "The p-values must be in the range (0, 1].".to_string(),
+        ));
+    }
+    let x = -2.0 * p_values.iter().map(|p| p.ln()).sum::<f64>();
+    let chi2 = ChiSquared::new(2.0 * p_values.len() as f64).unwrap();
+    Ok(chi2.sf(x))
+}
+
+/// Combines independent one-sided p-values by the weighted Stouffer's method.
+///
+/// All the p-values must be for the same direction (e.g., system A is better than system B).
+/// The weights are typically the square roots of the numbers of topics.
+///
+/// # Formula
+///
+/// ```math
+/// Z = \frac{\sum_{i=1}^{k} w_i z_i}{\sqrt{\sum_{i=1}^{k} w_i^2}}
+/// ```
+///
+/// where $`z_i = \Phi^{-1}(1 - p_i)`$,
+/// and the returned one-sided p-value is $`1 - \Phi(Z)`$.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `p_values` is empty.
+/// * [`ElinorError::InvalidArgument`] if `p_values` and `weights` have different lengths.
+/// * [`ElinorError::InvalidArgument`] if a p-value is not in the range `(0, 1)`.
+/// * [`ElinorError::InvalidArgument`] if a weight is not positive.
+///
+/// # Examples
+///
+/// ```
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::meta_analysis::stouffer_method;
+///
+/// // Z = 2 * 1.6449 / sqrt(2) = 2.3262.
+/// let p_value = stouffer_method(&[0.05, 0.05], &[1.0, 1.0]).unwrap();
+/// assert_abs_diff_eq!(p_value, 0.01, epsilon = 1e-4);
+/// ```
+pub fn stouffer_method(p_values: &[f64], weights: &[f64]) -> Result<f64> {
+    if p_values.is_empty() {
+        return Err(ElinorError::InvalidArgument(
+            "The input must have at least one p-value.".to_string(),
+        ));
+    }
+    if p_values.len() != weights.len() {
+        return Err(ElinorError::InvalidArgument(
+            "The p-values and weights must have the same length.".to_string(),
+        ));
+    }
+    if p_values.iter().any(|&p| !(p > 0.0 && p < 1.0)) {
+        return Err(ElinorError::InvalidArgument(
+            "The p-values must be in the range (0, 1).".to_string(),
+        ));
+    }
+    if weights.iter().any(|&w| w.is_nan() || w <= 0.0) {
+        return Err(ElinorError::InvalidArgument(
+            "The weights must be positive.".to_string(),
+        ));
+    }
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    let numerator = p_values
+        .iter()
+        .zip(weights)
+        .map(|(&p, &w)| w * normal.inverse_cdf(1.0 - p))
+        .sum::<f64>();
+    let denominator = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+    Ok(normal.sf(numerator / denominator))
+}
+
+/// Random-effects meta-analysis of the mean differences between two systems over collections,
+/// with the between-collection variance estimated by the DerSimonian-Laird method.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::statistical_tests::meta_analysis::RandomEffectsMetaAnalysis;
+///
+/// // Paired scores of systems A and B for each collection.
+/// let collection_1 = vec![(0.70, 0.50), (0.30, 0.10), (0.20, 0.00), (0.60, 0.20)];
+/// let collection_2 = vec![(0.40, 0.30), (0.00, 0.00), (0.70, 0.50), (0.10, 0.30)];
+/// let result = RandomEffectsMetaAnalysis::from_paired_samples([collection_1, collection_2])?;
+///
+/// assert_eq!(result.n_collections(), 2);
+/// assert!(result.mean_difference() > 0.0);
+/// let (ci95_btm, ci95_top) = result.confidence_interval(0.05)?;
+/// assert!(ci95_btm <= result.mean_difference() && result.mean_difference() <= ci95_top);
+/// assert!((0.0..=1.0).contains(&result.p_value()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RandomEffectsMetaAnalysis {
+    mean_differences: Vec<f64>,
+    variances: Vec<f64>,
+    q_stat: f64,
+    tau_squared: f64,
+    mean_difference: f64,
+    std_error: f64,
+    z_stat: f64,
+    p_value: f64,
+}
+
+impl RandomEffectsMetaAnalysis {
+    /// Computes a random-effects meta-analysis from the paired samples $`(a_{ij}, b_{ij})`$
+    /// of each collection $`i`$, where $`a_{ij} - b_{ij}`$ is the difference for the $`j`$-th topic.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two collections.
+    /// * [`ElinorError::InvalidArgument`] if a collection does not have at least two samples.
+    /// * [`ElinorError::Uncomputable`] if the variance of the differences in a collection is zero.
+    pub fn from_paired_samples<I, J>(collections: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = J>,
+        J: IntoIterator<Item = (f64, f64)>,
+    {
+        let mut mean_differences = vec![];
+        let mut variances = vec![];
+        for samples in collections {
+            let diffs: Vec<f64> = samples.into_iter().map(|(a, b)| a - b).collect();
+            if diffs.len() <= 1 {
+                return Err(ElinorError::InvalidArgument(
+                    "Each collection must have at least two samples.".to_string(),
+                ));
+            }
+            let n = diffs.len() as f64;
+            let mean = diffs.iter().sum::<f64>() / n;
+            let variance = diffs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            if variance == 0.0 {
+                return Err(ElinorError::Uncomputable(
+                    "The variance of the differences must be positive in each collection."
+                        .to_string(),
+                ));
+            }
+            mean_differences.push(mean);
+            // Variance of the mean difference.
+            variances.push(variance / n);
+        }
+        let k = mean_differences.len();
+        if k <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two collections.".to_string(),
+            ));
+        }
+
+        // Fixed-effect estimate to compute Cochran's Q.
+        let weights: Vec<f64> = variances.iter().map(|v| 1.0 / v).collect();
+        let sum_w = weights.iter().sum::<f64>();
+        let fixed_mean = weighted_mean(&mean_differences, &weights);
+        let q_stat = weights
+            .iter()
+            .zip(&mean_differences)
+            .map(|(w, d)| w * (d - fixed_mean).powi(2))
+            .sum::<f64>();
+        let c = sum_w - weights.iter().map(|w| w * w).sum::<f64>() / sum_w;
+        let tau_squared = ((q_stat - (k - 1) as f64) / c).max(0.0);
+
+        let weights: Vec<f64> = variances.iter().map(|v| 1.0 / (v + tau_squared)).collect();
+        let mean_difference = weighted_mean(&mean_differences, &weights);
+        let std_error = (1.0 / weights.iter().sum::<f64>()).sqrt();
+        let z_stat = mean_difference / std_error;
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let p_value = normal.sf(z_stat.abs()) * 2.0;
+
+        Ok(Self {
+            mean_differences,
+            variances,
+            q_stat,
+            tau_squared,
+            mean_difference,
+            std_error,
+            z_stat,
+            p_value,
+        })
+    }
+
+    /// Number of collections, $`k`$.
+    pub fn n_collections(&self) -> usize {
+        self.mean_differences.len()
+    }
+
+    /// Mean differences of the collections, $`d_i`$.
+    pub fn collection_mean_differences(&self) -> &[f64] {
+        &self.mean_differences
+    }
+
+    /// Variances of the mean differences of the collections, $`v_i = s_i^2 / n_i`$,
+    /// where $`s_i^2`$ is the unbiased variance of the differences and $`n_i`$ is the number of topics.
+    pub fn collection_variances(&self) -> &[f64] {
+        &self.variances
+    }
+
+    /// Cochran's Q statistic for the heterogeneity of the collections.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// Q = \sum_{i=1}^{k} w_i (d_i - \bar{d}_{\text{FE}})^2
+    /// ```
+    ///
+    /// where $`w_i = 1 / v_i`$ and $`\bar{d}_{\text{FE}} = \sum_i w_i d_i / \sum_i w_i`$.
+    pub const fn q_stat(&self) -> f64 {
+        self.q_stat
+    }
+
+    /// Proportion of the variation due to the heterogeneity of the collections, $`I^2`$.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// I^2 = \max\left(0, \frac{Q - (k - 1)}{Q}\right)
+    /// ```
+    pub fn i_squared(&self) -> f64 {
+        if self.q_stat > 0.0 {
+            ((self.q_stat - (self.n_collections() - 1) as f64) / self.q_stat).max(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Between-collection variance estimated by the DerSimonian-Laird method.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \tau^2 = \max\left(0, \frac{Q - (k - 1)}{\sum_i w_i - \sum_i w_i^2 / \sum_i w_i}\right)
+    /// ```
+    pub const fn tau_squared(&self) -> f64 {
+        self.tau_squared
+    }
+
+    /// Combined mean difference under the random-effects model.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \bar{d}_{\text{RE}} = \frac{\sum_{i=1}^{k} w^{*}_i d_i}{\sum_{i=1}^{k} w^{*}_i}
+    /// ```
+    ///
+    /// where $`w^{*}_i = 1 / (v_i + \tau^2)`$.
+    pub const fn mean_difference(&self) -> f64 {
+        self.mean_difference
+    }
+
+    /// Standard error of the combined mean difference, $`1 / \sqrt{\sum_i w^{*}_i}`$.
+    pub const fn std_error(&self) -> f64 {
+        self.std_error
+    }
+
+    /// z-statistic of the combined mean difference.
+    pub const fn z_stat(&self) -> f64 {
+        self.z_stat
+    }
+
+    /// p-value for the two-sided test of the combined mean difference.
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Confidence interval of the combined mean difference at a given significance level $`\alpha`$.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let moe = normal.inverse_cdf(1.0 - significance_level / 2.0) * self.std_error;
+        Ok((self.mean_difference - moe, self.mean_difference + moe))
+    }
+}
+
+fn weighted_mean(values: &[f64], weights: &[f64]) -> f64 {
+    values.iter().zip(weights).map(|(x, w)| x * w).sum::<f64>() / weights.iter().sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_fisher_method_invalid() {
+        assert_eq!(
+            fisher_method(&[]),
+            Err(ElinorError::InvalidArgument(
+                "The input must have at least one p-value.".to_string()
+            ))
+        );
+        assert_eq!(
+            fisher_method(&[0.0, 0.5]),
+            Err(ElinorError::InvalidArgument(
+                "The p-values must be in the range (0, 1].".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_stouffer_method_weighted() {
+        // Z = (1 * 1.6449 + 2 * 0.0) / sqrt(5) = 0.7356.
+        let p_value = stouffer_method(&[0.05, 0.5], &[1.0, 2.0]).unwrap();
+        assert_abs_diff_eq!(p_value, 0.2310, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_stouffer_method_different_lengths() {
+        assert_eq!(
+            stouffer_method(&[0.05, 0.5], &[1.0]),
+            Err(ElinorError::InvalidArgument(
+                "The p-values and weights must have the same length.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_random_effects_meta_analysis() {
+        let collections = vec![
+            vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)],
+            vec![(0.4, 0.3), (0.0, 0.0), (0.7, 0.5), (0.1, 0.3), (0.3, 0.3)],
+            vec![
+                (0.5, 0.4),
+                (0.4, 0.4),
+                (0.0, 0.1),
+                (0.6, 0.4),
+                (0.5, 0.2),
+                (0.3, 0.1),
+            ],
+        ];
+        let result = RandomEffectsMetaAnalysis::from_paired_samples(collections).unwrap();
+        assert_eq!(result.n_collections(), 3);
+        assert_abs_diff_eq!(
+            result.collection_mean_differences()[0],
+            0.2,
+            epsilon = 1e-10
+        );
+        assert_abs_diff_eq!(result.collection_variances()[0], 0.004, epsilon = 1e-10);
+        assert_abs_diff_eq!(result.q_stat(), 3.8581, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.i_squared(), 0.4816, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.tau_squared(), 0.003708, epsilon = 1e-6);
+        assert_abs_diff_eq!(result.mean_difference(), 0.1138, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.std_error(), 0.0507, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.z_stat(), 2.2469, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.p_value(), 0.0246, epsilon = 1e-4);
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert_abs_diff_eq!(ci95_btm, 0.0145, epsilon = 1e-4);
+        assert_abs_diff_eq!(ci95_top, 0.2131, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_random_effects_meta_analysis_homogeneous() {
+        // The same collection twice has no heterogeneity.
+        let collection = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let result =
+            RandomEffectsMetaAnalysis::from_paired_samples([collection.clone(), collection])
+                .unwrap();
+        assert_abs_diff_eq!(result.q_stat(), 0.0, epsilon = 1e-10);
+        assert_eq!(result.tau_squared(), 0.0);
+        assert_eq!(result.i_squared(), 0.0);
+        assert_abs_diff_eq!(result.mean_difference(), 0.2, epsilon = 1e-10);
+        assert_abs_diff_eq!(result.std_error(), 0.002_f64.sqrt(), epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_random_effects_meta_analysis_one_collection() {
+        let result = RandomEffectsMetaAnalysis::from_paired_samples([vec![(0.7, 0.5), (0.3, 0.2)]]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The input must have at least two collections.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_random_effects_meta_analysis_zero_variance() {
+        let result = RandomEffectsMetaAnalysis::from_paired_samples([
+            vec![(1.0, 0.5), (0.75, 0.25)],
+            vec![(0.7, 0.5), (0.3, 0.2)],
+        ]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::Uncomputable(
+                "The variance of the differences must be positive in each collection.".to_string()
+            )
+        );
+    }
+}
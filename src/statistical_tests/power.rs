@@ -0,0 +1,391 @@
+//! Statistical power analysis for topic set size design.
+//!
+//! This module provides functions to determine how many topics are needed
+//! to detect a given effect with a given significance level $`\alpha`$ and power $`1 - \beta`$,
+//! and to compute the power achieved by an existing experiment,
+//! for the two-sided paired t-test and the one-way ANOVA.
+//!
+//! The power is computed by the normal approximations of the noncentral $`t`$ and $`F`$ distributions
+//! used in Sakai's book, which are accurate enough for designing the topic set size.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::statistical_tests::power;
+//!
+//! // Topics required to detect an effect size of 0.5 with alpha = 0.05 and power = 0.8.
+//! let n_topics = power::paired_t_required_topics(0.5, 0.05, 0.8)?;
+//! assert_eq!(n_topics, 34);
+//! assert!(power::paired_t_power(0.5, n_topics, 0.05)? >= 0.8);
+//! assert!(power::paired_t_power(0.5, n_topics - 1, 0.05)? < 0.8);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! * Tetsuya Sakai.
+//!   [Laboratory Experiments in Information Retrieval: Sample Sizes, Effect Sizes, and Statistical Power](https://doi.org/10.1007/978-981-13-1199-4).
+//!   Chapter 6. Springer, 2018.
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::FisherSnedecor;
+use statrs::distribution::Normal;
+use statrs::distribution::StudentsT;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::StudentTTest;
+use crate::statistical_tests::TwoWayAnovaWithoutReplication;
+
+/// Maximum number of topics to search for the required topic set size.
+const MAX_TOPICS: usize = 1_000_000;
+
+/// Power of the two-sided paired t-test with $`n`$ topics
+/// to detect the effect size $`d`$ (i.e., the mean difference divided by the standard deviation).
+///
+/// # Formula
+///
+/// ```math
+/// 1 - \beta \approx 1 - \Phi\left(\frac{t_{\alpha/2}(\phi)(1 - \frac{1}{4\phi}) - \lambda}{\sqrt{1 + \frac{t_{\alpha/2}(\phi)^2}{2\phi}}}\right)
+/// + \Phi\left(\frac{-t_{\alpha/2}(\phi)(1 - \frac{1}{4\phi}) - \lambda}{\sqrt{1 + \frac{t_{\alpha/2}(\phi)^2}{2\phi}}}\right)
+/// ```
+///
+/// where $`\phi = n - 1`$, $`\lambda = \sqrt{n} d`$, and
+/// $`t_{\alpha/2}(\phi)`$ is the $`1 - \alpha/2`$ quantile of the $`t`$ distribution with $`\phi`$ degrees of freedom.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `n_topics` is less than two.
+/// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1)`.
+pub fn paired_t_power(effect_size: f64, n_topics: usize, significance_level: f64) -> Result<f64> {
+    if n_topics <= 1 {
+        return Err(ElinorError::InvalidArgument(
+            "The number of topics must be at least two.".to_string(),
+        ));
+    }
+    validate_probability(significance_level, "significance level")?;
+    let phi = (n_topics - 1) as f64;
+    let lambda = (n_topics as f64).sqrt() * effect_size;
+    let t_dist = StudentsT::new(0.0, 1.0, phi).unwrap();
+    let t = t_dist.inverse_cdf(1.0 - significance_level / 2.0);
+    let scale = (1.0 + t * t / (2.0 * phi)).sqrt();
+    let corrected = t * (1.0 - 1.0 / (4.0 * phi));
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    Ok(normal.sf((corrected - lambda) / scale) + normal.cdf((-corrected - lambda) / scale))
+}
+
+/// Minimum number of topics for the two-sided paired t-test
+/// to detect the effect size $`d`$ with the given significance level and power,
+/// where the power is computed by [`paired_t_power`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the effect size is zero or not finite.
+/// * [`ElinorError::InvalidArgument`] if the significance level or power is not in the range `(0, 1)`.
+/// * [`ElinorError::Uncomputable`] if more than one million topics are required.
+pub fn paired_t_required_topics(
+    effect_size: f64,
+    significance_level: f64,
+    power: f64,
+) -> Result<usize> {
+    if effect_size == 0.0 || !effect_size.is_finite() {
+        return Err(ElinorError::InvalidArgument(
+            "The effect size must be non-zero and finite.".to_string(),
+        ));
+    }
+    validate_probability(significance_level, "significance level")?;
+    validate_probability(power, "power")?;
+    required_topics(
+        |n_topics| paired_t_power(effect_size, n_topics, significance_level),
+        power,
+    )
+}
+
+/// Power of the one-way ANOVA for $`m`$ systems with $`n`$ topics
+/// to detect the effect $`\Delta`$.
+///
+/// The effect is $`\Delta = \sum_{i=1}^{m} (\mu_i - \mu)^2 / \sigma^2`$,
+/// where $`\mu_i`$ is the population mean of the $`i`$-th system, $`\mu`$ is the grand mean,
+/// and $`\sigma^2`$ is the within-system variance.
+/// Use [`anova_effect_from_min_difference`] to obtain it from the minimum detectable range.
+///
+/// # Formula
+///
+/// ```math
+/// 1 - \beta \approx 1 - \Phi(w), \quad
+/// w = \frac{\sqrt{\frac{(2\phi_E - 1)\phi_A F}{\phi_E}} - \sqrt{2(\phi_A + \lambda) - \frac{\phi_A + 2\lambda}{\phi_A + \lambda}}}{\sqrt{\frac{\phi_A F}{\phi_E} + \frac{\phi_A + 2\lambda}{\phi_A + \lambda}}}
+/// ```
+///
+/// where $`\phi_A = m - 1`$, $`\phi_E = m(n - 1)`$, $`\lambda = n \Delta`$, and
+/// $`F`$ is the $`1 - \alpha`$ quantile of the $`F`$ distribution with $`\phi_A`$ and $`\phi_E`$ degrees of freedom.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `n_systems` or `n_topics` is less than two.
+/// * [`ElinorError::InvalidArgument`] if the effect is negative or not finite.
+/// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1)`.
+pub fn one_way_anova_power(
+    n_systems: usize,
+    effect: f64,
+    n_topics: usize,
+    significance_level: f64,
+) -> Result<f64> {
+    if n_systems <= 1 || n_topics <= 1 {
+        return Err(ElinorError::InvalidArgument(
+            "The numbers of systems and topics must be at least two.".to_string(),
+        ));
+    }
+    let phi_a = (n_systems - 1) as f64;
+    let phi_e = (n_systems * (n_topics - 1)) as f64;
+    f_test_power(phi_a, phi_e, n_topics as f64 * effect, significance_level)
+}
+
+/// Minimum number of topics for the one-way ANOVA for $`m`$ systems
+/// to detect the effect $`\Delta`$ with the given significance level and power,
+/// where the power is computed by [`one_way_anova_power`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if `n_systems` is less than two.
+/// * [`ElinorError::InvalidArgument`] if the effect is not positive or not finite.
+/// * [`ElinorError::InvalidArgument`] if the significance level or power is not in the range `(0, 1)`.
+/// * [`ElinorError::Uncomputable`] if more than one million topics are required.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::statistical_tests::power;
+///
+/// // Three systems whose true means differ by at least 0.1 with the within-system variance of 0.05.
+/// let effect = power::anova_effect_from_min_difference(0.1, 0.05)?;
+/// let n_topics = power::one_way_anova_required_topics(3, effect, 0.05, 0.8)?;
+/// assert!(power::one_way_anova_power(3, effect, n_topics, 0.05)? >= 0.8);
+/// # Ok(())
+/// # }
+/// ```
+pub fn one_way_anova_required_topics(
+    n_systems: usize,
+    effect: f64,
+    significance_level: f64,
+    power: f64,
+) -> Result<usize> {
+    if n_systems <= 1 {
+        return Err(ElinorError::InvalidArgument(
+            "The number of systems must be at least two.".to_string(),
+        ));
+    }
+    if effect <= 0.0 || !effect.is_finite() {
+        return Err(ElinorError::InvalidArgument(
+            "The effect must be positive and finite.".to_string(),
+        ));
+    }
+    validate_probability(significance_level, "significance level")?;
+    validate_probability(power, "power")?;
+    required_topics(
+        |n_topics| one_way_anova_power(n_systems, effect, n_topics, significance_level),
+        power,
+    )
+}
+
+/// Effect $`\Delta`$ of the one-way ANOVA in the least favorable configuration,
+/// where the best and worst systems differ by the minimum detectable range $`D`$
+/// and the other systems are at the grand mean.
+///
+/// # Formula
+///
+/// ```math
+/// \Delta = \frac{D^2}{2\sigma^2}
+/// ```
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the variance is not positive.
+pub fn anova_effect_from_min_difference(min_difference: f64, variance: f64) -> Result<f64> {
+    if variance <= 0.0 || !variance.is_finite() {
+        return Err(ElinorError::InvalidArgument(
+            "The variance must be positive and finite.".to_string(),
+        ));
+    }
+    Ok(min_difference * min_difference / (2.0 * variance))
+}
+
+/// Power achieved by the paired t-test,
+/// regarding the observed effect size as the population one.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1)`.
+pub fn achieved_power_of_t_test(stat: &StudentTTest, significance_level: f64) -> Result<f64> {
+    paired_t_power(stat.effect_size(), stat.n_topics(), significance_level)
+}
+
+/// Power achieved by the between-system factor of the two-way ANOVA without replication.
+///
+/// The noncentrality parameter estimated from the observed F-statistic,
+/// $`\hat{\lambda} = \max(0, \phi_A (F_A - 1))`$, is regarded as the population one.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1)`.
+pub fn achieved_power_of_anova(
+    stat: &TwoWayAnovaWithoutReplication,
+    significance_level: f64,
+) -> Result<f64> {
+    let phi_a = (stat.n_systems() - 1) as f64;
+    let phi_e = ((stat.n_systems() - 1) * (stat.n_topics() - 1)) as f64;
+    let lambda = (phi_a * (stat.between_system_f_stat() - 1.0)).max(0.0);
+    f_test_power(phi_a, phi_e, lambda, significance_level)
+}
+
+/// Power of the F-test with the degrees of freedom $`\phi_A`$ and $`\phi_E`$
+/// and the noncentrality parameter $`\lambda`$.
+fn f_test_power(phi_a: f64, phi_e: f64, lambda: f64, significance_level: f64) -> Result<f64> {
+    if lambda < 0.0 || !lambda.is_finite() {
+        return Err(ElinorError::InvalidArgument(
+            "The effect must be non-negative and finite.".to_string(),
+        ));
+    }
+    validate_probability(significance_level, "significance level")?;
+    let f_dist = FisherSnedecor::new(phi_a, phi_e).unwrap();
+    let f = f_dist.inverse_cdf(1.0 - significance_level);
+    let c = phi_a * f / phi_e;
+    let g = 2.0f64.mul_add(lambda, phi_a) / (phi_a + lambda);
+    let w = ((2.0f64.mul_add(phi_e, -1.0) * c).sqrt() - 2.0f64.mul_add(phi_a + lambda, -g).sqrt())
+        / (c + g).sqrt();
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    Ok(normal.sf(w))
+}
+
+/// Finds the minimum number of topics whose power is at least the target,
+/// assuming that the power increases with the number of topics.
+fn required_topics<F>(power_fn: F, power: f64) -> Result<usize>
+where
+    F: Fn(usize) -> Result<f64>,
+{
+    // Doubles the upper bound until the power is achieved, and then bisects.
+    let mut lo = 1;
+    let mut hi = 2;
+    loop {
+        if power_fn(hi)? >= power {
+            break;
+        }
+        if hi >= MAX_TOPICS {
+            return Err(ElinorError::Uncomputable(
+                "More than one million topics are required.".to_string(),
+            ));
+        }
+        lo = hi;
+        hi = (hi * 2).min(MAX_TOPICS);
+    }
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        if power_fn(mid)? >= power {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Ok(hi)
+}
+
+fn validate_probability(value: f64, name: &str) -> Result<()> {
+    if value <= 0.0 || value >= 1.0 || value.is_nan() {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The {name} must be in the range (0, 1)."
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_paired_t_power() {
+        // The exact power by the noncentral t distribution is 0.8078.
+        let power = paired_t_power(0.5, 34, 0.05).unwrap();
+        assert_abs_diff_eq!(power, 0.8077, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_paired_t_required_topics_negative_effect() {
+        assert_eq!(paired_t_required_topics(-0.5, 0.05, 0.8).unwrap(), 34);
+    }
+
+    #[test]
+    fn test_paired_t_required_topics_invalid() {
+        assert_eq!(
+            paired_t_required_topics(0.0, 0.05, 0.8),
+            Err(ElinorError::InvalidArgument(
+                "The effect size must be non-zero and finite.".to_string()
+            ))
+        );
+        assert_eq!(
+            paired_t_required_topics(0.5, 0.05, 1.0),
+            Err(ElinorError::InvalidArgument(
+                "The power must be in the range (0, 1).".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_one_way_anova_required_topics() {
+        // Cohen's f = 0.25 for three systems, i.e., Delta = 3 * 0.25^2.
+        // The exact answer by the noncentral F distribution is 53.
+        let effect = 3.0 * 0.25 * 0.25;
+        let n_topics = one_way_anova_required_topics(3, effect, 0.05, 0.8).unwrap();
+        assert_eq!(n_topics, 52);
+        assert_abs_diff_eq!(
+            one_way_anova_power(3, effect, 52, 0.05).unwrap(),
+            0.8029,
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn test_one_way_anova_power_zero_effect() {
+        // The power equals the significance level if there is no effect.
+        let power = one_way_anova_power(3, 0.0, 20, 0.05).unwrap();
+        assert_abs_diff_eq!(power, 0.05, epsilon = 1e-2);
+    }
+
+    #[test]
+    fn test_achieved_power_of_t_test() {
+        let stat = StudentTTest::from_paired_samples(vec![
+            (0.70, 0.50),
+            (0.30, 0.10),
+            (0.20, 0.00),
+            (0.60, 0.20),
+            (0.40, 0.40),
+        ])
+        .unwrap();
+        let power = achieved_power_of_t_test(&stat, 0.05).unwrap();
+        assert_abs_diff_eq!(
+            power,
+            paired_t_power(stat.effect_size(), 5, 0.05).unwrap(),
+            epsilon = 1e-10
+        );
+        assert!((0.0..=1.0).contains(&power));
+    }
+
+    #[test]
+    fn test_achieved_power_of_anova() {
+        let stat = TwoWayAnovaWithoutReplication::from_tupled_samples(
+            [
+                [0.7, 0.5, 0.1],
+                [0.3, 0.1, 0.0],
+                [0.2, 0.0, 0.1],
+                [0.6, 0.2, 0.3],
+            ],
+            3,
+        )
+        .unwrap();
+        let power = achieved_power_of_anova(&stat, 0.05).unwrap();
+        assert!(stat.between_system_f_stat() > 1.0);
+        assert!(power > 0.05 && power < 1.0);
+    }
+}
@@ -0,0 +1,391 @@
+//! Paired bootstrap test for the difference of quantiles.
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::interpolated_quantile_of_sorted;
+use crate::statistical_tests::map_resamples;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::quantile_of_sorted;
+use crate::Evaluation;
+
+/// Paired bootstrap test for the difference of a quantile of per-topic scores between two systems.
+///
+/// Mean-based tests can miss improvements concentrated in a part of the topics,
+/// such as the hardest ones.
+/// This test compares the $`q`$-quantiles (e.g., the median or the 10th/90th percentiles)
+/// of the scores of two systems, resampling the topics with replacement while keeping the pairs.
+/// The quantiles are linearly interpolated between the closest ranks.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::quantile_bootstrap_test::QuantileBootstrapTester;
+///
+/// // System A improves only the hard topics of system B.
+/// let a = vec![0.30, 0.35, 0.40, 0.50, 0.60, 0.70, 0.80, 0.90];
+/// let b = vec![0.00, 0.05, 0.10, 0.50, 0.60, 0.70, 0.80, 0.90];
+///
+/// let samples = a.into_iter().zip(b).collect::<Vec<_>>();
+/// let tester = QuantileBootstrapTester::new().with_random_state(42);
+///
+/// let median = tester.with_quantile(0.5).test(samples.clone())?;
+/// assert_abs_diff_eq!(median.difference(), 0.0);
+///
+/// let tail = tester.with_quantile(0.1).test(samples)?;
+/// assert_abs_diff_eq!(tail.quantile_a(), 0.335, epsilon = 1e-10);
+/// assert_abs_diff_eq!(tail.quantile_b(), 0.035, epsilon = 1e-10);
+/// assert!(tail.p_value() < median.p_value());
+///
+/// // Percentile confidence interval at a 95% confidence level.
+/// let (ci95_btm, ci95_top) = tail.confidence_interval(0.05)?;
+/// assert!(ci95_btm <= tail.difference() && tail.difference() <= ci95_top);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # References
+///
+/// * Bradley Efron and R.J. Tibshirani.
+///   [An Introduction to the Bootstrap](https://doi.org/10.1201/9780429246593).
+///   Chapman & Hall/CRC, 1994.
+#[derive(Debug, Clone)]
+pub struct QuantileBootstrapTest {
+    n_topics: usize,
+    n_resamples: usize,
+    random_state: u64,
+    quantile: f64,
+    quantile_a: f64,
+    quantile_b: f64,
+    p_value: f64,
+    resampled_diffs: Vec<f64>,
+}
+
+impl QuantileBootstrapTest {
+    /// Computes a bootstrap test for the medians of paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
+    ///
+    /// It uses the default parameters defined in [`QuantileBootstrapTester`].
+    /// To customize the parameters, use [`QuantileBootstrapTester`].
+    ///
+    /// # Errors
+    ///
+    /// See [`QuantileBootstrapTester::test`].
+    pub fn from_paired_samples<I>(samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        QuantileBootstrapTester::new().test(samples)
+    }
+
+    /// Computes a bootstrap test for the medians of the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
+    /// Number of topics, $`n`$.
+    pub const fn n_topics(&self) -> usize {
+        self.n_topics
+    }
+
+    /// Number of resamples.
+    pub const fn n_resamples(&self) -> usize {
+        self.n_resamples
+    }
+
+    /// Random state used for the resampling.
+    pub const fn random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    /// Quantile compared, $`q`$.
+    pub const fn quantile(&self) -> f64 {
+        self.quantile
+    }
+
+    /// $`q`$-quantile of the scores of system $`A`$.
+    pub const fn quantile_a(&self) -> f64 {
+        self.quantile_a
+    }
+
+    /// $`q`$-quantile of the scores of system $`B`$.
+    pub const fn quantile_b(&self) -> f64 {
+        self.quantile_b
+    }
+
+    /// Difference of the quantiles, $`Q_{q}(A) - Q_{q}(B)`$.
+    pub fn difference(&self) -> f64 {
+        self.quantile_a - self.quantile_b
+    }
+
+    /// p-value for the two-sided test, obtained by inverting the percentile confidence interval.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// p = \min\left(1, \frac{2}{B} \min\left(\#\{d^{*}_{b} \le 0\}, \#\{d^{*}_{b} \ge 0\}\right)\right)
+    /// ```
+    ///
+    /// where $`d^{*}_{b}`$ is the difference of the quantiles for the $`b`$-th of $`B`$ resamples.
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Percentile confidence interval of the difference at a given significance level $`\alpha`$,
+    /// formed by the $`\alpha/2`$ and $`1 - \alpha/2`$ quantiles of the resampled differences.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        Ok((
+            quantile_of_sorted(&self.resampled_diffs, significance_level / 2.0)?,
+            quantile_of_sorted(&self.resampled_diffs, 1.0 - significance_level / 2.0)?,
+        ))
+    }
+}
+
+/// Bootstrap tester for the difference of quantiles.
+///
+/// # Default parameters
+///
+/// * `quantile`: `0.5`
+/// * `n_resamples`: `10000`
+/// * `random_state`: `None`
+#[derive(Debug, Clone, Copy)]
+pub struct QuantileBootstrapTester {
+    quantile: f64,
+    n_resamples: usize,
+    random_state: Option<u64>,
+}
+
+impl Default for QuantileBootstrapTester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuantileBootstrapTester {
+    /// Creates a new bootstrap tester for the medians.
+    pub const fn new() -> Self {
+        Self {
+            quantile: 0.5,
+            n_resamples: 10000,
+            random_state: None,
+        }
+    }
+
+    /// Sets the quantile to compare, e.g., `0.1` for the 10th percentile.
+    pub const fn with_quantile(mut self, quantile: f64) -> Self {
+        self.quantile = quantile;
+        self
+    }
+
+    /// Sets the number of resamples.
+    ///
+    /// If the input is less than `1`, it is modified to `1`.
+    pub fn with_n_resamples(mut self, n_resamples: usize) -> Self {
+        self.n_resamples = n_resamples.max(1);
+        self
+    }
+
+    /// Sets the random state.
+    pub const fn with_random_state(mut self, random_state: u64) -> Self {
+        self.random_state = Some(random_state);
+        self
+    }
+
+    /// Computes a bootstrap test for the samples.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the quantile is not in the range `[0, 1]`.
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    pub fn test<I>(&self, samples: I) -> Result<QuantileBootstrapTest>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        if !(0.0..=1.0).contains(&self.quantile) {
+            return Err(ElinorError::InvalidArgument(
+                "The quantile must be in the range [0, 1].".to_string(),
+            ));
+        }
+        let samples: Vec<(f64, f64)> = samples.into_iter().collect();
+        if samples.len() <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two samples.".to_string(),
+            ));
+        }
+        if !samples.iter().all(|(a, b)| a.is_finite() && b.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
+
+        // Prepare the random number generator.
+        let random_state = self
+            .random_state
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(random_state);
+
+        let quantiles = |samples: &mut dyn Iterator<Item = (f64, f64)>| {
+            let (mut a, mut b): (Vec<f64>, Vec<f64>) = samples.unzip();
+            a.sort_by(f64::total_cmp);
+            b.sort_by(f64::total_cmp);
+            (
+                interpolated_quantile_of_sorted(&a, self.quantile),
+                interpolated_quantile_of_sorted(&b, self.quantile),
+            )
+        };
+        let (quantile_a, quantile_b) = quantiles(&mut samples.iter().copied());
+
+        let mut resampled_diffs = map_resamples(self.n_resamples, &mut rng, |rng| {
+            let (resampled_a, resampled_b) = quantiles(
+                &mut (0..samples.len()).map(|_| samples[rng.gen_range(0..samples.len())]),
+            );
+            resampled_a - resampled_b
+        });
+        resampled_diffs.sort_by(f64::total_cmp);
+
+        let n_non_positive = resampled_diffs.iter().filter(|&&d| d <= 0.0).count();
+        let n_non_negative = resampled_diffs.iter().filter(|&&d| d >= 0.0).count();
+        let p_value =
+            (2.0 * n_non_positive.min(n_non_negative) as f64 / self.n_resamples as f64).min(1.0);
+
+        Ok(QuantileBootstrapTest {
+            n_topics: samples.len(),
+            n_resamples: self.n_resamples,
+            random_state,
+            quantile: self.quantile,
+            quantile_a,
+            quantile_b,
+            p_value,
+            resampled_diffs,
+        })
+    }
+
+    /// Computes a bootstrap test for the per-query scores of two evaluations,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<QuantileBootstrapTest>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use rstest::*;
+
+    #[rstest]
+    #[case::nan_a(vec![(f64::NAN, 0.0), (1.0, 0.0)])]
+    #[case::nan_b(vec![(1.0, f64::NAN), (1.0, 0.0)])]
+    #[case::infinity(vec![(f64::INFINITY, 0.0), (1.0, 0.0)])]
+    fn test_quantile_bootstrap_test_from_paired_samples_non_finite(
+        #[case] samples: Vec<(f64, f64)>,
+    ) {
+        let result = QuantileBootstrapTest::from_paired_samples(samples);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quantile_bootstrap_test_from_paired_samples_single() {
+        let result = QuantileBootstrapTest::from_paired_samples(vec![(1.0, 0.0)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must have at least two samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quantile_bootstrap_tester_invalid_quantile() {
+        let tester = QuantileBootstrapTester::new().with_quantile(1.5);
+        assert_eq!(
+            tester.test(vec![(1.0, 0.0), (0.0, 1.0)]).unwrap_err(),
+            ElinorError::InvalidArgument("The quantile must be in the range [0, 1].".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quantile_bootstrap_tester_with_parameters() {
+        let tester = QuantileBootstrapTester::new()
+            .with_quantile(0.9)
+            .with_n_resamples(334)
+            .with_random_state(42);
+        let result = tester
+            .test(vec![(1.0, 0.0), (0.0, 1.0), (1.0, 3.0)])
+            .unwrap();
+        assert_eq!(result.quantile(), 0.9);
+        assert_eq!(result.n_resamples(), 334);
+        assert_eq!(result.random_state(), 42);
+        assert_abs_diff_eq!(result.quantile_a(), 1.0);
+        assert_abs_diff_eq!(result.quantile_b(), 2.6, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_quantile_bootstrap_test_dominance() {
+        // A is better than B on every topic, so every resampled difference is positive.
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.5, 0.4)];
+        let result = QuantileBootstrapTester::new()
+            .with_n_resamples(1000)
+            .with_random_state(42)
+            .test(samples)
+            .unwrap();
+        assert_abs_diff_eq!(result.difference(), 0.3, epsilon = 1e-10);
+        assert_eq!(result.p_value(), 0.0);
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert!(0.0 < ci95_btm && ci95_btm <= ci95_top);
+        assert_eq!(
+            result.confidence_interval(0.0).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_quantile_bootstrap_test_identical() {
+        let samples = vec![(0.7, 0.7), (0.3, 0.3), (0.2, 0.2)];
+        let result = QuantileBootstrapTester::new()
+            .with_n_resamples(100)
+            .with_random_state(42)
+            .test(samples)
+            .unwrap();
+        assert_eq!(result.difference(), 0.0);
+        assert_eq!(result.p_value(), 1.0);
+    }
+}
@@ -6,10 +6,17 @@ use rand::SeedableRng;
 use statrs::statistics::Statistics;
 
 use crate::errors::ElinorError;
+use crate::statistical_tests::map_resamples;
+use crate::statistical_tests::quantile_of_sorted;
+use crate::statistical_tests::tuples_from_evaluations;
+use crate::statistical_tests::Alternative;
+use crate::Evaluation;
 
 /// Randomized Tukey HSD test for comparing two or more systems.
 ///
-/// When comparing two systems, it is equivalent to Fisher's randomization test.
+/// When comparing two systems, it is equivalent to Fisher's randomization test,
+/// which is available as [`FisherRandomizationTest`](crate::statistical_tests::FisherRandomizationTest)
+/// with the details of the paired comparison.
 ///
 /// # Examples
 ///
@@ -35,6 +42,10 @@ use crate::errors::ElinorError;
 /// assert!((0.0..=1.0).contains(&p_values[0][1]));  // a vs. b
 /// assert!((0.0..=1.0).contains(&p_values[0][2]));  // a vs. c
 /// assert!((0.0..=1.0).contains(&p_values[1][2]));  // b vs. c
+///
+/// let effect_sizes = result.effect_sizes();
+/// assert!(effect_sizes[0][1] > 0.0);  // a vs. b
+/// assert_abs_diff_eq!(effect_sizes[0][1], -effect_sizes[1][0]);
 /// # Ok(())
 /// # }
 /// ```
@@ -56,7 +67,11 @@ pub struct RandomizedTukeyHsdTest {
     n_topics: usize,
     n_iters: usize,
     random_state: u64,
+    permutation_scheme: PermutationScheme,
+    alternative: Alternative,
     p_values: Vec<Vec<f64>>,
+    effect_sizes: Vec<Vec<f64>>,
+    null_ranges: Vec<f64>,
 }
 
 impl RandomizedTukeyHsdTest {
@@ -79,6 +94,19 @@ impl RandomizedTukeyHsdTest {
         RandomizedTukeyHsdTester::new(n_systems).test(samples)
     }
 
+    /// Creates a new randomized Tukey HSD test
+    /// from the per-query scores of evaluations $`A_1, A_2, \dots, A_m`$, tupled by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`tuples_from_evaluations`] and [`Self::from_tupled_samples`].
+    pub fn from_evaluations<K>(evaluations: &[Evaluation<K>]) -> Result<Self, ElinorError>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_tupled_samples(tuples_from_evaluations(evaluations)?, evaluations.len())
+    }
+
     /// Number of systems, $`m`$.
     pub const fn n_systems(&self) -> usize {
         self.n_systems
@@ -99,6 +127,16 @@ impl RandomizedTukeyHsdTest {
         self.random_state
     }
 
+    /// Permutation scheme used for the randomization.
+    pub const fn permutation_scheme(&self) -> &PermutationScheme {
+        &self.permutation_scheme
+    }
+
+    /// Alternative hypothesis of the test.
+    pub const fn alternative(&self) -> Alternative {
+        self.alternative
+    }
+
     /// p-values for all combinations of systems,
     /// returning a matrix of size $`m \times m`$,
     /// where $`m`$ is the number of systems.
@@ -106,9 +144,121 @@ impl RandomizedTukeyHsdTest {
     /// The $`(i, j)`$-th element has the p-value
     /// between the $`i`$-th and $`j`$-th systems.
     /// The diagonal elements are always one.
+    ///
+    /// For a one-sided alternative, which is available only for two systems,
+    /// both off-diagonal elements have the p-value for the difference of the first system from the second.
     pub fn p_values(&self) -> Vec<Vec<f64>> {
         self.p_values.clone()
     }
+
+    /// Effect sizes for all combinations of systems,
+    /// standardized by the permutation distribution,
+    /// returning a matrix of size $`m \times m`$ for $`m`$ systems.
+    ///
+    /// The $`(i, j)`$-th element is $`\text{ES}_{ij}`$.
+    /// The diagonal elements are always zero.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{ES}_{ij} = \frac{\bar{x}_{i*} - \bar{x}_{j*}}{\sigma^{*}_{ij}}
+    /// ```
+    ///
+    /// where
+    ///
+    /// * $`\bar{x}_{i*}`$ is the mean score of the $`i`$-th system, and
+    /// * $`\sigma^{*}_{ij}`$ is the standard deviation of $`\bar{x}^{*}_{i*} - \bar{x}^{*}_{j*}`$
+    ///   over the permuted samples.
+    ///
+    /// If $`\sigma^{*}_{ij}`$ is zero, e.g., when the systems are never exchanged,
+    /// the element is not finite.
+    pub fn effect_sizes(&self) -> Vec<Vec<f64>> {
+        self.effect_sizes.clone()
+    }
+
+    /// Quantile of the empirical null distribution of the range
+    /// $`\max_{i} \bar{x}^{*}_{i*} - \min_{i} \bar{x}^{*}_{i*}`$ over the permuted samples.
+    ///
+    /// For example, `null_quantile(0.95)` gives the critical difference at a 5% significance level.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the quantile is not in the range `[0, 1]`.
+    pub fn null_quantile(&self, q: f64) -> Result<f64, ElinorError> {
+        quantile_of_sorted(&self.null_ranges, q)
+    }
+}
+
+/// Scheme to permute system labels within each topic.
+///
+/// The appropriate null hypothesis depends on the experimental design,
+/// so the scheme should be chosen accordingly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PermutationScheme {
+    /// Permutes the labels of all systems for each topic.
+    #[default]
+    Full,
+
+    /// Swaps the labels of two systems with probability 0.5 for each topic,
+    /// i.e., flips the sign of the paired difference.
+    ///
+    /// It is available only for comparing two systems.
+    SignFlip,
+
+    /// Permutes the labels only within each group of system indices,
+    /// e.g., when systems in a group share components.
+    ///
+    /// Systems not in any group are never permuted.
+    Restricted(Vec<Vec<usize>>),
+}
+
+impl PermutationScheme {
+    fn validate(&self, n_systems: usize) -> Result<(), ElinorError> {
+        match self {
+            Self::Full => Ok(()),
+            Self::SignFlip => {
+                if n_systems != 2 {
+                    return Err(ElinorError::InvalidArgument(
+                        "The sign-flip scheme requires exactly two systems.".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            Self::Restricted(groups) => {
+                let mut used = vec![false; n_systems];
+                for &i in groups.iter().flatten() {
+                    if i >= n_systems || used[i] {
+                        return Err(ElinorError::InvalidArgument(
+                            "The groups must consist of distinct system indices less than the number of systems."
+                                .to_string(),
+                        ));
+                    }
+                    used[i] = true;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn permute<R: Rng>(&self, sample: &mut [f64], rng: &mut R) {
+        match self {
+            Self::Full => sample.shuffle(rng),
+            Self::SignFlip => {
+                if rng.gen::<bool>() {
+                    sample.swap(0, 1);
+                }
+            }
+            Self::Restricted(groups) => {
+                for group in groups {
+                    let mut values: Vec<f64> = group.iter().map(|&i| sample[i]).collect();
+                    values.shuffle(rng);
+                    for (&i, value) in group.iter().zip(values) {
+                        sample[i] = value;
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Randomized Tukey HSD tester.
@@ -117,11 +267,15 @@ impl RandomizedTukeyHsdTest {
 ///
 /// * `n_iters`: `10000`
 /// * `random_state`: `None`
+/// * `permutation_scheme`: [`PermutationScheme::Full`]
+/// * `alternative`: [`Alternative::TwoSided`]
 #[derive(Debug, Clone)]
 pub struct RandomizedTukeyHsdTester {
     n_systems: usize,
     n_iters: usize,
     random_state: Option<u64>,
+    permutation_scheme: PermutationScheme,
+    alternative: Alternative,
 }
 
 impl RandomizedTukeyHsdTester {
@@ -131,6 +285,8 @@ impl RandomizedTukeyHsdTester {
             n_systems,
             n_iters: 10000,
             random_state: None,
+            permutation_scheme: PermutationScheme::Full,
+            alternative: Alternative::TwoSided,
         }
     }
 
@@ -148,16 +304,42 @@ impl RandomizedTukeyHsdTester {
         self
     }
 
+    /// Sets the permutation scheme.
+    pub fn with_permutation_scheme(mut self, permutation_scheme: PermutationScheme) -> Self {
+        self.permutation_scheme = permutation_scheme;
+        self
+    }
+
+    /// Sets the alternative hypothesis on the difference of the first system from the second.
+    ///
+    /// One-sided alternatives are available only for comparing two systems,
+    /// where the test is equivalent to
+    /// [`FisherRandomizationTest`](crate::statistical_tests::FisherRandomizationTest).
+    pub const fn with_alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
     /// Computes a randomized Tukey HSD test for the samples.
     ///
     /// # Errors
     ///
     /// * [`ElinorError::InvalidArgument`] if the length of each sample is not equal to the number of systems.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite score.
+    /// * [`ElinorError::InvalidArgument`] if the permutation scheme is not applicable to the systems.
+    /// * [`ElinorError::InvalidArgument`] if the alternative is one-sided but the number of systems is not two.
     pub fn test<I, S>(&self, samples: I) -> Result<RandomizedTukeyHsdTest, ElinorError>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<[f64]>,
     {
+        self.permutation_scheme.validate(self.n_systems)?;
+        if self.alternative != Alternative::TwoSided && self.n_systems != 2 {
+            return Err(ElinorError::InvalidArgument(
+                "One-sided alternatives require exactly two systems.".to_string(),
+            ));
+        }
+
         let samples: Vec<Vec<f64>> = samples
             .into_iter()
             .map(|topic| {
@@ -168,6 +350,11 @@ impl RandomizedTukeyHsdTester {
                             .to_string(),
                     ));
                 }
+                if !topic.iter().all(|x| x.is_finite()) {
+                    return Err(ElinorError::InvalidArgument(
+                        "The input must not have non-finite scores.".to_string(),
+                    ));
+                }
                 Ok(topic.to_vec())
             })
             .collect::<Result<_, _>>()?;
@@ -183,7 +370,7 @@ impl RandomizedTukeyHsdTester {
         // Prepare the random number generator.
         let random_state = self
             .random_state
-            .map_or_else(|| rand::thread_rng().gen(), |seed| seed);
+            .unwrap_or_else(|| rand::thread_rng().gen());
         let mut rng = StdRng::seed_from_u64(random_state);
 
         // Compute the means of each system.
@@ -200,34 +387,60 @@ impl RandomizedTukeyHsdTester {
             }
         }
 
-        let mut counts = vec![vec![0_usize; self.n_systems]; self.n_systems];
-        for _ in 0..self.n_iters {
+        let all_shuffled_means = map_resamples(self.n_iters, &mut rng, |rng| {
             let mut shuffled_samples = Vec::with_capacity(samples.len());
             for sample in &samples {
                 let mut shuffled_sample = sample.clone();
-                shuffled_sample.shuffle(&mut rng);
+                self.permutation_scheme.permute(&mut shuffled_sample, rng);
                 shuffled_samples.push(shuffled_sample);
             }
-
-            let shuffled_means: Vec<_> = (0..self.n_systems)
+            (0..self.n_systems)
                 .map(|i| shuffled_samples.iter().map(|sample| sample[i]).sum::<f64>() / n_samples)
-                .collect();
+                .collect::<Vec<_>>()
+        });
 
+        let mut counts = vec![vec![0_usize; self.n_systems]; self.n_systems];
+        let mut null_sums = vec![vec![0_f64; self.n_systems]; self.n_systems];
+        let mut null_sq_sums = vec![vec![0_f64; self.n_systems]; self.n_systems];
+        let mut null_ranges = Vec::with_capacity(self.n_iters);
+        for shuffled_means in all_shuffled_means {
             let shuffled_diff = shuffled_means.as_slice().max() - shuffled_means.as_slice().min();
+            null_ranges.push(shuffled_diff);
             for i in 0..self.n_systems {
                 for j in (i + 1)..self.n_systems {
-                    if shuffled_diff >= diffs[i][j].abs() {
+                    let null_diff = shuffled_means[i] - shuffled_means[j];
+                    // For two systems, the range is the absolute difference,
+                    // so the one-sided tests compare the signed differences instead.
+                    let exceeds = match self.alternative {
+                        Alternative::TwoSided => shuffled_diff >= diffs[i][j].abs(),
+                        _ => {
+                            self.alternative.orient(null_diff)
+                                >= self.alternative.orient(diffs[i][j])
+                        }
+                    };
+                    if exceeds {
                         counts[i][j] += 1;
                     }
+                    null_sums[i][j] += null_diff;
+                    null_sq_sums[i][j] += null_diff * null_diff;
                 }
             }
         }
 
+        null_ranges.sort_by(f64::total_cmp);
+        let n_iters = self.n_iters as f64;
         let mut p_values = vec![vec![1_f64; self.n_systems]; self.n_systems];
+        let mut effect_sizes = vec![vec![0_f64; self.n_systems]; self.n_systems];
         for i in 0..self.n_systems {
             for j in (i + 1)..self.n_systems {
-                p_values[i][j] = counts[i][j] as f64 / self.n_iters as f64;
+                p_values[i][j] = counts[i][j] as f64 / n_iters;
                 p_values[j][i] = p_values[i][j];
+                let null_mean = null_sums[i][j] / n_iters;
+                let null_variance = null_mean
+                    .mul_add(-null_mean, null_sq_sums[i][j] / n_iters)
+                    .max(0.0);
+                effect_sizes[i][j] = diffs[i][j] / null_variance.sqrt();
+                effect_sizes[j][i] = -effect_sizes[i][j];
             }
         }
 
@@ -236,14 +449,35 @@ impl RandomizedTukeyHsdTester {
             n_topics: samples.len(),
             n_iters: self.n_iters,
             random_state,
+            permutation_scheme: self.permutation_scheme.clone(),
+            alternative: self.alternative,
             p_values,
+            effect_sizes,
+            null_ranges,
         })
     }
+
+    /// Computes a randomized Tukey HSD test for the per-query scores of evaluations,
+    /// tupled by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`tuples_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluations: &[Evaluation<K>],
+    ) -> Result<RandomizedTukeyHsdTest, ElinorError>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(tuples_from_evaluations(evaluations)?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_abs_diff_eq;
 
     #[test]
     fn test_randomized_tukey_hsd_test_from_tupled_samples_empty() {
@@ -273,4 +507,131 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_randomized_tukey_hsd_test_from_tupled_samples_non_finite() {
+        let samples = vec![[1.0, 2.0], [f64::NAN, 3.0]];
+        let result = RandomizedTukeyHsdTest::from_tupled_samples(samples, 2);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite scores.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_sign_flip() {
+        let samples = vec![[0.7, 0.5], [0.3, 0.1], [0.2, 0.0], [0.6, 0.2], [0.4, 0.4]];
+        let tester = RandomizedTukeyHsdTester::new(2)
+            .with_random_state(42)
+            .with_permutation_scheme(PermutationScheme::SignFlip);
+        let result = tester.test(samples).unwrap();
+        assert_eq!(result.permutation_scheme(), &PermutationScheme::SignFlip);
+        assert!((0.0..=1.0).contains(&result.p_values()[0][1]));
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_sign_flip_invalid_n_systems() {
+        let samples = vec![[0.7, 0.5, 0.1]];
+        let tester =
+            RandomizedTukeyHsdTester::new(3).with_permutation_scheme(PermutationScheme::SignFlip);
+        assert_eq!(
+            tester.test(samples).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The sign-flip scheme requires exactly two systems.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_restricted() {
+        // System 2 is never exchanged with the others,
+        // so the observed range of the means is always reproduced.
+        let samples = vec![[0.5, 0.5, 0.0], [0.4, 0.4, 0.0], [0.3, 0.3, 0.0]];
+        let tester = RandomizedTukeyHsdTester::new(3)
+            .with_random_state(42)
+            .with_permutation_scheme(PermutationScheme::Restricted(vec![vec![0, 1]]));
+        let p_values = tester.test(samples).unwrap().p_values();
+        assert_eq!(p_values[0][1], 1.0);
+        assert_eq!(p_values[0][2], 1.0);
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_restricted_invalid_groups() {
+        let samples = vec![[0.7, 0.5, 0.1]];
+        for groups in [vec![vec![0, 3]], vec![vec![0, 1], vec![1, 2]]] {
+            let tester = RandomizedTukeyHsdTester::new(3)
+                .with_permutation_scheme(PermutationScheme::Restricted(groups));
+            assert_eq!(
+                tester.test(samples.clone()).unwrap_err(),
+                ElinorError::InvalidArgument(
+                    "The groups must consist of distinct system indices less than the number of systems."
+                        .to_string()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_test_effect_sizes() {
+        // Under sign-flipping, the null difference is a sum of ±d_j / n,
+        // so its standard deviation is sqrt(sum_j d_j^2) / n.
+        let samples = vec![[0.7, 0.5], [0.3, 0.1], [0.2, 0.0], [0.6, 0.2], [0.4, 0.4]];
+        let tester = RandomizedTukeyHsdTester::new(2)
+            .with_n_iters(100000)
+            .with_random_state(42)
+            .with_permutation_scheme(PermutationScheme::SignFlip);
+        let effect_sizes = tester.test(samples).unwrap().effect_sizes();
+        let null_stddev = 0.04_f64.mul_add(3.0, 0.16).sqrt() / 5.0;
+        assert_abs_diff_eq!(effect_sizes[0][1], 0.2 / null_stddev, epsilon = 1e-2);
+        assert_abs_diff_eq!(effect_sizes[1][0], -effect_sizes[0][1]);
+        assert_eq!(effect_sizes[0][0], 0.0);
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_test_null_quantile() {
+        let samples = vec![[0.7, 0.5], [0.3, 0.1], [0.2, 0.0], [0.6, 0.2], [0.4, 0.4]];
+        let tester = RandomizedTukeyHsdTester::new(2).with_random_state(42);
+        let result = tester.test(samples).unwrap();
+        // All differences are non-negative, so no permutation exceeds the observed one.
+        let critical = result.null_quantile(0.95).unwrap();
+        assert!(result.null_quantile(0.0).unwrap() <= critical);
+        assert!(critical <= result.null_quantile(1.0).unwrap());
+        assert!(result.null_quantile(1.0).unwrap() <= 0.2 + 1e-10);
+        assert_eq!(
+            result.null_quantile(-0.1).unwrap_err(),
+            ElinorError::InvalidArgument("The quantile must be in the range [0, 1].".to_string())
+        );
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_alternative() {
+        let samples = vec![[0.7, 0.5], [0.3, 0.1], [0.2, 0.0], [0.6, 0.2], [0.4, 0.4]];
+        let test = |alternative| {
+            RandomizedTukeyHsdTester::new(2)
+                .with_random_state(42)
+                .with_alternative(alternative)
+                .test(samples.clone())
+                .unwrap()
+        };
+        let two_sided = test(Alternative::TwoSided).p_values()[0][1];
+        let greater = test(Alternative::Greater);
+        let less = test(Alternative::Less).p_values()[0][1];
+        assert_eq!(greater.alternative(), Alternative::Greater);
+        let greater = greater.p_values()[0][1];
+        // All differences are non-negative, so no permutation increases the observed difference.
+        assert!(greater <= two_sided);
+        assert_eq!(less, 1.0);
+    }
+
+    #[test]
+    fn test_randomized_tukey_hsd_tester_alternative_invalid_n_systems() {
+        let samples = vec![[0.7, 0.5, 0.1]];
+        let tester = RandomizedTukeyHsdTester::new(3).with_alternative(Alternative::Greater);
+        assert_eq!(
+            tester.test(samples).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "One-sided alternatives require exactly two systems.".to_string()
+            )
+        );
+    }
 }
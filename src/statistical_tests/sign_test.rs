@@ -0,0 +1,298 @@
+//! Two-sided paired sign test.
+
+use statrs::distribution::Binomial;
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::DiscreteCDF;
+use statrs::distribution::Normal;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::SignificanceTest;
+use crate::Evaluation;
+
+/// Tolerance to regard a difference as a tie,
+/// absorbing floating-point errors in the subtraction of scores.
+const TIE_TOLERANCE: f64 = 1e-10;
+
+/// Two-sided paired sign test.
+///
+/// The test only counts the wins and losses of the first system over the topics,
+/// and the ties are discarded.
+/// It makes no assumption on the distribution of the differences,
+/// which is useful for skewed per-topic score distributions.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::SignTest;
+///
+/// let a = vec![0.60, 0.40, 0.20, 0.70, 0.90, 0.30];
+/// let b = vec![0.50, 0.10, 0.40, 0.30, 0.40, 0.30];
+///
+/// // a - b = [0.10, 0.30, -0.20, 0.40, 0.50, 0.00]
+/// let samples = a.into_iter().zip(b);
+/// let result = SignTest::from_paired_samples(samples)?;
+/// assert_eq!(result.n_wins(), 4);
+/// assert_eq!(result.n_losses(), 1);
+/// assert_eq!(result.n_ties(), 1);
+///
+/// // Exact p-value: 2 * P(X <= 1) for X ~ Binomial(5, 0.5).
+/// assert_abs_diff_eq!(result.p_value(), 2.0 * 6.0 / 32.0, epsilon = 1e-10);
+/// assert!((0.0..=1.0).contains(&result.approx_p_value()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SignTest {
+    sorted_diffs: Vec<f64>,
+    n_wins: usize,
+    n_losses: usize,
+    p_value: f64,
+    z_stat: f64,
+    approx_p_value: f64,
+}
+
+impl SignTest {
+    /// Computes a sign test for $`n`$ samples $`x_{1},x_{2},\dots,x_{n}`$,
+    /// where $`x_{i} = a_{i} - b_{i}`$ for given paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    /// * [`ElinorError::Uncomputable`] if all the differences are ties.
+    pub fn from_paired_samples<I>(samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let mut sorted_diffs: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
+        if sorted_diffs.len() <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two samples.".to_string(),
+            ));
+        }
+        if !sorted_diffs.iter().all(|d| d.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
+        sorted_diffs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n_wins = sorted_diffs.iter().filter(|&&d| d > TIE_TOLERANCE).count();
+        let n_losses = sorted_diffs.iter().filter(|&&d| d < -TIE_TOLERANCE).count();
+        let n = n_wins + n_losses;
+        if n == 0 {
+            return Err(ElinorError::Uncomputable(
+                "All the differences are ties.".to_string(),
+            ));
+        }
+
+        let binomial = Binomial::new(0.5, n as u64).unwrap();
+        let p_value = (binomial.cdf(n_wins.min(n_losses) as u64) * 2.0).min(1.0);
+
+        let diff = n_wins.abs_diff(n_losses) as f64;
+        let z_stat = ((diff - 1.0) / (n as f64).sqrt()).max(0.0);
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let approx_p_value = (normal.sf(z_stat) * 2.0).min(1.0);
+
+        Ok(Self {
+            sorted_diffs,
+            n_wins,
+            n_losses,
+            p_value,
+            z_stat,
+            approx_p_value,
+        })
+    }
+
+    /// Computes a sign test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
+    /// Number of topics, $`n`$.
+    pub fn n_topics(&self) -> usize {
+        self.sorted_diffs.len()
+    }
+
+    /// Number of topics where the first system wins, $`n^{+}`$.
+    pub const fn n_wins(&self) -> usize {
+        self.n_wins
+    }
+
+    /// Number of topics where the first system loses, $`n^{-}`$.
+    pub const fn n_losses(&self) -> usize {
+        self.n_losses
+    }
+
+    /// Number of tied topics.
+    pub fn n_ties(&self) -> usize {
+        self.sorted_diffs.len() - self.n_wins - self.n_losses
+    }
+
+    /// Exact p-value for the two-sided test.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// p = 2 P(X \le \min(n^{+}, n^{-})), \quad X \sim \text{Binomial}(n^{+} + n^{-}, 0.5)
+    /// ```
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// z-statistic of the normal approximation with the continuity correction.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// z = \frac{|n^{+} - n^{-}| - 1}{\sqrt{n^{+} + n^{-}}}
+    /// ```
+    pub const fn z_stat(&self) -> f64 {
+        self.z_stat
+    }
+
+    /// p-value for the two-sided test by the continuity-corrected normal approximation.
+    pub const fn approx_p_value(&self) -> f64 {
+        self.approx_p_value
+    }
+
+    /// Sample effect size, i.e., the difference between the proportions of wins and losses.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{ES} = \frac{n^{+} - n^{-}}{n^{+} + n^{-}}
+    /// ```
+    pub fn effect_size(&self) -> f64 {
+        (self.n_wins as f64 - self.n_losses as f64) / (self.n_wins + self.n_losses) as f64
+    }
+
+    /// Distribution-free confidence interval of the median difference at a given significance level $`\alpha`$,
+    /// given by the order statistics $`[x_{(k)}, x_{(n-k+1)}]`$ of the differences,
+    /// where $`k`$ is the largest integer such that $`2 P(X \le k - 1) \le \alpha`$ for $`X \sim \text{Binomial}(n, 0.5)`$.
+    ///
+    /// If no such $`k`$ exists (i.e., too few topics), the range of the differences is returned.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let n = self.sorted_diffs.len();
+        let binomial = Binomial::new(0.5, n as u64).unwrap();
+        let mut k = 1;
+        while k < (n + 1) / 2 && binomial.cdf(k as u64) * 2.0 <= significance_level {
+            k += 1;
+        }
+        Ok((self.sorted_diffs[k - 1], self.sorted_diffs[n - k]))
+    }
+}
+
+impl SignificanceTest for SignTest {
+    fn method_name(&self) -> &'static str {
+        "Two-sided paired sign test"
+    }
+
+    fn p_value(&self) -> f64 {
+        Self::p_value(self)
+    }
+
+    fn effect_size(&self) -> f64 {
+        Self::effect_size(self)
+    }
+
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        Self::confidence_interval(self, significance_level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_sign_test_one_sample() {
+        let result = SignTest::from_paired_samples(vec![(0.0, 1.0)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must have at least two samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sign_test_non_finite() {
+        let result = SignTest::from_paired_samples(vec![(0.0, 1.0), (f64::NAN, 0.5)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must not have non-finite samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sign_test_all_ties() {
+        let result = SignTest::from_paired_samples(vec![(1.0, 1.0), (0.3, 0.3)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::Uncomputable("All the differences are ties.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sign_test_sakai_book_15() {
+        // From Table 5.1 in Sakai's book, "情報アクセス評価方法論".
+        let a = vec![
+            0.70, 0.30, 0.20, 0.60, 0.40, 0.40, 0.00, 0.70, 0.10, 0.30, //
+            0.50, 0.40, 0.00, 0.60, 0.50, 0.30, 0.10, 0.50, 0.20, 0.10,
+        ];
+        let b = vec![
+            0.50, 0.10, 0.00, 0.20, 0.40, 0.30, 0.00, 0.50, 0.30, 0.30, //
+            0.40, 0.40, 0.10, 0.40, 0.20, 0.10, 0.10, 0.60, 0.30, 0.20,
+        ];
+        let samples = a.into_iter().zip(b);
+        let result = SignTest::from_paired_samples(samples).unwrap();
+
+        assert_eq!(result.n_topics(), 20);
+        assert_eq!(result.n_wins(), 10);
+        assert_eq!(result.n_losses(), 5);
+        assert_eq!(result.n_ties(), 5);
+        assert_abs_diff_eq!(result.effect_size(), 5.0 / 15.0);
+
+        // 2 * P(X <= 5) for X ~ Binomial(15, 0.5) = 2 * 4944 / 32768.
+        assert_abs_diff_eq!(result.p_value(), 0.3018, epsilon = 1e-4);
+        // z = (5 - 1) / sqrt(15).
+        assert_abs_diff_eq!(result.z_stat(), 1.0328, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.approx_p_value(), 0.3017, epsilon = 1e-4);
+
+        // 2 * P(X <= 5) = 0.0414 and 2 * P(X <= 6) = 0.1153 for X ~ Binomial(20, 0.5), so k = 6.
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert_abs_diff_eq!(ci95_btm, 0.0, epsilon = 1e-10);
+        assert_abs_diff_eq!(ci95_top, 0.2, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_sign_test_confidence_interval_few_topics() {
+        let result = SignTest::from_paired_samples(vec![(2.0, 1.0), (2.0, 0.5)]).unwrap();
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        assert_abs_diff_eq!(ci95_btm, 1.0);
+        assert_abs_diff_eq!(ci95_top, 1.5);
+    }
+}
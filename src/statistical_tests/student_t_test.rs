@@ -1,4 +1,4 @@
-//! Two-sided paired Student's t-test
+//! Paired Student's t-test.
 
 use statrs::distribution::ContinuousCDF;
 use statrs::distribution::StudentsT;
@@ -6,8 +6,17 @@ use statrs::statistics::Statistics;
 
 use crate::errors::ElinorError;
 use crate::errors::Result;
+use crate::statistical_tests::effective_sample_size;
+use crate::statistical_tests::noncentrality_confidence_interval;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::weighted_differences;
+use crate::statistical_tests::Alternative;
+use crate::statistical_tests::SignificanceTest;
+use crate::Evaluation;
 
-/// Two-sided paired Student's t-test.
+/// Paired Student's t-test.
+///
+/// It is two-sided by default. Use [`StudentTTester`] to specify an [`Alternative`].
 ///
 /// # Examples
 ///
@@ -22,7 +31,7 @@ use crate::errors::Result;
 /// let b = vec![0.50, 0.10, 0.00];
 ///
 /// // a - b = [0.10, 0.00, 0.20]
-/// let samples = a.into_iter().zip(b.into_iter());
+/// let samples = a.into_iter().zip(b);
 /// let result = StudentTTest::from_paired_samples(samples)?;
 /// assert_eq!(result.n_samples(), 3);
 ///
@@ -44,9 +53,35 @@ use crate::errors::Result;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// An example to test whether a system is better than a baseline:
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::Alternative;
+/// use elinor::statistical_tests::student_t_test::StudentTTester;
+///
+/// let system = vec![0.60, 0.10, 0.20];
+/// let baseline = vec![0.50, 0.10, 0.00];
+/// let samples = system.into_iter().zip(baseline).collect::<Vec<_>>();
+///
+/// let two_sided = StudentTTester::new().test(samples.clone())?;
+/// let greater = StudentTTester::new()
+///     .with_alternative(Alternative::Greater)
+///     .test(samples)?;
+/// assert_eq!(greater.alternative(), Alternative::Greater);
+///
+/// // The mean difference is positive, so the one-tailed p-value is half of the two-tailed one.
+/// assert_abs_diff_eq!(greater.p_value(), two_sided.p_value() / 2.0);
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug, Clone)]
 pub struct StudentTTest {
     n_topics: usize,
+    effective_n_topics: f64,
+    alternative: Alternative,
     mean: f64,
     variance: f64,
     t_stat: f64,
@@ -55,36 +90,35 @@ pub struct StudentTTest {
 }
 
 impl StudentTTest {
-    /// Computes a Student's t-test for $`n`$ samples $`x_{1},x_{2},\dots,x_{n}`$,
+    /// Computes a two-sided Student's t-test for $`n`$ samples $`x_{1},x_{2},\dots,x_{n}`$,
     /// where $`x_{i} = a_{i} - b_{i}`$ for given paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
     ///
+    /// To specify an alternative hypothesis, use [`StudentTTester`].
+    ///
     /// # Errors
     ///
-    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
-    /// * [`ElinorError::Uncomputable`] if the variance is zero.
+    /// See [`StudentTTester::test`].
     pub fn from_paired_samples<I>(samples: I) -> Result<Self>
     where
         I: IntoIterator<Item = (f64, f64)>,
     {
-        let samples: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
-        if samples.len() <= 1 {
-            return Err(ElinorError::InvalidArgument(
-                "The input must have at least two samples.".to_string(),
-            ));
-        }
-        let (t_stat, mean, variance) = compute_t_stat(&samples)?;
-        let n = samples.len() as f64;
-        let t_dist = StudentsT::new(0.0, 1.0, n - 1.0).unwrap();
-        let p_value = t_dist.sf(t_stat.abs()) * 2.0; // two-tailed
-        let scaled_t_dist = StudentsT::new(0.0, (variance / n).sqrt(), n - 1.0).unwrap();
-        Ok(Self {
-            n_topics: samples.len(),
-            mean,
-            variance,
-            t_stat,
-            p_value,
-            scaled_t_dist,
-        })
+        StudentTTester::new().test(samples)
+    }
+
+    /// Computes a Student's t-test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
     }
 
     /// Number of topics, $`n`$.
@@ -92,8 +126,28 @@ impl StudentTTest {
         self.n_topics
     }
 
+    /// Effective number of topics, $`n_e`$, which is $`n`$ for unweighted samples.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// n_e = \frac{(\sum_{i=1}^{n} w_{i})^{2}}{\sum_{i=1}^{n} w_{i}^{2}}
+    /// ```
+    ///
+    /// where $`w_{i}`$ is the weight of the $`i`$-th topic given to [`StudentTTester::test_weighted`].
+    /// For weighted samples, $`n_e`$ substitutes for $`n`$ in the formulas of the statistics,
+    /// and the mean and variance are weighted ones.
+    pub const fn effective_n_topics(&self) -> f64 {
+        self.effective_n_topics
+    }
+
+    /// Alternative hypothesis of the test.
+    pub const fn alternative(&self) -> Alternative {
+        self.alternative
+    }
+
     /// Number of samples, $`n`$.
-    #[deprecated(since = "0.5.0", note = "Use `n_topics` instead.")]
+    #[deprecated(since = "0.4.0", note = "Use `n_topics` instead.")]
     pub const fn n_samples(&self) -> usize {
         self.n_topics
     }
@@ -142,22 +196,27 @@ impl StudentTTest {
         self.t_stat
     }
 
-    /// p-value for the two-sided test.
+    /// p-value under the alternative hypothesis.
     ///
     /// # Formula
     ///
     /// ```math
-    /// p = P(|t_0| > t_{\alpha/2}(n-1))
+    /// p = \begin{cases}
+    /// 2 P(T > |t_0|) & \text{(two-sided)} \\
+    /// P(T > t_0) & \text{(greater)} \\
+    /// P(T < t_0) & \text{(less)}
+    /// \end{cases}
     /// ```
     ///
-    /// where $`t_{\alpha/2}(n-1)`$ is the $`1 - \alpha/2`$ quantile of the Student's t-distribution
-    /// with $`n-1`$ degrees of freedom.
+    /// where $`T`$ follows the Student's t-distribution with $`n-1`$ degrees of freedom.
     pub const fn p_value(&self) -> f64 {
         self.p_value
     }
 
     /// Margin of error at a given significance level $`\alpha`$.
     ///
+    /// It is always for the two-sided confidence interval, regardless of the alternative hypothesis.
+    ///
     /// # Errors
     ///
     /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
@@ -193,6 +252,193 @@ impl StudentTTest {
         let moe = self.margin_of_error(significance_level)?;
         Ok((self.mean - moe, self.mean + moe))
     }
+
+    /// Confidence interval of the effect size at a given significance level $`\alpha`$.
+    ///
+    /// It is obtained from the noncentral t-distribution with $`n-1`$ degrees of freedom,
+    /// whose noncentrality parameter is $`\lambda = \sqrt{n} \cdot \text{ES}`$.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{CI} = \left[\frac{\lambda_L}{\sqrt{n}}, \frac{\lambda_U}{\sqrt{n}}\right]
+    /// ```
+    ///
+    /// where $`\lambda_L`$ and $`\lambda_U`$ are the noncentrality parameters such that
+    /// $`P(T \le t_0 \mid \lambda_L) = 1 - \alpha/2`$ and $`P(T \le t_0 \mid \lambda_U) = \alpha/2`$.
+    pub fn effect_size_confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let n = self.effective_n_topics;
+        let (lower, upper) =
+            noncentrality_confidence_interval(self.t_stat, n - 1.0, significance_level);
+        Ok((lower / n.sqrt(), upper / n.sqrt()))
+    }
+}
+
+impl SignificanceTest for StudentTTest {
+    fn method_name(&self) -> &'static str {
+        match self.alternative {
+            Alternative::TwoSided => "Two-sided paired Student's t-test",
+            Alternative::Greater | Alternative::Less => "One-sided paired Student's t-test",
+        }
+    }
+
+    fn p_value(&self) -> f64 {
+        Self::p_value(self)
+    }
+
+    fn effect_size(&self) -> f64 {
+        Self::effect_size(self)
+    }
+
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        Self::confidence_interval(self, significance_level)
+    }
+}
+
+/// Paired Student's t-tester.
+///
+/// # Default parameters
+///
+/// * `alternative`: [`Alternative::TwoSided`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StudentTTester {
+    alternative: Alternative,
+}
+
+impl StudentTTester {
+    /// Creates a new Student's t-tester.
+    pub const fn new() -> Self {
+        Self {
+            alternative: Alternative::TwoSided,
+        }
+    }
+
+    /// Sets the alternative hypothesis.
+    pub const fn with_alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Computes a Student's t-test for the samples.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::Uncomputable`] if the variance is zero.
+    pub fn test<I>(&self, samples: I) -> Result<StudentTTest>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let samples: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
+        if samples.len() <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two samples.".to_string(),
+            ));
+        }
+        let (t_stat, mean, variance) = compute_t_stat(&samples)?;
+        Ok(self.build(samples.len(), samples.len() as f64, t_stat, mean, variance))
+    }
+
+    /// Computes a Student's t-test for the samples weighted per topic (e.g., by traffic),
+    /// where each sample is a triple $`(a_{i}, b_{i}, w_{i})`$ of the paired scores and the weight.
+    ///
+    /// The mean and variance are weighted ones,
+    /// and the [effective number of topics](StudentTTest::effective_n_topics) substitutes for $`n`$.
+    /// With equal weights, the result is the same as that of [`Self::test`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use approx::assert_abs_diff_eq;
+    /// use elinor::statistical_tests::student_t_test::StudentTTester;
+    ///
+    /// let samples = vec![(0.60, 0.50, 3.0), (0.10, 0.10, 1.0), (0.20, 0.00, 1.0)];
+    /// let result = StudentTTester::new().test_weighted(samples)?;
+    /// assert_abs_diff_eq!(result.mean(), (0.10 * 3.0 + 0.00 + 0.20) / 5.0);
+    /// assert_abs_diff_eq!(result.effective_n_topics(), 25.0 / 11.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the weights are negative, non-finite, or all zero.
+    /// * [`ElinorError::InvalidArgument`] if the effective number of topics is not greater than one.
+    /// * [`ElinorError::Uncomputable`] if the variance is zero.
+    pub fn test_weighted<I>(&self, samples: I) -> Result<StudentTTest>
+    where
+        I: IntoIterator<Item = (f64, f64, f64)>,
+    {
+        let (samples, weights) = weighted_differences(samples)?;
+        if samples.is_empty() || effective_sample_size(&weights) <= 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have an effective number of samples greater than one.".to_string(),
+            ));
+        }
+        let (t_stat, mean, variance) = compute_weighted_t_stat(&samples, &weights)?;
+        Ok(self.build(
+            samples.len(),
+            effective_sample_size(&weights),
+            t_stat,
+            mean,
+            variance,
+        ))
+    }
+
+    fn build(
+        &self,
+        n_topics: usize,
+        effective_n_topics: f64,
+        t_stat: f64,
+        mean: f64,
+        variance: f64,
+    ) -> StudentTTest {
+        let n = effective_n_topics;
+        let t_dist = StudentsT::new(0.0, 1.0, n - 1.0).unwrap();
+        let p_value = match self.alternative {
+            Alternative::TwoSided => t_dist.sf(t_stat.abs()) * 2.0,
+            Alternative::Greater => t_dist.sf(t_stat),
+            Alternative::Less => t_dist.cdf(t_stat),
+        };
+        let scaled_t_dist = StudentsT::new(0.0, (variance / n).sqrt(), n - 1.0).unwrap();
+        StudentTTest {
+            n_topics,
+            effective_n_topics,
+            alternative: self.alternative,
+            mean,
+            variance,
+            t_stat,
+            p_value,
+            scaled_t_dist,
+        }
+    }
+
+    /// Computes a Student's t-test for the per-query scores of two evaluations,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::test`].
+    pub fn test_evaluations<K>(
+        &self,
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<StudentTTest>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        self.test(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
 }
 
 /// Computes a t-statistic, returning:
@@ -217,6 +463,46 @@ pub fn compute_t_stat(samples: &[f64]) -> Result<(f64, f64, f64)> {
     Ok((t_stat, mean, variance))
 }
 
+/// Computes a t-statistic for the weighted samples, returning:
+///
+/// * the t-statistic,
+/// * the weighted mean, and
+/// * the unbiased weighted variance with reliability weights.
+///
+/// The effective sample size substitutes for the number of samples,
+/// so the results are the same as those of [`compute_t_stat`] for equal weights.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the samples and weights have different lengths.
+/// * [`ElinorError::Uncomputable`] if the variance is zero.
+pub fn compute_weighted_t_stat(samples: &[f64], weights: &[f64]) -> Result<(f64, f64, f64)> {
+    if samples.len() != weights.len() {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The samples and weights must have the same length, but got {} and {}.",
+            samples.len(),
+            weights.len()
+        )));
+    }
+    let sum = weights.iter().sum::<f64>();
+    let sum_sq = weights.iter().map(|w| w * w).sum::<f64>();
+    let mean = samples.iter().zip(weights).map(|(x, w)| x * w).sum::<f64>() / sum;
+    let variance = samples
+        .iter()
+        .zip(weights)
+        .map(|(x, w)| w * (x - mean).powi(2))
+        .sum::<f64>()
+        / (sum - sum_sq / sum);
+    if variance == 0.0 || !variance.is_finite() {
+        return Err(ElinorError::Uncomputable(
+            "The variance is zero.".to_string(),
+        ));
+    }
+    let n = sum * sum / sum_sq;
+    let t_stat = mean / (variance / n).sqrt();
+    Ok((t_stat, mean, variance))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,7 +583,7 @@ mod tests {
             0.40, 0.40, 0.10, 0.40, 0.20, 0.10, 0.10, 0.60, 0.30, 0.20,
         ];
 
-        let samples = a.into_iter().zip(b.into_iter());
+        let samples = a.into_iter().zip(b);
         let result = StudentTTest::from_paired_samples(samples).unwrap();
 
         assert_abs_diff_eq!(result.mean(), 0.0750, epsilon = 1e-4);
@@ -312,5 +598,104 @@ mod tests {
         let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
         assert_abs_diff_eq!(ci95_btm, result.mean() - moe95, epsilon = 1e-4);
         assert_abs_diff_eq!(ci95_top, result.mean() + moe95, epsilon = 1e-4);
+
+        // Compared with the values computed by the numerical integration of mpmath.
+        let (es95_btm, es95_top) = result.effect_size_confidence_interval(0.05).unwrap();
+        assert_abs_diff_eq!(es95_btm, 0.0045, epsilon = 1e-4);
+        assert_abs_diff_eq!(es95_top, 0.9306, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_student_t_tester_alternative() {
+        let samples = vec![(0.7, 0.5), (0.3, 0.1), (0.2, 0.0), (0.6, 0.2), (0.4, 0.4)];
+        let two_sided = StudentTTester::new().test(samples.clone()).unwrap();
+        let greater = StudentTTester::new()
+            .with_alternative(Alternative::Greater)
+            .test(samples.clone())
+            .unwrap();
+        let less = StudentTTester::new()
+            .with_alternative(Alternative::Less)
+            .test(samples)
+            .unwrap();
+        assert_eq!(two_sided.alternative(), Alternative::TwoSided);
+        assert_abs_diff_eq!(
+            greater.p_value(),
+            two_sided.p_value() / 2.0,
+            epsilon = 1e-10
+        );
+        assert_abs_diff_eq!(less.p_value(), 1.0 - greater.p_value(), epsilon = 1e-10);
+        assert_eq!(
+            SignificanceTest::method_name(&greater),
+            "One-sided paired Student's t-test"
+        );
+    }
+
+    #[test]
+    fn test_student_t_tester_weighted_equal_weights() {
+        let a = vec![0.70, 0.30, 0.20, 0.60, 0.40];
+        let b = vec![0.50, 0.10, 0.00, 0.20, 0.40];
+        let unweighted =
+            StudentTTest::from_paired_samples(a.iter().copied().zip(b.clone())).unwrap();
+        let samples = a.into_iter().zip(b).map(|(x, y)| (x, y, 0.5));
+        let weighted = StudentTTester::new().test_weighted(samples).unwrap();
+        assert_eq!(weighted.n_topics(), 5);
+        assert_abs_diff_eq!(weighted.effective_n_topics(), 5.0, epsilon = 1e-10);
+        assert_abs_diff_eq!(weighted.mean(), unweighted.mean(), epsilon = 1e-10);
+        assert_abs_diff_eq!(weighted.variance(), unweighted.variance(), epsilon = 1e-10);
+        assert_abs_diff_eq!(weighted.t_stat(), unweighted.t_stat(), epsilon = 1e-10);
+        assert_abs_diff_eq!(weighted.p_value(), unweighted.p_value(), epsilon = 1e-10);
+        assert_abs_diff_eq!(
+            weighted.margin_of_error(0.05).unwrap(),
+            unweighted.margin_of_error(0.05).unwrap(),
+            epsilon = 1e-10
+        );
+    }
+
+    #[test]
+    fn test_student_t_tester_weighted() {
+        // Integer weights are equivalent to the frequencies of the topics in the mean,
+        // but the effective number of topics is smaller than the total frequency.
+        let samples = vec![(0.3, 0.1, 2.0), (0.1, 0.1, 1.0), (0.4, 0.0, 1.0)];
+        let result = StudentTTester::new().test_weighted(samples).unwrap();
+        assert_abs_diff_eq!(
+            result.mean(),
+            0.2f64.mul_add(2.0, 0.4) / 4.0,
+            epsilon = 1e-10
+        );
+        assert_abs_diff_eq!(result.effective_n_topics(), 16.0 / 6.0, epsilon = 1e-10);
+        let variance = 2.0f64.mul_add(0.0f64.powi(2), 2.0 * 0.2f64.powi(2)) / (4.0 - 6.0 / 4.0);
+        assert_abs_diff_eq!(result.variance(), variance, epsilon = 1e-10);
+        assert_abs_diff_eq!(
+            result.t_stat(),
+            result.mean() / (variance / (16.0 / 6.0)).sqrt(),
+            epsilon = 1e-10
+        );
+    }
+
+    #[test]
+    fn test_student_t_tester_weighted_invalid() {
+        let tester = StudentTTester::new();
+        assert_eq!(
+            tester
+                .test_weighted(vec![(0.1, 0.0, 1.0), (0.2, 0.0, -1.0)])
+                .unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The weights must be finite and non-negative.".to_string()
+            )
+        );
+        assert_eq!(
+            tester
+                .test_weighted(vec![(0.1, 0.0, 0.0), (0.2, 0.0, 0.0)])
+                .unwrap_err(),
+            ElinorError::InvalidArgument("The weights must not be all zero.".to_string())
+        );
+        assert_eq!(
+            tester
+                .test_weighted(vec![(0.1, 0.0, 1.0), (0.2, 0.0, 0.0)])
+                .unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The input must have an effective number of samples greater than one.".to_string()
+            )
+        );
     }
 }
@@ -1,6 +1,8 @@
 //! Tukey HSD test.
 use crate::errors::ElinorError;
+use crate::statistical_tests::tuples_from_evaluations;
 use crate::statistical_tests::TwoWayAnovaWithoutReplication;
+use crate::Evaluation;
 
 /// Tukey HSD (with paired observations) test for comparing three or more systems.
 ///
@@ -42,6 +44,19 @@ impl TukeyHsdTest {
         Ok(Self { anova })
     }
 
+    /// Creates a new Tukey HSD test
+    /// from the per-query scores of evaluations $`A_1, A_2, \dots, A_m`$, tupled by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`tuples_from_evaluations`] and [`Self::from_tupled_samples`].
+    pub fn from_evaluations<K>(evaluations: &[Evaluation<K>]) -> Result<Self, ElinorError>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_tupled_samples(tuples_from_evaluations(evaluations)?, evaluations.len())
+    }
+
     /// Number of systems, $`m`$.
     pub const fn n_systems(&self) -> usize {
         self.anova.n_systems()
@@ -82,6 +97,23 @@ impl TukeyHsdTest {
         }
         effect_sizes
     }
+
+    /// Confidence intervals of [the effect sizes](Self::effect_sizes) for all combinations of systems
+    /// at a given significance level $`\alpha`$,
+    /// returning a matrix of size $`m \times m`$ for $`m`$ systems.
+    ///
+    /// See [`TwoWayAnovaWithoutReplication::effect_size_confidence_intervals`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn effect_size_confidence_intervals(
+        &self,
+        significance_level: f64,
+    ) -> Result<Vec<Vec<(f64, f64)>>, ElinorError> {
+        self.anova
+            .effect_size_confidence_intervals(significance_level)
+    }
 }
 
 #[cfg(test)]
@@ -127,5 +159,13 @@ mod tests {
         assert_abs_diff_eq!(effect_sizes[2][0], -0.6760, epsilon = 1e-4);
         assert_abs_diff_eq!(effect_sizes[2][1], -0.1690, epsilon = 1e-4);
         assert_abs_diff_eq!(effect_sizes[2][2], 0.0000, epsilon = 1e-4);
+
+        let intervals = stat.effect_size_confidence_intervals(0.05).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                let (lower, upper) = intervals[i][j];
+                assert!(lower <= effect_sizes[i][j] && effect_sizes[i][j] <= upper);
+            }
+        }
     }
 }
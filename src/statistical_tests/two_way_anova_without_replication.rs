@@ -5,6 +5,9 @@ use statrs::distribution::StudentsT;
 use statrs::statistics::Statistics;
 
 use crate::errors::ElinorError;
+use crate::statistical_tests::noncentrality_confidence_interval;
+use crate::statistical_tests::tuples_from_evaluations;
+use crate::Evaluation;
 
 /// Two-way ANOVA without replication.
 ///
@@ -186,6 +189,19 @@ impl TwoWayAnovaWithoutReplication {
         })
     }
 
+    /// Computes a new two-way ANOVA without replication
+    /// from the per-query scores of evaluations $`A_1, A_2, \dots, A_m`$, tupled by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`tuples_from_evaluations`] and [`Self::from_tupled_samples`].
+    pub fn from_evaluations<K>(evaluations: &[Evaluation<K>]) -> Result<Self, ElinorError>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_tupled_samples(tuples_from_evaluations(evaluations)?, evaluations.len())
+    }
+
     /// Number of systems, $`m`$.
     ///
     /// # Examples
@@ -587,6 +603,61 @@ impl TwoWayAnovaWithoutReplication {
             .system_t_dist
             .inverse_cdf(1.0 - (significance_level / 2.0)))
     }
+
+    /// Confidence intervals of the effect sizes for all combinations of systems
+    /// at a given significance level $`\alpha`$,
+    /// returning a matrix of size $`m \times m`$ for $`m`$ systems.
+    ///
+    /// The $`(i, j)`$-th element is the interval for
+    /// $`\text{ES}_{ij} = (\bar{x}_{i*} - \bar{x}_{j*}) / \sqrt{V_E}`$,
+    /// i.e., [the effect size of the Tukey HSD test](crate::statistical_tests::TukeyHsdTest::effect_sizes).
+    /// The diagonal elements are always `(0.0, 0.0)`.
+    ///
+    /// Each interval is obtained from the noncentral t-distribution with $`(m - 1)(n - 1)`$ degrees of freedom,
+    /// whose noncentrality parameter is $`\lambda = \sqrt{n/2} \cdot \text{ES}_{ij}`$,
+    /// and is not adjusted for the multiple comparisons.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// \text{CI}_{ij} = \left[\lambda_L \sqrt{\frac{2}{n}}, \lambda_U \sqrt{\frac{2}{n}}\right]
+    /// ```
+    ///
+    /// where $`\lambda_L`$ and $`\lambda_U`$ are the noncentrality parameters such that
+    /// $`P(T \le t_{ij} \mid \lambda_L) = 1 - \alpha/2`$ and $`P(T \le t_{ij} \mid \lambda_U) = \alpha/2`$
+    /// for $`t_{ij} = (\bar{x}_{i*} - \bar{x}_{j*}) / \sqrt{2 V_E / n}`$.
+    pub fn effect_size_confidence_intervals(
+        &self,
+        significance_level: f64,
+    ) -> Result<Vec<Vec<(f64, f64)>>, ElinorError> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let scale = (2.0 / self.n_topics as f64).sqrt();
+        let residual_freedom = ((self.n_systems - 1) * (self.n_topics - 1)) as f64;
+        let system_means = &self.system_means;
+        let residual_stddev = self.residual_variance.sqrt();
+        let mut intervals = vec![vec![(0.0, 0.0); self.n_systems]; self.n_systems];
+        for i in 0..self.n_systems {
+            for j in (i + 1)..self.n_systems {
+                let effect_size = (system_means[i] - system_means[j]) / residual_stddev;
+                let (lower, upper) = noncentrality_confidence_interval(
+                    effect_size / scale,
+                    residual_freedom,
+                    significance_level,
+                );
+                intervals[i][j] = (lower * scale, upper * scale);
+                intervals[j][i] = (-upper * scale, -lower * scale);
+            }
+        }
+        Ok(intervals)
+    }
 }
 
 #[cfg(test)]
@@ -662,5 +733,16 @@ mod tests {
         assert_abs_diff_eq!(stat.between_system_p_value(), 0.098, epsilon = 1e-3);
         assert_abs_diff_eq!(stat.between_topic_p_value(), 0.009, epsilon = 1e-3);
         assert_abs_diff_eq!(stat.margin_of_error(0.05).unwrap(), 0.0670, epsilon = 1e-4);
+
+        // Compared with the values computed by the numerical integration of mpmath.
+        let intervals = stat.effect_size_confidence_intervals(0.05).unwrap();
+        assert_eq!(intervals[0][0], (0.0, 0.0));
+        assert_abs_diff_eq!(intervals[0][1].0, -0.1264, epsilon = 1e-4);
+        assert_abs_diff_eq!(intervals[0][1].1, 1.1339, epsilon = 1e-4);
+        assert_abs_diff_eq!(intervals[0][2].0, 0.0337, epsilon = 1e-4);
+        assert_abs_diff_eq!(intervals[0][2].1, 1.3098, epsilon = 1e-4);
+        assert_abs_diff_eq!(intervals[2][1].0, -0.7888, epsilon = 1e-4);
+        assert_abs_diff_eq!(intervals[2][1].1, 0.4531, epsilon = 1e-4);
+        assert!(stat.effect_size_confidence_intervals(0.0).is_err());
     }
 }
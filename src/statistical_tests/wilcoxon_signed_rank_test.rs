@@ -0,0 +1,416 @@
+//! Two-sided Wilcoxon signed-rank test.
+
+use statrs::distribution::ContinuousCDF;
+use statrs::distribution::Normal;
+
+use crate::errors::ElinorError;
+use crate::errors::Result;
+use crate::statistical_tests::pairs_from_evaluations;
+use crate::statistical_tests::SignificanceTest;
+use crate::Evaluation;
+
+/// Maximum number of non-zero differences to compute the exact p-value.
+const MAX_EXACT_SAMPLES: usize = 50;
+
+/// Tolerance to regard two absolute differences as tied (or a difference as zero),
+/// absorbing floating-point errors in the subtraction of scores.
+const TIE_TOLERANCE: f64 = 1e-10;
+
+/// Two-sided Wilcoxon signed-rank test.
+///
+/// The differences of zero are discarded, and tied absolute differences are given average ranks.
+/// The p-value is computed from the exact null distribution
+/// if there are at most 50 non-zero differences without ties,
+/// and from the normal approximation (with the tie correction) otherwise.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use approx::assert_abs_diff_eq;
+/// use elinor::statistical_tests::WilcoxonSignedRankTest;
+///
+/// let a = vec![0.60, 0.40, 0.20, 0.70, 0.90];
+/// let b = vec![0.50, 0.10, 0.40, 0.30, 0.40];
+///
+/// // a - b = [0.10, 0.30, -0.20, 0.40, 0.50]
+/// let samples = a.into_iter().zip(b);
+/// let result = WilcoxonSignedRankTest::from_paired_samples(samples)?;
+/// assert_eq!(result.n_topics(), 5);
+/// assert_eq!(result.n_nonzero(), 5);
+///
+/// // Ranks of the absolute differences are [1, 3, 2, 4, 5].
+/// assert_abs_diff_eq!(result.w_plus(), 13.0);
+/// assert_abs_diff_eq!(result.w_minus(), 2.0);
+/// assert_abs_diff_eq!(result.w_stat(), 2.0);
+/// assert_abs_diff_eq!(result.effect_size(), (13.0 - 2.0) / 15.0);
+///
+/// // Exact p-value: 2 * P(W+ <= 2) = 2 * 3 / 2^5.
+/// assert!(result.is_exact());
+/// assert_abs_diff_eq!(result.p_value(), 0.1875);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WilcoxonSignedRankTest {
+    diffs: Vec<f64>,
+    n_nonzero: usize,
+    w_plus: f64,
+    w_minus: f64,
+    z_stat: f64,
+    p_value: f64,
+    is_exact: bool,
+}
+
+impl WilcoxonSignedRankTest {
+    /// Computes a Wilcoxon signed-rank test for $`n`$ samples $`x_{1},x_{2},\dots,x_{n}`$,
+    /// where $`x_{i} = a_{i} - b_{i}`$ for given paired samples $`(a_{1},b_{1}),(a_{2},b_{2}),\dots,(a_{n},b_{n})`$.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the input does not have at least two samples.
+    /// * [`ElinorError::InvalidArgument`] if the input has a non-finite sample.
+    /// * [`ElinorError::Uncomputable`] if all the differences are zero.
+    pub fn from_paired_samples<I>(samples: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        let diffs: Vec<f64> = samples.into_iter().map(|(x, y)| x - y).collect();
+        if diffs.len() <= 1 {
+            return Err(ElinorError::InvalidArgument(
+                "The input must have at least two samples.".to_string(),
+            ));
+        }
+        if !diffs.iter().all(|d| d.is_finite()) {
+            return Err(ElinorError::InvalidArgument(
+                "The input must not have non-finite samples.".to_string(),
+            ));
+        }
+        let nonzero = diffs
+            .iter()
+            .copied()
+            .filter(|d| d.abs() > TIE_TOLERANCE)
+            .collect::<Vec<_>>();
+        if nonzero.is_empty() {
+            return Err(ElinorError::Uncomputable(
+                "All the differences are zero.".to_string(),
+            ));
+        }
+        let n = nonzero.len();
+        let (ranks, tie_sizes) = average_ranks(&nonzero);
+        let w_plus = nonzero
+            .iter()
+            .zip(&ranks)
+            .filter(|(&d, _)| d > 0.0)
+            .map(|(_, &r)| r)
+            .sum::<f64>();
+        let w_minus = (n * (n + 1)) as f64 / 2.0 - w_plus;
+
+        let nf = n as f64;
+        let mean = nf * (nf + 1.0) / 4.0;
+        let tie_correction = tie_sizes
+            .iter()
+            .map(|&t| (t.pow(3) - t) as f64)
+            .sum::<f64>()
+            / 48.0;
+        let variance = nf * (nf + 1.0) * 2.0f64.mul_add(nf, 1.0) / 24.0 - tie_correction;
+        let z_stat = if variance > 0.0 {
+            (w_plus - mean) / variance.sqrt()
+        } else {
+            0.0
+        };
+
+        let is_exact = n <= MAX_EXACT_SAMPLES && tie_sizes.iter().all(|&t| t == 1);
+        let p_value = if is_exact {
+            exact_p_value(n, w_plus.min(w_minus))
+        } else {
+            let normal = Normal::new(0.0, 1.0).unwrap();
+            (normal.sf(z_stat.abs()) * 2.0).min(1.0) // two-tailed
+        };
+
+        Ok(Self {
+            diffs,
+            n_nonzero: n,
+            w_plus,
+            w_minus,
+            z_stat,
+            p_value,
+            is_exact,
+        })
+    }
+
+    /// Computes a Wilcoxon signed-rank test for the per-query scores of two evaluations, $`A`$ and $`B`$,
+    /// paired by query ids.
+    ///
+    /// # Errors
+    ///
+    /// See [`pairs_from_evaluations`] and [`Self::from_paired_samples`].
+    pub fn from_evaluations<K>(
+        evaluation_a: &Evaluation<K>,
+        evaluation_b: &Evaluation<K>,
+    ) -> Result<Self>
+    where
+        K: Clone + Eq + Ord + std::fmt::Display,
+    {
+        Self::from_paired_samples(pairs_from_evaluations(evaluation_a, evaluation_b)?)
+    }
+
+    /// Number of topics, $`n`$.
+    pub fn n_topics(&self) -> usize {
+        self.diffs.len()
+    }
+
+    /// Number of non-zero differences used to compute the ranks.
+    pub const fn n_nonzero(&self) -> usize {
+        self.n_nonzero
+    }
+
+    /// Sum of the ranks of the positive differences, $`W^{+}`$.
+    pub const fn w_plus(&self) -> f64 {
+        self.w_plus
+    }
+
+    /// Sum of the ranks of the negative differences, $`W^{-}`$.
+    pub const fn w_minus(&self) -> f64 {
+        self.w_minus
+    }
+
+    /// W statistic, $`W = \min(W^{+}, W^{-})`$.
+    pub fn w_stat(&self) -> f64 {
+        self.w_plus.min(self.w_minus)
+    }
+
+    /// z-statistic of the normal approximation.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// z = \frac{W^{+} - m(m+1)/4}{\sqrt{m(m+1)(2m+1)/24 - \sum_{j} (t_{j}^{3} - t_{j})/48}}
+    /// ```
+    ///
+    /// where $`m`$ is the number of non-zero differences and $`t_{j}`$ is the size of the $`j`$-th group of ties.
+    pub const fn z_stat(&self) -> f64 {
+        self.z_stat
+    }
+
+    /// p-value for the two-sided test.
+    pub const fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// Whether the p-value is computed from the exact null distribution.
+    pub const fn is_exact(&self) -> bool {
+        self.is_exact
+    }
+
+    /// Matched-pairs rank-biserial correlation as the effect size.
+    ///
+    /// # Formula
+    ///
+    /// ```math
+    /// r = \frac{W^{+} - W^{-}}{W^{+} + W^{-}}
+    /// ```
+    pub fn effect_size(&self) -> f64 {
+        (self.w_plus - self.w_minus) / (self.w_plus + self.w_minus)
+    }
+
+    /// Hodges-Lehmann estimate of the location shift,
+    /// i.e., the median of the Walsh averages $`(x_{i} + x_{j}) / 2`$ for $`i \le j`$.
+    pub fn hodges_lehmann_estimate(&self) -> f64 {
+        let walsh = self.walsh_averages();
+        let m = walsh.len();
+        if m % 2 == 1 {
+            walsh[m / 2]
+        } else {
+            (walsh[m / 2 - 1] + walsh[m / 2]) / 2.0
+        }
+    }
+
+    /// Confidence interval of the location shift at a given significance level $`\alpha`$,
+    /// given by the order statistics of the Walsh averages with the normal approximation.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if the significance level is not in the range `(0, 1]`.
+    pub fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        if significance_level <= 0.0 || significance_level > 1.0 {
+            return Err(ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string(),
+            ));
+        }
+        let walsh = self.walsh_averages();
+        let m = walsh.len();
+        let n = self.diffs.len() as f64;
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let z = normal.inverse_cdf(1.0 - significance_level / 2.0);
+        let c = m as f64 / 2.0 - z * (n * (n + 1.0) * 2.0f64.mul_add(n, 1.0) / 24.0).sqrt();
+        let c = (c.floor().max(0.0) as usize).min((m - 1) / 2);
+        Ok((walsh[c], walsh[m - 1 - c]))
+    }
+
+    fn walsh_averages(&self) -> Vec<f64> {
+        let n = self.diffs.len();
+        let mut walsh = Vec::with_capacity(n * (n + 1) / 2);
+        for i in 0..n {
+            for j in i..n {
+                walsh.push((self.diffs[i] + self.diffs[j]) / 2.0);
+            }
+        }
+        walsh.sort_by(f64::total_cmp);
+        walsh
+    }
+}
+
+impl SignificanceTest for WilcoxonSignedRankTest {
+    fn method_name(&self) -> &'static str {
+        "Two-sided Wilcoxon signed-rank test"
+    }
+
+    fn p_value(&self) -> f64 {
+        Self::p_value(self)
+    }
+
+    fn effect_size(&self) -> f64 {
+        Self::effect_size(self)
+    }
+
+    fn confidence_interval(&self, significance_level: f64) -> Result<(f64, f64)> {
+        Self::confidence_interval(self, significance_level)
+    }
+}
+
+/// Computes the average ranks of the absolute values, returning the ranks and the sizes of the tie groups.
+fn average_ranks(values: &[f64]) -> (Vec<f64>, Vec<usize>) {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| values[i].abs().total_cmp(&values[j].abs()));
+    let mut ranks = vec![0.0; values.len()];
+    let mut tie_sizes = vec![];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len()
+            && values[order[j + 1]].abs() - values[order[i]].abs() <= TIE_TOLERANCE
+        {
+            j += 1;
+        }
+        // 1-based ranks from i + 1 to j + 1.
+        let rank = (i + j + 2) as f64 / 2.0;
+        for &k in &order[i..=j] {
+            ranks[k] = rank;
+        }
+        tie_sizes.push(j - i + 1);
+        i = j + 1;
+    }
+    (ranks, tie_sizes)
+}
+
+/// Computes the exact two-sided p-value, $`2 P(W^{+} \le w)`$, for $`n`$ distinct ranks.
+fn exact_p_value(n: usize, w: f64) -> f64 {
+    // counts[s] is the number of sign assignments whose sum of positive ranks is s.
+    let max_sum = n * (n + 1) / 2;
+    let mut counts = vec![0.0; max_sum + 1];
+    counts[0] = 1.0;
+    for rank in 1..=n {
+        for s in (rank..=max_sum).rev() {
+            counts[s] += counts[s - rank];
+        }
+    }
+    let w = w.floor() as usize;
+    let n_lower = counts[..=w].iter().sum::<f64>();
+    let total = 2f64.powi(n as i32);
+    (2.0 * n_lower / total).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_one_sample() {
+        let result = WilcoxonSignedRankTest::from_paired_samples(vec![(0.0, 1.0)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::InvalidArgument("The input must have at least two samples.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_non_finite() {
+        for x in [f64::NAN, f64::INFINITY] {
+            let result =
+                WilcoxonSignedRankTest::from_paired_samples(vec![(x, 0.0), (0.5, 0.1), (0.3, 0.4)]);
+            assert_eq!(
+                result.unwrap_err(),
+                ElinorError::InvalidArgument(
+                    "The input must not have non-finite samples.".to_string()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_all_zero() {
+        let result = WilcoxonSignedRankTest::from_paired_samples(vec![(1.0, 1.0), (0.5, 0.5)]);
+        assert_eq!(
+            result.unwrap_err(),
+            ElinorError::Uncomputable("All the differences are zero.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_exact() {
+        let diffs = [0.1, 0.3, -0.2, 0.6, 0.5, 0.7, 0.8, -0.4, 0.9, 1.0];
+        let samples = diffs.iter().map(|&d| (d, 0.0));
+        let result = WilcoxonSignedRankTest::from_paired_samples(samples).unwrap();
+        assert!(result.is_exact());
+        assert_abs_diff_eq!(result.w_plus(), 49.0);
+        assert_abs_diff_eq!(result.w_minus(), 6.0);
+        assert_abs_diff_eq!(result.effect_size(), 0.7818, epsilon = 1e-4);
+        // Enumerated over all the 2^10 sign assignments.
+        assert_abs_diff_eq!(result.p_value(), 0.02734375, epsilon = 1e-10);
+        assert_abs_diff_eq!(result.z_stat(), 2.1915, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_sakai_book_15() {
+        // From Table 5.1 in Sakai's book, "情報アクセス評価方法論".
+        let a = vec![
+            0.70, 0.30, 0.20, 0.60, 0.40, 0.40, 0.00, 0.70, 0.10, 0.30, //
+            0.50, 0.40, 0.00, 0.60, 0.50, 0.30, 0.10, 0.50, 0.20, 0.10,
+        ];
+        let b = vec![
+            0.50, 0.10, 0.00, 0.20, 0.40, 0.30, 0.00, 0.50, 0.30, 0.30, //
+            0.40, 0.40, 0.10, 0.40, 0.20, 0.10, 0.10, 0.60, 0.30, 0.20,
+        ];
+        let samples = a.into_iter().zip(b);
+        let result = WilcoxonSignedRankTest::from_paired_samples(samples).unwrap();
+
+        // Five differences are zero, and the others have ties.
+        assert_eq!(result.n_topics(), 20);
+        assert_eq!(result.n_nonzero(), 15);
+        assert!(!result.is_exact());
+        assert_abs_diff_eq!(result.w_plus(), 96.0);
+        assert_abs_diff_eq!(result.w_minus(), 24.0);
+        assert_abs_diff_eq!(result.w_stat(), 24.0);
+        assert_abs_diff_eq!(result.effect_size(), 0.6);
+        assert_abs_diff_eq!(result.z_stat(), 2.0832, epsilon = 1e-4);
+        assert_abs_diff_eq!(result.p_value(), 0.0372, epsilon = 1e-4);
+
+        let (ci95_btm, ci95_top) = result.confidence_interval(0.05).unwrap();
+        let estimate = result.hodges_lehmann_estimate();
+        assert!(ci95_btm <= estimate && estimate <= ci95_top);
+    }
+
+    #[test]
+    fn test_wilcoxon_signed_rank_test_confidence_interval_invalid_argument() {
+        let result =
+            WilcoxonSignedRankTest::from_paired_samples(vec![(2.0, 1.0), (2.0, 0.5)]).unwrap();
+        assert_eq!(
+            result.confidence_interval(0.0).unwrap_err(),
+            ElinorError::InvalidArgument(
+                "The significance level must be in the range (0, 1].".to_string()
+            )
+        );
+    }
+}
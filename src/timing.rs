@@ -0,0 +1,161 @@
+//! Timing report to profile evaluations and comparisons.
+//!
+//! A sweep over hundreds of metrics can take minutes,
+//! and it is not obvious which step (building the stores, evaluating a metric, or running a test) dominates.
+//! This module provides [`TimingReport`] to record the wall-clock time of such steps,
+//! which is filled by [`crate::comparison::compare_score_maps_with_timings`] for the statistical tests
+//! and can be filled by the caller for the other steps.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use elinor::{TrueRelStoreBuilder, PredRelStoreBuilder, Metric};
+//! use elinor::timing::TimingReport;
+//!
+//! let mut timings = TimingReport::new();
+//!
+//! let true_rels = timings.time("build_true_rels", || {
+//!     let mut b = TrueRelStoreBuilder::new();
+//!     b.add_record("q_1", "d_1", 1)?;
+//!     Ok::<_, elinor::ElinorError>(b.build())
+//! })?;
+//! let pred_rels = timings.time("build_pred_rels", || {
+//!     let mut b = PredRelStoreBuilder::new();
+//!     b.add_record("q_1", "d_1", 0.5.into())?;
+//!     Ok::<_, elinor::ElinorError>(b.build())
+//! })?;
+//! let metric = Metric::AP { k: 0 };
+//! let evaluation = timings.time(metric.to_string(), || {
+//!     elinor::evaluate(&true_rels, &pred_rels, metric)
+//! })?;
+//!
+//! assert_eq!(evaluation.mean(), 1.0);
+//! assert_eq!(timings.len(), 3);
+//! assert_eq!(timings.entries()[2].0, "ap");
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Wall-clock time of labeled steps, in the recorded order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TimingReport {
+    entries: Vec<(String, Duration)>,
+}
+
+impl TimingReport {
+    /// Creates an empty report.
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Runs the function and records its elapsed time with the label.
+    pub fn time<S, T, F>(&mut self, label: S, f: F) -> T
+    where
+        S: Into<String>,
+        F: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let output = f();
+        self.record(label, start.elapsed());
+        output
+    }
+
+    /// Records the elapsed time with the label.
+    pub fn record<S>(&mut self, label: S, elapsed: Duration)
+    where
+        S: Into<String>,
+    {
+        self.entries.push((label.into(), elapsed));
+    }
+
+    /// Appends the entries of the other report, prefixing their labels with `"{prefix}/"`.
+    pub fn extend_with_prefix(&mut self, prefix: &str, other: Self) {
+        self.entries.extend(
+            other
+                .entries
+                .into_iter()
+                .map(|(label, elapsed)| (format!("{prefix}/{label}"), elapsed)),
+        );
+    }
+
+    /// Pairs of the label and elapsed time, in the recorded order.
+    pub fn entries(&self) -> &[(String, Duration)] {
+        &self.entries
+    }
+
+    /// Number of the recorded entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if no entries are recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sum of the elapsed times.
+    pub fn total(&self) -> Duration {
+        self.entries.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+}
+
+/// Formats the report as `<label>\t<seconds>` lines, followed by the `total` line.
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, elapsed) in &self.entries {
+            writeln!(f, "{label}\t{:.6}", elapsed.as_secs_f64())?;
+        }
+        write!(f, "total\t{:.6}", self.total().as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_total() {
+        let mut timings = TimingReport::new();
+        timings.record("a", Duration::from_millis(1500));
+        timings.record("b", Duration::from_millis(250));
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings.total(), Duration::from_millis(1750));
+        assert_eq!(
+            timings.to_string(),
+            "a\t1.500000\nb\t0.250000\ntotal\t1.750000"
+        );
+    }
+
+    #[test]
+    fn test_time() {
+        let mut timings = TimingReport::new();
+        let output = timings.time("step", || 42);
+        assert_eq!(output, 42);
+        assert_eq!(timings.entries()[0].0, "step");
+    }
+
+    #[test]
+    fn test_extend_with_prefix() {
+        let mut inner = TimingReport::new();
+        inner.record("student_t", Duration::from_millis(1));
+        let mut timings = TimingReport::new();
+        timings.extend_with_prefix("ap", inner);
+        assert_eq!(
+            timings.entries(),
+            &[("ap/student_t".to_string(), Duration::from_millis(1))]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let timings = TimingReport::new();
+        assert!(timings.is_empty());
+        assert_eq!(timings.to_string(), "total\t0.000000");
+    }
+}
@@ -1,5 +1,14 @@
-//! TREC format parser.
+//! TREC format parser and writer.
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use crate::errors::ElinorError;
+use crate::metrics::SampledPool;
+use crate::relevance::NonFiniteScorePolicy;
+use crate::relevance::ScoreQuantization;
 use crate::PredRelStore;
 use crate::PredRelStoreBuilder;
 use crate::PredScore;
@@ -46,6 +55,52 @@ where
     S: AsRef<str>,
 {
     let mut b = TrueRelStoreBuilder::new();
+    for line in lines {
+        let (query_id, doc_id, score) = parse_qrels_line(line.as_ref())?;
+        b.add_record(query_id, doc_id, score)?;
+    }
+    Ok(b.build())
+}
+
+/// Parses the Qrels data in the TREC format with the metadata of the sampled pool,
+/// used with [`Metric::InfAP`](crate::Metric::InfAP) and [`Metric::XInfAP`](crate::Metric::XInfAP).
+///
+/// # Format
+///
+/// Each line should be `<QueryID> <Dummy> <DocID> <Score> [<Stratum>]`,
+/// where `<Dummy>` is ignored.
+/// As in trec_eval, the score of `-1` indicates a pooled document that is not judged.
+/// The optional `<Stratum>` is a non-negative integer, defaulting to 0.
+/// All the documents in the lines are added to the pool.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::trec::parse_true_rels_with_pool_in_trec;
+///
+/// let data = "
+/// q_1 0 d_1 1 0
+/// q_1 0 d_2 -1 0
+/// q_1 0 d_3 0 1
+/// ".trim();
+///
+/// let (true_rels, pool) = parse_true_rels_with_pool_in_trec(data.lines())?;
+/// assert_eq!(true_rels.n_docs(), 2);
+/// assert_eq!(pool.n_docs(), 3);
+/// assert_eq!(pool.get_stratum("q_1", "d_3"), Some(1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_true_rels_with_pool_in_trec<I, S>(
+    lines: I,
+) -> Result<(TrueRelStore<String>, SampledPool<String>), ElinorError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut b = TrueRelStoreBuilder::new();
+    let mut pool = SampledPool::new();
     for line in lines {
         let line = line.as_ref();
         let rows = line.split_whitespace().collect::<Vec<_>>();
@@ -54,18 +109,60 @@ where
                 "Qrels line must have four columns at least, but got {line}"
             )));
         }
-        let query_id = rows[0].to_string();
-        let doc_id = rows[2].to_string();
         let score = rows[3].parse::<i32>().map_err(|_| {
             ElinorError::InvalidFormat(format!(
                 "The fourth column must be i32, but got {}",
                 rows[3]
             ))
         })?;
-        let score = TrueScore::try_from(score.max(0)).unwrap();
-        b.add_record(query_id, doc_id, score)?;
+        let stratum = rows
+            .get(4)
+            .map(|stratum| {
+                stratum.parse::<usize>().map_err(|_| {
+                    ElinorError::InvalidFormat(format!(
+                        "The fifth column must be a non-negative integer, but got {stratum}"
+                    ))
+                })
+            })
+            .transpose()?
+            .unwrap_or(0);
+        let (query_id, doc_id) = (rows[0].to_string(), rows[2].to_string());
+        pool.add_doc(query_id.clone(), doc_id.clone(), stratum)?;
+        if score != -1 {
+            b.add_record(query_id, doc_id, TrueScore::try_from(score.max(0)).unwrap())?;
+        }
     }
-    Ok(b.build())
+    Ok((b.build(), pool))
+}
+
+fn parse_qrels_line(line: &str) -> Result<(String, String, TrueScore), ElinorError> {
+    let rows = line.split_whitespace().collect::<Vec<_>>();
+    if rows.len() < 4 {
+        return Err(ElinorError::InvalidFormat(format!(
+            "Qrels line must have four columns at least, but got {line}"
+        )));
+    }
+    let score = rows[3].parse::<i32>().map_err(|_| {
+        ElinorError::InvalidFormat(format!(
+            "The fourth column must be i32, but got {}",
+            rows[3]
+        ))
+    })?;
+    let score = TrueScore::try_from(score.max(0)).unwrap();
+    Ok((rows[0].to_string(), rows[2].to_string(), score))
+}
+
+fn parse_run_line(line: &str) -> Result<(String, String, PredScore, &str), ElinorError> {
+    let rows = line.split_whitespace().collect::<Vec<_>>();
+    if rows.len() < 5 {
+        return Err(ElinorError::InvalidFormat(format!(
+            "Run line must have five columns at least, but got {line}"
+        )));
+    }
+    let score = rows[4].parse::<PredScore>().map_err(|_| {
+        ElinorError::InvalidFormat(format!("The fifth column must be f64, but got {}", rows[4]))
+    })?;
+    Ok((rows[0].to_string(), rows[2].to_string(), score, rows[4]))
 }
 
 /// Parses the Run data in the TREC format into a [`PredRelStore`].
@@ -78,6 +175,7 @@ where
 /// # Caution
 ///
 /// Ties are arbitrarily broken because the rank is ignored.
+/// Non-finite scores are rejected; use [`parse_pred_rels_in_trec_with_policy`] to handle them otherwise.
 ///
 /// # Example
 ///
@@ -106,21 +204,1022 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<str>,
 {
-    let mut b = PredRelStoreBuilder::new();
-    for line in lines {
-        let line = line.as_ref();
-        let rows = line.split_whitespace().collect::<Vec<_>>();
-        if rows.len() < 5 {
+    parse_pred_rels_in_trec_with_policy(lines, NonFiniteScorePolicy::Reject)
+        .map(|(pred_rels, _)| pred_rels)
+}
+
+/// Parses the Run data in the TREC format into a [`PredRelStore`],
+/// handling non-finite scores according to the policy.
+///
+/// Returns the store and the number of non-finite scores encountered.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::relevance::NonFiniteScorePolicy;
+/// use elinor::trec::parse_pred_rels_in_trec_with_policy;
+///
+/// let data = "
+/// q_1 0 d_1 1 0.5 SAMPLE
+/// q_1 0 d_2 2 NaN SAMPLE
+/// ".trim();
+///
+/// let (pred_rels, n_non_finite) =
+///     parse_pred_rels_in_trec_with_policy(data.lines(), NonFiniteScorePolicy::Drop)?;
+/// assert_eq!(pred_rels.n_docs(), 1);
+/// assert_eq!(n_non_finite, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_pred_rels_in_trec_with_policy<I, S>(
+    lines: I,
+    policy: NonFiniteScorePolicy,
+) -> Result<(PredRelStore<String>, usize), ElinorError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let run = TrecRunParser::new()
+        .with_non_finite_policy(policy)
+        .parse(lines)?;
+    let n_non_finite = run.n_non_finite();
+    Ok((run.into_pred_rels(), n_non_finite))
+}
+
+/// Writes a [`TrueRelStore`] as the Qrels data in the TREC format.
+///
+/// Each line is `<QueryID> 0 <DocID> <Score>`,
+/// where the queries and the documents of each query are sorted by their ids.
+/// The lines can be parsed back by [`parse_true_rels_in_trec`].
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if an id is empty or contains whitespace.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::TrueRelStoreBuilder;
+/// use elinor::trec::write_true_rels_in_trec;
+///
+/// let mut b = TrueRelStoreBuilder::new();
+/// b.add_record("q_1", "d_2", 0)?;
+/// b.add_record("q_1", "d_1", 1)?;
+/// b.add_record("q_2", "d_1", 2)?;
+/// let true_rels = b.build();
+///
+/// let lines = write_true_rels_in_trec(&true_rels)?;
+/// assert_eq!(lines, vec!["q_1 0 d_1 1", "q_1 0 d_2 0", "q_2 0 d_1 2"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_true_rels_in_trec<K>(true_rels: &TrueRelStore<K>) -> Result<Vec<String>, ElinorError>
+where
+    K: Eq + Ord + Clone + std::fmt::Display,
+{
+    let mut lines = Vec::with_capacity(true_rels.n_docs());
+    for query_id in true_rels.query_ids() {
+        let rels = true_rels.get_map(query_id).unwrap();
+        let query_id = trec_field(query_id, "query id")?;
+        for (doc_id, score) in rels {
+            lines.push(format!(
+                "{query_id} 0 {} {score}",
+                trec_field(doc_id, "doc id")?
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+/// Writes a [`PredRelStore`] as the Run data in the TREC format.
+///
+/// Each line is `<QueryID> Q0 <DocID> <Rank> <Score> <RunName>`,
+/// where the queries are sorted by their ids,
+/// and the documents of each query are sorted by their scores in descending order
+/// (ties are broken by the doc ids in ascending order, as in the evaluation).
+/// The rank starts from 1 for each query.
+/// The lines can be parsed back by [`parse_pred_rels_in_trec`],
+/// and the scores are written with the shortest representation that round-trips.
+///
+/// # Caution
+///
+/// trec_eval ignores the rank and breaks ties by its own rule,
+/// so tied scores may be evaluated in a different order from elinor.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the run name or an id is empty or contains whitespace.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::PredRelStoreBuilder;
+/// use elinor::trec::write_pred_rels_in_trec;
+///
+/// let mut b = PredRelStoreBuilder::new();
+/// b.add_record("q_1", "d_1", 0.2.into())?;
+/// b.add_record("q_1", "d_2", 0.5.into())?;
+/// b.add_record("q_2", "d_1", 1.0.into())?;
+/// let pred_rels = b.build();
+///
+/// let lines = write_pred_rels_in_trec(&pred_rels, "SAMPLE")?;
+/// assert_eq!(
+///     lines,
+///     vec![
+///         "q_1 Q0 d_2 1 0.5 SAMPLE",
+///         "q_1 Q0 d_1 2 0.2 SAMPLE",
+///         "q_2 Q0 d_1 1 1 SAMPLE",
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_pred_rels_in_trec<K>(
+    pred_rels: &PredRelStore<K>,
+    run_name: &str,
+) -> Result<Vec<String>, ElinorError>
+where
+    K: Eq + Ord + Clone + std::fmt::Display,
+{
+    let run_name = trec_field(run_name, "run name")?;
+    let mut lines = Vec::with_capacity(pred_rels.n_docs());
+    for query_id in pred_rels.query_ids() {
+        let sorted = pred_rels.get_sorted(query_id).unwrap();
+        let query_id = trec_field(query_id, "query id")?;
+        for (rank, rel) in sorted.iter().enumerate() {
+            lines.push(format!(
+                "{query_id} Q0 {} {} {} {run_name}",
+                trec_field(&rel.doc_id, "doc id")?,
+                rank + 1,
+                rel.score
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+/// Formats a field of the TREC format, which must be non-empty and free of whitespace.
+fn trec_field<T: std::fmt::Display + ?Sized>(value: &T, name: &str) -> Result<String, ElinorError> {
+    let field = value.to_string();
+    if field.is_empty() || field.contains(char::is_whitespace) {
+        return Err(ElinorError::InvalidArgument(format!(
+            "The {name} must be non-empty and free of whitespace, but got {field:?}"
+        )));
+    }
+    Ok(field)
+}
+
+/// Mapping of columns for "almost TREC" files, such as those with extra columns or in a different order.
+///
+/// The column indices are zero-based.
+/// The columns are separated by whitespace unless a delimiter is specified.
+/// Either of the score or rank column is needed for Run data,
+/// and the negated rank is used as the score if the score column is not specified.
+///
+/// # Default mappings
+///
+/// * [`ColumnMapping::qrels`] - `query_id:0,doc_id:2,score:3`
+/// * [`ColumnMapping::run`] - `query_id:0,doc_id:2,rank:3,score:4`
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::trec::ColumnMapping;
+/// use elinor::trec::TrecRunParser;
+///
+/// // <DocID>,<QueryID>,<Rank>,<Extra>
+/// let data = "
+/// d_1,q_1,1,x
+/// d_2,q_1,2,y
+/// ".trim();
+///
+/// let columns: ColumnMapping = "query_id:1,doc_id:0,rank:2".parse()?;
+/// let columns = columns.with_delimiter(Some(','));
+/// let run = TrecRunParser::new().with_columns(columns).parse(data.lines())?;
+/// assert_eq!(run.pred_rels().get_score("q_1", "d_1"), Some(&(-1.0).into()));
+/// assert_eq!(run.pred_rels().get_score("q_1", "d_2"), Some(&(-2.0).into()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnMapping {
+    query_id: usize,
+    doc_id: usize,
+    score: Option<usize>,
+    rank: Option<usize>,
+    delimiter: Option<char>,
+}
+
+impl ColumnMapping {
+    /// Creates a mapping with the query and document id columns.
+    pub const fn new(query_id: usize, doc_id: usize) -> Self {
+        Self {
+            query_id,
+            doc_id,
+            score: None,
+            rank: None,
+            delimiter: None,
+        }
+    }
+
+    /// Creates the mapping of the standard Qrels format.
+    pub const fn qrels() -> Self {
+        Self::new(0, 2).with_score(3)
+    }
+
+    /// Creates the mapping of the standard Run format.
+    pub const fn run() -> Self {
+        Self::new(0, 2).with_rank(3).with_score(4)
+    }
+
+    /// Sets the score column.
+    pub const fn with_score(mut self, score: usize) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// Sets the rank column.
+    pub const fn with_rank(mut self, rank: usize) -> Self {
+        self.rank = Some(rank);
+        self
+    }
+
+    /// Sets the delimiter of columns, or whitespace if `None`.
+    pub const fn with_delimiter(mut self, delimiter: Option<char>) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Returns the query id column.
+    pub const fn query_id(&self) -> usize {
+        self.query_id
+    }
+
+    /// Returns the document id column.
+    pub const fn doc_id(&self) -> usize {
+        self.doc_id
+    }
+
+    /// Returns the score column.
+    pub const fn score(&self) -> Option<usize> {
+        self.score
+    }
+
+    /// Returns the rank column.
+    pub const fn rank(&self) -> Option<usize> {
+        self.rank
+    }
+
+    /// Returns the delimiter of columns.
+    pub const fn delimiter(&self) -> Option<char> {
+        self.delimiter
+    }
+
+    fn split<'a>(&self, line: &'a str) -> Result<Vec<&'a str>, ElinorError> {
+        let rows = self.delimiter.map_or_else(
+            || line.split_whitespace().collect::<Vec<_>>(),
+            |delimiter| line.split(delimiter).map(|row| row.trim()).collect(),
+        );
+        let n_columns = [
+            Some(self.query_id),
+            Some(self.doc_id),
+            self.score,
+            self.rank,
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap()
+            + 1;
+        if rows.len() < n_columns {
             return Err(ElinorError::InvalidFormat(format!(
-                "Run line must have five columns at least, but got {line}"
+                "Line must have {n_columns} columns at least, but got {line}"
             )));
         }
-        let query_id = rows[0].to_string();
-        let doc_id = rows[2].to_string();
-        let score = rows[4].parse::<PredScore>().map_err(|_| {
-            ElinorError::InvalidFormat(format!("The fifth column must be f32, but got {}", rows[4]))
+        Ok(rows)
+    }
+}
+
+impl FromStr for ColumnMapping {
+    type Err = ElinorError;
+
+    /// Parses the mapping in the form of `<name>:<index>,...`,
+    /// where `<name>` is `query_id`, `doc_id`, `score`, or `rank`,
+    /// and `query_id` and `doc_id` are required.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut indices: BTreeMap<&str, usize> = BTreeMap::new();
+        for pair in s.split(',') {
+            let (name, index) = pair.split_once(':').ok_or_else(|| {
+                ElinorError::InvalidFormat(format!(
+                    "Each column must be in the form of <name>:<index>, but got {pair}"
+                ))
+            })?;
+            let name = name.trim();
+            if !["query_id", "doc_id", "score", "rank"].contains(&name) {
+                return Err(ElinorError::InvalidFormat(format!(
+                    "Invalid column name: {name}"
+                )));
+            }
+            let index = index.trim().parse::<usize>().map_err(|_| {
+                ElinorError::InvalidFormat(format!("Invalid column index: {index}"))
+            })?;
+            if indices.insert(name, index).is_some() {
+                return Err(ElinorError::DuplicateEntry(format!(
+                    "Each column name must be unique, but got {name} twice"
+                )));
+            }
+        }
+        let (Some(&query_id), Some(&doc_id)) = (indices.get("query_id"), indices.get("doc_id"))
+        else {
+            return Err(ElinorError::InvalidFormat(
+                "The query_id and doc_id columns must be specified.".to_string(),
+            ));
+        };
+        Ok(Self {
+            query_id,
+            doc_id,
+            score: indices.get("score").copied(),
+            rank: indices.get("rank").copied(),
+            delimiter: None,
+        })
+    }
+}
+
+/// Parses the Qrels data with the column mapping into a [`TrueRelStore`].
+///
+/// The rank column is ignored, and negative scores are clamped to 0.
+///
+/// # Errors
+///
+/// * [`ElinorError::InvalidArgument`] if the score column is not specified.
+/// * [`ElinorError::InvalidFormat`] if a line has too few columns or the score is not i32.
+/// * [`ElinorError::DuplicateEntry`] if a query-document pair is duplicated.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::trec::ColumnMapping;
+/// use elinor::trec::parse_true_rels_with_columns;
+///
+/// // <QueryID>\t<DocID>\t<Score>
+/// let data = "q_1\td_1\t1\nq_1\td_2\t0";
+///
+/// let columns = ColumnMapping::new(0, 1).with_score(2).with_delimiter(Some('\t'));
+/// let true_rels = parse_true_rels_with_columns(data.lines(), columns)?;
+/// assert_eq!(true_rels.get_score("q_1", "d_1"), Some(&1));
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_true_rels_with_columns<I, S>(
+    lines: I,
+    columns: ColumnMapping,
+) -> Result<TrueRelStore<String>, ElinorError>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let Some(score_column) = columns.score else {
+        return Err(ElinorError::InvalidArgument(
+            "The score column must be specified for Qrels data.".to_string(),
+        ));
+    };
+    let mut b = TrueRelStoreBuilder::new();
+    for line in lines {
+        let rows = columns.split(line.as_ref())?;
+        let score = rows[score_column].parse::<i32>().map_err(|_| {
+            ElinorError::InvalidFormat(format!(
+                "The score column must be i32, but got {}",
+                rows[score_column]
+            ))
         })?;
-        b.add_record(query_id, doc_id, score)?;
+        let score = TrueScore::try_from(score.max(0)).unwrap();
+        b.add_record(
+            rows[columns.query_id].to_string(),
+            rows[columns.doc_id].to_string(),
+            score,
+        )?;
     }
     Ok(b.build())
 }
+
+fn parse_run_line_with_columns(
+    line: &str,
+    columns: ColumnMapping,
+) -> Result<(String, String, PredScore, String), ElinorError> {
+    let rows = columns.split(line)?;
+    let (score, score_string) = match (columns.score, columns.rank) {
+        (Some(score_column), _) => {
+            let score = rows[score_column].parse::<PredScore>().map_err(|_| {
+                ElinorError::InvalidFormat(format!(
+                    "The score column must be f64, but got {}",
+                    rows[score_column]
+                ))
+            })?;
+            (score, rows[score_column].to_string())
+        }
+        (None, Some(rank_column)) => {
+            let rank = rows[rank_column].parse::<usize>().map_err(|_| {
+                ElinorError::InvalidFormat(format!(
+                    "The rank column must be usize, but got {}",
+                    rows[rank_column]
+                ))
+            })?;
+            (PredScore::from(-(rank as f64)), format!("-{rank}"))
+        }
+        (None, None) => {
+            return Err(ElinorError::InvalidArgument(
+                "The score or rank column must be specified for Run data.".to_string(),
+            ))
+        }
+    };
+    Ok((
+        rows[columns.query_id].to_string(),
+        rows[columns.doc_id].to_string(),
+        score,
+        score_string,
+    ))
+}
+
+/// Parses the Qrels data in the TREC format into a [`TrueRelStore`] in parallel.
+///
+/// The lines are split into chunks, each of which is parsed into a builder in a thread,
+/// and the builders are merged at the end.
+/// The result is the same as [`parse_true_rels_in_trec`].
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::trec::parse_true_rels_in_trec_parallel;
+///
+/// let lines = vec!["q_1 0 d_1 1", "q_1 0 d_2 0", "q_2 0 d_1 2"];
+/// let true_rels = parse_true_rels_in_trec_parallel(&lines)?;
+/// assert_eq!(true_rels.n_queries(), 2);
+/// assert_eq!(true_rels.n_docs(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "parallel")]
+pub fn parse_true_rels_in_trec_parallel<S>(lines: &[S]) -> Result<TrueRelStore<String>, ElinorError>
+where
+    S: AsRef<str> + Sync,
+{
+    use rayon::prelude::*;
+
+    let builders = lines
+        .par_chunks(parallel_chunk_size(lines.len()))
+        .map(|chunk| {
+            let mut b = TrueRelStoreBuilder::new();
+            for line in chunk {
+                let (query_id, doc_id, score) = parse_qrels_line(line.as_ref())?;
+                b.add_record(query_id, doc_id, score)?;
+            }
+            Ok(b)
+        })
+        .collect::<Result<Vec<_>, ElinorError>>()?;
+    let mut b = TrueRelStoreBuilder::new();
+    for other in builders {
+        b.merge(other)?;
+    }
+    Ok(b.build())
+}
+
+/// Parses the Run data in the TREC format into a [`PredRelStore`] in parallel,
+/// handling non-finite scores according to the policy.
+///
+/// The lines are split into chunks, each of which is parsed into a builder in a thread,
+/// and the builders are merged at the end.
+/// The result is the same as [`parse_pred_rels_in_trec_with_policy`].
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::relevance::NonFiniteScorePolicy;
+/// use elinor::trec::parse_pred_rels_in_trec_parallel;
+///
+/// let lines = vec!["q_1 0 d_1 1 0.5 X", "q_1 0 d_2 2 NaN X", "q_2 0 d_1 1 0.3 X"];
+/// let (pred_rels, n_non_finite) =
+///     parse_pred_rels_in_trec_parallel(&lines, NonFiniteScorePolicy::Drop)?;
+/// assert_eq!(pred_rels.n_docs(), 2);
+/// assert_eq!(n_non_finite, 1);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "parallel")]
+pub fn parse_pred_rels_in_trec_parallel<S>(
+    lines: &[S],
+    policy: NonFiniteScorePolicy,
+) -> Result<(PredRelStore<String>, usize), ElinorError>
+where
+    S: AsRef<str> + Sync,
+{
+    use rayon::prelude::*;
+
+    let builders = lines
+        .par_chunks(parallel_chunk_size(lines.len()))
+        .map(|chunk| {
+            let mut n_non_finite = 0;
+            let mut b = PredRelStoreBuilder::new();
+            for line in chunk {
+                let (query_id, doc_id, score, _) = parse_run_line(line.as_ref())?;
+                if b.add_record_with_policy(query_id, doc_id, score, policy)? {
+                    n_non_finite += 1;
+                }
+            }
+            Ok((b, n_non_finite))
+        })
+        .collect::<Result<Vec<_>, ElinorError>>()?;
+    let mut n_non_finite = 0;
+    let mut b = PredRelStoreBuilder::new();
+    for (other, n) in builders {
+        b.merge(other)?;
+        n_non_finite += n;
+    }
+    Ok((b.build(), n_non_finite))
+}
+
+/// Returns the chunk size so that each thread parses a few chunks.
+#[cfg(feature = "parallel")]
+fn parallel_chunk_size(n_lines: usize) -> usize {
+    let n_chunks = rayon::current_num_threads() * 4;
+    ((n_lines + n_chunks - 1) / n_chunks).max(1)
+}
+
+/// Tie between two documents created by the precision loss of parsing their scores,
+/// i.e., their scores are distinct in the input but equal after parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollapsedTie {
+    /// Query id.
+    pub query_id: String,
+
+    /// Document ids.
+    pub doc_ids: [String; 2],
+
+    /// Original score strings of the documents.
+    pub score_strings: [String; 2],
+}
+
+/// Parsed Run data in the TREC format with auditing information.
+pub struct TrecRun {
+    pred_rels: PredRelStore<String>,
+    n_non_finite: usize,
+    collapsed_ties: Vec<CollapsedTie>,
+    score_strings: Option<BTreeMap<(String, String), String>>,
+}
+
+impl TrecRun {
+    /// Returns the reference to the parsed store.
+    pub const fn pred_rels(&self) -> &PredRelStore<String> {
+        &self.pred_rels
+    }
+
+    /// Converts into the parsed store.
+    pub fn into_pred_rels(self) -> PredRelStore<String> {
+        self.pred_rels
+    }
+
+    /// Returns the number of non-finite scores encountered.
+    pub const fn n_non_finite(&self) -> usize {
+        self.n_non_finite
+    }
+
+    /// Returns the ties created by the precision loss of parsing scores,
+    /// which is empty unless the detection is enabled in the parser.
+    pub fn collapsed_ties(&self) -> &[CollapsedTie] {
+        &self.collapsed_ties
+    }
+
+    /// Returns the mapping from query-document pairs to the original score strings,
+    /// or `None` if not requested in the parser.
+    pub const fn score_strings(&self) -> Option<&BTreeMap<(String, String), String>> {
+        self.score_strings.as_ref()
+    }
+}
+
+/// Parser of the Run data in the TREC format.
+///
+/// # Default parameters
+///
+/// * `non_finite_policy`: [`NonFiniteScorePolicy::Reject`]
+/// * `keep_score_strings`: `false`
+/// * `detect_collapsed_ties`: `false`
+/// * `columns`: [`ColumnMapping::run`]
+/// * `quantization`: `None`
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use elinor::trec::TrecRunParser;
+///
+/// // The scores are distinct, but equal in f64.
+/// let data = "
+/// q_1 0 d_1 1 0.10000000000000000001 SAMPLE
+/// q_1 0 d_2 2 0.10000000000000000002 SAMPLE
+/// ".trim();
+///
+/// let run = TrecRunParser::new()
+///     .with_keep_score_strings(true)
+///     .with_detect_collapsed_ties(true)
+///     .parse(data.lines())?;
+/// assert_eq!(run.collapsed_ties().len(), 1);
+/// assert_eq!(run.collapsed_ties()[0].doc_ids, ["d_1".to_string(), "d_2".to_string()]);
+///
+/// let score_strings = run.score_strings().unwrap();
+/// let key = ("q_1".to_string(), "d_2".to_string());
+/// assert_eq!(score_strings[&key], "0.10000000000000000002");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrecRunParser {
+    non_finite_policy: NonFiniteScorePolicy,
+    keep_score_strings: bool,
+    detect_collapsed_ties: bool,
+    columns: Option<ColumnMapping>,
+    quantization: Option<ScoreQuantization>,
+}
+
+impl TrecRunParser {
+    /// Creates a new parser.
+    pub const fn new() -> Self {
+        Self {
+            non_finite_policy: NonFiniteScorePolicy::Reject,
+            keep_score_strings: false,
+            detect_collapsed_ties: false,
+            columns: None,
+            quantization: None,
+        }
+    }
+
+    /// Sets the policy for non-finite scores.
+    pub const fn with_non_finite_policy(mut self, policy: NonFiniteScorePolicy) -> Self {
+        self.non_finite_policy = policy;
+        self
+    }
+
+    /// Sets whether to keep the original score strings for auditing.
+    pub const fn with_keep_score_strings(mut self, keep_score_strings: bool) -> Self {
+        self.keep_score_strings = keep_score_strings;
+        self
+    }
+
+    /// Sets whether to detect the ties created by the precision loss of parsing scores,
+    /// which are reported by [`TrecRun::collapsed_ties`].
+    ///
+    /// The detection compares the exact decimal values of the score strings for every line,
+    /// so it is disabled by default.
+    pub const fn with_detect_collapsed_ties(mut self, detect_collapsed_ties: bool) -> Self {
+        self.detect_collapsed_ties = detect_collapsed_ties;
+        self
+    }
+
+    /// Sets the mapping of columns for non-standard files.
+    pub const fn with_columns(mut self, columns: ColumnMapping) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Sets the quantization of scores at ingestion.
+    ///
+    /// Collapsed ties are detected from the scores before quantization.
+    pub const fn with_quantization(mut self, quantization: ScoreQuantization) -> Self {
+        self.quantization = Some(quantization);
+        self
+    }
+
+    /// Parses the Run data.
+    ///
+    /// # Errors
+    ///
+    /// * [`ElinorError::InvalidArgument`] if neither of the score or rank column is specified in the mapping.
+    /// * [`ElinorError::InvalidFormat`] if a line has fewer than five columns (or those in the mapping)
+    ///   or the score is not f64.
+    /// * [`ElinorError::InvalidArgument`] if a score is non-finite and the policy is [`NonFiniteScorePolicy::Reject`].
+    /// * [`ElinorError::DuplicateEntry`] if a query-document pair is duplicated.
+    pub fn parse<I, S>(&self, lines: I) -> Result<TrecRun, ElinorError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut n_non_finite = 0;
+        let mut collapsed_ties = vec![];
+        let mut score_strings = self.keep_score_strings.then(BTreeMap::new);
+        // Mapping from query-score pairs to the first document and its canonical score.
+        let mut first_docs = self
+            .detect_collapsed_ties
+            .then(HashMap::<(String, PredScore), (String, String, String)>::new);
+        let mut b = PredRelStoreBuilder::new();
+        for line in lines {
+            let line = line.as_ref();
+            let (query_id, doc_id, score, score_string) = match self.columns {
+                Some(columns) => {
+                    let (query_id, doc_id, score, score_string) =
+                        parse_run_line_with_columns(line, columns)?;
+                    (query_id, doc_id, score, Cow::Owned(score_string))
+                }
+                None => {
+                    let (query_id, doc_id, score, score_string) = parse_run_line(line)?;
+                    (query_id, doc_id, score, Cow::Borrowed(score_string))
+                }
+            };
+            let score_string = score_string.as_ref();
+            let quantized = self.quantization.map_or(score, |q| q.quantize(score));
+            // The ids are copied only for the auditing information.
+            let ids = (first_docs.is_some() || score_strings.is_some())
+                .then(|| (query_id.clone(), doc_id.clone()));
+            if b.add_record_with_policy(query_id, doc_id, quantized, self.non_finite_policy)? {
+                n_non_finite += 1;
+                continue;
+            }
+            let Some((query_id, doc_id)) = ids else {
+                continue;
+            };
+            let canonical = first_docs.as_mut().and_then(|first_docs| {
+                canonical_decimal(score_string).map(|canonical| (first_docs, canonical))
+            });
+            if let Some((first_docs, canonical)) = canonical {
+                match first_docs.entry((query_id.clone(), score)) {
+                    Entry::Occupied(entry) => {
+                        let (first_doc_id, first_canonical, first_string) = entry.get();
+                        if *first_canonical != canonical {
+                            collapsed_ties.push(CollapsedTie {
+                                query_id: query_id.clone(),
+                                doc_ids: [first_doc_id.clone(), doc_id.clone()],
+                                score_strings: [first_string.clone(), score_string.to_string()],
+                            });
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert((doc_id.clone(), canonical, score_string.to_string()));
+                    }
+                }
+            }
+            if let Some(score_strings) = score_strings.as_mut() {
+                score_strings.insert((query_id, doc_id), score_string.to_string());
+            }
+        }
+        Ok(TrecRun {
+            pred_rels: b.build(),
+            n_non_finite,
+            collapsed_ties,
+            score_strings,
+        })
+    }
+}
+
+/// Returns the canonical form of a decimal string, such as `1.50e1` and `15`,
+/// so that strings of the same exact value have the same form.
+///
+/// Returns `None` if the string is not a plain decimal number (e.g., `inf`).
+fn canonical_decimal(s: &str) -> Option<String> {
+    let (negative, s) = s
+        .strip_prefix('-')
+        .map_or_else(|| (false, s.strip_prefix('+').unwrap_or(s)), |s| (true, s));
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    if !int_part
+        .chars()
+        .chain(frac_part.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let digits = format!("{int_part}{frac_part}");
+    let exponent = exponent + int_part.len() as i64;
+    let leading = digits.len() - digits.trim_start_matches('0').len();
+    let digits = digits.trim_matches('0');
+    if digits.is_empty() {
+        return Some("0".to_string());
+    }
+    let exponent = exponent - leading as i64;
+    let sign = if negative { "-" } else { "" };
+    Some(format!("{sign}0.{digits}e{exponent}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_decimal() {
+        assert_eq!(canonical_decimal("15"), Some("0.15e2".to_string()));
+        assert_eq!(canonical_decimal("1.50e1"), Some("0.15e2".to_string()));
+        assert_eq!(canonical_decimal("+0015.0"), Some("0.15e2".to_string()));
+        assert_eq!(canonical_decimal("0.015E3"), Some("0.15e2".to_string()));
+        assert_eq!(canonical_decimal("-.5"), Some("-0.5e0".to_string()));
+        assert_eq!(canonical_decimal("0.000"), Some("0".to_string()));
+        assert_eq!(canonical_decimal("inf"), None);
+        assert_eq!(canonical_decimal("."), None);
+    }
+
+    #[test]
+    fn test_trec_run_parser_no_collapsed_ties() {
+        // Ties in the input are not collapsed ones.
+        let data = "q_1 0 d_1 1 0.5 X\nq_1 0 d_2 2 0.50 X\nq_2 0 d_1 1 5e-1 X";
+        let run = TrecRunParser::new()
+            .with_detect_collapsed_ties(true)
+            .parse(data.lines())
+            .unwrap();
+        assert!(run.collapsed_ties().is_empty());
+        assert!(run.score_strings().is_none());
+        assert_eq!(run.pred_rels().n_docs(), 3);
+    }
+
+    #[test]
+    fn test_trec_run_parser_collapsed_ties_disabled() {
+        let data = "q_1 0 d_1 1 0.10000000000000000001 X\nq_1 0 d_2 2 0.10000000000000000002 X";
+        let run = TrecRunParser::new().parse(data.lines()).unwrap();
+        assert!(run.collapsed_ties().is_empty());
+        let run = TrecRunParser::new()
+            .with_detect_collapsed_ties(true)
+            .parse(data.lines())
+            .unwrap();
+        assert_eq!(run.collapsed_ties().len(), 1);
+    }
+
+    #[test]
+    fn test_trec_run_parser_with_quantization() {
+        let data = "q_1 0 d_1 1 0.3000000000001 X\nq_1 0 d_2 2 0.3 X\nq_1 0 d_3 3 0.2 X";
+        let quantization = ScoreQuantization::new(1e-6).unwrap();
+        let run = TrecRunParser::new()
+            .with_quantization(quantization)
+            .parse(data.lines())
+            .unwrap();
+        let pred_rels = run.pred_rels();
+        assert_eq!(
+            pred_rels.get_score("q_1", "d_1"),
+            pred_rels.get_score("q_1", "d_2")
+        );
+        assert!(run.collapsed_ties().is_empty());
+        let doc_ids = pred_rels
+            .get_sorted("q_1")
+            .unwrap()
+            .iter()
+            .map(|r| r.doc_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(doc_ids, ["d_1", "d_2", "d_3"]);
+    }
+
+    #[test]
+    fn test_trec_run_parser_invalid_score() {
+        let data = "q_1 0 d_1 1 abc X";
+        assert_eq!(
+            TrecRunParser::new().parse(data.lines()).err(),
+            Some(ElinorError::InvalidFormat(
+                "The fifth column must be f64, but got abc".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_column_mapping_from_str() {
+        assert_eq!(
+            "query_id:0,doc_id:2,rank:3,score:4".parse(),
+            Ok(ColumnMapping::run())
+        );
+        assert_eq!(
+            "doc_id:2, query_id:0, score:3".parse(),
+            Ok(ColumnMapping::qrels())
+        );
+        assert_eq!(
+            "query_id:0,score:1".parse::<ColumnMapping>(),
+            Err(ElinorError::InvalidFormat(
+                "The query_id and doc_id columns must be specified.".to_string()
+            ))
+        );
+        assert_eq!(
+            "query_id:0,doc_id:1,docid:2".parse::<ColumnMapping>(),
+            Err(ElinorError::InvalidFormat(
+                "Invalid column name: docid".to_string()
+            ))
+        );
+        assert!(matches!(
+            "query_id:0,doc_id:1,query_id:2".parse::<ColumnMapping>(),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+    }
+
+    #[test]
+    fn test_trec_run_parser_with_columns() {
+        // <Extra> <DocID> <Score> <QueryID>
+        let data = "x d_1 0.5 q_1\ny d_2 0.4 q_1\nz d_1 0.3 q_2";
+        let columns = ColumnMapping::new(3, 1).with_score(2);
+        let run = TrecRunParser::new()
+            .with_columns(columns)
+            .parse(data.lines())
+            .unwrap();
+        let pred_rels = run.pred_rels();
+        assert_eq!(pred_rels.n_docs(), 3);
+        assert_eq!(pred_rels.get_score("q_1", "d_2"), Some(&0.4.into()));
+        assert_eq!(pred_rels.get_score("q_2", "d_1"), Some(&0.3.into()));
+
+        assert_eq!(
+            TrecRunParser::new()
+                .with_columns(ColumnMapping::new(3, 1).with_score(4))
+                .parse(data.lines())
+                .err(),
+            Some(ElinorError::InvalidFormat(
+                "Line must have 5 columns at least, but got x d_1 0.5 q_1".to_string()
+            ))
+        );
+        assert!(matches!(
+            TrecRunParser::new()
+                .with_columns(ColumnMapping::new(3, 1))
+                .parse(data.lines()),
+            Err(ElinorError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_true_rels_with_columns() {
+        let data = "q_1,d_1,-1,x\nq_1,d_2,2,y";
+        let columns = ColumnMapping::new(0, 1)
+            .with_score(2)
+            .with_delimiter(Some(','));
+        let true_rels = parse_true_rels_with_columns(data.lines(), columns).unwrap();
+        assert_eq!(true_rels.get_score("q_1", "d_1"), Some(&0));
+        assert_eq!(true_rels.get_score("q_1", "d_2"), Some(&2));
+
+        assert_eq!(
+            parse_true_rels_with_columns(data.lines(), ColumnMapping::new(0, 1)).err(),
+            Some(ElinorError::InvalidArgument(
+                "The score column must be specified for Qrels data.".to_string()
+            ))
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parse_in_trec_parallel() {
+        let true_lines = (0..1000)
+            .map(|i| format!("q_{} 0 d_{} {}", i % 7, i, i % 3))
+            .collect::<Vec<_>>();
+        let expected = parse_true_rels_in_trec(&true_lines).unwrap();
+        let actual = parse_true_rels_in_trec_parallel(&true_lines).unwrap();
+        assert_eq!(actual.records(), expected.records());
+
+        let pred_lines = (0..1000)
+            .map(|i| format!("q_{} 0 d_{} 0 {} X", i % 7, i, i % 13))
+            .collect::<Vec<_>>();
+        let expected = parse_pred_rels_in_trec(&pred_lines).unwrap();
+        let (actual, _) =
+            parse_pred_rels_in_trec_parallel(&pred_lines, NonFiniteScorePolicy::Reject).unwrap();
+        assert_eq!(actual.records(), expected.records());
+
+        let dup_lines = ["q_1 0 d_1 1"; 100];
+        assert!(matches!(
+            parse_true_rels_in_trec_parallel(&dup_lines),
+            Err(ElinorError::DuplicateEntry(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_in_trec_round_trip() {
+        let true_data = "q_1 0 d_1 1\nq_1 0 d_2 0\nq_10 0 d_3 2";
+        let true_rels = parse_true_rels_in_trec(true_data.lines()).unwrap();
+        let lines = write_true_rels_in_trec(&true_rels).unwrap();
+        assert_eq!(lines.join("\n"), true_data);
+
+        let pred_data =
+            "q_1 Q0 d_3 1 0.5 X\nq_1 Q0 d_1 2 0.25 X\nq_1 Q0 d_2 3 0.25 X\nq_2 Q0 d_1 1 -1e-7 X";
+        let pred_rels = parse_pred_rels_in_trec(pred_data.lines()).unwrap();
+        let lines = write_pred_rels_in_trec(&pred_rels, "X").unwrap();
+        assert_eq!(lines.join("\n"), pred_data.replace("-1e-7", "-0.0000001"));
+        let reparsed = parse_pred_rels_in_trec(&lines).unwrap();
+        assert_eq!(reparsed.records(), pred_rels.records());
+    }
+
+    #[test]
+    fn test_write_in_trec_invalid_fields() {
+        let pred_rels = parse_pred_rels_in_trec(["q_1 Q0 d_1 1 0.5 X"]).unwrap();
+        assert!(matches!(
+            write_pred_rels_in_trec(&pred_rels, "my run"),
+            Err(ElinorError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            write_pred_rels_in_trec(&pred_rels, ""),
+            Err(ElinorError::InvalidArgument(_))
+        ));
+
+        let mut b = TrueRelStoreBuilder::new();
+        b.add_record("q 1".to_string(), "d_1".to_string(), 1)
+            .unwrap();
+        assert!(matches!(
+            write_true_rels_in_trec(&b.build()),
+            Err(ElinorError::InvalidArgument(_))
+        ));
+    }
+}